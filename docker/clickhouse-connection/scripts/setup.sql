@@ -0,0 +1,43 @@
+DROP TABLE IF EXISTS `clickhouse-connection`.all_native_data_types;
+
+CREATE TABLE `clickhouse-connection`.all_native_data_types (
+    id UInt32,
+    int8_col Int8,
+    int16_col Int16,
+    int32_col Int32,
+    int64_col Int64,
+    int128_col Int128,
+    uint8_col UInt8,
+    uint16_col UInt16,
+    uint32_col UInt32,
+    uint64_col UInt64,
+    uint256_col UInt256,
+    float32_col Float32,
+    float64_col Float64,
+    decimal_col Decimal(10, 2),
+    decimal64_col Decimal64(4),
+    bool_col Bool,
+    string_col String,
+    fixed_string_col FixedString(5),
+    nullable_col Nullable(String),
+    low_cardinality_col LowCardinality(String),
+    enum_col Enum8('value1' = 1, 'value2' = 2),
+    uuid_col UUID,
+    ipv4_col IPv4,
+    ipv6_col IPv6,
+    date_col Date,
+    date32_col Date32,
+    datetime_col DateTime,
+    datetime64_col DateTime64(3),
+    array_col Array(Int32),
+    nullable_int_col Nullable(Int32)
+) ENGINE = MergeTree ORDER BY id;
+
+INSERT INTO `clickhouse-connection`.all_native_data_types VALUES (
+    1, 127, 32767, 2147483647, 9223372036854775807,
+    170141183460469231731687303715884105727, 255, 65535, 4294967295, 18446744073709551615,
+    1, 123.45, 123456789.123456, 12345.67, 1234.5678,
+    true, 'string_data', 'fixed', NULL, 'low_cardinality_data',
+    'value1', '123e4567-e89b-12d3-a456-426614174000', '192.168.0.1', '2001:db8::1', '2024-05-28',
+    '2024-05-28', '2024-05-28 12:34:56', '2024-05-28 12:34:56.789', [1, 2, 3], NULL
+);
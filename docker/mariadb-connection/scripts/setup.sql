@@ -0,0 +1,20 @@
+DROP TABLE IF EXISTS `mariadb_data_types`;
+
+CREATE TABLE `mariadb_data_types` (
+    `id` INT AUTO_INCREMENT PRIMARY KEY,
+    `int_unsigned_col` INT UNSIGNED,
+    `decimal_col` DECIMAL(10,2),
+    `uuid_col` UUID,
+    `inet4_col` INET4,
+    `inet6_col` INET6,
+    `json_col` JSON,
+    `datetime_col` DATETIME(6)
+);
+
+INSERT INTO `mariadb_data_types` (
+    `int_unsigned_col`, `decimal_col`, `uuid_col`, `inet4_col`, `inet6_col`,
+    `json_col`, `datetime_col`
+) VALUES (
+    4294967295, 12345.67, '123e4567-e89b-12d3-a456-426614174000', '192.168.0.1', '2001:db8::1',
+    '{"key": "value"}', '2024-05-28 12:34:56.123456'
+);
@@ -0,0 +1,4 @@
+#!/bin/sh
+
+# the image disables forwarding, which SSH tunnels need
+sed -i 's/^AllowTcpForwarding no/AllowTcpForwarding yes/' /config/sshd/sshd_config
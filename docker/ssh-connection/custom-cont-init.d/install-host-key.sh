@@ -0,0 +1,5 @@
+#!/bin/sh
+
+# a fixed host key, so tests can verify the bastion
+cp /ssh_host_keys/ssh_host_ed25519_key /ssh_host_keys/ssh_host_ed25519_key.pub /config/ssh_host_keys/
+chmod 600 /config/ssh_host_keys/ssh_host_ed25519_key
@@ -3,6 +3,7 @@ import express from 'express';
 import { param, validationResult } from 'express-validator';
 import dbClient from '../../dbClient';
 import { validateM2MAuth } from '../../internal/validateM2MAuth';
+import { getFileUrl } from '../../storage/storage';
 import type { Request } from '../../types/Request';
 
 export const validateUUID = () => param('uuid').isUUID(4);
@@ -34,6 +35,7 @@ router.get('/file/:uuid/checkpoint', validateM2MAuth(), validateUUID(), async (r
   });
 
   const checkpoint = result.FileCheckpoint[0];
+  const dataUrl = await getFileUrl(checkpoint.s3Key);
 
   return res.status(200).json({
     fileUuid: fileUuid,
@@ -42,6 +44,7 @@ router.get('/file/:uuid/checkpoint', validateM2MAuth(), validateUUID(), async (r
       version: checkpoint.version,
       s3Key: checkpoint.s3Key,
       s3Bucket: checkpoint.s3Bucket,
+      dataUrl,
     },
   });
 });
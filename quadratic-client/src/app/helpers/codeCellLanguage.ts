@@ -6,10 +6,15 @@ export const codeCellsById = {
   Javascript: { id: 'Javascript', label: 'JavaScript', type: undefined },
   Python: { id: 'Python', label: 'Python', type: undefined },
   Import: { id: 'Import', label: 'Import', type: undefined },
+  Sql: { id: 'Sql', label: 'SQL', type: undefined },
   POSTGRES: { id: 'POSTGRES', label: 'Postgres', type: 'connection' },
   MYSQL: { id: 'MYSQL', label: 'MySQL', type: 'connection' },
   MSSQL: { id: 'MSSQL', label: 'MS SQL Server', type: 'connection' },
   SNOWFLAKE: { id: 'SNOWFLAKE', label: 'Snowflake', type: 'connection' },
+  SQLITE: { id: 'SQLITE', label: 'SQLite', type: 'connection' },
+  DUCKDB: { id: 'DUCKDB', label: 'DuckDB', type: 'connection' },
+  CLICKHOUSE: { id: 'CLICKHOUSE', label: 'ClickHouse', type: 'connection' },
+  HTTP: { id: 'HTTP', label: 'HTTP', type: 'connection' },
 } as const;
 export type CodeCellIds = keyof typeof codeCellsById;
 // type CodeCell = (typeof codeCellsById)[CodeCellIds];
@@ -59,6 +64,14 @@ export const getLanguageForMonaco = (language?: CodeCellLanguage): string => {
         return 'sql';
       case 'SNOWFLAKE':
         return 'sql';
+      case 'SQLITE':
+        return 'sql';
+      case 'DUCKDB':
+        return 'sql';
+      case 'CLICKHOUSE':
+        return 'sql';
+      case 'HTTP':
+        return 'plaintext';
     }
   }
 
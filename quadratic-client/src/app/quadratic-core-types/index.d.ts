@@ -13,7 +13,7 @@ export interface BorderStyleTimestamp { color: Rgba, line: CellBorderLine, times
 export interface JsCellsA1Error { core_error: string, }
 export interface JsCellsA1Response { values: JsCellsA1Values | null, error: JsCellsA1Error | null, }
 export interface JsCellsA1Value { x: number, y: number, v: string, t: number, }
-export interface JsCellsA1Values { cells: Array<JsCellsA1Value>, x: number, y: number, w: number, h: number, one_dimensional: boolean, two_dimensional: boolean, has_headers: boolean, }
+export interface JsCellsA1Values { cells: Array<JsCellsA1Value>, x: number, y: number, w: number, h: number, one_dimensional: boolean, two_dimensional: boolean, has_headers: boolean, column_types: Array<DataTableColumnType | null>, }
 export type JsCellValueResult = [string, number];
 export type CellAlign = "center" | "left" | "right";
 export type CellBorderLine = "line1" | "line2" | "line3" | "dotted" | "dashed" | "double" | "clear";
@@ -23,9 +23,12 @@ export type CellRefRange = { range: RefRangeBounds, } | { range: TableRef, };
 export interface CellRefRangeEnd { col: CellRefCoord, row: CellRefCoord, }
 export type CellVerticalAlign = "top" | "middle" | "bottom";
 export type CellWrap = "overflow" | "wrap" | "clip";
-export type CodeCellLanguage = "Python" | "Formula" | { "Connection": { kind: ConnectionKind, id: string, } } | "Javascript" | "Import";
+export type CodeCellLanguage = "Python" | "Formula" | { "Connection": { kind: ConnectionKind, id: string, } } | "Javascript" | "Import" | "Sql";
 export interface ColumnRow { column: number, row: number, }
-export type ConnectionKind = "POSTGRES" | "MYSQL" | "MSSQL" | "SNOWFLAKE";
+export type ConnectionKind = "POSTGRES" | "MYSQL" | "MSSQL" | "SNOWFLAKE" | "SQLITE" | "DUCKDB" | "CLICKHOUSE" | "HTTP";
+export type CsvEncoding = "Utf8" | "Utf16Le" | "Utf16Be" | "Latin1" | "Windows1252";
+export interface CsvImportOptions { delimiter: number | null, quote: number | null, escape: number | null, comment: number | null, encoding: CsvEncoding | null, skip_rows: number, header_row: number | null, header_is_first_row: boolean | null, column_types: Record<number, DataTableColumnType>, }
+export type DataTableColumnType = "Text" | "Number" | "Date" | "DateTime" | "Boolean" | "Currency";
 export interface DataTableSort { column_index: number, direction: SortDirection, }
 export type DateTimeRange = { "DateRange": [bigint | null, bigint | null] } | { "DateEqual": Array<bigint> } | { "DateNotEqual": Array<bigint> } | { "TimeRange": [number | null, number | null] } | { "TimeEqual": Array<number> } | { "TimeNotEqual": Array<number> };
 export type Direction = "Up" | "Down" | "Left" | "Right";
@@ -44,7 +47,7 @@ export interface JsCodeCell { x: bigint, y: bigint, code_string: string, languag
 export interface JsCodeResult { transaction_id: string, success: boolean, std_out: string | null, std_err: string | null, line_number: number | null, output_value: JsCellValueResult | null, output_array: Array<Array<JsCellValueResult>> | null, output_display_type: string | null, cancel_compute: boolean | null, chart_pixel_output: [number, number] | null, has_headers: boolean, }
 export interface JsCodeTableContext { sheet_name: string, code_table_name: string, all_columns: Array<string>, visible_columns: Array<string>, first_row_visible_values: Array<JsCellValuePos>, last_row_visible_values: Array<JsCellValuePos>, bounds: string, show_name: boolean, show_columns: boolean, language: CodeCellLanguage, code_string: string, std_err: string | null, error: boolean, spill: boolean, }
 export interface JsCoordinate { x: number, y: number, }
-export interface JsDataTableColumnHeader { name: string, display: boolean, valueIndex: number, }
+export interface JsDataTableColumnHeader { name: string, display: boolean, valueIndex: number, columnType: DataTableColumnType | null, formula: string | null, }
 export interface JsDataTableContext { sheet_name: string, data_table_name: string, all_columns: Array<string>, visible_columns: Array<string>, first_row_visible_values: Array<JsCellValuePos>, last_row_visible_values: Array<JsCellValuePos>, bounds: string, show_name: boolean, show_columns: boolean, }
 export interface JsFormulaParseResult { parse_error_msg: string | null, parse_error_span: Span | null, cells_accessed: Array<JsCellsAccessed>, spans: Array<Span>, }
 export interface JsCellsA1Value { x: number, y: number, v: string, t: number, }
@@ -72,6 +75,11 @@ export interface NumericFormat { type: NumericFormatKind, symbol: string | null,
 export type NumericFormatKind = "NUMBER" | "CURRENCY" | "PERCENTAGE" | "EXPONENTIAL";
 export type PasteSpecial = "None" | "Values" | "Formats";
 export interface Pos { x: bigint, y: bigint, }
+export interface Protection { id: string, target: ProtectionTarget, allow: ProtectionAllowList, }
+export interface ProtectionAllowList { user_ids: Array<string>, roles: Array<ProtectionRole>, }
+export type ProtectionRole = "Owner" | "Editor";
+export type ProtectionTarget = "Sheet" | { "Range": Rect } | { "DataTable": Pos };
+export interface ProtectionUser { user_id: string, roles: Array<ProtectionRole>, }
 export interface Rect { min: Pos, max: Pos, }
 export interface RefRangeBounds { start: CellRefRangeEnd, end: CellRefRangeEnd, }
 export interface Rgba { red: number, green: number, blue: number, alpha: number, }
@@ -80,8 +88,9 @@ export type RunErrorMsg = { "CodeRunError": string } | "Spill" | { "Unimplemente
 export interface SearchOptions { case_sensitive?: boolean, whole_cell?: boolean, search_code?: boolean, sheet_id?: string, }
 export interface SheetBounds { sheet_id: string, bounds: GridBounds, bounds_without_formatting: GridBounds, }
 export interface SheetId { id: string, }
-export interface SheetInfo { sheet_id: string, name: string, order: string, color: string | null, offsets: string, bounds: GridBounds, bounds_without_formatting: GridBounds, }
+export interface SheetInfo { sheet_id: string, name: string, order: string, color: string | null, offsets: string, bounds: GridBounds, bounds_without_formatting: GridBounds, protections: SheetProtections, }
 export interface SheetPos { x: bigint, y: bigint, sheet_id: SheetId, }
+export interface SheetProtections { protections: Array<Protection>, }
 export interface SheetRect { min: Pos, max: Pos, sheet_id: SheetId, }
 export type SmallTimestamp = number;
 export type SortDirection = "Ascending" | "Descending" | "None";
@@ -6,11 +6,13 @@ import {
 } from '@/app/atoms/editorInteractionStateAtom';
 import { events } from '@/app/events/events';
 import { pixiApp } from '@/app/gridGL/pixiApp/PixiApp';
+import type { ProtectionRole } from '@/app/quadratic-core-types';
 import QuadraticUIContext from '@/app/ui/QuadraticUIContext';
 import { javascriptWebWorker } from '@/app/web-workers/javascriptWebWorker/javascriptWebWorker';
 import { multiplayer } from '@/app/web-workers/multiplayerWebWorker/multiplayer';
 import type { MultiplayerState } from '@/app/web-workers/multiplayerWebWorker/multiplayerClientMessages';
 import { pythonWebWorker } from '@/app/web-workers/pythonWebWorker/pythonWebWorker';
+import { quadraticCore } from '@/app/web-workers/quadraticCore/quadraticCore';
 import { SEARCH_PARAMS } from '@/shared/constants/routes';
 import { useEffect, useRef, useState } from 'react';
 import { isMobile } from 'react-device-detect';
@@ -43,6 +45,15 @@ export function QuadraticApp() {
     }
   }, [permissions]);
 
+  // Edits are checked against the sheet protections for the user, using the
+  // same roles as the multiplayer server
+  useEffect(() => {
+    const roles: ProtectionRole[] = [];
+    if (permissions.includes('FILE_DELETE')) roles.push('Owner');
+    if (hasPermissionToEditFile(permissions)) roles.push('Editor');
+    quadraticCore.setProtectionUser({ user_id: loggedInUser?.sub ?? '', roles });
+  }, [permissions, loggedInUser]);
+
   useEffect(() => {
     if (fileUuid && !pixiApp.initialized) {
       pixiApp.init().then(() => {
@@ -1,7 +1,7 @@
 import type { CodeCellIds } from '@/app/helpers/codeCellLanguage';
 import { colors } from '@/app/theme/colors';
 import { Formula, JavaScript, MssqlIcon, MysqlIcon, PostgresIcon, Python, SnowflakeIcon } from '@/app/ui/icons';
-import { Subject } from '@mui/icons-material';
+import { Storage, Subject } from '@mui/icons-material';
 import type { SvgIconProps } from '@mui/material/SvgIcon';
 
 interface LanguageIconProps extends SvgIconProps {
@@ -28,6 +28,8 @@ export function LanguageIcon({ language, ...props }: LanguageIconProps) {
     <MssqlIcon {...props} sx={{ color: colors.languageMssql, ...internalSx }} />
   ) : language && 'snowflake'.startsWith(language) ? (
     <SnowflakeIcon {...props} sx={{ color: colors.languageSnowflake, ...internalSx }} />
+  ) : language === 'sql' ? (
+    <Storage {...props} sx={internalSx} />
   ) : (
     <Subject {...props} />
   );
@@ -52,6 +52,13 @@ let CELL_TYPE_OPTIONS: CellTypeOption[] = [
     icon: <JavaScript sx={{ color: colors.languageJavascript }} />,
     experimental: true,
   },
+  {
+    name: 'SQL',
+    searchStrings: ['query', 'tables', 'join'],
+    mode: 'Sql',
+    icon: <LanguageIcon language="Sql" />,
+    experimental: true,
+  },
 ];
 
 export default function CellTypeMenu() {
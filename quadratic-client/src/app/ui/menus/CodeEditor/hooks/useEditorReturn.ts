@@ -18,7 +18,7 @@ export const useEditorReturn = (
   const codeCell = getCodeCell(language);
 
   useEffect(() => {
-    if (codeCell?.id === 'Formula' || codeCell?.type === 'connection') return;
+    if (codeCell?.id === 'Formula' || codeCell?.id === 'Sql' || codeCell?.type === 'connection') return;
 
     if (!isValidRef || !editorInst || !monacoInst) return;
 
@@ -26,6 +26,12 @@ export interface MultiplayerCoreReceiveCurrentTransaction {
   sequenceNum: number;
 }
 
+export interface MultiplayerCoreRejectTransaction {
+  type: 'multiplayerCoreRejectTransaction';
+  transactionId: string;
+  message: string;
+}
+
 export interface CoreMultiplayerRequestTransactions {
   type: 'coreMultiplayerRequestTransactions';
   sequenceNum: number;
@@ -35,6 +41,7 @@ export type MultiplayerCoreMessage =
   | MultiplayerCoreSequenceNum
   | MultiplayerCoreReceiveTransactions
   | MultiplayerCoreReceiveTransaction
-  | MultiplayerCoreReceiveCurrentTransaction;
+  | MultiplayerCoreReceiveCurrentTransaction
+  | MultiplayerCoreRejectTransaction;
 
 export type CoreMultiplayerMessage = CoreMultiplayerTransaction | CoreMultiplayerRequestTransactions;
@@ -54,6 +54,14 @@ class MultiplayerCore {
     });
   }
 
+  rejectTransaction(transactionId: string, message: string) {
+    this.send({
+      type: 'multiplayerCoreRejectTransaction',
+      transactionId,
+      message,
+    });
+  }
+
   receiveTransactions(receive_transactions: ReceiveTransactions) {
     this.send({
       type: 'multiplayerCoreReceiveTransactions',
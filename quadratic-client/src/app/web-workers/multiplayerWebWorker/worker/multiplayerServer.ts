@@ -260,6 +260,13 @@ export class MultiplayerServer {
         break;
 
       case 'Error':
+        // the server rejected one of our transactions, so roll it back
+        if (typeof data.error != 'string' && 'ProtectedEdit' in data.error) {
+          const [transactionId, message] = data.error.ProtectedEdit;
+          multiplayerCore.rejectTransaction(transactionId, message);
+          break;
+        }
+
         if (data.error_level === 'Error') {
           // If the server is missing transactions, reload the page
           if (typeof data.error != 'string' && 'MissingTransactions' in data.error) {
@@ -27,6 +27,7 @@ import type {
   JsValidationWarning,
   MinMax,
   Pos,
+  ProtectionUser,
   SearchOptions,
   SheetBounds,
   SheetInfo,
@@ -48,6 +49,7 @@ export interface ClientCoreLoad {
   sequenceNumber: number;
   id: number;
   fileId: string;
+  userId?: string;
   teamUuid: string;
 }
 
@@ -632,6 +634,11 @@ export interface ClientCoreSetSheetColor {
   cursor: string;
 }
 
+export interface ClientCoreSetProtectionUser {
+  type: 'clientCoreSetProtectionUser';
+  user?: ProtectionUser;
+}
+
 export interface ClientCoreDuplicateSheet {
   type: 'clientCoreDuplicateSheet';
   sheetId: string;
@@ -1309,6 +1316,7 @@ export type ClientCoreMessage =
   | ClientCoreMoveSheet
   | ClientCoreSetSheetName
   | ClientCoreSetSheetColor
+  | ClientCoreSetProtectionUser
   | ClientCoreDuplicateSheet
   | ClientCoreUndo
   | ClientCoreRedo
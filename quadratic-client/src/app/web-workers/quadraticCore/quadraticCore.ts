@@ -31,6 +31,7 @@ import type {
   MinMax,
   PasteSpecial,
   Pos,
+  ProtectionUser,
   SearchOptions,
   SheetPos,
   SheetRect,
@@ -248,12 +249,14 @@ class QuadraticCore {
   // Loads a Grid file and initializes renderWebWorker upon response
   async load({
     fileId,
+    userId,
     teamUuid,
     url,
     version,
     sequenceNumber,
   }: {
     fileId: string;
+    userId?: string;
     teamUuid: string;
     url: string;
     version: string;
@@ -284,6 +287,7 @@ class QuadraticCore {
         sequenceNumber,
         id,
         fileId,
+        userId,
         teamUuid,
       };
       if (debugShowFileIO) console.log(`[quadraticCore] loading file ${url}`);
@@ -860,6 +864,11 @@ class QuadraticCore {
     this.send({ type: 'clientCoreDuplicateSheet', sheetId, cursor });
   }
 
+  // Sets the user that edits are checked against the sheet protections for
+  setProtectionUser(user?: ProtectionUser) {
+    this.send({ type: 'clientCoreSetProtectionUser', user });
+  }
+
   //#endregion
 
   //#region Undo/redo
@@ -31,6 +31,7 @@ import type {
   JsTablesContext,
   MinMax,
   Pos,
+  ProtectionUser,
   Rect,
   SearchOptions,
   SheetPos,
@@ -73,6 +74,9 @@ class Core {
   gridController?: GridController;
   teamUuid?: string;
 
+  // kept so it can be set once the file loads
+  protectionUser?: ProtectionUser;
+
   private sendAnalyticsError = (from: string, error: Error | unknown) => {
     console.error(error);
     mixpanel.track(`[core] ${from} error`, {
@@ -102,6 +106,9 @@ class Core {
     try {
       const results = await Promise.all([this.fetchGridFile(message.url), initCore()]);
       this.gridController = GridController.newFromFile(results[0], message.sequenceNumber, true);
+      if (this.protectionUser) {
+        this.gridController.setProtectionUser(JSON.stringify(this.protectionUser));
+      }
     } catch (e) {
       this.sendAnalyticsError('loadFile', e);
       return { error: 'Unable to load file' };
@@ -332,6 +339,24 @@ class Core {
     });
   }
 
+  rejectTransaction(transactionId: string, message: string) {
+    return new Promise(async (resolve) => {
+      if (!this.gridController) throw new Error('Expected gridController to be defined');
+      try {
+        this.gridController.rejectedTransaction(transactionId, message);
+        offline.markTransactionSent(transactionId);
+        if (await offline.unsentTransactionsCount()) {
+          coreClient.sendMultiplayerState('syncing');
+        } else {
+          coreClient.sendMultiplayerState('connected');
+        }
+      } catch (e) {
+        this.handleCoreError('rejectTransaction', e);
+      }
+      resolve(undefined);
+    });
+  }
+
   receiveTransactions(receive_transactions: MultiplayerCoreReceiveTransactions) {
     return new Promise(async (resolve) => {
       if (!this.gridController) throw new Error('Expected gridController to be defined');
@@ -690,6 +715,18 @@ class Core {
     });
   }
 
+  setProtectionUser(user?: ProtectionUser) {
+    return new Promise((resolve) => {
+      this.protectionUser = user;
+      try {
+        this.gridController?.setProtectionUser(user ? JSON.stringify(user) : undefined);
+      } catch (e) {
+        this.handleCoreError('setProtectionUser', e);
+      }
+      resolve(undefined);
+    });
+  }
+
   duplicateSheet(sheetId: string, cursor: string) {
     return new Promise((resolve) => {
       if (!this.gridController) throw new Error('Expected gridController to be defined');
@@ -1073,6 +1110,37 @@ class Core {
     }
   }
 
+  // Returns true if the undo history was restored.
+  restoreUndoHistory(history: string): boolean {
+    if (!this.gridController) throw new Error('Expected gridController to be defined');
+    try {
+      return this.gridController.restoreUndoHistory(history);
+    } catch (error: any) {
+      this.handleCoreError('restoreUndoHistory', error);
+      return false;
+    }
+  }
+
+  // Returns the undo history to persist, or undefined if it hasn't changed.
+  takeUndoHistory(): string | undefined {
+    if (!this.gridController) throw new Error('Expected gridController to be defined');
+    try {
+      return this.gridController.takeUndoHistory();
+    } catch (error: any) {
+      this.handleCoreError('takeUndoHistory', error);
+      return undefined;
+    }
+  }
+
+  setPersistUndoHistory(persist: boolean) {
+    if (!this.gridController) throw new Error('Expected gridController to be defined');
+    try {
+      this.gridController.setPersistUndoHistory(persist);
+    } catch (error: any) {
+      this.handleCoreError('setPersistUndoHistory', error);
+    }
+  }
+
   clearFormatting(selection: string, cursor?: string) {
     if (!this.gridController) throw new Error('Expected gridController to be defined');
     try {
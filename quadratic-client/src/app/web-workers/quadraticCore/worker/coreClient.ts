@@ -145,7 +145,7 @@ class CoreClient {
 
     switch (e.data.type) {
       case 'clientCoreLoad':
-        await offline.init(e.data.fileId);
+        await offline.init(e.data.fileId, e.data.userId);
 
         this.send({
           type: 'coreClientLoad',
@@ -321,6 +321,10 @@ class CoreClient {
         await core.setSheetColor(e.data.sheetId, e.data.color, e.data.cursor);
         return;
 
+      case 'clientCoreSetProtectionUser':
+        await core.setProtectionUser(e.data.user);
+        return;
+
       case 'clientCoreDuplicateSheet':
         await core.duplicateSheet(e.data.sheetId, e.data.cursor);
         return;
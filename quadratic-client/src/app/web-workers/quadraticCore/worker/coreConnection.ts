@@ -13,6 +13,7 @@ declare var self: WorkerGlobalScope &
       y: number,
       sheetId: string,
       code: string,
+      params: string,
       connector_type: ConnectionKind,
       connection_id: String
     ) => void;
@@ -51,6 +52,7 @@ class CoreConnection {
     y: number,
     sheetId: string,
     code: string,
+    params: string,
     connector_type: ConnectionKind,
     connection_id: String
   ) => {
@@ -63,6 +65,9 @@ class CoreConnection {
     const body = {
       connection_id,
       query: code,
+      params: JSON.parse(params),
+      // lets the connection service cancel the query in the database
+      query_id: transactionId,
     };
 
     let buffer = new ArrayBuffer(0);
@@ -101,6 +106,12 @@ class CoreConnection {
           const isOverTheLimit = headers.get('over-the-limit') === 'true';
           std_out = isOverTheLimit ? 'Exceeded maximum allowed bytes, not all available records returned.' : '';
           extra = ` in ${headers.get('elapsed-total-ms')}ms`;
+
+          // results cached by the connection service, rather than queried
+          if (headers.get('cache-hit') === 'true') {
+            const ageSeconds = Math.round(Number(headers.get('cache-age-ms')) / 1000);
+            extra += ` (cached ${ageSeconds}s ago)`;
+          }
         }
       }
 
@@ -113,6 +124,21 @@ class CoreConnection {
     }
   };
 
+  // Aborting the request doesn't stop the query in the database, so ask the
+  // connection service to cancel it too
+  private cancelQuery = async (transactionId: string) => {
+    try {
+      const base = coreClient.env.VITE_QUADRATIC_CONNECTION_URL;
+      const jwt = await coreClient.getJwt();
+      await fetch(`${base}/query/cancel/${transactionId}`, {
+        method: 'POST',
+        headers: { Authorization: `Bearer ${jwt}` },
+      });
+    } catch (e) {
+      console.warn(`Error cancelling query ${transactionId}`, e);
+    }
+  };
+
   cancelExecution = () => {
     try {
       this.controller.abort();
@@ -128,6 +154,8 @@ class CoreConnection {
     // It's possible that the transaction was completed before the message was
     // received.
     if (this.lastTransactionId) {
+      this.cancelQuery(this.lastTransactionId);
+
       const buffer = new ArrayBuffer(0);
       const std_out = undefined;
       const std_err = 'Execution cancelled by user';
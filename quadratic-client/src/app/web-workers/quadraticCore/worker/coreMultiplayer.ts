@@ -46,6 +46,10 @@ class CoreMultiplayer {
         core.receiveTransactions(e.data);
         break;
 
+      case 'multiplayerCoreRejectTransaction':
+        core.rejectTransaction(e.data.transactionId, e.data.message);
+        break;
+
       default:
         console.warn('[coreMultiplayer] Unhandled message type', e.data);
     }
@@ -10,7 +10,7 @@ declare var self: WorkerGlobalScope &
 describe('offline', () => {
   beforeAll(async () => {
     self.indexedDB = indexedDB;
-    await offline.init('123');
+    await offline.init('123', 'user');
   });
 
   beforeEach(() => {
@@ -3,12 +3,17 @@ import { core } from './core';
 import { coreClient } from './coreClient';
 
 const DB_NAME = 'Quadratic-Offline';
-const DB_VERSION = 1;
+const DB_VERSION = 2;
 const DB_STORE = 'transactions';
+const DB_UNDO_STORE = 'undoHistory';
+
+// how long to wait after the undo history changes before saving it
+const UNDO_HISTORY_SAVE_DELAY = 1000;
 
 declare var self: WorkerGlobalScope &
   typeof globalThis & {
     addUnsentTransaction: (transactionId: string, transaction: string, operations: number) => void;
+    undoHistoryChanged: () => void;
   };
 
 interface OfflineEntry {
@@ -29,17 +34,21 @@ interface OfflineStats {
 class Offline {
   private db: IDBDatabase | undefined;
   private index = 0;
+  private undoHistoryTimeout?: number;
   fileId?: string;
+  userId?: string;
 
   // The `stats.operations` are not particularly interesting right now because
   // we send the entire operations batched together; we'll need to send partial
   // messages with separate operations to get good progress information.
   stats: OfflineStats = { transactions: 0, operations: 0, timestamps: [] };
 
-  // Creates a connection to the indexedDb database
-  init = (fileId: string): Promise<undefined> => {
+  // Creates a connection to the indexedDb database. The undo history is only
+  // saved when there is a user.
+  init = (fileId: string, userId?: string): Promise<undefined> => {
     return new Promise((resolve) => {
       this.fileId = fileId;
+      this.userId = userId;
       const request = self.indexedDB.open(DB_NAME, DB_VERSION);
       request.onerror = (event) => {
         console.error('Error opening indexedDB', event);
@@ -48,17 +57,24 @@ class Offline {
       request.onsuccess = () => {
         this.db = request.result;
         self.addUnsentTransaction = this.addUnsentTransaction;
+        self.undoHistoryChanged = this.undoHistoryChanged;
         resolve(undefined);
       };
 
       request.onupgradeneeded = () => {
         const db = request.result;
-        // creates an Object store that holds operations for a given key
-        const objectStore = db.createObjectStore(DB_STORE, {
-          keyPath: ['fileId', 'transactionId', 'index'],
-        });
-        objectStore.createIndex('fileId', 'fileId');
-        objectStore.createIndex('transactionId', 'transactionId');
+        if (!db.objectStoreNames.contains(DB_STORE)) {
+          // creates an Object store that holds operations for a given key
+          const objectStore = db.createObjectStore(DB_STORE, {
+            keyPath: ['fileId', 'transactionId', 'index'],
+          });
+          objectStore.createIndex('fileId', 'fileId');
+          objectStore.createIndex('transactionId', 'transactionId');
+        }
+        if (!db.objectStoreNames.contains(DB_UNDO_STORE)) {
+          // creates an Object store that holds the undo history for each file and user
+          db.createObjectStore(DB_UNDO_STORE, { keyPath: ['fileId', 'userId'] });
+        }
       };
     });
   };
@@ -129,6 +145,42 @@ class Offline {
     }
   };
 
+  // Schedules saving the undo history (called by core when it changes). Core
+  // only calls this again after the history is taken, so changes in the
+  // meantime are saved together.
+  undoHistoryChanged = () => {
+    if (this.undoHistoryTimeout !== undefined) return;
+    this.undoHistoryTimeout = self.setTimeout(this.saveUndoHistory, UNDO_HISTORY_SAVE_DELAY);
+  };
+
+  // Saves the undo history for this file and user.
+  private saveUndoHistory = () => {
+    this.undoHistoryTimeout = undefined;
+    if (!this.db || !this.fileId || !this.userId) return;
+    const history = core.takeUndoHistory();
+    if (history === undefined) return;
+    const tx = this.db.transaction(DB_UNDO_STORE, 'readwrite');
+    tx.objectStore(DB_UNDO_STORE).put({ fileId: this.fileId, userId: this.userId, history });
+    if (debugOffline) {
+      console.log('[Offline] Saved undo history to indexedDB.');
+    }
+  };
+
+  // Loads the saved undo history for this file and user from indexedDb
+  private async loadUndoHistory(): Promise<string | undefined> {
+    if (!this.db || !this.fileId || !this.userId) return undefined;
+    return new Promise((resolve) => {
+      const tx = this.db!.transaction(DB_UNDO_STORE, 'readonly');
+      const request = tx.objectStore(DB_UNDO_STORE).get([this.fileId!, this.userId!]);
+      request.onsuccess = () => {
+        resolve(request.result?.history);
+      };
+      request.onerror = () => {
+        resolve(undefined);
+      };
+    });
+  }
+
   // Removes the transaction from the unsent transactions list.
   markTransactionSent(transactionId: string) {
     const index = this.getFileIndex(false, 'transactionId');
@@ -190,6 +242,16 @@ class Offline {
       });
     }
 
+    // the undo history is restored after the unsent transactions, since
+    // applying them adds to the undo stack
+    if (this.userId) {
+      const undoHistory = await this.loadUndoHistory();
+      if (undoHistory && !core.restoreUndoHistory(undoHistory) && debugOffline) {
+        console.log('[Offline] Saved undo history is older than the file; ignoring it.');
+      }
+      core.setPersistUndoHistory(true);
+    }
+
     coreClient.sendOfflineTransactionsApplied(this.stats.timestamps);
   }
 
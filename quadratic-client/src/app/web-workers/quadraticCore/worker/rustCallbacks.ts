@@ -21,6 +21,7 @@ import type {
 declare var self: WorkerGlobalScope &
   typeof globalThis & {
     addUnsentTransaction: (transactionId: string, transaction: string, operations: number) => void;
+    undoHistoryChanged: () => void;
     sendTransaction: (transactionId: string, operations: ArrayBufferLike) => void;
     sendImportProgress: (
       filename: string,
@@ -76,6 +77,7 @@ declare var self: WorkerGlobalScope &
       y: number,
       sheetId: string,
       code: string,
+      params: string,
       connector_type: ConnectionKind,
       connection_id: String
     ) => void;
@@ -248,16 +250,21 @@ export const jsUndoRedo = (undo: string, redo: string) => {
   self.sendUndoRedo(undo, redo);
 };
 
+export const jsUndoHistoryChanged = () => {
+  self.undoHistoryChanged();
+};
+
 export const jsConnection = (
   transactionId: string,
   x: number,
   y: number,
   sheetId: string,
   code: string,
+  params: string,
   connector_type: ConnectionKind,
   connection_id: String
 ) => {
-  self.sendConnection(transactionId, x, y, sheetId, code, connector_type, connection_id);
+  self.sendConnection(transactionId, x, y, sheetId, code, params, connector_type, connection_id);
 };
 
 export const jsSendImage = (sheetId: string, x: number, y: number, image?: string, w?: string, h?: string) => {
@@ -82,9 +82,12 @@ export const loader = async ({ request, params }: LoaderFunctionArgs): Promise<F
     checkpoint.sequenceNumber = c.sequenceNumber;
   }
 
-  // initialize Core web worker
+  // initialize Core web worker (the undo history is saved offline for each
+  // file and signed-in user)
+  const user = await authClient.user();
   const result = await quadraticCore.load({
     fileId: uuid,
+    userId: user?.sub,
     teamUuid: data.team.uuid,
     url: checkpoint.url,
     version: checkpoint.version,
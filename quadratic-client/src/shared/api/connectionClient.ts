@@ -21,13 +21,37 @@ const SqlSchema = z.object({
     z.object({
       name: z.string(),
       schema: z.string(), // public or ...?
+      kind: z.enum(['table', 'view']),
       columns: z.array(
         z.object({
           name: z.string(),
           type: z.string(),
           is_nullable: z.boolean(),
+          default: z.string().nullable(),
+          comment: z.string().nullable(),
         })
       ),
+      primary_key: z.array(z.string()),
+      foreign_keys: z.array(
+        z.object({
+          name: z.string(),
+          columns: z.array(z.string()),
+          referenced_schema: z.string(),
+          referenced_table: z.string(),
+          referenced_columns: z.array(z.string()),
+        })
+      ),
+      indexes: z.array(
+        z.object({
+          name: z.string(),
+          columns: z.array(z.string()),
+          is_unique: z.boolean(),
+          is_primary: z.boolean(),
+        })
+      ),
+      // estimated by the database
+      row_count: z.number().nullable(),
+      comment: z.string().nullable(),
     })
   ),
 });
@@ -43,7 +67,9 @@ export const connectionClient = {
     get: async (
       connectionType: 'postgres' | 'mysql' | 'mssql' | 'snowflake',
       connectionId: string,
-      teamUuid: string
+      teamUuid: string,
+      // only introspect these schemas, for huge catalogs
+      schemas: string[] = []
     ): Promise<SqlSchemaResponse | null> => {
       // This might get called on a public file (where the user might not be
       // logged in but can still access the file). If they're not logged in,
@@ -57,7 +83,8 @@ export const connectionClient = {
       const headers = new Headers(await jwtHeader());
       headers.set('X-Team-Id', teamUuid);
 
-      const res = await fetch(`${API_URL}/${connectionType}/schema/${connectionId}`, {
+      const query = schemas.length ? `?schemas=${encodeURIComponent(schemas.join(','))}` : '';
+      const res = await fetch(`${API_URL}/${connectionType}/schema/${connectionId}${query}`, {
         method: 'GET',
         headers,
       });
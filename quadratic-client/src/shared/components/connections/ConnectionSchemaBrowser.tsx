@@ -199,6 +199,11 @@ function getTableQuery({ table: { name, schema }, connectionKind }: { table: Tab
       return `SELECT TOP 100 * FROM [${schema}].[${name}]`;
     case 'SNOWFLAKE':
       return `SELECT * FROM "${schema}"."${name}" LIMIT 100`;
+    case 'SQLITE':
+    case 'DUCKDB':
+      return `SELECT * FROM "${schema}"."${name}" LIMIT 100`;
+    case 'CLICKHOUSE':
+      return `SELECT * FROM \`${schema}\`.\`${name}\` LIMIT 100`;
     default:
       return '';
   }
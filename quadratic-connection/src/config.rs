@@ -10,6 +10,14 @@ use dotenv::dotenv;
 use quadratic_rust_shared::environment::Environment;
 use serde::Deserialize;
 
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum StorageType {
+    S3,
+    #[default]
+    FileSystem,
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 pub(crate) struct Config {
@@ -22,6 +30,93 @@ pub(crate) struct Config {
     pub(crate) m2m_auth_token: String,
     pub(crate) max_response_bytes: u64,
     pub(crate) static_ips: Vec<String>,
+
+    // Longest a query may run before it's cancelled
+    #[serde(default = "default_query_timeout_s")]
+    pub(crate) query_timeout_s: u64,
+
+    // How long a pooled database connection may be idle before it's closed
+    #[serde(default = "default_pool_idle_timeout_s")]
+    pub(crate) pool_idle_timeout_s: u64,
+
+    // Hosts the proxy may send requests to, eg, "api.example.com" or
+    // "*.example.com".  Any public host is allowed when empty.
+    #[serde(default)]
+    pub(crate) proxy_allowed_hosts: Vec<String>,
+
+    // Allow the proxy to send requests to private and other non-public
+    // addresses.  Only enable this for local development.
+    #[serde(default)]
+    pub(crate) proxy_allow_private_ips: bool,
+
+    #[serde(default = "default_proxy_max_response_bytes")]
+    pub(crate) proxy_max_response_bytes: u64,
+
+    #[serde(default = "default_proxy_timeout_s")]
+    pub(crate) proxy_timeout_s: u64,
+
+    #[serde(default = "default_proxy_max_redirects")]
+    pub(crate) proxy_max_redirects: usize,
+
+    // Directory that SQLite and DuckDB connections open database files from.
+    // Paths in those connections are relative to it and may not escape it.
+    #[serde(default = "default_file_connections_dir")]
+    pub(crate) file_connections_dir: String,
+
+    // Cache query results, so that rerunning a query within its TTL doesn't
+    // send it to the database again
+    #[serde(default)]
+    pub(crate) query_cache_enabled: bool,
+
+    // How long cached results are used for, unless a connection overrides it
+    #[serde(default = "default_query_cache_ttl_s")]
+    pub(crate) query_cache_ttl_s: u64,
+
+    // Storage Type: s3 or file-system
+    #[serde(default)]
+    pub(crate) query_cache_storage_type: StorageType,
+
+    // StorageType::S3
+    pub(crate) aws_s3_region: Option<String>,
+    pub(crate) aws_s3_bucket_name: Option<String>,
+    pub(crate) aws_s3_access_key_id: Option<String>,
+    pub(crate) aws_s3_secret_access_key: Option<String>,
+
+    // StorageType::FileSystem
+    #[serde(default = "default_query_cache_dir")]
+    pub(crate) query_cache_dir: String,
+}
+
+fn default_query_timeout_s() -> u64 {
+    300
+}
+
+fn default_pool_idle_timeout_s() -> u64 {
+    300
+}
+
+fn default_proxy_max_response_bytes() -> u64 {
+    15_728_640
+}
+
+fn default_proxy_timeout_s() -> u64 {
+    15
+}
+
+fn default_proxy_max_redirects() -> usize {
+    5
+}
+
+fn default_file_connections_dir() -> String {
+    "data".into()
+}
+
+fn default_query_cache_ttl_s() -> u64 {
+    300
+}
+
+fn default_query_cache_dir() -> String {
+    "query-cache".into()
 }
 
 /// Load the global configuration from the environment into Config.
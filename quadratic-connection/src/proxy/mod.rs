@@ -0,0 +1,274 @@
+//! Proxy
+//!
+//! Forward requests from user code to the URL in the `x-proxy-url` header,
+//! within the limits of the proxy policy (see `policy`).  Every proxied
+//! request is logged with the `proxy_audit` target.
+
+pub(crate) mod policy;
+
+use std::time::Duration;
+
+use axum::{
+    Extension,
+    body::Body,
+    extract::Request,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use http::{HeaderName, HeaderValue};
+use reqwest::{Client, Method, RequestBuilder, Url};
+use tokio::time::Instant;
+
+use crate::auth::Claims;
+use crate::error::{ConnectionError, Result, proxy_error};
+use crate::state::State;
+
+const AUDIT_TARGET: &str = "proxy_audit";
+const AUTHORIZATION_HEADER: &str = "authorization";
+const PROXY_URL_HEADER: &str = "x-proxy-url";
+const PROXY_HEADER_PREFIX: &str = "x-proxy-";
+
+pub(crate) async fn axum_to_reqwest(
+    url: &str,
+    req: Request<Body>,
+    client: Client,
+    timeout: Duration,
+) -> Result<RequestBuilder> {
+    let method_bytes = req.method().as_str().as_bytes();
+    let method = Method::from_bytes(method_bytes).map_err(proxy_error)?;
+
+    let mut headers = reqwest::header::HeaderMap::with_capacity(req.headers().len());
+    let headers_to_ignore = ["host", AUTHORIZATION_HEADER, PROXY_URL_HEADER];
+
+    for (name, value) in req
+        .headers()
+        .into_iter()
+        .filter(|(name, _)| !headers_to_ignore.contains(&name.as_str()))
+        .map(|(name, value)| {
+            let name = name.as_str();
+            if let Some(name) = name.strip_prefix(PROXY_HEADER_PREFIX) {
+                (name, value)
+            } else {
+                (name, value)
+            }
+        })
+    {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_ref()).map_err(proxy_error)?;
+        let value =
+            reqwest::header::HeaderValue::from_bytes(value.as_ref()).map_err(proxy_error)?;
+        headers.insert(name, value);
+    }
+
+    let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+        .await
+        .map_err(proxy_error)?;
+
+    let reqwest = reqwest::Request::new(method, url.parse().map_err(proxy_error)?);
+    let reqwest = reqwest::RequestBuilder::from_parts(client, reqwest)
+        .headers(headers)
+        .body(reqwest::Body::from(body))
+        .timeout(timeout);
+
+    Ok(reqwest)
+}
+
+/// Convert the response, ending its body with an error if it's larger than
+/// `max_bytes`
+pub(crate) fn reqwest_to_axum(
+    reqwest_response: reqwest::Response,
+    max_bytes: u64,
+) -> Result<Response<Body>> {
+    if reqwest_response
+        .content_length()
+        .is_some_and(|length| length > max_bytes)
+    {
+        return Err(too_large(max_bytes));
+    }
+
+    let mut response_builder = Response::builder().status(reqwest_response.status().as_u16());
+
+    for (name, value) in reqwest_response.headers().into_iter() {
+        let name = HeaderName::from_bytes(name.as_ref()).map_err(proxy_error)?;
+        let value = HeaderValue::from_bytes(value.as_ref()).map_err(proxy_error)?;
+        response_builder = response_builder.header(name, value);
+    }
+
+    // the content length is optional, so the body is counted as it streams
+    let mut received = 0;
+    let body = reqwest_response.bytes_stream().map(move |chunk| {
+        let chunk = chunk.map_err(proxy_error)?;
+        received += chunk.len() as u64;
+
+        match received > max_bytes {
+            true => Err(too_large(max_bytes)),
+            false => Ok(chunk),
+        }
+    });
+
+    let response = response_builder
+        .body(Body::from_stream(body))
+        .map_err(proxy_error)?;
+
+    Ok(response)
+}
+
+fn too_large(max_bytes: u64) -> ConnectionError {
+    ConnectionError::Proxy(format!("Response is larger than {max_bytes} bytes"))
+}
+
+/// The URL without its credentials, query or fragment, which may hold secrets
+fn audit_url(url: &Url) -> String {
+    let mut url = url.to_owned();
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.set_query(None);
+    url.set_fragment(None);
+    url.to_string()
+}
+
+pub(crate) async fn proxy(
+    state: Extension<State>,
+    claims: Claims,
+    req: Request<Body>,
+) -> Result<impl IntoResponse> {
+    let start = Instant::now();
+    let method = req.method().to_owned();
+    let url = req
+        .headers()
+        .get(PROXY_URL_HEADER)
+        .ok_or_else(|| ConnectionError::Proxy("No proxy header found".to_string()))?
+        .to_str()
+        .map_err(proxy_error)?
+        .parse::<Url>()
+        .map_err(proxy_error)?;
+    let logged_url = audit_url(&url);
+
+    let reqwest_response = async {
+        state.settings.proxy_policy.check_url(&url)?;
+
+        let timeout = state.settings.proxy_timeout;
+        let request_builder = axum_to_reqwest(url.as_str(), req, state.client.clone(), timeout);
+        request_builder.await?.send().await.map_err(proxy_error)
+    }
+    .await
+    .inspect_err(|error| {
+        tracing::warn!(
+            target: AUDIT_TARGET,
+            user = %claims.sub,
+            %method,
+            url = %logged_url,
+            %error,
+            "Proxy request failed"
+        );
+    })?;
+
+    tracing::info!(
+        target: AUDIT_TARGET,
+        user = %claims.sub,
+        %method,
+        url = %logged_url,
+        status = reqwest_response.status().as_u16(),
+        content_length = ?reqwest_response.content_length(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "Proxied request"
+    );
+
+    let response = reqwest_to_axum(reqwest_response, state.settings.proxy_max_response_bytes)?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use http::header::ACCEPT;
+
+    use super::*;
+    use crate::test_util::{get_claims, new_state, response_bytes};
+
+    const URL: &str = "https://www.google.com/";
+
+    fn proxy_request_to(url: &'static str) -> Request<Body> {
+        let mut request = Request::new(Body::empty());
+        request
+            .headers_mut()
+            .insert(PROXY_URL_HEADER, HeaderValue::from_static(url));
+        request
+    }
+
+    #[tokio::test]
+    async fn proxy_request() {
+        let state = Extension(new_state().await);
+        let request = proxy_request_to(URL);
+        let data = proxy(state, get_claims(), request).await.unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), 200);
+        assert_ne!(response_bytes(response).await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn proxy_denies_internal_addresses() {
+        let denied = [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:3003/health",
+            // hostnames are checked once they're resolved
+            "http://localhost:3003/health",
+        ];
+
+        for url in denied {
+            let state = Extension(new_state().await);
+            let request = proxy_request_to(url);
+            let response = proxy(state, get_claims(), request).await;
+
+            assert!(response.is_err(), "{url} was proxied");
+        }
+    }
+
+    #[tokio::test]
+    async fn proxy_limits_response_bytes() {
+        let mut state = Extension(new_state().await);
+        state.settings.proxy_max_response_bytes = 1;
+        let request = proxy_request_to(URL);
+
+        // responses without a content length fail once the body is too large
+        match proxy(state, get_claims(), request).await {
+            Ok(data) => {
+                let body = data.into_response().into_body();
+                assert!(axum::body::to_bytes(body, usize::MAX).await.is_err());
+            }
+            Err(error) => assert!(error.to_string().contains("larger than")),
+        }
+    }
+
+    #[tokio::test]
+    async fn proxy_axum_to_reqwest() {
+        let state = Extension(new_state().await);
+        let accept = "application/json";
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = http::Method::POST;
+        request
+            .headers_mut()
+            .insert(ACCEPT, HeaderValue::from_static(accept));
+        request
+            .headers_mut()
+            .insert(PROXY_URL_HEADER, HeaderValue::from_static(URL));
+
+        let timeout = state.settings.proxy_timeout;
+        let result = axum_to_reqwest(URL, request, state.client.clone(), timeout)
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(result.method(), Method::POST);
+        assert_eq!(result.url().to_string(), URL);
+
+        // PROXY_URL_HEADER doesn't get copied over
+        assert_eq!(result.headers().len(), 1);
+        assert_eq!(
+            result.headers().get(reqwest::header::ACCEPT).unwrap(),
+            accept
+        );
+    }
+}
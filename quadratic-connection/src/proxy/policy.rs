@@ -0,0 +1,307 @@
+//! Proxy Policy
+//!
+//! Restrict where the proxy may send requests, so that it can't be used to
+//! reach our internal network (SSRF).  Loopback, private, link-local and other
+//! non-public addresses are denied unless allowed in the config, and hosts can
+//! be limited to an allow-list.
+//!
+//! Hostnames are checked by the client's DNS resolver rather than before the
+//! request is sent.  The addresses that are checked are the ones connected
+//! to, so a host that resolves to a public address and then a private one
+//! (DNS rebinding) is still denied.  Redirects are checked the same way.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::{Client, ClientBuilder, Url};
+
+use crate::config::Config;
+use crate::error::{ConnectionError, Result, proxy_error};
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProxyPolicy {
+    // hosts that may be proxied to, eg, "api.example.com" or "*.example.com",
+    // or any public host when empty
+    allowed_hosts: Vec<String>,
+
+    // allow non-public addresses, for local development
+    allow_private_ips: bool,
+}
+
+impl ProxyPolicy {
+    pub(crate) fn new(config: &Config) -> Self {
+        ProxyPolicy {
+            allowed_hosts: config
+                .proxy_allowed_hosts
+                .iter()
+                .map(|host| host.trim().to_ascii_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect(),
+            allow_private_ips: config.proxy_allow_private_ips,
+        }
+    }
+
+    /// Check a URL's scheme and host.  Hostnames are resolved and checked
+    /// when connecting, but IP addresses aren't resolved so are checked here.
+    pub(crate) fn check_url(&self, url: &Url) -> Result<()> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(denied(format!("the {} scheme", url.scheme())));
+        }
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| denied("a URL without a host"))?
+            .to_ascii_lowercase();
+
+        if !self.is_allowed_host(&host) {
+            return Err(denied(format!("the host {host}")));
+        }
+
+        // IPv6 hosts are bracketed in URLs
+        match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(ip) => self.check_ip(ip),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Check an address that a request would connect to
+    pub(crate) fn check_ip(&self, ip: IpAddr) -> Result<()> {
+        match self.allow_private_ips || is_public(ip) {
+            true => Ok(()),
+            false => Err(denied(format!("the address {ip}"))),
+        }
+    }
+
+    fn is_allowed_host(&self, host: &str) -> bool {
+        self.allowed_hosts.is_empty()
+            || self
+                .allowed_hosts
+                .iter()
+                .any(|allowed| match allowed.strip_prefix("*.") {
+                    Some(domain) => host
+                        .strip_suffix(domain)
+                        .is_some_and(|subdomain| subdomain.ends_with('.')),
+                    None => host == allowed,
+                })
+    }
+}
+
+fn denied(destination: impl std::fmt::Display) -> ConnectionError {
+    ConnectionError::Proxy(format!("Proxying to {destination} is not allowed"))
+}
+
+/// Build a client that only connects to addresses the policy allows
+fn builder(policy: Arc<ProxyPolicy>) -> ClientBuilder {
+    Client::builder()
+        // a proxy would resolve hostnames itself, bypassing the resolver
+        .no_proxy()
+        .dns_resolver(Arc::new(PolicyResolver { policy }))
+}
+
+/// Build the proxy's client, which only connects to addresses the policy
+/// allows and follows at most `max_redirects` redirects
+pub(crate) fn client(policy: ProxyPolicy, max_redirects: usize) -> Result<Client> {
+    let policy = Arc::new(policy);
+    let redirect_policy = Arc::clone(&policy);
+
+    builder(policy)
+        .cookie_store(true)
+        .redirect(Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                return attempt.error(format!("Too many redirects (max {max_redirects})"));
+            }
+
+            match redirect_policy.check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(error) => attempt.error(error),
+            }
+        }))
+        .build()
+        .map_err(proxy_error)
+}
+
+/// Build the client of HTTP connections.  Their requests carry a team's
+/// secrets, so cookies aren't kept between requests and redirects may not
+/// leave the request's origin.
+pub(crate) fn connection_client(policy: ProxyPolicy, max_redirects: usize) -> Result<Client> {
+    builder(Arc::new(policy))
+        .redirect(Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                return attempt.error(format!("Too many redirects (max {max_redirects})"));
+            }
+
+            let same_origin = attempt
+                .previous()
+                .first()
+                .is_some_and(|first| first.origin() == attempt.url().origin());
+
+            match same_origin {
+                true => attempt.follow(),
+                false => {
+                    let error = denied(format!("a redirect to {}", attempt.url()));
+                    attempt.error(error)
+                }
+            }
+        }))
+        .build()
+        .map_err(proxy_error)
+}
+
+/// Resolves hostnames, failing if any of their addresses are denied
+struct PolicyResolver {
+    policy: Arc<ProxyPolicy>,
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = Arc::clone(&self.policy);
+
+        Box::pin(async move {
+            // the port is replaced with the URL's when connecting
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .collect::<Vec<SocketAddr>>();
+
+            for addr in &addrs {
+                policy.check_ip(addr.ip())?;
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether an address is publicly routable
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "this network", 0.0.0.0/8
+        || a == 0
+        // shared address space (carrier-grade NAT), 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // benchmarking, 198.18.0.0/15
+        || (a == 198 && (b & 0xfe) == 18)
+        // reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+
+    // NAT64 addresses, 64:ff9b::/96, embed an IPv4 address
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // IPv4-compatible addresses, ::/96 (deprecated)
+        || segments[..6] == [0; 6]
+        // site-local, fec0::/10 (deprecated)
+        || (segments[0] & 0xffc0) == 0xfec0
+        // documentation, 2001:db8::/32
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allowed_hosts: &[&str]) -> ProxyPolicy {
+        ProxyPolicy {
+            allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+            allow_private_ips: false,
+        }
+    }
+
+    fn check(policy: &ProxyPolicy, url: &str) -> bool {
+        policy.check_url(&url.parse().unwrap()).is_ok()
+    }
+
+    #[test]
+    fn denies_non_public_addresses() {
+        let denied = [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "::ffff:127.0.0.1",
+            "::127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "fd00::1",
+            "fe80::1",
+            "ff02::1",
+        ];
+
+        for ip in denied {
+            assert!(!is_public(ip.parse().unwrap()), "{ip} is public");
+        }
+
+        for ip in ["8.8.8.8", "2606:4700:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip} isn't public");
+        }
+    }
+
+    #[test]
+    fn checks_urls() {
+        let policy = policy(&[]);
+
+        assert!(check(&policy, "https://example.com/path"));
+        assert!(check(&policy, "http://8.8.8.8/"));
+
+        assert!(!check(&policy, "http://169.254.169.254/latest/meta-data"));
+        assert!(!check(&policy, "http://[::1]:3003/health"));
+        assert!(!check(&policy, "http://0x7f.1/"));
+        assert!(!check(&policy, "file:///etc/passwd"));
+
+        let private = ProxyPolicy {
+            allow_private_ips: true,
+            ..policy
+        };
+        assert!(check(&private, "http://127.0.0.1:3003/health"));
+    }
+
+    #[test]
+    fn checks_allowed_hosts() {
+        let policy = policy(&["api.example.com", "*.example.org"]);
+
+        assert!(check(&policy, "https://api.example.com/"));
+        assert!(check(&policy, "https://API.example.com/"));
+        assert!(check(&policy, "https://a.b.example.org/"));
+
+        assert!(!check(&policy, "https://example.com/"));
+        assert!(!check(&policy, "https://example.org/"));
+        assert!(!check(&policy, "https://badexample.org/"));
+    }
+}
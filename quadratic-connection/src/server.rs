@@ -7,15 +7,16 @@ use axum::{
     Extension, Json, Router,
     http::{Method, header::AUTHORIZATION},
     middleware::map_response,
-    response::Response,
+    response::{IntoResponse, Response},
     routing::{any, get, post},
 };
 use http::{
     HeaderName, HeaderValue,
-    header::{CACHE_CONTROL, PRAGMA},
+    header::{CACHE_CONTROL, CONTENT_TYPE, PRAGMA},
 };
 use quadratic_rust_shared::auth::jwt::get_jwks;
-use quadratic_rust_shared::sql::Connection;
+use quadratic_rust_shared::metrics::{self, METRICS};
+use quadratic_rust_shared::sql::{Connection, parameter::SqlParameter, stream::Page};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::time;
@@ -34,20 +35,48 @@ use crate::{
     health::{full_healthcheck, healthcheck},
     proxy::proxy,
     sql::{
+        cancel as cancel_query,
+        clickhouse::{
+            query as query_clickhouse, schema as schema_clickhouse, test as test_clickhouse,
+        },
+        duckdb::{query as query_duckdb, schema as schema_duckdb, test as test_duckdb},
+        http::{query as query_http, schema as schema_http, test as test_http},
         mssql::{query as query_mssql, schema as schema_mssql, test as test_mssql},
         mysql::{query as query_mysql, schema as schema_mysql, test as test_mysql},
         postgres::{query as query_postgres, schema as schema_postgres, test as test_postgres},
         snowflake::{query as query_snowflake, schema as schema_snowflake, test as test_snowflake},
+        sqlite::{query as query_sqlite, schema as schema_sqlite, test as test_sqlite},
     },
     state::State,
 };
 
 const STATS_INTERVAL_S: u64 = 5;
+const POOL_EVICTION_INTERVAL_S: u64 = 30;
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct SqlQuery {
     pub(crate) query: String,
     pub(crate) connection_id: Uuid,
+
+    // values bound to the query's placeholders, in order
+    #[serde(default)]
+    pub(crate) params: Vec<SqlParameter>,
+
+    // send the results as Parquet row groups with chunked transfer
+    #[serde(default)]
+    pub(crate) stream: bool,
+
+    // a page of the results, eg, `"offset": 1000, "limit": 1000`
+    #[serde(default, flatten)]
+    pub(crate) page: Page,
+
+    // chosen by the client, so that it can cancel the query while it runs
+    #[serde(default)]
+    pub(crate) query_id: Option<Uuid>,
+
+    // query the database even when the results are cached
+    #[serde(default)]
+    pub(crate) refresh_cache: bool,
 }
 
 #[derive(Serialize, PartialEq, Debug)]
@@ -120,6 +149,25 @@ pub(crate) fn app(state: State) -> Result<Router> {
         .route("/snowflake/test", post(test_snowflake))
         .route("/snowflake/query", post(query_snowflake))
         .route("/snowflake/schema/:id", get(schema_snowflake))
+        // clickhouse
+        .route("/clickhouse/test", post(test_clickhouse))
+        .route("/clickhouse/query", post(query_clickhouse))
+        .route("/clickhouse/schema/:id", get(schema_clickhouse))
+        // sqlite
+        .route("/sqlite/test", post(test_sqlite))
+        .route("/sqlite/query", post(query_sqlite))
+        .route("/sqlite/schema/:id", get(schema_sqlite))
+        // duckdb
+        .route("/duckdb/test", post(test_duckdb))
+        .route("/duckdb/query", post(query_duckdb))
+        .route("/duckdb/schema/:id", get(schema_duckdb))
+        // http
+        .route("/http/test", post(test_http))
+        .route("/http/query", post(query_http))
+        .route("/http/schema/:id", get(schema_http))
+        //
+        // cancel a running query
+        .route("/query/cancel/:id", post(cancel_query))
         //
         // proxy
         .route("/proxy", any(proxy))
@@ -138,6 +186,9 @@ pub(crate) fn app(state: State) -> Result<Router> {
         // full healthcheck of dependencies
         .route("/health/full", get(full_healthcheck))
         //
+        // prometheus metrics
+        .route("/metrics", get(metrics))
+        //
         // state, required
         .with_state(state.clone())
         //
@@ -185,7 +236,7 @@ pub(crate) async fn serve() -> Result<()> {
 
     let config = config()?;
     let jwks = get_jwks(&config.auth0_jwks_uri).await?;
-    let state = State::new(&config, Some(jwks.clone()))?;
+    let state = State::new(&config, Some(jwks.clone())).await?;
     let app = app(state.clone())?;
 
     let listener = tokio::net::TcpListener::bind(format!("{}:{}", config.host, config.port))
@@ -196,6 +247,25 @@ pub(crate) async fn serve() -> Result<()> {
         .local_addr()
         .map_err(|e| ConnectionError::InternalServer(e.to_string()))?;
 
+    // close pooled connections that have been idle for too long
+    tokio::spawn({
+        let state = state.clone();
+
+        async move {
+            let mut interval = time::interval(Duration::from_secs(POOL_EVICTION_INTERVAL_S));
+
+            loop {
+                interval.tick().await;
+
+                let evicted = state.pool.evict_idle(state.settings.pool_idle_timeout);
+
+                if evicted > 0 {
+                    tracing::info!("Closed {evicted} idle database connections");
+                }
+            }
+        }
+    });
+
     // log stats in a separate thread
     tokio::spawn({
         async move {
@@ -235,6 +305,10 @@ pub(crate) async fn static_ips() -> Result<Json<StaticIpsResponse>> {
     Ok(response.into())
 }
 
+pub(crate) async fn metrics() -> impl IntoResponse {
+    ([(CONTENT_TYPE, metrics::CONTENT_TYPE)], METRICS.render())
+}
+
 pub(crate) async fn test_connection(connection: impl Connection) -> Json<TestResponse> {
     let message = match connection.connect().await {
         Ok(_) => None,
@@ -252,6 +326,12 @@ pub(crate) mod tests {
 
     use super::*;
 
+    #[tokio::test]
+    async fn gets_metrics() {
+        let response = process_route("/metrics", http::Method::GET, Body::empty()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn gets_static_ips() {
         let response = process_route("/static-ips", http::Method::GET, Body::empty()).await;
@@ -0,0 +1,221 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    response::IntoResponse,
+};
+use http::HeaderMap;
+use quadratic_rust_shared::{
+    quadratic_api::Connection as ApiConnection,
+    sql::{Connection, clickhouse_connection::ClickHouseConnection},
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    connection::get_api_connection,
+    error::Result,
+    header::get_team_id_header,
+    server::{SqlQuery, TestResponse, test_connection},
+    state::State,
+};
+
+use super::{Schema, SchemaQuery, query_generic};
+
+/// Test the connection to the database.
+pub(crate) async fn test(Json(connection): Json<ClickHouseConnection>) -> Json<TestResponse> {
+    test_connection(connection).await
+}
+
+/// Get the connection details from the API and create a ClickHouseConnection.
+async fn get_connection(
+    state: &State,
+    claims: &Claims,
+    connection_id: &Uuid,
+    team_id: &Uuid,
+) -> Result<(ClickHouseConnection, ApiConnection<ClickHouseConnection>)> {
+    let connection = if cfg!(not(test)) {
+        get_api_connection(state, "", &claims.sub, connection_id, team_id).await?
+    } else {
+        ApiConnection {
+            uuid: Uuid::new_v4(),
+            name: "".into(),
+            r#type: "".into(),
+            created_date: "".into(),
+            updated_date: "".into(),
+            type_details: ClickHouseConnection {
+                host: "0.0.0.0".into(),
+                port: Some("8123".into()),
+                username: Some("user".into()),
+                password: Some("password".into()),
+                database: "clickhouse-connection".into(),
+                tls: None,
+                ssh: None,
+                cache_ttl_s: None,
+                read_only: false,
+            },
+        }
+    };
+
+    let mut clickhouse_connection = ClickHouseConnection::new(
+        connection.type_details.username.to_owned(),
+        connection.type_details.password.to_owned(),
+        connection.type_details.host.to_owned(),
+        connection.type_details.port.to_owned(),
+        connection.type_details.database.to_owned(),
+        connection.type_details.tls.to_owned(),
+        connection.type_details.ssh.to_owned(),
+    );
+
+    clickhouse_connection.cache_ttl_s = connection.type_details.cache_ttl_s;
+    clickhouse_connection.read_only = connection.type_details.read_only;
+
+    Ok((clickhouse_connection, connection))
+}
+
+/// Query the database and return the results as a parquet file.
+pub(crate) async fn query(
+    headers: HeaderMap,
+    state: Extension<State>,
+    claims: Claims,
+    sql_query: Json<SqlQuery>,
+) -> Result<impl IntoResponse> {
+    let team_id = get_team_id_header(&headers)?;
+    let connection = get_connection(&state, &claims, &sql_query.connection_id, &team_id)
+        .await?
+        .0;
+    query_generic::<ClickHouseConnection>(connection, state, sql_query, Some(&claims)).await
+}
+
+/// Get the schema of the database
+pub(crate) async fn schema(
+    Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
+    headers: HeaderMap,
+    state: Extension<State>,
+    claims: Claims,
+) -> Result<Json<Schema>> {
+    let team_id = get_team_id_header(&headers)?;
+    let (connection, api_connection) = get_connection(&state, &claims, &id, &team_id).await?;
+    let mut pool = connection.connect().await?;
+    let database_schema = connection
+        .schema(&mut pool, &schema_query.schemas())
+        .await?;
+    let schema = Schema {
+        id: api_connection.uuid,
+        name: api_connection.name,
+        r#type: api_connection.r#type,
+        database: api_connection.type_details.database,
+        tables: database_schema.tables.into_values().collect(),
+    };
+
+    Ok(Json(schema))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        num_vec, test_connection,
+        test_util::{
+            get_claims, new_state, new_team_id_with_header, response_bytes, str_vec,
+            validate_parquet,
+        },
+    };
+    use arrow_schema::DataType;
+    use bytes::Bytes;
+    use http::StatusCode;
+    use quadratic_rust_shared::sql::{parameter::SqlParameter, schema::SchemaColumn, stream::Page};
+    use tracing_test::traced_test;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    #[traced_test]
+    async fn clickhouse_test_connection() {
+        test_connection!(get_connection);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn clickhouse_schema() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let state = Extension(new_state().await);
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            headers,
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.0.database, "clickhouse-connection");
+
+        let table = &response.0.tables[0];
+        assert_eq!(table.name, "all_native_data_types");
+        assert_eq!(table.schema, "clickhouse-connection");
+        assert_eq!(table.columns[0], SchemaColumn::new("id", "UInt32", false));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn clickhouse_query_with_parameters() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let sql_query = SqlQuery {
+            query: "select id, string_col from all_native_data_types \
+                where string_col = {p1:String} and id = {p2:Int64}"
+                .into(),
+            connection_id,
+            params: vec![
+                SqlParameter::Text("string_data".into()),
+                SqlParameter::Integer(1),
+            ],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
+        };
+        let state = Extension(new_state().await);
+        let data = query(headers, state, get_claims(), Json(sql_query))
+            .await
+            .unwrap();
+        let response = data.into_response();
+
+        let expected = vec![
+            (DataType::UInt32, num_vec!(1_u32)),
+            (DataType::Utf8, str_vec("string_data")),
+        ];
+
+        validate_parquet(response, expected).await;
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn clickhouse_query_max_response_bytes() {
+        let connection_id = Uuid::new_v4();
+        let sql_query = SqlQuery {
+            query: "select * from all_native_data_types order by id limit 1".into(),
+            connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
+        };
+        let mut state = Extension(new_state().await);
+        state.settings.max_response_bytes = 0;
+        let (_, headers) = new_team_id_with_header().await;
+        let data = query(headers, state, get_claims(), Json(sql_query))
+            .await
+            .unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response_bytes(response).await;
+        assert_eq!(body, Bytes::new());
+    }
+}
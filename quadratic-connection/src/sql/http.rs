@@ -0,0 +1,188 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    response::IntoResponse,
+};
+use http::HeaderMap;
+use quadratic_rust_shared::{
+    quadratic_api::Connection as ApiConnection,
+    sql::{Connection, http_connection::HttpConnection},
+};
+use reqwest::Url;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    connection::get_api_connection,
+    error::{ConnectionError, Result},
+    header::get_team_id_header,
+    server::{SqlQuery, TestResponse, test_connection},
+    state::State,
+};
+
+use super::{Schema, SchemaQuery, query_generic};
+
+/// Test the connection to the API.  Requests are sent with the proxy's
+/// policy, so users can't reach our internal network.
+pub(crate) async fn test(
+    state: Extension<State>,
+    Json(connection): Json<HttpConnection>,
+) -> Json<TestResponse> {
+    match new_http_connection(&state, &connection) {
+        Ok(connection) => test_connection(connection).await,
+        Err(e) => TestResponse::new(false, Some(e.to_string())).into(),
+    }
+}
+
+/// Create an HttpConnection that sends requests with the connection client.
+/// Hostnames are checked when they're resolved, but the base URL's scheme
+/// and IP address are checked here.
+fn new_http_connection(state: &State, connection: &HttpConnection) -> Result<HttpConnection> {
+    let base_url = Url::parse(&connection.base_url)
+        .map_err(|e| ConnectionError::Connection(format!("Invalid base URL: {e}")))?;
+    state.settings.proxy_policy.check_url(&base_url)?;
+
+    let mut http_connection = HttpConnection::new(
+        connection.base_url.to_owned(),
+        connection.auth.to_owned(),
+        connection.pagination.to_owned(),
+    );
+
+    http_connection.cache_ttl_s = connection.cache_ttl_s;
+    http_connection.client = Some(state.http_client.to_owned());
+
+    Ok(http_connection)
+}
+
+/// Get the connection details from the API and create an HttpConnection.
+async fn get_connection(
+    state: &State,
+    claims: &Claims,
+    connection_id: &Uuid,
+    team_id: &Uuid,
+) -> Result<(HttpConnection, ApiConnection<HttpConnection>)> {
+    let connection = if cfg!(not(test)) {
+        get_api_connection(state, "", &claims.sub, connection_id, team_id).await?
+    } else {
+        ApiConnection {
+            uuid: Uuid::new_v4(),
+            name: "".into(),
+            r#type: "".into(),
+            created_date: "".into(),
+            updated_date: "".into(),
+            type_details: HttpConnection::new(
+                "https://api.example.com/v1".into(),
+                None,
+                Default::default(),
+            ),
+        }
+    };
+
+    let http_connection = new_http_connection(state, &connection.type_details)?;
+
+    Ok((http_connection, connection))
+}
+
+/// Query the API and return the results as a parquet file.
+pub(crate) async fn query(
+    headers: HeaderMap,
+    state: Extension<State>,
+    claims: Claims,
+    sql_query: Json<SqlQuery>,
+) -> Result<impl IntoResponse> {
+    let team_id = get_team_id_header(&headers)?;
+    let connection = get_connection(&state, &claims, &sql_query.connection_id, &team_id)
+        .await?
+        .0;
+    query_generic::<HttpConnection>(connection, state, sql_query, Some(&claims)).await
+}
+
+/// Get the schema of the API, which has no tables
+pub(crate) async fn schema(
+    Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
+    headers: HeaderMap,
+    state: Extension<State>,
+    claims: Claims,
+) -> Result<Json<Schema>> {
+    let team_id = get_team_id_header(&headers)?;
+    let (connection, api_connection) = get_connection(&state, &claims, &id, &team_id).await?;
+    let mut pool = connection.connect().await?;
+    let database_schema = connection
+        .schema(&mut pool, &schema_query.schemas())
+        .await?;
+    let schema = Schema {
+        id: api_connection.uuid,
+        name: api_connection.name,
+        r#type: api_connection.r#type,
+        database: database_schema.database,
+        tables: database_schema.tables.into_values().collect(),
+    };
+
+    Ok(Json(schema))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::{get_claims, new_state, new_team_id_with_header};
+    use quadratic_rust_shared::sql::http_connection::{HttpAuth, HttpPagination};
+    use tracing_test::traced_test;
+
+    #[tokio::test]
+    #[traced_test]
+    async fn http_test_connection_denies_internal_addresses() {
+        let state = new_state().await;
+
+        for base_url in ["http://169.254.169.254/latest", "file:///etc/passwd", "/v1"] {
+            let connection = HttpConnection::new(base_url.into(), None, Default::default());
+            let response = test(Extension(state.to_owned()), Json(connection)).await;
+
+            assert!(!response.0.connected, "{base_url}");
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn http_get_connection() {
+        let connection_id = Uuid::new_v4();
+        let (team_id, _) = new_team_id_with_header().await;
+        let state = new_state().await;
+        let (connection, api_connection) =
+            get_connection(&state, &get_claims(), &connection_id, &team_id)
+                .await
+                .unwrap();
+
+        assert!(connection.client.is_some());
+        assert_eq!(connection.pagination, HttpPagination::None);
+        assert_eq!(api_connection.type_details.base_url, connection.base_url);
+    }
+
+    #[test]
+    fn http_deserializes_connection() {
+        let json = r#"{
+            "base_url": "https://api.example.com",
+            "auth": {"type": "api_key", "header": "X-Api-Key", "key": "secret"},
+            "pagination": {"type": "page", "page_param": "page"},
+            "cache_ttl_s": 60
+        }"#;
+        let connection = serde_json::from_str::<HttpConnection>(json).unwrap();
+
+        assert_eq!(
+            connection.auth,
+            Some(HttpAuth::ApiKey {
+                header: "X-Api-Key".into(),
+                key: "secret".into(),
+            })
+        );
+        assert_eq!(
+            connection.pagination,
+            HttpPagination::Page {
+                page_param: "page".into(),
+                first_page: 1,
+            }
+        );
+        assert_eq!(connection.cache_ttl_s, Some(60));
+    }
+}
@@ -1,20 +1,54 @@
-use axum::{Extension, Json, http::HeaderMap, response::IntoResponse};
-use quadratic_rust_shared::sql::{Connection, schema::SchemaTable};
-use serde::Serialize;
-use tokio::time::Instant;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::Path,
+    http::{HeaderMap, HeaderValue, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures::{StreamExt, stream};
+use quadratic_rust_shared::{
+    SharedError,
+    error::Result as SharedResult,
+    metrics::{METRICS, QUERY_BYTES, QUERY_CACHE_HITS, QUERY_DURATION},
+    sql::{Connection, error::Sql as SqlError, read_only::check_read_only, schema::SchemaTable},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc::channel, time::Instant};
 use uuid::Uuid;
 
 use crate::{
-    error::Result,
+    auth::Claims,
+    error::{ConnectionError, Result},
     header::{number_header, time_header},
     server::SqlQuery,
-    state::State,
+    state::{State, cache::record_count, pool::PoolKey, queries::QueryGuard},
 };
 
+pub(crate) mod clickhouse;
+pub(crate) mod duckdb;
+pub(crate) mod http;
 pub(crate) mod mssql;
 pub(crate) mod mysql;
 pub(crate) mod postgres;
 pub(crate) mod snowflake;
+pub(crate) mod sqlite;
+
+/// Parquet chunks buffered between a streaming query and its response
+const STREAM_CHANNEL_CAPACITY: usize = 8;
+
+/// The directory of a team's database files, within the data directory.  Each
+/// team has its own, so a connection can't open another team's files.
+pub(crate) fn team_data_dir(state: &State, team_id: &Uuid) -> PathBuf {
+    state
+        .settings
+        .file_connections_dir
+        .join(team_id.to_string())
+}
 
 #[derive(Debug, Serialize, PartialEq)]
 pub struct Schema {
@@ -25,26 +59,231 @@ pub struct Schema {
     pub tables: Vec<SchemaTable>,
 }
 
+/// Restricts introspection to some schemas in huge catalogs, eg,
+/// `?schemas=public,sales`.  All schemas are introspected by default.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct SchemaQuery {
+    schemas: Option<String>,
+}
+
+impl SchemaQuery {
+    pub(crate) fn schemas(&self) -> Vec<String> {
+        self.schemas
+            .iter()
+            .flat_map(|schemas| schemas.split(','))
+            .map(str::trim)
+            .filter(|schema| !schema.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// A query's database session, for cancelling it when the user asks to or
+/// when it runs for too long
+struct Cancellable {
+    guard: Option<QueryGuard>,
+    session_id: Option<String>,
+    timeout: Duration,
+}
+
+impl Cancellable {
+    /// Only queries with an id can be cancelled by the user, though all queries
+    /// time out
+    async fn new<T: Connection + Sync>(
+        connection: &T,
+        pool: &mut T::Conn,
+        state: &State,
+        query_id: Option<Uuid>,
+        user: Option<&str>,
+    ) -> Result<Self> {
+        let guard = match (query_id, user) {
+            (Some(query_id), Some(user)) => Some(state.queries.start(query_id, user)?),
+            _ => None,
+        };
+
+        Ok(Cancellable {
+            guard,
+            session_id: connection.session_id(pool).await?,
+            timeout: state.settings.query_timeout,
+        })
+    }
+
+    /// Run a query until it finishes, times out or is cancelled.
+    ///
+    /// Dropping the query's future doesn't stop it in the database, so queries
+    /// that don't finish are also cancelled there.  Their connections are left
+    /// mid-query, so they're closed rather than pooled.
+    async fn run<T: Connection + Sync, R>(
+        &self,
+        connection: &T,
+        query: impl Future<Output = SharedResult<R>>,
+    ) -> SharedResult<R> {
+        let cancelled = async {
+            match &self.guard {
+                Some(guard) => guard.cancel.notified().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let reason = tokio::select! {
+            result = query => return result,
+            _ = cancelled => "cancelled".to_string(),
+            _ = tokio::time::sleep(self.timeout) => {
+                format!("timed out after {}s", self.timeout.as_secs())
+            }
+        };
+
+        if let Some(session_id) = &self.session_id {
+            if let Err(error) = connection.cancel(session_id).await {
+                tracing::warn!("Error cancelling query in the database: {error}");
+            }
+        }
+
+        Err(SharedError::Sql(SqlError::Query(reason)))
+    }
+}
+
+/// Take a connection from the pool, or connect if there isn't one
+async fn connect<T: Connection + Sync>(
+    connection: &T,
+    state: &State,
+    pool_key: Option<&PoolKey>,
+) -> Result<(T::Conn, bool)>
+where
+    T::Conn: 'static,
+{
+    match pool_key.and_then(|pool_key| state.pool.take::<T::Conn>(pool_key)) {
+        Some(pool) => Ok((pool, true)),
+        None => Ok((connection.connect().await?, false)),
+    }
+}
+
 /// Query the database and return the results as a parquet file.
-pub(crate) async fn query_generic<T: Connection>(
+///
+/// Queries for a user's connection (ie, with claims) reuse pooled database
+/// connections and can be cancelled by their `query_id`.  Queries on
+/// read-only connections are rejected before they run if they write data.
+///
+/// When the query asks for a stream or a page of results, the results are
+/// sent as Parquet row groups while the query runs (see `query_stream`).
+/// Otherwise, results of queries that only read data are read from the query
+/// cache when it's enabled and they're fresh, unless the query asks to refresh
+/// them.
+pub(crate) async fn query_generic<T>(
     connection: T,
     state: Extension<State>,
     sql_query: Json<SqlQuery>,
-) -> Result<impl IntoResponse> {
+    claims: Option<&Claims>,
+) -> Result<Response>
+where
+    T: Connection + Serialize + Send + Sync + 'static,
+    T::Conn: 'static,
+{
+    if connection.read_only() {
+        check_read_only(&sql_query.query)?;
+    }
+
     let mut headers = HeaderMap::new();
     let start = Instant::now();
     let max_response_bytes = Some(state.settings.max_response_bytes);
+    let user = claims.map(|claims| claims.sub.as_str());
+    // read-only sessions aren't pooled, so a query that changes its session
+    // can't affect later queries
+    let pool_key = user
+        .filter(|_| !connection.read_only())
+        .map(|user| PoolKey::new(sql_query.connection_id, user, &connection));
+
+    // only whole results of a user's queries are cached, so streams, pages
+    // and connection tests always query the database.  Queries that may write
+    // data aren't cached, so that rerunning them runs them again.
+    let reads = connection.read_only() || check_read_only(&sql_query.query).is_ok();
+    let cache_entry = state
+        .cache
+        .as_ref()
+        .filter(|_| reads && user.is_some() && !sql_query.stream && sql_query.page.is_all())
+        .and_then(|cache| {
+            let entry = cache.entry(
+                sql_query.connection_id,
+                &connection,
+                connection.cache_ttl_s(),
+                &sql_query.query,
+                &sql_query.params,
+            )?;
+            Some((cache, entry))
+        });
+
+    let cached = match &cache_entry {
+        Some((cache, entry)) if !sql_query.refresh_cache => cache.get(entry).await,
+        _ => None,
+    };
+
+    if let Some(cached) = cached {
+        METRICS.increment(&QUERY_CACHE_HITS, &[("kind", T::KIND)], 1.0);
+
+        headers.insert("CACHE-HIT", number_header(true));
+        headers.insert("CACHE-AGE-MS", number_header(cached.age.as_millis()));
+        headers.insert("RECORD-COUNT", number_header(record_count(&cached.parquet)));
+        headers.insert("OVER-THE-LIMIT", number_header(false));
+        headers.insert("ELAPSED-TOTAL-MS", time_header(start));
+
+        return Ok((headers, cached.parquet).into_response());
+    }
 
     let start_connect = Instant::now();
-    let mut pool = connection.connect().await?;
+    let (mut pool, pooled) = connect(&connection, &state, pool_key.as_ref()).await?;
 
     headers.insert("ELAPSED-DATABASE-CONNECTION-MS", time_header(start_connect));
+    headers.insert("POOLED-CONNECTION", number_header(pooled));
+
+    let cancellable =
+        Cancellable::new(&connection, &mut pool, &state, sql_query.query_id, user).await?;
+
+    if sql_query.stream || !sql_query.page.is_all() {
+        let query = Query {
+            connection,
+            pool,
+            pool_key,
+            cancellable,
+        };
+        return query_stream(query, state, sql_query.0, headers, start).await;
+    }
 
     let start_query = Instant::now();
-    let (parquet, over_the_limit, num_records) = connection
-        .query(&mut pool, &sql_query.query, max_response_bytes)
+    let (parquet, over_the_limit, num_records) = cancellable
+        .run(
+            &connection,
+            connection.query(
+                &mut pool,
+                &sql_query.query,
+                &sql_query.params,
+                max_response_bytes,
+            ),
+        )
         .await?;
 
+    if let Some(pool_key) = pool_key {
+        state.pool.put(pool_key, pool);
+    }
+
+    let labels = [("kind", T::KIND)];
+    METRICS.observe(
+        &QUERY_DURATION,
+        &labels,
+        start_query.elapsed().as_secs_f64(),
+    );
+    METRICS.increment(&QUERY_BYTES, &labels, parquet.len() as f64);
+
+    // partial results aren't cached, so that a rerun can get them all
+    if let Some((cache, entry)) = cache_entry {
+        headers.insert("CACHE-HIT", number_header(false));
+
+        if !over_the_limit {
+            let cache = Arc::clone(cache);
+            let parquet = parquet.to_owned();
+            tokio::spawn(async move { cache.put(&entry, &parquet).await });
+        }
+    }
+
     headers.insert("RECORD-COUNT", number_header(num_records));
     headers.insert("ELAPSED-DATABASE-QUERY-MS", time_header(start_query));
     headers.insert("OVER-THE-LIMIT", number_header(over_the_limit));
@@ -52,5 +291,154 @@ pub(crate) async fn query_generic<T: Connection>(
     state.stats.lock().await.last_query_time = Some(Instant::now());
     headers.insert("ELAPSED-TOTAL-MS", time_header(start));
 
-    Ok((headers, parquet))
+    Ok((headers, parquet).into_response())
+}
+
+/// A connected query, moved into the task that streams its results
+struct Query<T: Connection> {
+    connection: T,
+    pool: T::Conn,
+    pool_key: Option<PoolKey>,
+    cancellable: Cancellable,
+}
+
+/// Query a page of the results as Parquet row groups.
+///
+/// Streamed responses use chunked transfer, so the headers are sent before
+/// the results are known.  Whether they're over the limit or there's another
+/// page is recorded in the Parquet footer instead.  Otherwise, the page is
+/// collected and returned with the usual headers, plus `NEXT-OFFSET` when
+/// there's another page.
+async fn query_stream<T>(
+    query: Query<T>,
+    state: Extension<State>,
+    sql_query: SqlQuery,
+    mut headers: HeaderMap,
+    start: Instant,
+) -> Result<Response>
+where
+    T: Connection + Send + Sync + 'static,
+    T::Conn: 'static,
+{
+    let max_response_bytes = Some(state.settings.max_response_bytes);
+    let stats = Arc::clone(&state.stats);
+    let connection_pool = Arc::clone(&state.pool);
+    let streaming = sql_query.stream;
+    let (sender, mut receiver) = channel(STREAM_CHANNEL_CAPACITY);
+    let start_query = Instant::now();
+
+    let task = tokio::spawn(async move {
+        let Query {
+            connection,
+            mut pool,
+            pool_key,
+            cancellable,
+        } = query;
+        let result = cancellable
+            .run(
+                &connection,
+                connection.query_stream(
+                    &mut pool,
+                    &sql_query.query,
+                    &sql_query.params,
+                    sql_query.page,
+                    max_response_bytes,
+                    sender.clone(),
+                ),
+            )
+            .await;
+
+        match &result {
+            Ok(summary) => {
+                let labels = [("kind", T::KIND)];
+                METRICS.observe(
+                    &QUERY_DURATION,
+                    &labels,
+                    start_query.elapsed().as_secs_f64(),
+                );
+                METRICS.increment(&QUERY_BYTES, &labels, summary.bytes as f64);
+
+                if let Some(pool_key) = pool_key {
+                    connection_pool.put(pool_key, pool);
+                }
+            }
+
+            // once a response is streaming, an error ends it early
+            Err(error) => {
+                let _ = sender.send(Err(error.to_owned())).await;
+            }
+        }
+
+        stats.lock().await.last_query_time = Some(Instant::now());
+        result
+    });
+
+    if streaming {
+        // wait for the first chunk, so that a query that fails to run gets an
+        // error response rather than an empty one
+        let first = receiver.recv().await.transpose()?;
+        let rest = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        });
+        let chunks = stream::iter(first.map(Ok::<_, SharedError>)).chain(rest);
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+
+        return Ok((headers, Body::from_stream(chunks)).into_response());
+    }
+
+    let mut parquet = Vec::new();
+
+    while let Some(chunk) = receiver.recv().await {
+        parquet.extend_from_slice(&chunk?);
+    }
+
+    let summary = task
+        .await
+        .map_err(|e| ConnectionError::InternalServer(e.to_string()))??;
+
+    headers.insert("RECORD-COUNT", number_header(summary.num_records));
+    headers.insert("ELAPSED-DATABASE-QUERY-MS", time_header(start_query));
+    headers.insert("OVER-THE-LIMIT", number_header(summary.over_the_limit));
+
+    if let Some(next_offset) = summary.next_offset {
+        headers.insert("NEXT-OFFSET", number_header(next_offset));
+    }
+
+    headers.insert("ELAPSED-TOTAL-MS", time_header(start));
+
+    Ok((headers, Bytes::from(parquet)).into_response())
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct CancelResponse {
+    pub(crate) cancelled: bool,
+}
+
+/// Cancel a running query that the user started
+pub(crate) async fn cancel(
+    Path(query_id): Path<Uuid>,
+    state: Extension<State>,
+    claims: Claims,
+) -> Json<CancelResponse> {
+    let cancelled = state.queries.cancel(&query_id, &claims.sub);
+
+    Json(CancelResponse { cancelled })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_query_schemas() {
+        let query = SchemaQuery {
+            schemas: Some("public, sales,,".into()),
+        };
+        assert_eq!(query.schemas(), vec!["public", "sales"]);
+        assert!(SchemaQuery::default().schemas().is_empty());
+    }
 }
@@ -1,4 +1,8 @@
-use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    response::IntoResponse,
+};
 use http::HeaderMap;
 use quadratic_rust_shared::{
     quadratic_api::Connection as ApiConnection,
@@ -15,7 +19,7 @@ use crate::{
     state::State,
 };
 
-use super::{Schema, query_generic};
+use super::{Schema, SchemaQuery, query_generic};
 
 /// Test the connection to the database.
 pub(crate) async fn test(Json(connection): Json<MsSqlConnection>) -> Json<TestResponse> {
@@ -44,18 +48,27 @@ async fn get_connection(
                 username: Some("sa".into()),
                 password: Some("yourStrong(!)Password".into()),
                 database: "AllTypes".into(),
+                tls: None,
+                ssh: None,
+                cache_ttl_s: None,
+                read_only: false,
             },
         }
     };
 
-    let mssql_connection = MsSqlConnection::new(
+    let mut mssql_connection = MsSqlConnection::new(
         connection.type_details.username.to_owned(),
         connection.type_details.password.to_owned(),
         connection.type_details.host.to_owned(),
         connection.type_details.port.to_owned(),
         connection.type_details.database.to_owned(),
+        connection.type_details.tls.to_owned(),
+        connection.type_details.ssh.to_owned(),
     );
 
+    mssql_connection.cache_ttl_s = connection.type_details.cache_ttl_s;
+    mssql_connection.read_only = connection.type_details.read_only;
+
     Ok((mssql_connection, connection))
 }
 
@@ -70,12 +83,13 @@ pub(crate) async fn query(
     let connection = get_connection(&state, &claims, &sql_query.connection_id, &team_id)
         .await?
         .0;
-    query_generic::<MsSqlConnection>(connection, state, sql_query).await
+    query_generic::<MsSqlConnection>(connection, state, sql_query, Some(&claims)).await
 }
 
 /// Get the schema of the database
 pub(crate) async fn schema(
     Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
     headers: HeaderMap,
     state: Extension<State>,
     claims: Claims,
@@ -83,7 +97,9 @@ pub(crate) async fn schema(
     let team_id = get_team_id_header(&headers)?;
     let (connection, api_connection) = get_connection(&state, &claims, &id, &team_id).await?;
     let mut pool = connection.connect().await?;
-    let database_schema = connection.schema(&mut pool).await?;
+    let database_schema = connection
+        .schema(&mut pool, &schema_query.schemas())
+        .await?;
     let schema = Schema {
         id: api_connection.uuid,
         name: api_connection.name,
@@ -113,7 +129,10 @@ mod tests {
     use bytes::Bytes;
     use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
     use http::StatusCode;
-    use quadratic_rust_shared::sql::schema::{SchemaColumn, SchemaTable};
+    use quadratic_rust_shared::sql::{
+        schema::{SchemaColumn, SchemaIndex, SchemaTable},
+        stream::Page,
+    };
     use tracing_test::traced_test;
     use uuid::Uuid;
 
@@ -129,9 +148,15 @@ mod tests {
         let connection_id = Uuid::new_v4();
         let (_, headers) = new_team_id_with_header().await;
         let state = Extension(new_state().await);
-        let response = schema(Path(connection_id), headers, state, get_claims())
-            .await
-            .unwrap();
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            headers,
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
 
         let expected = Schema {
             id: response.0.id,
@@ -142,172 +167,49 @@ mod tests {
                 name: "all_native_data_types".into(),
                 schema: "dbo".into(),
                 columns: vec![
-                    SchemaColumn {
-                        name: "id".into(),
-                        r#type: "int".into(),
-                        is_nullable: false,
-                    },
-                    SchemaColumn {
-                        name: "tinyint_col".into(),
-                        r#type: "tinyint".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "smallint_col".into(),
-                        r#type: "smallint".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "int_col".into(),
-                        r#type: "int".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "bigint_col".into(),
-                        r#type: "bigint".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "bit_col".into(),
-                        r#type: "bit".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "decimal_col".into(),
-                        r#type: "decimal".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "numeric_col".into(),
-                        r#type: "numeric".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "money_col".into(),
-                        r#type: "money".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "smallmoney_col".into(),
-                        r#type: "smallmoney".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "float_col".into(),
-                        r#type: "float".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "real_col".into(),
-                        r#type: "real".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "date_col".into(),
-                        r#type: "date".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "time_col".into(),
-                        r#type: "time".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "datetime2_col".into(),
-                        r#type: "datetime2".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "datetimeoffset_col".into(),
-                        r#type: "datetimeoffset".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "datetime_col".into(),
-                        r#type: "datetime".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "smalldatetime_col".into(),
-                        r#type: "smalldatetime".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "char_col".into(),
-                        r#type: "char".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "varchar_col".into(),
-                        r#type: "varchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "text_col".into(),
-                        r#type: "text".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "nchar_col".into(),
-                        r#type: "nchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "nvarchar_col".into(),
-                        r#type: "nvarchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "ntext_col".into(),
-                        r#type: "ntext".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "binary_col".into(),
-                        r#type: "binary".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "varbinary_col".into(),
-                        r#type: "varbinary".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "image_col".into(),
-                        r#type: "image".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "json_col".into(),
-                        r#type: "nvarchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "uniqueidentifier_col".into(),
-                        r#type: "uniqueidentifier".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "xml_col".into(),
-                        r#type: "xml".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "varchar_max_col".into(),
-                        r#type: "varchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "nvarchar_max_col".into(),
-                        r#type: "nvarchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "varbinary_max_col".into(),
-                        r#type: "varbinary".into(),
-                        is_nullable: true,
-                    },
+                    SchemaColumn::new("id", "int", false),
+                    SchemaColumn::new("tinyint_col", "tinyint", true),
+                    SchemaColumn::new("smallint_col", "smallint", true),
+                    SchemaColumn::new("int_col", "int", true),
+                    SchemaColumn::new("bigint_col", "bigint", true),
+                    SchemaColumn::new("bit_col", "bit", true),
+                    SchemaColumn::new("decimal_col", "decimal", true),
+                    SchemaColumn::new("numeric_col", "numeric", true),
+                    SchemaColumn::new("money_col", "money", true),
+                    SchemaColumn::new("smallmoney_col", "smallmoney", true),
+                    SchemaColumn::new("float_col", "float", true),
+                    SchemaColumn::new("real_col", "real", true),
+                    SchemaColumn::new("date_col", "date", true),
+                    SchemaColumn::new("time_col", "time", true),
+                    SchemaColumn::new("datetime2_col", "datetime2", true),
+                    SchemaColumn::new("datetimeoffset_col", "datetimeoffset", true),
+                    SchemaColumn::new("datetime_col", "datetime", true),
+                    SchemaColumn::new("smalldatetime_col", "smalldatetime", true),
+                    SchemaColumn::new("char_col", "char", true),
+                    SchemaColumn::new("varchar_col", "varchar", true),
+                    SchemaColumn::new("text_col", "text", true),
+                    SchemaColumn::new("nchar_col", "nchar", true),
+                    SchemaColumn::new("nvarchar_col", "nvarchar", true),
+                    SchemaColumn::new("ntext_col", "ntext", true),
+                    SchemaColumn::new("binary_col", "binary", true),
+                    SchemaColumn::new("varbinary_col", "varbinary", true),
+                    SchemaColumn::new("image_col", "image", true),
+                    SchemaColumn::new("json_col", "nvarchar", true),
+                    SchemaColumn::new("uniqueidentifier_col", "uniqueidentifier", true),
+                    SchemaColumn::new("xml_col", "xml", true),
+                    SchemaColumn::new("varchar_max_col", "varchar", true),
+                    SchemaColumn::new("nvarchar_max_col", "nvarchar", true),
+                    SchemaColumn::new("varbinary_max_col", "varbinary", true),
                 ],
+                primary_key: vec!["id".into()],
+                indexes: vec![SchemaIndex {
+                    name: response.0.tables[0].indexes[0].name.to_owned(),
+                    columns: vec!["id".into()],
+                    is_unique: true,
+                    is_primary: true,
+                }],
+                row_count: response.0.tables[0].row_count,
+                ..Default::default()
             }],
         };
 
@@ -321,6 +223,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "SELECT TOP 1 * FROM [dbo].[all_native_data_types] ORDER BY id".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let state = Extension(new_state().await);
         let (_, headers) = new_team_id_with_header().await;
@@ -428,6 +335,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "SELECT TOP 1 * FROM [dbo].[all_native_data_types] ORDER BY id".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let mut state = Extension(new_state().await);
         state.settings.max_response_bytes = 0;
@@ -1,4 +1,8 @@
-use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    response::IntoResponse,
+};
 use http::HeaderMap;
 use quadratic_rust_shared::{
     quadratic_api::Connection as ApiConnection,
@@ -15,7 +19,7 @@ use crate::{
     state::State,
 };
 
-use super::{Schema, query_generic};
+use super::{Schema, SchemaQuery, query_generic};
 
 /// Test the connection to the database.
 pub(crate) async fn test(Json(connection): Json<MySqlConnection>) -> Json<TestResponse> {
@@ -44,18 +48,27 @@ async fn get_connection(
                 username: Some("user".into()),
                 password: Some("password".into()),
                 database: "mysql-connection".into(),
+                tls: None,
+                ssh: None,
+                cache_ttl_s: None,
+                read_only: false,
             },
         }
     };
 
-    let mysql_connection = MySqlConnection::new(
+    let mut mysql_connection = MySqlConnection::new(
         connection.type_details.username.to_owned(),
         connection.type_details.password.to_owned(),
         connection.type_details.host.to_owned(),
         connection.type_details.port.to_owned(),
         connection.type_details.database.to_owned(),
+        connection.type_details.tls.to_owned(),
+        connection.type_details.ssh.to_owned(),
     );
 
+    mysql_connection.cache_ttl_s = connection.type_details.cache_ttl_s;
+    mysql_connection.read_only = connection.type_details.read_only;
+
     Ok((mysql_connection, connection))
 }
 
@@ -70,12 +83,13 @@ pub(crate) async fn query(
     let connection = get_connection(&state, &claims, &sql_query.connection_id, &team_id)
         .await?
         .0;
-    query_generic::<MySqlConnection>(connection, state, sql_query).await
+    query_generic::<MySqlConnection>(connection, state, sql_query, Some(&claims)).await
 }
 
 /// Get the schema of the database
 pub(crate) async fn schema(
     Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
     headers: HeaderMap,
     state: Extension<State>,
     claims: Claims,
@@ -83,7 +97,9 @@ pub(crate) async fn schema(
     let team_id = get_team_id_header(&headers)?;
     let (connection, api_connection) = get_connection(&state, &claims, &id, &team_id).await?;
     let mut pool = connection.connect().await?;
-    let database_schema = connection.schema(&mut pool).await?;
+    let database_schema = connection
+        .schema(&mut pool, &schema_query.schemas())
+        .await?;
     let schema = Schema {
         id: api_connection.uuid,
         name: api_connection.name,
@@ -111,7 +127,10 @@ mod tests {
     use bytes::Bytes;
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
     use http::StatusCode;
-    use quadratic_rust_shared::sql::schema::{SchemaColumn, SchemaTable};
+    use quadratic_rust_shared::sql::{
+        schema::{SchemaColumn, SchemaIndex, SchemaTable},
+        stream::Page,
+    };
     use tracing_test::traced_test;
     use uuid::Uuid;
 
@@ -127,9 +146,15 @@ mod tests {
         let connection_id = Uuid::new_v4();
         let (_, headers) = new_team_id_with_header().await;
         let state = Extension(new_state().await);
-        let response = schema(Path(connection_id), headers, state, get_claims())
-            .await
-            .unwrap();
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            headers,
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
 
         let expected = Schema {
             id: response.0.id,
@@ -140,157 +165,46 @@ mod tests {
                 name: "all_native_data_types".into(),
                 schema: "mysql-connection".into(),
                 columns: vec![
-                    SchemaColumn {
-                        name: "id".into(),
-                        r#type: "int".into(),
-                        is_nullable: false,
-                    },
-                    SchemaColumn {
-                        name: "tinyint_col".into(),
-                        r#type: "tinyint".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "smallint_col".into(),
-                        r#type: "smallint".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "mediumint_col".into(),
-                        r#type: "mediumint".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "int_col".into(),
-                        r#type: "int".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "bigint_col".into(),
-                        r#type: "bigint".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "decimal_col".into(),
-                        r#type: "decimal".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "float_col".into(),
-                        r#type: "float".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "double_col".into(),
-                        r#type: "double".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "bit_col".into(),
-                        r#type: "bit".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "char_col".into(),
-                        r#type: "char".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "varchar_col".into(),
-                        r#type: "varchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "binary_col".into(),
-                        r#type: "binary".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "varbinary_col".into(),
-                        r#type: "varbinary".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "tinyblob_col".into(),
-                        r#type: "tinyblob".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "blob_col".into(),
-                        r#type: "blob".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "mediumblob_col".into(),
-                        r#type: "mediumblob".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "longblob_col".into(),
-                        r#type: "longblob".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "tinytext_col".into(),
-                        r#type: "tinytext".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "text_col".into(),
-                        r#type: "text".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "mediumtext_col".into(),
-                        r#type: "mediumtext".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "longtext_col".into(),
-                        r#type: "longtext".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "enum_col".into(),
-                        r#type: "enum".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "set_col".into(),
-                        r#type: "set".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "date_col".into(),
-                        r#type: "date".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "datetime_col".into(),
-                        r#type: "datetime".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "timestamp_col".into(),
-                        r#type: "timestamp".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "time_col".into(),
-                        r#type: "time".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "year_col".into(),
-                        r#type: "year".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "json_col".into(),
-                        r#type: "json".into(),
-                        is_nullable: true,
-                    },
+                    SchemaColumn::new("id", "int", false),
+                    SchemaColumn::new("tinyint_col", "tinyint", true),
+                    SchemaColumn::new("smallint_col", "smallint", true),
+                    SchemaColumn::new("mediumint_col", "mediumint", true),
+                    SchemaColumn::new("int_col", "int", true),
+                    SchemaColumn::new("bigint_col", "bigint", true),
+                    SchemaColumn::new("decimal_col", "decimal", true),
+                    SchemaColumn::new("float_col", "float", true),
+                    SchemaColumn::new("double_col", "double", true),
+                    SchemaColumn::new("bit_col", "bit", true),
+                    SchemaColumn::new("char_col", "char", true),
+                    SchemaColumn::new("varchar_col", "varchar", true),
+                    SchemaColumn::new("binary_col", "binary", true),
+                    SchemaColumn::new("varbinary_col", "varbinary", true),
+                    SchemaColumn::new("tinyblob_col", "tinyblob", true),
+                    SchemaColumn::new("blob_col", "blob", true),
+                    SchemaColumn::new("mediumblob_col", "mediumblob", true),
+                    SchemaColumn::new("longblob_col", "longblob", true),
+                    SchemaColumn::new("tinytext_col", "tinytext", true),
+                    SchemaColumn::new("text_col", "text", true),
+                    SchemaColumn::new("mediumtext_col", "mediumtext", true),
+                    SchemaColumn::new("longtext_col", "longtext", true),
+                    SchemaColumn::new("enum_col", "enum", true),
+                    SchemaColumn::new("set_col", "set", true),
+                    SchemaColumn::new("date_col", "date", true),
+                    SchemaColumn::new("datetime_col", "datetime", true),
+                    SchemaColumn::new("timestamp_col", "timestamp", true),
+                    SchemaColumn::new("time_col", "time", true),
+                    SchemaColumn::new("year_col", "year", true),
+                    SchemaColumn::new("json_col", "json", true),
                 ],
+                primary_key: vec!["id".into()],
+                indexes: vec![SchemaIndex {
+                    name: "PRIMARY".into(),
+                    columns: vec!["id".into()],
+                    is_unique: true,
+                    is_primary: true,
+                }],
+                row_count: response.0.tables[0].row_count,
+                ..Default::default()
             }],
         };
 
@@ -305,6 +219,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let state = Extension(new_state().await);
         let data = query(headers, state, get_claims(), Json(sql_query))
@@ -384,6 +303,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let mut state = Extension(new_state().await);
         state.settings.max_response_bytes = 0;
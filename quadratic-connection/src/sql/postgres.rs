@@ -1,4 +1,8 @@
-use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    response::IntoResponse,
+};
 use http::HeaderMap;
 use quadratic_rust_shared::{
     quadratic_api::Connection as ApiConnection,
@@ -15,7 +19,7 @@ use crate::{
     state::State,
 };
 
-use super::{Schema, query_generic};
+use super::{Schema, SchemaQuery, query_generic};
 
 /// Test the connection to the database.
 #[axum::debug_handler]
@@ -45,18 +49,27 @@ async fn get_connection(
                 username: Some("user".into()),
                 password: Some("password".into()),
                 database: "postgres-connection".into(),
+                tls: None,
+                ssh: None,
+                cache_ttl_s: None,
+                read_only: false,
             },
         }
     };
 
-    let pg_connection = PostgresConnection::new(
+    let mut pg_connection = PostgresConnection::new(
         connection.type_details.username.to_owned(),
         connection.type_details.password.to_owned(),
         connection.type_details.host.to_owned(),
         connection.type_details.port.to_owned(),
         connection.type_details.database.to_owned(),
+        connection.type_details.tls.to_owned(),
+        connection.type_details.ssh.to_owned(),
     );
 
+    pg_connection.cache_ttl_s = connection.type_details.cache_ttl_s;
+    pg_connection.read_only = connection.type_details.read_only;
+
     Ok((pg_connection, connection))
 }
 
@@ -71,12 +84,13 @@ pub(crate) async fn query(
     let connection = get_connection(&state, &claims, &sql_query.connection_id, &team_id)
         .await?
         .0;
-    query_generic::<PostgresConnection>(connection, state, sql_query).await
+    query_generic::<PostgresConnection>(connection, state, sql_query, Some(&claims)).await
 }
 
 /// Get the schema of the database
 pub(crate) async fn schema(
     Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
     headers: HeaderMap,
     state: Extension<State>,
     claims: Claims,
@@ -84,7 +98,9 @@ pub(crate) async fn schema(
     let team_id = get_team_id_header(&headers)?;
     let (connection, api_connection) = get_connection(&state, &claims, &id, &team_id).await?;
     let mut pool = connection.connect().await?;
-    let database_schema = connection.schema(&mut pool).await?;
+    let database_schema = connection
+        .schema(&mut pool, &schema_query.schemas())
+        .await?;
     let schema = Schema {
         id: api_connection.uuid,
         name: api_connection.name,
@@ -100,7 +116,10 @@ pub(crate) async fn schema(
 mod tests {
     use super::*;
     use crate::{
-        num_vec, test_connection,
+        config::{StorageType, config},
+        num_vec,
+        sql::cancel,
+        test_connection,
         test_util::{
             get_claims, new_state, new_team_id_with_header, response_bytes, str_vec,
             validate_parquet,
@@ -111,7 +130,13 @@ mod tests {
     use bytes::Bytes;
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
     use http::StatusCode;
-    use quadratic_rust_shared::sql::schema::{SchemaColumn, SchemaTable};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use quadratic_rust_shared::sql::{
+        parameter::SqlParameter,
+        schema::{SchemaColumn, SchemaIndex, SchemaTable},
+        stream::{NEXT_OFFSET_KEY, Page},
+    };
+    use std::time::Duration;
     use tracing_test::traced_test;
     use uuid::Uuid;
 
@@ -127,9 +152,15 @@ mod tests {
         let connection_id = Uuid::new_v4();
         let (_, headers) = new_team_id_with_header().await;
         let state = Extension(new_state().await);
-        let response = schema(Path(connection_id), headers, state, get_claims())
-            .await
-            .unwrap();
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            headers,
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
 
         let expected = Schema {
             id: response.0.id,
@@ -141,196 +172,66 @@ mod tests {
                 schema: "public".into(),
                 columns: vec![
                     SchemaColumn {
-                        name: "id".into(),
-                        r#type: "int4".into(),
-                        is_nullable: false,
-                    },
-                    SchemaColumn {
-                        name: "smallint_col".into(),
-                        r#type: "int2".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "integer_col".into(),
-                        r#type: "int4".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "bigint_col".into(),
-                        r#type: "int8".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "decimal_col".into(),
-                        r#type: "numeric".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "numeric_col".into(),
-                        r#type: "numeric".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "real_col".into(),
-                        r#type: "float4".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "double_col".into(),
-                        r#type: "float8".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "serial_col".into(),
-                        r#type: "int4".into(),
-                        is_nullable: false,
-                    },
-                    SchemaColumn {
-                        name: "bigserial_col".into(),
-                        r#type: "int8".into(),
-                        is_nullable: false,
-                    },
-                    SchemaColumn {
-                        name: "money_col".into(),
-                        r#type: "money".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "char_col".into(),
-                        r#type: "bpchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "varchar_col".into(),
-                        r#type: "varchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "text_col".into(),
-                        r#type: "text".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "bytea_col".into(),
-                        r#type: "bytea".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "timestamp_col".into(),
-                        r#type: "timestamp".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "timestamptz_col".into(),
-                        r#type: "timestamptz".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "date_col".into(),
-                        r#type: "date".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "time_col".into(),
-                        r#type: "time".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "timetz_col".into(),
-                        r#type: "timetz".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "interval_col".into(),
-                        r#type: "interval".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "boolean_col".into(),
-                        r#type: "bool".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "enum_col".into(),
-                        r#type: "varchar".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "point_col".into(),
-                        r#type: "point".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "line_col".into(),
-                        r#type: "line".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "lseg_col".into(),
-                        r#type: "lseg".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "box_col".into(),
-                        r#type: "box".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "path_col".into(),
-                        r#type: "path".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "polygon_col".into(),
-                        r#type: "polygon".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "circle_col".into(),
-                        r#type: "circle".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "cidr_col".into(),
-                        r#type: "cidr".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "inet_col".into(),
-                        r#type: "inet".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "macaddr_col".into(),
-                        r#type: "macaddr".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "json_col".into(),
-                        r#type: "json".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "jsonb_col".into(),
-                        r#type: "jsonb".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "uuid_col".into(),
-                        r#type: "uuid".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "xml_col".into(),
-                        r#type: "xml".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "array_col".into(),
-                        r#type: "_int4".into(),
-                        is_nullable: true,
-                    },
+                        default: Some("nextval('all_native_data_types_id_seq'::regclass)".into()),
+                        ..SchemaColumn::new("id", "int4", false)
+                    },
+                    SchemaColumn::new("smallint_col", "int2", true),
+                    SchemaColumn::new("integer_col", "int4", true),
+                    SchemaColumn::new("bigint_col", "int8", true),
+                    SchemaColumn::new("decimal_col", "numeric", true),
+                    SchemaColumn::new("numeric_col", "numeric", true),
+                    SchemaColumn::new("real_col", "float4", true),
+                    SchemaColumn::new("double_col", "float8", true),
+                    SchemaColumn {
+                        default: Some(
+                            "nextval('all_native_data_types_serial_col_seq'::regclass)".into(),
+                        ),
+                        ..SchemaColumn::new("serial_col", "int4", false)
+                    },
+                    SchemaColumn {
+                        default: Some(
+                            "nextval('all_native_data_types_bigserial_col_seq'::regclass)".into(),
+                        ),
+                        ..SchemaColumn::new("bigserial_col", "int8", false)
+                    },
+                    SchemaColumn::new("money_col", "money", true),
+                    SchemaColumn::new("char_col", "bpchar", true),
+                    SchemaColumn::new("varchar_col", "varchar", true),
+                    SchemaColumn::new("text_col", "text", true),
+                    SchemaColumn::new("bytea_col", "bytea", true),
+                    SchemaColumn::new("timestamp_col", "timestamp", true),
+                    SchemaColumn::new("timestamptz_col", "timestamptz", true),
+                    SchemaColumn::new("date_col", "date", true),
+                    SchemaColumn::new("time_col", "time", true),
+                    SchemaColumn::new("timetz_col", "timetz", true),
+                    SchemaColumn::new("interval_col", "interval", true),
+                    SchemaColumn::new("boolean_col", "bool", true),
+                    SchemaColumn::new("enum_col", "varchar", true),
+                    SchemaColumn::new("point_col", "point", true),
+                    SchemaColumn::new("line_col", "line", true),
+                    SchemaColumn::new("lseg_col", "lseg", true),
+                    SchemaColumn::new("box_col", "box", true),
+                    SchemaColumn::new("path_col", "path", true),
+                    SchemaColumn::new("polygon_col", "polygon", true),
+                    SchemaColumn::new("circle_col", "circle", true),
+                    SchemaColumn::new("cidr_col", "cidr", true),
+                    SchemaColumn::new("inet_col", "inet", true),
+                    SchemaColumn::new("macaddr_col", "macaddr", true),
+                    SchemaColumn::new("json_col", "json", true),
+                    SchemaColumn::new("jsonb_col", "jsonb", true),
+                    SchemaColumn::new("uuid_col", "uuid", true),
+                    SchemaColumn::new("xml_col", "xml", true),
+                    SchemaColumn::new("array_col", "_int4", true),
                 ],
+                primary_key: vec!["id".into()],
+                indexes: vec![SchemaIndex {
+                    name: "all_native_data_types_pkey".into(),
+                    columns: vec!["id".into()],
+                    is_unique: true,
+                    is_primary: true,
+                }],
+                row_count: response.0.tables[0].row_count,
+                ..Default::default()
             }],
         };
         assert_eq!(response.0, expected)
@@ -344,6 +245,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let state = Extension(new_state().await);
         let data = query(headers, state, get_claims(), Json(sql_query))
@@ -434,6 +340,133 @@ mod tests {
         // assert_eq!(response.status(), 200);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_with_parameters() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let sql_query = SqlQuery {
+            query: "select $1::text as name, $2::int8 as age".into(),
+            connection_id,
+            params: vec![
+                SqlParameter::Text("O'Brien".into()),
+                SqlParameter::Integer(42),
+            ],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
+        };
+        let state = Extension(new_state().await);
+        let data = query(headers, state, get_claims(), Json(sql_query))
+            .await
+            .unwrap();
+        let response = data.into_response();
+
+        let expected = vec![
+            (DataType::Utf8, str_vec("O'Brien")),
+            (DataType::Int64, num_vec!(42_i64)),
+        ];
+
+        validate_parquet(response, expected).await;
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_page() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let sql_query = SqlQuery {
+            query: "select generate_series(1, 5) as n".into(),
+            connection_id,
+            params: vec![],
+            stream: false,
+            page: Page {
+                offset: 1,
+                limit: Some(2),
+            },
+            query_id: None,
+            refresh_cache: false,
+        };
+        let state = Extension(new_state().await);
+        let data = query(headers, state, get_claims(), Json(sql_query))
+            .await
+            .unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.headers()["record-count"], "2");
+        assert_eq!(response.headers()["next-offset"], "3");
+
+        let expected = vec![(DataType::Int32, [num_vec!(2_i32), num_vec!(3_i32)].concat())];
+        validate_parquet(response, expected).await;
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_stream() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let sql_query = SqlQuery {
+            query: "select generate_series(1, 5) as n".into(),
+            connection_id,
+            params: vec![],
+            stream: true,
+            page: Page {
+                offset: 0,
+                limit: Some(3),
+            },
+            query_id: None,
+            refresh_cache: false,
+        };
+        let state = Extension(new_state().await);
+        let data = query(headers, state, get_claims(), Json(sql_query))
+            .await
+            .unwrap();
+        let response = data.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // the next offset is in the footer, since headers are sent first
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(body).unwrap();
+        let metadata = builder.metadata().file_metadata().key_value_metadata();
+        let next_offset = metadata
+            .unwrap()
+            .iter()
+            .find(|kv| kv.key == NEXT_OFFSET_KEY)
+            .and_then(|kv| kv.value.to_owned());
+        assert_eq!(next_offset, Some("3".into()));
+
+        let num_rows = builder
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum::<usize>();
+        assert_eq!(num_rows, 3);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_stream_error() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let sql_query = SqlQuery {
+            query: "select * from not_a_table".into(),
+            connection_id,
+            params: vec![],
+            stream: true,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
+        };
+        let state = Extension(new_state().await);
+        let response = query(headers, state, get_claims(), Json(sql_query)).await;
+
+        assert!(response.is_err());
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn postgres_query_max_response_bytes() {
@@ -442,6 +475,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types order by id limit 1".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let mut state = Extension(new_state().await);
         state.settings.max_response_bytes = 0;
@@ -455,4 +493,138 @@ mod tests {
         let body = response_bytes(response).await;
         assert_eq!(body, Bytes::new());
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_cache_skips_writes() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let mut config = config().unwrap();
+        config.query_cache_enabled = true;
+        config.query_cache_storage_type = StorageType::FileSystem;
+        config.query_cache_dir = std::env::temp_dir().to_string_lossy().into_owned();
+        let state = State::new(&config, None).await.unwrap();
+        let table = format!("cache_writes_{}", Uuid::new_v4().simple());
+
+        let run = |sql: String| {
+            let sql_query = SqlQuery {
+                query: sql,
+                connection_id,
+                params: vec![],
+                stream: false,
+                page: Page::default(),
+                query_id: None,
+                refresh_cache: false,
+            };
+            query(
+                headers.clone(),
+                Extension(state.clone()),
+                get_claims(),
+                Json(sql_query),
+            )
+        };
+
+        run(format!("create table {table} (id int)")).await.unwrap();
+
+        // writes run every time, and aren't cached
+        for _ in 0..2 {
+            let response = run(format!("insert into {table} values (1)"))
+                .await
+                .unwrap()
+                .into_response();
+            assert!(!response.headers().contains_key("cache-hit"));
+        }
+
+        let response = run(format!("select count(*) as n from {table}"))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.headers()["cache-hit"], "false");
+
+        run(format!("drop table {table}")).await.unwrap();
+
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(2_i64))]).await;
+    }
+
+    fn sleep_query(connection_id: Uuid, query_id: Option<Uuid>) -> SqlQuery {
+        SqlQuery {
+            query: "select pg_sleep(30)".into(),
+            connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id,
+            refresh_cache: false,
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_pools_connections() {
+        let connection_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let state = new_state().await;
+
+        for pooled in ["0", "1"] {
+            let sql_query = SqlQuery {
+                query: "select 1 as n".into(),
+                connection_id,
+                params: vec![],
+                stream: false,
+                page: Page::default(),
+                query_id: None,
+                refresh_cache: false,
+            };
+            let data = query(
+                headers.clone(),
+                Extension(state.clone()),
+                get_claims(),
+                Json(sql_query),
+            )
+            .await
+            .unwrap();
+            let response = data.into_response();
+
+            assert_eq!(response.headers()["pooled-connection"], pooled);
+        }
+
+        assert_eq!(state.pool.idle_count(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_timeout() {
+        let (_, headers) = new_team_id_with_header().await;
+        let mut state = Extension(new_state().await);
+        state.settings.query_timeout = Duration::from_millis(500);
+        let sql_query = sleep_query(Uuid::new_v4(), None);
+        let response = query(headers, state.clone(), get_claims(), Json(sql_query)).await;
+
+        assert!(response.err().unwrap().to_string().contains("timed out"));
+
+        // connections left mid-query aren't pooled
+        assert_eq!(state.pool.idle_count(), 0);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn postgres_query_cancel() {
+        let query_id = Uuid::new_v4();
+        let (_, headers) = new_team_id_with_header().await;
+        let state = Extension(new_state().await);
+        let sql_query = sleep_query(Uuid::new_v4(), Some(query_id));
+        let running = tokio::spawn(query(headers, state.clone(), get_claims(), Json(sql_query)));
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let cancelled = cancel(Path(query_id), state.clone(), get_claims()).await;
+        assert!(cancelled.cancelled);
+
+        let response = running.await.unwrap();
+        assert!(response.err().unwrap().to_string().contains("cancelled"));
+
+        // the query has finished, so there's nothing to cancel
+        let cancelled = cancel(Path(query_id), state, get_claims()).await;
+        assert!(!cancelled.cancelled);
+    }
 }
@@ -1,8 +1,12 @@
-use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    response::IntoResponse,
+};
 use http::HeaderMap;
 use quadratic_rust_shared::{
     quadratic_api::Connection as ApiConnection,
-    sql::{Connection, snowflake_connection::SnowflakeConnection},
+    sql::{Connection, snowflake_connection::SnowflakeConnection, stream::Page},
 };
 use uuid::Uuid;
 
@@ -15,7 +19,7 @@ use crate::{
     state::State,
 };
 
-use super::{Schema, query_generic};
+use super::{Schema, SchemaQuery, query_generic};
 
 /// Test the connection to the database.
 pub(crate) async fn test(
@@ -25,8 +29,14 @@ pub(crate) async fn test(
     let sql_query = SqlQuery {
         query: "SELECT 1".into(),
         connection_id: Uuid::new_v4(), // This is not used
+        params: vec![],
+        stream: false,
+        page: Page::default(),
+        query_id: None,
+        refresh_cache: false,
     };
-    let response = query_generic::<SnowflakeConnection>(connection, state, sql_query.into()).await;
+    let response =
+        query_generic::<SnowflakeConnection>(connection, state, sql_query.into(), None).await;
     let message = match response {
         Ok(_) => None,
         Err(e) => Some(e.to_string()),
@@ -59,11 +69,13 @@ async fn get_connection(
                 warehouse: None,
                 schema: None,
                 role: None,
+                cache_ttl_s: None,
+                read_only: false,
             },
         }
     };
 
-    let snowflake_connection = SnowflakeConnection::new(
+    let mut snowflake_connection = SnowflakeConnection::new(
         connection.type_details.account_identifier.to_owned(),
         connection.type_details.username.to_owned(),
         connection.type_details.password.to_owned(),
@@ -73,6 +85,9 @@ async fn get_connection(
         None,
     );
 
+    snowflake_connection.cache_ttl_s = connection.type_details.cache_ttl_s;
+    snowflake_connection.read_only = connection.type_details.read_only;
+
     Ok((snowflake_connection, connection))
 }
 
@@ -87,12 +102,13 @@ pub(crate) async fn query(
     let connection = get_connection(&state, &claims, &sql_query.connection_id, &team_id)
         .await?
         .0;
-    query_generic::<SnowflakeConnection>(connection, state, sql_query).await
+    query_generic::<SnowflakeConnection>(connection, state, sql_query, Some(&claims)).await
 }
 
 /// Get the schema of the database
 pub(crate) async fn schema(
     Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
     headers: HeaderMap,
     state: Extension<State>,
     claims: Claims,
@@ -100,7 +116,9 @@ pub(crate) async fn schema(
     let team_id = get_team_id_header(&headers)?;
     let (connection, api_connection) = get_connection(&state, &claims, &id, &team_id).await?;
     let mut pool = connection.connect().await?;
-    let database_schema = connection.schema(&mut pool).await?;
+    let database_schema = connection
+        .schema(&mut pool, &schema_query.schemas())
+        .await?;
     let schema = Schema {
         id: api_connection.uuid,
         name: api_connection.name,
@@ -146,9 +164,15 @@ mod tests {
         let connection_id = Uuid::new_v4();
         let (_, headers) = new_team_id_with_header().await;
         let state = Extension(new_state().await);
-        let response = schema(Path(connection_id), headers, state, get_claims())
-            .await
-            .unwrap();
+        let response = schema(
+            Path(connection_id),
+            Query(SchemaQuery::default()),
+            headers,
+            state,
+            get_claims(),
+        )
+        .await
+        .unwrap();
 
         let expected = Schema {
             id: response.0.id,
@@ -159,97 +183,26 @@ mod tests {
                 name: "ALL_NATIVE_DATA_TYPES".into(),
                 schema: "PUBLIC".into(),
                 columns: vec![
-                    SchemaColumn {
-                        name: "INTEGER_COL".into(),
-                        r#type: "NUMBER".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "FLOAT_COL".into(),
-                        r#type: "FLOAT".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "NUMBER_COL".into(),
-                        r#type: "NUMBER".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "DECIMAL_COL".into(),
-                        r#type: "NUMBER".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "BOOLEAN_COL".into(),
-                        r#type: "BOOLEAN".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "VARCHAR_COL".into(),
-                        r#type: "TEXT".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "CHAR_COL".into(),
-                        r#type: "TEXT".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "STRING_COL".into(),
-                        r#type: "TEXT".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "BINARY_COL".into(),
-                        r#type: "BINARY".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "DATE_COL".into(),
-                        r#type: "DATE".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "TIME_COL".into(),
-                        r#type: "TIME".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "TIMESTAMP_NTZ_COL".into(),
-                        r#type: "TIMESTAMP_NTZ".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "TIMESTAMP_LTZ_COL".into(),
-                        r#type: "TIMESTAMP_LTZ".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "TIMESTAMP_TZ_COL".into(),
-                        r#type: "TIMESTAMP_TZ".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "VARIANT_COL".into(),
-                        r#type: "VARIANT".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "OBJECT_COL".into(),
-                        r#type: "OBJECT".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "ARRAY_COL".into(),
-                        r#type: "ARRAY".into(),
-                        is_nullable: true,
-                    },
-                    SchemaColumn {
-                        name: "GEOGRAPHY_COL".into(),
-                        r#type: "GEOGRAPHY".into(),
-                        is_nullable: true,
-                    },
+                    SchemaColumn::new("INTEGER_COL", "NUMBER", true),
+                    SchemaColumn::new("FLOAT_COL", "FLOAT", true),
+                    SchemaColumn::new("NUMBER_COL", "NUMBER", true),
+                    SchemaColumn::new("DECIMAL_COL", "NUMBER", true),
+                    SchemaColumn::new("BOOLEAN_COL", "BOOLEAN", true),
+                    SchemaColumn::new("VARCHAR_COL", "TEXT", true),
+                    SchemaColumn::new("CHAR_COL", "TEXT", true),
+                    SchemaColumn::new("STRING_COL", "TEXT", true),
+                    SchemaColumn::new("BINARY_COL", "BINARY", true),
+                    SchemaColumn::new("DATE_COL", "DATE", true),
+                    SchemaColumn::new("TIME_COL", "TIME", true),
+                    SchemaColumn::new("TIMESTAMP_NTZ_COL", "TIMESTAMP_NTZ", true),
+                    SchemaColumn::new("TIMESTAMP_LTZ_COL", "TIMESTAMP_LTZ", true),
+                    SchemaColumn::new("TIMESTAMP_TZ_COL", "TIMESTAMP_TZ", true),
+                    SchemaColumn::new("VARIANT_COL", "VARIANT", true),
+                    SchemaColumn::new("OBJECT_COL", "OBJECT", true),
+                    SchemaColumn::new("ARRAY_COL", "ARRAY", true),
+                    SchemaColumn::new("GEOGRAPHY_COL", "GEOGRAPHY", true),
                 ],
+                ..Default::default()
             }],
         };
 
@@ -263,6 +216,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "select * from all_native_data_types;".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let state = Extension(new_state().await);
         let (_, headers) = new_team_id_with_header().await;
@@ -285,6 +243,11 @@ mod tests {
         let sql_query = SqlQuery {
             query: "SELECT TOP 1 * FROM [dbo].[all_native_data_types] ORDER BY id".into(),
             connection_id,
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
         };
         let mut state = Extension(new_state().await);
         state.settings.max_response_bytes = 0;
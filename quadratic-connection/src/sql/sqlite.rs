@@ -0,0 +1,200 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    response::IntoResponse,
+};
+use http::HeaderMap;
+use quadratic_rust_shared::{
+    quadratic_api::Connection as ApiConnection,
+    sql::{Connection, sqlite_connection::SqliteConnection},
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    connection::get_api_connection,
+    error::Result,
+    header::get_team_id_header,
+    server::{SqlQuery, TestResponse, test_connection},
+    state::State,
+};
+
+use super::{Schema, SchemaQuery, query_generic, team_data_dir};
+
+/// Test the connection to the database.  Database files are opened from the
+/// team's data directory, which users can't set.
+pub(crate) async fn test(
+    headers: HeaderMap,
+    state: Extension<State>,
+    Json(connection): Json<SqliteConnection>,
+) -> Json<TestResponse> {
+    let team_id = match get_team_id_header(&headers) {
+        Ok(team_id) => team_id,
+        Err(e) => return Json(TestResponse::new(false, Some(e.to_string()))),
+    };
+    let connection = new_sqlite_connection(&state, &team_id, connection.path);
+    test_connection(connection).await
+}
+
+/// Create a SqliteConnection in the team's data directory.
+fn new_sqlite_connection(state: &State, team_id: &Uuid, path: String) -> SqliteConnection {
+    SqliteConnection::new(path, Some(team_data_dir(state, team_id)))
+}
+
+/// Get the connection details from the API and create a SqliteConnection.
+async fn get_connection(
+    state: &State,
+    claims: &Claims,
+    connection_id: &Uuid,
+    team_id: &Uuid,
+) -> Result<(SqliteConnection, ApiConnection<SqliteConnection>)> {
+    let connection = if cfg!(not(test)) {
+        get_api_connection(state, "", &claims.sub, connection_id, team_id).await?
+    } else {
+        ApiConnection {
+            uuid: Uuid::new_v4(),
+            name: "".into(),
+            r#type: "".into(),
+            created_date: "".into(),
+            updated_date: "".into(),
+            type_details: SqliteConnection::new("test.sqlite".into(), None),
+        }
+    };
+
+    let sqlite_connection =
+        new_sqlite_connection(state, team_id, connection.type_details.path.to_owned());
+
+    Ok((sqlite_connection, connection))
+}
+
+/// Query the database and return the results as a parquet file.
+pub(crate) async fn query(
+    headers: HeaderMap,
+    state: Extension<State>,
+    claims: Claims,
+    sql_query: Json<SqlQuery>,
+) -> Result<impl IntoResponse> {
+    let team_id = get_team_id_header(&headers)?;
+    let connection = get_connection(&state, &claims, &sql_query.connection_id, &team_id)
+        .await?
+        .0;
+    query_generic::<SqliteConnection>(connection, state, sql_query, Some(&claims)).await
+}
+
+/// Get the schema of the database
+pub(crate) async fn schema(
+    Path(id): Path<Uuid>,
+    Query(schema_query): Query<SchemaQuery>,
+    headers: HeaderMap,
+    state: Extension<State>,
+    claims: Claims,
+) -> Result<Json<Schema>> {
+    let team_id = get_team_id_header(&headers)?;
+    let (connection, api_connection) = get_connection(&state, &claims, &id, &team_id).await?;
+    let mut pool = connection.connect().await?;
+    let database_schema = connection
+        .schema(&mut pool, &schema_query.schemas())
+        .await?;
+    let schema = Schema {
+        id: api_connection.uuid,
+        name: api_connection.name,
+        r#type: api_connection.r#type,
+        database: api_connection.type_details.path,
+        tables: database_schema.tables.into_values().collect(),
+    };
+
+    Ok(Json(schema))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        num_vec,
+        test_util::{get_claims, new_state, new_team_id_with_header, validate_parquet},
+    };
+    use arrow_schema::DataType;
+    use quadratic_rust_shared::sql::stream::Page;
+    use std::path::PathBuf;
+    use tracing_test::traced_test;
+
+    /// Create a new state whose team data directory holds an empty database,
+    /// which SQLite opens as a database without tables
+    async fn new_state_with_data_dir() -> (State, PathBuf, HeaderMap) {
+        let dir = std::env::temp_dir().join(format!("sqlite-{}", Uuid::new_v4()));
+        let data_dir = dir.join("data");
+        let (team_id, headers) = new_team_id_with_header().await;
+
+        let mut state = new_state().await;
+        state.settings.file_connections_dir = data_dir;
+
+        let team_dir = team_data_dir(&state, &team_id);
+        std::fs::create_dir_all(&team_dir).unwrap();
+        std::fs::write(team_dir.join("test.sqlite"), "").unwrap();
+
+        (state, dir, headers)
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_test_connection() {
+        let (state, _, headers) = new_state_with_data_dir().await;
+        let connection = SqliteConnection::new("test.sqlite".into(), None);
+        let response = test(headers, Extension(state), Json(connection)).await;
+
+        assert_eq!(response.0, TestResponse::new(true, None));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_test_connection_outside_data_dir() {
+        let (state, dir, headers) = new_state_with_data_dir().await;
+        std::fs::write(dir.join("outside.sqlite"), "").unwrap();
+
+        let connection = SqliteConnection::new("../../outside.sqlite".into(), None);
+        let response = test(headers, Extension(state), Json(connection)).await;
+
+        assert!(!response.0.connected);
+        assert!(
+            response
+                .0
+                .message
+                .unwrap()
+                .contains("outside of the data directory")
+        );
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_test_connection_of_another_team() {
+        let (state, _, _) = new_state_with_data_dir().await;
+        let (_, other_team) = new_team_id_with_header().await;
+
+        let connection = SqliteConnection::new("test.sqlite".into(), None);
+        let response = test(other_team, Extension(state), Json(connection)).await;
+
+        assert!(!response.0.connected);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn sqlite_query() {
+        let (state, _, headers) = new_state_with_data_dir().await;
+        let sql_query = SqlQuery {
+            query: "select 1 as one".into(),
+            connection_id: Uuid::new_v4(),
+            params: vec![],
+            stream: false,
+            page: Page::default(),
+            query_id: None,
+            refresh_cache: false,
+        };
+        let data = query(headers, Extension(state), get_claims(), Json(sql_query))
+            .await
+            .unwrap();
+        let response = data.into_response();
+
+        validate_parquet(response, vec![(DataType::Int64, num_vec!(1_i64))]).await;
+    }
+}
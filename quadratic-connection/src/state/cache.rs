@@ -0,0 +1,311 @@
+//! Query Cache
+//!
+//! Keep the Parquet results of queries in storage, so that rerunning a query
+//! (eg, when a dependent cell recalculates or a transaction is replayed)
+//! doesn't send it to the database again while its results are fresh.
+//!
+//! Only queries that read data are cached, so that writes always run.
+//! Results are keyed by connection, normalized SQL and bound parameters.
+//! Stale results are overwritten when their query runs again, so storage
+//! should expire old objects itself (eg, with an S3 lifecycle rule).
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::Utc;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use quadratic_rust_shared::{
+    aws::client,
+    environment::Environment,
+    sql::parameter::SqlParameter,
+    storage::{
+        Storage, StorageContainer,
+        file_system::{FileSystem, FileSystemConfig},
+        s3::{S3, S3Config},
+    },
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::config::{Config, StorageType};
+use crate::error::{ConnectionError, Result};
+
+#[derive(Debug)]
+pub(crate) struct QueryCache {
+    storage: StorageContainer,
+    ttl: Duration,
+}
+
+/// Where a query's results are cached and for how long
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CacheEntry {
+    pub(crate) key: String,
+    pub(crate) ttl: Duration,
+}
+
+/// Results read from the cache
+#[derive(Debug)]
+pub(crate) struct Cached {
+    pub(crate) parquet: Bytes,
+    pub(crate) age: Duration,
+}
+
+impl QueryCache {
+    /// Create the cache from the config, or None when it's disabled
+    pub(crate) async fn new(config: &Config) -> Result<Option<Self>> {
+        if !config.query_cache_enabled {
+            return Ok(None);
+        }
+
+        let expected = |val: &Option<String>, var: &str| {
+            val.to_owned()
+                .ok_or_else(|| ConnectionError::Config(format!("Expected {var} to have a value")))
+        };
+
+        let storage = match config.query_cache_storage_type {
+            StorageType::S3 => {
+                let is_local = config.environment == Environment::Docker
+                    || config.environment == Environment::Local;
+
+                StorageContainer::S3(S3::new(S3Config {
+                    client: client(
+                        &expected(&config.aws_s3_access_key_id, "AWS_S3_ACCESS_KEY_ID")?,
+                        &expected(&config.aws_s3_secret_access_key, "AWS_S3_SECRET_ACCESS_KEY")?,
+                        &expected(&config.aws_s3_region, "AWS_S3_REGION")?,
+                        "Quadratic Connection Service",
+                        is_local,
+                    )
+                    .await,
+                    bucket: expected(&config.aws_s3_bucket_name, "AWS_S3_BUCKET_NAME")?,
+                }))
+            }
+            StorageType::FileSystem => {
+                StorageContainer::FileSystem(FileSystem::new(FileSystemConfig {
+                    path: config.query_cache_dir.to_owned(),
+                    encryption_keys: vec![],
+                }))
+            }
+        };
+
+        Ok(Some(QueryCache {
+            storage,
+            ttl: Duration::from_secs(config.query_cache_ttl_s),
+        }))
+    }
+
+    /// Where to cache a query's results, or None when its connection doesn't
+    /// cache results.  Connections may override the default TTL, and a TTL
+    /// of zero disables caching.
+    ///
+    /// The connection's details are part of the key, so that results aren't
+    /// read from a database that the connection no longer points to.
+    pub(crate) fn entry(
+        &self,
+        connection_id: Uuid,
+        details: &impl Serialize,
+        ttl_s: Option<u64>,
+        sql: &str,
+        params: &[SqlParameter],
+    ) -> Option<CacheEntry> {
+        let ttl = ttl_s.map_or(self.ttl, Duration::from_secs);
+
+        if ttl.is_zero() {
+            return None;
+        }
+
+        // the digest is stable across deploys, which share the cache.  Each
+        // part is length-prefixed, so parts can't run into each other.
+        let mut hasher = Sha256::new();
+        for part in [
+            serde_json::to_string(details).unwrap_or_default(),
+            normalize_sql(sql),
+            serde_json::to_string(params).unwrap_or_default(),
+        ] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+
+        Some(CacheEntry {
+            // stored as `{uuid}/{file_name}` by the file system
+            key: format!("{connection_id}-{:x}.parquet", hasher.finalize()),
+            ttl,
+        })
+    }
+
+    /// Read a query's results if they were cached within the TTL.  The cache
+    /// is best effort, so errors are misses.
+    pub(crate) async fn get(&self, entry: &CacheEntry) -> Option<Cached> {
+        // fails when the results aren't cached
+        let modified = self.storage.metadata(&entry.key).await.ok()?.modified?;
+        let age = (Utc::now() - modified).to_std().unwrap_or_default();
+
+        if age >= entry.ttl {
+            return None;
+        }
+
+        match self.storage.read(&entry.key).await {
+            Ok(parquet) => Some(Cached { parquet, age }),
+            Err(error) => {
+                tracing::warn!("Error reading cached results {}: {error}", entry.key);
+                None
+            }
+        }
+    }
+
+    /// Cache a query's results, replacing any stale results
+    pub(crate) async fn put(&self, entry: &CacheEntry, parquet: &Bytes) {
+        if let Err(error) = self.storage.write(&entry.key, parquet).await {
+            tracing::warn!("Error caching results {}: {error}", entry.key);
+        }
+    }
+}
+
+/// Normalize a query's whitespace and trailing semicolons, so that queries
+/// that only differ in formatting share results.  Quoted strings and
+/// identifiers are left as they are.
+pub(crate) fn normalize_sql(sql: &str) -> String {
+    let mut normalized = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut whitespace = false;
+
+    for c in sql.trim().chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c.is_whitespace() => {
+                whitespace = true;
+                continue;
+            }
+            None if matches!(c, '\'' | '"' | '`') => quote = Some(c),
+            None => {}
+        }
+
+        if whitespace {
+            normalized.push(' ');
+            whitespace = false;
+        }
+
+        normalized.push(c);
+    }
+
+    while normalized.ends_with([';', ' ']) {
+        normalized.pop();
+    }
+
+    normalized
+}
+
+/// The number of records in cached results, from the Parquet footer
+pub(crate) fn record_count(parquet: &Bytes) -> usize {
+    // empty results are cached without a footer
+    SerializedFileReader::new(parquet.to_owned())
+        .map(|reader| reader.metadata().file_metadata().num_rows() as usize)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_cache() -> QueryCache {
+        QueryCache {
+            storage: StorageContainer::FileSystem(FileSystem::new(FileSystemConfig {
+                path: std::env::temp_dir().to_string_lossy().into_owned(),
+                encryption_keys: vec![],
+            })),
+            ttl: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn normalizes_sql() {
+        assert_eq!(
+            normalize_sql("  select *\n\tfrom  users ;; "),
+            "select * from users"
+        );
+        assert_eq!(
+            normalize_sql("select 'a  b', \"c  d\" from t;"),
+            "select 'a  b', \"c  d\" from t"
+        );
+    }
+
+    #[test]
+    fn keys_entries_by_query_and_params() {
+        let cache = new_cache();
+        let connection_id = Uuid::new_v4();
+        let entry = |sql: &str, params: &[SqlParameter]| {
+            cache
+                .entry(connection_id, &"details", None, sql, params)
+                .unwrap()
+        };
+
+        let key = entry("select $1", &[SqlParameter::Integer(1)]).key;
+        assert_eq!(key, entry("select   $1;", &[SqlParameter::Integer(1)]).key);
+        assert_ne!(key, entry("select $1", &[SqlParameter::Integer(2)]).key);
+        assert_ne!(key, entry("select $2", &[SqlParameter::Integer(1)]).key);
+        assert_ne!(
+            key,
+            cache
+                .entry(
+                    connection_id,
+                    &"changed",
+                    None,
+                    "select $1",
+                    &[SqlParameter::Integer(1)]
+                )
+                .unwrap()
+                .key
+        );
+
+        // connections override the TTL, and zero disables caching
+        assert_eq!(entry("select 1", &[]).ttl, Duration::from_secs(60));
+        let entry = cache.entry(connection_id, &"details", Some(5), "select 1", &[]);
+        assert_eq!(entry.unwrap().ttl, Duration::from_secs(5));
+        assert_eq!(
+            cache.entry(connection_id, &"details", Some(0), "select 1", &[]),
+            None
+        );
+    }
+
+    #[test]
+    fn keys_are_stable() {
+        let entry = new_cache()
+            .entry(Uuid::nil(), &"details", None, "select  1;", &[])
+            .unwrap();
+
+        assert_eq!(
+            entry.key,
+            "00000000-0000-0000-0000-000000000000-cc716091ef50846df9ed8e04cf6764dbd0f8aae0a5f08d880c2e563efa459d5f.parquet"
+        );
+    }
+
+    #[tokio::test]
+    async fn caches_results_within_the_ttl() {
+        let cache = new_cache();
+        let entry = cache
+            .entry(Uuid::new_v4(), &"details", None, "select 1", &[])
+            .unwrap();
+        let parquet = Bytes::from("parquet");
+
+        assert!(cache.get(&entry).await.is_none());
+
+        cache.put(&entry, &parquet).await;
+        let cached = cache.get(&entry).await.unwrap();
+        assert_eq!(cached.parquet, parquet);
+        assert!(cached.age < entry.ttl);
+
+        let expired = CacheEntry {
+            ttl: Duration::ZERO,
+            ..entry.to_owned()
+        };
+        assert!(cache.get(&expired).await.is_none());
+
+        cache.storage.delete(&entry.key).await.unwrap();
+    }
+
+    #[test]
+    fn counts_records_of_empty_results() {
+        assert_eq!(record_count(&Bytes::new()), 0);
+    }
+}
@@ -3,6 +3,9 @@
 //! Store information about the state of the application in a send + sync
 //! struct.  All access and mutations to state should be performed here.
 
+pub mod cache;
+pub mod pool;
+pub mod queries;
 pub mod settings;
 pub mod stats;
 
@@ -10,32 +13,50 @@ use std::sync::Arc;
 
 use jsonwebtoken::jwk::JwkSet;
 use reqwest::Client;
-use reqwest::redirect::Policy;
 use tokio::sync::Mutex;
 
 use crate::config::Config;
-use crate::error::{Result, proxy_error};
+use crate::error::Result;
+use crate::proxy;
 use crate::state::settings::Settings;
 
+use self::cache::QueryCache;
+use self::pool::ConnectionPool;
+use self::queries::RunningQueries;
 use self::stats::Stats;
 
 #[derive(Debug, Clone)]
 pub(crate) struct State {
     pub(crate) settings: Settings,
     pub(crate) client: Client,
+    pub(crate) http_client: Client,
     pub(crate) stats: Arc<Mutex<Stats>>,
+    pub(crate) pool: Arc<ConnectionPool>,
+    pub(crate) queries: Arc<RunningQueries>,
+    pub(crate) cache: Option<Arc<QueryCache>>,
 }
 
 impl State {
-    pub(crate) fn new(config: &Config, jwks: Option<JwkSet>) -> Result<Self> {
+    pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Result<Self> {
+        let settings = Settings::new(config, jwks);
+        let client = proxy::policy::client(
+            settings.proxy_policy.to_owned(),
+            settings.proxy_max_redirects,
+        )?;
+        let http_client = proxy::policy::connection_client(
+            settings.proxy_policy.to_owned(),
+            settings.proxy_max_redirects,
+        )?;
+        let cache = QueryCache::new(config).await?.map(Arc::new);
+
         Ok(State {
-            settings: Settings::new(config, jwks),
-            client: Client::builder()
-                .cookie_store(true)
-                .redirect(Policy::limited(5))
-                .build()
-                .map_err(proxy_error)?,
+            settings,
+            client,
+            http_client,
             stats: Arc::new(Mutex::new(Stats::new())),
+            pool: Arc::new(ConnectionPool::default()),
+            queries: Arc::new(RunningQueries::default()),
+            cache,
         })
     }
 }
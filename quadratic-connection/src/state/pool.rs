@@ -0,0 +1,166 @@
+//! Connection Pool
+//!
+//! Keep database connections open between queries, so that each query
+//! doesn't pay for a new TCP, TLS and authentication handshake.  Connections
+//! are pooled per connection and user, and closed once they've been idle for
+//! too long.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Idle connections kept for each connection and user
+const MAX_IDLE_PER_KEY: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PoolKey {
+    connection_id: Uuid,
+    user: String,
+
+    // a hash of the connection's details, so that connections made with
+    // outdated details (eg, a changed password) aren't reused
+    details: u64,
+}
+
+impl PoolKey {
+    pub(crate) fn new(connection_id: Uuid, user: &str, details: &impl Serialize) -> Self {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(details)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+
+        PoolKey {
+            connection_id,
+            user: user.to_owned(),
+            details: hasher.finish(),
+        }
+    }
+}
+
+struct Idle {
+    // the database's connection type, eg, `PgConnection`
+    conn: Box<dyn Any + Send>,
+    since: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct ConnectionPool {
+    idle: Mutex<HashMap<PoolKey, Vec<Idle>>>,
+}
+
+impl std::fmt::Debug for ConnectionPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionPool")
+            .field("idle", &self.idle_count())
+            .finish()
+    }
+}
+
+impl ConnectionPool {
+    /// Take an idle connection, most recently used first
+    pub(crate) fn take<C: Send + 'static>(&self, key: &PoolKey) -> Option<C> {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let conns = idle.get_mut(key)?;
+
+        // a key is only ever used with one type of connection, so this
+        // downcast only fails if that changes
+        let conn = conns.pop()?.conn.downcast::<C>().ok();
+
+        if conns.is_empty() {
+            idle.remove(key);
+        }
+
+        conn.map(|conn| *conn)
+    }
+
+    /// Return a connection to the pool after a successful query.  Connections
+    /// beyond `MAX_IDLE_PER_KEY` are closed.
+    pub(crate) fn put<C: Send + 'static>(&self, key: PoolKey, conn: C) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let conns = idle.entry(key).or_default();
+
+        if conns.len() < MAX_IDLE_PER_KEY {
+            conns.push(Idle {
+                conn: Box::new(conn),
+                since: Instant::now(),
+            });
+        }
+    }
+
+    /// Close connections that have been idle for longer than `idle_timeout`.
+    /// Returns the number of connections closed.
+    pub(crate) fn evict_idle(&self, idle_timeout: Duration) -> usize {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let before = count(&idle);
+
+        idle.retain(|_, conns| {
+            conns.retain(|conn| conn.since.elapsed() < idle_timeout);
+            !conns.is_empty()
+        });
+
+        before - count(&idle)
+    }
+
+    /// The number of idle connections
+    pub(crate) fn idle_count(&self) -> usize {
+        let idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        count(&idle)
+    }
+}
+
+fn count(idle: &HashMap<PoolKey, Vec<Idle>>) -> usize {
+    idle.values().map(Vec::len).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pools_connections_by_key() {
+        let pool = ConnectionPool::default();
+        let connection_id = Uuid::new_v4();
+        let key = PoolKey::new(connection_id, "user", &"details");
+
+        assert_eq!(pool.take::<String>(&key), None);
+
+        pool.put(key.clone(), "conn".to_string());
+        assert_eq!(pool.idle_count(), 1);
+
+        // another user or changed details don't share connections
+        assert_eq!(
+            pool.take::<String>(&PoolKey::new(connection_id, "other", &"details")),
+            None
+        );
+        assert_eq!(
+            pool.take::<String>(&PoolKey::new(connection_id, "user", &"changed")),
+            None
+        );
+
+        assert_eq!(pool.take::<String>(&key), Some("conn".to_string()));
+        assert_eq!(pool.idle_count(), 0);
+
+        for _ in 0..MAX_IDLE_PER_KEY + 1 {
+            pool.put(key.clone(), "conn".to_string());
+        }
+        assert_eq!(pool.idle_count(), MAX_IDLE_PER_KEY);
+    }
+
+    #[test]
+    fn evicts_idle_connections() {
+        let pool = ConnectionPool::default();
+        let key = PoolKey::new(Uuid::new_v4(), "user", &"details");
+
+        pool.put(key.clone(), 1_u8);
+
+        assert_eq!(pool.evict_idle(Duration::from_secs(60)), 0);
+        assert_eq!(pool.evict_idle(Duration::ZERO), 1);
+        assert_eq!(pool.take::<u8>(&key), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Running Queries
+//!
+//! Track queries that are running, so that they can be cancelled by the user
+//! that started them.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::error::{ConnectionError, Result};
+
+#[derive(Debug)]
+struct RunningQuery {
+    user: String,
+    cancel: Arc<Notify>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RunningQueries {
+    queries: Mutex<HashMap<Uuid, RunningQuery>>,
+}
+
+/// Removes a query from the running queries when dropped
+#[derive(Debug)]
+pub(crate) struct QueryGuard {
+    queries: Arc<RunningQueries>,
+    query_id: Uuid,
+
+    /// Notified when the query is cancelled
+    pub(crate) cancel: Arc<Notify>,
+}
+
+impl Drop for QueryGuard {
+    fn drop(&mut self) {
+        self.queries
+            .queries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.query_id);
+    }
+}
+
+impl RunningQueries {
+    /// Start tracking a query.  Query ids are chosen by the client, so an id
+    /// that's already running is rejected.
+    pub(crate) fn start(self: &Arc<Self>, query_id: Uuid, user: &str) -> Result<QueryGuard> {
+        let mut queries = self.queries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if queries.contains_key(&query_id) {
+            return Err(ConnectionError::Query(format!(
+                "Query {query_id} is already running"
+            )));
+        }
+
+        let cancel = Arc::new(Notify::new());
+        queries.insert(
+            query_id,
+            RunningQuery {
+                user: user.to_owned(),
+                cancel: Arc::clone(&cancel),
+            },
+        );
+
+        Ok(QueryGuard {
+            queries: Arc::clone(self),
+            query_id,
+            cancel,
+        })
+    }
+
+    /// Cancel a query that the user started.  Returns false if there's no
+    /// such query.
+    pub(crate) fn cancel(&self, query_id: &Uuid, user: &str) -> bool {
+        let queries = self.queries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match queries.get(query_id) {
+            Some(query) if query.user == user => {
+                // stores a permit if the query isn't waiting yet
+                query.cancel.notify_one();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancels_running_queries() {
+        let queries = Arc::new(RunningQueries::default());
+        let query_id = Uuid::new_v4();
+        let guard = queries.start(query_id, "user").unwrap();
+
+        assert!(queries.start(query_id, "user").is_err());
+
+        // only the user that started the query can cancel it
+        assert!(!queries.cancel(&query_id, "other"));
+        assert!(queries.cancel(&query_id, "user"));
+        guard.cancel.notified().await;
+
+        drop(guard);
+        assert!(!queries.cancel(&query_id, "user"));
+        assert!(queries.start(query_id, "user").is_ok());
+    }
+}
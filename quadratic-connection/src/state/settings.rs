@@ -1,6 +1,10 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use jsonwebtoken::jwk::JwkSet;
 
 use crate::config::Config;
+use crate::proxy::policy::ProxyPolicy;
 
 #[derive(Debug, Clone)]
 pub(crate) struct Settings {
@@ -8,6 +12,13 @@ pub(crate) struct Settings {
     pub(crate) _m2m_auth_token: String,
     pub(crate) jwks: Option<JwkSet>,
     pub(crate) max_response_bytes: u64,
+    pub(crate) query_timeout: Duration,
+    pub(crate) pool_idle_timeout: Duration,
+    pub(crate) proxy_policy: ProxyPolicy,
+    pub(crate) proxy_max_response_bytes: u64,
+    pub(crate) proxy_timeout: Duration,
+    pub(crate) proxy_max_redirects: usize,
+    pub(crate) file_connections_dir: PathBuf,
 }
 
 impl Settings {
@@ -17,6 +28,13 @@ impl Settings {
             _m2m_auth_token: config.m2m_auth_token.to_owned(),
             jwks,
             max_response_bytes: config.max_response_bytes,
+            query_timeout: Duration::from_secs(config.query_timeout_s),
+            pool_idle_timeout: Duration::from_secs(config.pool_idle_timeout_s),
+            proxy_policy: ProxyPolicy::new(config),
+            proxy_max_response_bytes: config.proxy_max_response_bytes,
+            proxy_timeout: Duration::from_secs(config.proxy_timeout_s),
+            proxy_max_redirects: config.proxy_max_redirects,
+            file_connections_dir: PathBuf::from(&config.file_connections_dir),
         }
     }
 }
@@ -56,7 +56,7 @@ pub(crate) fn str_vec(value: &str) -> Vec<u8> {
 
 pub(crate) async fn new_state() -> State {
     let config = config().unwrap();
-    State::new(&config, None).unwrap()
+    State::new(&config, None).await.unwrap()
 }
 
 pub(crate) async fn new_team_id_with_header() -> (Uuid, HeaderMap) {
@@ -77,6 +77,8 @@ pub(crate) fn _new_postgres_connection() -> PostgresConnection {
         "0.0.0.0".into(),
         Some("5432".into()),
         "postgres".into(),
+        None,
+        None,
     )
 }
 
@@ -19,8 +19,9 @@
 //! - Table1[[Column1]:] - column 1 onward (Excel does not have this)
 //! - (not yet supported) Table1[[#TOTALS], [Column 1]] - reference the total line
 //!
-//! Note Table1[#THIS ROW] and Table1[@Column 1] are not supported (supported in
-//! Excel but not Google Sheets either)
+//! Table1[@Column 1] and Table1[[#THIS ROW],[Column 1]] are only supported
+//! within calculated columns, where they are parsed as a ThisRowRef (see
+//! this_row.rs) instead of a TableRef.
 //!
 //! For purposes of data frames, we'll probably ignore #DATA, since we want to
 //! define the data frame with the headers.
@@ -50,9 +51,11 @@ mod intersects;
 mod parse;
 mod query;
 mod range;
+mod this_row;
 mod tokenize;
 
 pub use range::*;
+pub use this_row::*;
 
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
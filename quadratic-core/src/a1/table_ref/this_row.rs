@@ -0,0 +1,300 @@
+//! A this-row reference to a column within a table.
+//!
+//! This-row references are used by calculated columns to reference a value
+//! in the same row as the cell being calculated:
+//! - [@Price] or [@[Unit Price]] - the column in the current row of the table
+//!   that contains the formula
+//! - Table1[@Price] - the column in the current row of Table1
+//! - Table1[[#THIS ROW],[Price]] - Excel's long form of the above
+//!
+//! Unlike a TableRef, a this-row reference always resolves to a single cell,
+//! and can only be resolved relative to a position within the table.
+
+use std::fmt;
+
+use crate::{Pos, SheetPos};
+
+use super::*;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ThisRowRef {
+    /// Table name, if specified. If None, the reference is to the table that
+    /// contains the cell being calculated.
+    pub table_name: Option<String>,
+    pub column: String,
+}
+
+impl ThisRowRef {
+    pub fn new(column: &str) -> Self {
+        Self {
+            table_name: None,
+            column: column.to_string(),
+        }
+    }
+
+    /// Parses the contents of the outer brackets of a this-row reference (eg,
+    /// `@Price`, `@[Unit Price]`, or `[#THIS ROW],[Price]`).
+    ///
+    /// Returns `None` if the string is not a this-row reference.
+    pub fn parse_brackets(s: &str) -> Option<Result<String, A1Error>> {
+        let s = s.trim();
+
+        if let Some(column) = s.strip_prefix('@') {
+            let column = column.trim();
+            let column = column
+                .strip_prefix('[')
+                .and_then(|c| c.strip_suffix(']'))
+                .unwrap_or(column);
+            return Some(Self::unescape_column(column));
+        }
+
+        if !s.starts_with('[') {
+            return None;
+        }
+
+        let entries = match TableRef::bracketed_entries(s) {
+            Ok(entries) => entries,
+            Err(_) => return None,
+        };
+        let (first, rest) = entries.split_first()?;
+        if !first.eq_ignore_ascii_case("#THISROW") {
+            return None;
+        }
+
+        Some(match rest {
+            [column] if !column.is_empty() => Ok(column.to_string()),
+            _ => Err(A1Error::InvalidTableRef(
+                "This row references must have exactly one column".into(),
+            )),
+        })
+    }
+
+    /// Removes ' escapes from a column name.
+    fn unescape_column(s: &str) -> Result<String, A1Error> {
+        let mut column = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                let Some(c) = chars.next() else {
+                    return Err(A1Error::InvalidTableRef(
+                        "Unexpected escape character '".into(),
+                    ));
+                };
+                column.push(c);
+            } else {
+                column.push(c);
+            }
+        }
+
+        if column.trim().is_empty() {
+            return Err(A1Error::InvalidTableRef("Empty entry found".into()));
+        }
+
+        Ok(column.trim().to_string())
+    }
+
+    /// Parses a this-row reference, eg, `[@Price]` or `Table1[@Price]`.
+    pub fn parse(s: &str) -> Result<Self, A1Error> {
+        let s = s.trim();
+        let (table_name, brackets) = match s.find('[') {
+            Some(0) => (None, s),
+            Some(index) => (Some(s[..index].trim().to_string()), &s[index..]),
+            None => {
+                return Err(A1Error::InvalidTableRef(
+                    "Expected a this row reference".into(),
+                ));
+            }
+        };
+
+        let inner = brackets
+            .strip_prefix('[')
+            .and_then(|b| b.strip_suffix(']'))
+            .ok_or_else(|| A1Error::InvalidTableRef("Expected a this row reference".into()))?;
+
+        match Self::parse_brackets(inner) {
+            Some(column) => Ok(Self {
+                table_name,
+                column: column?,
+            }),
+            None => Err(A1Error::InvalidTableRef(
+                "Expected a this row reference".into(),
+            )),
+        }
+    }
+
+    /// Resolves the reference to the cell in the same row as `sheet_pos`.
+    ///
+    /// `sheet_pos` must be within the data rows of the table. Hidden columns
+    /// cannot be referenced since they are not part of the grid.
+    pub fn to_sheet_pos(
+        &self,
+        sheet_pos: SheetPos,
+        a1_context: &A1Context,
+    ) -> Result<SheetPos, A1Error> {
+        let table = match &self.table_name {
+            Some(table_name) => a1_context
+                .try_table(table_name)
+                .ok_or_else(|| A1Error::TableNotFound(table_name.clone()))?,
+            None => a1_context.table_from_pos(sheet_pos).ok_or_else(|| {
+                A1Error::InvalidTableRef("This row reference is outside of a table".into())
+            })?,
+        };
+
+        let first_data_row = table.bounds.min.y + table.y_adjustment(false);
+        if table.sheet_id != sheet_pos.sheet_id
+            || sheet_pos.y < first_data_row
+            || sheet_pos.y > table.bounds.max.y
+        {
+            return Err(A1Error::InvalidTableRef(
+                "This row reference is outside of the table's rows".into(),
+            ));
+        }
+
+        let column_index = table
+            .try_col_index(&self.column)
+            .ok_or_else(|| A1Error::InvalidColumn(self.column.clone()))?;
+
+        Ok(Pos {
+            x: table.bounds.min.x + column_index,
+            y: sheet_pos.y,
+        }
+        .to_sheet_pos(table.sheet_id))
+    }
+
+    /// Replaces a table column name in the reference. `table_name` is the
+    /// name of the table that owns the formula (used when the reference does
+    /// not specify a table name).
+    pub fn replace_column_name(&mut self, table_name: &str, old_name: &str, new_name: &str) {
+        let same_table = self
+            .table_name
+            .as_ref()
+            .is_none_or(|name| name.eq_ignore_ascii_case(table_name));
+        if same_table && self.column.eq_ignore_ascii_case(old_name) {
+            self.column = new_name.to_string();
+        }
+    }
+}
+
+impl fmt::Display for ThisRowRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let needs_brackets = self
+            .column
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, ',' | ':' | '[' | ']' | '@' | '#' | '\''));
+        let column = if needs_brackets {
+            let escaped = self
+                .column
+                .replace('\'', "''")
+                .replace('[', "'[")
+                .replace(']', "']");
+            format!("[{escaped}]")
+        } else {
+            self.column.clone()
+        };
+        write!(
+            f,
+            "{}[@{}]",
+            self.table_name.as_deref().unwrap_or_default(),
+            column
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rect, grid::SheetId};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_this_row() {
+        assert_eq!(
+            ThisRowRef::parse("[@Price]").unwrap(),
+            ThisRowRef::new("Price")
+        );
+        assert_eq!(
+            ThisRowRef::parse("[@[Unit Price]]").unwrap(),
+            ThisRowRef::new("Unit Price")
+        );
+        assert_eq!(
+            ThisRowRef::parse("Table1[@Price]").unwrap(),
+            ThisRowRef {
+                table_name: Some("Table1".to_string()),
+                column: "Price".to_string(),
+            }
+        );
+        assert_eq!(
+            ThisRowRef::parse("Table1[[#This Row],[Unit Price]]").unwrap(),
+            ThisRowRef {
+                table_name: Some("Table1".to_string()),
+                column: "Unit Price".to_string(),
+            }
+        );
+        assert!(ThisRowRef::parse("Table1[Price]").is_err());
+        assert!(ThisRowRef::parse("[@]").is_err());
+        assert_eq!(ThisRowRef::parse_brackets("Price"), None);
+    }
+
+    #[test]
+    fn test_this_row_to_string() {
+        let cases = ["[@Price]", "[@[Unit Price]]", "Table1[@Qty]"];
+        for case in cases {
+            assert_eq!(ThisRowRef::parse(case).unwrap().to_string(), case);
+        }
+    }
+
+    #[test]
+    fn test_this_row_to_sheet_pos() {
+        // Table1 has a name and column header row, so data starts at row 3
+        let context = A1Context::test(
+            &[("Sheet1", SheetId::TEST)],
+            &[("Table1", &["Price", "Qty", "Total"], Rect::test_a1("B1:D5"))],
+        );
+
+        let price = ThisRowRef::new("Price");
+        assert_eq!(
+            price
+                .to_sheet_pos(SheetPos::new(SheetId::TEST, 4, 3), &context)
+                .unwrap(),
+            SheetPos::new(SheetId::TEST, 2, 3)
+        );
+
+        let qty = ThisRowRef::parse("table1[@qty]").unwrap();
+        assert_eq!(
+            qty.to_sheet_pos(SheetPos::new(SheetId::TEST, 4, 5), &context)
+                .unwrap(),
+            SheetPos::new(SheetId::TEST, 3, 5)
+        );
+
+        // header row
+        assert!(
+            price
+                .to_sheet_pos(SheetPos::new(SheetId::TEST, 4, 2), &context)
+                .is_err()
+        );
+
+        // outside of the table
+        assert!(
+            price
+                .to_sheet_pos(SheetPos::new(SheetId::TEST, 10, 3), &context)
+                .is_err()
+        );
+
+        // unknown column
+        assert_eq!(
+            ThisRowRef::new("Tax").to_sheet_pos(SheetPos::new(SheetId::TEST, 4, 3), &context),
+            Err(A1Error::InvalidColumn("Tax".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_this_row_replace_column_name() {
+        let mut this_row = ThisRowRef::new("Price");
+        this_row.replace_column_name("Table1", "price", "Unit Price");
+        assert_eq!(this_row.to_string(), "[@[Unit Price]]");
+
+        let mut this_row = ThisRowRef::parse("Table2[@Price]").unwrap();
+        this_row.replace_column_name("Table1", "Price", "Cost");
+        assert_eq!(this_row.to_string(), "Table2[@Price]");
+    }
+}
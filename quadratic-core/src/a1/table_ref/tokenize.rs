@@ -16,7 +16,7 @@ pub(crate) enum Token {
 impl TableRef {
     /// Separates bracketed entries, allowing double brackets and ' to escape
     /// special characters. Returns a list of Strings that can be tokenized.
-    fn bracketed_entries(s: &str) -> Result<Vec<String>, A1Error> {
+    pub(super) fn bracketed_entries(s: &str) -> Result<Vec<String>, A1Error> {
         let mut entries = Vec::new();
 
         // inside an escaped region
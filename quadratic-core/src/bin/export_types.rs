@@ -16,10 +16,12 @@ use quadratic_core::controller::execution::run_code::get_cells::JsCellsA1Respons
 use quadratic_core::controller::execution::run_code::get_cells::JsCellsA1Value;
 use quadratic_core::controller::execution::run_code::get_cells::JsCellsA1Values;
 use quadratic_core::controller::operations::clipboard::PasteSpecial;
+use quadratic_core::controller::operations::csv_import::{CsvEncoding, CsvImportOptions};
 use quadratic_core::controller::transaction_types::JsCellValueResult;
 use quadratic_core::controller::transaction_types::JsCodeResult;
 use quadratic_core::formulas::parse_formula::JsFormulaParseResult;
 use quadratic_core::grid::JsCellsAccessed;
+use quadratic_core::grid::column_type::DataTableColumnType;
 use quadratic_core::grid::formats::Format;
 use quadratic_core::grid::js_types::JsChartContext;
 use quadratic_core::grid::js_types::JsCodeTableContext;
@@ -43,6 +45,10 @@ use quadratic_core::grid::sheet::borders::JsBorderHorizontal;
 use quadratic_core::grid::sheet::borders::JsBorderVertical;
 use quadratic_core::grid::sheet::borders::JsBordersSheet;
 use quadratic_core::grid::sheet::keyboard::Direction;
+use quadratic_core::grid::sheet::protection::{
+    Protection, ProtectionAllowList, ProtectionRole, ProtectionTarget, ProtectionUser,
+    SheetProtections,
+};
 use quadratic_core::grid::sheet::search::SearchOptions;
 use quadratic_core::grid::sheet::validations::validation::{
     Validation, ValidationError, ValidationMessage, ValidationStyle,
@@ -117,6 +123,9 @@ fn main() {
         CodeCellLanguage,
         ColumnRow,
         ConnectionKind,
+        CsvEncoding,
+        CsvImportOptions,
+        DataTableColumnType,
         DataTableSort,
         DateTimeRange,
         Direction,
@@ -163,6 +172,11 @@ fn main() {
         NumericFormatKind,
         PasteSpecial,
         Pos,
+        Protection,
+        ProtectionAllowList,
+        ProtectionRole,
+        ProtectionTarget,
+        ProtectionUser,
         Rect,
         RefRangeBounds,
         Rgba,
@@ -173,6 +187,7 @@ fn main() {
         SheetId,
         SheetInfo,
         SheetPos,
+        SheetProtections,
         SheetRect,
         SmallTimestamp,
         SortDirection,
@@ -1,7 +1,7 @@
 use anyhow::{Result, anyhow};
 use bincode::config;
 use flate2::{
-    Compression,
+    Compression, read,
     write::{ZlibDecoder, ZlibEncoder},
 };
 use serde::de::DeserializeOwned;
@@ -52,6 +52,21 @@ where
     deserialize::<T>(serialization_format, &decompressed)
 }
 
+/// Like `decompress_and_deserialize`, but fails once the decompressed data
+/// exceeds `max_bytes`, so small payloads can't expand into huge ones.
+pub fn decompress_and_deserialize_with_limit<T>(
+    serialization_format: &SerializationFormat,
+    compression_format: &CompressionFormat,
+    data: &[u8],
+    max_bytes: usize,
+) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let decompressed = decompress_with_limit(compression_format, data, max_bytes)?;
+    deserialize::<T>(serialization_format, &decompressed)
+}
+
 // SERIALIZATION
 
 pub fn serialize<T>(serialization_format: &SerializationFormat, data: T) -> Result<Vec<u8>>
@@ -121,6 +136,33 @@ pub fn decompress_zlib(data: &[u8]) -> Result<Vec<u8>> {
     Ok(decoder.finish()?)
 }
 
+pub fn decompress_with_limit(
+    compression_format: &CompressionFormat,
+    data: &[u8],
+    max_bytes: usize,
+) -> Result<Vec<u8>> {
+    let decompressed = match compression_format {
+        CompressionFormat::None => data.to_vec(),
+        CompressionFormat::Zlib => {
+            let mut decompressed = Vec::new();
+
+            // read one byte past the limit to detect oversized data
+            read::ZlibDecoder::new(data)
+                .take(max_bytes as u64 + 1)
+                .read_to_end(&mut decompressed)?;
+            decompressed
+        }
+    };
+
+    if decompressed.len() > max_bytes {
+        return Err(anyhow!(
+            "Decompressed data exceeds the limit of {max_bytes} bytes"
+        ));
+    }
+
+    Ok(decompressed)
+}
+
 // HEADER
 
 pub fn add_header(header: Vec<u8>, data: Vec<u8>) -> Result<Vec<u8>> {
@@ -180,4 +222,29 @@ mod test {
 
         assert_roundtrip_compression(&serialization_format, &compression_format);
     }
+
+    #[test]
+    fn decompress_with_a_limit() {
+        let data = "a".repeat(1000);
+        let compressed =
+            serialize_and_compress(&SerializationFormat::Json, &CompressionFormat::Zlib, &data)
+                .unwrap();
+
+        let decompressed = decompress_and_deserialize_with_limit::<String>(
+            &SerializationFormat::Json,
+            &CompressionFormat::Zlib,
+            &compressed,
+            1002,
+        )
+        .unwrap();
+        assert_eq!(decompressed, data);
+
+        let result = decompress_and_deserialize_with_limit::<String>(
+            &SerializationFormat::Json,
+            &CompressionFormat::Zlib,
+            &compressed,
+            1001,
+        );
+        assert!(result.is_err());
+    }
 }
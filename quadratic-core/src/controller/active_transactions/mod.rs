@@ -4,6 +4,7 @@
 //! * tracking the state of the current active transaction
 //! * tracking the state of pending async transactions
 //! * tracking the state of pending multiplayer transactions (both sent and received)
+//! * persisting the local undo history
 
 use self::{pending_transaction::PendingTransaction, unsaved_transactions::UnsavedTransactions};
 use super::transaction::Transaction;
@@ -13,6 +14,7 @@ use uuid::Uuid;
 
 pub mod pending_transaction;
 pub mod transaction_name;
+pub mod undo_history;
 pub mod unsaved_transactions;
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -31,6 +33,15 @@ pub struct ActiveTransactions {
 
     // The last sequence_num we applied locally.
     pub last_sequence_num: u64,
+
+    // The last sequence_num that the undo and redo stacks were transformed against (set when they are restored after a reload).
+    pub undo_sequence_num: u64,
+
+    // Whether the client is told to persist the undo and redo stacks whenever they change.
+    pub persist_undo_history: bool,
+
+    // Whether the undo and redo stacks changed since the client last took them.
+    pub undo_history_changed: bool,
 }
 
 impl ActiveTransactions {
@@ -0,0 +1,18 @@
+use crate::controller::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+
+/// The most bytes of serialized transactions kept in a persisted history. The
+/// oldest transactions are dropped first.
+pub const MAX_UNDO_HISTORY_BYTES: usize = 5 * 1024 * 1024;
+
+/// The local user's undo and redo stacks, which the client may persist
+/// alongside the [`super::unsaved_transactions::UnsavedTransactions`] so they
+/// survive a reload.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct UndoHistory {
+    /// The last sequence_num applied when the history was saved. The stacks
+    /// are already transformed against transactions up to it.
+    pub sequence_num: u64,
+    pub undo_stack: Vec<Transaction>,
+    pub redo_stack: Vec<Transaction>,
+}
@@ -26,6 +26,39 @@ impl GridController {
             Some(dependent_cells)
         }
     }
+
+    /// Searches all data tables in all sheets for calculated columns that read
+    /// cells outside of their table within the given sheet_rect. Edits within
+    /// a data table already recompute its own calculated columns.
+    pub fn get_dependent_calculated_tables(&self, sheet_rect: &SheetRect) -> Vec<SheetPos> {
+        let context = self.a1_context();
+        let mut dependent_tables = vec![];
+
+        self.grid.sheets().iter().for_each(|sheet| {
+            sheet.data_tables.iter().for_each(|(pos, data_table)| {
+                if data_table.calculated_cells_accessed.cells.is_empty() {
+                    return;
+                }
+
+                if sheet.id == sheet_rect.sheet_id
+                    && data_table
+                        .output_rect(*pos, false)
+                        .contains_rect(&(*sheet_rect).into())
+                {
+                    return;
+                }
+
+                if data_table
+                    .calculated_cells_accessed
+                    .intersects(sheet_rect, context)
+                {
+                    dependent_tables.push(pos.to_sheet_pos(sheet.id));
+                }
+            });
+        });
+
+        dependent_tables
+    }
 }
 
 #[cfg(test)]
@@ -521,6 +521,7 @@ mod tests {
                     one_dimensional: false,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
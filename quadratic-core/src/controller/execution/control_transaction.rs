@@ -124,6 +124,10 @@ impl GridController {
             TransactionSource::Unset => panic!("Expected a transaction type"),
         }
 
+        if transaction.is_user_undo_redo() {
+            self.undo_history_changed();
+        }
+
         self.send_transaction_client_updates(&mut transaction);
 
         transaction.send_transaction();
@@ -144,6 +148,9 @@ impl GridController {
         cursor: Option<String>,
         transaction_name: TransactionName,
     ) -> String {
+        if !self.allowed_by_protections(&operations) {
+            return String::new();
+        }
         let mut transaction = PendingTransaction {
             source: TransactionSource::User,
             operations: operations.into(),
@@ -246,6 +253,10 @@ impl GridController {
                     ConnectionKind::Mysql => "MySQL1",
                     ConnectionKind::Mssql => "MSSQL1",
                     ConnectionKind::Snowflake => "Snowflake1",
+                    ConnectionKind::Sqlite => "SQLite1",
+                    ConnectionKind::DuckDb => "DuckDB1",
+                    ConnectionKind::ClickHouse => "ClickHouse1",
+                    ConnectionKind::Http => "HTTP1",
                 },
                 // this should not happen
                 _ => "Connection 1",
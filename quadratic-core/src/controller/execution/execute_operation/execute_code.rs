@@ -8,7 +8,9 @@ use crate::{
 };
 use anyhow::Result;
 impl GridController {
-    /// Adds operations to compute cells that are dependents within a SheetRect
+    /// Adds operations to compute cells that are dependents within a SheetRect.
+    /// Data tables whose calculated columns depend on the SheetRect are
+    /// recomputed as well.
     pub fn add_compute_operations(
         &mut self,
         transaction: &mut PendingTransaction,
@@ -36,6 +38,30 @@ impl GridController {
                     }
                 });
             });
+
+        // calculated columns are not code cells: re-applying one of a table's
+        // formulas recomputes all of its calculated columns, on every client
+        for sheet_pos in self.get_dependent_calculated_tables(output) {
+            let Some((column_index, formula)) = self
+                .try_sheet(sheet_pos.sheet_id)
+                .and_then(|sheet| sheet.data_table(sheet_pos.into()))
+                .and_then(|data_table| data_table.calculated_columns().into_iter().next())
+            else {
+                continue;
+            };
+
+            let op = Operation::SetDataTableColumnFormula {
+                sheet_pos,
+                column_index: column_index as u32,
+                formula: Some(formula),
+                values: None,
+            };
+
+            // only add a recompute operation if there isn't already one pending
+            if !transaction.operations.contains(&op) {
+                transaction.operations.push_back(op);
+            }
+        }
     }
 
     /// **Deprecated** and replaced with SetChartCellSize
@@ -133,6 +159,9 @@ impl GridController {
                 CodeCellLanguage::Javascript => {
                     self.run_javascript(transaction, sheet_pos, code);
                 }
+                CodeCellLanguage::Sql => {
+                    self.run_sql(transaction, sheet_pos, code);
+                }
                 CodeCellLanguage::Import => {} // no-op
             }
         }
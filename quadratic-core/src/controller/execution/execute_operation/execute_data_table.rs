@@ -9,6 +9,7 @@ use crate::{
     },
     grid::{
         DataTable, SheetId,
+        column_type::DataTableColumnType,
         formats::{FormatUpdate, SheetFormatUpdates},
         js_types::JsSnackbarSeverity,
         unique_data_table_name,
@@ -402,6 +403,7 @@ impl GridController {
                 old_values = old_sorted_values;
             }
 
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
             self.send_updated_bounds(transaction, sheet_id);
 
             let forward_operations = vec![op];
@@ -770,6 +772,7 @@ impl GridController {
             }
 
             // update column names that have changed in code cells
+            let mut renamed_columns = vec![];
             if let (Some(columns), Some(old_columns)) = (columns.to_owned(), old_columns) {
                 for (index, old_column) in old_columns.iter().enumerate() {
                     if let Some(new_column) = columns.get(index) {
@@ -798,6 +801,8 @@ impl GridController {
                                 &new_column.name.to_string(),
                                 &context,
                             );
+                            renamed_columns
+                                .push((old_column.name.to_string(), new_column.name.to_string()));
                         }
                     }
                 }
@@ -833,6 +838,9 @@ impl GridController {
             let old_columns = columns.to_owned().and_then(|columns| {
                 let old_columns = std::mem::replace(&mut data_table.column_headers, Some(columns));
                 data_table.normalize_column_header_names();
+                for (old_column_name, new_column_name) in renamed_columns.iter() {
+                    data_table.replace_column_name_in_formulas(old_column_name, new_column_name);
+                }
                 // mark code cells as dirty to updata meta data
                 transaction.add_code_cell(sheet_id, data_table_pos);
                 old_columns
@@ -851,6 +859,7 @@ impl GridController {
 
             // changing these options shifts the entire data table, need to mark the entire data table as dirty
             if show_name.is_some() || show_columns.is_some() || columns.is_some() {
+                self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
                 self.mark_data_table_dirty(transaction, sheet_id, data_table_pos)?;
                 self.send_updated_bounds(transaction, sheet_id);
             }
@@ -906,6 +915,7 @@ impl GridController {
                 data_table.sort_all()?;
             }
 
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
             self.mark_data_table_dirty(transaction, sheet_id, data_table_pos)?;
             self.send_updated_bounds(transaction, sheet_id);
 
@@ -1082,6 +1092,7 @@ impl GridController {
 
             transaction.add_code_cell(sheet_id, data_table_pos);
             data_table.add_dirty_fills_and_borders(transaction, sheet_id);
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
             self.mark_data_table_dirty(transaction, sheet_id, data_table_pos)?;
             self.send_updated_bounds(transaction, sheet_id);
 
@@ -1122,6 +1133,7 @@ impl GridController {
             }
 
             let mut reverse_columns = vec![];
+            let mut reverse_formulas = vec![];
             let mut reverse_operations: Vec<Operation> = vec![];
 
             // ensure columns are deleted in reverse order
@@ -1221,6 +1233,15 @@ impl GridController {
 
                 let old_values = data_table.get_column_sorted(index as usize)?;
                 reverse_columns.push((index, old_column_header, Some(old_values)));
+
+                if let Some(formula) = data_table.column_formula(index as usize) {
+                    reverse_formulas.push(Operation::SetDataTableColumnFormula {
+                        sheet_pos,
+                        column_index: index,
+                        formula: Some(formula.to_string()),
+                        values: None,
+                    });
+                }
             }
 
             let sheet = self.try_sheet_result(sheet_id)?;
@@ -1257,6 +1278,9 @@ impl GridController {
                 data_table.delete_column_sorted(*index as usize)?;
             }
 
+            // formulas are restored after the columns are inserted
+            reverse_operations.extend(reverse_formulas);
+
             if old_sort.is_some() || old_display_buffer.is_some() {
                 reverse_operations.push(Operation::SortDataTable {
                     sheet_pos,
@@ -1294,6 +1318,8 @@ impl GridController {
                 }
             }
 
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
+
             let sheet = self.try_sheet_result(sheet_id)?;
             let data_table = sheet.data_table_result(data_table_pos)?;
             if select_table {
@@ -1428,6 +1454,7 @@ impl GridController {
 
             transaction.add_code_cell(sheet_id, data_table_pos);
             data_table.add_dirty_fills_and_borders(transaction, sheet_id);
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
             self.mark_data_table_dirty(transaction, sheet_id, data_table_pos)?;
             self.send_updated_bounds(transaction, sheet_id);
 
@@ -1622,6 +1649,8 @@ impl GridController {
                 }
             }
 
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
+
             let sheet = self.try_sheet_result(sheet_id)?;
             let data_table = sheet.data_table_result(data_table_pos)?;
             if select_table {
@@ -1644,6 +1673,103 @@ impl GridController {
         bail!("Expected Operation::DeleteDataTableRows in execute_delete_data_table_row");
     }
 
+    pub(super) fn execute_set_data_table_column_formula(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) -> Result<()> {
+        if let Operation::SetDataTableColumnFormula {
+            sheet_pos,
+            column_index,
+            formula,
+            values,
+        } = op.to_owned()
+        {
+            let sheet_id = sheet_pos.sheet_id;
+            let sheet = self.try_sheet_mut_result(sheet_id)?;
+            let data_table_pos = sheet.first_data_table_within(sheet_pos.into())?;
+            let data_table = sheet.data_table_mut(data_table_pos)?;
+            let data_table_rect = data_table
+                .output_rect(data_table_pos, true)
+                .to_sheet_rect(sheet_id);
+
+            if data_table.is_code() {
+                dbgjs!(format!("Data table {} is readonly", data_table.name));
+                return Ok(());
+            }
+
+            if data_table
+                .get_column_header(column_index as usize)
+                .is_none()
+            {
+                bail!("Column {column_index} not found in data table");
+            }
+
+            // re-applying the current formula only recomputes the table (eg, a
+            // cell it reads outside of the table changed)
+            if values.is_none()
+                && formula.is_some()
+                && data_table.column_formula(column_index as usize) == formula.as_deref()
+            {
+                self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
+                self.mark_data_table_dirty(transaction, sheet_id, data_table_pos)?;
+
+                if transaction.is_user_undo_redo() {
+                    transaction.forward_operations.push(op.clone());
+
+                    // reverse operations run last to first, so undo recomputes
+                    // the table after the cells it reads have been restored
+                    transaction.reverse_operations.insert(0, op);
+                }
+
+                return Ok(());
+            }
+
+            // keep the values of a regular column so they can be restored
+            let old_values = match data_table.column_formula(column_index as usize) {
+                Some(_) => None,
+                None => Some(data_table.get_column(column_index as usize)?),
+            };
+            let old_formula = data_table.set_column_formula(column_index as usize, formula);
+
+            // restore the values of a column that is no longer calculated
+            if let Some(values) = values {
+                let skip = data_table.adjust_for_header(0);
+                let column_type = DataTableColumnType::infer(values.iter().skip(skip), false);
+                let array = data_table.mut_value_as_array()?;
+                for (y, value) in values.into_iter().enumerate() {
+                    array.set(column_index, y as u32, value)?;
+                }
+                data_table.set_column_type(column_index as usize, column_type);
+                data_table.check_sort()?;
+                transaction.add_code_cell(sheet_id, data_table_pos);
+            }
+
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
+            self.mark_data_table_dirty(transaction, sheet_id, data_table_pos)?;
+
+            let forward_operations = vec![op];
+            let reverse_operations = vec![Operation::SetDataTableColumnFormula {
+                sheet_pos,
+                column_index,
+                formula: old_formula,
+                values: old_values,
+            }];
+            self.data_table_operations(
+                transaction,
+                forward_operations,
+                reverse_operations,
+                Some(&data_table_rect),
+            );
+
+            return Ok(());
+        };
+
+        bail!(
+            "Expected Operation::SetDataTableColumnFormula in execute_set_data_table_column_formula"
+        );
+    }
+
     pub(super) fn execute_data_table_first_row_as_header(
         &mut self,
         transaction: &mut PendingTransaction,
@@ -1676,8 +1802,10 @@ impl GridController {
                 .output_rect(data_table_pos, true)
                 .to_sheet_rect(sheet_id);
 
+            self.recompute_calculated_columns(transaction, sheet_id, data_table_pos)?;
+
             // mark dirty if the first row is not the header, so that largest rect gets marked dirty
-            if !data_table.header_is_first_row {
+            if !first_row_is_header {
                 self.mark_data_table_dirty(transaction, sheet_id, data_table_pos)?;
             }
             self.send_updated_bounds(transaction, sheet_id);
@@ -1870,6 +1998,7 @@ mod tests {
         assert_eq!(
             headers[index as usize],
             DataTableColumnHeader::new(name.into(), true, index)
+                .with_column_type(headers[index as usize].column_type)
         );
         assert_eq!(data_table.value.into_array().unwrap().size().w.get(), width);
     }
@@ -257,6 +257,39 @@ impl GridController {
         }
     }
 
+    pub(crate) fn execute_set_sheet_protections(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::SetSheetProtections {
+            sheet_id,
+            protections,
+        } = op
+        {
+            let Some(sheet) = self.try_sheet_mut(sheet_id) else {
+                // sheet may have been deleted
+                return;
+            };
+            let old_protections = std::mem::replace(&mut sheet.protections, protections.clone());
+
+            transaction
+                .forward_operations
+                .push(Operation::SetSheetProtections {
+                    sheet_id,
+                    protections,
+                });
+            transaction
+                .reverse_operations
+                .push(Operation::SetSheetProtections {
+                    sheet_id,
+                    protections: old_protections,
+                });
+
+            transaction.sheet_info.insert(sheet_id);
+        }
+    }
+
     pub(crate) fn execute_duplicate_sheet(
         &mut self,
         transaction: &mut PendingTransaction,
@@ -110,6 +110,11 @@ impl GridController {
                 Operation::DeleteDataTableRows { .. } => Self::handle_execution_operation_result(
                     self.execute_delete_data_table_row(transaction, op),
                 ),
+                Operation::SetDataTableColumnFormula { .. } => {
+                    Self::handle_execution_operation_result(
+                        self.execute_set_data_table_column_formula(transaction, op),
+                    );
+                }
                 Operation::DataTableFirstRowAsHeader { .. } => {
                     Self::handle_execution_operation_result(
                         self.execute_data_table_first_row_as_header(transaction, op),
@@ -158,6 +163,9 @@ impl GridController {
                 }
                 Operation::SetSheetColor { .. } => self.execute_set_sheet_color(transaction, op),
                 Operation::DuplicateSheet { .. } => self.execute_duplicate_sheet(transaction, op),
+                Operation::SetSheetProtections { .. } => {
+                    self.execute_set_sheet_protections(transaction, op);
+                }
 
                 Operation::ResizeColumn { .. } => self.execute_resize_column(transaction, op),
                 Operation::ResizeRow { .. } => self.execute_resize_row(transaction, op),
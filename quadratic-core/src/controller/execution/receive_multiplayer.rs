@@ -10,6 +10,7 @@ use crate::controller::active_transactions::transaction_name::TransactionName;
 use crate::controller::active_transactions::unsaved_transactions::UnsavedTransaction;
 use crate::controller::operations::operation::Operation;
 use crate::controller::transaction::{Transaction, TransactionServer};
+use crate::grid::js_types::JsSnackbarSeverity;
 
 // seconds to wait before requesting wait_for_transactions
 const SECONDS_TO_WAIT_FOR_GET_TRANSACTIONS: i64 = 5;
@@ -132,11 +133,19 @@ impl GridController {
 
         // combines all out of order transactions into a single vec of operations
         let mut operations = VecDeque::new();
+        let mut other_operations = vec![];
+        let unsaved_transactions = &self.transactions.unsaved_transactions;
+        let undo_sequence_num = self.transactions.undo_sequence_num;
         self.transactions.out_of_order_transactions.retain(|t| {
             // while the out of order transaction is next in sequence, we apply it and remove it from the list
             if let Some(transaction_sequence_num) = t.sequence_num {
                 if transaction_sequence_num == sequence_num + 1 {
                     operations.extend(t.operations.clone());
+                    if transaction_sequence_num > undo_sequence_num
+                        && unsaved_transactions.find_index(t.id).is_none()
+                    {
+                        other_operations.extend(t.operations.iter().cloned());
+                    }
                     sequence_num += 1;
                     false
                 } else {
@@ -147,6 +156,7 @@ impl GridController {
                 true
             }
         });
+        self.transform_undo_history(sequence_num, &other_operations);
         let mut out_of_order_transaction = PendingTransaction {
             source: TransactionSource::Multiplayer,
             operations,
@@ -185,12 +195,18 @@ impl GridController {
                 }
             } else {
                 // If the transaction is not one of ours, then we just apply the transaction after rolling back any unsaved transactions
+                self.transform_undo_history(sequence_num, &transaction.operations);
                 self.rollback_unsaved_transactions();
                 self.start_transaction(&mut transaction);
                 self.finalize_transaction(transaction);
                 self.apply_out_of_order_transactions(sequence_num);
                 self.reapply_unsaved_transactions();
             }
+
+            // the saved undo history must be as new as the transactions it
+            // includes (eg, our acknowledged transaction), or a checkpoint with
+            // them would discard it
+            self.undo_history_changed();
         } else if sequence_num > self.transactions.last_sequence_num {
             // If we receive an unexpected later transaction then we just hold on to it in a sorted list.
             // We could apply these transactions as they come in, but only if multiplayer also sent all undo
@@ -278,6 +294,35 @@ impl GridController {
             self.finalize_transaction(transaction);
         }
     }
+
+    /// Called when the server rejects one of our transactions (eg, because it
+    /// edits a protected range). The transaction is rolled back and removed
+    /// from the undo history, and the message is shown to the user.
+    pub fn rejected_transaction(&mut self, transaction_id: Uuid, message: String) {
+        let Some(index) = self
+            .transactions
+            .unsaved_transactions
+            .find_index(transaction_id)
+        else {
+            return;
+        };
+
+        self.rollback_unsaved_transactions();
+        self.transactions.unsaved_transactions.remove(index);
+        self.undo_stack
+            .retain(|transaction| transaction.id != transaction_id);
+        self.redo_stack
+            .retain(|transaction| transaction.id != transaction_id);
+        self.reapply_unsaved_transactions();
+        self.undo_history_changed();
+
+        if cfg!(target_family = "wasm") || cfg!(test) {
+            crate::wasm_bindings::js::jsClientMessage(
+                message,
+                JsSnackbarSeverity::Error.to_string(),
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -609,14 +654,14 @@ mod tests {
             Some(CellValue::Text("Client unsaved value".to_string()))
         );
 
-        // We undo our old unsaved transaction and it will clear it (since we don't update our undo stack w/server changes).
+        // We undo our old unsaved transaction, which restores the value from the transactions that were ordered before it.
         client.undo(None);
         assert_eq!(
             client
                 .try_sheet(sheet_id)
                 .unwrap()
                 .display_value(Pos { x: 0, y: 0 }),
-            None
+            Some(CellValue::Text("This is sequence_num = 2".to_string()))
         );
     }
 
@@ -1114,4 +1159,122 @@ mod tests {
             Some(CellValue::Number(BigDecimal::from(3)))
         );
     }
+
+    /// Sets a cell value and receives the transaction back from the server.
+    fn set_cell_value_saved(gc: &mut GridController, sheet_pos: SheetPos, value: &str) {
+        gc.set_cell_value(sheet_pos, value.to_string(), None);
+        let transaction = gc.last_transaction().unwrap().clone();
+        let sequence_num = gc.transactions.last_sequence_num + 1;
+        gc.received_transaction(transaction.id, sequence_num, transaction.operations);
+    }
+
+    #[test]
+    fn test_undo_transformed_against_multiplayer_insert_column() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        set_cell_value_saved(&mut gc, pos![sheet_id!A1], "mine");
+
+        // another user inserts a column before our cell
+        gc.received_transaction(
+            Uuid::new_v4(),
+            2,
+            vec![Operation::InsertColumn {
+                sheet_id,
+                column: 1,
+                copy_formats: Default::default(),
+            }],
+        );
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![B1]),
+            Some(CellValue::Text("mine".into()))
+        );
+
+        gc.undo(None);
+        assert_eq!(gc.sheet(sheet_id).display_value(pos![B1]), None);
+
+        // the redo also targets the moved cell
+        gc.redo(None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![B1]),
+            Some(CellValue::Text("mine".into()))
+        );
+        assert_eq!(gc.sheet(sheet_id).display_value(pos![A1]), None);
+    }
+
+    #[test]
+    fn test_undo_keeps_multiplayer_changes() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        set_cell_value_saved(&mut gc, pos![sheet_id!A1], "mine");
+        set_cell_value_saved(&mut gc, pos![sheet_id!A2], "also mine");
+
+        // another user changes our first cell
+        gc.received_transaction(
+            Uuid::new_v4(),
+            3,
+            vec![Operation::SetCellValues {
+                sheet_pos: pos![sheet_id!A1],
+                values: CellValue::Text("theirs".into()).into(),
+            }],
+        );
+
+        // the undo for A1 no longer has anything to do, so it's removed
+        gc.undo(None);
+        assert_eq!(gc.sheet(sheet_id).display_value(pos![A2]), None);
+        assert!(!gc.has_undo());
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![A1]),
+            Some(CellValue::Text("theirs".into()))
+        );
+    }
+
+    #[test]
+    fn test_undo_transformed_against_multiplayer_delete_sheet() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.add_sheet(None);
+        let other_sheet_id = gc.sheet_ids()[1];
+        let transaction = gc.last_transaction().unwrap().clone();
+        gc.received_transaction(transaction.id, 1, transaction.operations);
+        set_cell_value_saved(&mut gc, pos![other_sheet_id!A1], "mine");
+        assert_eq!(gc.undo_stack().len(), 2);
+
+        gc.received_transaction(
+            Uuid::new_v4(),
+            3,
+            vec![Operation::DeleteSheet {
+                sheet_id: other_sheet_id,
+            }],
+        );
+        assert!(!gc.has_undo());
+        assert_eq!(gc.sheet_ids(), vec![sheet_id]);
+    }
+
+    #[test]
+    fn test_rejected_transaction() {
+        clear_js_calls();
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(pos![sheet_id!A1], "rejected".to_string(), None);
+        let rejected = gc.last_transaction().unwrap().id;
+        gc.set_cell_value(pos![sheet_id!A2], "kept".to_string(), None);
+
+        gc.rejected_transaction(rejected, "This range is locked.".to_string());
+        expect_js_call(
+            "jsClientMessage",
+            format!("This range is locked.,{}", JsSnackbarSeverity::Error),
+            true,
+        );
+        assert_eq!(gc.sheet(sheet_id).display_value(pos![A1]), None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![A2]),
+            Some(CellValue::Text("kept".into()))
+        );
+        assert_eq!(gc.transactions.unsaved_transactions.len(), 1);
+
+        // only the kept transaction can be undone
+        gc.undo(None);
+        assert_eq!(gc.sheet(sheet_id).display_value(pos![A2]), None);
+        assert!(!gc.has_undo());
+    }
 }
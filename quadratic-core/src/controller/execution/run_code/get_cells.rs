@@ -2,8 +2,11 @@ use ts_rs::TS;
 use uuid::Uuid;
 
 use crate::{
-    CellValue, a1::CellRefRange, controller::GridController, error_core::CoreError,
-    grid::CodeCellLanguage,
+    CellValue,
+    a1::CellRefRange,
+    controller::GridController,
+    error_core::CoreError,
+    grid::{CodeCellLanguage, column_type::DataTableColumnType},
 };
 use serde::{Deserialize, Serialize};
 
@@ -23,6 +26,10 @@ pub struct JsCellsA1Values {
     pub one_dimensional: bool,
     pub two_dimensional: bool,
     pub has_headers: bool,
+
+    // data table column types for each column in the rect, empty if none of
+    // the columns are typed
+    pub column_types: Vec<Option<DataTableColumnType>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TS)]
@@ -161,6 +168,7 @@ impl GridController {
                 one_dimensional: selection.is_col_range(),
                 two_dimensional,
                 has_headers: selection.has_table_headers(context, is_python),
+                column_types: selection_sheet.column_types_in_rect(*rect),
             }
         } else {
             JsCellsA1Values {
@@ -172,6 +180,7 @@ impl GridController {
                 one_dimensional: false,
                 two_dimensional: false,
                 has_headers: false,
+                column_types: vec![],
             }
         };
 
@@ -359,6 +368,7 @@ mod test {
                     one_dimensional: false,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -464,6 +474,7 @@ mod test {
                     one_dimensional: true,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -510,6 +521,7 @@ mod test {
                     one_dimensional: false,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -609,6 +621,7 @@ mod test {
                     one_dimensional: false,
                     two_dimensional: true,
                     has_headers: true,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -679,6 +692,7 @@ mod test {
                     one_dimensional: false,
                     two_dimensional: true,
                     has_headers: true,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -7,10 +7,12 @@ use crate::grid::{CodeCellLanguage, CodeRun, DataTable, DataTableKind, unique_da
 use crate::{Array, CellValue, Pos, RunError, RunErrorMsg, SheetPos, SheetRect, Span, Value};
 
 pub mod get_cells;
+pub mod run_calculated_columns;
 pub mod run_connection;
 pub mod run_formula;
 pub mod run_javascript;
 pub mod run_python;
+pub mod run_sql;
 
 // this should be kept in sync with HtmlCell.ts and aiToolsSpec.ts
 const DEFAULT_HTML_WIDTH: f32 = 600.0;
@@ -355,6 +357,7 @@ impl GridController {
             CodeCellLanguage::Formula => "Formula1",
             CodeCellLanguage::Javascript => "JavaScript1",
             CodeCellLanguage::Python => "Python1",
+            CodeCellLanguage::Sql => "SQL1",
             _ => "Table1",
         };
         let new_data_table = DataTable::new(
@@ -386,6 +389,7 @@ impl GridController {
             CodeCellLanguage::Formula => "Formula1",
             CodeCellLanguage::Javascript => "JavaScript1",
             CodeCellLanguage::Python => "Python1",
+            CodeCellLanguage::Sql => "SQL1",
             _ => "Table1",
         };
 
@@ -0,0 +1,293 @@
+use anyhow::Result;
+
+use crate::{
+    CellValue, CodeResult, Pos, SheetPos,
+    controller::{GridController, active_transactions::pending_transaction::PendingTransaction},
+    formulas::{Ctx, Formula, parse_formula},
+    grid::{CellsAccessed, SheetId, column_type::DataTableColumnType},
+};
+
+impl GridController {
+    /// Recomputes all calculated columns of a data table.
+    ///
+    /// Each formula is evaluated once per row, at the grid position of the
+    /// row's cell, so this-row references resolve to the same (display) row.
+    /// Columns are computed left to right, so a calculated column may
+    /// reference a calculated column to its left.
+    ///
+    /// The cells read by the formulas are stored with the data table, so
+    /// changes to cells outside of the table also recompute its columns.
+    pub(crate) fn recompute_calculated_columns(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        sheet_id: SheetId,
+        data_table_pos: Pos,
+    ) -> Result<()> {
+        let sheet = self.try_sheet_result(sheet_id)?;
+        let data_table = sheet.data_table_result(data_table_pos)?;
+        let calculated_columns = data_table.calculated_columns();
+        if calculated_columns.is_empty() {
+            if !data_table.calculated_cells_accessed.cells.is_empty() {
+                let sheet = self.try_sheet_mut_result(sheet_id)?;
+                sheet
+                    .data_table_mut(data_table_pos)?
+                    .calculated_cells_accessed = Default::default();
+            }
+            return Ok(());
+        }
+
+        let mut cells_accessed = CellsAccessed::default();
+        for (column_index, formula) in calculated_columns {
+            let sheet = self.try_sheet_result(sheet_id)?;
+            let data_table = sheet.data_table_result(data_table_pos)?;
+
+            let x = data_table_pos.x
+                + data_table.get_display_index_from_column_index(column_index as u32, false);
+            let y_start = data_table_pos.y + data_table.y_adjustment(true);
+            let first_row = data_table.adjust_for_header(0) as u64;
+            let height = data_table.value_as_array()?.height() as u64;
+
+            // the formula is parsed once, at the first row, and its this-row
+            // references are moved to each following row
+            let mut parsed: Option<(i64, CodeResult<Formula>)> = None;
+
+            let values = (first_row..height)
+                .map(|row_index| {
+                    let display_index = data_table.get_display_index_from_row_index(row_index);
+                    let sheet_pos = SheetPos {
+                        x,
+                        y: y_start + display_index as i64,
+                        sheet_id,
+                    };
+                    let (parsed_y, parsed) = parsed.get_or_insert_with(|| {
+                        let parsed = parse_formula(&formula, self.a1_context(), sheet_pos);
+                        (sheet_pos.y, parsed)
+                    });
+                    let value = match parsed {
+                        Ok(parsed) => {
+                            parsed.translate_this_row_refs(&formula, sheet_pos.y - *parsed_y);
+                            *parsed_y = sheet_pos.y;
+                            self.evaluate_calculated_cell(parsed, sheet_pos, &mut cells_accessed)
+                        }
+                        Err(error) => CellValue::Error(Box::new(error.clone())),
+                    };
+                    (row_index as u32, value)
+                })
+                .collect::<Vec<_>>();
+
+            // calculated columns are typed by their results
+            let column_type = DataTableColumnType::infer(values.iter().map(|(_, v)| v), false);
+
+            let sheet = self.try_sheet_mut_result(sheet_id)?;
+            let data_table = sheet.data_table_mut(data_table_pos)?;
+            let array = data_table.mut_value_as_array()?;
+            for (row_index, value) in values {
+                array.set(column_index as u32, row_index, value)?;
+            }
+            data_table.set_column_type(column_index, column_type);
+        }
+
+        let sheet = self.try_sheet_mut_result(sheet_id)?;
+        let data_table = sheet.data_table_mut(data_table_pos)?;
+        data_table.calculated_cells_accessed = cells_accessed;
+        data_table.check_sort()?;
+
+        transaction.add_code_cell(sheet_id, data_table_pos);
+
+        Ok(())
+    }
+
+    /// Evaluates a calculated column's parsed formula at a position within the
+    /// data table. Errors are returned as error values. The cells read by the
+    /// formula are added to `cells_accessed`.
+    fn evaluate_calculated_cell(
+        &self,
+        parsed: &Formula,
+        sheet_pos: SheetPos,
+        cells_accessed: &mut CellsAccessed,
+    ) -> CellValue {
+        let mut ctx = Ctx::new(self, sheet_pos);
+        let value = match parsed.eval(&mut ctx).into_non_tuple().into_cell_value() {
+            Ok(value) => value.inner,
+            Err(error) => CellValue::Error(Box::new(error)),
+        };
+
+        for (sheet_id, ranges) in ctx.cells_accessed.cells {
+            for range in ranges {
+                cells_accessed.add(sheet_id, range);
+            }
+        }
+
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CellValue, Pos, SheetPos,
+        controller::{
+            operations::operation::Operation, user_actions::import::tests::simple_csv_at,
+        },
+        grid::{
+            file::{export, import},
+            sort::{DataTableSort, SortDirection},
+        },
+    };
+
+    #[test]
+    fn test_calculated_column() {
+        let (mut gc, sheet_id, pos, _) = simple_csv_at(pos![E2]);
+
+        // city, region, country, population
+        gc.data_table_set_column_formula(
+            pos.to_sheet_pos(sheet_id),
+            1,
+            Some("=[@city] & \", \" & [@country]".to_string()),
+            None,
+        );
+
+        // the data starts two rows below the anchor (name and column headers)
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(
+            sheet.display_value(pos![F4]),
+            Some(CellValue::Text("Southborough, United States".into()))
+        );
+        assert_eq!(
+            sheet.data_table(pos).unwrap().column_formula(1),
+            Some("=[@city] & \", \" & [@country]")
+        );
+
+        // edits recompute the column
+        gc.set_cell_value(SheetPos::new(sheet_id, 5, 4), "Boston".to_string(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![F4]),
+            Some(CellValue::Text("Boston, United States".into()))
+        );
+
+        // undo restores the original values
+        gc.undo(None);
+        gc.undo(None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![F4]),
+            Some(CellValue::Text("MA".into()))
+        );
+        assert_eq!(
+            gc.sheet(sheet_id)
+                .data_table(pos)
+                .unwrap()
+                .column_formula(1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_calculated_column_sort() {
+        let (mut gc, sheet_id, pos, _) = simple_csv_at(pos![E2]);
+        let sheet_pos = pos.to_sheet_pos(sheet_id);
+
+        gc.data_table_set_column_formula(sheet_pos, 1, Some("=[@city]".to_string()), None);
+
+        gc.sort_data_table(
+            sheet_pos,
+            Some(vec![DataTableSort {
+                column_index: 0,
+                direction: SortDirection::Descending,
+            }]),
+            None,
+        );
+
+        // every row's calculated value matches its city after sorting
+        let sheet = gc.sheet(sheet_id);
+        for y in 4..14 {
+            assert_eq!(
+                sheet.display_value(Pos { x: 6, y }),
+                sheet.display_value(Pos { x: 5, y })
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculated_column_file_round_trip() {
+        let (mut gc, sheet_id, pos, _) = simple_csv_at(pos![E2]);
+
+        gc.data_table_set_column_formula(
+            pos.to_sheet_pos(sheet_id),
+            1,
+            Some("=[@city]".to_string()),
+            None,
+        );
+
+        let exported = export(gc.grid().clone()).unwrap();
+        let imported = import(exported).unwrap();
+        let data_table = imported.sheets()[0].data_table(pos).unwrap();
+        assert_eq!(data_table.column_formula(1), Some("=[@city]"));
+        assert_eq!(
+            data_table.cell_value_at(1, 2),
+            Some(CellValue::Text("Southborough".into()))
+        );
+    }
+
+    #[test]
+    fn test_calculated_column_outside_reference() {
+        let (mut gc, sheet_id, pos, _) = simple_csv_at(pos![E2]);
+        gc.set_cell_value(pos![sheet_id!B1], "2".to_string(), None);
+
+        gc.data_table_set_column_formula(
+            pos.to_sheet_pos(sheet_id),
+            1,
+            Some("=[@population] * B1".to_string()),
+            None,
+        );
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![F4]),
+            Some(CellValue::Number(19372.into()))
+        );
+
+        // editing the referenced cell recomputes the column
+        gc.set_cell_value(pos![sheet_id!B1], "3".to_string(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![F4]),
+            Some(CellValue::Number(29058.into()))
+        );
+
+        // only the formula is sent to recompute the table, not its values
+        let undo = gc.undo_stack().last().unwrap();
+        assert!(undo.operations.iter().any(|op| matches!(
+            op,
+            Operation::SetDataTableColumnFormula { values: None, .. }
+        )));
+        assert!(!undo.operations.iter().any(|op| matches!(
+            op,
+            Operation::SetDataTable { .. } | Operation::SetDataTableAt { .. }
+        )));
+
+        // so does undoing the edit
+        gc.undo(None);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(pos![F4]),
+            Some(CellValue::Number(19372.into()))
+        );
+
+        // clearing the formula clears the dependencies
+        gc.data_table_set_column_formula(pos.to_sheet_pos(sheet_id), 1, None, None);
+        let data_table = gc.sheet(sheet_id).data_table(pos).unwrap();
+        assert!(data_table.calculated_cells_accessed.cells.is_empty());
+    }
+
+    #[test]
+    fn test_calculated_column_errors() {
+        let (mut gc, sheet_id, pos, _) = simple_csv_at(pos![E2]);
+
+        gc.data_table_set_column_formula(
+            pos.to_sheet_pos(sheet_id),
+            1,
+            Some("=[@region]".to_string()),
+            None,
+        );
+
+        // a calculated column cannot reference itself
+        let value = gc.sheet(sheet_id).display_value(pos![F4]).unwrap();
+        assert!(matches!(value, CellValue::Error(_)));
+    }
+}
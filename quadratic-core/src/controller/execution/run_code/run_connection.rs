@@ -1,8 +1,11 @@
 use anyhow::Result;
+use bigdecimal::ToPrimitive;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    RunError, RunErrorMsg, SheetPos,
+    CellValue, RunError, RunErrorMsg, SheetPos,
     a1::{A1Error, A1Selection},
     controller::{GridController, active_transactions::pending_transaction::PendingTransaction},
     grid::{CodeCellLanguage, CodeCellValue, ConnectionKind, SheetId},
@@ -15,59 +18,404 @@ lazy_static! {
         Regex::new(r#"\{\{(.*?)\}\}"#).expect("Failed to compile regex");
 }
 
+/// Maximum number of cells that handlebars may bind into a single query (SQL
+/// Server allows at most 2100 parameters).
+const MAX_SQL_PARAMETERS: usize = 2000;
+
+/// A typed value bound to a query parameter in place of a handlebars
+/// reference. This must match `SqlParameter` in quadratic-rust-shared.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "value")]
+pub enum SqlParameter {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+}
+
+impl From<CellValue> for SqlParameter {
+    fn from(value: CellValue) -> Self {
+        match value {
+            CellValue::Blank => SqlParameter::Null,
+            CellValue::Logical(b) => SqlParameter::Boolean(b),
+            CellValue::Number(n) => match n.is_integer().then(|| n.to_i64()).flatten() {
+                Some(n) => SqlParameter::Integer(n),
+                None => SqlParameter::Float(n.to_f64().unwrap_or_default()),
+            },
+            CellValue::Text(s) => SqlParameter::Text(s),
+            CellValue::Date(d) => SqlParameter::Date(d),
+            CellValue::Time(t) => SqlParameter::Time(t),
+            CellValue::DateTime(dt) => SqlParameter::DateTime(dt),
+            other => SqlParameter::Text(other.to_display()),
+        }
+    }
+}
+
+/// Returns the driver's placeholder for the 1-based parameter `index`.
+/// ClickHouse's placeholders are named and typed, eg, `{p1:Int64}`, and HTTP
+/// requests format parameters by where they're placed, eg, `{p1}`.
+fn sql_placeholder(kind: ConnectionKind, index: usize, param: &SqlParameter) -> String {
+    match kind {
+        ConnectionKind::Postgres => format!("${index}"),
+        ConnectionKind::Mssql => format!("@P{index}"),
+        ConnectionKind::ClickHouse => {
+            let data_type = match param {
+                SqlParameter::Null => "Nullable(String)",
+                SqlParameter::Boolean(_) => "Bool",
+                SqlParameter::Integer(_) => "Int64",
+                SqlParameter::Float(_) => "Float64",
+                SqlParameter::Text(_) | SqlParameter::Time(_) => "String",
+                SqlParameter::Date(_) => "Date",
+                SqlParameter::DateTime(_) => "DateTime64(6)",
+            };
+            format!("{{p{index}:{data_type}}}")
+        }
+        ConnectionKind::Http => format!("{{p{index}}}"),
+        ConnectionKind::Mysql
+        | ConnectionKind::Snowflake
+        | ConnectionKind::Sqlite
+        | ConnectionKind::DuckDb => "?".to_string(),
+    }
+}
+
+/// What the scanned part of a SQL query is in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SqlContext {
+    Code,
+    /// A string literal opened at a byte offset, closed by a quote
+    String {
+        opened_at: usize,
+        quote: char,
+    },
+    /// A dollar-quoted string (eg, `$$...$$` or `$tag$...$tag$`), closed by
+    /// the `len` bytes of its opening delimiter at `opened_at`
+    DollarQuoted {
+        opened_at: usize,
+        len: usize,
+    },
+    /// A quoted identifier, closed by a quote (eg, `"`, `` ` `` or `]`)
+    QuotedIdentifier(char),
+    LineComment,
+    BlockComment,
+}
+
+/// Keywords that are followed by a table, alias or type, rather than a value
+const IDENTIFIER_KEYWORDS: [&str; 6] = ["FROM", "JOIN", "INTO", "UPDATE", "TABLE", "AS"];
+
+/// Tracks whether a SQL query is in a string, quoted identifier or comment,
+/// so handlebars can be checked for where they're placed.
+struct SqlScanner {
+    kind: ConnectionKind,
+    context: SqlContext,
+}
+
+impl SqlScanner {
+    fn new(kind: ConnectionKind) -> Self {
+        Self {
+            kind,
+            context: SqlContext::Code,
+        }
+    }
+
+    /// Whether string literals escape characters with backslashes
+    fn backslash_escapes(&self) -> bool {
+        matches!(
+            self.kind,
+            ConnectionKind::Mysql | ConnectionKind::ClickHouse | ConnectionKind::Snowflake
+        )
+    }
+
+    /// The length of the dollar-quote delimiter at the start of `code`, if
+    /// any (eg, `$$` or `$tag$`). Snowflake only has `$$`.
+    fn dollar_quote_len(&self, code: &str) -> Option<usize> {
+        let tags = match self.kind {
+            ConnectionKind::Postgres | ConnectionKind::DuckDb => true,
+            ConnectionKind::Snowflake => false,
+            _ => return None,
+        };
+        let tag_len = code[1..].find('$')?;
+        let tag = &code[1..1 + tag_len];
+        let is_tag = tag.is_empty()
+            || (tags
+                && !tag.starts_with(|c: char| c.is_ascii_digit())
+                && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+
+        is_tag.then_some(tag_len + 2)
+    }
+
+    /// Scans `code[from..to]`, continuing from the current context
+    fn scan(&mut self, code: &str, from: usize, to: usize) {
+        let backslash_escapes = self.backslash_escapes();
+        let mut chars = code[from..to].char_indices().peekable();
+        let mut prev = code[..from].chars().next_back();
+
+        while let Some((i, c)) = chars.next() {
+            let next = chars.peek().map(|(_, c)| *c);
+            let is_word = prev.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            prev = Some(c);
+            self.context = match (self.context, c) {
+                // `$` within a word is part of an identifier (eg, `a$b`)
+                (SqlContext::Code, '$') if !is_word => {
+                    match self.dollar_quote_len(&code[from + i..to]) {
+                        Some(len) => {
+                            // the delimiter is ascii, so it's one byte a char
+                            chars.nth(len - 2);
+                            SqlContext::DollarQuoted {
+                                opened_at: from + i,
+                                len,
+                            }
+                        }
+                        None => self.context,
+                    }
+                }
+                (SqlContext::Code, '\'') => SqlContext::String {
+                    opened_at: from + i,
+                    quote: c,
+                },
+                (SqlContext::Code, '"') if self.kind == ConnectionKind::Mysql => {
+                    SqlContext::String {
+                        opened_at: from + i,
+                        quote: c,
+                    }
+                }
+                (SqlContext::Code, '"') => SqlContext::QuotedIdentifier('"'),
+                (SqlContext::Code, '`') => SqlContext::QuotedIdentifier('`'),
+                (SqlContext::Code, '[') if self.kind == ConnectionKind::Mssql => {
+                    SqlContext::QuotedIdentifier(']')
+                }
+                (SqlContext::Code, '-') if next == Some('-') => {
+                    chars.next();
+                    SqlContext::LineComment
+                }
+                (SqlContext::Code, '/') if next == Some('*') => {
+                    chars.next();
+                    SqlContext::BlockComment
+                }
+                (SqlContext::String { .. }, '\\') if backslash_escapes => {
+                    chars.next();
+                    self.context
+                }
+                // doubled quotes are escaped quotes
+                (SqlContext::String { quote, .. }, c) if c == quote && next == Some(quote) => {
+                    chars.next();
+                    self.context
+                }
+                (SqlContext::String { quote, .. }, c) if c == quote => SqlContext::Code,
+                (SqlContext::DollarQuoted { opened_at, len }, '$')
+                    if code[from + i..to].starts_with(&code[opened_at..opened_at + len]) =>
+                {
+                    chars.nth(len - 2);
+                    SqlContext::Code
+                }
+                (SqlContext::QuotedIdentifier(quote), c) if c == quote => SqlContext::Code,
+                (SqlContext::LineComment, '\n') => SqlContext::Code,
+                (SqlContext::BlockComment, '*') if next == Some('/') => {
+                    chars.next();
+                    SqlContext::Code
+                }
+                (context, _) => context,
+            };
+        }
+    }
+}
+
+/// Whether unquoted handlebars name a table, column, alias or type, which
+/// can't be bound as a value (eg, `FROM {{A1}}` or `schema.{{A1}}`).
+fn is_identifier_position(before: &str, after: &str) -> bool {
+    let before = before.trim_end();
+    if before.ends_with('.') || after.trim_start().starts_with('.') {
+        return true;
+    }
+
+    let word = before
+        .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or_default();
+    IDENTIFIER_KEYWORDS
+        .iter()
+        .any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
 impl GridController {
-    /// Attempts to replace handlebars with the actual value from the grid
-    fn replace_handlebars(
+    /// Replaces handlebars with the connection's bind parameters and returns
+    /// the values of the referenced cells. A range (eg, `{{A1:A5}}`) expands
+    /// into a comma-separated list of parameters for use in `IN (...)`.
+    ///
+    /// A string literal that is only handlebars (eg, `'{{A1}}'`) loses its
+    /// quotes, since the value is now bound as text. Parameters can't be
+    /// bound elsewhere in a string literal (eg, `'%{{A1}}%'`) or in a
+    /// dollar-quoted body, so there the cell's value is substituted as
+    /// escaped text, as it was before handlebars were bound. Handlebars in a
+    /// quoted identifier or in place of a table or column name are an error.
+    /// Handlebars in comments are left as they are.
+    fn parameterize_handlebars(
         &self,
         transaction: &mut PendingTransaction,
-
-        // todo: do we need this?
-        _sheet_pos: SheetPos,
-
         code: &str,
+        kind: ConnectionKind,
         default_sheet_id: SheetId,
-    ) -> Result<String, A1Error> {
+    ) -> Result<(String, Vec<SqlParameter>), String> {
         let mut result = String::new();
+        let mut params = vec![];
         let mut last_match_end = 0;
 
+        // HTTP requests aren't SQL, so only the quotes around handlebars
+        // are checked
+        let is_sql = kind != ConnectionKind::Http;
+        let mut scanner = SqlScanner::new(kind);
+
         let context = self.a1_context();
         for cap in HANDLEBARS_REGEX.captures_iter(code) {
             let Some(whole_match) = cap.get(0) else {
                 continue;
             };
 
-            result.push_str(&code[last_match_end..whole_match.start()]);
+            let (mut start, mut end) = (whole_match.start(), whole_match.end());
+            let quoted = code[..start].ends_with('\'') && code[end..].starts_with('\'');
+
+            if is_sql {
+                scanner.scan(code, last_match_end, start);
+
+                let sql_context = scanner.context;
+                match sql_context {
+                    SqlContext::Code => {
+                        if is_identifier_position(&code[..start], &code[end..]) {
+                            return Err(format!(
+                                "{} is used as a table or column name. Handlebars are bound as values, so they can't name tables, columns or types",
+                                whole_match.as_str()
+                            ));
+                        }
+                    }
+                    SqlContext::String { opened_at, .. } if quoted && opened_at + 1 == start => {
+                        // the literal is closed by the quote after the handlebars
+                        scanner.context = SqlContext::Code;
+                        start -= 1;
+                        end += 1;
+                    }
+                    SqlContext::String { quote, .. } => {
+                        let mut text = self.handlebars_text(
+                            transaction,
+                            cap.get(1).map_or("", |m| m.as_str()),
+                            default_sheet_id,
+                        )?;
+                        if scanner.backslash_escapes() {
+                            text = text.replace('\\', "\\\\");
+                        }
+                        result.push_str(&code[last_match_end..start]);
+                        result.push_str(&text.replace(quote, &format!("{quote}{quote}")));
+                        last_match_end = end;
+                        continue;
+                    }
+                    SqlContext::DollarQuoted { opened_at, len } => {
+                        let text = self.handlebars_text(
+                            transaction,
+                            cap.get(1).map_or("", |m| m.as_str()),
+                            default_sheet_id,
+                        )?;
+                        let delimiter = &code[opened_at..opened_at + len];
+                        if text.contains(delimiter) {
+                            return Err(format!(
+                                "The value of {} contains {delimiter}, which would end the quoted string",
+                                whole_match.as_str()
+                            ));
+                        }
+                        result.push_str(&code[last_match_end..start]);
+                        result.push_str(&text);
+                        last_match_end = end;
+                        continue;
+                    }
+                    SqlContext::QuotedIdentifier(_) => {
+                        return Err(format!(
+                            "{} is inside a quoted identifier. Handlebars are bound as values, so they can't name tables, columns or types",
+                            whole_match.as_str()
+                        ));
+                    }
+                    SqlContext::LineComment | SqlContext::BlockComment => {
+                        result.push_str(&code[last_match_end..end]);
+                        last_match_end = end;
+                        continue;
+                    }
+                }
+            } else if quoted {
+                start -= 1;
+                end += 1;
+            }
 
-            let content = cap.get(1).map(|m| m.as_str().trim()).unwrap_or("");
-            let selection = A1Selection::parse_a1(content, default_sheet_id, context)?;
+            result.push_str(&code[last_match_end..start]);
 
-            let Some(pos) = selection.try_to_pos(context) else {
-                return Err(A1Error::WrongCellCount(
-                    "Connections only supports one cell".to_string(),
-                ));
-            };
+            let content = cap.get(1).map(|m| m.as_str().trim()).unwrap_or("");
+            let selection = A1Selection::parse_a1(content, default_sheet_id, context)
+                .map_err(|e| e.to_string())?;
 
             let Some(sheet) = self.try_sheet(selection.sheet_id) else {
-                return Err(A1Error::SheetNotFound);
+                return Err(A1Error::SheetNotFound.to_string());
             };
 
-            let value = sheet
-                .display_value(pos)
-                .map(|value| value.to_display())
-                .unwrap_or_default();
+            let mut placeholders = vec![];
+            for rect in sheet.selection_to_rects(&selection, false, false, context) {
+                for pos in rect.iter() {
+                    if params.len() >= MAX_SQL_PARAMETERS {
+                        return Err(A1Error::WrongCellCount(format!(
+                            "Connections support at most {MAX_SQL_PARAMETERS} referenced cells"
+                        ))
+                        .to_string());
+                    }
+
+                    let param = SqlParameter::from(sheet.display_value(pos).unwrap_or_default());
+                    placeholders.push(sql_placeholder(kind, params.len() + 1, &param));
+                    params.push(param);
+                }
+            }
 
-            transaction
-                .cells_accessed
-                .add_sheet_pos(SheetPos::new(sheet.id, pos.x, pos.y));
-            result.push_str(&value);
+            for range in selection.ranges.iter() {
+                transaction.cells_accessed.add(sheet.id, range.clone());
+            }
+            result.push_str(&placeholders.join(", "));
 
-            last_match_end = whole_match.end();
+            last_match_end = end;
         }
 
         // Add the remaining part of the string
         result.push_str(&code[last_match_end..]);
 
-        Ok(result)
+        Ok((result, params))
+    }
+
+    /// Returns the display value of the single cell referenced by the
+    /// handlebars `content`, to substitute into a string literal as text
+    fn handlebars_text(
+        &self,
+        transaction: &mut PendingTransaction,
+        content: &str,
+        default_sheet_id: SheetId,
+    ) -> Result<String, String> {
+        let context = self.a1_context();
+        let selection = A1Selection::parse_a1(content.trim(), default_sheet_id, context)
+            .map_err(|e| e.to_string())?;
+
+        let Some(pos) = selection.try_to_pos(context) else {
+            return Err(A1Error::WrongCellCount(
+                "Handlebars inside a string literal may only reference one cell".to_string(),
+            )
+            .to_string());
+        };
+
+        let Some(sheet) = self.try_sheet(selection.sheet_id) else {
+            return Err(A1Error::SheetNotFound.to_string());
+        };
+
+        transaction
+            .cells_accessed
+            .add_sheet_pos(SheetPos::new(sheet.id, pos.x, pos.y));
+
+        Ok(sheet
+            .display_value(pos)
+            .map(|value| value.to_display())
+            .unwrap_or_default())
     }
 
     pub(crate) fn run_connection(
@@ -80,14 +428,15 @@ impl GridController {
     ) {
         // send the request to get the sql data via the connector to the host
         if (cfg!(target_family = "wasm") || cfg!(test)) && !transaction.is_server() {
-            match self.replace_handlebars(transaction, sheet_pos, &code, sheet_pos.sheet_id) {
-                Ok(replaced_code) => {
+            match self.parameterize_handlebars(transaction, &code, kind, sheet_pos.sheet_id) {
+                Ok((query, params)) => {
                     crate::wasm_bindings::js::jsConnection(
                         transaction.id.to_string(),
                         sheet_pos.x as i32,
                         sheet_pos.y as i32,
                         sheet_pos.sheet_id.to_string(),
-                        replaced_code,
+                        query,
+                        serde_json::to_string(&params).unwrap_or_default(),
                         kind,
                         id.to_owned(),
                     );
@@ -95,7 +444,7 @@ impl GridController {
                 Err(msg) => {
                     let error = RunError {
                         span: None,
-                        msg: RunErrorMsg::CodeRunError(std::borrow::Cow::Owned(msg.to_string())),
+                        msg: RunErrorMsg::CodeRunError(std::borrow::Cow::Owned(msg)),
                     };
                     transaction.current_sheet_pos = Some(sheet_pos);
                     let _ = self.code_cell_sheet_error(transaction, &error);
@@ -128,10 +477,13 @@ mod tests {
             GridController, active_transactions::pending_transaction::PendingTransaction,
         },
         grid::{CodeCellLanguage, ConnectionKind, SheetId},
+        wasm_bindings::js::{clear_js_calls, expect_js_call},
     };
 
+    use super::SqlParameter;
+
     #[test]
-    fn test_replace_handlebars() {
+    fn test_parameterize_handlebars() {
         let mut gc = GridController::test();
         let sheet_id = gc.sheet_ids()[0];
 
@@ -140,17 +492,14 @@ mod tests {
 
         let mut transaction = PendingTransaction::default();
 
-        let sheet_pos = SheetPos {
-            x: 1,
-            y: 1,
-            sheet_id,
-        };
-
         let code = r#"{{$A$2}}"#;
         let result = gc
-            .replace_handlebars(&mut transaction, sheet_pos, code, sheet_id)
+            .parameterize_handlebars(&mut transaction, code, ConnectionKind::Postgres, sheet_id)
             .unwrap();
-        assert_eq!(result, "test".to_string());
+        assert_eq!(
+            result,
+            ("$1".to_string(), vec![SqlParameter::Text("test".into())])
+        );
         assert_eq!(transaction.cells_accessed.len(sheet_id), Some(1));
         assert!(
             transaction
@@ -165,19 +514,22 @@ mod tests {
 
         let code = r#"{{'Sheet 2'!$A$2}}"#;
         let result = gc
-            .replace_handlebars(&mut transaction, sheet_pos, code, sheet_id)
+            .parameterize_handlebars(&mut transaction, code, ConnectionKind::Postgres, sheet_id)
             .unwrap();
-        assert_eq!(result, "test2".to_string());
-        assert_eq!(transaction.cells_accessed.len(sheet_id), Some(1));
+        assert_eq!(
+            result,
+            ("$1".to_string(), vec![SqlParameter::Text("test2".into())])
+        );
+        assert_eq!(transaction.cells_accessed.len(sheet_2_id), Some(1));
         assert!(
             transaction
                 .cells_accessed
-                .contains(SheetPos::new(sheet_id, 1, 2), gc.a1_context())
+                .contains(SheetPos::new(sheet_2_id, 1, 2), gc.a1_context())
         );
     }
 
     #[test]
-    fn test_replace_handlebars_relative() {
+    fn test_parameterize_handlebars_relative() {
         let mut gc = GridController::test();
         let sheet_id = gc.sheet_ids()[0];
 
@@ -186,17 +538,12 @@ mod tests {
 
         let mut transaction = PendingTransaction::default();
 
-        let sheet_pos = SheetPos {
-            x: 1,
-            y: 1,
-            sheet_id,
-        };
-
         let code = r#"{{A2}}"#;
-        let result = gc
-            .replace_handlebars(&mut transaction, sheet_pos, code, sheet_id)
+        let (query, params) = gc
+            .parameterize_handlebars(&mut transaction, code, ConnectionKind::Mysql, sheet_id)
             .unwrap();
-        assert_eq!(result, "test".to_string());
+        assert_eq!(query, "?");
+        assert_eq!(params, vec![SqlParameter::Text("test".into())]);
         assert_eq!(transaction.cells_accessed.len(sheet_id), Some(1));
         let context = gc.a1_context();
         assert!(
@@ -206,11 +553,11 @@ mod tests {
         );
 
         let code = format!(r#"{{{{'{}1'!A2}}}}"#, SHEET_NAME);
-        let result = gc
-            .replace_handlebars(&mut transaction, sheet_pos, &code, sheet_id)
+        let (query, params) = gc
+            .parameterize_handlebars(&mut transaction, &code, ConnectionKind::Mysql, sheet_id)
             .unwrap();
-        assert_eq!(result, "test".to_string());
-        assert_eq!(transaction.cells_accessed.len(sheet_id), Some(1));
+        assert_eq!(query, "?");
+        assert_eq!(params, vec![SqlParameter::Text("test".into())]);
         assert!(
             transaction
                 .cells_accessed
@@ -219,27 +566,300 @@ mod tests {
     }
 
     #[test]
-    fn test_replace_handlebars_actual_case() {
-        let code = "SELECT age FROM 'public'.'test_table' WHERE name='{{A1}}' LIMIT 100";
+    fn test_parameterize_handlebars_actual_case() {
+        let code =
+            "SELECT age FROM 'public'.'test_table' WHERE name='{{A1}}' AND age > {{B1}} LIMIT 100";
         let mut gc = GridController::test();
         let sheet_id = gc.sheet_ids()[0];
 
         let sheet = gc.sheet_mut(sheet_id);
-        sheet.set_cell_value(Pos { x: 1, y: 1 }, "test".to_string());
+        sheet.set_cell_value(Pos { x: 1, y: 1 }, "O'Brien".to_string());
+        sheet.set_cell_value(Pos { x: 2, y: 1 }, crate::CellValue::Number(21.into()));
 
-        let sheet_pos = SheetPos {
-            x: 1,
-            y: 2,
-            sheet_id,
-        };
+        let mut transaction = PendingTransaction::default();
+        let (query, params) = gc
+            .parameterize_handlebars(&mut transaction, code, ConnectionKind::Mssql, sheet_id)
+            .unwrap();
+        assert_eq!(
+            query,
+            "SELECT age FROM 'public'.'test_table' WHERE name=@P1 AND age > @P2 LIMIT 100"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlParameter::Text("O'Brien".into()),
+                SqlParameter::Integer(21)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parameterize_handlebars_range() {
+        let code = "SELECT * FROM users WHERE id IN ({{A1:A3}}) AND active = {{B1}}";
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.set_cell_value(Pos { x: 1, y: 1 }, crate::CellValue::Number(1.into()));
+        sheet.set_cell_value(
+            Pos { x: 1, y: 2 },
+            crate::CellValue::Number("2.5".parse().unwrap()),
+        );
+        sheet.set_cell_value(Pos { x: 2, y: 1 }, crate::CellValue::Logical(true));
 
         let mut transaction = PendingTransaction::default();
-        let result = gc
-            .replace_handlebars(&mut transaction, sheet_pos, code, sheet_id)
+        let (query, params) = gc
+            .parameterize_handlebars(&mut transaction, code, ConnectionKind::Postgres, sheet_id)
             .unwrap();
         assert_eq!(
-            result,
-            "SELECT age FROM 'public'.'test_table' WHERE name='test' LIMIT 100"
+            query,
+            "SELECT * FROM users WHERE id IN ($1, $2, $3) AND active = $4"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlParameter::Integer(1),
+                SqlParameter::Float(2.5),
+                SqlParameter::Null,
+                SqlParameter::Boolean(true),
+            ]
+        );
+        assert!(
+            transaction
+                .cells_accessed
+                .contains(SheetPos::new(sheet_id, 1, 3), gc.a1_context())
+        );
+    }
+
+    #[test]
+    fn test_parameterize_handlebars_clickhouse() {
+        let code = "SELECT * FROM events WHERE name = '{{A1}}' AND id IN ({{B1:B2}})";
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.set_cell_value(Pos { x: 1, y: 1 }, "signup".to_string());
+        sheet.set_cell_value(Pos { x: 2, y: 1 }, crate::CellValue::Number(7.into()));
+
+        let mut transaction = PendingTransaction::default();
+        let (query, params) = gc
+            .parameterize_handlebars(&mut transaction, code, ConnectionKind::ClickHouse, sheet_id)
+            .unwrap();
+        assert_eq!(
+            query,
+            "SELECT * FROM events WHERE name = {p1:String} AND id IN ({p2:Int64}, {p3:Nullable(String)})"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlParameter::Text("signup".into()),
+                SqlParameter::Integer(7),
+                SqlParameter::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parameterize_handlebars_http() {
+        let code = "GET /events?name={{A1}}#data\n\n{\"ids\": [{{B1:B2}}]}";
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.set_cell_value(Pos { x: 1, y: 1 }, "signup".to_string());
+        sheet.set_cell_value(Pos { x: 2, y: 1 }, crate::CellValue::Number(7.into()));
+
+        let mut transaction = PendingTransaction::default();
+        let (query, params) = gc
+            .parameterize_handlebars(&mut transaction, code, ConnectionKind::Http, sheet_id)
+            .unwrap();
+        assert_eq!(
+            query,
+            "GET /events?name={p1}#data\n\n{\"ids\": [{p2}, {p3}]}"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlParameter::Text("signup".into()),
+                SqlParameter::Integer(7),
+                SqlParameter::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parameterize_handlebars_in_string_literals() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.set_cell_value(Pos { x: 1, y: 1 }, "test".to_string());
+        sheet.set_cell_value(Pos { x: 1, y: 2 }, r#"it's \ "quoted""#.to_string());
+        let mut transaction = PendingTransaction::default();
+        let mut parameterize =
+            |code: &str, kind| gc.parameterize_handlebars(&mut transaction, code, kind, sheet_id);
+
+        // existing queries substitute the value as text inside literals
+        for (code, expected) in [
+            (
+                "SELECT * FROM users WHERE name LIKE '%{{A1}}%'",
+                "SELECT * FROM users WHERE name LIKE '%test%'",
+            ),
+            (
+                "SELECT * FROM users WHERE name = 'Mr {{A1}}'",
+                "SELECT * FROM users WHERE name = 'Mr test'",
+            ),
+            (
+                "SELECT * FROM users WHERE name = 'it''s {{A1}}'",
+                "SELECT * FROM users WHERE name = 'it''s test'",
+            ),
+            (
+                "SELECT * FROM users WHERE name = '''{{A1}}'''",
+                "SELECT * FROM users WHERE name = '''test'''",
+            ),
+            (
+                "SELECT * FROM users WHERE name LIKE '%{{A2}}%'",
+                r#"SELECT * FROM users WHERE name LIKE '%it''s \ "quoted"%'"#,
+            ),
+        ] {
+            let (query, params) = parameterize(code, ConnectionKind::Postgres).unwrap();
+            assert_eq!(query, expected);
+            assert!(params.is_empty());
+        }
+
+        // only one cell can be substituted
+        let code = "SELECT * FROM users WHERE name LIKE '%{{A1:A2}}%'";
+        let error = parameterize(code, ConnectionKind::Postgres).unwrap_err();
+        assert!(error.contains("may only reference one cell"));
+
+        // MySQL strings may be double quoted and escape quotes with backslashes
+        let code = r#"SELECT * FROM users WHERE name LIKE "%{{A2}}%""#;
+        let (query, _) = parameterize(code, ConnectionKind::Mysql).unwrap();
+        assert_eq!(
+            query,
+            r#"SELECT * FROM users WHERE name LIKE "%it's \\ ""quoted""%""#
+        );
+        let code = r"SELECT * FROM users WHERE name = 'it\'s' AND id = {{A1}}";
+        let (query, _) = parameterize(code, ConnectionKind::Mysql).unwrap();
+        assert_eq!(
+            query,
+            r"SELECT * FROM users WHERE name = 'it\'s' AND id = ?"
+        );
+
+        // quotes in other literals and comments don't affect handlebars
+        let code = "SELECT 'it''s' AS a, '{{A1}}' AS b -- '{{A1}}\n/* {{A1}} */ WHERE c = {{A1}}";
+        let (query, params) = parameterize(code, ConnectionKind::Postgres).unwrap();
+        assert_eq!(
+            query,
+            "SELECT 'it''s' AS a, $1 AS b -- '{{A1}}\n/* {{A1}} */ WHERE c = $2"
+        );
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_parameterize_handlebars_in_dollar_quotes() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.set_cell_value(Pos { x: 1, y: 1 }, "test".to_string());
+        sheet.set_cell_value(Pos { x: 1, y: 2 }, "$$".to_string());
+        let mut transaction = PendingTransaction::default();
+        let mut parameterize =
+            |code: &str, kind| gc.parameterize_handlebars(&mut transaction, code, kind, sheet_id);
+
+        // function bodies are strings, so their handlebars are substituted
+        let code = "CREATE FUNCTION f() RETURNS text AS $$ SELECT '{{A1}}' $$ LANGUAGE sql; SELECT f() WHERE a$b = {{A1}}";
+        let (query, params) = parameterize(code, ConnectionKind::Postgres).unwrap();
+        assert_eq!(
+            query,
+            "CREATE FUNCTION f() RETURNS text AS $$ SELECT 'test' $$ LANGUAGE sql; SELECT f() WHERE a$b = $1"
+        );
+        assert_eq!(params, vec![SqlParameter::Text("test".into())]);
+
+        let code = "DO $body$ BEGIN RAISE NOTICE '$$ {{A1}}'; END $body$; SELECT {{A1}}";
+        let (query, params) = parameterize(code, ConnectionKind::Postgres).unwrap();
+        assert_eq!(
+            query,
+            "DO $body$ BEGIN RAISE NOTICE '$$ test'; END $body$; SELECT $1"
+        );
+        assert_eq!(params.len(), 1);
+
+        // placeholders aren't dollar quotes
+        let (query, _) = parameterize("SELECT $1, {{A1}}", ConnectionKind::Postgres).unwrap();
+        assert_eq!(query, "SELECT $1, $1");
+
+        // a value can't close the dollar quote
+        let error = parameterize("SELECT $$ {{A2}} $$", ConnectionKind::Postgres).unwrap_err();
+        assert!(error.contains("would end the quoted string"));
+    }
+
+    #[test]
+    fn test_parameterize_handlebars_as_identifiers() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.sheet_mut(sheet_id)
+            .set_cell_value(Pos { x: 1, y: 1 }, "users".to_string());
+        let mut transaction = PendingTransaction::default();
+        let mut parameterize =
+            |code: &str, kind| gc.parameterize_handlebars(&mut transaction, code, kind, sheet_id);
+
+        for code in [
+            "SELECT * FROM {{A1}}",
+            "SELECT * FROM users JOIN {{A1}} ON true",
+            "INSERT INTO {{A1}} VALUES (1)",
+            "SELECT * FROM public.{{A1}}",
+            "SELECT {{A1}}.id FROM users",
+            "SELECT id AS {{A1}} FROM users",
+        ] {
+            let error = parameterize(code, ConnectionKind::Postgres).unwrap_err();
+            assert!(
+                error.contains("{{A1}} is used as a table or column name"),
+                "{code}"
+            );
+        }
+
+        for (code, kind) in [
+            (r#"SELECT * FROM "{{A1}}""#, ConnectionKind::Postgres),
+            ("SELECT * FROM `{{A1}}`", ConnectionKind::Mysql),
+            ("SELECT * FROM [{{A1}}]", ConnectionKind::Mssql),
+        ] {
+            let error = parameterize(code, kind).unwrap_err();
+            assert!(
+                error.contains("{{A1}} is inside a quoted identifier"),
+                "{code}"
+            );
+        }
+
+        // brackets are only identifiers in SQL Server
+        let (query, _) =
+            parameterize("SELECT tags[{{A1}}] FROM users", ConnectionKind::Postgres).unwrap();
+        assert_eq!(query, "SELECT tags[$1] FROM users");
+    }
+
+    #[test]
+    fn test_run_connection_sends_parameters() {
+        clear_js_calls();
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(pos![sheet_id!A1], "a'b".to_string(), None);
+
+        gc.set_code_cell(
+            pos![sheet_id!B2],
+            CodeCellLanguage::Connection {
+                kind: ConnectionKind::Postgres,
+                id: "test".to_string(),
+            },
+            "SELECT {{A1}}".to_string(),
+            None,
+        );
+
+        let transaction_id = gc.transactions.async_transactions()[0].id;
+        expect_js_call(
+            "jsConnection",
+            format!(
+                "{},2,2,{},SELECT $1,{},Postgres,test",
+                transaction_id, sheet_id, r#"[{"type":"Text","value":"a'b"}]"#
+            ),
+            true,
         );
     }
 
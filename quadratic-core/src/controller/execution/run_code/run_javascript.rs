@@ -149,6 +149,7 @@ mod tests {
                     one_dimensional: false,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -229,6 +230,7 @@ mod tests {
                     one_dimensional: false,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -158,6 +158,7 @@ mod tests {
                     one_dimensional: false,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -240,6 +241,7 @@ mod tests {
                     one_dimensional: false,
                     two_dimensional: false,
                     has_headers: false,
+                    column_types: vec![],
                 }),
                 error: None,
             }
@@ -0,0 +1,228 @@
+use crate::{
+    Array, CellValue, CodeResult, Pos, RunErrorMsg, SheetPos, TableRef, Value,
+    a1::CellRefRange,
+    controller::{GridController, active_transactions::pending_transaction::PendingTransaction},
+    grid::{CodeCellLanguage, CodeRun, DataTable, DataTableKind},
+    sql::{SqlTable, parse_sql},
+};
+
+impl GridController {
+    pub(crate) fn run_sql(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        sheet_pos: SheetPos,
+        code: String,
+    ) {
+        transaction.current_sheet_pos = Some(sheet_pos);
+
+        let result = parse_sql(&code)
+            .and_then(|query| query.eval(|name| self.sql_table(transaction, sheet_pos, name)));
+
+        match result {
+            Ok(table) => {
+                let return_type = format!("{}×{} Array", table.columns.len(), table.rows.len());
+                let new_code_run = CodeRun {
+                    language: CodeCellLanguage::Sql,
+                    code,
+                    std_out: None,
+                    std_err: None,
+                    cells_accessed: std::mem::take(&mut transaction.cells_accessed),
+                    error: None,
+                    return_type: Some(return_type),
+                    line_number: None,
+                    output_type: None,
+                };
+
+                // the column names are the first row
+                let mut rows = vec![table.columns.into_iter().map(CellValue::Text).collect()];
+                rows.extend(table.rows);
+                let new_data_table = DataTable::new(
+                    DataTableKind::CodeRun(new_code_run),
+                    "SQL1",
+                    Value::Array(Array::from(rows)),
+                    false,
+                    true,
+                    None,
+                    None,
+                    None,
+                );
+                self.finalize_data_table(transaction, sheet_pos, Some(new_data_table), None);
+            }
+            Err(error) => {
+                let _ = self.code_cell_sheet_error(transaction, &error);
+            }
+        }
+    }
+
+    /// Reads a data table's visible columns for a SQL query, and adds the
+    /// table to the cells accessed by the code cell.
+    fn sql_table(
+        &self,
+        transaction: &mut PendingTransaction,
+        sheet_pos: SheetPos,
+        name: &str,
+    ) -> CodeResult<SqlTable> {
+        let context = self.a1_context();
+        let Some(table) = context.try_table(name) else {
+            return Err(
+                RunErrorMsg::CodeRunError(format!("Table `{name}` doesn't exist").into()).into(),
+            );
+        };
+
+        if table.sheet_id == sheet_pos.sheet_id && table.bounds.contains(sheet_pos.into()) {
+            return Err(RunErrorMsg::CircularReference.into());
+        }
+        if table.is_html_image {
+            return Err(RunErrorMsg::CodeRunError(
+                format!("Table `{name}` is a chart and has no data").into(),
+            )
+            .into());
+        }
+
+        let range = TableRef::new(&table.table_name);
+        transaction.cells_accessed.add(
+            table.sheet_id,
+            CellRefRange::Table {
+                range: range.clone(),
+            },
+        );
+
+        let Some(sheet) = self.try_sheet(table.sheet_id) else {
+            return Err(RunErrorMsg::CodeRunError("Sheet not found".into()).into());
+        };
+
+        // tables without data have no rect
+        let rows = sheet
+            .table_ref_to_rect(&range, false, false, context)
+            .map(|rect| {
+                rect.y_range()
+                    .map(|y| {
+                        rect.x_range()
+                            .map(|x| sheet.display_value(Pos { x, y }).unwrap_or_default())
+                            .collect()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(SqlTable {
+            columns: table.visible_columns.to_owned(),
+            rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CellValue, RunErrorMsg, SheetPos,
+        a1::{CellRefRange, TableRef},
+        controller::GridController,
+        grid::{CodeCellLanguage, SheetId},
+        test_util::*,
+    };
+
+    fn add_table(gc: &mut GridController, sheet_pos: SheetPos, name: &str, values: Vec<Vec<&str>>) {
+        let values = values
+            .into_iter()
+            .map(|row| row.into_iter().map(String::from).collect())
+            .collect();
+        gc.add_data_table(sheet_pos, name.to_string(), values, true, None);
+    }
+
+    fn set_sql(gc: &mut GridController, sheet_pos: SheetPos, sql: &str) {
+        gc.set_code_cell(sheet_pos, CodeCellLanguage::Sql, sql.to_string(), None);
+    }
+
+    #[test]
+    fn test_run_sql_joins_tables() {
+        let mut gc = GridController::test();
+        let sheet_id = SheetId::TEST;
+        add_table(
+            &mut gc,
+            pos![sheet_id!A1],
+            "Orders",
+            vec![
+                vec!["id", "customer", "total"],
+                vec!["1", "Ada", "30"],
+                vec!["2", "Bob", "5"],
+                vec!["3", "Ada", "12"],
+            ],
+        );
+        add_table(
+            &mut gc,
+            pos![sheet_id!A10],
+            "Customers",
+            vec![
+                vec!["name", "region"],
+                vec!["Ada", "north"],
+                vec!["Bob", "south"],
+            ],
+        );
+
+        let sql_pos = pos![sheet_id!F1];
+        set_sql(
+            &mut gc,
+            sql_pos,
+            "SELECT c.region, SUM(o.total) AS total FROM Orders o \
+             JOIN Customers c ON o.customer = c.name GROUP BY c.region ORDER BY total DESC",
+        );
+
+        let sheet = gc.sheet(sheet_id);
+        let dt = sheet.data_table(sql_pos.into()).unwrap();
+        assert_eq!(dt.name(), "SQL1");
+        assert_eq!(dt.get_error(), None);
+        assert_data_table_row(dt, 0, vec!["region", "total"]);
+        assert_data_table_row(dt, 1, vec!["north", "42"]);
+        assert_data_table_row(dt, 2, vec!["south", "5"]);
+
+        let code_run = dt.code_run().unwrap();
+        assert_eq!(code_run.return_type, Some("2×2 Array".to_string()));
+        for name in ["Orders", "Customers"] {
+            assert!(
+                code_run.cells_accessed.cells[&sheet_id].contains(&CellRefRange::Table {
+                    range: TableRef::new(name),
+                })
+            );
+        }
+
+        // changing a table reruns the query
+        gc.set_cell_value(pos![sheet_id!C5], "100".to_string(), None);
+        let dt = gc.sheet(sheet_id).data_table(sql_pos.into()).unwrap();
+        assert_data_table_row(dt, 1, vec!["north", "130"]);
+    }
+
+    #[test]
+    fn test_run_sql_errors() {
+        let mut gc = GridController::test();
+        let sheet_id = SheetId::TEST;
+        let sql_pos = pos![sheet_id!A1];
+
+        set_sql(&mut gc, sql_pos, "SELECT * FROM Missing");
+        let error = gc
+            .sheet(sheet_id)
+            .data_table(sql_pos.into())
+            .unwrap()
+            .get_error();
+        assert_eq!(
+            error.unwrap().msg,
+            RunErrorMsg::CodeRunError("Table `Missing` doesn't exist".into())
+        );
+
+        set_sql(&mut gc, sql_pos, "SELECT 1 AS one");
+        let sheet = gc.sheet(sheet_id);
+        let name = sheet.data_table(sql_pos.into()).unwrap().name().to_string();
+        assert_eq!(
+            sheet.display_value(pos![A3]),
+            Some(CellValue::Number(1.into()))
+        );
+
+        set_sql(&mut gc, sql_pos, &format!("SELECT * FROM {name}"));
+        let error = gc
+            .sheet(sheet_id)
+            .data_table(sql_pos.into())
+            .unwrap()
+            .get_error();
+        assert_eq!(error.unwrap().msg, RunErrorMsg::CircularReference);
+    }
+}
@@ -4,8 +4,11 @@ use itertools::PeekingNext;
 
 use super::GridController;
 use crate::{
-    Pos,
-    a1::{A1Selection, CellRefRange},
+    CellValue, Pos,
+    a1::{A1Selection, CellRefRange, column_name},
+    arrow::cell_values_to_arrow_ipc,
+    grid::column_type::DataTableColumnType,
+    parquet::vec_to_parquet,
 };
 
 impl GridController {
@@ -50,6 +53,117 @@ impl GridController {
         let output = String::from_utf8(writer.into_inner()?)?;
         Ok(output)
     }
+
+    /// exports a Parquet file from a selection or table on the grid.
+    ///
+    /// Returns the file as bytes.
+    pub fn export_parquet(&self, selection: &mut A1Selection) -> Result<Vec<u8>> {
+        let (headers, columns, column_types) = self.export_selection_columns(selection)?;
+        vec_to_parquet(headers, columns, &column_types)
+    }
+
+    /// exports an Arrow IPC file from a selection or table on the grid.
+    ///
+    /// Returns the file as bytes.
+    pub fn export_arrow_ipc(&self, selection: &mut A1Selection) -> Result<Vec<u8>> {
+        let (headers, columns, column_types) = self.export_selection_columns(selection)?;
+        cell_values_to_arrow_ipc(headers, columns, &column_types)
+    }
+
+    /// Collects the values of a selection as columns for a columnar export.
+    ///
+    /// Column names come from the table's headers when exporting a table, and
+    /// are the column letters otherwise. Unselected cells within the
+    /// selection's bounds are exported as blanks.
+    ///
+    /// Also returns the declared type of each column when exporting a table.
+    #[allow(clippy::type_complexity)]
+    fn export_selection_columns(
+        &self,
+        selection: &mut A1Selection,
+    ) -> Result<(
+        Vec<String>,
+        Vec<Vec<CellValue>>,
+        Vec<Option<DataTableColumnType>>,
+    )> {
+        let sheet = self
+            .grid
+            .try_sheet(selection.sheet_id)
+            .context("Sheet not found")?;
+
+        let is_table = selection.ranges.len() == 1;
+        let table = match selection.ranges.first_mut() {
+            Some(CellRefRange::Table { range }) if is_table => {
+                range.headers = true;
+                self.a1_context
+                    .try_table(&range.table_name)
+                    .and_then(|table| Some((table.bounds.min, sheet.data_table(table.bounds.min)?)))
+            }
+            _ => None,
+        };
+        let is_table = table.is_some();
+
+        let bounds = sheet
+            .selection_bounds(selection, false, false, &self.a1_context)
+            .context("No values")?;
+
+        let values = sheet.selection_sorted_vec(selection, false, &self.a1_context);
+        let mut iter = values.iter();
+        let context = self.a1_context();
+
+        let mut columns = vec![vec![]; bounds.width() as usize];
+        let mut selected_columns = vec![false; bounds.width() as usize];
+        for y in bounds.min.y..=bounds.max.y {
+            for (index, x) in (bounds.min.x..=bounds.max.x).enumerate() {
+                let mut value = CellValue::Blank;
+
+                // we need to ignore unselected columns or rows
+                if selection.might_contain_pos(Pos { x, y }, context) {
+                    selected_columns[index] = true;
+                    if let Some((_, cell_value)) =
+                        iter.peeking_next(|(pos, _)| pos.x == x && pos.y == y)
+                    {
+                        value = (*cell_value).to_owned();
+                    }
+                }
+
+                columns[index].push(value);
+            }
+        }
+
+        let column_type = |x: i64| {
+            let (table_pos, data_table) = table?;
+            let display_index = u32::try_from(x - table_pos.x).ok()?;
+            let column_index = data_table.get_column_index_from_display_index(display_index, true);
+            data_table.column_type(column_index as usize)
+        };
+        let column_types = (bounds.min.x..=bounds.max.x)
+            .zip(&selected_columns)
+            .filter(|(_, selected)| **selected)
+            .map(|(x, _)| column_type(x))
+            .collect();
+
+        let (headers, columns): (Vec<String>, Vec<Vec<CellValue>>) = (bounds.min.x..=bounds.max.x)
+            .zip(columns)
+            .zip(selected_columns)
+            .filter(|(_, selected)| *selected)
+            .map(|((x, mut column), _)| {
+                let header = if is_table && !column.is_empty() {
+                    column.remove(0).to_string()
+                } else {
+                    String::new()
+                };
+                let header = if header.is_empty() {
+                    column_name(x)
+                } else {
+                    header
+                };
+                (header, column)
+            })
+            .unzip();
+
+        Ok((headers, columns, column_types))
+    }
 }
 
 #[cfg(test)]
@@ -92,4 +206,88 @@ mod tests {
         let result = gc.export_csv_selection(&mut selected).unwrap();
         println!("{}", result);
     }
+
+    #[test]
+    fn exports_parquet_from_a_data_table() {
+        let (mut gc, sheet_id, pos, file_name) = simple_csv();
+        let context = gc.a1_context().to_owned();
+        let table_name = gc
+            .sheet(sheet_id)
+            .data_table(pos)
+            .unwrap()
+            .name()
+            .to_string();
+
+        let mut selected = A1Selection::test_a1_context(&table_name, &context);
+        let parquet = gc.export_parquet(&mut selected).unwrap();
+
+        // round trip through the parquet importer
+        let insert_at = pos![J1];
+        gc.import_parquet(sheet_id, parquet, file_name, insert_at, None)
+            .unwrap();
+
+        let sheet = gc.sheet(sheet_id);
+        let original = sheet.data_table(pos).unwrap();
+        let imported = sheet.data_table(insert_at).unwrap();
+        assert_eq!(
+            imported
+                .column_headers
+                .iter()
+                .flatten()
+                .map(|header| header.name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["city", "region", "country", "population"]
+        );
+        assert_eq!(
+            imported.value_as_array().unwrap(),
+            original.value_as_array().unwrap()
+        );
+    }
+
+    #[test]
+    fn exports_arrow_ipc_with_declared_column_types() {
+        let (mut gc, sheet_id, pos, _) = simple_csv();
+        let context = gc.a1_context().to_owned();
+        let data_table = gc.sheet_mut(sheet_id).data_table_mut(pos).unwrap();
+        let table_name = data_table.name().to_string();
+
+        // the declared type wins over the values
+        data_table.set_column_type(3, Some(DataTableColumnType::Text));
+
+        let mut selected = A1Selection::test_a1_context(&table_name, &context);
+        let ipc = gc.export_arrow_ipc(&mut selected).unwrap();
+
+        let reader =
+            arrow_ipc::reader::FileReader::try_new(std::io::Cursor::new(ipc), None).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.field(0).name(), "city");
+        assert_eq!(schema.field(0).data_type(), &arrow_schema::DataType::Utf8);
+        assert_eq!(schema.field(3).name(), "population");
+        assert_eq!(schema.field(3).data_type(), &arrow_schema::DataType::Utf8);
+    }
+
+    #[test]
+    fn exports_arrow_ipc_from_a_selection() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        gc.set_cell_value(pos![sheet_id!A1], "1.5".to_string(), None);
+        gc.set_cell_value(pos![sheet_id!A2], "2".to_string(), None);
+        gc.set_cell_value(pos![sheet_id!B1], "true".to_string(), None);
+        gc.set_cell_value(pos![sheet_id!B2], "hello".to_string(), None);
+
+        let mut selected = A1Selection::test_a1("A1:B2");
+        let ipc = gc.export_arrow_ipc(&mut selected).unwrap();
+
+        let reader =
+            arrow_ipc::reader::FileReader::try_new(std::io::Cursor::new(ipc), None).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.field(0).name(), "A");
+        assert_eq!(
+            schema.field(0).data_type(),
+            &arrow_schema::DataType::Decimal128(2, 1)
+        );
+        assert_eq!(schema.field(1).name(), "B");
+        assert_eq!(schema.field(1).data_type(), &arrow_schema::DataType::Utf8);
+    }
 }
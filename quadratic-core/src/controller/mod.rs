@@ -4,7 +4,7 @@ use self::{active_transactions::ActiveTransactions, transaction::Transaction};
 use crate::{
     Pos,
     a1::{A1Context, TableMapEntry},
-    grid::{Grid, SheetId},
+    grid::{Grid, SheetId, sheet::protection::ProtectionUser},
     util::case_fold_ascii,
     viewport::ViewportBuffer,
 };
@@ -15,6 +15,7 @@ pub mod execution;
 pub mod export;
 pub mod formula;
 pub mod operations;
+pub mod protection;
 pub mod send_render;
 pub mod sheet_offsets;
 pub mod sheets;
@@ -40,6 +41,9 @@ pub struct GridController {
     // the viewport buffer is a shared array buffer that is accessed by the render web worker and the controller
     // contains current viewport position and sheet id, updated by render web worker on viewport change
     viewport_buffer: Option<ViewportBuffer>,
+
+    // the local user, used to check edits against sheet protections
+    protection_user: Option<ProtectionUser>,
 }
 
 impl Default for GridController {
@@ -53,6 +57,7 @@ impl Default for GridController {
             redo_stack: Vec::new(),
             transactions: ActiveTransactions::new(0),
             viewport_buffer: None,
+            protection_user: None,
         }
     }
 }
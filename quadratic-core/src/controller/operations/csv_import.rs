@@ -0,0 +1,364 @@
+//! CSV import options
+//!
+//! Describes the dialect and text encoding of a CSV file, and decodes the file
+//! while it is being read.
+//!
+//! Decoding works on any [`Read`]. The wasm bindings read the file from the
+//! client in chunks, and the imported rows become operations in batches (see
+//! `GridController::import_csv_operations_from_reader`).
+
+use std::{
+    collections::HashMap,
+    io::{Chain, Cursor, Read},
+};
+
+use anyhow::Result;
+use csv_sniffer::Sniffer;
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::grid::column_type::DataTableColumnType;
+
+/// Number of bytes read from the start of the file to detect the encoding and
+/// the delimiter.
+const SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Number of bytes decoded at a time.
+const DECODE_BUFFER_BYTES: usize = 64 * 1024;
+
+/// The byte order mark, which is dropped wherever it appears in the text.
+const BYTE_ORDER_MARK: char = '\u{feff}';
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum CsvEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+}
+
+impl CsvEncoding {
+    /// Detects the encoding of a file from its first bytes.
+    ///
+    /// A byte order mark wins. Otherwise the sample is UTF-16 if it is mostly
+    /// ASCII with interleaved zero bytes, UTF-8 if it is valid UTF-8, and
+    /// Windows-1252 as a last resort.
+    pub fn detect(sample: &[u8]) -> Self {
+        if let Some((encoding, _)) = Encoding::for_bom(sample) {
+            if encoding == UTF_16LE {
+                return CsvEncoding::Utf16Le;
+            } else if encoding == UTF_16BE {
+                return CsvEncoding::Utf16Be;
+            }
+            return CsvEncoding::Utf8;
+        }
+
+        // zero bytes are valid UTF-8, so look for UTF-16 first
+        let zeros_at = |parity| {
+            sample
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .filter(|&&b| b == 0)
+                .count()
+        };
+        let quarter = sample.len() / 4;
+        if zeros_at(1) > quarter {
+            return CsvEncoding::Utf16Le;
+        } else if zeros_at(0) > quarter {
+            return CsvEncoding::Utf16Be;
+        }
+
+        match std::str::from_utf8(sample) {
+            Ok(_) => CsvEncoding::Utf8,
+            // the sample may end in the middle of a character
+            Err(e) if e.error_len().is_none() => CsvEncoding::Utf8,
+            Err(_) => CsvEncoding::Windows1252,
+        }
+    }
+
+    /// Returns a decoder for the encoding, or `None` for Latin-1, which maps
+    /// every byte to the code point of the same value.
+    fn decoder(&self) -> Option<Decoder> {
+        let encoding = match self {
+            CsvEncoding::Utf8 => UTF_8,
+            CsvEncoding::Utf16Le => UTF_16LE,
+            CsvEncoding::Utf16Be => UTF_16BE,
+            CsvEncoding::Windows1252 => WINDOWS_1252,
+            CsvEncoding::Latin1 => return None,
+        };
+        Some(encoding.new_decoder_with_bom_removal())
+    }
+}
+
+/// Options for importing a CSV file. Everything is optional: the delimiter,
+/// encoding and header row are detected when not set.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, TS)]
+#[serde(default)]
+pub struct CsvImportOptions {
+    /// Field delimiter. Detected from the start of the file when not set.
+    pub delimiter: Option<u8>,
+
+    /// Quote character, defaults to `"`.
+    pub quote: Option<u8>,
+
+    /// Escape character for quotes within quoted fields. When not set, quotes
+    /// are escaped by doubling them.
+    pub escape: Option<u8>,
+
+    /// Lines starting with this character are ignored.
+    pub comment: Option<u8>,
+
+    /// Text encoding. Detected from the start of the file when not set.
+    pub encoding: Option<CsvEncoding>,
+
+    /// Number of rows to skip at the start of the file.
+    pub skip_rows: u32,
+
+    /// Index of the header row, counted after `skip_rows`. Rows above it are
+    /// skipped. Takes precedence over `header_is_first_row`.
+    pub header_row: Option<u32>,
+
+    /// Whether the first row is the header. Guessed when not set.
+    pub header_is_first_row: Option<bool>,
+
+    /// Column types that override the inferred types, by column index.
+    /// A declared type is only set when every value of the column can be
+    /// converted to it; otherwise the values are imported as is and the column
+    /// keeps its inferred type.
+    pub column_types: HashMap<u32, DataTableColumnType>,
+}
+
+/// A CSV reader over a decoded file.
+pub type CsvReader<R> = csv::Reader<CsvDecodingReader<Chain<Cursor<Vec<u8>>, R>>>;
+
+impl CsvImportOptions {
+    /// Number of rows to skip before the first imported row.
+    pub fn rows_to_skip(&self) -> u64 {
+        self.skip_rows as u64 + self.header_row.unwrap_or(0) as u64
+    }
+
+    /// Returns the header setting, where `None` means the header should be
+    /// guessed.
+    pub fn header_is_first_row(&self) -> Option<bool> {
+        match self.header_row {
+            Some(_) => Some(true),
+            None => self.header_is_first_row,
+        }
+    }
+
+    /// Creates a CSV reader that decodes the file as it is read.
+    ///
+    /// The start of the file is buffered to detect the encoding and the
+    /// delimiter (when not set), the rest is streamed.
+    pub fn csv_reader<R: Read>(&self, mut reader: R) -> Result<CsvReader<R>> {
+        let mut sample = vec![];
+        reader
+            .by_ref()
+            .take(SAMPLE_BYTES)
+            .read_to_end(&mut sample)?;
+
+        let encoding = self
+            .encoding
+            .unwrap_or_else(|| CsvEncoding::detect(&sample));
+
+        let delimiter = match self.delimiter {
+            Some(delimiter) => delimiter,
+            None => {
+                // auto detect the delimiter, default to ',' if it fails
+                let mut text = String::new();
+                CsvDecodingReader::new(sample.as_slice(), encoding).read_to_string(&mut text)?;
+                Sniffer::new()
+                    .sniff_reader(Cursor::new(text.as_bytes()))
+                    .map_or_else(|_| b',', |metadata| metadata.dialect.delimiter)
+            }
+        };
+
+        let decoding_reader = CsvDecodingReader::new(Cursor::new(sample).chain(reader), encoding);
+
+        Ok(csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .quote(self.quote.unwrap_or(b'"'))
+            .escape(self.escape)
+            .comment(self.comment)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(decoding_reader))
+    }
+}
+
+/// Decodes a file to UTF-8 while it is being read.
+pub struct CsvDecodingReader<R> {
+    inner: R,
+    decoder: Option<Decoder>,
+    input: Vec<u8>,
+    input_start: usize,
+    input_end: usize,
+    output: String,
+    output_start: usize,
+    bytes_read: u64,
+    eof: bool,
+    finished: bool,
+}
+
+impl<R: Read> CsvDecodingReader<R> {
+    pub fn new(inner: R, encoding: CsvEncoding) -> Self {
+        CsvDecodingReader {
+            inner,
+            decoder: encoding.decoder(),
+            input: vec![0; DECODE_BUFFER_BYTES],
+            input_start: 0,
+            input_end: 0,
+            output: String::new(),
+            output_start: 0,
+            bytes_read: 0,
+            eof: false,
+            finished: false,
+        }
+    }
+
+    /// Number of (encoded) bytes read from the underlying reader.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Decodes the next chunk of input into the output buffer.
+    fn decode_next(&mut self) -> std::io::Result<()> {
+        self.output.clear();
+        self.output_start = 0;
+
+        if self.input_start == self.input_end && !self.eof {
+            let read = self.inner.read(&mut self.input)?;
+            self.input_start = 0;
+            self.input_end = read;
+            self.bytes_read += read as u64;
+            self.eof = read == 0;
+        }
+
+        let input = &self.input[self.input_start..self.input_end];
+        match self.decoder.as_mut() {
+            None => {
+                self.output.extend(input.iter().map(|&b| b as char));
+                self.input_start = self.input_end;
+                self.finished = self.eof;
+            }
+            Some(decoder) => {
+                let capacity = decoder
+                    .max_utf8_buffer_length(input.len())
+                    .unwrap_or(input.len() * 3 + 16);
+                self.output.reserve(capacity);
+                let (result, read, _) = decoder.decode_to_string(input, &mut self.output, self.eof);
+                self.input_start += read;
+                self.finished = self.eof && result == CoderResult::InputEmpty;
+            }
+        }
+
+        if self.output.contains(BYTE_ORDER_MARK) {
+            self.output.retain(|c| c != BYTE_ORDER_MARK);
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for CsvDecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let pending = &self.output.as_bytes()[self.output_start..];
+            if !pending.is_empty() {
+                let len = pending.len().min(buf.len());
+                buf[..len].copy_from_slice(&pending[..len]);
+                self.output_start += len;
+                return Ok(len);
+            }
+
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.decode_next()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], encoding: CsvEncoding) -> String {
+        let mut text = String::new();
+        CsvDecodingReader::new(bytes, encoding)
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(CsvEncoding::detect(b"a,b\n1,2"), CsvEncoding::Utf8);
+        assert_eq!(CsvEncoding::detect("é,b".as_bytes()), CsvEncoding::Utf8);
+        assert_eq!(
+            CsvEncoding::detect(&[0xFF, 0xFE, b'a', 0]),
+            CsvEncoding::Utf16Le
+        );
+        assert_eq!(
+            CsvEncoding::detect(&[b'a', 0, b',', 0, b'b', 0]),
+            CsvEncoding::Utf16Le
+        );
+        assert_eq!(
+            CsvEncoding::detect(&[0, b'a', 0, b',', 0, b'b']),
+            CsvEncoding::Utf16Be
+        );
+        assert_eq!(
+            CsvEncoding::detect(&[b'c', b'a', b'f', 0xE9, b',']),
+            CsvEncoding::Windows1252
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(&[b'c', 0xE9, 0x80], CsvEncoding::Latin1), "cé\u{80}");
+        assert_eq!(decode(&[b'c', 0xE9, 0x80], CsvEncoding::Windows1252), "cé€");
+        assert_eq!(
+            decode(&[0xFE, 0xFF, 0, b'h', 0, b'i'], CsvEncoding::Utf16Be),
+            "hi"
+        );
+
+        // multi-byte characters split across decode buffers
+        let text = "é".repeat(DECODE_BUFFER_BYTES);
+        assert_eq!(decode(text.as_bytes(), CsvEncoding::Utf8), text);
+    }
+
+    #[test]
+    fn test_decode_utf16_with_byte_order_marks() {
+        const INVALID_ENCODING_FILE: &[u8] =
+            include_bytes!("../../../../quadratic-rust-shared/data/csv/encoding_issue.csv");
+
+        let encoding = CsvEncoding::detect(INVALID_ENCODING_FILE);
+        assert_eq!(encoding, CsvEncoding::Utf16Le);
+        assert_eq!(
+            decode(INVALID_ENCODING_FILE, encoding),
+            "issue, test, value\r\n0, 1, Invalid\r\n0, 2, Valid"
+        );
+    }
+
+    #[test]
+    fn test_csv_reader_dialect() {
+        let options = CsvImportOptions {
+            delimiter: Some(b';'),
+            quote: Some(b'\''),
+            escape: Some(b'\\'),
+            comment: Some(b'#'),
+            ..Default::default()
+        };
+        let file = "# comment\na;'b;\\'c'\n";
+        let mut reader = options.csv_reader(file.as_bytes()).unwrap();
+        let records = reader
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(records, vec![vec!["a", "b;'c"]]);
+    }
+}
@@ -193,6 +193,20 @@ impl GridController {
         }]
     }
 
+    pub fn data_table_set_column_formula_operations(
+        &self,
+        sheet_pos: SheetPos,
+        column_index: u32,
+        formula: Option<String>,
+    ) -> Vec<Operation> {
+        vec![Operation::SetDataTableColumnFormula {
+            sheet_pos,
+            column_index,
+            formula,
+            values: None,
+        }]
+    }
+
     pub fn data_table_first_row_as_header_operations(
         &self,
         sheet_pos: SheetPos,
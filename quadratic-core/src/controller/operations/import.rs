@@ -1,8 +1,14 @@
-use std::{borrow::Cow, io::Cursor};
+use std::{
+    collections::HashSet,
+    io::{Cursor, Read},
+    str::FromStr,
+};
 
 use anyhow::{Result, anyhow, bail};
+use bigdecimal::BigDecimal;
 use chrono::{NaiveDate, NaiveTime};
-use csv_sniffer::Sniffer;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     Array, ArraySize, CellValue, Pos, SheetPos,
@@ -10,8 +16,11 @@ use crate::{
     cellvalue::Import,
     controller::GridController,
     grid::{
-        CodeCellLanguage, CodeCellValue, DataTable, Sheet, SheetId,
-        file::sheet_schema::export_sheet, formats::SheetFormatUpdates,
+        CodeCellLanguage, CodeCellValue, DataTable, NumericFormat, NumericFormatKind, Sheet,
+        SheetId,
+        column_type::{ColumnTypeInference, DataTableColumnType},
+        file::sheet_schema::export_sheet,
+        formats::{FormatUpdate, SheetFormatUpdates},
     },
 };
 use bytes::Bytes;
@@ -19,10 +28,14 @@ use calamine::{Data as ExcelData, Reader as ExcelReader, Xlsx, XlsxError};
 use lexicon_fractional_index::key_between;
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 
-use super::operation::Operation;
+use super::{csv_import::CsvImportOptions, operation::Operation};
 
 const IMPORT_LINES_PER_OPERATION: u32 = 10000;
 
+/// Default depth to which nested JSON objects are flattened into dotted
+/// column names.
+pub const DEFAULT_JSON_MAX_DEPTH: usize = 5;
+
 impl GridController {
     /// Guesses if the first row of a CSV file is a header based on the types of the
     /// first three rows.
@@ -55,41 +68,32 @@ impl GridController {
         max_rows: u32,
         delimiter: Option<u8>,
     ) -> Result<Vec<Vec<String>>> {
-        let error = |message: String| anyhow!("Error parsing CSV file for preview: {}", message);
-        let file: &[u8] = match String::from_utf8_lossy(&file) {
-            std::borrow::Cow::Borrowed(_) => &file,
-            std::borrow::Cow::Owned(_) => {
-                if let Some(utf) = read_utf16(&file) {
-                    return Self::get_csv_preview(utf.as_bytes().to_vec(), max_rows, delimiter);
-                }
-                &file
-            }
-        };
-
-        let delimiter = match delimiter {
-            Some(d) => d,
-            None => {
-                // auto detect the delimiter, default to ',' if it fails
-                let cursor = Cursor::new(&file);
-                Sniffer::new()
-                    .sniff_reader(cursor)
-                    .map_or_else(|_| b',', |metadata| metadata.dialect.delimiter)
-            }
+        let options = CsvImportOptions {
+            delimiter,
+            ..Default::default()
         };
+        Self::get_csv_preview_with_options(file.as_slice(), max_rows, &options)
+    }
 
-        let mut reader = csv::ReaderBuilder::new()
-            .delimiter(delimiter)
-            .has_headers(false)
-            .flexible(true)
-            .from_reader(file);
+    /// Returns the first `max_rows` rows of a CSV file, after the skipped
+    /// rows. Only the start of the file is read.
+    pub fn get_csv_preview_with_options(
+        file: impl Read,
+        max_rows: u32,
+        options: &CsvImportOptions,
+    ) -> Result<Vec<Vec<String>>> {
+        let error = |message: String| anyhow!("Error parsing CSV file for preview: {}", message);
+        let mut reader = options.csv_reader(file)?;
+        let rows_to_skip = options.rows_to_skip() as usize;
 
         let mut preview = vec![];
         for (i, entry) in reader.records().enumerate() {
-            if i >= max_rows as usize {
+            if preview.len() >= max_rows as usize {
                 break;
             }
             match entry {
                 Err(e) => return Err(error(format!("line {}: {}", i + 1, e))),
+                Ok(_) if i < rows_to_skip => continue,
                 Ok(record) => preview.push(record.iter().map(|s| s.to_string()).collect()),
             }
         }
@@ -106,135 +110,138 @@ impl GridController {
         insert_at: Pos,
         delimiter: Option<u8>,
         header_is_first_row: Option<bool>,
+    ) -> Result<Vec<Operation>> {
+        let options = CsvImportOptions {
+            delimiter,
+            header_is_first_row,
+            ..Default::default()
+        };
+        let total_bytes = file.len() as u64;
+        self.import_csv_operations_from_reader(
+            sheet_id,
+            Cursor::new(file),
+            Some(total_bytes),
+            file_name,
+            insert_at,
+            &options,
+        )
+    }
+
+    /// Imports a CSV file into the grid, reading it from a stream.
+    ///
+    /// The file is decoded and parsed as it is read. The first
+    /// `IMPORT_LINES_PER_OPERATION` rows add the table, and every following
+    /// batch of rows is appended to it with its own operation, so the file is
+    /// never held as a single table. Column types are inferred from the whole
+    /// file and set by a final operation when they differ from the types of
+    /// the first batch. `total_bytes` is only used to estimate the import
+    /// progress.
+    pub fn import_csv_operations_from_reader(
+        &mut self,
+        sheet_id: SheetId,
+        file: impl Read,
+        total_bytes: Option<u64>,
+        file_name: &str,
+        insert_at: Pos,
+        options: &CsvImportOptions,
     ) -> Result<Vec<Operation>> {
         let error = |message: String| anyhow!("Error parsing CSV file {}: {}", file_name, message);
         let sheet_pos = SheetPos::from((insert_at, sheet_id));
 
-        let file: &[u8] = match String::from_utf8_lossy(&file) {
-            Cow::Borrowed(_) => &file,
-            Cow::Owned(_) => {
-                if let Some(utf) = read_utf16(&file) {
-                    return self.import_csv_operations(
-                        sheet_id,
-                        utf.as_bytes().to_vec(),
-                        file_name,
-                        insert_at,
-                        delimiter,
-                        header_is_first_row,
-                    );
-                }
-                &file
-            }
-        };
+        let mut reader = options.csv_reader(file)?;
+        let rows_to_skip = options.rows_to_skip();
 
-        let delimiter = match delimiter {
-            Some(d) => d,
-            None => {
-                // auto detect the delimiter, default to ',' if it fails
-                let cursor = Cursor::new(&file);
-                Sniffer::new()
-                    .sniff_reader(cursor)
-                    .map_or_else(|_| b',', |metadata| metadata.dialect.delimiter)
-            }
+        let mut header_is_first_row = options.header_is_first_row();
+        let mut chunks = CsvChunks {
+            header_is_first_row: header_is_first_row == Some(true),
+            ..Default::default()
         };
 
-        let reader = |flexible| {
-            csv::ReaderBuilder::new()
-                .delimiter(delimiter)
-                .has_headers(false)
-                .flexible(flexible)
-                .from_reader(file)
-        };
-
-        let height = reader(false).records().count() as u32;
-
-        // since the first row or more can be headers, look at the width of the last row
-        let width = reader(true)
-            .records()
-            .last()
-            .iter()
-            .flatten()
-            .next()
-            .map(|s| s.len())
-            .unwrap_or(0) as u32;
+        // rows are held back until the header is guessed from the first three
+        let mut pending = vec![];
+        let mut record = csv::StringRecord::new();
+        let mut line: u64 = 0;
 
-        if width == 0 {
-            bail!("empty files cannot be processed");
-        }
+        loop {
+            match reader.read_record(&mut record) {
+                Err(e) => return Err(error(format!("line {}: {}", line + 1, e))),
+                Ok(false) => break,
+                Ok(true) => line += 1,
+            }
 
-        let array_size = ArraySize::new_or_err(width, height).map_err(|e| error(e.to_string()))?;
-        let mut cell_values = Array::new_empty(array_size);
-        let mut sheet_format_updates = SheetFormatUpdates::default();
-        let mut y: u32 = 0;
+            if line <= rows_to_skip {
+                continue;
+            }
 
-        for entry in reader(true).records() {
-            match entry {
-                Err(e) => return Err(error(format!("line {}: {}", y + 1, e))),
-                Ok(record) => {
-                    for (x, value) in record.iter().enumerate() {
-                        let (cell_value, format_update) = self.string_to_cell_value(value, false);
-
-                        cell_values
-                            .set(u32::try_from(x)?, y, cell_value)
-                            .map_err(|e| error(e.to_string()))?;
-
-                        if !format_update.is_default() {
-                            let pos = Pos {
-                                x: x as i64 + 1,
-                                y: y as i64 + 1,
-                            };
-                            sheet_format_updates.set_format_cell(pos, format_update);
-                        }
-                    }
+            if header_is_first_row.is_none() {
+                pending.push(record.clone());
+                if pending.len() < 3 {
+                    continue;
+                }
+                let header = self.guess_csv_records_first_row_is_header(&pending);
+                header_is_first_row = Some(header);
+                chunks.header_is_first_row = header;
+                for record in pending.drain(..) {
+                    chunks.push(&record, options)?;
                 }
+            } else {
+                chunks.push(&record, options)?;
             }
 
-            y += 1;
+            if chunks.height >= IMPORT_LINES_PER_OPERATION {
+                chunks.flush(self, sheet_pos, file_name, options)?;
 
-            // update the progress bar every time there's a new batch
-            let should_update = y % IMPORT_LINES_PER_OPERATION == 0;
-
-            if should_update && (cfg!(target_family = "wasm") || cfg!(test)) {
-                crate::wasm_bindings::js::jsImportProgress(
-                    file_name,
-                    y,
-                    height,
-                    insert_at.x,
-                    insert_at.y,
-                    width,
-                    height,
-                );
+                // update the progress bar every time there's a new batch
+                if cfg!(target_family = "wasm") || cfg!(test) {
+                    // estimate the total number of rows from the bytes read so far
+                    let height = chunks.start_y;
+                    let bytes_read = reader.get_ref().bytes_read().max(1);
+                    let total = total_bytes.map_or(height, |total_bytes| {
+                        (height as u64 * total_bytes / bytes_read)
+                            .clamp(height as u64, u32::MAX as u64) as u32
+                    });
+                    crate::wasm_bindings::js::jsImportProgress(
+                        file_name,
+                        height,
+                        total,
+                        insert_at.x,
+                        insert_at.y,
+                        chunks.width,
+                        total,
+                    );
+                }
             }
         }
 
-        let context = self.a1_context();
-        let import = Import::new(file_name.into());
-        let mut data_table =
-            DataTable::from((import.to_owned(), Array::new_empty(array_size), context));
-
-        let apply_first_row_as_header = match header_is_first_row {
-            Some(true) => true,
-            Some(false) => false,
-            None => self.guess_csv_first_row_is_header(&cell_values),
-        };
-
-        data_table.value = cell_values.into();
-        data_table.formats.apply_updates(&sheet_format_updates);
-
-        if apply_first_row_as_header {
-            data_table.apply_first_row_as_header();
+        // files with less than three rows
+        if header_is_first_row.is_none() {
+            chunks.header_is_first_row = self.guess_csv_records_first_row_is_header(&pending);
+            for record in pending.iter() {
+                chunks.push(record, options)?;
+            }
         }
 
-        drop(sheet_format_updates);
+        chunks.finish(self, sheet_pos, file_name, options)
+    }
 
-        let ops = vec![Operation::AddDataTable {
-            sheet_pos,
-            data_table,
-            cell_value: CellValue::Import(import),
-            index: None,
-        }];
+    /// Guesses if the first of the CSV records is a header, see
+    /// [`Self::guess_csv_first_row_is_header`].
+    fn guess_csv_records_first_row_is_header(&self, records: &[csv::StringRecord]) -> bool {
+        let width = records.iter().map(|record| record.len()).max().unwrap_or(0);
+        let values = records
+            .iter()
+            .flat_map(|record| {
+                (0..width).map(|x| {
+                    record.get(x).map_or(CellValue::Blank, |value| {
+                        CellValue::string_to_cell_value(value, false).0
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
 
-        Ok(ops)
+        ArraySize::new(width as u32, records.len() as u32)
+            .and_then(|size| Array::new_row_major(size, values.into()).ok())
+            .is_some_and(|values| self.guess_csv_first_row_is_header(&values))
     }
 
     /// Imports an Excel file into the grid.
@@ -427,6 +434,14 @@ impl GridController {
         let headers: Vec<CellValue> = fields.iter().map(|f| f.name().into()).collect();
         let mut width = headers.len() as u32;
 
+        // column types come straight from the arrow schema
+        let column_types = builder
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| DataTableColumnType::from_arrow(field.data_type()))
+            .collect::<Vec<_>>();
+
         // add 1 to the height for the headers
         let array_size =
             ArraySize::new_or_err(width, total_size + 1).map_err(|e| error(e.to_string()))?;
@@ -484,6 +499,156 @@ impl GridController {
         let mut data_table = DataTable::from((import.to_owned(), cell_values, context));
         data_table.apply_first_row_as_header();
 
+        for (index, column_type) in column_types.into_iter().enumerate() {
+            data_table.set_column_type(index, column_type);
+        }
+
+        let ops = vec![Operation::AddDataTable {
+            sheet_pos: SheetPos::from((insert_at, sheet_id)),
+            data_table,
+            cell_value: CellValue::Import(import),
+            index: None,
+        }];
+
+        Ok(ops)
+    }
+
+    /// Imports a JSON file into the grid.
+    ///
+    /// The file can be an array of objects, an array of arrays, or newline
+    /// delimited JSON (NDJSON) of either. Nested objects are flattened into
+    /// dotted column names (eg, `address.city`) up to `max_depth` levels;
+    /// deeper objects and arrays are imported as JSON text.
+    pub fn import_json_operations(
+        &mut self,
+        sheet_id: SheetId,
+        file: Vec<u8>,
+        file_name: &str,
+        insert_at: Pos,
+        max_depth: Option<usize>,
+    ) -> Result<Vec<Operation>> {
+        let error = |message: String| anyhow!("Error parsing JSON file {}: {}", file_name, message);
+        let max_depth = max_depth.unwrap_or(DEFAULT_JSON_MAX_DEPTH);
+
+        let rows = JsonValue::parse_rows(&file).map_err(|e| error(e.to_string()))?;
+        if rows.is_empty() {
+            bail!("empty files cannot be processed");
+        }
+
+        let is_objects = rows.iter().all(|row| matches!(row, JsonValue::Object(_)));
+        let is_mixed = !is_objects && rows.iter().any(|row| matches!(row, JsonValue::Object(_)));
+        if is_mixed {
+            return Err(error(
+                "expected an array of objects or an array of arrays".to_string(),
+            ));
+        }
+
+        // collect the rows as (column index, value) pairs
+        let mut columns: IndexMap<String, usize> = IndexMap::new();
+        let mut flattened_rows = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let values = match row {
+                JsonValue::Object(object) => {
+                    let mut flattened = vec![];
+                    JsonValue::flatten(object, "", 0, max_depth, &mut flattened);
+                    flattened
+                        .into_iter()
+                        .map(|(name, value)| {
+                            let len = columns.len();
+                            (*columns.entry(name).or_insert(len), value)
+                        })
+                        .collect::<Vec<_>>()
+                }
+                JsonValue::Array(values) => values.iter().enumerate().collect(),
+                value => vec![(0, value)],
+            };
+            flattened_rows.push(values);
+        }
+
+        let width = if is_objects {
+            columns.len()
+        } else {
+            flattened_rows
+                .iter()
+                .map(|row| row.iter().map(|(x, _)| x + 1).max().unwrap_or(0))
+                .max()
+                .unwrap_or(0)
+        } as u32;
+
+        if width == 0 {
+            bail!("empty files cannot be processed");
+        }
+
+        // objects have a header row with the column names
+        let header_height = if is_objects { 1 } else { 0 };
+        let height = flattened_rows.len() as u32 + header_height;
+        let array_size = ArraySize::new_or_err(width, height).map_err(|e| error(e.to_string()))?;
+        let mut cell_values = Array::new_empty(array_size);
+        let mut sheet_format_updates = SheetFormatUpdates::default();
+        let mut currency_columns = vec![];
+
+        for (x, name) in columns.keys().enumerate() {
+            cell_values
+                .set(x as u32, 0, CellValue::Text(name.to_owned()))
+                .map_err(|e| error(e.to_string()))?;
+        }
+
+        for (index, row) in flattened_rows.into_iter().enumerate() {
+            let y = index as u32 + header_height;
+
+            for (x, value) in row {
+                let (cell_value, format_update) = value.to_cell_value(self);
+
+                cell_values
+                    .set(x as u32, y, cell_value)
+                    .map_err(|e| error(e.to_string()))?;
+
+                if is_currency_format(&format_update) && !currency_columns.contains(&x) {
+                    currency_columns.push(x);
+                }
+
+                if !format_update.is_default() {
+                    let pos = Pos {
+                        x: x as i64 + 1,
+                        y: y as i64 + 1,
+                    };
+                    sheet_format_updates.set_format_cell(pos, format_update);
+                }
+            }
+
+            // update the progress bar every time there's a new batch
+            let should_update = (y + 1) % IMPORT_LINES_PER_OPERATION == 0;
+
+            if should_update && (cfg!(target_family = "wasm") || cfg!(test)) {
+                crate::wasm_bindings::js::jsImportProgress(
+                    file_name,
+                    y + 1,
+                    height,
+                    insert_at.x,
+                    insert_at.y,
+                    width,
+                    height,
+                );
+            }
+        }
+
+        let context = self.a1_context();
+        let import = Import::new(file_name.into());
+        let mut data_table =
+            DataTable::from((import.to_owned(), Array::new_empty(array_size), context));
+
+        let apply_first_row_as_header =
+            is_objects || self.guess_csv_first_row_is_header(&cell_values);
+
+        data_table.value = cell_values.into();
+        data_table.formats.apply_updates(&sheet_format_updates);
+
+        if apply_first_row_as_header {
+            data_table.apply_first_row_as_header();
+        }
+
+        data_table.infer_column_types(&currency_columns);
+
         let ops = vec![Operation::AddDataTable {
             sheet_pos: SheetPos::from((insert_at, sheet_id)),
             data_table,
@@ -495,42 +660,385 @@ impl GridController {
     }
 }
 
-fn read_utf16(bytes: &[u8]) -> Option<String> {
-    if bytes.is_empty() && bytes.len() % 2 == 0 {
-        return None;
+/// A JSON value that keeps the order of object keys, so columns are imported
+/// in the order they appear in the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(IndexMap<String, JsonValue>),
+}
+
+impl JsonValue {
+    /// Parses a JSON document or, if that fails, newline delimited JSON.
+    /// Returns the rows of the file.
+    fn parse_rows(file: &[u8]) -> Result<Vec<JsonValue>> {
+        match serde_json::from_slice::<JsonValue>(file) {
+            Ok(JsonValue::Array(rows)) => Ok(rows),
+            Ok(value) => Ok(vec![value]),
+            Err(e) => {
+                let text = std::str::from_utf8(file)?;
+                let lines = text
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .collect::<Vec<_>>();
+
+                // a single line that fails to parse is not NDJSON
+                if lines.len() < 2 {
+                    bail!(e);
+                }
+
+                lines
+                    .into_iter()
+                    .map(|(index, line)| {
+                        serde_json::from_str::<JsonValue>(line)
+                            .map_err(|e| anyhow!("line {}: {}", index + 1, e))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Flattens an object into `(column name, value)` pairs. Nested objects
+    /// are flattened into dotted column names up to `max_depth` levels.
+    fn flatten<'a>(
+        object: &'a IndexMap<String, JsonValue>,
+        prefix: &str,
+        depth: usize,
+        max_depth: usize,
+        output: &mut Vec<(String, &'a JsonValue)>,
+    ) {
+        for (key, value) in object {
+            let name = if prefix.is_empty() {
+                key.to_owned()
+            } else {
+                format!("{prefix}.{key}")
+            };
+
+            match value {
+                JsonValue::Object(nested) if depth < max_depth && !nested.is_empty() => {
+                    Self::flatten(nested, &name, depth + 1, max_depth, output);
+                }
+                value => output.push((name, value)),
+            }
+        }
+    }
+
+    /// Converts a (flattened) value into a cell value. Strings are parsed in
+    /// the same way as CSV values. Arrays and objects are kept as JSON text.
+    fn to_cell_value(&self, gc: &GridController) -> (CellValue, FormatUpdate) {
+        match self {
+            JsonValue::Null => (CellValue::Blank, FormatUpdate::default()),
+            JsonValue::Bool(b) => (CellValue::Logical(*b), FormatUpdate::default()),
+            JsonValue::Number(n) => {
+                let value = BigDecimal::from_str(&n.to_string())
+                    .map(CellValue::Number)
+                    .unwrap_or_else(|_| CellValue::Text(n.to_string()));
+                (value, FormatUpdate::default())
+            }
+            JsonValue::String(s) => gc.string_to_cell_value(s, false),
+            JsonValue::Array(_) | JsonValue::Object(_) => (
+                CellValue::Text(serde_json::to_string(self).unwrap_or_default()),
+                FormatUpdate::default(),
+            ),
+        }
+    }
+}
+
+/// The rows of a CSV file being imported, converted to operations every
+/// `IMPORT_LINES_PER_OPERATION` rows.
+#[derive(Default)]
+struct CsvChunks {
+    /// whether the first row of the file is the header
+    header_is_first_row: bool,
+
+    /// width of the widest row so far
+    width: u32,
+
+    /// number of rows in the previous chunks, including the header row
+    start_y: u32,
+
+    /// number of rows in the current chunk
+    height: u32,
+
+    /// row-major values of the current chunk, padded to `width`
+    values: Vec<CellValue>,
+
+    /// formats of the current chunk, in (1-indexed) table value coordinates
+    formats: SheetFormatUpdates,
+
+    currency_columns: Vec<usize>,
+    column_types: Vec<ColumnTypeInference>,
+
+    /// columns whose declared type could not be applied to every value
+    rejected_column_types: HashSet<usize>,
+
+    operations: Vec<Operation>,
+
+    /// the table as added by the first chunk, without values; used to name
+    /// the columns added by wider rows and to type the columns at the end
+    table: Option<DataTable>,
+}
+
+impl CsvChunks {
+    /// Converts a row of the file into cell values.
+    fn push(&mut self, record: &csv::StringRecord, options: &CsvImportOptions) -> Result<()> {
+        let record_width = u32::try_from(record.len())?;
+        if record_width > self.width {
+            self.values = widen_rows(std::mem::take(&mut self.values), self.width, record_width);
+            self.width = record_width;
+            self.column_types
+                .resize(record_width as usize, ColumnTypeInference::default());
+        }
+
+        let y = self.start_y + self.height;
+        let is_header = self.header_is_first_row && y == 0;
+
+        for (x, value) in record.iter().enumerate() {
+            if is_header {
+                self.values
+                    .push(CellValue::string_to_cell_value(value, false).0);
+                continue;
+            }
+
+            let coerced = match options.column_types.get(&u32::try_from(x)?) {
+                Some(column_type) if !value.trim().is_empty() => {
+                    let coerced = column_type.coerce(CellValue::Text(value.into()));
+                    if coerced.is_none() {
+                        self.rejected_column_types.insert(x);
+                    }
+                    coerced
+                }
+                _ => None,
+            };
+            let (cell_value, format_update) = match coerced {
+                Some(cell_value) => (cell_value, FormatUpdate::default()),
+                None => CellValue::string_to_cell_value(value, false),
+            };
+
+            self.column_types[x].add(&cell_value);
+            self.values.push(cell_value);
+
+            if is_currency_format(&format_update) && !self.currency_columns.contains(&x) {
+                self.currency_columns.push(x);
+            }
+
+            if !format_update.is_default() {
+                let pos = Pos {
+                    x: x as i64 + 1,
+                    y: y as i64 + 1,
+                };
+                self.formats.set_format_cell(pos, format_update);
+            }
+        }
+        self.values.resize(
+            self.values.len() + (self.width - record_width) as usize,
+            CellValue::Blank,
+        );
+        self.height += 1;
+
+        Ok(())
     }
 
-    // convert u8 to u16
-    let mut utf16vec: Vec<u16> = Vec::with_capacity(bytes.len() / 2);
-    for chunk in bytes.to_owned().chunks_exact(2) {
-        let Ok(vec2) = <[u8; 2]>::try_from(chunk) else {
-            return None;
+    /// Types of the columns from the rows so far. A declared type is only
+    /// used when every value of the column was converted to it.
+    fn column_types(&self, options: &CsvImportOptions) -> Vec<Option<DataTableColumnType>> {
+        (0..self.width as usize)
+            .map(|x| match options.column_types.get(&(x as u32)) {
+                Some(&column_type) if !self.rejected_column_types.contains(&x) => Some(column_type),
+                _ => self.column_types[x].column_type(self.currency_columns.contains(&x)),
+            })
+            .collect()
+    }
+
+    /// Converts the current chunk into operations: the first chunk adds the
+    /// table, later chunks append their rows to it.
+    fn flush(
+        &mut self,
+        gc: &GridController,
+        sheet_pos: SheetPos,
+        file_name: &str,
+        options: &CsvImportOptions,
+    ) -> Result<()> {
+        let error = |message: String| anyhow!("Error parsing CSV file {}: {}", file_name, message);
+
+        let start_y = self.start_y;
+        let height = self.height;
+        let values = std::mem::take(&mut self.values);
+        let formats = std::mem::take(&mut self.formats);
+        self.start_y += height;
+        self.height = 0;
+
+        let Some(table) = self.table.as_mut() else {
+            if self.width == 0 {
+                bail!("empty files cannot be processed");
+            }
+
+            let array_size =
+                ArraySize::new_or_err(self.width, height).map_err(|e| error(e.to_string()))?;
+            let cell_values = Array::new_row_major(array_size, values.into())
+                .map_err(|e| error(e.to_string()))?;
+
+            let context = gc.a1_context();
+            let import = Import::new(file_name.into());
+            let mut data_table =
+                DataTable::from((import.to_owned(), Array::new_empty(array_size), context));
+            data_table.value = cell_values.into();
+            data_table.formats.apply_updates(&formats);
+
+            if self.header_is_first_row {
+                data_table.apply_first_row_as_header();
+            }
+
+            for (index, column_type) in self.column_types(options).into_iter().enumerate() {
+                data_table.set_column_type(index, column_type);
+            }
+
+            let mut table = DataTable::from((
+                import.to_owned(),
+                Array::new_empty(
+                    ArraySize::new_or_err(self.width, 1).map_err(|e| error(e.to_string()))?,
+                ),
+                context,
+            ));
+            table.header_is_first_row = data_table.header_is_first_row;
+            table.column_headers = data_table.column_headers.to_owned();
+            self.table = Some(table);
+
+            self.operations.push(Operation::AddDataTable {
+                sheet_pos,
+                data_table,
+                cell_value: CellValue::Import(import),
+                index: None,
+            });
+
+            return Ok(());
         };
-        utf16vec.push(u16::from_ne_bytes(vec2));
+
+        // rows wider than the table add columns to it
+        let table_width = table.width() as u32;
+        if self.width > table_width {
+            for index in table_width..self.width {
+                table.insert_column_sorted(index as usize, None, None)?;
+            }
+            self.operations.push(Operation::InsertDataTableColumns {
+                sheet_pos,
+                columns: (table_width..self.width)
+                    .map(|index| (index, None, None))
+                    .collect(),
+                swallow: false,
+                select_table: false,
+                copy_formats_from: None,
+                copy_formats: None,
+            });
+        }
+
+        // rows are inserted at their display index
+        let y_adjustment = table.y_adjustment(true);
+        let mut values = values.into_iter();
+        let mut rows = Vec::with_capacity(height as usize);
+        for y in start_y..start_y + height {
+            let index = u32::try_from(y_adjustment + y as i64)?;
+            let row = values.by_ref().take(self.width as usize).collect();
+            rows.push((index, Some(row)));
+        }
+        self.operations.push(Operation::InsertDataTableRows {
+            sheet_pos,
+            rows,
+            swallow: false,
+            select_table: false,
+            copy_formats_from: None,
+            copy_formats: None,
+        });
+
+        if !formats.is_default() {
+            self.operations
+                .push(Operation::DataTableFormats { sheet_pos, formats });
+        }
+
+        Ok(())
     }
 
-    // convert to string
-    let Ok(str) = String::from_utf16(utf16vec.as_slice()) else {
-        return None;
-    };
+    /// Converts the last chunk into operations, and sets the column types
+    /// inferred from the whole file.
+    fn finish(
+        mut self,
+        gc: &GridController,
+        sheet_pos: SheetPos,
+        file_name: &str,
+        options: &CsvImportOptions,
+    ) -> Result<Vec<Operation>> {
+        if self.height > 0 || self.table.is_none() {
+            self.flush(gc, sheet_pos, file_name, options)?;
+        }
+
+        let column_types = self.column_types(options);
+        if let Some(table) = self.table.as_mut() {
+            let columns = table.column_headers.to_owned();
+            for (index, column_type) in column_types.into_iter().enumerate() {
+                table.set_column_type(index, column_type);
+            }
+            if table.column_headers != columns {
+                self.operations.push(Operation::DataTableOptionMeta {
+                    sheet_pos,
+                    name: None,
+                    alternating_colors: None,
+                    columns: table.column_headers.to_owned(),
+                    show_name: None,
+                    show_columns: None,
+                });
+            }
+        }
+
+        Ok(self.operations)
+    }
+}
+
+fn is_currency_format(format_update: &FormatUpdate) -> bool {
+    matches!(
+        &format_update.numeric_format,
+        Some(Some(NumericFormat {
+            kind: NumericFormatKind::Currency,
+            ..
+        }))
+    )
+}
+
+/// Re-lays out row-major values for a wider row, padding the existing rows
+/// with blanks.
+fn widen_rows(values: Vec<CellValue>, width: u32, new_width: u32) -> Vec<CellValue> {
+    if values.is_empty() {
+        return values;
+    }
 
-    // strip invalid characters
-    let result: String = str.chars().filter(|&c| c.len_utf8() <= 2).collect();
+    let (width, new_width) = (width as usize, new_width as usize);
+    let mut widened = Vec::with_capacity(values.len() / width * new_width);
+    let mut values = values.into_iter();
+    while widened.len() < widened.capacity() {
+        widened.extend(values.by_ref().take(width));
+        widened.resize(widened.len() + new_width - width, CellValue::Blank);
+    }
 
-    Some(result)
+    widened
 }
 
 #[cfg(test)]
 mod test {
-    use super::{read_utf16, *};
+    use super::*;
     use crate::{
-        CellValue, controller::user_actions::import::tests::simple_csv_at,
+        CellValue,
+        controller::{
+            operations::csv_import::CsvEncoding, user_actions::import::tests::simple_csv_at,
+        },
         test_util::assert_display_cell_value,
     };
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-
-    const INVALID_ENCODING_FILE: &[u8] =
-        include_bytes!("../../../../quadratic-rust-shared/data/csv/encoding_issue.csv");
+    use std::collections::HashMap;
 
     #[test]
     fn guesses_the_csv_header() {
@@ -581,12 +1089,6 @@ mod test {
         assert_eq!(preview[1], vec!["value1", "value2"]);
     }
 
-    #[test]
-    fn transmute_u8_to_u16() {
-        let result = read_utf16(INVALID_ENCODING_FILE).unwrap();
-        assert_eq!("issue, test, value\r\n0, 1, Invalid\r\n0, 2, Valid", result);
-    }
-
     #[test]
     fn imports_a_simple_csv() {
         let mut gc = GridController::test();
@@ -616,6 +1118,7 @@ mod test {
         let import = Import::new(file_name.into());
         let cell_value = CellValue::Import(import.clone());
         let mut expected_data_table = DataTable::from((import, values.into(), context));
+        expected_data_table.infer_column_types(&[]);
         assert_display_cell_value(&gc, sheet_id, 1, 1, &cell_value.to_string());
 
         let data_table = match ops[0].clone() {
@@ -636,6 +1139,112 @@ mod test {
         assert_eq!(ops[0], expected);
     }
 
+    #[test]
+    fn imports_a_csv_with_options() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+
+        // Latin-1, semicolon delimited, with a title row and a comment
+        let mut file = b"Report\n# generated\nzip;caf".to_vec();
+        file.push(0xE9);
+        file.extend(b";price\n02134;'a;b';1.5\n00501;c;2\n");
+
+        let options = CsvImportOptions {
+            delimiter: Some(b';'),
+            quote: Some(b'\''),
+            comment: Some(b'#'),
+            encoding: Some(CsvEncoding::Latin1),
+            header_row: Some(1),
+            column_types: HashMap::from([(0, DataTableColumnType::Text)]),
+            ..Default::default()
+        };
+
+        let preview =
+            GridController::get_csv_preview_with_options(file.as_slice(), 2, &options).unwrap();
+        assert_eq!(
+            preview,
+            vec![vec!["zip", "café", "price"], vec!["02134", "a;b", "1.5"]]
+        );
+
+        gc.import_csv_with_options(
+            sheet_id,
+            file.as_slice(),
+            None,
+            "options.csv",
+            pos![A1],
+            &options,
+            None,
+        )
+        .unwrap();
+
+        let data_table = gc.sheet(sheet_id).data_table(pos![A1]).unwrap();
+        assert!(data_table.header_is_first_row);
+        assert_eq!(
+            data_table
+                .column_headers
+                .iter()
+                .flatten()
+                .map(|header| header.name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["zip", "café", "price"]
+        );
+        assert_eq!(
+            data_table.cell_value_at(0, 2),
+            Some(CellValue::Text("02134".into()))
+        );
+        assert_eq!(
+            data_table.cell_value_at(2, 3),
+            Some(CellValue::Number(2.into()))
+        );
+        assert_eq!(
+            data_table.column_types(),
+            vec![
+                Some(DataTableColumnType::Text),
+                Some(DataTableColumnType::Text),
+                Some(DataTableColumnType::Number)
+            ]
+        );
+    }
+
+    #[test]
+    fn imports_a_csv_from_a_reader() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+
+        // rows wider than the first row widen the table
+        let file = "a,b\n1,2\n3,4,5\n6\n";
+        let ops = gc
+            .import_csv_operations_from_reader(
+                sheet_id,
+                file.as_bytes(),
+                None,
+                "reader.csv",
+                pos![A1],
+                &CsvImportOptions {
+                    skip_rows: 1,
+                    header_is_first_row: Some(false),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let data_table = match &ops[0] {
+            Operation::AddDataTable { data_table, .. } => data_table,
+            _ => panic!("Expected AddDataTable operation"),
+        };
+        let expected: Array = vec![
+            vec![CellValue::Number(1.into()), 2.into(), CellValue::Blank],
+            vec![CellValue::Number(3.into()), 4.into(), 5.into()],
+            vec![
+                CellValue::Number(6.into()),
+                CellValue::Blank,
+                CellValue::Blank,
+            ],
+        ]
+        .into();
+        assert_eq!(data_table.value_as_array().unwrap(), &expected);
+    }
+
     #[test]
     fn imports_a_long_csv() {
         let mut gc = GridController::test();
@@ -661,9 +1270,13 @@ mod test {
         let cell_value = CellValue::Import(import.clone());
         assert_display_cell_value(&gc, sheet_id, 0, 0, &cell_value.to_string());
 
-        assert_eq!(ops.as_ref().unwrap().len(), 1);
+        // the first batch adds the table, the following batches append rows
+        let ops = ops.unwrap();
+        assert_eq!(ops.len(), 3);
+        assert!(matches!(ops[1], Operation::InsertDataTableRows { .. }));
+        assert!(matches!(ops[2], Operation::InsertDataTableRows { .. }));
 
-        let (sheet_pos, data_table) = match &ops.unwrap()[0] {
+        let (sheet_pos, data_table) = match &ops[0] {
             Operation::AddDataTable {
                 sheet_pos,
                 data_table,
@@ -678,6 +1291,93 @@ mod test {
         );
     }
 
+    #[test]
+    fn imports_a_long_csv_in_chunks() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        let mut csv = "name,count,zip\n".to_string();
+        for i in 0..IMPORT_LINES_PER_OPERATION + 10 {
+            csv.push_str(&format!("name{i},{i},{i:05}\n"));
+        }
+        // the last row is wider and changes the type of the count column
+        csv.push_str("last,many,00000,extra\n");
+
+        // names cannot be numbers, so the declared type of the first column
+        // is not applied
+        let options = CsvImportOptions {
+            header_is_first_row: Some(true),
+            column_types: HashMap::from([
+                (0, DataTableColumnType::Number),
+                (2, DataTableColumnType::Text),
+            ]),
+            ..Default::default()
+        };
+
+        let ops = gc
+            .import_csv_operations_from_reader(
+                sheet_id,
+                csv.as_bytes(),
+                None,
+                "chunks.csv",
+                pos![A1],
+                &options,
+            )
+            .unwrap();
+        assert_eq!(ops.len(), 4);
+        assert!(matches!(ops[0], Operation::AddDataTable { .. }));
+        assert!(matches!(ops[1], Operation::InsertDataTableColumns { .. }));
+        assert!(matches!(ops[2], Operation::InsertDataTableRows { .. }));
+        assert!(matches!(ops[3], Operation::DataTableOptionMeta { .. }));
+
+        gc.import_csv_with_options(
+            sheet_id,
+            csv.as_bytes(),
+            None,
+            "chunks.csv",
+            pos![A1],
+            &options,
+            None,
+        )
+        .unwrap();
+
+        let data_table = gc.sheet(sheet_id).data_table(pos![A1]).unwrap();
+        assert_eq!(
+            data_table.height(false),
+            IMPORT_LINES_PER_OPERATION as usize + 12
+        );
+        assert_eq!(
+            data_table
+                .column_headers
+                .iter()
+                .flatten()
+                .map(|header| header.name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["name", "count", "zip", "Column 4"]
+        );
+        assert_eq!(
+            data_table.cell_value_at(2, 14),
+            Some(CellValue::Text("00012".into()))
+        );
+        assert_eq!(
+            data_table.cell_value_at(1, IMPORT_LINES_PER_OPERATION + 12),
+            Some(CellValue::Text("many".into()))
+        );
+        assert_eq!(
+            data_table.cell_value_at(3, IMPORT_LINES_PER_OPERATION + 12),
+            Some(CellValue::Text("extra".into()))
+        );
+        assert_eq!(
+            data_table.column_types(),
+            vec![
+                Some(DataTableColumnType::Text),
+                None,
+                Some(DataTableColumnType::Text),
+                Some(DataTableColumnType::Text),
+            ]
+        );
+    }
+
     #[test]
     fn import_csv_date_time() {
         let mut gc = GridController::test();
@@ -709,6 +1409,45 @@ mod test {
                 .unwrap(),
         );
         assert_display_cell_value(&gc, sheet_id, 3, 3, &value.to_string());
+
+        let data_table = gc.sheet(sheet_id).data_table(pos).unwrap();
+        assert_eq!(
+            data_table.column_types(),
+            vec![
+                Some(DataTableColumnType::Date),
+                None,
+                Some(DataTableColumnType::DateTime)
+            ]
+        );
+    }
+
+    #[test]
+    fn import_csv_column_types() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+        let pos = pos![A1];
+        let csv = "name,count,price,active\na,1,$1.50,true\nb,2,$2.00,false\nc,,$3.25,true\n";
+        gc.import_csv(
+            sheet_id,
+            csv.as_bytes().to_vec(),
+            "csv",
+            pos,
+            None,
+            Some(b','),
+            Some(true),
+        )
+        .unwrap();
+
+        let data_table = gc.sheet(sheet_id).data_table(pos).unwrap();
+        assert_eq!(
+            data_table.column_types(),
+            vec![
+                Some(DataTableColumnType::Text),
+                Some(DataTableColumnType::Number),
+                Some(DataTableColumnType::Currency),
+                Some(DataTableColumnType::Boolean),
+            ]
+        );
     }
 
     #[test]
@@ -827,6 +1566,14 @@ mod test {
                     .unwrap()
             ))
         );
+
+        // column types come from the parquet schema
+        assert_eq!(data_table.column_type(0), Some(DataTableColumnType::Date));
+        assert_eq!(data_table.column_type(1), None);
+        assert_eq!(
+            data_table.column_type(2),
+            Some(DataTableColumnType::DateTime)
+        );
     }
 
     #[test]
@@ -898,4 +1645,127 @@ mod test {
             ))
         );
     }
+
+    #[test]
+    fn import_json_array_of_objects() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let json = r#"[
+            {"name": "Ada", "age": 36, "active": true, "address": {"city": "London", "geo": {"lat": 51.5}}},
+            {"name": "Grace", "age": null, "address": {"city": "New York"}, "tags": ["a", "b"]}
+        ]"#;
+
+        gc.import_json(sheet_id, json.into(), "people.json", pos![A1], None, None)
+            .unwrap();
+
+        let data_table = gc.sheet(sheet_id).data_table(pos![A1]).unwrap();
+        assert!(data_table.header_is_first_row);
+        assert_eq!(
+            data_table
+                .column_headers
+                .iter()
+                .flatten()
+                .map(|header| header.name.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "name",
+                "age",
+                "active",
+                "address.city",
+                "address.geo.lat",
+                "tags"
+            ]
+        );
+        assert_eq!(data_table.column_type(1), Some(DataTableColumnType::Number));
+
+        let values = data_table.value_as_array().unwrap();
+        assert_eq!(values.get(0, 1).unwrap(), &CellValue::Text("Ada".into()));
+        assert_eq!(
+            values.get(4, 1).unwrap(),
+            &CellValue::Number(BigDecimal::from_str("51.5").unwrap())
+        );
+        assert_eq!(values.get(1, 2).unwrap(), &CellValue::Blank);
+        assert_eq!(values.get(2, 2).unwrap(), &CellValue::Blank);
+        assert_eq!(
+            values.get(5, 2).unwrap(),
+            &CellValue::Text(r#"["a","b"]"#.into())
+        );
+    }
+
+    #[test]
+    fn import_json_max_depth() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let json = r#"[{"a": {"b": {"c": 1}}}]"#;
+
+        gc.import_json(
+            sheet_id,
+            json.into(),
+            "nested.json",
+            pos![A1],
+            Some(1),
+            None,
+        )
+        .unwrap();
+
+        let data_table = gc.sheet(sheet_id).data_table(pos![A1]).unwrap();
+        let values = data_table.value_as_array().unwrap();
+        assert_eq!(values.get(0, 0).unwrap(), &CellValue::Text("a.b".into()));
+        assert_eq!(
+            values.get(0, 1).unwrap(),
+            &CellValue::Text(r#"{"c":1}"#.into())
+        );
+    }
+
+    #[test]
+    fn import_json_array_of_arrays() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let json =
+            r#"[["date", "amount"], ["2024-01-01", 1], ["2024-01-02", 2], ["2024-01-03", 3]]"#;
+
+        gc.import_json(sheet_id, json.into(), "rows.json", pos![A1], None, None)
+            .unwrap();
+
+        let data_table = gc.sheet(sheet_id).data_table(pos![A1]).unwrap();
+        assert!(data_table.header_is_first_row);
+        assert_eq!(
+            data_table.cell_value_at(0, 2),
+            Some(CellValue::Date(
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+            ))
+        );
+        assert_eq!(data_table.column_type(0), Some(DataTableColumnType::Date));
+    }
+
+    #[test]
+    fn import_ndjson() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let ndjson = "{\"id\": 1, \"price\": \"$1.50\"}\n\n{\"id\": 2, \"price\": \"$2.00\"}\n";
+
+        gc.import_json(sheet_id, ndjson.into(), "rows.ndjson", pos![A1], None, None)
+            .unwrap();
+
+        let data_table = gc.sheet(sheet_id).data_table(pos![A1]).unwrap();
+        assert_eq!(data_table.height(true), 3);
+        assert_eq!(
+            data_table.column_type(1),
+            Some(DataTableColumnType::Currency)
+        );
+
+        // invalid json reports the line
+        let invalid = "{\"id\": 1}\n{\"id\": }";
+        let error = gc
+            .import_json_operations(sheet_id, invalid.into(), "bad.ndjson", pos![A1], None)
+            .unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+
+        // mixed rows are rejected
+        let mixed = r#"[{"id": 1}, [1]]"#;
+        assert!(
+            gc.import_json_operations(sheet_id, mixed.into(), "mixed.json", pos![A1], None)
+                .is_err()
+        );
+    }
 }
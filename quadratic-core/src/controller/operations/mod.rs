@@ -7,8 +7,11 @@ pub mod borders;
 pub mod cell_value;
 pub mod clipboard;
 pub mod code_cell;
+pub mod csv_import;
 pub mod data_table;
 pub mod formats;
 pub mod import;
 pub mod operation;
+pub mod protection;
 pub mod sheets;
+pub mod transform;
@@ -18,6 +18,7 @@ use crate::{
                 BordersUpdates,
                 borders_old::{BorderStyleCellUpdates, SheetBorders},
             },
+            protection::SheetProtections,
             validations::validation::Validation,
         },
     },
@@ -185,6 +186,20 @@ pub enum Operation {
         // select the table after the operation
         select_table: bool,
     },
+    /// Sets (or clears) the formula of a calculated column and recomputes the
+    /// table's calculated columns.
+    SetDataTableColumnFormula {
+        sheet_pos: SheetPos,
+
+        // the column index is the actual index, not the display index
+        column_index: u32,
+
+        formula: Option<String>,
+
+        /// Values to restore when the formula is cleared (used by undo).
+        #[serde(default)]
+        values: Option<Vec<CellValue>>,
+    },
     /// Runs the code cell at a specific position.
     ComputeCode {
         sheet_pos: SheetPos,
@@ -385,4 +400,10 @@ pub enum Operation {
         #[serde(default)]
         copy_formats: CopyFormats,
     },
+
+    /// Sets a sheet's protections. Only owners may send this operation.
+    SetSheetProtections {
+        sheet_id: SheetId,
+        protections: SheetProtections,
+    },
 }
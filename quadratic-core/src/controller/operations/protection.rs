@@ -0,0 +1,410 @@
+//! Maps operations to what they edit, so they can be checked against the
+//! sheets' protections (see [`crate::grid::sheet::protection`]).
+
+use crate::{
+    Pos, Rect, SheetPos,
+    a1::{A1Selection, CellRefRange},
+    grid::{SheetId, file::sheet_schema::SheetSchema, sheet::protection::ProtectedEdit},
+    selection::OldSelection,
+};
+
+use super::operation::Operation;
+
+fn rect_edit(sheet_id: SheetId, rect: Rect) -> (SheetId, ProtectedEdit) {
+    (sheet_id, ProtectedEdit::Rect(rect))
+}
+
+fn data_table_edit(sheet_pos: &SheetPos) -> (SheetId, ProtectedEdit) {
+    (
+        sheet_pos.sheet_id,
+        ProtectedEdit::DataTable(Pos::from(*sheet_pos)),
+    )
+}
+
+fn sheet_edit(sheet_id: SheetId) -> (SheetId, ProtectedEdit) {
+    (sheet_id, ProtectedEdit::Sheet)
+}
+
+/// Every cell of the sheet
+fn all_cells_edit(sheet_id: SheetId) -> (SheetId, ProtectedEdit) {
+    rect_edit(sheet_id, Rect::new(1, 1, i64::MAX, i64::MAX))
+}
+
+/// The columns from `start` to `end`, which an insert, delete or move shifts
+fn columns_edit(sheet_id: SheetId, start: i64, end: i64) -> (SheetId, ProtectedEdit) {
+    rect_edit(sheet_id, Rect::new(start, 1, end, i64::MAX))
+}
+
+/// The rows from `start` to `end`, which an insert, delete or move shifts
+fn rows_edit(sheet_id: SheetId, start: i64, end: i64) -> (SheetId, ProtectedEdit) {
+    rect_edit(sheet_id, Rect::new(1, start, i64::MAX, end))
+}
+
+/// The range shifted by moving `start..=end` to `to`: the moved range and
+/// everything between it and where it lands.
+fn moved_range(start: i64, end: i64, to: i64) -> (i64, i64) {
+    (start.min(to), end.max(to + end - start))
+}
+
+/// The cells of a selection. Table ranges can't be resolved without the
+/// grid, so they cover every cell of the sheet.
+fn selection_edits(selection: &A1Selection) -> Vec<(SheetId, ProtectedEdit)> {
+    selection
+        .ranges
+        .iter()
+        .map(|range| match range {
+            CellRefRange::Sheet { range } => {
+                rect_edit(selection.sheet_id, range.to_rect_unbounded())
+            }
+            CellRefRange::Table { .. } => all_cells_edit(selection.sheet_id),
+        })
+        .collect()
+}
+
+/// The cells of a selection from an older file.
+fn old_selection_edits(selection: &OldSelection) -> Vec<(SheetId, ProtectedEdit)> {
+    let sheet_id = selection.sheet_id;
+    if selection.all {
+        return vec![all_cells_edit(sheet_id)];
+    }
+
+    let rects = selection.rects.iter().flatten();
+    let rows = selection.rows.iter().flatten();
+    let columns = selection.columns.iter().flatten();
+    rects
+        .map(|rect| rect_edit(sheet_id, *rect))
+        .chain(rows.map(|row| rows_edit(sheet_id, *row, *row)))
+        .chain(columns.map(|column| columns_edit(sheet_id, *column, *column)))
+        .collect()
+}
+
+impl Operation {
+    /// Returns what the operation edits, by sheet. Operations that don't
+    /// change the grid (eg, cursor changes) return nothing.
+    pub fn protected_edits(&self) -> Vec<(SheetId, ProtectedEdit)> {
+        match self {
+            Operation::SetCellValues { sheet_pos, values }
+            | Operation::SetDataTableAt { sheet_pos, values } => {
+                if values.w == 0 || values.h == 0 {
+                    return vec![];
+                }
+                let rect =
+                    Rect::from_numbers(sheet_pos.x, sheet_pos.y, values.w as i64, values.h as i64);
+                vec![rect_edit(sheet_pos.sheet_id, rect)]
+            }
+
+            Operation::SetDataTable {
+                sheet_pos,
+                data_table,
+                ..
+            } => {
+                let mut edits = vec![data_table_edit(sheet_pos)];
+                if let Some(data_table) = data_table {
+                    let rect = data_table.output_rect(Pos::from(*sheet_pos), true);
+                    edits.push(rect_edit(sheet_pos.sheet_id, rect));
+                }
+                edits
+            }
+            Operation::AddDataTable {
+                sheet_pos,
+                data_table,
+                ..
+            } => {
+                let rect = data_table.output_rect(Pos::from(*sheet_pos), true);
+                vec![
+                    data_table_edit(sheet_pos),
+                    rect_edit(sheet_pos.sheet_id, rect),
+                ]
+            }
+
+            Operation::DeleteDataTable { sheet_pos }
+            | Operation::SetChartSize { sheet_pos, .. }
+            | Operation::SetChartCellSize { sheet_pos, .. }
+            | Operation::FlattenDataTable { sheet_pos }
+            | Operation::SwitchDataTableKind { sheet_pos, .. }
+            | Operation::DataTableMeta { sheet_pos, .. }
+            | Operation::DataTableOptionMeta { sheet_pos, .. }
+            | Operation::DataTableFormats { sheet_pos, .. }
+            | Operation::DataTableBorders { sheet_pos, .. }
+            | Operation::SortDataTable { sheet_pos, .. }
+            | Operation::DataTableFirstRowAsHeader { sheet_pos, .. }
+            | Operation::InsertDataTableColumns { sheet_pos, .. }
+            | Operation::DeleteDataTableColumns { sheet_pos, .. }
+            | Operation::InsertDataTableRows { sheet_pos, .. }
+            | Operation::DeleteDataTableRows { sheet_pos, .. }
+            | Operation::SetDataTableColumnFormula { sheet_pos, .. }
+            | Operation::ComputeCode { sheet_pos } => vec![data_table_edit(sheet_pos)],
+
+            Operation::GridToDataTable { sheet_rect }
+            | Operation::SetCellFormats { sheet_rect, .. }
+            | Operation::SetBorders { sheet_rect, .. } => {
+                vec![rect_edit(sheet_rect.sheet_id, Rect::from(*sheet_rect))]
+            }
+            Operation::SetCellFormatsA1 { sheet_id, formats } => formats
+                .rects()
+                .into_iter()
+                .map(|rect| rect_edit(*sheet_id, rect))
+                .collect(),
+            Operation::SetBordersA1 { sheet_id, borders } => borders
+                .rects()
+                .into_iter()
+                .map(|rect| rect_edit(*sheet_id, rect))
+                .collect(),
+            Operation::SetCellFormatsSelection { selection, .. }
+            | Operation::SetBordersSelection { selection, .. } => old_selection_edits(selection),
+
+            Operation::AddSheet { sheet } => match sheet.protections.is_empty() {
+                true => vec![],
+                false => vec![(sheet.id, ProtectedEdit::Protections)],
+            },
+            Operation::AddSheetSchema { schema } => match schema.as_ref() {
+                SheetSchema::V1_9(schema) if !schema.protections.is_empty() => {
+                    match schema.id.id.parse::<SheetId>() {
+                        Ok(sheet_id) => vec![(sheet_id, ProtectedEdit::Protections)],
+                        Err(_) => vec![],
+                    }
+                }
+                _ => vec![],
+            },
+            Operation::SetSheetProtections { sheet_id, .. } => {
+                vec![(*sheet_id, ProtectedEdit::Protections)]
+            }
+
+            // the copy keeps the protections of the original sheet
+            Operation::DuplicateSheet { .. } => vec![],
+
+            // deleting the sheet deletes everything it protects
+            Operation::DeleteSheet { sheet_id } => vec![all_cells_edit(*sheet_id)],
+            Operation::SetSheetName { sheet_id, .. }
+            | Operation::SetSheetColor { sheet_id, .. }
+            | Operation::ReorderSheet {
+                target: sheet_id, ..
+            } => vec![sheet_edit(*sheet_id)],
+
+            Operation::ResizeColumn {
+                sheet_id, column, ..
+            } => vec![rect_edit(
+                *sheet_id,
+                Rect::new(*column, 1, *column, i64::MAX),
+            )],
+            Operation::ResizeRow { sheet_id, row, .. } => {
+                vec![rect_edit(*sheet_id, Rect::new(1, *row, i64::MAX, *row))]
+            }
+            Operation::ResizeRows {
+                sheet_id,
+                row_heights,
+            } => row_heights
+                .iter()
+                .map(|row| rect_edit(*sheet_id, Rect::new(1, row.row, i64::MAX, row.row)))
+                .collect(),
+
+            Operation::SetCursor { .. }
+            | Operation::SetCursorSelection { .. }
+            | Operation::SetCursorA1 { .. } => vec![],
+
+            Operation::MoveCells {
+                source,
+                dest,
+                columns,
+                rows,
+            } => {
+                if *columns {
+                    let (start, end) = moved_range(source.min.x, source.max.x, dest.x);
+                    return vec![
+                        columns_edit(source.sheet_id, start, end),
+                        columns_edit(dest.sheet_id, start, end),
+                    ];
+                }
+                if *rows {
+                    let (start, end) = moved_range(source.min.y, source.max.y, dest.y);
+                    return vec![
+                        rows_edit(source.sheet_id, start, end),
+                        rows_edit(dest.sheet_id, start, end),
+                    ];
+                }
+                let dest_rect = Rect::from_numbers(
+                    dest.x,
+                    dest.y,
+                    source.width() as i64,
+                    source.height() as i64,
+                );
+                vec![
+                    rect_edit(source.sheet_id, Rect::from(*source)),
+                    rect_edit(dest.sheet_id, dest_rect),
+                ]
+            }
+
+            Operation::SetValidation { validation } => selection_edits(&validation.selection),
+            Operation::RemoveValidation { sheet_id, .. } => vec![sheet_edit(*sheet_id)],
+            Operation::SetValidationWarning { sheet_pos, .. } => vec![rect_edit(
+                sheet_pos.sheet_id,
+                Rect::single_pos(Pos::from(*sheet_pos)),
+            )],
+
+            // these shift the cells after them
+            Operation::DeleteColumn {
+                sheet_id, column, ..
+            }
+            | Operation::InsertColumn {
+                sheet_id, column, ..
+            } => vec![columns_edit(*sheet_id, *column, i64::MAX)],
+            Operation::DeleteRow { sheet_id, row, .. }
+            | Operation::InsertRow { sheet_id, row, .. } => {
+                vec![rows_edit(*sheet_id, *row, i64::MAX)]
+            }
+            Operation::DeleteColumns {
+                sheet_id, columns, ..
+            } => match columns.iter().min() {
+                Some(column) => vec![columns_edit(*sheet_id, *column, i64::MAX)],
+                None => vec![],
+            },
+            Operation::DeleteRows { sheet_id, rows, .. } => match rows.iter().min() {
+                Some(row) => vec![rows_edit(*sheet_id, *row, i64::MAX)],
+                None => vec![],
+            },
+            Operation::MoveColumns {
+                sheet_id,
+                col_start,
+                col_end,
+                to,
+            } => {
+                let (start, end) = moved_range(*col_start, *col_end, *to);
+                vec![columns_edit(*sheet_id, start, end)]
+            }
+            Operation::MoveRows {
+                sheet_id,
+                row_start,
+                row_end,
+                to,
+            } => {
+                let (start, end) = moved_range(*row_start, *row_end, *to);
+                vec![rows_edit(*sheet_id, start, end)]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CellValue, SheetRect,
+        a1::A1Selection,
+        cell_values::CellValues,
+        controller::GridController,
+        grid::{formats::SheetFormatUpdates, sheet::borders::BordersUpdates},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_protected_edits() {
+        let sheet_id = SheetId::TEST;
+
+        let op = Operation::SetCellValues {
+            sheet_pos: pos![sheet_id!B2],
+            values: CellValues::new(2, 3),
+        };
+        assert_eq!(
+            op.protected_edits(),
+            vec![rect_edit(sheet_id, Rect::new(2, 2, 3, 4))]
+        );
+
+        let op = Operation::ComputeCode {
+            sheet_pos: pos![sheet_id!C3],
+        };
+        assert_eq!(
+            op.protected_edits(),
+            vec![(sheet_id, ProtectedEdit::DataTable(pos![C3]))]
+        );
+
+        let op = Operation::InsertRow {
+            sheet_id,
+            row: 3,
+            copy_formats: Default::default(),
+        };
+        assert_eq!(
+            op.protected_edits(),
+            vec![rect_edit(sheet_id, Rect::new(1, 3, i64::MAX, i64::MAX))]
+        );
+
+        let op = Operation::MoveColumns {
+            sheet_id,
+            col_start: 2,
+            col_end: 3,
+            to: 6,
+        };
+        assert_eq!(
+            op.protected_edits(),
+            vec![rect_edit(sheet_id, Rect::new(2, 1, 7, i64::MAX))]
+        );
+
+        let op = Operation::SetSheetName {
+            sheet_id,
+            name: "name".to_string(),
+        };
+        assert_eq!(op.protected_edits(), vec![sheet_edit(sheet_id)]);
+
+        let op = Operation::MoveCells {
+            source: SheetRect::new(1, 1, 2, 2, sheet_id),
+            dest: pos![sheet_id!E5],
+            columns: false,
+            rows: false,
+        };
+        assert_eq!(
+            op.protected_edits(),
+            vec![
+                rect_edit(sheet_id, Rect::new(1, 1, 2, 2)),
+                rect_edit(sheet_id, Rect::new(5, 5, 6, 6)),
+            ]
+        );
+
+        let op = Operation::SetSheetProtections {
+            sheet_id,
+            protections: Default::default(),
+        };
+        assert_eq!(
+            op.protected_edits(),
+            vec![(sheet_id, ProtectedEdit::Protections)]
+        );
+
+        let op = Operation::SetCursorA1 {
+            selection: A1Selection::test_a1("A1"),
+        };
+        assert!(op.protected_edits().is_empty());
+    }
+
+    #[test]
+    fn test_protected_edits_formats_and_borders() {
+        let gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        let selection = A1Selection::test_a1("B2:C3,E");
+        let op = Operation::SetCellFormatsA1 {
+            sheet_id,
+            formats: SheetFormatUpdates::from_selection(
+                &selection,
+                crate::grid::formats::FormatUpdate {
+                    bold: Some(Some(true)),
+                    ..Default::default()
+                },
+            ),
+        };
+        let edits = op.protected_edits();
+        assert!(edits.contains(&rect_edit(sheet_id, Rect::new(2, 2, 3, 3))));
+        assert!(edits.contains(&rect_edit(sheet_id, Rect::new(5, 1, 5, i64::MAX))));
+
+        let mut borders = BordersUpdates::default();
+        borders.set_style_cell(pos![D4], Default::default());
+        let op = Operation::SetBordersA1 { sheet_id, borders };
+        assert!(op.protected_edits().is_empty());
+
+        let op = Operation::SetCellValues {
+            sheet_pos: pos![sheet_id!A1],
+            values: CellValues::from(CellValue::Text("a".to_string())),
+        };
+        assert_eq!(
+            op.protected_edits(),
+            vec![rect_edit(sheet_id, Rect::single_pos(pos![A1]))]
+        );
+    }
+}
@@ -3,7 +3,7 @@ use lexicon_fractional_index::key_between;
 
 use crate::{
     controller::GridController,
-    grid::{Sheet, SheetId, file::sheet_schema::export_sheet},
+    grid::{Sheet, SheetId, file::sheet_schema::export_sheet, sheet::protection::SheetProtections},
     util,
 };
 
@@ -22,6 +22,17 @@ impl GridController {
         vec![Operation::SetSheetColor { sheet_id, color }]
     }
 
+    pub fn set_sheet_protections_operations(
+        &mut self,
+        sheet_id: SheetId,
+        protections: SheetProtections,
+    ) -> Vec<Operation> {
+        vec![Operation::SetSheetProtections {
+            sheet_id,
+            protections,
+        }]
+    }
+
     /// Returns all sheet names
     pub fn sheet_names(&self) -> Vec<&str> {
         self.grid.sheets().iter().map(|s| s.name.as_str()).collect()
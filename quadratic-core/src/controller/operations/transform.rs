@@ -0,0 +1,859 @@
+//! Transforms operations against operations that were applied before them.
+//!
+//! The undo and redo stacks hold the local user's operations, made against the
+//! grid as it was when the user made their edits. When another user's
+//! transaction is applied, the stacks are transformed so they still target the
+//! same cells (eg, after a column was inserted above them), and so an undo
+//! doesn't overwrite cells that the other user has since changed.
+//!
+//! Only operations that move or delete cells and sheets, and cell value
+//! changes, are transformed against. Other operations are kept as they are.
+
+use std::fmt::Debug;
+
+use crate::{
+    CopyFormats, Pos, Rect, SheetPos, SheetRect,
+    cell_values::CellValues,
+    grid::{Contiguous2D, SheetId, formats::SheetFormatUpdates, sheet::borders::BordersUpdates},
+};
+
+use super::operation::Operation;
+
+/// A column or row that was inserted or deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shift {
+    InsertColumn(i64),
+    DeleteColumn(i64),
+    InsertRow(i64),
+    DeleteRow(i64),
+}
+
+impl Shift {
+    fn is_column(self) -> bool {
+        matches!(self, Shift::InsertColumn(_) | Shift::DeleteColumn(_))
+    }
+
+    /// Adjusts a column or row along the shifted axis. Returns `None` if it
+    /// was deleted.
+    fn coord(self, coord: i64) -> Option<i64> {
+        match self {
+            Shift::InsertColumn(at) | Shift::InsertRow(at) if coord >= at => Some(coord + 1),
+            Shift::DeleteColumn(at) | Shift::DeleteRow(at) if coord == at => None,
+            Shift::DeleteColumn(at) | Shift::DeleteRow(at) if coord > at => Some(coord - 1),
+            _ => Some(coord),
+        }
+    }
+
+    /// Adjusts the position of an insertion along the shifted axis. Unlike
+    /// [`Shift::coord`], this is never deleted.
+    fn insertion(self, coord: i64) -> i64 {
+        match self {
+            Shift::DeleteColumn(at) | Shift::DeleteRow(at) if coord > at => coord - 1,
+            _ => self.coord(coord).unwrap_or(coord),
+        }
+    }
+
+    fn pos(self, pos: Pos) -> Option<Pos> {
+        Some(match self.is_column() {
+            true => Pos {
+                x: self.coord(pos.x)?,
+                y: pos.y,
+            },
+            false => Pos {
+                x: pos.x,
+                y: self.coord(pos.y)?,
+            },
+        })
+    }
+
+    fn sheet_pos(self, sheet_pos: SheetPos) -> Option<SheetPos> {
+        Some(self.pos(sheet_pos.into())?.to_sheet_pos(sheet_pos.sheet_id))
+    }
+
+    /// Adjusts a range along the shifted axis, which grows when a column or
+    /// row is inserted inside it. Returns `None` if the whole range was
+    /// deleted.
+    fn range(self, start: i64, end: i64) -> Option<(i64, i64)> {
+        let start = self.insertion(start);
+        let end = match self {
+            Shift::InsertColumn(at) | Shift::InsertRow(at) if end >= at => end + 1,
+            Shift::DeleteColumn(at) | Shift::DeleteRow(at) if end >= at => end - 1,
+            _ => end,
+        };
+        (start <= end).then_some((start, end))
+    }
+
+    fn rect(self, rect: Rect) -> Option<Rect> {
+        Some(match self.is_column() {
+            true => {
+                let (x0, x1) = self.range(rect.min.x, rect.max.x)?;
+                Rect::new(x0, rect.min.y, x1, rect.max.y)
+            }
+            false => {
+                let (y0, y1) = self.range(rect.min.y, rect.max.y)?;
+                Rect::new(rect.min.x, y0, rect.max.x, y1)
+            }
+        })
+    }
+
+    fn sheet_rect(self, sheet_rect: SheetRect) -> Option<SheetRect> {
+        Some(
+            self.rect(sheet_rect.into())?
+                .to_sheet_rect(sheet_rect.sheet_id),
+        )
+    }
+
+    fn contiguous<T: Default + Clone + PartialEq + Debug>(
+        self,
+        item: &mut Option<Contiguous2D<T>>,
+    ) {
+        if let Some(item) = item {
+            match self {
+                Shift::InsertColumn(column) => item.insert_column(column, CopyFormats::None),
+                Shift::DeleteColumn(column) => {
+                    item.remove_column(column);
+                }
+                Shift::InsertRow(row) => item.insert_row(row, CopyFormats::None),
+                Shift::DeleteRow(row) => {
+                    item.remove_row(row);
+                }
+            }
+        }
+    }
+
+    fn formats(self, formats: &mut SheetFormatUpdates) {
+        self.contiguous(&mut formats.align);
+        self.contiguous(&mut formats.vertical_align);
+        self.contiguous(&mut formats.wrap);
+        self.contiguous(&mut formats.numeric_format);
+        self.contiguous(&mut formats.numeric_decimals);
+        self.contiguous(&mut formats.numeric_commas);
+        self.contiguous(&mut formats.bold);
+        self.contiguous(&mut formats.italic);
+        self.contiguous(&mut formats.text_color);
+        self.contiguous(&mut formats.fill_color);
+        self.contiguous(&mut formats.date_time);
+        self.contiguous(&mut formats.underline);
+        self.contiguous(&mut formats.strike_through);
+    }
+
+    fn borders(self, borders: &mut BordersUpdates) {
+        self.contiguous(&mut borders.left);
+        self.contiguous(&mut borders.right);
+        self.contiguous(&mut borders.top);
+        self.contiguous(&mut borders.bottom);
+    }
+
+    /// Adjusts cell values, which are split in two when a column or row is
+    /// inserted inside them.
+    fn cell_values(self, sheet_pos: SheetPos, mut values: CellValues) -> Vec<Operation> {
+        let (start, len) = match self.is_column() {
+            true => (sheet_pos.x, values.w as i64),
+            false => (sheet_pos.y, values.h as i64),
+        };
+        let at_pos = |coord: i64| match self.is_column() {
+            true => SheetPos::new(sheet_pos.sheet_id, coord, sheet_pos.y),
+            false => SheetPos::new(sheet_pos.sheet_id, sheet_pos.x, coord),
+        };
+
+        match self {
+            Shift::InsertColumn(at) | Shift::InsertRow(at) if at > start && at < start + len => {
+                let local = Rect::from_numbers(0, 0, values.w as i64, values.h as i64);
+                let (before, after) = match self.is_column() {
+                    true => (
+                        Rect::new(0, 0, at - start - 1, local.max.y),
+                        Rect::new(at - start, 0, local.max.x, local.max.y),
+                    ),
+                    false => (
+                        Rect::new(0, 0, local.max.x, at - start - 1),
+                        Rect::new(0, at - start, local.max.x, local.max.y),
+                    ),
+                };
+                vec![
+                    Operation::SetCellValues {
+                        sheet_pos,
+                        values: cell_values_part(&values, before),
+                    },
+                    Operation::SetCellValues {
+                        sheet_pos: at_pos(at + 1),
+                        values: cell_values_part(&values, after),
+                    },
+                ]
+            }
+            Shift::DeleteColumn(at) | Shift::DeleteRow(at) if at >= start && at < start + len => {
+                if len == 1 {
+                    return vec![];
+                }
+                let index = (at - start) as u64;
+                if self.is_column() {
+                    values.columns.remove(index as usize);
+                    values.w -= 1;
+                } else {
+                    for column in values.columns.iter_mut() {
+                        *column = std::mem::take(column)
+                            .into_iter()
+                            .filter(|(y, _)| *y != index)
+                            .map(|(y, value)| (if y > index { y - 1 } else { y }, value))
+                            .collect();
+                    }
+                    values.h -= 1;
+                }
+                vec![Operation::SetCellValues { sheet_pos, values }]
+            }
+            _ => vec![Operation::SetCellValues {
+                sheet_pos: at_pos(self.insertion(start)),
+                values,
+            }],
+        }
+    }
+}
+
+/// Returns the cell values within `rect`, which is relative to the values.
+fn cell_values_part(values: &CellValues, rect: Rect) -> CellValues {
+    let mut part = CellValues::new(rect.width(), rect.height());
+    for pos in rect.iter() {
+        if let Some(value) = values.get(pos.x as u32, pos.y as u32) {
+            part.set(
+                (pos.x - rect.min.x) as u32,
+                (pos.y - rect.min.y) as u32,
+                value.clone(),
+            );
+        }
+    }
+    part
+}
+
+impl Operation {
+    /// Returns the sheet that the operation changes. Operations that add a
+    /// sheet return `None`.
+    fn sheet_id(&self) -> Option<SheetId> {
+        match self {
+            Operation::SetCellValues { sheet_pos, .. }
+            | Operation::SetDataTable { sheet_pos, .. }
+            | Operation::AddDataTable { sheet_pos, .. }
+            | Operation::DeleteDataTable { sheet_pos }
+            | Operation::SetChartSize { sheet_pos, .. }
+            | Operation::SetChartCellSize { sheet_pos, .. }
+            | Operation::SetDataTableAt { sheet_pos, .. }
+            | Operation::FlattenDataTable { sheet_pos }
+            | Operation::SwitchDataTableKind { sheet_pos, .. }
+            | Operation::DataTableMeta { sheet_pos, .. }
+            | Operation::DataTableOptionMeta { sheet_pos, .. }
+            | Operation::DataTableFormats { sheet_pos, .. }
+            | Operation::DataTableBorders { sheet_pos, .. }
+            | Operation::SortDataTable { sheet_pos, .. }
+            | Operation::DataTableFirstRowAsHeader { sheet_pos, .. }
+            | Operation::InsertDataTableColumns { sheet_pos, .. }
+            | Operation::DeleteDataTableColumns { sheet_pos, .. }
+            | Operation::InsertDataTableRows { sheet_pos, .. }
+            | Operation::DeleteDataTableRows { sheet_pos, .. }
+            | Operation::SetDataTableColumnFormula { sheet_pos, .. }
+            | Operation::ComputeCode { sheet_pos }
+            | Operation::SetValidationWarning { sheet_pos, .. } => Some(sheet_pos.sheet_id),
+
+            Operation::GridToDataTable { sheet_rect }
+            | Operation::SetCellFormats { sheet_rect, .. }
+            | Operation::SetBorders { sheet_rect, .. }
+            | Operation::SetCursor { sheet_rect } => Some(sheet_rect.sheet_id),
+            Operation::MoveCells { source, .. } => Some(source.sheet_id),
+
+            Operation::SetCellFormatsSelection { selection, .. }
+            | Operation::SetBordersSelection { selection, .. }
+            | Operation::SetCursorSelection { selection } => Some(selection.sheet_id),
+            Operation::SetCursorA1 { selection } => Some(selection.sheet_id),
+            Operation::SetValidation { validation } => Some(validation.selection.sheet_id),
+
+            Operation::AddSheet { .. } | Operation::AddSheetSchema { .. } => None,
+
+            Operation::SetCellFormatsA1 { sheet_id, .. }
+            | Operation::SetBordersA1 { sheet_id, .. }
+            | Operation::DuplicateSheet { sheet_id, .. }
+            | Operation::DeleteSheet { sheet_id }
+            | Operation::SetSheetName { sheet_id, .. }
+            | Operation::SetSheetColor { sheet_id, .. }
+            | Operation::ReorderSheet {
+                target: sheet_id, ..
+            }
+            | Operation::ResizeColumn { sheet_id, .. }
+            | Operation::ResizeRow { sheet_id, .. }
+            | Operation::ResizeRows { sheet_id, .. }
+            | Operation::RemoveValidation { sheet_id, .. }
+            | Operation::DeleteColumn { sheet_id, .. }
+            | Operation::DeleteRow { sheet_id, .. }
+            | Operation::InsertColumn { sheet_id, .. }
+            | Operation::InsertRow { sheet_id, .. }
+            | Operation::MoveColumns { sheet_id, .. }
+            | Operation::MoveRows { sheet_id, .. }
+            | Operation::DeleteColumns { sheet_id, .. }
+            | Operation::DeleteRows { sheet_id, .. }
+            | Operation::SetSheetProtections { sheet_id, .. } => Some(*sheet_id),
+        }
+    }
+
+    /// Returns whether other operations are transformed against this one.
+    pub fn transforms_others(&self) -> bool {
+        matches!(
+            self,
+            Operation::DeleteSheet { .. }
+                | Operation::InsertColumn { .. }
+                | Operation::DeleteColumn { .. }
+                | Operation::InsertRow { .. }
+                | Operation::DeleteRow { .. }
+                | Operation::DeleteColumns { .. }
+                | Operation::DeleteRows { .. }
+                | Operation::SetCellValues { .. }
+        )
+    }
+
+    /// Transforms an undo or redo operation against `applied`, which is an
+    /// operation from another user. Returns the operations to apply instead,
+    /// which may be empty (eg, if its sheet was deleted).
+    ///
+    /// `unsaved` is whether the operation reverts a transaction that the
+    /// server hasn't saved yet. That transaction is ordered after `applied`
+    /// and was reapplied on top of it, so the operation is not shifted, and it
+    /// restores the other user's values instead of keeping them.
+    pub fn transform(self, applied: &Operation, unsaved: bool) -> Vec<Operation> {
+        match applied {
+            Operation::DeleteSheet { sheet_id } => match self.sheet_id() == Some(*sheet_id) {
+                true => vec![],
+                false => vec![self],
+            },
+            Operation::SetCellValues { sheet_pos, values } if unsaved => {
+                vec![self.with_cells(*sheet_pos, values)]
+            }
+            _ if unsaved => vec![self],
+
+            Operation::InsertColumn {
+                sheet_id, column, ..
+            } => self.shift(*sheet_id, Shift::InsertColumn(*column)),
+            Operation::DeleteColumn {
+                sheet_id, column, ..
+            } => self.shift(*sheet_id, Shift::DeleteColumn(*column)),
+            Operation::InsertRow { sheet_id, row, .. } => {
+                self.shift(*sheet_id, Shift::InsertRow(*row))
+            }
+            Operation::DeleteRow { sheet_id, row, .. } => {
+                self.shift(*sheet_id, Shift::DeleteRow(*row))
+            }
+
+            // columns and rows are deleted from last to first
+            Operation::DeleteColumns {
+                sheet_id, columns, ..
+            } => Self::shift_all(self, *sheet_id, columns, Shift::DeleteColumn),
+            Operation::DeleteRows { sheet_id, rows, .. } => {
+                Self::shift_all(self, *sheet_id, rows, Shift::DeleteRow)
+            }
+
+            Operation::SetCellValues { sheet_pos, values } => {
+                let rect =
+                    Rect::from_numbers(sheet_pos.x, sheet_pos.y, values.w as i64, values.h as i64);
+                self.without_cells(sheet_pos.sheet_id, rect)
+            }
+
+            _ => vec![self],
+        }
+    }
+
+    fn shift_all(self, sheet_id: SheetId, coords: &[i64], shift: fn(i64) -> Shift) -> Vec<Self> {
+        let mut coords = coords.to_vec();
+        coords.sort_unstable();
+        coords.dedup();
+        coords.into_iter().rev().fold(vec![self], |ops, coord| {
+            ops.into_iter()
+                .flat_map(|op| op.shift(sheet_id, shift(coord)))
+                .collect()
+        })
+    }
+
+    /// Returns the cell that the operation is anchored to, for operations on a
+    /// single data table or cell.
+    fn anchor_mut(&mut self) -> Option<&mut SheetPos> {
+        match self {
+            Operation::SetDataTable { sheet_pos, .. }
+            | Operation::AddDataTable { sheet_pos, .. }
+            | Operation::DeleteDataTable { sheet_pos }
+            | Operation::SetChartSize { sheet_pos, .. }
+            | Operation::SetChartCellSize { sheet_pos, .. }
+            | Operation::SetDataTableAt { sheet_pos, .. }
+            | Operation::FlattenDataTable { sheet_pos }
+            | Operation::SwitchDataTableKind { sheet_pos, .. }
+            | Operation::DataTableMeta { sheet_pos, .. }
+            | Operation::DataTableOptionMeta { sheet_pos, .. }
+            | Operation::DataTableFormats { sheet_pos, .. }
+            | Operation::DataTableBorders { sheet_pos, .. }
+            | Operation::SortDataTable { sheet_pos, .. }
+            | Operation::DataTableFirstRowAsHeader { sheet_pos, .. }
+            | Operation::InsertDataTableColumns { sheet_pos, .. }
+            | Operation::DeleteDataTableColumns { sheet_pos, .. }
+            | Operation::InsertDataTableRows { sheet_pos, .. }
+            | Operation::DeleteDataTableRows { sheet_pos, .. }
+            | Operation::SetDataTableColumnFormula { sheet_pos, .. }
+            | Operation::ComputeCode { sheet_pos }
+            | Operation::SetValidationWarning { sheet_pos, .. } => Some(sheet_pos),
+            _ => None,
+        }
+    }
+
+    /// Shifts the cells that the operation changes after a column or row was
+    /// inserted or deleted. Operations anchored to a deleted cell are dropped.
+    fn shift(mut self, sheet_id: SheetId, shift: Shift) -> Vec<Self> {
+        if self.sheet_id() != Some(sheet_id) {
+            return match self {
+                // a move may still target the shifted sheet
+                Operation::MoveCells {
+                    source,
+                    dest,
+                    columns,
+                    rows,
+                } if dest.sheet_id == sheet_id => shift
+                    .sheet_pos(dest)
+                    .map(|dest| Operation::MoveCells {
+                        source,
+                        dest,
+                        columns,
+                        rows,
+                    })
+                    .into_iter()
+                    .collect(),
+                op => vec![op],
+            };
+        }
+
+        if let Some(sheet_pos) = self.anchor_mut() {
+            return match shift.sheet_pos(*sheet_pos) {
+                Some(shifted) => {
+                    *sheet_pos = shifted;
+                    vec![self]
+                }
+                None => vec![],
+            };
+        }
+
+        let op = match self {
+            Operation::SetCellValues { sheet_pos, values } => {
+                return shift.cell_values(sheet_pos, values);
+            }
+
+            Operation::GridToDataTable { sheet_rect } => shift
+                .sheet_rect(sheet_rect)
+                .map(|sheet_rect| Operation::GridToDataTable { sheet_rect }),
+            Operation::MoveCells {
+                source,
+                dest,
+                columns,
+                rows,
+            } => {
+                let dest = match dest.sheet_id == sheet_id {
+                    true => shift.sheet_pos(dest),
+                    false => Some(dest),
+                };
+                shift
+                    .sheet_rect(source)
+                    .zip(dest)
+                    .map(|(source, dest)| Operation::MoveCells {
+                        source,
+                        dest,
+                        columns,
+                        rows,
+                    })
+            }
+
+            Operation::SetCellFormatsA1 {
+                sheet_id,
+                mut formats,
+            } => {
+                shift.formats(&mut formats);
+                Some(Operation::SetCellFormatsA1 { sheet_id, formats })
+            }
+            Operation::SetBordersA1 {
+                sheet_id,
+                mut borders,
+            } => {
+                shift.borders(&mut borders);
+                Some(Operation::SetBordersA1 { sheet_id, borders })
+            }
+
+            Operation::ResizeColumn {
+                sheet_id,
+                column,
+                new_size,
+                client_resized,
+            } if shift.is_column() => shift.coord(column).map(|column| Operation::ResizeColumn {
+                sheet_id,
+                column,
+                new_size,
+                client_resized,
+            }),
+            Operation::ResizeRow {
+                sheet_id,
+                row,
+                new_size,
+                client_resized,
+            } if !shift.is_column() => shift.coord(row).map(|row| Operation::ResizeRow {
+                sheet_id,
+                row,
+                new_size,
+                client_resized,
+            }),
+            Operation::ResizeRows {
+                sheet_id,
+                mut row_heights,
+            } if !shift.is_column() => {
+                row_heights.retain_mut(|row_height| match shift.coord(row_height.row) {
+                    Some(row) => {
+                        row_height.row = row;
+                        true
+                    }
+                    None => false,
+                });
+                (!row_heights.is_empty()).then_some(Operation::ResizeRows {
+                    sheet_id,
+                    row_heights,
+                })
+            }
+
+            Operation::InsertColumn {
+                sheet_id,
+                column,
+                copy_formats,
+            } if shift.is_column() => Some(Operation::InsertColumn {
+                sheet_id,
+                column: shift.insertion(column),
+                copy_formats,
+            }),
+            Operation::InsertRow {
+                sheet_id,
+                row,
+                copy_formats,
+            } if !shift.is_column() => Some(Operation::InsertRow {
+                sheet_id,
+                row: shift.insertion(row),
+                copy_formats,
+            }),
+            Operation::DeleteColumn {
+                sheet_id,
+                column,
+                copy_formats,
+            } if shift.is_column() => shift.coord(column).map(|column| Operation::DeleteColumn {
+                sheet_id,
+                column,
+                copy_formats,
+            }),
+            Operation::DeleteRow {
+                sheet_id,
+                row,
+                copy_formats,
+            } if !shift.is_column() => shift.coord(row).map(|row| Operation::DeleteRow {
+                sheet_id,
+                row,
+                copy_formats,
+            }),
+            Operation::DeleteColumns {
+                sheet_id,
+                columns,
+                copy_formats,
+            } if shift.is_column() => {
+                let columns: Vec<_> = columns.into_iter().filter_map(|c| shift.coord(c)).collect();
+                (!columns.is_empty()).then_some(Operation::DeleteColumns {
+                    sheet_id,
+                    columns,
+                    copy_formats,
+                })
+            }
+            Operation::DeleteRows {
+                sheet_id,
+                rows,
+                copy_formats,
+            } if !shift.is_column() => {
+                let rows: Vec<_> = rows.into_iter().filter_map(|r| shift.coord(r)).collect();
+                (!rows.is_empty()).then_some(Operation::DeleteRows {
+                    sheet_id,
+                    rows,
+                    copy_formats,
+                })
+            }
+            Operation::MoveColumns {
+                sheet_id,
+                col_start,
+                col_end,
+                to,
+            } if shift.is_column() => {
+                shift
+                    .range(col_start, col_end)
+                    .map(|(col_start, col_end)| Operation::MoveColumns {
+                        sheet_id,
+                        col_start,
+                        col_end,
+                        to: shift.insertion(to),
+                    })
+            }
+            Operation::MoveRows {
+                sheet_id,
+                row_start,
+                row_end,
+                to,
+            } if !shift.is_column() => {
+                shift
+                    .range(row_start, row_end)
+                    .map(|(row_start, row_end)| Operation::MoveRows {
+                        sheet_id,
+                        row_start,
+                        row_end,
+                        to: shift.insertion(to),
+                    })
+            }
+
+            op => Some(op),
+        };
+        op.into_iter().collect()
+    }
+
+    /// Replaces the values that the operation sets with `applied_values`
+    /// where they overlap.
+    fn with_cells(self, applied_pos: SheetPos, applied_values: &CellValues) -> Self {
+        let Operation::SetCellValues {
+            sheet_pos,
+            mut values,
+        } = self
+        else {
+            return self;
+        };
+        let own = Rect::from_numbers(sheet_pos.x, sheet_pos.y, values.w as i64, values.h as i64);
+        let applied = Rect::from_numbers(
+            applied_pos.x,
+            applied_pos.y,
+            applied_values.w as i64,
+            applied_values.h as i64,
+        );
+        if sheet_pos.sheet_id == applied_pos.sheet_id
+            && let Some(overlap) = own.intersection(&applied)
+        {
+            for pos in overlap.iter() {
+                let (x, y) = ((pos.x - own.min.x) as u32, (pos.y - own.min.y) as u32);
+                match applied_values.get(
+                    (pos.x - applied.min.x) as u32,
+                    (pos.y - applied.min.y) as u32,
+                ) {
+                    Some(value) => values.set(x, y, value.clone()),
+                    None => {
+                        values.remove(x, y);
+                    }
+                }
+            }
+        }
+        Operation::SetCellValues { sheet_pos, values }
+    }
+
+    /// Removes cells in `rect` from the values that the operation sets, so
+    /// they keep the values that another user set.
+    fn without_cells(self, sheet_id: SheetId, rect: Rect) -> Vec<Self> {
+        let Operation::SetCellValues { sheet_pos, values } = self else {
+            return vec![self];
+        };
+        let own = Rect::from_numbers(sheet_pos.x, sheet_pos.y, values.w as i64, values.h as i64);
+        if sheet_pos.sheet_id != sheet_id || !own.intersects(rect) {
+            return vec![Operation::SetCellValues { sheet_pos, values }];
+        }
+        own.subtract(rect)
+            .into_iter()
+            .map(|part| {
+                let mut local = part;
+                local.translate(-own.min.x, -own.min.y);
+                Operation::SetCellValues {
+                    sheet_pos: part.min.to_sheet_pos(sheet_id),
+                    values: cell_values_part(&values, local),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellValue, grid::formats::FormatUpdate};
+
+    fn set_values(sheet_pos: SheetPos, values: Vec<Vec<&str>>) -> Operation {
+        Operation::SetCellValues {
+            sheet_pos,
+            values: CellValues::from(values),
+        }
+    }
+
+    #[test]
+    fn test_transform_insert_and_delete_column() {
+        let sheet_id = SheetId::TEST;
+        let op = set_values(pos![sheet_id!B2], vec![vec!["a"], vec!["b"]]);
+
+        // inserting before the values shifts them
+        let insert = Operation::InsertColumn {
+            sheet_id,
+            column: 1,
+            copy_formats: CopyFormats::None,
+        };
+        assert_eq!(
+            op.clone().transform(&insert, false),
+            vec![set_values(pos![sheet_id!C2], vec![vec!["a"], vec!["b"]])]
+        );
+
+        // inserting inside the values splits them
+        let insert = Operation::InsertColumn {
+            sheet_id,
+            column: 3,
+            copy_formats: CopyFormats::None,
+        };
+        assert_eq!(
+            op.clone().transform(&insert, false),
+            vec![
+                set_values(pos![sheet_id!B2], vec![vec!["a"]]),
+                set_values(pos![sheet_id!D2], vec![vec!["b"]]),
+            ]
+        );
+
+        // deleting a column removes its values
+        let delete = Operation::DeleteColumns {
+            sheet_id,
+            columns: vec![1, 2],
+            copy_formats: CopyFormats::None,
+        };
+        assert_eq!(
+            op.clone().transform(&delete, false),
+            vec![set_values(pos![sheet_id!A2], vec![vec!["b"]])]
+        );
+
+        // other sheets are unchanged
+        let delete = Operation::DeleteColumn {
+            sheet_id: SheetId::new(),
+            column: 1,
+            copy_formats: CopyFormats::None,
+        };
+        assert_eq!(op.clone().transform(&delete, false), vec![op]);
+    }
+
+    #[test]
+    fn test_transform_delete_row() {
+        let sheet_id = SheetId::TEST;
+        let op = set_values(pos![sheet_id!A2], vec![vec!["a", "b", "c"]]);
+        let delete = Operation::DeleteRow {
+            sheet_id,
+            row: 3,
+            copy_formats: CopyFormats::None,
+        };
+        assert_eq!(
+            op.transform(&delete, false),
+            vec![set_values(pos![sheet_id!A2], vec![vec!["a", "c"]])]
+        );
+
+        let op = Operation::ComputeCode {
+            sheet_pos: pos![sheet_id!B3],
+        };
+        assert_eq!(op.transform(&delete, false), vec![]);
+
+        let op = Operation::ResizeRow {
+            sheet_id,
+            row: 5,
+            new_size: 10.0,
+            client_resized: false,
+        };
+        assert_eq!(
+            op.transform(&delete, false),
+            vec![Operation::ResizeRow {
+                sheet_id,
+                row: 4,
+                new_size: 10.0,
+                client_resized: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_transform_formats() {
+        let sheet_id = SheetId::TEST;
+        let mut formats = SheetFormatUpdates::default();
+        formats.set_format_cell(
+            pos![B2],
+            FormatUpdate {
+                bold: Some(Some(true)),
+                ..Default::default()
+            },
+        );
+        let op = Operation::SetCellFormatsA1 { sheet_id, formats };
+        let insert = Operation::InsertRow {
+            sheet_id,
+            row: 1,
+            copy_formats: CopyFormats::None,
+        };
+        let Operation::SetCellFormatsA1 { formats, .. } = &op.transform(&insert, false)[0] else {
+            panic!("Expected SetCellFormatsA1");
+        };
+        assert_eq!(formats.rects(), vec![Rect::test_a1("B3")]);
+    }
+
+    #[test]
+    fn test_transform_delete_sheet() {
+        let sheet_id = SheetId::TEST;
+        let delete = Operation::DeleteSheet { sheet_id };
+        let op = set_values(pos![sheet_id!A1], vec![vec!["a"]]);
+        assert_eq!(op.transform(&delete, false), vec![]);
+
+        let other_sheet_id = SheetId::new();
+        let op = set_values(pos![other_sheet_id!A1], vec![vec!["a"]]);
+        assert_eq!(op.clone().transform(&delete, false), vec![op]);
+    }
+
+    #[test]
+    fn test_transform_set_cell_values() {
+        let sheet_id = SheetId::TEST;
+        let op = set_values(pos![sheet_id!A1], vec![vec!["a", "b"], vec!["c", "d"]]);
+
+        // cells set by the other user are removed
+        let applied = set_values(pos![sheet_id!B2], vec![vec!["x"]]);
+        let ops = op.transform(&applied, false);
+        let cells: Vec<(Pos, CellValue)> = ops
+            .iter()
+            .flat_map(|op| match op {
+                Operation::SetCellValues { sheet_pos, values } => values
+                    .into_iter()
+                    .map(|(x, y, value)| {
+                        (
+                            Pos {
+                                x: sheet_pos.x + x as i64,
+                                y: sheet_pos.y + y as i64,
+                            },
+                            value.clone(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+                _ => vec![],
+            })
+            .collect();
+        assert_eq!(cells.len(), 3);
+        assert!(!cells.iter().any(|(pos, _)| *pos == pos![B2]));
+        assert!(cells.contains(&(pos![A2], CellValue::Text("b".into()))));
+    }
+
+    #[test]
+    fn test_transform_unsaved() {
+        let sheet_id = SheetId::TEST;
+        let op = set_values(pos![sheet_id!A1], vec![vec!["a", "b"]]);
+
+        // the other user's values are restored
+        let applied = set_values(pos![sheet_id!A2], vec![vec!["x", "y"]]);
+        assert_eq!(
+            op.clone().transform(&applied, true),
+            vec![set_values(pos![sheet_id!A1], vec![vec!["a", "x"]])]
+        );
+
+        // unsaved operations are not shifted
+        let insert = Operation::InsertRow {
+            sheet_id,
+            row: 1,
+            copy_formats: CopyFormats::None,
+        };
+        assert_eq!(op.clone().transform(&insert, true), vec![op.clone()]);
+
+        let delete = Operation::DeleteSheet { sheet_id };
+        assert_eq!(op.transform(&delete, true), vec![]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Enforces sheet protections for the local user.
+//!
+//! The protection user is set by the client once the user's permissions are
+//! known. Without one (eg, when running tests or on the server), operations
+//! are not checked. Multiplayer also checks transactions before accepting
+//! them, so this only prevents the local user from making a blocked edit.
+
+use crate::grid::{js_types::JsSnackbarSeverity, sheet::protection::ProtectionUser};
+
+use super::{GridController, operations::operation::Operation};
+
+impl GridController {
+    pub fn protection_user(&self) -> Option<&ProtectionUser> {
+        self.protection_user.as_ref()
+    }
+
+    pub fn set_protection_user(&mut self, user: Option<ProtectionUser>) {
+        self.protection_user = user;
+    }
+
+    /// Checks the operations against the sheets' protections. Returns the
+    /// message explaining the first blocked edit.
+    pub(crate) fn check_protections(&self, operations: &[Operation]) -> Result<(), String> {
+        let Some(user) = self.protection_user.as_ref() else {
+            return Ok(());
+        };
+
+        for op in operations.iter() {
+            for (sheet_id, edit) in op.protected_edits() {
+                let Some(sheet) = self.try_sheet(sheet_id) else {
+                    continue;
+                };
+                sheet.protections.check(user, &edit, |pos| {
+                    sheet
+                        .data_table(pos)
+                        .map(|data_table| data_table.output_rect(pos, false))
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the operations against the sheets' protections, and notifies
+    /// the client if an edit is blocked. Returns true if the operations may
+    /// be run.
+    pub(crate) fn allowed_by_protections(&self, operations: &[Operation]) -> bool {
+        match self.check_protections(operations) {
+            Ok(()) => true,
+            Err(e) => {
+                if cfg!(target_family = "wasm") || cfg!(test) {
+                    crate::wasm_bindings::js::jsClientMessage(
+                        e,
+                        JsSnackbarSeverity::Error.to_string(),
+                    );
+                }
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{
+        Rect,
+        grid::sheet::protection::{
+            Protection, ProtectionAllowList, ProtectionRole, ProtectionTarget, SheetProtections,
+        },
+        test_util::*,
+        wasm_bindings::js::{clear_js_calls, expect_js_call},
+    };
+
+    use super::*;
+
+    fn editor() -> ProtectionUser {
+        ProtectionUser {
+            user_id: "editor".to_string(),
+            roles: vec![ProtectionRole::Editor],
+        }
+    }
+
+    fn lock_range(gc: &mut GridController, rect: Rect) {
+        let sheet_id = first_sheet_id(gc);
+        gc.set_sheet_protections(
+            sheet_id,
+            SheetProtections {
+                protections: vec![Protection {
+                    id: Uuid::new_v4(),
+                    target: ProtectionTarget::Range(rect),
+                    allow: ProtectionAllowList::default(),
+                }],
+            },
+            None,
+        );
+    }
+
+    #[test]
+    fn test_protected_range() {
+        clear_js_calls();
+        let mut gc = test_create_gc();
+        let sheet_id = first_sheet_id(&gc);
+        lock_range(&mut gc, Rect::new(1, 1, 2, 2));
+        gc.set_protection_user(Some(editor()));
+
+        gc.set_cell_value(pos![sheet_id!A1], "locked".to_string(), None);
+        expect_js_call(
+            "jsClientMessage",
+            format!("This range is locked.,{}", JsSnackbarSeverity::Error),
+            true,
+        );
+        assert_display_cell_value(&gc, sheet_id, 1, 1, "");
+
+        gc.set_cell_value(pos![sheet_id!C3], "open".to_string(), None);
+        assert_display_cell_value(&gc, sheet_id, 3, 3, "open");
+
+        // editors can't change protections
+        let protections = gc.sheet(sheet_id).protections.clone();
+        gc.set_sheet_protections(sheet_id, SheetProtections::default(), None);
+        assert_eq!(gc.sheet(sheet_id).protections, protections);
+
+        // inserting a row would shift the locked range
+        gc.insert_row(sheet_id, 1, false, None);
+        assert_display_cell_value(&gc, sheet_id, 3, 3, "open");
+
+        // inserting a row below it doesn't
+        gc.insert_row(sheet_id, 3, false, None);
+        assert_display_cell_value(&gc, sheet_id, 3, 4, "open");
+    }
+
+    #[test]
+    fn test_protected_undo() {
+        let mut gc = test_create_gc();
+        let sheet_id = first_sheet_id(&gc);
+
+        gc.set_cell_value(pos![sheet_id!A1], "before".to_string(), None);
+        lock_range(&mut gc, Rect::new(1, 1, 1, 1));
+        gc.undo(None);
+        assert!(gc.sheet(sheet_id).protections.is_empty());
+
+        // redo the protection, then undo the cell value as an editor
+        gc.redo(None);
+        gc.set_protection_user(Some(editor()));
+        gc.undo(None);
+        gc.undo(None);
+        assert!(!gc.sheet(sheet_id).protections.is_empty());
+        assert_display_cell_value(&gc, sheet_id, 1, 1, "before");
+
+        // the blocked undo is kept for owners
+        gc.set_protection_user(None);
+        gc.undo(None);
+        assert!(gc.sheet(sheet_id).protections.is_empty());
+    }
+}
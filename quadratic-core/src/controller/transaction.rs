@@ -8,8 +8,9 @@ use super::active_transactions::pending_transaction::PendingTransaction;
 use super::execution::TransactionSource;
 use super::operations::operation::Operation;
 use crate::compression::{
-    CompressionFormat, SerializationFormat, add_header, decompress_and_deserialize, deserialize,
-    remove_header, serialize, serialize_and_compress,
+    CompressionFormat, SerializationFormat, add_header, decompress_and_deserialize,
+    decompress_and_deserialize_with_limit, deserialize, remove_header, serialize,
+    serialize_and_compress,
 };
 
 pub static SERIALIZATION_FORMAT: SerializationFormat = SerializationFormat::Json;
@@ -74,6 +75,24 @@ impl Transaction {
 
         decompress_and_deserialize::<T>(&SERIALIZATION_FORMAT, &COMPRESSION_FORMAT, data)
     }
+
+    /// Like `decompress_and_deserialize`, but fails if the decompressed
+    /// operations are larger than `max_bytes`.  Used to validate operations
+    /// received from untrusted clients.
+    pub fn decompress_and_deserialize_with_limit<T: DeserializeOwned>(
+        operations: &[u8],
+        max_bytes: usize,
+    ) -> Result<T> {
+        let (header, data) = remove_header(operations)?;
+        let _version = deserialize::<TransactionVersion>(&HEADER_SERIALIZATION_FORMAT, header)?;
+
+        decompress_and_deserialize_with_limit::<T>(
+            &SERIALIZATION_FORMAT,
+            &COMPRESSION_FORMAT,
+            data,
+            max_bytes,
+        )
+    }
 }
 
 // Transaction received from Server
@@ -1,6 +1,7 @@
 use crate::controller::GridController;
 use crate::controller::active_transactions::transaction_name::TransactionName;
-use crate::{SheetPos, a1::A1Selection};
+use crate::grid::js_types::JsSnackbarSeverity;
+use crate::{CellValue, Pos, SheetPos, a1::A1Selection};
 
 impl GridController {
     /// Starts a transaction to set the value of a cell by converting a user's String input
@@ -15,6 +16,19 @@ impl GridController {
         values: Vec<Vec<String>>,
         cursor: Option<String>,
     ) {
+        // reject the whole edit if a value does not fit its data table column
+        if let Some(table_name) = self.column_type_rejection(sheet_pos, &values) {
+            if cfg!(target_family = "wasm") || cfg!(test) {
+                crate::wasm_bindings::js::jsClientMessage(
+                    format!(
+                        "Some values do not match the column type in {table_name} and were not set."
+                    ),
+                    JsSnackbarSeverity::Error.to_string(),
+                );
+            }
+            return;
+        }
+
         // TODO(ddimaria): implement actuall error bubbling and remove this dbgjs! and return a Result
         match self.set_cell_values_operations(sheet_pos, values) {
             Ok((ops, data_table_ops)) => {
@@ -28,6 +42,30 @@ impl GridController {
         }
     }
 
+    /// Returns the name of the data table whose column type cannot hold one
+    /// of the user's values. Values that can be coerced are accepted.
+    fn column_type_rejection(&self, sheet_pos: SheetPos, values: &[Vec<String>]) -> Option<String> {
+        let sheet = self.try_sheet(sheet_pos.sheet_id)?;
+
+        values.iter().enumerate().find_map(|(y, row)| {
+            row.iter().enumerate().find_map(|(x, value)| {
+                let pos = Pos::new(sheet_pos.x + x as i64, sheet_pos.y + y as i64);
+                let (data_table, column_type) = sheet.data_table_column_type_at(pos)?;
+                let (cell_value, _) = CellValue::string_to_cell_value(value.trim(), true);
+
+                // code replaces the table's source cell rather than a value
+                if matches!(cell_value, CellValue::Code(_)) {
+                    return None;
+                }
+
+                column_type
+                    .coerce(cell_value)
+                    .is_none()
+                    .then(|| data_table.name().to_string())
+            })
+        })
+    }
+
     /// Starts a transaction to deletes the cell values and code in a given rect and updates dependent cells.
     pub fn delete_cells(&mut self, selection: &A1Selection, cursor: Option<String>) {
         let ops = self.delete_cells_operations(selection, true);
@@ -57,11 +95,12 @@ mod test {
         CellValue, Pos, Rect, SheetPos,
         a1::A1Selection,
         controller::{GridController, user_actions::import::tests::simple_csv_at},
-        grid::{NumericFormat, SheetId, sort::SortDirection},
+        grid::{NumericFormat, SheetId, js_types::JsSnackbarSeverity, sort::SortDirection},
         test_util::{
-            assert_cell_value_col, assert_cell_value_row, assert_display_cell_value_pos,
-            print_sheet, print_table_in_rect, str_vec_to_string_vec,
+            assert_cell_value_col, assert_cell_value_row, assert_display_cell_value,
+            assert_display_cell_value_pos, print_sheet, print_table_in_rect, str_vec_to_string_vec,
         },
+        wasm_bindings::js::{clear_js_calls, expect_js_call},
     };
     use std::str::FromStr;
 
@@ -754,4 +793,37 @@ mod test {
         assert_cell_value_col(&gc, sheet_id, 5, 3, 5, vec!["a", "b", "c"]);
         assert_cell_value_col(&gc, sheet_id, 6, 3, 5, vec!["d", "e", "f"]);
     }
+
+    #[test]
+    fn test_set_cell_values_column_type_rejection() {
+        clear_js_calls();
+        let (mut gc, sheet_id, pos, _) = simple_csv_at(pos![E2]);
+        let table_name = gc
+            .sheet(sheet_id)
+            .data_table(pos)
+            .unwrap()
+            .name()
+            .to_string();
+        let undo_len = gc.undo_stack().len();
+
+        // population is a number column
+        gc.set_cell_value(pos![sheet_id!H4], "abc".to_string(), None);
+        expect_js_call(
+            "jsClientMessage",
+            format!(
+                "Some values do not match the column type in {table_name} and were not set.,{}",
+                JsSnackbarSeverity::Error
+            ),
+            true,
+        );
+        assert_display_cell_value(&gc, sheet_id, 8, 4, "9686");
+
+        // no operations were created for the rejected edit
+        assert_eq!(gc.undo_stack().len(), undo_len);
+
+        // values that can be coerced are set
+        gc.set_cell_value(pos![sheet_id!H4], "123".to_string(), None);
+        assert_display_cell_value(&gc, sheet_id, 8, 4, "123");
+        assert_eq!(gc.undo_stack().len(), undo_len + 1);
+    }
 }
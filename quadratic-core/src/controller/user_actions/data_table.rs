@@ -132,6 +132,19 @@ impl GridController {
         self.start_user_transaction(ops, cursor, TransactionName::GridToDataTable);
     }
 
+    /// Sets (or clears) the formula of a calculated column. The column index
+    /// is the actual index, not the display index.
+    pub fn data_table_set_column_formula(
+        &mut self,
+        sheet_pos: SheetPos,
+        column_index: u32,
+        formula: Option<String>,
+        cursor: Option<String>,
+    ) {
+        let ops = self.data_table_set_column_formula_operations(sheet_pos, column_index, formula);
+        self.start_user_transaction(ops, cursor, TransactionName::DataTableMutations);
+    }
+
     pub fn data_table_first_row_as_header(
         &mut self,
         sheet_pos: SheetPos,
@@ -1,8 +1,11 @@
+use std::io::Read;
+
 use anyhow::Result;
 
 use crate::Pos;
 use crate::controller::GridController;
 use crate::controller::active_transactions::transaction_name::TransactionName;
+use crate::controller::operations::csv_import::CsvImportOptions;
 use crate::grid::SheetId;
 
 impl GridController {
@@ -37,6 +40,39 @@ impl GridController {
         Ok(())
     }
 
+    /// Imports a CSV file into the grid using the given dialect, encoding and
+    /// column type options. The file is decoded as it is read from `file`;
+    /// `total_bytes` is only used to report progress.
+    ///
+    /// Using `cursor` here also as a flag to denote import into new / existing file.
+    #[allow(clippy::too_many_arguments)]
+    pub fn import_csv_with_options(
+        &mut self,
+        sheet_id: SheetId,
+        file: impl Read,
+        total_bytes: Option<u64>,
+        file_name: &str,
+        insert_at: Pos,
+        options: &CsvImportOptions,
+        cursor: Option<String>,
+    ) -> Result<()> {
+        let ops = self.import_csv_operations_from_reader(
+            sheet_id,
+            file,
+            total_bytes,
+            file_name,
+            insert_at,
+            options,
+        )?;
+        if cursor.is_some() {
+            self.start_user_transaction(ops, cursor, TransactionName::Import);
+        } else {
+            self.server_apply_transaction(ops, Some(TransactionName::Import));
+        }
+
+        Ok(())
+    }
+
     /// Imports an Excel file into the grid.
     ///
     /// Using `cursor` here also as a flag to denote import into new / existing file.
@@ -81,6 +117,28 @@ impl GridController {
 
         Ok(())
     }
+
+    /// Imports a JSON or NDJSON file into the grid.
+    ///
+    /// Using `cursor` here also as a flag to denote import into new / existing file.
+    pub fn import_json(
+        &mut self,
+        sheet_id: SheetId,
+        file: Vec<u8>,
+        file_name: &str,
+        insert_at: Pos,
+        max_depth: Option<usize>,
+        cursor: Option<String>,
+    ) -> Result<()> {
+        let ops = self.import_json_operations(sheet_id, file, file_name, insert_at, max_depth)?;
+        if cursor.is_some() {
+            self.start_user_transaction(ops, cursor, TransactionName::Import);
+        } else {
+            self.server_apply_transaction(ops, Some(TransactionName::Import));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
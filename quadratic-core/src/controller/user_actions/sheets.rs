@@ -1,6 +1,6 @@
 use crate::{
     controller::{GridController, active_transactions::transaction_name::TransactionName},
-    grid::SheetId,
+    grid::{SheetId, sheet::protection::SheetProtections},
 };
 
 impl GridController {
@@ -19,6 +19,16 @@ impl GridController {
         self.start_user_transaction(ops, cursor, TransactionName::SetSheetMetadata);
     }
 
+    pub fn set_sheet_protections(
+        &mut self,
+        sheet_id: SheetId,
+        protections: SheetProtections,
+        cursor: Option<String>,
+    ) {
+        let ops = self.set_sheet_protections_operations(sheet_id, protections);
+        self.start_user_transaction(ops, cursor, TransactionName::SetSheetMetadata);
+    }
+
     pub fn add_sheet(&mut self, cursor: Option<String>) {
         let ops = self.add_sheet_operations(None);
         self.start_user_transaction(ops, cursor, TransactionName::SheetAdd);
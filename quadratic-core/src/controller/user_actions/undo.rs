@@ -1,6 +1,12 @@
 use uuid::Uuid;
 
-use crate::controller::{GridController, execution::TransactionSource};
+use crate::controller::{
+    GridController,
+    active_transactions::undo_history::{MAX_UNDO_HISTORY_BYTES, UndoHistory},
+    execution::TransactionSource,
+    operations::operation::Operation,
+    transaction::Transaction,
+};
 
 impl GridController {
     pub fn has_undo(&self) -> bool {
@@ -10,6 +16,14 @@ impl GridController {
         !self.redo_stack.is_empty()
     }
     pub fn undo(&mut self, cursor: Option<String>) {
+        // a blocked undo is kept on the stack
+        if self
+            .undo_stack
+            .last()
+            .is_some_and(|transaction| !self.allowed_by_protections(&transaction.operations))
+        {
+            return;
+        }
         if let Some(mut transaction) = self.undo_stack.pop() {
             // we need to assign the transaction a new id to avoid conflicts with the original transaction.
             transaction.id = Uuid::new_v4();
@@ -17,10 +31,294 @@ impl GridController {
         }
     }
     pub fn redo(&mut self, cursor: Option<String>) {
+        if self
+            .redo_stack
+            .last()
+            .is_some_and(|transaction| !self.allowed_by_protections(&transaction.operations))
+        {
+            return;
+        }
         if let Some(mut transaction) = self.redo_stack.pop() {
             // we need to assign the transaction a new id to avoid conflicts with the original transaction.
             transaction.id = Uuid::new_v4();
             self.start_undo_transaction(transaction, TransactionSource::Redo, cursor);
         }
     }
+
+    /// Returns the undo and redo stacks so they can be persisted.
+    pub fn undo_history(&self) -> UndoHistory {
+        UndoHistory {
+            sequence_num: self.transactions.last_sequence_num,
+            undo_stack: self.undo_stack.clone(),
+            redo_stack: self.redo_stack.clone(),
+        }
+    }
+
+    /// Restores persisted undo and redo stacks after a reload. This should be
+    /// called after the offline unsaved transactions are applied.
+    ///
+    /// Returns false if the history is older than the file, since the stacks
+    /// can't be transformed against transactions that are already in it.
+    pub fn restore_undo_history(&mut self, history: UndoHistory) -> bool {
+        if history.sequence_num < self.transactions.last_sequence_num {
+            return false;
+        }
+        self.transactions.undo_sequence_num = history.sequence_num;
+        self.undo_stack = history.undo_stack;
+        self.redo_stack = history.redo_stack;
+
+        if cfg!(target_family = "wasm") || cfg!(test) {
+            crate::wasm_bindings::js::jsUndoRedo(self.has_undo(), self.has_redo());
+        }
+        true
+    }
+
+    /// Returns the most recent undo and redo transactions that fit in
+    /// [`MAX_UNDO_HISTORY_BYTES`], newest undo transactions first.
+    pub fn capped_undo_history(&self) -> UndoHistory {
+        let mut remaining = MAX_UNDO_HISTORY_BYTES;
+        let mut fits = |transaction: &Transaction| {
+            let size = serde_json::to_vec(transaction).map_or(usize::MAX, |bytes| bytes.len());
+            if size > remaining {
+                return false;
+            }
+            remaining -= size;
+            true
+        };
+        let undo = self.undo_stack.iter().rev().take_while(|t| fits(t)).count();
+        let redo = match undo == self.undo_stack.len() {
+            true => self.redo_stack.iter().rev().take_while(|t| fits(t)).count(),
+            false => 0,
+        };
+
+        UndoHistory {
+            sequence_num: self.transactions.last_sequence_num,
+            undo_stack: self.undo_stack[self.undo_stack.len() - undo..].to_vec(),
+            redo_stack: self.redo_stack[self.redo_stack.len() - redo..].to_vec(),
+        }
+    }
+
+    /// Sets whether the client is told to persist the undo and redo stacks
+    /// whenever they change.
+    pub fn set_persist_undo_history(&mut self, persist: bool) {
+        self.transactions.persist_undo_history = persist;
+        self.undo_history_changed();
+    }
+
+    /// Tells the client that the undo and redo stacks changed, if persisting
+    /// is enabled. The client is only told once until it takes the history, so
+    /// it can save it as often as it likes.
+    pub(crate) fn undo_history_changed(&mut self) {
+        if !self.transactions.persist_undo_history || self.transactions.undo_history_changed {
+            return;
+        }
+        self.transactions.undo_history_changed = true;
+
+        if cfg!(target_family = "wasm") || cfg!(test) {
+            crate::wasm_bindings::js::jsUndoHistoryChanged();
+        }
+    }
+
+    /// Returns the serialized undo and redo stacks for the client to persist,
+    /// or None if they haven't changed since they were last taken.
+    pub fn take_undo_history(&mut self) -> Option<String> {
+        if !self.transactions.undo_history_changed {
+            return None;
+        }
+        self.transactions.undo_history_changed = false;
+        serde_json::to_string(&self.capped_undo_history()).ok()
+    }
+
+    /// Transforms the undo and redo stacks against operations from another
+    /// user, so undo only reverts the local user's own changes and still
+    /// targets the same cells. Transactions left without operations are
+    /// removed.
+    pub(crate) fn transform_undo_history<'a>(
+        &mut self,
+        sequence_num: u64,
+        operations: impl IntoIterator<Item = &'a Operation>,
+    ) {
+        // the restored stacks already account for these transactions
+        if sequence_num <= self.transactions.undo_sequence_num {
+            return;
+        }
+        let applied = operations
+            .into_iter()
+            .filter(|op| op.transforms_others())
+            .collect::<Vec<_>>();
+        if applied.is_empty() || (self.undo_stack.is_empty() && self.redo_stack.is_empty()) {
+            return;
+        }
+
+        let mut changed = false;
+        let unsaved_transactions = &self.transactions.unsaved_transactions;
+        let mut transform = |transaction: &mut Transaction| {
+            if transaction.operations.is_empty() {
+                return true;
+            }
+            let unsaved = unsaved_transactions.find_index(transaction.id).is_some();
+            let operations =
+                applied
+                    .iter()
+                    .fold(transaction.operations.clone(), |operations, applied| {
+                        operations
+                            .into_iter()
+                            .flat_map(|op| op.transform(applied, unsaved))
+                            .collect()
+                    });
+            if operations != transaction.operations {
+                changed = true;
+                transaction.operations = operations;
+            }
+            !transaction.operations.is_empty()
+        };
+        self.undo_stack.retain_mut(&mut transform);
+        self.redo_stack.retain_mut(&mut transform);
+
+        if changed {
+            self.undo_history_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{
+        CellValue, SheetPos,
+        controller::{
+            GridController,
+            active_transactions::undo_history::{MAX_UNDO_HISTORY_BYTES, UndoHistory},
+            operations::operation::Operation,
+        },
+        grid::SheetId,
+        wasm_bindings::js::{clear_js_calls, expect_js_call, expect_js_call_count},
+    };
+
+    #[test]
+    fn test_persist_and_restore_undo_history() {
+        clear_js_calls();
+        let mut gc = GridController::test();
+        let sheet_id = SheetId::TEST;
+        gc.set_persist_undo_history(true);
+        gc.set_cell_value(pos![sheet_id!A1], "hello".to_string(), None);
+        expect_js_call("jsUndoHistoryChanged", "".to_string(), true);
+
+        // the client is only told once until it takes the history
+        gc.set_cell_value(pos![sheet_id!A2], "world".to_string(), None);
+        gc.undo(None);
+        expect_js_call_count("jsUndoHistoryChanged", 0, false);
+        let history = gc.take_undo_history().unwrap();
+        assert_eq!(gc.take_undo_history(), None);
+        let history: UndoHistory = serde_json::from_str(&history).unwrap();
+        assert_eq!(history, gc.undo_history());
+        assert_eq!(history.undo_stack.len(), 1);
+        assert_eq!(history.redo_stack.len(), 1);
+
+        // a reloaded file with the same sequence_num
+        let mut reloaded = GridController::from_grid(gc.grid().clone(), 0);
+        assert!(!reloaded.has_undo());
+        assert!(reloaded.restore_undo_history(history.clone()));
+        reloaded.undo(None);
+        assert_eq!(reloaded.sheet(sheet_id).display_value(pos![A1]), None);
+        reloaded.redo(None);
+        assert_eq!(
+            reloaded.sheet(sheet_id).display_value(pos![A1]),
+            Some(CellValue::Text("hello".into()))
+        );
+
+        // a file that is newer than the history
+        let mut newer = GridController::from_grid(gc.grid().clone(), 1);
+        assert!(!newer.restore_undo_history(history));
+        assert!(!newer.has_undo());
+
+        // the client is told again once the history changes after it's taken
+        gc.redo(None);
+        expect_js_call("jsUndoHistoryChanged", "".to_string(), true);
+    }
+
+    #[test]
+    fn test_undo_history_saved_when_transaction_acknowledged() {
+        clear_js_calls();
+        let mut gc = GridController::test();
+        let sheet_id = SheetId::TEST;
+        gc.set_persist_undo_history(true);
+        gc.set_cell_value(pos![sheet_id!A1], "hello".to_string(), None);
+        let history: UndoHistory = serde_json::from_str(&gc.take_undo_history().unwrap()).unwrap();
+        assert_eq!(history.sequence_num, 0);
+        expect_js_call("jsUndoHistoryChanged", "".to_string(), true);
+
+        // the server acknowledges our transaction, so the history is saved again
+        let transaction = gc.last_transaction().unwrap().clone();
+        gc.received_transaction(transaction.id, 1, transaction.operations);
+        expect_js_call("jsUndoHistoryChanged", "".to_string(), true);
+        let history: UndoHistory = serde_json::from_str(&gc.take_undo_history().unwrap()).unwrap();
+        assert_eq!(history.sequence_num, 1);
+
+        // the reloaded checkpoint includes our transaction
+        let mut reloaded = GridController::from_grid(gc.grid().clone(), 1);
+        assert!(reloaded.restore_undo_history(history));
+        reloaded.undo(None);
+        assert_eq!(reloaded.sheet(sheet_id).display_value(pos![A1]), None);
+    }
+
+    #[test]
+    fn test_restored_undo_history_skips_applied_transactions() {
+        let mut gc = GridController::test();
+        let sheet_id = SheetId::TEST;
+        gc.set_cell_value(pos![sheet_id!A2], "hello".to_string(), None);
+        let transaction = gc.last_transaction().unwrap().clone();
+        gc.received_transaction(transaction.id, 1, transaction.operations.clone());
+
+        // the history was saved after another user's transaction was applied
+        let insert_row = Operation::InsertRow {
+            sheet_id,
+            row: 1,
+            copy_formats: Default::default(),
+        };
+        gc.received_transaction(Uuid::new_v4(), 2, vec![insert_row.clone()]);
+        let history = gc.undo_history();
+        assert_eq!(history.sequence_num, 2);
+
+        // the reloaded file is from before both transactions, which are
+        // received again
+        let mut reloaded = GridController::test();
+        assert!(reloaded.restore_undo_history(history));
+        reloaded.received_transaction(transaction.id, 1, transaction.operations);
+        reloaded.received_transaction(Uuid::new_v4(), 2, vec![insert_row]);
+        assert_eq!(
+            reloaded.sheet(sheet_id).display_value(pos![A3]),
+            Some(CellValue::Text("hello".into()))
+        );
+        reloaded.undo(None);
+        assert_eq!(reloaded.sheet(sheet_id).display_value(pos![A3]), None);
+        assert_eq!(reloaded.undo_history().sequence_num, 2);
+    }
+
+    #[test]
+    fn test_capped_undo_history() {
+        let mut gc = GridController::test();
+        let sheet_id = SheetId::TEST;
+        let value = "a".repeat(MAX_UNDO_HISTORY_BYTES / 3);
+
+        // each overwrite's undo transaction holds a third of the limit
+        for y in 1..=4 {
+            let sheet_pos = SheetPos::new(sheet_id, 1, y);
+            gc.set_cell_value(sheet_pos, value.clone(), None);
+            gc.set_cell_value(sheet_pos, "b".to_string(), None);
+        }
+        gc.undo(None);
+
+        // only the newest undo transactions fit, so the redo stack is dropped
+        let history = gc.capped_undo_history();
+        assert_eq!(history.undo_stack, gc.undo_stack[2..]);
+        assert!(history.redo_stack.is_empty());
+
+        // everything fits once the oldest transactions are gone
+        gc.undo_stack.drain(..2);
+        let history = gc.capped_undo_history();
+        assert_eq!(history.undo_stack, gc.undo_stack);
+        assert_eq!(history.redo_stack, gc.redo_stack);
+    }
 }
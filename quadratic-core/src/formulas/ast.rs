@@ -8,7 +8,9 @@ use super::*;
 use crate::{
     Array, ArraySize, CellValue, CodeResult, CoerceInto, Pos, RunErrorMsg, SheetRect, Spanned,
     Value,
-    a1::{CellRefCoord, CellRefRange, CellRefRangeEnd, RefRangeBounds, SheetCellRefRange},
+    a1::{
+        CellRefCoord, CellRefRange, CellRefRangeEnd, RefRangeBounds, SheetCellRefRange, ThisRowRef,
+    },
     grid::SheetId,
 };
 
@@ -69,9 +71,44 @@ impl Formula {
             inner: e.into(),
         })
     }
+
+    /// Moves the this-row references (eg, `[@Price]`) of a calculated column
+    /// formula down by `dy` rows, so the formula can be parsed once and then
+    /// evaluated at every row. `source` is the text the formula was parsed
+    /// from.
+    pub fn translate_this_row_refs(&mut self, source: &str, dy: i64) {
+        self.ast.translate_this_row_refs(source, dy);
+    }
 }
 
 impl AstNode {
+    fn translate_this_row_refs(&mut self, source: &str, dy: i64) {
+        match &mut self.inner {
+            AstNodeContents::RangeRef(SheetCellRefRange {
+                cells: CellRefRange::Sheet { range },
+                ..
+            }) => {
+                let is_this_row = source
+                    .get(std::ops::Range::<usize>::from(self.span))
+                    .is_some_and(|text| ThisRowRef::parse(text).is_ok());
+                if is_this_row {
+                    *range = range.translate_unchecked(0, dy);
+                }
+            }
+            AstNodeContents::FunctionCall { args, .. } | AstNodeContents::Paren(args) => {
+                for arg in args {
+                    arg.translate_this_row_refs(source, dy);
+                }
+            }
+            AstNodeContents::Array(rows) => {
+                for node in rows.iter_mut().flatten() {
+                    node.translate_this_row_refs(source, dy);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn eval<'expr, 'ctx: 'expr>(&'expr self, ctx: &'expr mut Ctx<'ctx>) -> CodeResult {
         let value: Value = match &self.inner {
             AstNodeContents::Empty => Value::Single(CellValue::Blank),
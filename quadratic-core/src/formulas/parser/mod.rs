@@ -14,7 +14,7 @@ use rules::SyntaxRule;
 use super::*;
 use crate::{
     CodeResult, CoerceInto, RefError, RunError, RunErrorMsg, SheetPos, Span, Spanned, TableRef,
-    a1::{A1Context, CellRefRange, RefRangeBounds, SheetCellRefRange},
+    a1::{A1Context, CellRefRange, RefRangeBounds, SheetCellRefRange, ThisRowRef},
     controller::GridController,
     grid::{RefAdjust, SheetId},
 };
@@ -190,6 +190,48 @@ pub fn replace_column_name(
     })
 }
 
+/// Replaces a column name in the this-row references (eg, `[@Price]`) of a
+/// calculated column formula. `table_name` is the table that owns the formula.
+#[must_use = "this method returns a new value instead of modifying its input"]
+pub fn replace_this_row_column_name(
+    source: &str,
+    table_name: &str,
+    old_name: &str,
+    new_name: &str,
+) -> String {
+    let tokens = lexer::tokenize(source)
+        .filter(|t| !t.inner.is_skip())
+        .collect_vec();
+    let mut replaced = source.to_string();
+
+    // replace in reverse order to preserve previous span indexes into string
+    for (i, token) in tokens.iter().enumerate().rev() {
+        if token.inner != Token::TableRefBracketsExpression {
+            continue;
+        }
+
+        // include the table name, if the brackets directly follow it
+        let mut span = token.span;
+        if let Some(prev) = i.checked_sub(1).and_then(|i| tokens.get(i)) {
+            if prev.inner == Token::CellOrTableRef && prev.span.end == span.start {
+                span = Span::merge(prev.span, span);
+            }
+        }
+
+        let range: Range<usize> = span.into();
+        let Ok(mut this_row) = ThisRowRef::parse(&source[range.clone()]) else {
+            continue;
+        };
+        let old_column = this_row.column.clone();
+        this_row.replace_column_name(table_name, old_name, new_name);
+        if this_row.column != old_column {
+            replaced.replace_range(range, &this_row.to_string());
+        }
+    }
+
+    replaced
+}
+
 #[must_use = "this method returns a new value instead of modifying its input"]
 fn replace_table_references(
     source: &str,
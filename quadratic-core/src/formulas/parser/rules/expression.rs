@@ -207,7 +207,7 @@ impl SyntaxRule for ExpressionWithPrecedence {
                 | Token::InternalCellRef
                 | Token::Error => true,
 
-                Token::TableRefBracketsExpression => false,
+                Token::TableRefBracketsExpression => is_this_row_brackets(p.token_str()),
                 Token::Whitespace => false,
                 Token::Unknown => false,
             }
@@ -224,6 +224,7 @@ impl SyntaxRule for ExpressionWithPrecedence {
                 [
                     FunctionCall.map(Some),
                     CellReferenceExpression.map(Some),
+                    ThisRowReferenceExpression.map(Some),
                     TableReferenceExpression.map(Some),
                     StringLiteralExpression.map(Some),
                     NumericLiteral.map(Some),
@@ -424,6 +425,24 @@ impl SyntaxRule for CellReferenceExpression {
     }
 }
 
+/// Matches a this-row table reference.
+#[derive(Debug, Copy, Clone)]
+pub struct ThisRowReferenceExpression;
+impl_display!(for ThisRowReferenceExpression, "this row reference such as '[@ColumnName]'");
+impl SyntaxRule for ThisRowReferenceExpression {
+    type Output = AstNode;
+
+    fn prefix_matches(&self, p: Parser<'_>) -> bool {
+        ThisRowReference.prefix_matches(p)
+    }
+    fn consume_match(&self, p: &mut Parser<'_>) -> CodeResult<Self::Output> {
+        Ok(p.parse(ThisRowReference)?.map(|result| match result {
+            Ok(range) => ast::AstNodeContents::RangeRef(range),
+            Err(e) => ast::AstNodeContents::Error(e),
+        }))
+    }
+}
+
 /// Matches a table reference.
 #[derive(Debug, Copy, Clone)]
 pub struct TableReferenceExpression;
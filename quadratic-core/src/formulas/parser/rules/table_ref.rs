@@ -2,7 +2,10 @@ use std::{iter::Peekable, str::Chars};
 
 use itertools::PeekingNext;
 
-use crate::{CodeResultExt, TableRef, a1::ColRange};
+use crate::{
+    CodeResultExt, TableRef,
+    a1::{ColRange, RefRangeBounds, ThisRowRef},
+};
 
 use super::*;
 
@@ -21,6 +24,14 @@ pub(super) fn is_table_ref(mut p: Parser<'_>) -> Option<bool> {
     }
 }
 
+/// Returns whether a table reference bracket expression (including the outer
+/// brackets) is a this-row reference, such as `[@Price]`.
+pub(super) fn is_this_row_brackets(s: &str) -> bool {
+    s.strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .is_some_and(|s| ThisRowRef::parse_brackets(s).is_some())
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum TableRefToken {
     Column(String),
@@ -189,6 +200,54 @@ impl SyntaxRule for TableReference {
     }
 }
 
+/// Matches a this-row reference, such as `[@Price]` or `MyTable[@Price]`, and
+/// resolves it to the cell in the same row as the formula. This-row references
+/// are only valid within a table, and are used by calculated columns.
+#[derive(Debug, Copy, Clone)]
+pub struct ThisRowReference;
+impl_display!(for ThisRowReference, "this row reference such as '[@ColumnName]'");
+impl SyntaxRule for ThisRowReference {
+    type Output = Spanned<Result<SheetCellRefRange, RunErrorMsg>>;
+
+    fn prefix_matches(&self, mut p: Parser<'_>) -> bool {
+        match p.next() {
+            Some(Token::TableRefBracketsExpression) => is_this_row_brackets(p.token_str()),
+            Some(Token::CellOrTableRef) if p.ctx.has_table(p.token_str()) => {
+                p.next() == Some(Token::TableRefBracketsExpression)
+                    && is_this_row_brackets(p.token_str())
+            }
+            _ => false,
+        }
+    }
+
+    fn consume_match(&self, p: &mut Parser<'_>) -> CodeResult<Self::Output> {
+        let start_span = p.peek_next_span();
+
+        let mut table_name = "";
+        if p.next() == Some(Token::CellOrTableRef) {
+            table_name = p.token_str();
+            p.next();
+        }
+        let span = Span::merge(start_span, p.span());
+
+        let result = ThisRowRef::parse(&format!("{table_name}{}", p.token_str()))
+            .and_then(|this_row| this_row.to_sheet_pos(p.pos, p.ctx))
+            .map(|sheet_pos| SheetCellRefRange {
+                sheet_id: sheet_pos.sheet_id,
+                cells: CellRefRange::Sheet {
+                    range: RefRangeBounds::new_relative_pos(sheet_pos.into()),
+                },
+                explicit_sheet_name: false,
+            })
+            .map_err(|_| RunErrorMsg::BadCellReference);
+
+        Ok(Spanned {
+            span,
+            inner: result,
+        })
+    }
+}
+
 /// Matches a table reference and includes the sheet ID in the output.
 #[derive(Debug, Copy, Clone)]
 pub struct SheetTableReference;
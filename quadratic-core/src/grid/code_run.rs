@@ -59,6 +59,8 @@ pub enum CodeCellLanguage {
     Javascript,
     /// CSV or other file import.
     Import,
+    /// SQL query over data tables in the grid.
+    Sql,
 }
 
 impl CodeCellLanguage {
@@ -84,6 +86,10 @@ pub enum ConnectionKind {
     Mysql,
     Mssql,
     Snowflake,
+    Sqlite,
+    DuckDb,
+    ClickHouse,
+    Http,
 }
 
 impl wasm_bindgen::describe::WasmDescribe for ConnectionKind {
@@ -627,6 +627,16 @@ impl<T: Clone + PartialEq + fmt::Debug> Contiguous2D<Option<T>> {
             })
     }
 
+    /// Returns the set of rectangles that have values, where unbounded
+    /// rectangles extend to `i64::MAX`.
+    ///
+    /// `None` values are skipped.
+    pub fn to_unbounded_rects(&self) -> impl '_ + Iterator<Item = Rect> {
+        self.to_rects().map(|(x1, y1, x2, y2, _)| {
+            Rect::new(x1, y1, x2.unwrap_or(i64::MAX), y2.unwrap_or(i64::MAX))
+        })
+    }
+
     /// Returns the set of rectangles that have values. Each rectangle is `(x1,
     /// y1, x2, y2, value)` with inclusive coordinates. Unlike `to_rects()`,
     /// this returns concrete coordinates rather than potentially infinite
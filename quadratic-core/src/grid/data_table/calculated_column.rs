@@ -0,0 +1,93 @@
+//! DataTable calculated columns
+//!
+//! A calculated column holds a single formula that is evaluated for each row
+//! of the table. The formula references values in the same row using this-row
+//! references, eg, `=[@Price]*[@Qty]`. The results are stored as the column's
+//! values, so they are sorted, rendered and exported like any other column.
+
+use super::DataTable;
+use crate::formulas::replace_this_row_column_name;
+
+impl DataTable {
+    /// Returns the formula of the column at the given (value) index, if it is
+    /// a calculated column.
+    pub fn column_formula(&self, column_index: usize) -> Option<&str> {
+        self.get_column_header(column_index)
+            .and_then(|header| header.formula.as_deref())
+    }
+
+    /// Sets (or clears) the formula of the column at the given (value) index.
+    /// Returns the old formula.
+    pub fn set_column_formula(
+        &mut self,
+        column_index: usize,
+        formula: Option<String>,
+    ) -> Option<String> {
+        let header = self
+            .column_headers
+            .as_mut()
+            .and_then(|headers| headers.get_mut(column_index))?;
+
+        std::mem::replace(&mut header.formula, formula)
+    }
+
+    /// Returns the (value) index and formula of all calculated columns, in
+    /// column order.
+    pub fn calculated_columns(&self) -> Vec<(usize, String)> {
+        self.column_headers
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(index, header)| header.formula.clone().map(|formula| (index, formula)))
+            .collect()
+    }
+
+    /// Returns true if the data table has at least one calculated column.
+    pub fn has_calculated_columns(&self) -> bool {
+        self.column_headers
+            .iter()
+            .flatten()
+            .any(|header| header.formula.is_some())
+    }
+
+    /// Replaces a column name in the this-row references of all calculated
+    /// column formulas.
+    pub fn replace_column_name_in_formulas(&mut self, old_name: &str, new_name: &str) {
+        let table_name = self.name.to_string();
+        for header in self.column_headers.iter_mut().flatten() {
+            if let Some(formula) = header.formula.as_mut() {
+                *formula = replace_this_row_column_name(formula, &table_name, old_name, new_name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use crate::grid::test::new_data_table;
+
+    #[test]
+    fn test_column_formula() {
+        let (_, mut data_table) = new_data_table();
+        assert!(!data_table.has_calculated_columns());
+
+        let old = data_table.set_column_formula(1, Some("=[@city] & \"!\"".to_string()));
+        assert_eq!(old, None);
+        assert!(data_table.has_calculated_columns());
+        assert_eq!(data_table.column_formula(1), Some("=[@city] & \"!\""));
+        assert_eq!(
+            data_table.calculated_columns(),
+            vec![(1, "=[@city] & \"!\"".to_string())]
+        );
+
+        data_table.replace_column_name_in_formulas("city", "Town Name");
+        assert_eq!(
+            data_table.column_formula(1),
+            Some("=[@[Town Name]] & \"!\"")
+        );
+
+        let old = data_table.set_column_formula(1, None);
+        assert_eq!(old, Some("=[@[Town Name]] & \"!\"".to_string()));
+        assert!(!data_table.has_calculated_columns());
+    }
+}
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 use super::DataTable;
+use super::column_type::DataTableColumnType;
 use crate::grid::js_types::JsDataTableColumnHeader;
 use crate::util::unique_name;
 use crate::{CellValue, Value};
@@ -12,6 +13,13 @@ pub struct DataTableColumnHeader {
     pub name: CellValue,
     pub display: bool,
     pub value_index: u32,
+
+    #[serde(default)]
+    pub column_type: Option<DataTableColumnType>,
+
+    /// Formula of a calculated column, evaluated for each row.
+    #[serde(default)]
+    pub formula: Option<String>,
 }
 
 impl From<DataTableColumnHeader> for CellValue {
@@ -26,8 +34,22 @@ impl DataTableColumnHeader {
             name: CellValue::Text(name),
             display,
             value_index,
+            column_type: None,
+            formula: None,
         }
     }
+
+    /// Sets the column type.
+    pub fn with_column_type(mut self, column_type: Option<DataTableColumnType>) -> Self {
+        self.column_type = column_type;
+        self
+    }
+
+    /// Sets the formula of a calculated column.
+    pub fn with_formula(mut self, formula: Option<String>) -> Self {
+        self.formula = formula;
+        self
+    }
 }
 
 impl DataTable {
@@ -55,6 +77,8 @@ impl DataTable {
                 .map(|(i, value)| {
                     let display = self.header_display(i);
                     DataTableColumnHeader::new(value.to_string(), display, i as u32)
+                        .with_column_type(self.column_type(i))
+                        .with_formula(self.column_formula(i).map(str::to_string))
                 })
                 .collect()
         });
@@ -92,6 +116,8 @@ impl DataTable {
                 .map(|i| {
                     let display = self.header_display(i as usize);
                     DataTableColumnHeader::new(func(i), display, i - 1)
+                        .with_column_type(self.column_type(i as usize - 1))
+                        .with_formula(self.column_formula(i as usize - 1).map(str::to_string))
                 })
                 .collect::<Vec<DataTableColumnHeader>>(),
             _ => vec![],
@@ -308,6 +334,7 @@ pub mod test {
             borders: Default::default(),
             chart_output: None,
             chart_pixel_output: None,
+            calculated_cells_accessed: Default::default(),
         };
         sheet.set_cell_value(
             pos,
@@ -360,6 +387,7 @@ pub mod test {
             borders: Default::default(),
             chart_output: None,
             chart_pixel_output: None,
+            calculated_cells_accessed: Default::default(),
         };
         t.apply_default_header();
         sheet.set_cell_value(
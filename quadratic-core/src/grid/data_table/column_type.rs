@@ -0,0 +1,376 @@
+//! DataTable column types
+//!
+//! Column types are optional. When set, they are inferred once at import time
+//! and used to coerce values written to the column. User edits that cannot be
+//! coerced are rejected before they become operations (see
+//! `GridController::set_cell_values`); pasted and programmatic values that do
+//! not fit are kept as they are.
+
+use std::str::FromStr;
+
+use arrow_schema::DataType;
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::DataTable;
+use crate::CellValue;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, TS)]
+pub enum DataTableColumnType {
+    Text,
+    Number,
+    Date,
+    DateTime,
+    Boolean,
+    Currency,
+}
+
+impl DataTableColumnType {
+    /// Infers the type of a column from its values. Blank values are ignored.
+    /// Returns `None` if the column is empty or contains mixed types.
+    ///
+    /// `is_currency` promotes an all-number column to `Currency`.
+    pub fn infer<'a>(
+        values: impl IntoIterator<Item = &'a CellValue>,
+        is_currency: bool,
+    ) -> Option<Self> {
+        let mut inference = ColumnTypeInference::default();
+        for value in values {
+            inference.add(value);
+        }
+        inference.column_type(is_currency)
+    }
+
+    /// Maps an Arrow data type to a column type.
+    pub fn from_arrow(data_type: &DataType) -> Option<Self> {
+        match data_type {
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float16
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Decimal128(_, _)
+            | DataType::Decimal256(_, _) => Some(DataTableColumnType::Number),
+            DataType::Boolean => Some(DataTableColumnType::Boolean),
+            DataType::Utf8 | DataType::LargeUtf8 => Some(DataTableColumnType::Text),
+            DataType::Date32 | DataType::Date64 => Some(DataTableColumnType::Date),
+            DataType::Timestamp(_, _) => Some(DataTableColumnType::DateTime),
+            _ => None,
+        }
+    }
+
+    /// Coerces a value into this column type.
+    ///
+    /// Returns `None` if the value cannot be represented by the column type,
+    /// in which case the edit should be rejected. Blanks and errors are always
+    /// accepted.
+    pub fn coerce(&self, value: CellValue) -> Option<CellValue> {
+        match (self, value) {
+            (_, CellValue::Blank) => Some(CellValue::Blank),
+            (_, CellValue::Error(e)) => Some(CellValue::Error(e)),
+
+            // text columns accept any plain value, as is
+            (
+                DataTableColumnType::Text,
+                value @ (CellValue::Text(_)
+                | CellValue::Number(_)
+                | CellValue::Logical(_)
+                | CellValue::Date(_)
+                | CellValue::DateTime(_)
+                | CellValue::Instant(_)
+                | CellValue::Time(_)
+                | CellValue::Duration(_)),
+            ) => Some(value),
+
+            (DataTableColumnType::Number | DataTableColumnType::Currency, CellValue::Number(n)) => {
+                Some(CellValue::Number(n))
+            }
+            (
+                DataTableColumnType::Number | DataTableColumnType::Currency,
+                CellValue::Text(text),
+            ) => {
+                let text = text.trim();
+                CellValue::unpack_currency(text)
+                    .map(|(_, n)| n)
+                    .or_else(|| BigDecimal::from_str(&text.replace(',', "")).ok())
+                    .map(CellValue::Number)
+            }
+
+            (DataTableColumnType::Boolean, CellValue::Logical(b)) => Some(CellValue::Logical(b)),
+            (DataTableColumnType::Boolean, CellValue::Text(text)) => {
+                CellValue::unpack_boolean(text.trim())
+            }
+
+            (DataTableColumnType::Date, CellValue::Date(d)) => Some(CellValue::Date(d)),
+            (DataTableColumnType::Date, CellValue::DateTime(dt)) => {
+                Some(CellValue::Date(dt.date()))
+            }
+            (DataTableColumnType::Date, CellValue::Text(text)) => {
+                CellValue::unpack_date(text.trim())
+            }
+
+            (DataTableColumnType::DateTime, CellValue::DateTime(dt)) => {
+                Some(CellValue::DateTime(dt))
+            }
+            (DataTableColumnType::DateTime, CellValue::Date(d)) => {
+                Some(CellValue::DateTime(d.and_time(chrono::NaiveTime::MIN)))
+            }
+            (DataTableColumnType::DateTime, CellValue::Text(text)) => {
+                let text = text.trim();
+                CellValue::unpack_date_time(text).or_else(|| {
+                    CellValue::unpack_date(text).and_then(|date| match date {
+                        CellValue::Date(d) => {
+                            Some(CellValue::DateTime(d.and_time(chrono::NaiveTime::MIN)))
+                        }
+                        _ => None,
+                    })
+                })
+            }
+
+            _ => None,
+        }
+    }
+}
+
+/// Infers the type of a column from values that arrive in batches, with the
+/// same rules as [`DataTableColumnType::infer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ColumnTypeInference {
+    column_type: Option<DataTableColumnType>,
+    mixed: bool,
+}
+
+impl ColumnTypeInference {
+    /// Adds a value of the column. Blank values are ignored.
+    pub fn add(&mut self, value: &CellValue) {
+        if self.mixed {
+            return;
+        }
+
+        let value_type = match value {
+            CellValue::Blank => return,
+            CellValue::Text(_) => DataTableColumnType::Text,
+            CellValue::Number(_) => DataTableColumnType::Number,
+            CellValue::Logical(_) => DataTableColumnType::Boolean,
+            CellValue::Date(_) => DataTableColumnType::Date,
+            CellValue::DateTime(_) | CellValue::Instant(_) => DataTableColumnType::DateTime,
+            _ => {
+                self.mixed = true;
+                return;
+            }
+        };
+
+        self.column_type = match (self.column_type, value_type) {
+            (None, value_type) => Some(value_type),
+            (Some(current), value_type) if current == value_type => Some(current),
+
+            // dates and date times can live together in a date time column
+            (Some(DataTableColumnType::Date), DataTableColumnType::DateTime)
+            | (Some(DataTableColumnType::DateTime), DataTableColumnType::Date) => {
+                Some(DataTableColumnType::DateTime)
+            }
+            _ => {
+                self.mixed = true;
+                None
+            }
+        };
+    }
+
+    /// Returns the type of the values added so far, or `None` if there were
+    /// none or they have mixed types.
+    ///
+    /// `is_currency` promotes an all-number column to `Currency`.
+    pub fn column_type(&self, is_currency: bool) -> Option<DataTableColumnType> {
+        match self.column_type {
+            _ if self.mixed => None,
+            Some(DataTableColumnType::Number) if is_currency => Some(DataTableColumnType::Currency),
+            column_type => column_type,
+        }
+    }
+}
+
+impl DataTable {
+    /// Returns the type of the column at the given (value) index.
+    pub fn column_type(&self, column_index: usize) -> Option<DataTableColumnType> {
+        self.get_column_header(column_index)
+            .and_then(|header| header.column_type)
+    }
+
+    /// Sets the type of the column at the given (value) index.
+    pub fn set_column_type(
+        &mut self,
+        column_index: usize,
+        column_type: Option<DataTableColumnType>,
+    ) {
+        if let Some(header) = self
+            .column_headers
+            .as_mut()
+            .and_then(|headers| headers.get_mut(column_index))
+        {
+            header.column_type = column_type;
+        }
+    }
+
+    /// Returns the types of all columns, in value order.
+    pub fn column_types(&self) -> Vec<Option<DataTableColumnType>> {
+        self.column_headers
+            .as_ref()
+            .map(|headers| headers.iter().map(|header| header.column_type).collect())
+            .unwrap_or_default()
+    }
+
+    /// Infers the type of every column from its values and stores it in the
+    /// column headers. `currency_columns` lists the column indices that were
+    /// formatted as currency during import.
+    pub fn infer_column_types(&mut self, currency_columns: &[usize]) {
+        let skip = self.adjust_for_header(0);
+        let Ok(array) = self.value_as_array() else {
+            return;
+        };
+        let width = array.width() as usize;
+
+        let column_types = (0..width)
+            .map(|x| {
+                let values = array.rows().skip(skip).filter_map(|row| row.get(x));
+                DataTableColumnType::infer(values, currency_columns.contains(&x))
+            })
+            .collect::<Vec<_>>();
+
+        for (index, column_type) in column_types.into_iter().enumerate() {
+            self.set_column_type(index, column_type);
+        }
+    }
+
+    /// Coerces a value that is about to be written to (x, y) of the data
+    /// table's value. The header row (if the first row is the header) is never
+    /// coerced, and values that cannot be coerced are returned unchanged.
+    pub fn coerce_value_for_column(&self, x: u32, y: u32, value: CellValue) -> CellValue {
+        if self.header_is_first_row && y == 0 {
+            return value;
+        }
+
+        match self.column_type(x as usize) {
+            Some(column_type) => column_type.coerce(value.clone()).unwrap_or(value),
+            None => value,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::*;
+    use crate::{Array, Value, grid::test::new_data_table};
+
+    #[test]
+    fn test_infer_column_type() {
+        let number = CellValue::Number(1.into());
+        let text = CellValue::Text("a".into());
+        let date = CellValue::Date(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        let date_time = CellValue::DateTime(
+            NaiveDateTime::parse_from_str("2024-01-01 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        );
+
+        assert_eq!(
+            DataTableColumnType::infer(&[number.clone(), CellValue::Blank], false),
+            Some(DataTableColumnType::Number)
+        );
+        assert_eq!(
+            DataTableColumnType::infer(std::slice::from_ref(&number), true),
+            Some(DataTableColumnType::Currency)
+        );
+        assert_eq!(
+            DataTableColumnType::infer(&[date.clone(), date_time], false),
+            Some(DataTableColumnType::DateTime)
+        );
+        assert_eq!(
+            DataTableColumnType::infer(&[date], false),
+            Some(DataTableColumnType::Date)
+        );
+        assert_eq!(DataTableColumnType::infer(&[number, text], false), None);
+        assert_eq!(DataTableColumnType::infer(&[CellValue::Blank], false), None);
+    }
+
+    #[test]
+    fn test_coerce_column_type() {
+        let number = DataTableColumnType::Number;
+        assert_eq!(
+            number.coerce(CellValue::Text("1,234.5".into())),
+            Some(CellValue::Number(BigDecimal::from_str("1234.5").unwrap()))
+        );
+        assert_eq!(number.coerce(CellValue::Text("abc".into())), None);
+        assert_eq!(number.coerce(CellValue::Blank), Some(CellValue::Blank));
+
+        let currency = DataTableColumnType::Currency;
+        assert_eq!(
+            currency.coerce(CellValue::Text("$10".into())),
+            Some(CellValue::Number(10.into()))
+        );
+
+        let text = DataTableColumnType::Text;
+        assert_eq!(
+            text.coerce(CellValue::Number(10.into())),
+            Some(CellValue::Number(10.into()))
+        );
+
+        let boolean = DataTableColumnType::Boolean;
+        assert_eq!(
+            boolean.coerce(CellValue::Text("TRUE".into())),
+            Some(CellValue::Logical(true))
+        );
+        assert_eq!(boolean.coerce(CellValue::Number(1.into())), None);
+
+        let date_time = DataTableColumnType::DateTime;
+        assert_eq!(
+            date_time.coerce(CellValue::Date(
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+            )),
+            Some(CellValue::DateTime(
+                NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_time(chrono::NaiveTime::MIN)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_infer_column_types_and_coerce() {
+        let (_, mut data_table) = new_data_table();
+        data_table.header_is_first_row = false;
+        data_table.value = Value::Array(Array::from(vec![
+            vec![CellValue::Text("a".into()), CellValue::Number(1.into())],
+            vec![CellValue::Text("b".into()), CellValue::Number(2.into())],
+        ]));
+        data_table.apply_default_header();
+        data_table.infer_column_types(&[]);
+
+        assert_eq!(
+            data_table.column_types(),
+            vec![
+                Some(DataTableColumnType::Text),
+                Some(DataTableColumnType::Number)
+            ]
+        );
+
+        assert!(data_table.set_cell_value_at(1, 0, CellValue::Text("3".into())));
+        assert_eq!(
+            data_table.value_as_array().unwrap().get(1, 0).unwrap(),
+            &CellValue::Number(3.into())
+        );
+
+        // values that cannot be coerced are kept as they are
+        assert!(data_table.set_cell_value_at(1, 0, CellValue::Text("abc".into())));
+        assert_eq!(
+            data_table.value_as_array().unwrap().get(1, 0).unwrap(),
+            &CellValue::Text("abc".into())
+        );
+    }
+}
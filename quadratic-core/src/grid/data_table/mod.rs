@@ -4,8 +4,10 @@
 //! any given CellValue::Code type (ie, if it doesn't exist then a run hasn't been
 //! performed yet).
 
+pub mod calculated_column;
 pub mod column;
 pub mod column_header;
+pub mod column_type;
 pub mod display_value;
 pub mod formats;
 pub mod row;
@@ -16,7 +18,7 @@ use std::num::NonZeroU32;
 
 use crate::a1::A1Context;
 use crate::cellvalue::Import;
-use crate::grid::CodeRun;
+use crate::grid::{CellsAccessed, CodeRun};
 use crate::util::unique_name;
 use crate::{
     Array, ArraySize, CellValue, Pos, Rect, RunError, RunErrorMsg, SheetPos, SheetRect, Value,
@@ -156,6 +158,10 @@ pub struct DataTable {
     // width and height of the chart (html or image) output
     pub chart_pixel_output: Option<(f32, f32)>,
     pub chart_output: Option<(u32, u32)>,
+
+    // cells read by the calculated column formulas
+    #[serde(default)]
+    pub calculated_cells_accessed: CellsAccessed,
 }
 
 impl From<(Import, Array, &A1Context)> for DataTable {
@@ -212,6 +218,8 @@ impl DataTable {
             borders: Default::default(),
 
             chart_output: None,
+
+            calculated_cells_accessed: Default::default(),
         };
 
         if header_is_first_row {
@@ -244,6 +252,8 @@ impl DataTable {
 
             chart_pixel_output: self.chart_pixel_output,
             chart_output: self.chart_output,
+
+            calculated_cells_accessed: self.calculated_cells_accessed.clone(),
         }
     }
 
@@ -545,9 +555,12 @@ impl DataTable {
     }
 
     /// Sets the cell value at a relative location (0-indexed) into the code.
+    /// The value is coerced to the column type, if one is set.
     /// Returns `false` if the value cannot be set.
     pub fn set_cell_value_at(&mut self, x: u32, y: u32, value: CellValue) -> bool {
         if !self.spill_error && !self.has_error() {
+            let value = self.coerce_value_for_column(x, y, value);
+
             match self.value {
                 Value::Single(_) => {
                     self.value = Value::Single(value);
@@ -12,12 +12,17 @@ pub fn export_code_cell_language(language: CodeCellLanguage) -> current::CodeCel
         CodeCellLanguage::Python => current::CodeCellLanguageSchema::Python,
         CodeCellLanguage::Formula => current::CodeCellLanguageSchema::Formula,
         CodeCellLanguage::Javascript => current::CodeCellLanguageSchema::Javascript,
+        CodeCellLanguage::Sql => current::CodeCellLanguageSchema::Sql,
         CodeCellLanguage::Connection { kind, id } => current::CodeCellLanguageSchema::Connection {
             kind: match kind {
                 ConnectionKind::Postgres => current::ConnectionKindSchema::Postgres,
                 ConnectionKind::Mysql => current::ConnectionKindSchema::Mysql,
                 ConnectionKind::Mssql => current::ConnectionKindSchema::Mssql,
                 ConnectionKind::Snowflake => current::ConnectionKindSchema::Snowflake,
+                ConnectionKind::Sqlite => current::ConnectionKindSchema::Sqlite,
+                ConnectionKind::DuckDb => current::ConnectionKindSchema::DuckDb,
+                ConnectionKind::ClickHouse => current::ConnectionKindSchema::ClickHouse,
+                ConnectionKind::Http => current::ConnectionKindSchema::Http,
             },
             id,
         },
@@ -68,12 +73,17 @@ pub fn import_code_cell_language(language: current::CodeCellLanguageSchema) -> C
         current::CodeCellLanguageSchema::Python => CodeCellLanguage::Python,
         current::CodeCellLanguageSchema::Formula => CodeCellLanguage::Formula,
         current::CodeCellLanguageSchema::Javascript => CodeCellLanguage::Javascript,
+        current::CodeCellLanguageSchema::Sql => CodeCellLanguage::Sql,
         current::CodeCellLanguageSchema::Connection { kind, id } => CodeCellLanguage::Connection {
             kind: match kind {
                 current::ConnectionKindSchema::Postgres => ConnectionKind::Postgres,
                 current::ConnectionKindSchema::Mysql => ConnectionKind::Mysql,
                 current::ConnectionKindSchema::Mssql => ConnectionKind::Mssql,
                 current::ConnectionKindSchema::Snowflake => ConnectionKind::Snowflake,
+                current::ConnectionKindSchema::Sqlite => ConnectionKind::Sqlite,
+                current::ConnectionKindSchema::DuckDb => ConnectionKind::DuckDb,
+                current::ConnectionKindSchema::ClickHouse => ConnectionKind::ClickHouse,
+                current::ConnectionKindSchema::Http => ConnectionKind::Http,
             },
             id,
         },
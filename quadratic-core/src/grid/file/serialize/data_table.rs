@@ -14,6 +14,7 @@ use crate::{
         block::SameValue,
         data_table::{
             column_header::DataTableColumnHeader,
+            column_type::DataTableColumnType,
             sort::{DataTableSort, SortDirection},
         },
     },
@@ -264,6 +265,17 @@ fn import_code_run_builder(code_run: current::CodeRunSchema) -> Result<CodeRun>
     Ok(code_run)
 }
 
+fn import_column_type(column_type: current::DataTableColumnTypeSchema) -> DataTableColumnType {
+    match column_type {
+        current::DataTableColumnTypeSchema::Text => DataTableColumnType::Text,
+        current::DataTableColumnTypeSchema::Number => DataTableColumnType::Number,
+        current::DataTableColumnTypeSchema::Date => DataTableColumnType::Date,
+        current::DataTableColumnTypeSchema::DateTime => DataTableColumnType::DateTime,
+        current::DataTableColumnTypeSchema::Boolean => DataTableColumnType::Boolean,
+        current::DataTableColumnTypeSchema::Currency => DataTableColumnType::Currency,
+    }
+}
+
 pub(crate) fn import_data_table_builder(
     data_tables: Vec<(current::PosSchema, current::DataTableSchema)>,
 ) -> Result<IndexMap<Pos, DataTable>> {
@@ -313,6 +325,8 @@ pub(crate) fn import_data_table_builder(
                         };
 
                         DataTableColumnHeader::new(column_name, column.display, column.value_index)
+                            .with_column_type(column.column_type.map(import_column_type))
+                            .with_formula(column.formula)
                     })
                     .collect()
             }),
@@ -335,6 +349,7 @@ pub(crate) fn import_data_table_builder(
             borders: import_borders(data_table.borders),
             chart_pixel_output: data_table.chart_pixel_output,
             chart_output: data_table.chart_output,
+            calculated_cells_accessed: import_cells_accessed(data_table.calculated_cells_accessed)?,
         };
 
         new_data_tables.insert(Pos { x: pos.x, y: pos.y }, data_table);
@@ -435,6 +450,17 @@ fn export_run_error_msg(run_error_msg: RunErrorMsg) -> current::RunErrorMsgSchem
     }
 }
 
+fn export_column_type(column_type: DataTableColumnType) -> current::DataTableColumnTypeSchema {
+    match column_type {
+        DataTableColumnType::Text => current::DataTableColumnTypeSchema::Text,
+        DataTableColumnType::Number => current::DataTableColumnTypeSchema::Number,
+        DataTableColumnType::Date => current::DataTableColumnTypeSchema::Date,
+        DataTableColumnType::DateTime => current::DataTableColumnTypeSchema::DateTime,
+        DataTableColumnType::Boolean => current::DataTableColumnTypeSchema::Boolean,
+        DataTableColumnType::Currency => current::DataTableColumnTypeSchema::Currency,
+    }
+}
+
 fn export_code_run(code_run: CodeRun) -> current::CodeRunSchema {
     let error = if let Some(error) = code_run.error {
         Some(current::RunErrorSchema {
@@ -499,6 +525,8 @@ pub(crate) fn export_data_tables(
                         name: current::CellValueSchema::Text(column.name.to_string()),
                         display: column.display,
                         value_index: column.value_index,
+                        column_type: column.column_type.map(export_column_type),
+                        formula: column.formula,
                     })
                     .collect()
             });
@@ -546,6 +574,9 @@ pub(crate) fn export_data_tables(
                 borders: export_borders(data_table.borders),
                 chart_pixel_output: data_table.chart_pixel_output,
                 chart_output: data_table.chart_output,
+                calculated_cells_accessed: export_cells_accessed(
+                    data_table.calculated_cells_accessed,
+                ),
             };
 
             (current::PosSchema::from(pos), data_table)
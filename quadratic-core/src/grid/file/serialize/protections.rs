@@ -0,0 +1,79 @@
+use crate::{
+    Pos, Rect,
+    grid::sheet::protection::{
+        Protection, ProtectionAllowList, ProtectionRole, ProtectionTarget, SheetProtections,
+    },
+};
+
+use super::current;
+
+fn import_role(role: current::ProtectionRoleSchema) -> ProtectionRole {
+    match role {
+        current::ProtectionRoleSchema::Owner => ProtectionRole::Owner,
+        current::ProtectionRoleSchema::Editor => ProtectionRole::Editor,
+    }
+}
+
+fn export_role(role: ProtectionRole) -> current::ProtectionRoleSchema {
+    match role {
+        ProtectionRole::Owner => current::ProtectionRoleSchema::Owner,
+        ProtectionRole::Editor => current::ProtectionRoleSchema::Editor,
+    }
+}
+
+pub(crate) fn import_protections(protections: Vec<current::ProtectionSchema>) -> SheetProtections {
+    SheetProtections {
+        protections: protections
+            .into_iter()
+            .map(|protection| Protection {
+                id: protection.id,
+                target: match protection.target {
+                    current::ProtectionTargetSchema::Sheet => ProtectionTarget::Sheet,
+                    current::ProtectionTargetSchema::Range(rect) => {
+                        ProtectionTarget::Range(Rect::from(&rect))
+                    }
+                    current::ProtectionTargetSchema::DataTable(pos) => {
+                        ProtectionTarget::DataTable(Pos { x: pos.x, y: pos.y })
+                    }
+                },
+                allow: ProtectionAllowList {
+                    user_ids: protection.allow.user_ids,
+                    roles: protection
+                        .allow
+                        .roles
+                        .into_iter()
+                        .map(import_role)
+                        .collect(),
+                },
+            })
+            .collect(),
+    }
+}
+
+pub(crate) fn export_protections(protections: SheetProtections) -> Vec<current::ProtectionSchema> {
+    protections
+        .protections
+        .into_iter()
+        .map(|protection| current::ProtectionSchema {
+            id: protection.id,
+            target: match protection.target {
+                ProtectionTarget::Sheet => current::ProtectionTargetSchema::Sheet,
+                ProtectionTarget::Range(rect) => {
+                    current::ProtectionTargetSchema::Range(current::RectSchema::from(&rect))
+                }
+                ProtectionTarget::DataTable(pos) => {
+                    current::ProtectionTargetSchema::DataTable(current::PosSchema::from(pos))
+                }
+            },
+            allow: current::ProtectionAllowListSchema {
+                user_ids: protection.allow.user_ids,
+                roles: protection
+                    .allow
+                    .roles
+                    .into_iter()
+                    .map(export_role)
+                    .collect(),
+            },
+        })
+        .collect()
+}
@@ -13,6 +13,7 @@ use super::{
     current,
     data_table::{export_data_tables, import_data_table_builder},
     formats::{export_formats, import_formats},
+    protections::{export_protections, import_protections},
     row_resizes::{export_rows_size, import_rows_resize},
     validations::{export_validations, import_validations},
 };
@@ -32,6 +33,7 @@ pub fn import_sheet(sheet: current::SheetSchema) -> Result<Sheet> {
         validations: import_validations(sheet.validations),
         borders: import_borders(sheet.borders),
         formats: import_formats(sheet.formats),
+        protections: import_protections(sheet.protections),
     };
     Ok(new_sheet)
 }
@@ -51,5 +53,6 @@ pub(crate) fn export_sheet(sheet: Sheet) -> current::SheetSchema {
         data_tables: export_data_tables(sheet.data_tables),
         formats: export_formats(sheet.formats),
         columns: export_column_builder(sheet.columns),
+        protections: export_protections(sheet.protections),
     }
 }
@@ -265,6 +265,7 @@ pub enum CodeCellLanguage {
     Javascript,
     Connection { kind: ConnectionKind, id: String },
     Import,
+    Sql,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -273,6 +274,10 @@ pub enum ConnectionKind {
     Mysql,
     Mssql,
     Snowflake,
+    Sqlite,
+    DuckDb,
+    ClickHouse,
+    Http,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
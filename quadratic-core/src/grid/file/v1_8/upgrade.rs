@@ -75,7 +75,18 @@ fn upgrade_data_tables(
                     header_is_first_row: data_table.header_is_first_row,
                     show_name,
                     show_columns,
-                    columns: data_table.columns,
+                    columns: data_table.columns.map(|columns| {
+                        columns
+                            .into_iter()
+                            .map(|column| v1_9::DataTableColumnSchema {
+                                name: column.name,
+                                display: column.display,
+                                value_index: column.value_index,
+                                column_type: None,
+                                formula: None,
+                            })
+                            .collect()
+                    }),
                     sort: data_table.sort,
                     sort_dirty: data_table.sort_dirty,
                     display_buffer: data_table.display_buffer,
@@ -87,6 +98,7 @@ fn upgrade_data_tables(
                     borders: data_table.borders,
                     chart_pixel_output: data_table.chart_pixel_output,
                     chart_output: data_table.chart_output,
+                    calculated_cells_accessed: vec![],
                 },
             ))
         })
@@ -108,6 +120,7 @@ pub fn upgrade_sheet(sheet: current::SheetSchema) -> v1_9::SheetSchema {
         rows_resize: sheet.rows_resize,
         borders: sheet.borders,
         formats: sheet.formats,
+        protections: vec![],
     }
 }
 
@@ -1,6 +1,7 @@
 use crate::grid::file::v1_8;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 pub type A1SelectionSchema = v1_8::A1SelectionSchema;
 pub type AxisSchema = v1_8::AxisSchema;
@@ -29,7 +30,6 @@ pub type ColumnSchema = v1_8::ColumnSchema;
 pub type ColumnsSchema = v1_8::ColumnsSchema;
 pub type ConnectionKindSchema = v1_8::ConnectionKindSchema;
 pub type Contiguous2DSchema<T> = v1_8::Contiguous2DSchema<T>;
-pub type DataTableColumnSchema = v1_8::DataTableColumnSchema;
 pub type DataTableSortOrderSchema = v1_8::DataTableSortOrderSchema;
 pub type DateTimeRangeSchema = v1_8::DateTimeRangeSchema;
 pub type FormatSchema = v1_8::FormatSchema;
@@ -74,6 +74,29 @@ pub type ValidationStyleSchema = v1_8::ValidationStyleSchema;
 pub type ValidationTextSchema = v1_8::ValidationTextSchema;
 pub type ValidationsSchema = v1_8::ValidationsSchema;
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DataTableColumnTypeSchema {
+    Text,
+    Number,
+    Date,
+    DateTime,
+    Boolean,
+    Currency,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataTableColumnSchema {
+    pub name: CellValueSchema,
+    pub display: bool,
+    pub value_index: u32,
+
+    #[serde(default)]
+    pub column_type: Option<DataTableColumnTypeSchema>,
+
+    #[serde(default)]
+    pub formula: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CodeRunSchema {
     pub language: CodeCellLanguageSchema,
@@ -113,10 +136,39 @@ pub struct DataTableSchema {
     pub borders: BordersSchema,
     pub chart_pixel_output: Option<(f32, f32)>,
     pub chart_output: Option<(u32, u32)>,
+
+    #[serde(default)]
+    pub calculated_cells_accessed: CellsAccessedSchema,
 }
 
 pub type DataTablesSchema = Vec<(PosSchema, DataTableSchema)>;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProtectionRoleSchema {
+    Owner,
+    Editor,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtectionAllowListSchema {
+    pub user_ids: Vec<String>,
+    pub roles: Vec<ProtectionRoleSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProtectionTargetSchema {
+    Sheet,
+    Range(RectSchema),
+    DataTable(PosSchema),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtectionSchema {
+    pub id: Uuid,
+    pub target: ProtectionTargetSchema,
+    pub allow: ProtectionAllowListSchema,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SheetSchema {
     pub id: IdSchema,
@@ -130,6 +182,9 @@ pub struct SheetSchema {
     pub rows_resize: RowsResizesSchema,
     pub borders: BordersSchema,
     pub formats: SheetFormattingSchema,
+
+    #[serde(default)]
+    pub protections: Vec<ProtectionSchema>,
 }
 
 #[derive(Default, Debug, PartialEq, Serialize, Deserialize, Clone)]
@@ -90,6 +90,32 @@ impl SheetFormatUpdates {
             || Self::item_intersects(&self.strike_through, rect)
     }
 
+    fn item_rects<T>(item: &SheetFormatUpdatesType<T>) -> impl '_ + Iterator<Item = Rect>
+    where
+        T: Clone + Debug + PartialEq,
+    {
+        item.iter().flat_map(|item| item.to_unbounded_rects())
+    }
+
+    /// Returns the rects changed by the format update. Unbounded rects extend
+    /// to `i64::MAX`.
+    pub fn rects(&self) -> Vec<Rect> {
+        Self::item_rects(&self.align)
+            .chain(Self::item_rects(&self.vertical_align))
+            .chain(Self::item_rects(&self.wrap))
+            .chain(Self::item_rects(&self.numeric_format))
+            .chain(Self::item_rects(&self.numeric_decimals))
+            .chain(Self::item_rects(&self.numeric_commas))
+            .chain(Self::item_rects(&self.bold))
+            .chain(Self::item_rects(&self.italic))
+            .chain(Self::item_rects(&self.text_color))
+            .chain(Self::item_rects(&self.fill_color))
+            .chain(Self::item_rects(&self.date_time))
+            .chain(Self::item_rects(&self.underline))
+            .chain(Self::item_rects(&self.strike_through))
+            .collect()
+    }
+
     /// Returns whether the format update is empty.
     pub fn is_default(&self) -> bool {
         self.align.as_ref().is_none_or(|a| a.is_all_default())
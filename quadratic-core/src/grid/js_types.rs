@@ -5,7 +5,9 @@ use ts_rs::TS;
 use uuid::Uuid;
 
 use super::cells_accessed::JsCellsAccessed;
-use super::data_table::{column_header::DataTableColumnHeader, sort::DataTableSort};
+use super::data_table::{
+    column_header::DataTableColumnHeader, column_type::DataTableColumnType, sort::DataTableSort,
+};
 use super::formats::Format;
 use super::formatting::{CellAlign, CellVerticalAlign, CellWrap};
 use super::sheet::validations::validation::ValidationStyle;
@@ -357,6 +359,8 @@ pub struct JsDataTableColumnHeader {
     pub name: String,
     pub display: bool,
     pub value_index: u32,
+    pub column_type: Option<DataTableColumnType>,
+    pub formula: Option<String>,
 }
 
 impl From<DataTableColumnHeader> for JsDataTableColumnHeader {
@@ -365,6 +369,8 @@ impl From<DataTableColumnHeader> for JsDataTableColumnHeader {
             name: column.name.to_string(),
             display: column.display,
             value_index: column.value_index,
+            column_type: column.column_type,
+            formula: column.formula,
         }
     }
 }
@@ -375,6 +381,8 @@ impl From<JsDataTableColumnHeader> for DataTableColumnHeader {
             name: column.name.into(),
             display: column.display,
             value_index: column.value_index,
+            column_type: column.column_type,
+            formula: column.formula,
         }
     }
 }
@@ -6,6 +6,7 @@ use bigdecimal::{BigDecimal, RoundingMode};
 use borders::Borders;
 use indexmap::IndexMap;
 use lazy_static::lazy_static;
+use protection::SheetProtections;
 use rand::Rng;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -36,6 +37,7 @@ pub mod col_row;
 pub mod data_table;
 pub mod formats;
 pub mod keyboard;
+pub mod protection;
 pub mod rendering;
 pub mod rendering_date_time;
 pub mod row_resize;
@@ -82,6 +84,9 @@ pub struct Sheet {
     pub(super) rows_resize: ResizeMap,
 
     pub borders: Borders,
+
+    #[serde(default)]
+    pub protections: SheetProtections,
 }
 impl Sheet {
     /// Constructs a new empty sheet.
@@ -100,6 +105,7 @@ impl Sheet {
             validations: Validations::default(),
             rows_resize: ResizeMap::default(),
             borders: Borders::default(),
+            protections: SheetProtections::default(),
         }
     }
 
@@ -165,6 +165,16 @@ impl BordersUpdates {
             && self.bottom.as_ref().is_none_or(|c| c.is_all_default())
     }
 
+    /// Returns the rects changed by the update. Unbounded rects extend to
+    /// `i64::MAX`.
+    pub fn rects(&self) -> Vec<Rect> {
+        [&self.left, &self.right, &self.top, &self.bottom]
+            .into_iter()
+            .flatten()
+            .flat_map(|borders| borders.to_unbounded_rects())
+            .collect()
+    }
+
     pub fn intersects(&self, rect: Rect) -> bool {
         self.left.as_ref().is_some_and(|left| left.intersects(rect))
             || self
@@ -4,6 +4,7 @@ use anyhow::{Result, anyhow};
 
 use crate::{
     Array, CellValue, Pos, Rect, controller::execution::run_code::get_cells::JsCellsA1Value,
+    grid::column_type::DataTableColumnType,
 };
 
 use super::Sheet;
@@ -61,6 +62,26 @@ impl Sheet {
         response
     }
 
+    /// Returns the data table column type for each column in the rect. Returns
+    /// an empty vec if none of the columns belong to a typed data table column.
+    pub fn column_types_in_rect(&self, rect: Rect) -> Vec<Option<DataTableColumnType>> {
+        let column_types = rect
+            .x_range()
+            .map(|x| {
+                let pos = Pos { x, y: rect.max.y };
+                let (data_table_pos, data_table) = self.data_table_at(pos)?;
+                let display_x = u32::try_from(x - data_table_pos.x).ok()?;
+                data_table.display_header_at(display_x)?.column_type
+            })
+            .collect::<Vec<_>>();
+
+        if column_types.iter().all(Option::is_none) {
+            vec![]
+        } else {
+            column_types
+        }
+    }
+
     // todo: the following two functions are probably in the wrong place
 
     /// In a given rect, collect all cell values into an array.
@@ -5,7 +5,7 @@ use crate::{
     cell_values::CellValues,
     grid::{
         CodeCellLanguage, CodeCellValue, DataTableKind,
-        data_table::DataTable,
+        data_table::{DataTable, column_type::DataTableColumnType},
         formats::{FormatUpdate, SheetFormatUpdates},
     },
 };
@@ -165,6 +165,22 @@ impl Sheet {
             })
     }
 
+    /// Returns the data table and the type of its column at a position, if the
+    /// position is a value cell of a typed column. Name and header cells are
+    /// never typed.
+    pub fn data_table_column_type_at(&self, pos: Pos) -> Option<(&DataTable, DataTableColumnType)> {
+        let (data_table_pos, data_table) = self.data_table_at(pos)?;
+
+        if pos.y < data_table_pos.y + data_table.y_adjustment(false) {
+            return None;
+        }
+
+        let display_x = u32::try_from(pos.x - data_table_pos.x).ok()?;
+        let column_type = data_table.display_header_at(display_x)?.column_type?;
+
+        Some((data_table, column_type))
+    }
+
     /// Checks whether a chart intersects a position. We ignore the chart if it
     /// includes either exclude_x or exclude_y.
     pub fn chart_intersects(
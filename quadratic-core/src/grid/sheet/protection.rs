@@ -0,0 +1,312 @@
+//! Sheet protection
+//!
+//! A protection locks a whole sheet, a range of cells or a data table. Only
+//! users on the protection's allow-list (and owners) may edit what it covers.
+//! Protections are changed with `Operation::SetSheetProtections`, which only
+//! owners may send.
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{Pos, Rect, grid::Sheet};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, TS)]
+pub enum ProtectionRole {
+    Owner,
+    Editor,
+}
+
+/// Users and roles that may edit what a protection covers.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+pub struct ProtectionAllowList {
+    #[serde(default)]
+    pub user_ids: Vec<String>,
+
+    #[serde(default)]
+    pub roles: Vec<ProtectionRole>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub enum ProtectionTarget {
+    Sheet,
+    Range(Rect),
+
+    /// The data table anchored at the position.
+    DataTable(Pos),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct Protection {
+    pub id: Uuid,
+    pub target: ProtectionTarget,
+    pub allow: ProtectionAllowList,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+pub struct SheetProtections {
+    #[serde(default)]
+    pub protections: Vec<Protection>,
+}
+
+/// The output rects of a sheet's data tables, by anchor. The server doesn't
+/// keep a grid, so it checks edits against data table protections with the
+/// rects of the last saved copy of the file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DataTableRects {
+    #[serde(default)]
+    pub tables: Vec<(Pos, Rect)>,
+}
+
+/// The user making an edit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct ProtectionUser {
+    pub user_id: String,
+    pub roles: Vec<ProtectionRole>,
+}
+
+/// What an operation edits, used to check it against protections.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtectedEdit {
+    /// Edits the cells within the rect.
+    Rect(Rect),
+
+    /// Edits the data table anchored at the position.
+    DataTable(Pos),
+
+    /// Edits the sheet itself (eg, its name or color) without changing its
+    /// cells. Only a sheet protection covers it. Edits that shift cells (eg,
+    /// inserting a row) are `Rect` edits of the cells they shift.
+    Sheet,
+
+    /// Changes the sheet's protections.
+    Protections,
+}
+
+impl ProtectionUser {
+    pub fn is_owner(&self) -> bool {
+        self.roles.contains(&ProtectionRole::Owner)
+    }
+}
+
+impl ProtectionAllowList {
+    /// Returns true if the user may edit what the protection covers. Owners
+    /// are always allowed.
+    pub fn allows(&self, user: &ProtectionUser) -> bool {
+        user.is_owner()
+            || self.user_ids.contains(&user.user_id)
+            || user.roles.iter().any(|role| self.roles.contains(role))
+    }
+}
+
+impl DataTableRects {
+    pub fn get(&self, pos: Pos) -> Option<Rect> {
+        self.tables
+            .iter()
+            .find(|(anchor, _)| *anchor == pos)
+            .map(|(_, rect)| *rect)
+    }
+
+    pub fn set(&mut self, pos: Pos, rect: Rect) {
+        self.remove(pos);
+        self.tables.push((pos, rect));
+    }
+
+    pub fn remove(&mut self, pos: Pos) {
+        self.tables.retain(|(anchor, _)| *anchor != pos);
+    }
+}
+
+impl Sheet {
+    /// Returns the output rects of the sheet's data tables.
+    pub fn data_table_rects(&self) -> DataTableRects {
+        DataTableRects {
+            tables: self
+                .iter_data_tables()
+                .map(|(pos, data_table)| (*pos, data_table.output_rect(*pos, false)))
+                .collect(),
+        }
+    }
+}
+
+impl SheetProtections {
+    pub fn is_empty(&self) -> bool {
+        self.protections.is_empty()
+    }
+
+    /// Checks whether the user may make the edit. Returns a message
+    /// explaining why the edit is not allowed.
+    ///
+    /// `data_table_rect` returns the output rect of the data table anchored
+    /// at a position. Where it's unknown (eg, on the server), a data table
+    /// protection only covers its anchor cell and operations on the table.
+    pub fn check(
+        &self,
+        user: &ProtectionUser,
+        edit: &ProtectedEdit,
+        data_table_rect: impl Fn(Pos) -> Option<Rect>,
+    ) -> Result<(), String> {
+        if *edit == ProtectedEdit::Protections {
+            return match user.is_owner() {
+                true => Ok(()),
+                false => Err("Only owners can change protections.".to_string()),
+            };
+        }
+
+        let table_rect = |pos: Pos| data_table_rect(pos).unwrap_or(Rect::single_pos(pos));
+
+        for protection in self.protections.iter() {
+            if protection.allow.allows(user) {
+                continue;
+            }
+
+            let blocked = match (&protection.target, edit) {
+                (ProtectionTarget::Sheet, _) => true,
+                (_, ProtectedEdit::Sheet) => false,
+                (ProtectionTarget::Range(range), ProtectedEdit::Rect(rect)) => {
+                    range.intersects(*rect)
+                }
+                (ProtectionTarget::Range(range), ProtectedEdit::DataTable(pos)) => {
+                    range.intersects(table_rect(*pos))
+                }
+                (ProtectionTarget::DataTable(table), ProtectedEdit::Rect(rect)) => {
+                    table_rect(*table).intersects(*rect)
+                }
+                (ProtectionTarget::DataTable(table), ProtectedEdit::DataTable(pos)) => table == pos,
+                (_, ProtectedEdit::Protections) => false,
+            };
+
+            if blocked {
+                return Err(match protection.target {
+                    ProtectionTarget::Sheet => "This sheet is locked.",
+                    ProtectionTarget::Range(_) => "This range is locked.",
+                    ProtectionTarget::DataTable(_) => "This table is locked.",
+                }
+                .to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn user(roles: Vec<ProtectionRole>) -> ProtectionUser {
+        ProtectionUser {
+            user_id: "user".to_string(),
+            roles,
+        }
+    }
+
+    fn protection(target: ProtectionTarget, allow: ProtectionAllowList) -> Protection {
+        Protection {
+            id: Uuid::new_v4(),
+            target,
+            allow,
+        }
+    }
+
+    #[test]
+    fn test_check_protections() {
+        let editor = user(vec![ProtectionRole::Editor]);
+        let owner = user(vec![ProtectionRole::Owner, ProtectionRole::Editor]);
+        let no_table = |_| None;
+
+        let protections = SheetProtections {
+            protections: vec![
+                protection(
+                    ProtectionTarget::Range(Rect::new(1, 1, 2, 2)),
+                    ProtectionAllowList::default(),
+                ),
+                protection(
+                    ProtectionTarget::DataTable(pos![E5]),
+                    ProtectionAllowList::default(),
+                ),
+            ],
+        };
+
+        let check = |user: &ProtectionUser, edit: ProtectedEdit| {
+            protections.check(user, &edit, no_table).is_ok()
+        };
+
+        assert!(!check(&editor, ProtectedEdit::Rect(Rect::new(2, 2, 3, 3))));
+        assert!(check(&editor, ProtectedEdit::Rect(Rect::new(3, 3, 4, 4))));
+        assert!(!check(&editor, ProtectedEdit::DataTable(pos![E5])));
+        assert!(!check(
+            &editor,
+            ProtectedEdit::Rect(Rect::single_pos(pos![E5]))
+        ));
+        assert!(check(&editor, ProtectedEdit::DataTable(pos![F5])));
+        assert!(check(&editor, ProtectedEdit::Sheet));
+        assert!(!check(&editor, ProtectedEdit::Protections));
+
+        // owners may do anything
+        assert!(check(&owner, ProtectedEdit::Rect(Rect::new(2, 2, 3, 3))));
+        assert!(check(&owner, ProtectedEdit::Sheet));
+        assert!(check(&owner, ProtectedEdit::Protections));
+
+        // the table's output rect is protected when it's known
+        let table_rect = |_| Some(Rect::new(5, 5, 6, 8));
+        let edit = ProtectedEdit::Rect(Rect::single_pos(pos![F8]));
+        assert!(protections.check(&editor, &edit, table_rect).is_err());
+        assert!(protections.check(&editor, &edit, no_table).is_ok());
+    }
+
+    #[test]
+    fn test_check_protections_allow_list() {
+        let allowed = user(vec![ProtectionRole::Editor]);
+        let not_allowed = ProtectionUser {
+            user_id: "other".to_string(),
+            roles: vec![ProtectionRole::Editor],
+        };
+
+        let protections = SheetProtections {
+            protections: vec![protection(
+                ProtectionTarget::Sheet,
+                ProtectionAllowList {
+                    user_ids: vec!["user".to_string()],
+                    roles: vec![],
+                },
+            )],
+        };
+        let edit = ProtectedEdit::Rect(Rect::new(1, 1, 1, 1));
+
+        assert!(protections.check(&allowed, &edit, |_| None).is_ok());
+        assert_eq!(
+            protections.check(&not_allowed, &edit, |_| None),
+            Err("This sheet is locked.".to_string())
+        );
+        assert!(
+            protections
+                .check(&not_allowed, &ProtectedEdit::Sheet, |_| None)
+                .is_err()
+        );
+
+        // allowing a role allows every user with the role
+        let protections = SheetProtections {
+            protections: vec![protection(
+                ProtectionTarget::Sheet,
+                ProtectionAllowList {
+                    user_ids: vec![],
+                    roles: vec![ProtectionRole::Editor],
+                },
+            )],
+        };
+        assert!(protections.check(&not_allowed, &edit, |_| None).is_ok());
+    }
+
+    #[test]
+    fn test_data_table_rects() {
+        let mut rects = DataTableRects::default();
+        rects.set(pos![B2], Rect::new(2, 2, 3, 5));
+        rects.set(pos![B2], Rect::new(2, 2, 4, 5));
+        assert_eq!(rects.get(pos![B2]), Some(Rect::new(2, 2, 4, 5)));
+        assert_eq!(rects.tables.len(), 1);
+
+        rects.remove(pos![B2]);
+        assert_eq!(rects.get(pos![B2]), None);
+    }
+}
@@ -32,6 +32,7 @@ pub mod sheet_offsets;
 mod sheet_rect;
 pub mod small_timestamp;
 mod span;
+mod sql;
 #[macro_use]
 pub mod test_util;
 mod values;
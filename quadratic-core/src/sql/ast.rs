@@ -0,0 +1,184 @@
+//! Abstract syntax tree of SQL queries.
+
+use crate::{CellValue, Spanned};
+
+/// A `SELECT` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub distinct: bool,
+    pub items: Vec<SelectItem>,
+    pub from: Vec<TableSource>,
+    pub joins: Vec<Join>,
+    pub filter: Option<Spanned<Expr>>,
+    pub group_by: Vec<Spanned<Expr>>,
+    pub having: Option<Spanned<Expr>>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl Query {
+    /// Tables that the query reads, in the order they appear.
+    pub fn tables(&self) -> impl Iterator<Item = &TableSource> {
+        self.from
+            .iter()
+            .chain(self.joins.iter().map(|join| &join.table))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    /// `*`, or `table.*` when qualified
+    Wildcard(Option<String>),
+    Expr {
+        expr: Spanned<Expr>,
+        /// Name of the output column, from its alias or source
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSource {
+    pub name: Spanned<String>,
+    pub alias: Option<String>,
+}
+
+impl TableSource {
+    /// Name that columns are qualified with
+    pub fn reference(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name.inner)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableSource,
+    pub on: Option<Spanned<Expr>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expr: Spanned<Expr>,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Concat,
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(CellValue),
+    Column {
+        table: Option<String>,
+        name: String,
+    },
+    Neg(Box<Spanned<Expr>>),
+    Not(Box<Spanned<Expr>>),
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Spanned<Expr>>,
+        rhs: Box<Spanned<Expr>>,
+    },
+    IsNull {
+        expr: Box<Spanned<Expr>>,
+        negated: bool,
+    },
+    InList {
+        expr: Box<Spanned<Expr>>,
+        list: Vec<Spanned<Expr>>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<Spanned<Expr>>,
+        low: Box<Spanned<Expr>>,
+        high: Box<Spanned<Expr>>,
+        negated: bool,
+    },
+    Like {
+        expr: Box<Spanned<Expr>>,
+        pattern: Box<Spanned<Expr>>,
+        negated: bool,
+    },
+    Case {
+        operand: Option<Box<Spanned<Expr>>>,
+        branches: Vec<(Spanned<Expr>, Spanned<Expr>)>,
+        otherwise: Option<Box<Spanned<Expr>>>,
+    },
+    Function {
+        /// Uppercase function name
+        name: String,
+        args: Vec<Spanned<Expr>>,
+        distinct: bool,
+    },
+    /// `COUNT(*)`
+    CountAll,
+}
+
+impl Expr {
+    /// Whether the expression contains an aggregate function.
+    pub fn has_aggregate(&self) -> bool {
+        match self {
+            Expr::CountAll => true,
+            Expr::Function { name, args, .. } => {
+                is_aggregate(name) || args.iter().any(|arg| arg.inner.has_aggregate())
+            }
+            Expr::Literal(_) | Expr::Column { .. } => false,
+            Expr::Neg(expr) | Expr::Not(expr) | Expr::IsNull { expr, .. } => {
+                expr.inner.has_aggregate()
+            }
+            Expr::Binary { lhs, rhs, .. } => lhs.inner.has_aggregate() || rhs.inner.has_aggregate(),
+            Expr::InList { expr, list, .. } => {
+                expr.inner.has_aggregate() || list.iter().any(|e| e.inner.has_aggregate())
+            }
+            Expr::Between {
+                expr, low, high, ..
+            } => {
+                expr.inner.has_aggregate()
+                    || low.inner.has_aggregate()
+                    || high.inner.has_aggregate()
+            }
+            Expr::Like { expr, pattern, .. } => {
+                expr.inner.has_aggregate() || pattern.inner.has_aggregate()
+            }
+            Expr::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                operand.as_ref().is_some_and(|e| e.inner.has_aggregate())
+                    || branches.iter().any(|(when, then)| {
+                        when.inner.has_aggregate() || then.inner.has_aggregate()
+                    })
+                    || otherwise.as_ref().is_some_and(|e| e.inner.has_aggregate())
+            }
+        }
+    }
+}
+
+/// Whether `name` (uppercase) is an aggregate function
+pub fn is_aggregate(name: &str) -> bool {
+    matches!(name, "COUNT" | "SUM" | "AVG" | "MIN" | "MAX")
+}
@@ -0,0 +1,749 @@
+//! Evaluation of SQL queries over tables of cell values.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive, Zero};
+
+use super::ast::*;
+use crate::util::case_fold;
+use crate::{CellValue, CellValueHash, CodeResult, IsBlank, RunErrorMsg, Span, Spanned};
+
+/// Maximum number of rows that joins may produce
+const MAX_ROWS: usize = 1_000_000;
+
+/// A table read by a query, or a query's results.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SqlTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<CellValue>>,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    /// Case-folded name of the table that the column came from
+    table: String,
+    name: String,
+    /// Case-folded name of the column
+    folded: String,
+}
+
+/// Rows of the joined tables
+#[derive(Debug, Default)]
+struct Relation {
+    columns: Vec<Column>,
+    rows: Vec<Vec<CellValue>>,
+}
+
+impl Relation {
+    fn new(source: &TableSource, table: SqlTable) -> Self {
+        let reference = case_fold(source.reference());
+        Relation {
+            columns: table
+                .columns
+                .iter()
+                .map(|name| Column {
+                    table: reference.to_owned(),
+                    name: name.to_owned(),
+                    folded: case_fold(name),
+                })
+                .collect(),
+            rows: table.rows,
+        }
+    }
+
+    /// Joins another table, keeping pairs of rows where `on` is true. Left
+    /// joins keep rows with no match, with `NULL`s in place of the other
+    /// table.
+    fn join(self, other: Relation, kind: JoinKind, on: Option<&Spanned<Expr>>) -> CodeResult<Self> {
+        let mut columns = self.columns;
+        columns.extend(other.columns);
+
+        let mut rows = vec![];
+        for lhs in self.rows {
+            let mut matched = false;
+            for rhs in &other.rows {
+                let mut row = lhs.clone();
+                row.extend(rhs.iter().cloned());
+
+                let keep = match on {
+                    Some(on) => {
+                        let scope = Scope {
+                            columns: &columns,
+                            row: Some(&row),
+                            group: None,
+                        };
+                        truthy(&scope.eval(on)?, on.span)? == Some(true)
+                    }
+                    None => true,
+                };
+                if keep {
+                    if rows.len() >= MAX_ROWS {
+                        return Err(RunErrorMsg::ArrayTooBig.without_span());
+                    }
+                    rows.push(row);
+                    matched = true;
+                }
+            }
+
+            if !matched && kind == JoinKind::Left {
+                let mut row = lhs;
+                row.resize(columns.len(), CellValue::Blank);
+                rows.push(row);
+            }
+        }
+
+        Ok(Relation { columns, rows })
+    }
+}
+
+impl Query {
+    /// Runs the query, reading each table that it names with `get_table`.
+    pub fn eval(
+        &self,
+        mut get_table: impl FnMut(&str) -> CodeResult<SqlTable>,
+    ) -> CodeResult<SqlTable> {
+        let mut load = |source: &TableSource| -> CodeResult<Relation> {
+            let table = get_table(&source.name.inner).map_err(|e| e.with_span(source.name.span))?;
+            Ok(Relation::new(source, table))
+        };
+
+        // without FROM, expressions are evaluated once
+        let mut relation = Relation {
+            columns: vec![],
+            rows: vec![vec![]],
+        };
+        for source in &self.from {
+            relation = relation
+                .join(load(source)?, JoinKind::Cross, None)
+                .map_err(|e| e.with_span(source.name.span))?;
+        }
+        for join in &self.joins {
+            relation = relation
+                .join(load(&join.table)?, join.kind, join.on.as_ref())
+                .map_err(|e| e.with_span(join.table.name.span))?;
+        }
+
+        if let Some(filter) = &self.filter {
+            let mut rows = vec![];
+            for row in std::mem::take(&mut relation.rows) {
+                let scope = Scope {
+                    columns: &relation.columns,
+                    row: Some(&row),
+                    group: None,
+                };
+                if truthy(&scope.eval(filter)?, filter.span)? == Some(true) {
+                    rows.push(row);
+                }
+            }
+            relation.rows = rows;
+        }
+
+        let (names, exprs) = self.output_columns(&relation)?;
+
+        // rows, or groups of rows, that become rows of the results
+        let grouped = !self.group_by.is_empty()
+            || exprs.iter().any(|expr| expr.inner.has_aggregate())
+            || self.having.is_some()
+            || self.order_by.iter().any(|o| o.expr.inner.has_aggregate());
+        let units: Vec<Vec<&Vec<CellValue>>> = if grouped {
+            let group_exprs = self
+                .group_by
+                .iter()
+                .map(|expr| self.resolve_output_ref(expr, &names, &exprs, &relation))
+                .collect::<CodeResult<Vec<_>>>()?;
+
+            let mut keyed = vec![];
+            for row in &relation.rows {
+                let scope = Scope {
+                    columns: &relation.columns,
+                    row: Some(row),
+                    group: None,
+                };
+                let key = group_exprs
+                    .iter()
+                    .map(|expr| scope.eval(expr))
+                    .collect::<CodeResult<Vec<_>>>()?;
+                keyed.push((key, row));
+            }
+
+            let groups = group_values(keyed)
+                .into_iter()
+                .map(|(_, rows)| rows)
+                .collect::<Vec<_>>();
+
+            // aggregates without GROUP BY always have one row of results
+            match groups.is_empty() && self.group_by.is_empty() {
+                true => vec![vec![]],
+                false => groups,
+            }
+        } else {
+            relation.rows.iter().map(|row| vec![row]).collect()
+        };
+
+        let mut results = vec![];
+        for rows in &units {
+            let scope = Scope {
+                columns: &relation.columns,
+                row: rows.first().map(|row| row.as_slice()),
+                group: grouped.then_some(rows.as_slice()),
+            };
+
+            if let Some(having) = &self.having
+                && truthy(&scope.eval(having)?, having.span)? != Some(true)
+            {
+                continue;
+            }
+
+            let values = exprs
+                .iter()
+                .map(|expr| scope.eval(expr))
+                .collect::<CodeResult<Vec<_>>>()?;
+
+            let mut keys = vec![];
+            for order_by in &self.order_by {
+                let expr = self.resolve_output_ref(&order_by.expr, &names, &exprs, &relation)?;
+                keys.push(scope.eval(&expr)?);
+            }
+
+            results.push((values, keys));
+        }
+
+        if self.distinct {
+            results = group_values(results)
+                .into_iter()
+                .filter_map(|(values, keys)| Some((values, keys.into_iter().next()?)))
+                .collect();
+        }
+
+        results.sort_by(|(_, a), (_, b)| {
+            a.iter()
+                .zip(b)
+                .zip(&self.order_by)
+                .map(|((a, b), order_by)| match order_by.descending {
+                    true => sort_cmp(b, a),
+                    false => sort_cmp(a, b),
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let rows = results
+            .into_iter()
+            .map(|(values, _)| values)
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Ok(SqlTable {
+            columns: names,
+            rows,
+        })
+    }
+
+    /// Names and expressions of the output columns, with wildcards expanded
+    fn output_columns(&self, relation: &Relation) -> CodeResult<(Vec<String>, Vec<Spanned<Expr>>)> {
+        let mut names = vec![];
+        let mut exprs = vec![];
+
+        for item in &self.items {
+            match item {
+                SelectItem::Expr { expr, name } => {
+                    names.push(name.to_owned());
+                    exprs.push(expr.to_owned());
+                }
+                SelectItem::Wildcard(table) => {
+                    let table = table.as_deref().map(case_fold);
+                    let columns = relation
+                        .columns
+                        .iter()
+                        .filter(|column| table.as_ref().is_none_or(|t| *t == column.table))
+                        .collect::<Vec<_>>();
+
+                    if let (Some(table), true) = (&table, columns.is_empty()) {
+                        let source = self
+                            .tables()
+                            .find(|source| case_fold(source.reference()) == *table);
+                        if source.is_none() {
+                            return Err(RunErrorMsg::CodeRunError(
+                                format!("Table `{table}` isn't in the query").into(),
+                            )
+                            .without_span());
+                        }
+                    }
+
+                    for column in columns {
+                        names.push(column.name.to_owned());
+                        exprs.push(Spanned {
+                            span: Span::empty(0),
+                            inner: Expr::Column {
+                                table: Some(column.table.to_owned()),
+                                name: column.name.to_owned(),
+                            },
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok((names, exprs))
+    }
+
+    /// Resolves `ORDER BY` and `GROUP BY` references to output columns, by
+    /// position (eg, `ORDER BY 1`) or by alias
+    fn resolve_output_ref(
+        &self,
+        expr: &Spanned<Expr>,
+        names: &[String],
+        exprs: &[Spanned<Expr>],
+        relation: &Relation,
+    ) -> CodeResult<Spanned<Expr>> {
+        match &expr.inner {
+            Expr::Literal(CellValue::Number(n)) if n.is_integer() => {
+                let index = n.to_usize().filter(|&i| i >= 1 && i <= exprs.len());
+                match index {
+                    Some(i) => Ok(exprs[i - 1].to_owned()),
+                    None => Err(RunErrorMsg::IndexOutOfBounds.with_span(expr.span)),
+                }
+            }
+            Expr::Column { table: None, name } => {
+                let folded = case_fold(name);
+                let is_column = relation.columns.iter().any(|c| c.folded == folded);
+                match names.iter().position(|n| case_fold(n) == folded) {
+                    Some(i) if !is_column => Ok(exprs[i].to_owned()),
+                    _ => Ok(expr.to_owned()),
+                }
+            }
+            _ => Ok(expr.to_owned()),
+        }
+    }
+}
+
+/// Where expressions are evaluated: a row of the joined tables, and for
+/// grouped queries, the group of rows that aggregates read.
+struct Scope<'a> {
+    columns: &'a [Column],
+    row: Option<&'a [CellValue]>,
+    group: Option<&'a [&'a Vec<CellValue>]>,
+}
+
+impl Scope<'_> {
+    fn eval(&self, expr: &Spanned<Expr>) -> CodeResult<CellValue> {
+        let span = expr.span;
+
+        match &expr.inner {
+            Expr::Literal(value) => Ok(value.to_owned()),
+            Expr::Column { table, name } => self.column(table.as_deref(), name, span),
+            Expr::Neg(expr) => {
+                let value = self.eval(expr)?;
+                match value.is_blank() {
+                    true => Ok(CellValue::Blank),
+                    false => Ok(CellValue::neg(Spanned {
+                        span,
+                        inner: &value,
+                    })?
+                    .inner),
+                }
+            }
+            Expr::Not(expr) => {
+                let value = truthy(&self.eval(expr)?, expr.span)?;
+                Ok(value.map_or(CellValue::Blank, |b| CellValue::Logical(!b)))
+            }
+            Expr::Binary { op, lhs, rhs } => self.binary(*op, lhs, rhs, span),
+            Expr::IsNull { expr, negated } => {
+                Ok(CellValue::Logical(self.eval(expr)?.is_blank() != *negated))
+            }
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let value = self.eval(expr)?;
+                if value.is_blank() {
+                    return Ok(CellValue::Blank);
+                }
+
+                let mut has_null = false;
+                for item in list {
+                    let item = self.eval(item)?;
+                    if item.is_blank() {
+                        has_null = true;
+                    } else if value.total_cmp(&item).is_eq() {
+                        return Ok(CellValue::Logical(!negated));
+                    }
+                }
+
+                match has_null {
+                    true => Ok(CellValue::Blank),
+                    false => Ok(CellValue::Logical(*negated)),
+                }
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let (value, low, high) = (self.eval(expr)?, self.eval(low)?, self.eval(high)?);
+                if value.is_blank() || low.is_blank() || high.is_blank() {
+                    return Ok(CellValue::Blank);
+                }
+
+                let between = value.total_cmp(&low).is_ge() && value.total_cmp(&high).is_le();
+                Ok(CellValue::Logical(between != *negated))
+            }
+            Expr::Like {
+                expr,
+                pattern,
+                negated,
+            } => {
+                let (value, pattern) = (self.eval(expr)?, self.eval(pattern)?);
+                if value.is_blank() || pattern.is_blank() {
+                    return Ok(CellValue::Blank);
+                }
+
+                let matches = like(&value.to_display(), &pattern.to_display());
+                Ok(CellValue::Logical(matches != *negated))
+            }
+            Expr::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let operand = operand.as_ref().map(|e| self.eval(e)).transpose()?;
+                for (when, then) in branches {
+                    let value = self.eval(when)?;
+                    let is_match = match &operand {
+                        Some(operand) => {
+                            !operand.is_blank()
+                                && !value.is_blank()
+                                && operand.total_cmp(&value).is_eq()
+                        }
+                        None => truthy(&value, when.span)? == Some(true),
+                    };
+                    if is_match {
+                        return self.eval(then);
+                    }
+                }
+
+                match otherwise {
+                    Some(otherwise) => self.eval(otherwise),
+                    None => Ok(CellValue::Blank),
+                }
+            }
+            Expr::Function {
+                name,
+                args,
+                distinct,
+            } => match is_aggregate(name) {
+                true => self.aggregate(name, args, *distinct, span),
+                false => self.function(name, args, span),
+            },
+            Expr::CountAll => Ok(CellValue::from(self.group(span)?.len())),
+        }
+    }
+
+    fn column(&self, table: Option<&str>, name: &str, span: Span) -> CodeResult<CellValue> {
+        let table = table.map(case_fold);
+        let folded = case_fold(name);
+        let mut indexes = self.columns.iter().enumerate().filter(|(_, column)| {
+            column.folded == folded && table.as_ref().is_none_or(|t| *t == column.table)
+        });
+
+        let index = match (indexes.next(), indexes.next()) {
+            (Some((index, _)), None) => index,
+            (None, _) => {
+                return Err(RunErrorMsg::CodeRunError(
+                    format!("Column `{name}` doesn't exist").into(),
+                )
+                .with_span(span));
+            }
+            (Some(_), Some(_)) => {
+                return Err(RunErrorMsg::CodeRunError(
+                    format!("Column `{name}` is in more than one table").into(),
+                )
+                .with_span(span));
+            }
+        };
+
+        // groups with no rows have no columns
+        Ok(self
+            .row
+            .and_then(|row| row.get(index).cloned())
+            .unwrap_or_default())
+    }
+
+    fn binary(
+        &self,
+        op: BinaryOp,
+        lhs: &Spanned<Expr>,
+        rhs: &Spanned<Expr>,
+        span: Span,
+    ) -> CodeResult<CellValue> {
+        let a = self.eval(lhs)?;
+
+        // AND and OR have three-valued logic, where NULL is unknown
+        if matches!(op, BinaryOp::And | BinaryOp::Or) {
+            let a = truthy(&a, lhs.span)?;
+            let short_circuit = Some(op == BinaryOp::Or);
+            if a == short_circuit {
+                return Ok(CellValue::Logical(op == BinaryOp::Or));
+            }
+
+            let b = truthy(&self.eval(rhs)?, rhs.span)?;
+            return Ok(match (a, b) {
+                (_, b) if b == short_circuit => CellValue::Logical(op == BinaryOp::Or),
+                (Some(_), Some(b)) => CellValue::Logical(b),
+                _ => CellValue::Blank,
+            });
+        }
+
+        let b = self.eval(rhs)?;
+        if a.is_blank() || b.is_blank() {
+            return Ok(CellValue::Blank);
+        }
+
+        let lhs = Spanned {
+            span: lhs.span,
+            inner: &a,
+        };
+        let rhs = Spanned {
+            span: rhs.span,
+            inner: &b,
+        };
+        let ordering = || a.total_cmp(&b);
+
+        Ok(match op {
+            BinaryOp::Add => CellValue::add(span, lhs, rhs)?.inner,
+            BinaryOp::Sub => CellValue::sub(span, lhs, rhs)?.inner,
+            BinaryOp::Mul => CellValue::mul(span, lhs, rhs)?.inner,
+            BinaryOp::Div => CellValue::checked_div(span, lhs, rhs)?.inner,
+            BinaryOp::Mod => {
+                let a = number(&a, lhs.span)?;
+                let b = number(&b, rhs.span)?;
+                if b.is_zero() {
+                    return Err(RunErrorMsg::DivideByZero.with_span(span));
+                }
+                CellValue::Number(a % b)
+            }
+            BinaryOp::Concat => CellValue::Text(a.to_display() + &b.to_display()),
+            BinaryOp::Eq => CellValue::Logical(ordering().is_eq()),
+            BinaryOp::NotEq => CellValue::Logical(ordering().is_ne()),
+            BinaryOp::Lt => CellValue::Logical(ordering().is_lt()),
+            BinaryOp::Lte => CellValue::Logical(ordering().is_le()),
+            BinaryOp::Gt => CellValue::Logical(ordering().is_gt()),
+            BinaryOp::Gte => CellValue::Logical(ordering().is_ge()),
+            BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+        })
+    }
+
+    /// Rows of the group, for aggregates
+    fn group(&self, span: Span) -> CodeResult<&[&Vec<CellValue>]> {
+        self.group.ok_or_else(|| {
+            RunErrorMsg::CodeRunError("Aggregate functions can't be used here".into())
+                .with_span(span)
+        })
+    }
+
+    fn aggregate(
+        &self,
+        name: &str,
+        args: &[Spanned<Expr>],
+        distinct: bool,
+        span: Span,
+    ) -> CodeResult<CellValue> {
+        let [arg] = args else {
+            return Err(arg_count_error(name, args, 1, span));
+        };
+
+        let mut values = vec![];
+        for row in self.group(span)? {
+            let scope = Scope {
+                columns: self.columns,
+                row: Some(row),
+                group: None,
+            };
+            let value = scope.eval(arg)?;
+            if !value.is_blank() {
+                values.push((vec![value], ()));
+            }
+        }
+        if distinct {
+            values = group_values(values)
+                .into_iter()
+                .map(|(value, _)| (value, ()))
+                .collect();
+        }
+        let values = values.into_iter().flat_map(|(value, _)| value);
+
+        Ok(match name {
+            "COUNT" => CellValue::from(values.count()),
+            "SUM" | "AVG" => {
+                let mut sum = BigDecimal::zero();
+                let mut count = 0;
+                for value in values {
+                    sum += number(&value, arg.span)?;
+                    count += 1;
+                }
+                match (name, count) {
+                    (_, 0) => CellValue::Blank,
+                    ("SUM", _) => CellValue::Number(sum),
+                    _ => CellValue::from(sum.to_f64().unwrap_or_default() / count as f64),
+                }
+            }
+            "MIN" => values.min_by(CellValue::total_cmp).unwrap_or_default(),
+            _ => values.max_by(CellValue::total_cmp).unwrap_or_default(),
+        })
+    }
+
+    fn function(&self, name: &str, args: &[Spanned<Expr>], span: Span) -> CodeResult<CellValue> {
+        let values = args
+            .iter()
+            .map(|arg| self.eval(arg))
+            .collect::<CodeResult<Vec<_>>>()?;
+
+        let arg_count = match name {
+            "COALESCE" => {
+                return Ok(values
+                    .into_iter()
+                    .find(|v| !v.is_blank())
+                    .unwrap_or_default());
+            }
+            "UPPER" | "LOWER" | "TRIM" | "LENGTH" | "ABS" => 1..=1,
+            "ROUND" => 1..=2,
+            "NULLIF" => 2..=2,
+            _ => return Err(RunErrorMsg::BadFunctionName.with_span(span)),
+        };
+        if !arg_count.contains(&args.len()) {
+            return Err(arg_count_error(name, args, *arg_count.end(), span));
+        }
+
+        let value = &values[0];
+        if value.is_blank() {
+            return Ok(CellValue::Blank);
+        }
+
+        Ok(match name {
+            "UPPER" => CellValue::Text(value.to_display().to_uppercase()),
+            "LOWER" => CellValue::Text(value.to_display().to_lowercase()),
+            "TRIM" => CellValue::Text(value.to_display().trim().to_string()),
+            "LENGTH" => CellValue::from(value.to_display().chars().count()),
+            "ABS" => CellValue::Number(number(value, args[0].span)?.abs()),
+            "ROUND" => {
+                let digits = match values.get(1) {
+                    Some(digits) => number(digits, args[1].span)?.to_i64().unwrap_or_default(),
+                    None => 0,
+                };
+                CellValue::Number(
+                    number(value, args[0].span)?.with_scale_round(digits, RoundingMode::HalfUp),
+                )
+            }
+            _ => match value.total_cmp(&values[1]).is_eq() {
+                true => CellValue::Blank,
+                false => value.to_owned(),
+            },
+        })
+    }
+}
+
+fn arg_count_error(name: &str, args: &[Spanned<Expr>], max: usize, span: Span) -> crate::RunError {
+    match args.len() > max {
+        true => RunErrorMsg::TooManyArguments {
+            func_name: name.to_owned().into(),
+            max_arg_count: max,
+        },
+        false => RunErrorMsg::MissingRequiredArgument {
+            func_name: name.to_owned().into(),
+            arg_name: "value".into(),
+        },
+    }
+    .with_span(span)
+}
+
+/// Converts a value to a number, eg, for `SUM`
+fn number(value: &CellValue, span: Span) -> CodeResult<BigDecimal> {
+    BigDecimal::try_from(value).map_err(|e| e.with_span(span))
+}
+
+/// Converts a condition to a boolean, where `NULL` is unknown
+fn truthy(value: &CellValue, span: Span) -> CodeResult<Option<bool>> {
+    match value.is_blank() {
+        true => Ok(None),
+        false => bool::try_from(value)
+            .map(Some)
+            .map_err(|e| e.with_span(span)),
+    }
+}
+
+/// Groups items with equal keys, in the order that the keys first appear
+fn group_values<T>(items: Vec<(Vec<CellValue>, T)>) -> Vec<(Vec<CellValue>, Vec<T>)> {
+    let mut groups: Vec<(Vec<CellValue>, Vec<T>)> = vec![];
+    // the hash isn't unique for all types, so keys are compared too
+    let mut buckets: HashMap<Vec<CellValueHash>, Vec<usize>> = HashMap::new();
+
+    for (key, item) in items {
+        let bucket = buckets
+            .entry(key.iter().map(CellValue::hash).collect())
+            .or_default();
+        let group = bucket.iter().copied().find(|&i| {
+            groups[i]
+                .0
+                .iter()
+                .zip(&key)
+                .all(|(a, b)| a.total_cmp(b).is_eq())
+        });
+
+        match group {
+            Some(i) => groups[i].1.push(item),
+            None => {
+                bucket.push(groups.len());
+                groups.push((key, vec![item]));
+            }
+        }
+    }
+
+    groups
+}
+
+/// Sort order, where `NULL`s are first
+fn sort_cmp(a: &CellValue, b: &CellValue) -> Ordering {
+    match (a.is_blank(), b.is_blank()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.total_cmp(b),
+    }
+}
+
+/// Case-insensitive `LIKE`, where `%` matches any text and `_` matches one
+/// character
+fn like(text: &str, pattern: &str) -> bool {
+    let text = case_fold(text).chars().collect::<Vec<_>>();
+    let pattern = case_fold(pattern).chars().collect::<Vec<_>>();
+
+    // backtracks to the last `%` when a character doesn't match
+    let (mut t, mut p) = (0, 0);
+    let mut last_wildcard = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                last_wildcard = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '_' || c == text[t] => {
+                t += 1;
+                p += 1;
+            }
+            _ => match last_wildcard {
+                Some((wildcard_p, wildcard_t)) => {
+                    last_wildcard = Some((wildcard_p, wildcard_t + 1));
+                    p = wildcard_p + 1;
+                    t = wildcard_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '%')
+}
@@ -0,0 +1,209 @@
+//! Tokenization of SQL queries.
+
+use crate::{RunErrorMsg, Span, Spanned};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// Unquoted keyword or identifier, eg, `SELECT` or `orders`
+    Word(String),
+    /// Quoted identifier, eg, `"Order Date"`, `` `Order Date` `` or
+    /// `[Order Date]`
+    QuotedIdent(String),
+    Number(String),
+    /// String literal with its quotes removed and escapes replaced
+    String(String),
+    Symbol(&'static str),
+}
+
+impl Token {
+    /// Whether this is the keyword `keyword` (which must be uppercase)
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Description of the token for error messages
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => format!("`{word}`"),
+            Token::QuotedIdent(ident) => format!("identifier \"{ident}\""),
+            Token::Number(n) => format!("number {n}"),
+            Token::String(_) => "string".to_string(),
+            Token::Symbol(symbol) => format!("`{symbol}`"),
+        }
+    }
+}
+
+/// Symbols, longest first so that eg, `<=` isn't read as `<`
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "<>", "!=", "==", "||", "(", ")", ",", ".", "*", "+", "-", "/", "%", "=", "<", ">",
+    ";",
+];
+
+/// Splits a query into tokens, skipping whitespace and comments.
+pub fn tokenize(sql: &str) -> Result<Vec<Spanned<Token>>, crate::RunError> {
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while let Some(c) = sql[i..].chars().next() {
+        let start = i;
+        let rest = &sql[i..];
+
+        let token = if c.is_whitespace() {
+            i += c.len_utf8();
+            None
+        } else if rest.starts_with("--") {
+            i = rest.find('\n').map_or(sql.len(), |end| i + end + 1);
+            None
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment
+                .find("*/")
+                .ok_or_else(|| unterminated("comment", start, sql.len()))?;
+            i += end + 4;
+            None
+        } else if c == '\'' {
+            let (s, len) =
+                quoted(rest, '\'', '\'').ok_or_else(|| unterminated("string", start, sql.len()))?;
+            i += len;
+            Some(Token::String(s))
+        } else if matches!(c, '"' | '`' | '[') {
+            let close = if c == '[' { ']' } else { c };
+            let (s, len) = quoted(rest, c, close)
+                .ok_or_else(|| unterminated("identifier", start, sql.len()))?;
+            i += len;
+            Some(Token::QuotedIdent(s))
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let len = number_len(rest);
+            i += len;
+            Some(Token::Number(rest[..len].to_string()))
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            i += len;
+            Some(Token::Word(rest[..len].to_string()))
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            i += symbol.len();
+            Some(Token::Symbol(symbol))
+        } else {
+            return Err(RunErrorMsg::Unexpected(format!("character `{c}`").into())
+                .with_span(span(start, start + c.len_utf8())));
+        };
+
+        if let Some(token) = token {
+            tokens.push(Spanned {
+                span: span(start, i),
+                inner: token,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn span(start: usize, end: usize) -> Span {
+    Span {
+        start: start as u32,
+        end: end as u32,
+    }
+}
+
+fn unterminated(what: &'static str, start: usize, end: usize) -> crate::RunError {
+    RunErrorMsg::Unterminated(what.into()).with_span(span(start, end))
+}
+
+/// Reads a quoted string or identifier, where a doubled closing quote is an
+/// escaped quote. Returns the contents and the length including the quotes.
+fn quoted(s: &str, open: char, close: char) -> Option<(String, usize)> {
+    let mut contents = String::new();
+    let mut chars = s.char_indices().skip(1).peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c == close {
+            match chars.peek() {
+                Some(&(_, next)) if next == close && open == close => {
+                    contents.push(close);
+                    chars.next();
+                }
+                _ => return Some((contents, i + c.len_utf8())),
+            }
+        } else {
+            contents.push(c);
+        }
+    }
+
+    None
+}
+
+/// The length of a number, eg, `12`, `1.5`, `.5` or `1e-3`
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut i = digits(0);
+    if bytes.get(i) == Some(&b'.') {
+        i = digits(i + 1);
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(i + 1), Some(b'+' | b'-')));
+        if bytes.get(i + 1 + sign).is_some_and(u8::is_ascii_digit) {
+            i = digits(i + 1 + sign);
+        }
+    }
+
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(sql: &str) -> Vec<Token> {
+        tokenize(sql)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.inner)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokens(
+                "SELECT \"Order Date\", [a b], 'it''s' -- comment\n FROM t /* x */ WHERE a<=1.5e2"
+            ),
+            vec![
+                Token::Word("SELECT".into()),
+                Token::QuotedIdent("Order Date".into()),
+                Token::Symbol(","),
+                Token::QuotedIdent("a b".into()),
+                Token::Symbol(","),
+                Token::String("it's".into()),
+                Token::Word("FROM".into()),
+                Token::Word("t".into()),
+                Token::Word("WHERE".into()),
+                Token::Word("a".into()),
+                Token::Symbol("<="),
+                Token::Number("1.5e2".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        assert_eq!(
+            tokenize("SELECT 'abc").unwrap_err().msg,
+            RunErrorMsg::Unterminated("string".into())
+        );
+        assert_eq!(
+            tokenize("SELECT a ? b").unwrap_err().span,
+            Some(Span { start: 9, end: 10 })
+        );
+    }
+}
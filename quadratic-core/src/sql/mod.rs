@@ -0,0 +1,33 @@
+//! SQL queries over data tables in the grid.
+//!
+//! SQL code cells run a `SELECT` statement locally, where each table in the
+//! query is a data table addressed by name (including the results of
+//! connection cells). The engine is small, and supports:
+//!
+//! - `SELECT [DISTINCT]` with `*`, `table.*` and `expr [AS alias]`
+//! - `FROM` with table aliases, `[INNER] JOIN`, `LEFT [OUTER] JOIN`,
+//!   `CROSS JOIN` and comma-separated tables
+//! - `WHERE`, `GROUP BY`, `HAVING`, `ORDER BY` and `LIMIT ... OFFSET`
+//! - `COUNT`, `SUM`, `AVG`, `MIN` and `MAX`, with `DISTINCT`
+//! - `CASE`, `IN`, `LIKE`, `BETWEEN`, `IS NULL` and a few scalar functions
+//!
+//! Blank cells are `NULL`. Like formulas, text compares case-insensitively.
+//!
+//! The engine is written here rather than built on DataFusion (or sqlparser
+//! plus Arrow) because core compiles to wasm and runs in the browser's web
+//! worker: DataFusion depends on tokio and an async planner, has limited
+//! wasm support and would add several megabytes to the bundle. The tables
+//! are already in memory and small enough to scan, and the queries need the
+//! grid's `CellValue` semantics (blanks, numbers stored as decimals and
+//! case-insensitive text), which Arrow types would lose on the way in.
+
+mod ast;
+mod eval;
+mod lexer;
+mod parser;
+
+#[cfg(test)]
+mod tests;
+
+pub use eval::SqlTable;
+pub use parser::parse_sql;
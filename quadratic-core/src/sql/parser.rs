@@ -0,0 +1,734 @@
+//! Recursive descent parser for SQL queries.
+
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+
+use super::ast::*;
+use super::lexer::{Token, tokenize};
+use crate::{CellValue, CodeResult, RunError, RunErrorMsg, Span, Spanned};
+
+/// Keywords that can't be used as unquoted identifiers or aliases
+const RESERVED: &[&str] = &[
+    "SELECT", "DISTINCT", "ALL", "FROM", "WHERE", "GROUP", "BY", "HAVING", "ORDER", "ASC", "DESC",
+    "LIMIT", "OFFSET", "JOIN", "INNER", "LEFT", "OUTER", "CROSS", "ON", "AS", "AND", "OR", "NOT",
+    "IS", "NULL", "IN", "LIKE", "BETWEEN", "CASE", "WHEN", "THEN", "ELSE", "END", "TRUE", "FALSE",
+    "UNION",
+];
+
+/// Parses a `SELECT` statement.
+pub fn parse_sql(sql: &str) -> CodeResult<Query> {
+    let mut parser = Parser {
+        sql,
+        tokens: tokenize(sql)?,
+        index: 0,
+    };
+
+    let query = parser.query()?;
+    while parser.eat_symbol(";") {}
+
+    match parser.peek() {
+        Some(token) => Err(RunErrorMsg::Unexpected(token.inner.describe().into()).with_span(token)),
+        None => Ok(query),
+    }
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Spanned<Token>>,
+    index: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Spanned<Token>> {
+        self.tokens.get(self.index)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.index + n).map(|token| &token.inner)
+    }
+
+    fn next(&mut self) -> Option<Spanned<Token>> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    /// Span of the previous token
+    fn prev_span(&self) -> Span {
+        self.tokens[self.index.saturating_sub(1)].span
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.peek()
+            .is_some_and(|token| token.inner.is_keyword(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let is_keyword = self.is_keyword(keyword);
+        if is_keyword {
+            self.index += 1;
+        }
+        is_keyword
+    }
+
+    fn expect_keyword(&mut self, keyword: &'static str) -> CodeResult<Span> {
+        match self.eat_keyword(keyword) {
+            true => Ok(self.prev_span()),
+            false => Err(self.expected(keyword)),
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &'static str) -> bool {
+        let is_symbol = matches!(self.peek(), Some(token) if token.inner == Token::Symbol(symbol));
+        if is_symbol {
+            self.index += 1;
+        }
+        is_symbol
+    }
+
+    fn expect_symbol(&mut self, symbol: &'static str) -> CodeResult<Span> {
+        match self.eat_symbol(symbol) {
+            true => Ok(self.prev_span()),
+            false => Err(self.expected(symbol)),
+        }
+    }
+
+    /// Error for an unexpected token or the end of the query
+    fn expected(&self, expected: &'static str) -> RunError {
+        match self.peek() {
+            Some(token) => RunErrorMsg::Expected {
+                expected: expected.into(),
+                got: Some(token.inner.describe().into()),
+            }
+            .with_span(token),
+            None => RunErrorMsg::Expected {
+                expected: expected.into(),
+                got: Some("end of query".into()),
+            }
+            .with_span(Span::empty(self.sql.len() as u32)),
+        }
+    }
+
+    /// Whether the next token is an identifier
+    fn is_ident(&self) -> bool {
+        match self.peek_nth(0) {
+            Some(Token::Word(word)) => !RESERVED.contains(&word.to_ascii_uppercase().as_str()),
+            Some(Token::QuotedIdent(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn ident(&mut self) -> CodeResult<Spanned<String>> {
+        if !self.is_ident() {
+            return Err(self.expected("identifier"));
+        }
+        match self.next() {
+            Some(Spanned {
+                span,
+                inner: Token::Word(s) | Token::QuotedIdent(s),
+            }) => Ok(Spanned { span, inner: s }),
+            _ => Err(self.expected("identifier")),
+        }
+    }
+
+    fn query(&mut self) -> CodeResult<Query> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.eat_keyword("DISTINCT");
+        if !distinct {
+            self.eat_keyword("ALL");
+        }
+
+        let mut items = vec![self.select_item()?];
+        while self.eat_symbol(",") {
+            items.push(self.select_item()?);
+        }
+
+        let mut from = vec![];
+        let mut joins = vec![];
+        if self.eat_keyword("FROM") {
+            from.push(self.table_source()?);
+            while self.eat_symbol(",") {
+                from.push(self.table_source()?);
+            }
+
+            loop {
+                let kind = if self.eat_keyword("CROSS") {
+                    JoinKind::Cross
+                } else if self.eat_keyword("LEFT") {
+                    self.eat_keyword("OUTER");
+                    JoinKind::Left
+                } else if self.eat_keyword("INNER") || self.is_keyword("JOIN") {
+                    JoinKind::Inner
+                } else {
+                    break;
+                };
+                self.expect_keyword("JOIN")?;
+
+                let table = self.table_source()?;
+                let on = match kind {
+                    JoinKind::Cross => None,
+                    JoinKind::Inner | JoinKind::Left => {
+                        self.expect_keyword("ON")?;
+                        Some(self.expr()?)
+                    }
+                };
+                joins.push(Join { kind, table, on });
+            }
+        }
+
+        let filter = match self.eat_keyword("WHERE") {
+            true => Some(self.expr()?),
+            false => None,
+        };
+
+        let mut group_by = vec![];
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by = self.expr_list()?;
+        }
+
+        let having = match self.eat_keyword("HAVING") {
+            true => Some(self.expr()?),
+            false => None,
+        };
+
+        let mut order_by = vec![];
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expr = self.expr()?;
+                let descending = self.eat_keyword("DESC");
+                if !descending {
+                    self.eat_keyword("ASC");
+                }
+                order_by.push(OrderBy { expr, descending });
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let limit = match self.eat_keyword("LIMIT") {
+            true => Some(self.count()?),
+            false => None,
+        };
+        let offset = match self.eat_keyword("OFFSET") {
+            true => self.count()?,
+            false => 0,
+        };
+
+        Ok(Query {
+            distinct,
+            items,
+            from,
+            joins,
+            filter,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn select_item(&mut self) -> CodeResult<SelectItem> {
+        if self.eat_symbol("*") {
+            return Ok(SelectItem::Wildcard(None));
+        }
+
+        // `table.*`, where the table's name may contain dots
+        let start = self.index;
+        if self.is_ident() {
+            let table = self.dotted_name()?;
+            if self.eat_symbol(".") && self.eat_symbol("*") {
+                return Ok(SelectItem::Wildcard(Some(table.inner)));
+            }
+        }
+        self.index = start;
+
+        let expr = self.expr()?;
+        let alias = if self.eat_keyword("AS") || self.is_ident() {
+            Some(self.ident()?.inner)
+        } else {
+            None
+        };
+        let name = match (alias, &expr.inner) {
+            (Some(alias), _) => alias,
+            (None, Expr::Column { name, .. }) => name.to_owned(),
+            (None, _) => expr.span.of_str(self.sql).to_string(),
+        };
+
+        Ok(SelectItem::Expr { expr, name })
+    }
+
+    fn table_source(&mut self) -> CodeResult<TableSource> {
+        let name = self.dotted_name()?;
+        let alias = if self.eat_keyword("AS") || self.is_ident() {
+            Some(self.ident()?.inner)
+        } else {
+            None
+        };
+
+        Ok(TableSource { name, alias })
+    }
+
+    /// A table name, which may contain dots (eg, `Sales.2024`)
+    fn dotted_name(&mut self) -> CodeResult<Spanned<String>> {
+        let parts = self.name_parts()?;
+        Ok(join_parts(&parts))
+    }
+
+    /// Dot-separated identifiers, eg, `Sales.2024.total`
+    fn name_parts(&mut self) -> CodeResult<Vec<Spanned<String>>> {
+        let mut parts = vec![self.ident()?];
+
+        loop {
+            match (self.peek_nth(0), self.peek_nth(1)) {
+                // `.2024` is read as a number
+                (Some(Token::Number(n)), _) if n.starts_with('.') => {
+                    let span = self.tokens[self.index].span;
+                    parts.push(Spanned {
+                        span: Span {
+                            start: span.start + 1,
+                            end: span.end,
+                        },
+                        inner: n[1..].to_string(),
+                    });
+                    self.index += 1;
+                }
+                (Some(Token::Symbol(".")), Some(Token::Word(_) | Token::QuotedIdent(_))) => {
+                    self.index += 1;
+                    parts.push(self.ident()?);
+                }
+                _ => return Ok(parts),
+            }
+        }
+    }
+
+    /// A non-negative integer for `LIMIT` or `OFFSET`
+    fn count(&mut self) -> CodeResult<usize> {
+        match self.next() {
+            Some(Spanned {
+                span,
+                inner: Token::Number(n),
+            }) => n
+                .parse()
+                .map_err(|_| RunErrorMsg::BadNumber.with_span(span)),
+            _ => {
+                self.index -= 1;
+                Err(self.expected("number"))
+            }
+        }
+    }
+
+    fn expr_list(&mut self) -> CodeResult<Vec<Spanned<Expr>>> {
+        let mut list = vec![self.expr()?];
+        while self.eat_symbol(",") {
+            list.push(self.expr()?);
+        }
+        Ok(list)
+    }
+
+    fn expr(&mut self) -> CodeResult<Spanned<Expr>> {
+        self.or()
+    }
+
+    fn or(&mut self) -> CodeResult<Spanned<Expr>> {
+        let mut lhs = self.and()?;
+        while self.eat_keyword("OR") {
+            let rhs = self.and()?;
+            lhs = binary(BinaryOp::Or, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> CodeResult<Spanned<Expr>> {
+        let mut lhs = self.not()?;
+        while self.eat_keyword("AND") {
+            let rhs = self.not()?;
+            lhs = binary(BinaryOp::And, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> CodeResult<Spanned<Expr>> {
+        if self.eat_keyword("NOT") {
+            let start = self.prev_span();
+            let expr = self.not()?;
+            return Ok(Spanned {
+                span: Span::merge(start, &expr),
+                inner: Expr::Not(Box::new(expr)),
+            });
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> CodeResult<Spanned<Expr>> {
+        let lhs = self.additive()?;
+
+        let op = match self.peek_nth(0) {
+            Some(Token::Symbol("=" | "==")) => Some(BinaryOp::Eq),
+            Some(Token::Symbol("<>" | "!=")) => Some(BinaryOp::NotEq),
+            Some(Token::Symbol("<")) => Some(BinaryOp::Lt),
+            Some(Token::Symbol("<=")) => Some(BinaryOp::Lte),
+            Some(Token::Symbol(">")) => Some(BinaryOp::Gt),
+            Some(Token::Symbol(">=")) => Some(BinaryOp::Gte),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.index += 1;
+            let rhs = self.additive()?;
+            return Ok(binary(op, lhs, rhs));
+        }
+
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            let end = self.expect_keyword("NULL")?;
+            return Ok(Spanned {
+                span: Span::merge(&lhs, end),
+                inner: Expr::IsNull {
+                    expr: Box::new(lhs),
+                    negated,
+                },
+            });
+        }
+
+        let negated = self.is_keyword("NOT")
+            && matches!(self.peek_nth(1), Some(token) if ["IN", "LIKE", "BETWEEN"].iter().any(|k| token.is_keyword(k)));
+        if negated {
+            self.index += 1;
+        }
+
+        if self.eat_keyword("IN") {
+            self.expect_symbol("(")?;
+            let list = self.expr_list()?;
+            let end = self.expect_symbol(")")?;
+            Ok(Spanned {
+                span: Span::merge(&lhs, end),
+                inner: Expr::InList {
+                    expr: Box::new(lhs),
+                    list,
+                    negated,
+                },
+            })
+        } else if self.eat_keyword("LIKE") {
+            let pattern = self.additive()?;
+            Ok(Spanned {
+                span: Span::merge(&lhs, &pattern),
+                inner: Expr::Like {
+                    expr: Box::new(lhs),
+                    pattern: Box::new(pattern),
+                    negated,
+                },
+            })
+        } else if self.eat_keyword("BETWEEN") {
+            let low = self.additive()?;
+            self.expect_keyword("AND")?;
+            let high = self.additive()?;
+            Ok(Spanned {
+                span: Span::merge(&lhs, &high),
+                inner: Expr::Between {
+                    expr: Box::new(lhs),
+                    low: Box::new(low),
+                    high: Box::new(high),
+                    negated,
+                },
+            })
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn additive(&mut self) -> CodeResult<Spanned<Expr>> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            let op = match self.peek_nth(0) {
+                Some(Token::Symbol("+")) => BinaryOp::Add,
+                Some(Token::Symbol("-")) => BinaryOp::Sub,
+                Some(Token::Symbol("||")) => BinaryOp::Concat,
+                _ => return Ok(lhs),
+            };
+            self.index += 1;
+            let rhs = self.multiplicative()?;
+            lhs = binary(op, lhs, rhs);
+        }
+    }
+
+    fn multiplicative(&mut self) -> CodeResult<Spanned<Expr>> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek_nth(0) {
+                Some(Token::Symbol("*")) => BinaryOp::Mul,
+                Some(Token::Symbol("/")) => BinaryOp::Div,
+                Some(Token::Symbol("%")) => BinaryOp::Mod,
+                _ => return Ok(lhs),
+            };
+            self.index += 1;
+            let rhs = self.unary()?;
+            lhs = binary(op, lhs, rhs);
+        }
+    }
+
+    fn unary(&mut self) -> CodeResult<Spanned<Expr>> {
+        if self.eat_symbol("-") {
+            let start = self.prev_span();
+            let expr = self.unary()?;
+            return Ok(Spanned {
+                span: Span::merge(start, &expr),
+                inner: Expr::Neg(Box::new(expr)),
+            });
+        }
+        if self.eat_symbol("+") {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> CodeResult<Spanned<Expr>> {
+        if self.eat_symbol("(") {
+            let start = self.prev_span();
+            let expr = self.expr()?;
+            let end = self.expect_symbol(")")?;
+            return Ok(Spanned {
+                span: Span::merge(start, end),
+                inner: expr.inner,
+            });
+        }
+
+        if self.is_keyword("CASE") {
+            return self.case();
+        }
+
+        let literal = match self.peek_nth(0) {
+            Some(Token::Number(n)) => Some(
+                BigDecimal::from_str(n)
+                    .map(CellValue::Number)
+                    .map_err(|_| RunErrorMsg::BadNumber.with_span(&self.tokens[self.index]))?,
+            ),
+            Some(Token::String(s)) => Some(CellValue::Text(s.to_owned())),
+            Some(token) if token.is_keyword("TRUE") => Some(CellValue::Logical(true)),
+            Some(token) if token.is_keyword("FALSE") => Some(CellValue::Logical(false)),
+            Some(token) if token.is_keyword("NULL") => Some(CellValue::Blank),
+            _ => None,
+        };
+        if let Some(literal) = literal {
+            self.index += 1;
+            return Ok(Spanned {
+                span: self.prev_span(),
+                inner: Expr::Literal(literal),
+            });
+        }
+
+        if matches!(self.peek_nth(0), Some(Token::Word(_)))
+            && self.peek_nth(1) == Some(&Token::Symbol("("))
+        {
+            return self.function();
+        }
+
+        // column, optionally qualified by its table
+        let mut parts = self.name_parts()?;
+        let span = Span::merge(&parts[0], &parts[parts.len() - 1]);
+        let name = parts.pop().map(|part| part.inner).unwrap_or_default();
+        let table = (!parts.is_empty()).then(|| join_parts(&parts).inner);
+
+        Ok(Spanned {
+            span,
+            inner: Expr::Column { table, name },
+        })
+    }
+
+    fn function(&mut self) -> CodeResult<Spanned<Expr>> {
+        let Some(Spanned {
+            span: start,
+            inner: Token::Word(name),
+        }) = self.next()
+        else {
+            return Err(self.expected("function"));
+        };
+        let name = name.to_ascii_uppercase();
+        self.expect_symbol("(")?;
+
+        if name == "COUNT" && self.eat_symbol("*") {
+            let end = self.expect_symbol(")")?;
+            return Ok(Spanned {
+                span: Span::merge(start, end),
+                inner: Expr::CountAll,
+            });
+        }
+
+        let distinct = self.eat_keyword("DISTINCT");
+        let args = match self.eat_symbol(")") {
+            true => vec![],
+            false => {
+                let args = self.expr_list()?;
+                self.expect_symbol(")")?;
+                args
+            }
+        };
+
+        Ok(Spanned {
+            span: Span::merge(start, self.prev_span()),
+            inner: Expr::Function {
+                name,
+                args,
+                distinct,
+            },
+        })
+    }
+
+    fn case(&mut self) -> CodeResult<Spanned<Expr>> {
+        let start = self.expect_keyword("CASE")?;
+        let operand = match self.is_keyword("WHEN") {
+            true => None,
+            false => Some(Box::new(self.expr()?)),
+        };
+
+        let mut branches = vec![];
+        while self.eat_keyword("WHEN") {
+            let when = self.expr()?;
+            self.expect_keyword("THEN")?;
+            branches.push((when, self.expr()?));
+        }
+        if branches.is_empty() {
+            return Err(self.expected("WHEN"));
+        }
+
+        let otherwise = match self.eat_keyword("ELSE") {
+            true => Some(Box::new(self.expr()?)),
+            false => None,
+        };
+        let end = self.expect_keyword("END")?;
+
+        Ok(Spanned {
+            span: Span::merge(start, end),
+            inner: Expr::Case {
+                operand,
+                branches,
+                otherwise,
+            },
+        })
+    }
+}
+
+fn join_parts(parts: &[Spanned<String>]) -> Spanned<String> {
+    Spanned {
+        span: Span::merge(&parts[0], &parts[parts.len() - 1]),
+        inner: parts
+            .iter()
+            .map(|part| part.inner.as_str())
+            .collect::<Vec<_>>()
+            .join("."),
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Spanned<Expr>, rhs: Spanned<Expr>) -> Spanned<Expr> {
+    Spanned {
+        span: Span::merge(&lhs, &rhs),
+        inner: Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clauses() {
+        let query = parse_sql(
+            "SELECT DISTINCT o.region AS r, COUNT(*) FROM Orders o \
+             LEFT JOIN \"Sales.2024\" s ON o.id = s.id \
+             WHERE o.total > 10 GROUP BY 1 HAVING COUNT(*) > 1 \
+             ORDER BY r DESC LIMIT 5 OFFSET 2;",
+        )
+        .unwrap();
+
+        assert!(query.distinct);
+        assert_eq!(query.items.len(), 2);
+        assert!(matches!(&query.items[0], SelectItem::Expr { name, .. } if name == "r"));
+        assert!(matches!(&query.items[1], SelectItem::Expr { name, .. } if name == "COUNT(*)"));
+        assert_eq!(
+            query
+                .tables()
+                .map(|table| table.name.inner.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Orders", "Sales.2024"]
+        );
+        assert_eq!(query.from[0].reference(), "o");
+        assert_eq!(query.joins[0].kind, JoinKind::Left);
+        assert!(query.filter.is_some());
+        assert_eq!(query.group_by.len(), 1);
+        assert!(query.having.is_some());
+        assert!(query.order_by[0].descending);
+        assert_eq!(query.limit, Some(5));
+        assert_eq!(query.offset, 2);
+    }
+
+    #[test]
+    fn test_parse_dotted_table_names() {
+        let query = parse_sql("SELECT Sales.2024.*, Sales.2024.total FROM Sales.2024").unwrap();
+
+        assert_eq!(query.from[0].name.inner, "Sales.2024");
+        assert_eq!(
+            query.items[0],
+            SelectItem::Wildcard(Some("Sales.2024".into()))
+        );
+        assert!(matches!(
+            &query.items[1],
+            SelectItem::Expr { expr: Spanned { inner: Expr::Column { table: Some(table), name }, .. }, .. }
+                if table == "Sales.2024" && name == "total"
+        ));
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let query = parse_sql("SELECT 1 + 2 * 3 = 7 AND NOT a IS NULL OR b").unwrap();
+        let SelectItem::Expr { expr, .. } = &query.items[0] else {
+            panic!("expected an expression");
+        };
+
+        let Expr::Binary { op, lhs, .. } = &expr.inner else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(*op, BinaryOp::Or);
+        assert!(matches!(
+            lhs.inner,
+            Expr::Binary {
+                op: BinaryOp::And,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_sql("SELECT a FROM").unwrap_err();
+        assert_eq!(
+            error.msg,
+            RunErrorMsg::Expected {
+                expected: "identifier".into(),
+                got: Some("end of query".into()),
+            }
+        );
+        assert_eq!(error.span, Some(Span::empty(13)));
+
+        let error = parse_sql("SELECT a FROM t WHERE").unwrap_err();
+        assert!(matches!(error.msg, RunErrorMsg::Expected { .. }));
+
+        let error = parse_sql("SELECT a FROM t t2 t3").unwrap_err();
+        assert_eq!(error.msg, RunErrorMsg::Unexpected("`t3`".into()));
+
+        let error = parse_sql("DELETE FROM t").unwrap_err();
+        assert_eq!(
+            error.msg,
+            RunErrorMsg::Expected {
+                expected: "SELECT".into(),
+                got: Some("`DELETE`".into()),
+            }
+        );
+    }
+}
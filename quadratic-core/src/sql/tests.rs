@@ -0,0 +1,202 @@
+use super::*;
+use crate::{CellValue, CodeResult, RunErrorMsg};
+
+fn orders() -> SqlTable {
+    SqlTable {
+        columns: vec!["id".into(), "customer".into(), "total".into()],
+        rows: vec![
+            vec![1.into(), "ada".into(), 30.into()],
+            vec![2.into(), "bob".into(), 5.into()],
+            vec![3.into(), "ada".into(), 12.into()],
+            vec![4.into(), "cy".into(), CellValue::Blank],
+        ],
+    }
+}
+
+fn customers() -> SqlTable {
+    SqlTable {
+        columns: vec!["Name".into(), "Region".into()],
+        rows: vec![
+            vec!["Ada".into(), "north".into()],
+            vec!["Bob".into(), "south".into()],
+            vec!["Dee".into(), "south".into()],
+        ],
+    }
+}
+
+fn query(sql: &str) -> CodeResult<SqlTable> {
+    parse_sql(sql)?.eval(|name| match name.to_ascii_lowercase().as_str() {
+        "orders" => Ok(orders()),
+        "customers" => Ok(customers()),
+        _ => Err(RunErrorMsg::CodeRunError(format!("Table `{name}` not found").into()).into()),
+    })
+}
+
+/// Results as display strings, with the column names first
+fn results(sql: &str) -> Vec<Vec<String>> {
+    let table = query(sql).unwrap();
+    std::iter::once(table.columns)
+        .chain(
+            table
+                .rows
+                .iter()
+                .map(|row| row.iter().map(CellValue::to_display).collect()),
+        )
+        .collect()
+}
+
+#[test]
+fn test_select_where_order_limit() {
+    assert_eq!(
+        results(
+            "SELECT customer, total * 2 AS doubled FROM orders WHERE total > 6 ORDER BY doubled"
+        ),
+        vec![
+            vec!["customer", "doubled"],
+            vec!["ada", "24"],
+            vec!["ada", "60"],
+        ]
+    );
+    assert_eq!(
+        results("SELECT id FROM orders ORDER BY total DESC LIMIT 2 OFFSET 1"),
+        vec![vec!["id"], vec!["3"], vec!["2"]]
+    );
+    // NULLs sort first
+    assert_eq!(
+        results("SELECT id FROM orders ORDER BY total LIMIT 1"),
+        vec![vec!["id"], vec!["4"]]
+    );
+}
+
+#[test]
+fn test_select_null_comparisons() {
+    assert_eq!(
+        results("SELECT id FROM orders WHERE total IS NULL OR total < 10"),
+        vec![vec!["id"], vec!["2"], vec!["4"]]
+    );
+    assert_eq!(
+        results("SELECT id FROM orders WHERE NOT total > 10"),
+        vec![vec!["id"], vec!["2"]]
+    );
+    assert_eq!(
+        results("SELECT COALESCE(total, 0) + 1 AS t FROM orders WHERE id = 4"),
+        vec![vec!["t"], vec!["1"]]
+    );
+}
+
+#[test]
+fn test_select_join() {
+    assert_eq!(
+        results(
+            "SELECT o.id, c.Region FROM orders o \
+             JOIN customers c ON o.customer = c.name ORDER BY 1"
+        ),
+        vec![
+            vec!["id", "Region"],
+            vec!["1", "north"],
+            vec!["2", "south"],
+            vec!["3", "north"],
+        ]
+    );
+    assert_eq!(
+        results(
+            "SELECT c.*, COUNT(o.id) AS orders FROM customers c \
+             LEFT JOIN orders o ON o.customer = c.Name GROUP BY c.Name ORDER BY c.Name"
+        ),
+        vec![
+            vec!["Name", "Region", "orders"],
+            vec!["Ada", "north", "2"],
+            vec!["Bob", "south", "1"],
+            vec!["Dee", "south", "0"],
+        ]
+    );
+    assert_eq!(
+        query("SELECT * FROM orders, customers").unwrap().rows.len(),
+        12
+    );
+}
+
+#[test]
+fn test_select_aggregates() {
+    assert_eq!(
+        results(
+            "SELECT customer, COUNT(*), SUM(total), AVG(total), MAX(total) FROM orders \
+             GROUP BY customer HAVING COUNT(*) > 0 ORDER BY 2 DESC, customer"
+        ),
+        vec![
+            vec![
+                "customer",
+                "COUNT(*)",
+                "SUM(total)",
+                "AVG(total)",
+                "MAX(total)"
+            ],
+            vec!["ada", "2", "42", "21", "30"],
+            vec!["bob", "1", "5", "5", "5"],
+            vec!["cy", "1", "", "", ""],
+        ]
+    );
+    assert_eq!(
+        results("SELECT COUNT(DISTINCT customer) AS n, MIN(total) FROM orders"),
+        vec![vec!["n", "MIN(total)"], vec!["3", "5"]]
+    );
+    // aggregates without rows still return a row
+    assert_eq!(
+        results("SELECT COUNT(*) FROM orders WHERE total > 100"),
+        vec![vec!["COUNT(*)"], vec!["0"]]
+    );
+}
+
+#[test]
+fn test_select_distinct_and_expressions() {
+    assert_eq!(
+        results("SELECT DISTINCT UPPER(customer) AS c FROM orders ORDER BY c DESC"),
+        vec![vec!["c"], vec!["CY"], vec!["BOB"], vec!["ADA"]]
+    );
+    assert_eq!(
+        results(
+            "SELECT id, CASE WHEN total >= 12 THEN 'big' ELSE 'small' END AS size, \
+             customer || '-' || id AS code FROM orders \
+             WHERE customer LIKE 'A%' AND id BETWEEN 1 AND 3 AND id NOT IN (2, 4)"
+        ),
+        vec![
+            vec!["id", "size", "code"],
+            vec!["1", "big", "ada-1"],
+            vec!["3", "big", "ada-3"],
+        ]
+    );
+    assert_eq!(
+        results("SELECT 7 % 4, ROUND(2.345, 2), LENGTH('abc'), 1 = 1"),
+        vec![
+            vec!["7 % 4", "ROUND(2.345, 2)", "LENGTH('abc')", "1 = 1"],
+            vec!["3", "2.35", "3", "true"],
+        ]
+    );
+}
+
+#[test]
+fn test_select_errors() {
+    let error = query("SELECT nope FROM orders").unwrap_err();
+    assert_eq!(
+        error.msg,
+        RunErrorMsg::CodeRunError("Column `nope` doesn't exist".into())
+    );
+    assert_eq!(error.span.map(|span| (span.start, span.end)), Some((7, 11)));
+
+    let error = query("SELECT * FROM missing").unwrap_err();
+    assert_eq!(
+        error.span.map(|span| (span.start, span.end)),
+        Some((14, 21))
+    );
+
+    assert!(query("SELECT id FROM orders, orders").is_err());
+    assert!(query("SELECT id FROM orders WHERE COUNT(*) > 1").is_err());
+    assert_eq!(
+        query("SELECT 1 / 0").unwrap_err().msg,
+        RunErrorMsg::DivideByZero
+    );
+    assert_eq!(
+        query("SELECT NOPE(1)").unwrap_err().msg,
+        RunErrorMsg::BadFunctionName
+    );
+}
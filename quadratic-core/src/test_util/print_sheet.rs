@@ -80,6 +80,7 @@ pub fn print_table_sheet(sheet: &Sheet, rect: Rect, display_cell_values: bool) {
                         CodeCellLanguage::Python => code_cell.code.to_string(),
                         CodeCellLanguage::Connection { .. } => code_cell.code.to_string(),
                         CodeCellLanguage::Javascript => code_cell.code.to_string(),
+                        CodeCellLanguage::Sql => code_cell.code.to_string(),
                         CodeCellLanguage::Import => "import".to_string(),
                     };
                     let value = sheet
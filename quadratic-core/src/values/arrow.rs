@@ -1,18 +1,22 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use arrow_array::{
-    Array, ArrayRef,
+    Array, ArrayRef, BooleanArray, Date32Array, Decimal128Array, DurationNanosecondArray,
+    Float64Array, RecordBatch, StringArray, Time64MicrosecondArray, TimestampMicrosecondArray,
     cast::AsArray,
-    types::{Date32Type, Date64Type},
+    types::{
+        Date32Type, Date64Type, Decimal128Type, DurationMicrosecondType, DurationMillisecondType,
+        DurationNanosecondType, DurationSecondType,
+    },
 };
 use arrow_buffer::ArrowNativeType;
 use arrow_data::ArrayData;
-use arrow_schema::{DataType, TimeUnit};
-use bigdecimal::BigDecimal;
-use chrono::{NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use arrow_schema::{DECIMAL128_MAX_PRECISION, DataType, Field, Schema, TimeUnit};
+use bigdecimal::{BigDecimal, num_bigint::BigInt, num_traits::ToPrimitive};
+use chrono::{NaiveDate, NaiveTime, TimeDelta, TimeZone, Timelike, Utc};
 
-use crate::{CellValue, cell_values::CellValues};
+use crate::{CellValue, Duration, cell_values::CellValues, grid::column_type::DataTableColumnType};
 
 use super::time::map_local_result;
 
@@ -54,6 +58,8 @@ pub fn arrow_col_to_cell_value_vec(array: &ArrayRef) -> Result<Vec<CellValue>> {
         DataType::Time32(unit) => arrow_time_unit_to_cell_values::<i32>(array_data, unit),
         DataType::Time64(unit) => arrow_time_unit_to_cell_values::<i64>(array_data, unit),
         DataType::Timestamp(unit, extra) => arrow_timestamp_to_cell_value(array_data, unit, extra),
+        DataType::Decimal128(_, scale) => Ok(arrow_decimal_to_cell_values(array, *scale)),
+        DataType::Duration(unit) => Ok(arrow_duration_to_cell_values(array, unit)),
         // unsupported data type
         _ => {
             dbgjs!(format!(
@@ -63,6 +69,20 @@ pub fn arrow_col_to_cell_value_vec(array: &ArrayRef) -> Result<Vec<CellValue>> {
             Ok(vec![])
         }
     }
+    .map(|values| nulls_to_blanks(array, values))
+}
+
+/// Replaces null entries with blanks.
+fn nulls_to_blanks(array: &ArrayRef, mut values: Vec<CellValue>) -> Vec<CellValue> {
+    if array.null_count() > 0 && values.len() == array.len() {
+        for (index, value) in values.iter_mut().enumerate() {
+            if array.is_null(index) {
+                *value = CellValue::Blank;
+            }
+        }
+    }
+
+    values
 }
 
 impl TryFrom<&ArrayRef> for CellValues {
@@ -247,3 +267,392 @@ fn arrow_timestamp_to_cell_value(
 
     Ok(values)
 }
+
+fn arrow_decimal_to_cell_values(col: &ArrayRef, scale: i8) -> Vec<CellValue> {
+    let array = col.as_primitive::<Decimal128Type>();
+
+    (0..col.len())
+        .map(|index| {
+            let value = BigDecimal::new(BigInt::from(array.value(index)), scale as i64);
+            CellValue::Number(value)
+        })
+        .collect()
+}
+
+fn arrow_duration_to_cell_values(col: &ArrayRef, unit: &TimeUnit) -> Vec<CellValue> {
+    (0..col.len())
+        .map(|index| {
+            let seconds = match unit {
+                TimeUnit::Second => col.as_primitive::<DurationSecondType>().value(index) as f64,
+                TimeUnit::Millisecond => {
+                    col.as_primitive::<DurationMillisecondType>().value(index) as f64 / 1e3
+                }
+                TimeUnit::Microsecond => {
+                    col.as_primitive::<DurationMicrosecondType>().value(index) as f64 / 1e6
+                }
+                TimeUnit::Nanosecond => {
+                    col.as_primitive::<DurationNanosecondType>().value(index) as f64 / 1e9
+                }
+            };
+            let duration = Duration::from_seconds(seconds);
+            CellValue::Duration(duration)
+        })
+        .collect()
+}
+
+/// The Arrow type used to export a column of cell values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArrowExportType {
+    Decimal(u8, i8),
+    Float,
+    Boolean,
+    Date,
+    Timestamp,
+    Time,
+    Duration,
+    Text,
+}
+
+impl ArrowExportType {
+    /// Chooses an Arrow type for a column of values. Blanks are exported as
+    /// nulls and don't affect the type. Mixed columns are exported as text.
+    ///
+    /// `allow_duration` is false for formats that don't support Arrow's
+    /// duration type (eg, Parquet), in which case durations are exported as
+    /// text.
+    fn infer(values: &[CellValue], allow_duration: bool) -> Self {
+        let mut export_type = None;
+
+        for value in values {
+            let value_type = match value {
+                CellValue::Blank => continue,
+                CellValue::Number(_) => ArrowExportType::Decimal(0, 0),
+                CellValue::Logical(_) => ArrowExportType::Boolean,
+                CellValue::Date(_) => ArrowExportType::Date,
+                CellValue::DateTime(_) => ArrowExportType::Timestamp,
+                CellValue::Time(_) => ArrowExportType::Time,
+                CellValue::Duration(duration) if allow_duration && duration.months == 0 => {
+                    ArrowExportType::Duration
+                }
+                _ => return ArrowExportType::Text,
+            };
+
+            export_type = match (export_type, value_type) {
+                (None, value_type) => Some(value_type),
+                (Some(current), value_type) if current == value_type => Some(current),
+
+                // dates are promoted to timestamps when mixed with date times
+                (Some(ArrowExportType::Date), ArrowExportType::Timestamp)
+                | (Some(ArrowExportType::Timestamp), ArrowExportType::Date) => {
+                    Some(ArrowExportType::Timestamp)
+                }
+                _ => return ArrowExportType::Text,
+            };
+        }
+
+        match export_type {
+            Some(ArrowExportType::Decimal(_, _)) => Self::infer_decimal(values),
+            Some(export_type) => export_type,
+            None => ArrowExportType::Text,
+        }
+    }
+
+    /// Maps a data table's declared column type to an Arrow type. Numbers
+    /// still use the smallest decimal that fits the values.
+    fn from_column_type(column_type: DataTableColumnType, values: &[CellValue]) -> Self {
+        match column_type {
+            DataTableColumnType::Text => ArrowExportType::Text,
+            DataTableColumnType::Number | DataTableColumnType::Currency => {
+                Self::infer_decimal(values)
+            }
+            DataTableColumnType::Boolean => ArrowExportType::Boolean,
+            DataTableColumnType::Date => ArrowExportType::Date,
+            DataTableColumnType::DateTime => ArrowExportType::Timestamp,
+        }
+    }
+
+    /// Finds the smallest decimal precision and scale that fits all numbers,
+    /// falling back to a float if the numbers don't fit in a Decimal128.
+    fn infer_decimal(values: &[CellValue]) -> Self {
+        let mut integer_digits = 1;
+        let mut scale = 0;
+
+        for value in values {
+            if let CellValue::Number(n) = value {
+                let (digits, exponent) = n.normalized().as_bigint_and_exponent();
+                let digit_count = digits.magnitude().to_string().len() as i64;
+                scale = scale.max(exponent.max(0));
+                integer_digits = integer_digits.max(digit_count - exponent);
+            }
+        }
+
+        let precision = integer_digits + scale;
+        if precision > DECIMAL128_MAX_PRECISION as i64 {
+            return ArrowExportType::Float;
+        }
+
+        ArrowExportType::Decimal(precision as u8, scale as i8)
+    }
+}
+
+/// Converts a column of cell values to an Arrow array.
+///
+/// Columns with a declared type are exported as that type, and values that
+/// cannot be coerced to it are exported as nulls. Otherwise, see
+/// [`ArrowExportType::infer`] for how the Arrow type is chosen.
+pub fn cell_values_to_arrow_col(
+    values: &[CellValue],
+    column_type: Option<DataTableColumnType>,
+    allow_duration: bool,
+) -> Result<ArrayRef> {
+    let coerced;
+    let (values, export_type) = match column_type {
+        Some(column_type) => {
+            coerced = values
+                .iter()
+                .map(|value| column_type.coerce(value.to_owned()))
+                .map(|value| value.unwrap_or(CellValue::Blank))
+                .collect::<Vec<_>>();
+            let export_type = ArrowExportType::from_column_type(column_type, &coerced);
+            (coerced.as_slice(), export_type)
+        }
+        None => (values, ArrowExportType::infer(values, allow_duration)),
+    };
+
+    let array: ArrayRef = match export_type {
+        ArrowExportType::Decimal(precision, scale) => {
+            let values = values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Number(n) => n
+                        .with_scale(scale as i64)
+                        .as_bigint_and_exponent()
+                        .0
+                        .to_i128(),
+                    _ => None,
+                })
+                .collect::<Decimal128Array>()
+                .with_precision_and_scale(precision, scale)?;
+            Arc::new(values)
+        }
+        ArrowExportType::Float => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Number(n) => n.to_f64(),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        ArrowExportType::Boolean => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Logical(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+        ArrowExportType::Date => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Date(d) => Some(Date32Type::from_naive_date(*d)),
+                    _ => None,
+                })
+                .collect::<Date32Array>(),
+        ),
+        ArrowExportType::Timestamp => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::DateTime(dt) => Some(dt.and_utc().timestamp_micros()),
+                    CellValue::Date(d) => {
+                        Some(d.and_time(NaiveTime::MIN).and_utc().timestamp_micros())
+                    }
+                    _ => None,
+                })
+                .collect::<TimestampMicrosecondArray>(),
+        ),
+        ArrowExportType::Time => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Time(t) => Some(
+                        t.num_seconds_from_midnight() as i64 * 1_000_000
+                            + t.nanosecond() as i64 / 1_000,
+                    ),
+                    _ => None,
+                })
+                .collect::<Time64MicrosecondArray>(),
+        ),
+        ArrowExportType::Duration => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Duration(d) => Some((d.seconds * 1e9).round() as i64),
+                    _ => None,
+                })
+                .collect::<DurationNanosecondArray>(),
+        ),
+        ArrowExportType::Text => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Blank => None,
+                    value => Some(value.to_string()),
+                })
+                .collect::<StringArray>(),
+        ),
+    };
+
+    Ok(array)
+}
+
+/// Converts columns of cell values to an Arrow record batch.
+///
+/// `column_types` holds the declared type of each column, if any.
+pub fn cell_values_to_record_batch(
+    headers: Vec<String>,
+    columns: Vec<Vec<CellValue>>,
+    column_types: &[Option<DataTableColumnType>],
+    allow_duration: bool,
+) -> Result<RecordBatch> {
+    if headers.len() != columns.len() {
+        return Err(anyhow!(
+            "Expected {} columns, found {}",
+            headers.len(),
+            columns.len()
+        ));
+    }
+
+    let arrays = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let column_type = column_types.get(index).copied().flatten();
+            cell_values_to_arrow_col(column, column_type, allow_duration)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let fields = headers
+        .into_iter()
+        .zip(arrays.iter())
+        .map(|(header, array)| Field::new(header, array.data_type().to_owned(), true))
+        .collect::<Vec<_>>();
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+/// Writes columns of cell values to an Arrow IPC file.
+pub fn cell_values_to_arrow_ipc(
+    headers: Vec<String>,
+    columns: Vec<Vec<CellValue>>,
+    column_types: &[Option<DataTableColumnType>],
+) -> Result<Vec<u8>> {
+    let batch = cell_values_to_record_batch(headers, columns, column_types, true)?;
+
+    let mut buffer = vec![];
+    let mut writer = arrow_ipc::writer::FileWriter::try_new(&mut buffer, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    drop(writer);
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use arrow_ipc::reader::FileReader;
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    #[test]
+    fn test_cell_values_to_arrow_col_with_column_type() {
+        // a text column is exported as text, even if its values are numbers
+        let numbers = vec![CellValue::Number(1.into()), CellValue::Number(2.into())];
+        let array =
+            cell_values_to_arrow_col(&numbers, Some(DataTableColumnType::Text), true).unwrap();
+        assert_eq!(array.data_type(), &DataType::Utf8);
+
+        // values that don't match a number column are exported as nulls
+        let mixed = vec![CellValue::Text("1.5".into()), CellValue::Text("a".into())];
+        let array =
+            cell_values_to_arrow_col(&mixed, Some(DataTableColumnType::Number), true).unwrap();
+        assert_eq!(array.data_type(), &DataType::Decimal128(2, 1));
+        assert!(array.is_null(1));
+
+        let dates = vec![CellValue::Date(
+            NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+        )];
+        let array =
+            cell_values_to_arrow_col(&dates, Some(DataTableColumnType::DateTime), true).unwrap();
+        assert_eq!(
+            array.data_type(),
+            &DataType::Timestamp(TimeUnit::Microsecond, None)
+        );
+    }
+
+    #[test]
+    fn test_cell_values_to_arrow_col() {
+        let numbers = vec![
+            CellValue::Number(BigDecimal::from_str("1.5").unwrap()),
+            CellValue::Blank,
+            CellValue::Number(BigDecimal::from_str("-123.25").unwrap()),
+        ];
+        let array = cell_values_to_arrow_col(&numbers, None, true).unwrap();
+        assert_eq!(array.data_type(), &DataType::Decimal128(5, 2));
+        assert!(array.is_null(1));
+        assert_eq!(arrow_col_to_cell_value_vec(&array).unwrap(), numbers);
+
+        let mixed = vec![CellValue::Number(1.into()), CellValue::Text("a".into())];
+        let array = cell_values_to_arrow_col(&mixed, None, true).unwrap();
+        assert_eq!(array.data_type(), &DataType::Utf8);
+
+        let dates = vec![
+            CellValue::Date(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()),
+            CellValue::DateTime(
+                NaiveDateTime::parse_from_str("2024-01-02 03:04:05", "%Y-%m-%d %H:%M:%S").unwrap(),
+            ),
+        ];
+        let array = cell_values_to_arrow_col(&dates, None, true).unwrap();
+        assert_eq!(
+            array.data_type(),
+            &DataType::Timestamp(TimeUnit::Microsecond, None)
+        );
+
+        let durations = vec![CellValue::Duration(Duration::from_hours(1.5))];
+        let array = cell_values_to_arrow_col(&durations, None, true).unwrap();
+        assert_eq!(array.data_type(), &DataType::Duration(TimeUnit::Nanosecond));
+        assert_eq!(arrow_col_to_cell_value_vec(&array).unwrap(), durations);
+        let array = cell_values_to_arrow_col(&durations, None, false).unwrap();
+        assert_eq!(array.data_type(), &DataType::Utf8);
+    }
+
+    #[test]
+    fn test_cell_values_to_arrow_ipc() {
+        let headers = vec!["time".to_string(), "done".to_string()];
+        let columns = vec![
+            vec![
+                CellValue::Time(NaiveTime::from_hms_opt(1, 2, 3).unwrap()),
+                CellValue::Blank,
+            ],
+            vec![CellValue::Logical(true), CellValue::Logical(false)],
+        ];
+
+        let ipc = cell_values_to_arrow_ipc(headers, columns.clone(), &[]).unwrap();
+        let mut reader = FileReader::try_new(std::io::Cursor::new(ipc), None).unwrap();
+        let batch = reader.next().unwrap().unwrap();
+
+        assert_eq!(batch.schema().field(0).name(), "time");
+        assert_eq!(batch.schema().field(1).name(), "done");
+        for (index, column) in columns.iter().enumerate() {
+            assert_eq!(
+                &arrow_col_to_cell_value_vec(batch.column(index)).unwrap(),
+                column
+            );
+        }
+    }
+}
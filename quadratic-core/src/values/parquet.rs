@@ -1,8 +1,12 @@
 use anyhow::Result;
 use bytes::Bytes;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, arrow_reader::ParquetRecordBatchReaderBuilder};
 
-use crate::{CellValue, arrow::arrow_col_to_cell_value_vec};
+use crate::{
+    CellValue,
+    arrow::{arrow_col_to_cell_value_vec, cell_values_to_record_batch},
+    grid::column_type::DataTableColumnType,
+};
 
 pub fn parquet_to_vec(file: Vec<u8>) -> Result<Vec<Vec<CellValue>>> {
     if file.is_empty() {
@@ -43,6 +47,26 @@ pub fn parquet_to_vec(file: Vec<u8>) -> Result<Vec<Vec<CellValue>>> {
 
     Ok(output)
 }
+
+/// Writes columns of cell values to a Parquet file.
+///
+/// Parquet does not support Arrow's duration type, so durations are written
+/// as text.
+pub fn vec_to_parquet(
+    headers: Vec<String>,
+    columns: Vec<Vec<CellValue>>,
+    column_types: &[Option<DataTableColumnType>],
+) -> Result<Vec<u8>> {
+    let batch = cell_values_to_record_batch(headers, columns, column_types, false)?;
+
+    let mut buffer = vec![];
+    let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(buffer)
+}
+
 #[cfg(test)]
 mod test {
     use std::fs::File;
@@ -62,4 +86,27 @@ mod test {
         let _results = parquet_to_vec(buffer);
         // println!("{:?}", results);
     }
+
+    #[test]
+    fn test_vec_to_parquet() {
+        let headers = vec!["name".to_string(), "amount".to_string()];
+        let columns = vec![
+            vec![CellValue::Text("a".into()), CellValue::Blank],
+            vec![CellValue::Number(1.into()), CellValue::Number(2.into())],
+        ];
+
+        let parquet = vec_to_parquet(headers, columns, &[]).unwrap();
+        let results = parquet_to_vec(parquet).unwrap();
+        assert_eq!(
+            results,
+            vec![
+                vec![
+                    CellValue::Text("name".into()),
+                    CellValue::Text("amount".into())
+                ],
+                vec![CellValue::Text("a".into()), CellValue::Number(1.into())],
+                vec![CellValue::Blank, CellValue::Number(2.into())],
+            ]
+        );
+    }
 }
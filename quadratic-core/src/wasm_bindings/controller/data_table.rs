@@ -89,6 +89,29 @@ impl GridController {
 
         Ok(())
     }
+
+    /// Set (or clear) the formula of a calculated column
+    #[wasm_bindgen(js_name = "dataTableSetColumnFormula")]
+    pub fn js_data_table_set_column_formula(
+        &mut self,
+        sheet_id: String,
+        pos: String,
+        column_index: u32,
+        formula: Option<String>,
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let pos = serde_json::from_str::<Pos>(&pos).map_err(|e| e.to_string())?;
+        let sheet_id = SheetId::from_str(&sheet_id).map_err(|e| e.to_string())?;
+        self.data_table_set_column_formula(
+            pos.to_sheet_pos(sheet_id),
+            column_index,
+            formula,
+            cursor,
+        );
+
+        Ok(())
+    }
+
     /// Update a Data Table's name
     #[allow(clippy::too_many_arguments)]
     #[wasm_bindgen(js_name = "dataTableMeta")]
@@ -15,4 +15,26 @@ impl GridController {
             .map_err(|e| e.to_string())?;
         Ok(output)
     }
+
+    /// Returns a Parquet file as bytes
+    #[wasm_bindgen(js_name = "exportParquet")]
+    pub fn js_export_parquet(&self, selection: String) -> Result<Vec<u8>, JsValue> {
+        let mut selection = serde_json::from_str::<A1Selection>(&selection)
+            .map_err(|_| "Unable to parse A1Selection")?;
+        let output = self
+            .export_parquet(&mut selection)
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+
+    /// Returns an Arrow IPC file as bytes
+    #[wasm_bindgen(js_name = "exportArrowIpc")]
+    pub fn js_export_arrow_ipc(&self, selection: String) -> Result<Vec<u8>, JsValue> {
+        let mut selection = serde_json::from_str::<A1Selection>(&selection)
+            .map_err(|_| "Unable to parse A1Selection")?;
+        let output = self
+            .export_arrow_ipc(&mut selection)
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
 }
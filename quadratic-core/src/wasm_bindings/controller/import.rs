@@ -1,12 +1,52 @@
+use std::io::Read;
 use std::str::FromStr;
 
-use wasm_bindgen::prelude::wasm_bindgen;
+use js_sys::{Function, Uint8Array};
 use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
 
+use crate::Pos;
 use crate::controller::GridController;
+use crate::controller::operations::csv_import::CsvImportOptions;
 use crate::grid::js_types::JsResponse;
 use crate::grid::{Grid, SheetId};
-use crate::Pos;
+
+/// Reads a file from the client one chunk at a time. `read_chunk` is called
+/// without arguments and returns the next chunk as a `Uint8Array`; an empty
+/// chunk ends the file.
+struct JsChunkReader {
+    read_chunk: Function,
+    chunk: Vec<u8>,
+    offset: usize,
+}
+
+impl JsChunkReader {
+    fn new(read_chunk: Function) -> Self {
+        JsChunkReader {
+            read_chunk,
+            chunk: vec![],
+            offset: 0,
+        }
+    }
+}
+
+impl Read for JsChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.offset == self.chunk.len() {
+            let chunk = self
+                .read_chunk
+                .call0(&JsValue::NULL)
+                .map_err(|e| std::io::Error::other(format!("{e:?}")))?;
+            self.chunk = Uint8Array::new(&chunk).to_vec();
+            self.offset = 0;
+        }
+
+        let len = (self.chunk.len() - self.offset).min(buf.len());
+        buf[..len].copy_from_slice(&self.chunk[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
 
 #[wasm_bindgen]
 impl GridController {
@@ -23,6 +63,53 @@ impl GridController {
         }
     }
 
+    #[wasm_bindgen(js_name = "getCsvPreviewWithOptions")]
+    pub fn js_get_csv_preview_with_options(
+        file: Vec<u8>,
+        max_rows: u32,
+        options: &str,
+    ) -> Result<JsValue, JsValue> {
+        let options =
+            serde_json::from_str::<CsvImportOptions>(options).map_err(|e| e.to_string())?;
+        let preview =
+            GridController::get_csv_preview_with_options(file.as_slice(), max_rows, &options);
+        match preview {
+            Ok(preview) => Ok(serde_wasm_bindgen::to_value(&preview)?),
+            Err(e) => Err(JsValue::from_str(&e.to_string())),
+        }
+    }
+
+    /// The file is read in chunks from `read_chunk` (see `JsChunkReader`);
+    /// `total_bytes` is only used to report progress.
+    #[wasm_bindgen(js_name = "importCsvWithOptions")]
+    pub fn js_import_csv_with_options(
+        read_chunk: Function,
+        total_bytes: Option<f64>,
+        file_name: &str,
+        options: &str,
+    ) -> Result<GridController, JsValue> {
+        let options =
+            serde_json::from_str::<CsvImportOptions>(options).map_err(|e| e.to_string())?;
+        let mut grid = Grid::new_blank();
+        let sheet_id = grid.add_sheet(None);
+        let insert_at = pos![A1];
+
+        let mut grid_controller = GridController::from_grid(grid, 0);
+        grid_controller
+            .import_csv_with_options(
+                sheet_id,
+                JsChunkReader::new(read_chunk),
+                total_bytes.map(|total_bytes| total_bytes as u64),
+                file_name,
+                insert_at,
+                &options,
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(grid_controller)
+    }
+
     #[wasm_bindgen(js_name = "importCsv")]
     pub fn js_import_csv(
         file: Vec<u8>,
@@ -82,6 +169,40 @@ impl GridController {
     }
 }
 
+#[wasm_bindgen]
+impl GridController {
+    /// The file is read in chunks from `read_chunk` (see `JsChunkReader`).
+    #[wasm_bindgen(js_name = "importCsvWithOptionsIntoExistingFile")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn js_import_csv_with_options_into_existing_file(
+        &mut self,
+        read_chunk: Function,
+        total_bytes: Option<f64>,
+        file_name: &str,
+        sheet_id: &str,
+        insert_at: &str,
+        cursor: Option<String>,
+        options: &str,
+    ) -> Result<(), JsValue> {
+        let sheet_id = SheetId::from_str(sheet_id).map_err(|e| e.to_string())?;
+        let insert_at = serde_json::from_str::<Pos>(insert_at).map_err(|e| e.to_string())?;
+        let options =
+            serde_json::from_str::<CsvImportOptions>(options).map_err(|e| e.to_string())?;
+        self.import_csv_with_options(
+            sheet_id,
+            JsChunkReader::new(read_chunk),
+            total_bytes.map(|total_bytes| total_bytes as u64),
+            file_name,
+            insert_at,
+            &options,
+            cursor,
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
+
 #[wasm_bindgen]
 impl GridController {
     #[wasm_bindgen(js_name = "importExcel")]
@@ -161,3 +282,45 @@ impl GridController {
         Ok(())
     }
 }
+
+#[wasm_bindgen]
+impl GridController {
+    #[wasm_bindgen(js_name = "importJson")]
+    pub fn js_import_json(
+        file: Vec<u8>,
+        file_name: &str,
+        max_depth: Option<usize>,
+    ) -> Result<GridController, JsValue> {
+        let mut grid = Grid::new_blank();
+        let sheet_id = grid.add_sheet(None);
+        let insert_at = pos![A1];
+
+        let mut grid_controller = GridController::from_grid(grid, 0);
+        grid_controller
+            .import_json(sheet_id, file, file_name, insert_at, max_depth, None)
+            .map_err(|e| e.to_string())?;
+
+        Ok(grid_controller)
+    }
+}
+
+#[wasm_bindgen]
+impl GridController {
+    #[wasm_bindgen(js_name = "importJsonIntoExistingFile")]
+    pub fn js_import_json_into_existing_file(
+        &mut self,
+        file: Vec<u8>,
+        file_name: &str,
+        sheet_id: &str,
+        insert_at: &str,
+        max_depth: Option<usize>,
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let sheet_id = SheetId::from_str(sheet_id).map_err(|e| e.to_string())?;
+        let insert_at = serde_json::from_str::<Pos>(insert_at).map_err(|e| e.to_string())?;
+        self.import_json(sheet_id, file, file_name, insert_at, max_depth, cursor)
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
@@ -159,4 +159,32 @@ impl GridController {
     pub fn js_redo(&mut self, cursor: Option<String>) -> Result<JsValue, JsValue> {
         Ok(serde_wasm_bindgen::to_value(&self.redo(cursor))?)
     }
+
+    /// Restores the undo and redo stacks persisted by the client (from
+    /// `takeUndoHistory`). Returns false if the history is invalid or older than
+    /// the file.
+    #[wasm_bindgen(js_name = "restoreUndoHistory")]
+    pub fn js_restore_undo_history(&mut self, history: String) -> bool {
+        match serde_json::from_str(&history) {
+            Ok(history) => self.restore_undo_history(history),
+            Err(e) => {
+                dbgjs!(format!("Invalid undo history in restoreUndoHistory: {e:?}"));
+                false
+            }
+        }
+    }
+
+    /// Sets whether the client is told (with `jsUndoHistoryChanged`) whenever
+    /// the undo and redo stacks change.
+    #[wasm_bindgen(js_name = "setPersistUndoHistory")]
+    pub fn js_set_persist_undo_history(&mut self, persist: bool) {
+        self.set_persist_undo_history(persist);
+    }
+
+    /// Returns the undo and redo stacks to persist, or undefined if they
+    /// haven't changed since they were last taken.
+    #[wasm_bindgen(js_name = "takeUndoHistory")]
+    pub fn js_take_undo_history(&mut self) -> Option<String> {
+        self.take_undo_history()
+    }
 }
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::grid::{GridBounds, Sheet};
+use crate::grid::{GridBounds, Sheet, sheet::protection::SheetProtections};
 
 #[derive(Serialize, Deserialize)]
 #[cfg_attr(feature = "js", derive(ts_rs::TS))]
@@ -12,6 +12,7 @@ pub struct SheetInfo {
     pub offsets: String,
     pub bounds: GridBounds,
     pub bounds_without_formatting: GridBounds,
+    pub protections: SheetProtections,
 }
 
 impl From<&Sheet> for SheetInfo {
@@ -25,6 +26,7 @@ impl From<&Sheet> for SheetInfo {
             offsets,
             bounds: sheet.bounds(false),
             bounds_without_formatting: sheet.bounds(true),
+            protections: sheet.protections.clone(),
         }
     }
 }
@@ -1,3 +1,5 @@
+use sheet::protection::{ProtectionUser, SheetProtections};
+
 use super::*;
 
 #[wasm_bindgen]
@@ -106,4 +108,36 @@ impl GridController {
             &self.set_sheet_color(sheet_id, color, cursor),
         )?)
     }
+
+    /// Sets a sheet's protections (owners only).
+    #[wasm_bindgen(js_name = "setSheetProtections")]
+    pub fn js_set_sheet_protections(
+        &mut self,
+        sheet_id: String,
+        protections: String, // SheetProtections
+        cursor: Option<String>,
+    ) -> Result<(), String> {
+        let sheet_id = SheetId::from_str(&sheet_id).map_err(|_| "Invalid sheet id".to_string())?;
+        let protections = serde_json::from_str::<SheetProtections>(&protections)
+            .map_err(|_| "Invalid protections".to_string())?;
+        self.set_sheet_protections(sheet_id, protections, cursor);
+        Ok(())
+    }
+
+    /// Sets the local user, used to check edits against sheet protections.
+    #[wasm_bindgen(js_name = "setProtectionUser")]
+    pub fn js_set_protection_user(
+        &mut self,
+        user: Option<String>, // ProtectionUser
+    ) -> Result<(), String> {
+        let user = match user {
+            Some(user) => Some(
+                serde_json::from_str::<ProtectionUser>(&user)
+                    .map_err(|_| "Invalid protection user".to_string())?,
+            ),
+            None => None,
+        };
+        self.set_protection_user(user);
+        Ok(())
+    }
 }
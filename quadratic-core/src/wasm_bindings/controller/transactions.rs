@@ -28,6 +28,19 @@ impl GridController {
         ))?)
     }
 
+    /// Rolls back a transaction the multiplayer server rejected.
+    #[wasm_bindgen(js_name = "rejectedTransaction")]
+    pub fn js_rejected_transaction(
+        &mut self,
+        transaction_id: String,
+        message: String,
+    ) -> Result<(), JsValue> {
+        let transaction_id = Uuid::parse_str(&transaction_id)
+            .map_err(|e| JsValue::from_str(&format!("Invalid transaction id: {}", e)))?;
+        self.rejected_transaction(transaction_id, message);
+        Ok(())
+    }
+
     /// Used to set the sequence_num for multiplayer. This should only be called when receiving the sequence_num
     /// directly from the file. Use receiveSequenceNum for all other cases.
     #[wasm_bindgen(js_name = "setMultiplayerSequenceNum")]
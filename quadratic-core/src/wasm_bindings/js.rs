@@ -93,6 +93,7 @@ extern "C" {
     pub fn jsSendTransaction(transaction_id: String, transaction: Vec<u8>);
 
     pub fn jsUndoRedo(undo: bool, redo: bool);
+    pub fn jsUndoHistoryChanged();
 
     pub fn jsConnection(
         transactionId: String,
@@ -100,6 +101,7 @@ extern "C" {
         y: i32,
         sheet_id: String,
         query: String,
+        params: String, /* Vec<SqlParameter> */
         connector_type: ConnectionKind,
         connection_id: String,
     );
@@ -498,20 +500,28 @@ pub fn jsUndoRedo(undo: bool, redo: bool) {
 
 #[cfg(test)]
 #[allow(non_snake_case)]
+pub fn jsUndoHistoryChanged() {
+    js_call("jsUndoHistoryChanged", "".to_string());
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+#[allow(clippy::too_many_arguments)]
 pub fn jsConnection(
     transactionId: String,
     x: i32,
     y: i32,
     sheet_id: String,
     query: String,
+    params: String,
     connector_type: ConnectionKind,
     connection_id: String,
 ) -> JsValue {
     js_call(
         "jsConnection",
         format!(
-            "{},{},{},{},{},{},{}",
-            transactionId, x, y, sheet_id, query, connector_type, connection_id
+            "{},{},{},{},{},{},{},{}",
+            transactionId, x, y, sheet_id, query, params, connector_type, connection_id
         ),
     );
     JsValue::NULL
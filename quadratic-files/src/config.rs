@@ -18,6 +18,16 @@ pub(crate) enum StorageType {
     FileSystem,
 }
 
+/// How long transactions are kept after they're checkpointed
+#[derive(Deserialize, Debug, Display, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum RetentionPolicyType {
+    #[default]
+    Age,
+    Checkpoints,
+    KeepAll,
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 pub(crate) struct Config {
@@ -25,8 +35,25 @@ pub(crate) struct Config {
     pub(crate) port: String,
     pub(crate) file_check_s: i64,
     pub(crate) files_per_check: i64,
+
+    // Maximum number of files processed at once
+    #[serde(default = "default_file_workers")]
+    pub(crate) file_workers: usize,
+
+    // How long a room stays leased to a worker that stops renewing it, before
+    // another worker can take it
+    #[serde(default = "default_room_lease_s")]
+    pub(crate) room_lease_s: u64,
     pub(crate) truncate_file_check_s: i64,
     pub(crate) truncate_transaction_age_days: i64,
+
+    // Retention Policy: age, checkpoints or keep-all
+    #[serde(default)]
+    pub(crate) retention_policy: RetentionPolicyType,
+
+    // RetentionPolicyType::Checkpoints
+    pub(crate) retention_checkpoints: Option<u64>,
+
     pub(crate) environment: Environment,
 
     pub(crate) pubsub_host: String,
@@ -53,6 +80,14 @@ pub(crate) struct Config {
     pub(crate) storage_encryption_keys: Option<Vec<String>>,
 }
 
+fn default_file_workers() -> usize {
+    8
+}
+
+fn default_room_lease_s() -> u64 {
+    30
+}
+
 /// Load the global configuration from the environment into Config.
 pub(crate) fn config() -> Result<Config> {
     let filename = if cfg!(test) { ".env.test" } else { ".env" };
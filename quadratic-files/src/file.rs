@@ -1,6 +1,7 @@
 use chrono::Utc;
 use serde::de::DeserializeOwned;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use quadratic_core::{
@@ -15,6 +16,7 @@ use quadratic_core::{
     },
 };
 use quadratic_rust_shared::{
+    metrics::{CHECKPOINT_DURATION, METRICS, TRANSACTIONS},
     pubsub::PubSub as PubSubTrait,
     quadratic_api::{get_file_checkpoint, set_file_checkpoint},
     storage::{Storage, StorageContainer},
@@ -22,7 +24,8 @@ use quadratic_rust_shared::{
 
 use crate::{
     error::{FilesError, Result},
-    state::{State, settings::Settings},
+    history::Checkpoint,
+    state::{State, pubsub::PubSub, settings::Settings},
     truncate::{add_processed_transaction, processed_transaction_key},
 };
 
@@ -62,9 +65,24 @@ pub(crate) fn key(file_id: Uuid, sequence: u64) -> String {
     format!("{file_id}-{sequence}.grid")
 }
 
+/// Key of the lease a files worker holds while processing a room
+pub(crate) fn room_lease_key(file_id: &Uuid) -> String {
+    format!("files:room:{file_id}:lease")
+}
+
+/// Run cpu-bound grid work off of the async runtime
+pub(crate) async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| FilesError::BackgroundService(e.to_string()))
+}
+
 /// Load a file from S3, add it to memory, process transactions and upload it back to S3
 pub(crate) async fn process_transactions(
     storage: &StorageContainer,
+    pubsub: &Mutex<PubSub>,
     file_id: Uuid,
     checkpoint_sequence_num: u64,
     final_sequence_num: u64,
@@ -78,11 +96,39 @@ pub(crate) async fn process_transactions(
     .await?;
     let key = key(file_id, final_sequence_num);
 
-    apply_transaction(&mut grid, operations);
-    let body = export_file(&key, grid.into_grid())?;
+    let grid = blocking(move || {
+        apply_transaction(&mut grid, operations);
+        grid
+    })
+    .await?;
+
+    // multiplayer enforces the protections, so make sure it has them
+    if let Err(error) = pubsub
+        .lock()
+        .await
+        .seed_protections(&file_id, grid.grid())
+        .await
+    {
+        tracing::warn!("Error seeding protections for room {file_id}: {error}");
+    }
+
+    let body = {
+        let key = key.to_owned();
+        blocking(move || export_file(&key, grid.into_grid())).await??
+    };
 
     storage.write(&key, &body.into()).await?;
 
+    let checkpoint = Checkpoint::new(file_id, final_sequence_num);
+    if let Err(error) = pubsub
+        .lock()
+        .await
+        .record_checkpoint(&file_id, &checkpoint)
+        .await
+    {
+        tracing::warn!("Error recording checkpoint {key} in the history: {error}");
+    }
+
     Ok(final_sequence_num)
 }
 
@@ -108,19 +154,33 @@ pub(crate) async fn process_queue_for_room(
             Err(_) => 0,
         };
 
-    // this is an expensive lock since we're waiting for the file to write to S3 before unlocking
-    let mut pubsub = state.pubsub.lock().await;
+    // only hold the lock while reading the queue, so other rooms can be
+    // processed while this file is loaded, updated and uploaded
+    let messages = {
+        let mut pubsub = state.pubsub.lock().await;
 
-    // subscribe to the channel
-    pubsub.connection.subscribe(channel, GROUP_NAME).await?;
+        // subscribe to the channel
+        pubsub.connection.subscribe(channel, GROUP_NAME).await?;
 
-    // get all transactions for the room in the queue
-    let transactions = pubsub
-        .connection
-        .get_messages_from(channel, &(checkpoint_sequence_num + 1).to_string(), false)
-        .await?
+        // get all transactions for the room in the queue
+        pubsub
+            .connection
+            .get_messages_from(channel, &(checkpoint_sequence_num + 1).to_string(), false)
+            .await?
+    };
+
+    let transactions = messages
         .into_iter()
-        .flat_map(|(_, message)| decompress_and_deserialize::<TransactionServer>(message))
+        .filter_map(|(id, message)| {
+            decompress_and_deserialize::<TransactionServer>(message)
+                .map_err(|error| {
+                    tracing::warn!(
+                        "Skipping undecodable message {id} for room {file_id}: {:?}",
+                        error
+                    )
+                })
+                .ok()
+        })
         .collect::<Vec<TransactionServer>>();
 
     tracing::trace!(
@@ -150,13 +210,22 @@ pub(crate) async fn process_queue_for_room(
     // combine all operations into a single vec
     let operations = transactions
         .into_iter()
-        .flat_map(|transaction| {
+        .filter_map(|transaction| {
             // tracing::info!(
             //     "Processing transaction {}, sequence number {} for room {file_id}",
             //     transaction.id,
             //     transaction.sequence_num
             // );
             decompress_and_deserialize::<Vec<Operation>>(transaction.operations)
+                .map_err(|error| {
+                    tracing::warn!(
+                        "Skipping undecodable transaction {}, sequence number {} for room {file_id}: {:?}",
+                        transaction.id,
+                        transaction.sequence_num,
+                        error
+                    )
+                })
+                .ok()
         })
         .flatten()
         .collect::<Vec<Operation>>();
@@ -164,6 +233,7 @@ pub(crate) async fn process_queue_for_room(
     // process the transactions and save the file to S3
     let last_sequence_num = process_transactions(
         storage,
+        &state.pubsub,
         file_id,
         checkpoint_sequence_num,
         last_sequence_num,
@@ -179,13 +249,14 @@ pub(crate) async fn process_queue_for_room(
     let keys = keys.iter().map(AsRef::as_ref).collect::<Vec<_>>();
 
     // confirm that transactions have been processed
-    pubsub
+    state
+        .pubsub
+        .lock()
+        .await
         .connection
         .ack(channel, GROUP_NAME, keys, Some(active_channels), false)
         .await?;
 
-    drop(pubsub);
-
     // update the checkpoint in quadratic-api
     let key = &key(file_id, last_sequence_num);
 
@@ -216,9 +287,17 @@ pub(crate) async fn process_queue_for_room(
 
     state.stats.lock().await.last_processed_file_time = Some(Utc::now());
 
+    let elapsed = Utc::now() - start;
+    METRICS.increment(&TRANSACTIONS, &[], sequence_numbers.len() as f64);
+    METRICS.observe(
+        &CHECKPOINT_DURATION,
+        &[],
+        elapsed.num_milliseconds() as f64 / 1000.0,
+    );
+
     tracing::info!(
         "Processed sequence numbers {first_sequence_num} - {last_sequence_num} for room {file_id} in {:?}ms",
-        (Utc::now() - start).num_milliseconds()
+        elapsed.num_milliseconds()
     );
 
     Ok(Some(last_sequence_num))
@@ -242,7 +321,91 @@ pub(crate) async fn get_files_to_process(
     Ok(files)
 }
 
-/// Process outstanding transactions in the queue
+/// Renew a room's lease every third of its ttl, so it's held for as long as
+/// the room is processed.  Only returns once the lease can't be renewed.
+pub(crate) async fn keep_lease(
+    state: &Arc<State>,
+    lease_key: &str,
+    owner: &str,
+    ttl_ms: u64,
+) -> FilesError {
+    let mut interval = tokio::time::interval(Duration::from_millis((ttl_ms / 3).max(1)));
+
+    // the first tick completes immediately, and the lease was just taken
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let renewed = state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .renew_lease(lease_key, owner, ttl_ms)
+            .await;
+
+        match renewed {
+            Ok(true) => {}
+            Ok(false) => return FilesError::PubSub(format!("Lost the lease {lease_key}")),
+            Err(error) => {
+                return FilesError::PubSub(format!(
+                    "Error renewing the lease {lease_key}: {error}"
+                ));
+            }
+        }
+    }
+}
+
+/// Process a room's queue while holding the room's lease, so that no other
+/// files worker processes it at the same time.  The lease is renewed while
+/// processing, which stops if it can't be.  Rooms leased by another worker
+/// are skipped until the next check.
+pub(crate) async fn process_leased_room(
+    state: &Arc<State>,
+    file_id: Uuid,
+    active_channels: &str,
+) -> Result<Option<u64>> {
+    let lease_key = room_lease_key(&file_id);
+    let owner = Uuid::new_v4().to_string();
+    let leased = state
+        .pubsub
+        .lock()
+        .await
+        .connection
+        .acquire_lease(&lease_key, &owner, state.settings.room_lease_ms)
+        .await?;
+
+    if !leased {
+        tracing::trace!("Room {file_id} is being processed by another worker");
+        state.stats.lock().await.files_skipped_while_leased += 1;
+        return Ok(None);
+    }
+
+    state.stats.lock().await.files_processing += 1;
+    let processed = tokio::select! {
+        processed = process_queue_for_room(state, file_id, active_channels) => processed,
+        error = keep_lease(state, &lease_key, &owner, state.settings.room_lease_ms) => Err(error),
+    };
+    state.stats.lock().await.files_processing -= 1;
+
+    if let Err(error) = state
+        .pubsub
+        .lock()
+        .await
+        .connection
+        .release_lease(&lease_key, &owner)
+        .await
+    {
+        tracing::warn!("Error releasing the lease for room {file_id}: {error}");
+    }
+
+    processed
+}
+
+/// Process outstanding transactions in the queue.  Rooms are processed
+/// concurrently, up to the number of file workers.  Waiting for a free
+/// worker holds up the next check, so the backlog stays in pubsub.
 pub(crate) async fn process(state: &Arc<State>, active_channels: &str) -> Result<()> {
     let files = get_files_to_process(state, active_channels).await?;
 
@@ -250,22 +413,41 @@ pub(crate) async fn process(state: &Arc<State>, active_channels: &str) -> Result
     state.stats.lock().await.files_to_process_in_pubsub = files.len() as u64;
 
     for file_id in files.into_iter() {
-        let state = Arc::clone(state);
+        state.stats.lock().await.files_waiting_for_worker += 1;
+
+        let permit = tokio::select! {
+            permit = Arc::clone(&state.workers).acquire_owned() => permit.ok(),
+            _ = state.shutdown.cancelled() => None,
+        };
+
+        state.stats.lock().await.files_waiting_for_worker -= 1;
+
+        // stop taking new files once shutting down
+        let Some(permit) = permit else {
+            break;
+        };
+
         let active_channels = active_channels.to_owned();
 
-        // process file in a separate thread
-        tokio::spawn(async move {
-            // TODO(ddimaria): instead of logging the error, move the file to a dead letter queue
-            if let Err(error) = process_queue_for_room(&state, file_id, &active_channels).await {
-                tracing::error!("Error processing file {file_id}: {error}");
-            };
+        // process file in a separate task, which shutdown waits for
+        state.tasks.spawn({
+            let state = Arc::clone(state);
+
+            async move {
+                // TODO(ddimaria): instead of logging the error, move the file to a dead letter queue
+                if let Err(error) = process_leased_room(&state, file_id, &active_channels).await {
+                    tracing::error!("Error processing file {file_id}: {error}");
+                };
+
+                drop(permit);
+            }
         });
     }
 
     Ok(())
 }
 
-fn decompress_and_deserialize<T: DeserializeOwned>(data: Vec<u8>) -> Result<T> {
+pub(crate) fn decompress_and_deserialize<T: DeserializeOwned>(data: Vec<u8>) -> Result<T> {
     Transaction::decompress_and_deserialize::<T>(&data)
         .map_err(|e| FilesError::Serialization(e.to_string()))
 }
@@ -273,6 +455,7 @@ fn decompress_and_deserialize<T: DeserializeOwned>(data: Vec<u8>) -> Result<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::new_arc_state;
     use quadratic_core::{CellValue, Pos, SheetPos};
 
     #[test]
@@ -319,6 +502,96 @@ mod tests {
         assert!(grid.is_ok());
     }
 
+    #[tokio::test]
+    async fn skips_leased_rooms() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let lease_key = room_lease_key(&file_id);
+        let owner = "another worker";
+
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .acquire_lease(&lease_key, owner, 10_000)
+            .await
+            .unwrap();
+
+        let processed = process_leased_room(&state, file_id, "active_channels")
+            .await
+            .unwrap();
+        assert_eq!(processed, None);
+
+        let stats = state.stats.lock().await.to_owned();
+        assert_eq!(stats.files_skipped_while_leased, 1);
+        assert_eq!(stats.files_processing, 0);
+
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .release_lease(&lease_key, owner)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_the_lease_until_it_is_lost() {
+        let state = new_arc_state().await;
+        let lease_key = room_lease_key(&Uuid::new_v4());
+        let owner = "this worker";
+        let ttl_ms = 300;
+
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .acquire_lease(&lease_key, owner, ttl_ms)
+            .await
+            .unwrap();
+
+        // the lease outlives its ttl while it's kept
+        let kept = tokio::time::timeout(
+            Duration::from_millis(ttl_ms * 3),
+            keep_lease(&state, &lease_key, owner, ttl_ms),
+        )
+        .await;
+        assert!(kept.is_err());
+
+        // another worker takes over once the lease is released
+        let mut pubsub = state.pubsub.lock().await;
+        assert!(
+            pubsub
+                .connection
+                .release_lease(&lease_key, owner)
+                .await
+                .unwrap()
+        );
+        assert!(
+            pubsub
+                .connection
+                .acquire_lease(&lease_key, "another worker", 10_000)
+                .await
+                .unwrap()
+        );
+        drop(pubsub);
+
+        let error = keep_lease(&state, &lease_key, owner, ttl_ms).await;
+        assert!(matches!(error, FilesError::PubSub(_)));
+
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .delete(&lease_key)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn processes_a_file() {
         // let state = new_arc_state().await;
@@ -0,0 +1,474 @@
+//! File History
+//!
+//! Every time transactions are applied to a file, a new checkpoint
+//! (`{file_id}-{sequence_num}.grid`) is written and recorded in the file's
+//! history.  A file can be rebuilt at any sequence number by loading the
+//! nearest earlier checkpoint and replaying the transactions after it, as long
+//! as the retention policy (see `truncate.rs`) has kept them.  Checkpoints
+//! before the transactions that are kept are pruned from the history and
+//! storage.
+//!
+//! The history routes are only served to users who may view the file.
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    response::{IntoResponse, Response},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use chrono::{DateTime, Utc};
+use quadratic_core::{
+    Pos,
+    controller::{
+        GridController, operations::operation::Operation, transaction::TransactionServer,
+    },
+    grid::{Grid, Sheet},
+};
+use quadratic_rust_shared::{
+    pubsub::PubSub as PubSubTrait,
+    quadratic_api::{can_view, get_file_perms},
+    storage::Storage,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::Arc,
+};
+use uuid::Uuid;
+
+use crate::{
+    error::{FilesError, Result},
+    file::{apply_transaction, blocking, decompress_and_deserialize, export_file, key, load_file},
+    state::State,
+};
+
+/// Key of the hash that holds a file's checkpoints (sequence number to
+/// json-encoded checkpoint)
+pub(crate) fn history_key(file_id: &Uuid) -> String {
+    format!("files:history:{file_id}")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    pub(crate) sequence_num: u64,
+    pub(crate) key: String,
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+impl Checkpoint {
+    pub(crate) fn new(file_id: Uuid, sequence_num: u64) -> Self {
+        Checkpoint {
+            sequence_num,
+            key: key(file_id, sequence_num),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// A cell whose display value differs between two versions of a file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct CellDiff {
+    pub(crate) sheet_id: String,
+    pub(crate) sheet_name: String,
+    pub(crate) x: i64,
+    pub(crate) y: i64,
+    pub(crate) before: Option<String>,
+    pub(crate) after: Option<String>,
+}
+
+/// Find the most recent checkpoint at or before the sequence number.
+/// Checkpoints must be sorted by sequence number.
+pub(crate) fn nearest_checkpoint(
+    checkpoints: &[Checkpoint],
+    sequence_num: u64,
+) -> Option<&Checkpoint> {
+    checkpoints
+        .iter()
+        .rev()
+        .find(|checkpoint| checkpoint.sequence_num <= sequence_num)
+}
+
+/// The checkpoints before the retained sequence number, which are pruned
+/// along with the transactions after them.  The checkpoint at the retained
+/// sequence number is kept, since it's where later versions are replayed
+/// from.
+pub(crate) fn prunable_checkpoints(
+    checkpoints: &[Checkpoint],
+    retained_sequence_num: u64,
+) -> Vec<&Checkpoint> {
+    checkpoints
+        .iter()
+        .filter(|checkpoint| checkpoint.sequence_num < retained_sequence_num)
+        .collect()
+}
+
+/// Collect the operations of the transactions after `from` up to and
+/// including `to`.  Errors if any of those transactions are missing, which
+/// happens once they've been truncated.
+pub(crate) fn operations_to_replay(
+    file_id: Uuid,
+    transactions: Vec<TransactionServer>,
+    from: u64,
+    to: u64,
+) -> Result<Vec<Operation>> {
+    let mut transactions = transactions
+        .into_iter()
+        .filter(|transaction| transaction.sequence_num > from && transaction.sequence_num <= to)
+        .collect::<Vec<_>>();
+    transactions.sort_by_key(|transaction| transaction.sequence_num);
+
+    let is_complete = transactions
+        .iter()
+        .zip(from + 1..=to)
+        .all(|(transaction, expected)| transaction.sequence_num == expected)
+        && transactions.len() as u64 == to - from;
+
+    if !is_complete {
+        return Err(FilesError::NotFound(format!(
+            "Transactions {} - {to} for file {file_id} are no longer retained",
+            from + 1
+        )));
+    }
+
+    let mut operations = vec![];
+
+    for transaction in transactions.into_iter() {
+        operations.extend(decompress_and_deserialize::<Vec<Operation>>(
+            transaction.operations,
+        )?);
+    }
+
+    Ok(operations)
+}
+
+/// Rebuild a file at a sequence number.  The pubsub lock is only held while
+/// reading the checkpoints and transactions, and the file is loaded and
+/// replayed on a blocking thread, so the transaction workers aren't stalled.
+pub(crate) async fn materialize(
+    state: &Arc<State>,
+    file_id: Uuid,
+    sequence_num: u64,
+) -> Result<GridController> {
+    let (checkpoint, messages) = {
+        let mut pubsub = state.pubsub.lock().await;
+        let checkpoints = pubsub.checkpoints(&file_id).await?;
+        let checkpoint = nearest_checkpoint(&checkpoints, sequence_num)
+            .cloned()
+            .ok_or_else(|| {
+                FilesError::NotFound(format!(
+                    "No checkpoint at or before sequence number {sequence_num} for file {file_id}"
+                ))
+            })?;
+
+        let messages = match checkpoint.sequence_num == sequence_num {
+            true => vec![],
+            false => {
+                pubsub
+                    .connection
+                    .get_messages_between(
+                        &file_id.to_string(),
+                        &(checkpoint.sequence_num + 1).to_string(),
+                        &sequence_num.to_string(),
+                        false,
+                    )
+                    .await?
+            }
+        };
+
+        (checkpoint, messages)
+    };
+
+    let body = state
+        .settings
+        .storage
+        .read(&checkpoint.key)
+        .await
+        .map_err(|e| FilesError::LoadFile(checkpoint.key.to_owned(), e.to_string()))?;
+
+    blocking(move || {
+        let transactions = messages
+            .into_iter()
+            .map(|(_, message)| decompress_and_deserialize::<TransactionServer>(message))
+            .collect::<Result<Vec<_>>>()?;
+        let operations =
+            operations_to_replay(file_id, transactions, checkpoint.sequence_num, sequence_num)?;
+        let grid = load_file(&checkpoint.key, body.to_vec())?;
+        let mut grid = GridController::from_grid(grid, checkpoint.sequence_num);
+
+        if !operations.is_empty() {
+            apply_transaction(&mut grid, operations);
+        }
+
+        Ok(grid)
+    })
+    .await?
+}
+
+/// Positions of a sheet that may hold a value
+fn cell_positions(sheet: &Sheet) -> HashSet<Pos> {
+    let mut positions = HashSet::new();
+
+    for (x, column) in sheet.iter_columns() {
+        positions.extend(column.values.keys().map(|y| Pos { x: *x, y: *y }));
+    }
+
+    for (pos, data_table) in sheet.iter_data_tables() {
+        positions.extend(data_table.output_rect(*pos, false).iter());
+    }
+
+    positions
+}
+
+/// Compare the display values of two versions of a file, cell by cell
+pub(crate) fn diff_grids(before: &Grid, after: &Grid) -> Vec<CellDiff> {
+    let mut sheet_ids = after.sheet_ids();
+    sheet_ids.extend(
+        before
+            .sheet_ids()
+            .into_iter()
+            .filter(|sheet_id| after.try_sheet(*sheet_id).is_none()),
+    );
+
+    let mut diffs = vec![];
+
+    for sheet_id in sheet_ids.into_iter() {
+        let before_sheet = before.try_sheet(sheet_id);
+        let after_sheet = after.try_sheet(sheet_id);
+        let Some(sheet_name) = after_sheet
+            .or(before_sheet)
+            .map(|sheet| sheet.name.to_owned())
+        else {
+            continue;
+        };
+
+        // sort by row, then column
+        let positions = before_sheet
+            .map(cell_positions)
+            .unwrap_or_default()
+            .into_iter()
+            .chain(after_sheet.map(cell_positions).unwrap_or_default())
+            .map(|pos| (pos.y, pos.x))
+            .collect::<BTreeSet<_>>();
+
+        for (y, x) in positions.into_iter() {
+            let pos = Pos { x, y };
+            let before_value = before_sheet.and_then(|sheet| sheet.display_value(pos));
+            let after_value = after_sheet.and_then(|sheet| sheet.display_value(pos));
+
+            if before_value != after_value {
+                diffs.push(CellDiff {
+                    sheet_id: sheet_id.to_string(),
+                    sheet_name: sheet_name.to_owned(),
+                    x,
+                    y,
+                    before: before_value.map(|value| value.to_display()),
+                    after: after_value.map(|value| value.to_display()),
+                });
+            }
+        }
+    }
+
+    diffs
+}
+
+/// Check that the user making the request may view the file
+async fn validate_can_view_file(state: &State, jwt: &str, file_id: Uuid) -> Result<()> {
+    // the api isn't available in tests
+    if cfg!(test) {
+        return Ok(());
+    }
+
+    let (permissions, _) =
+        get_file_perms(&state.settings.quadratic_api_uri, jwt.to_owned(), file_id)
+            .await
+            .map_err(|e| FilesError::FilePermissions(e.to_string()))?;
+
+    match can_view(&permissions) {
+        true => Ok(()),
+        false => Err(FilesError::FilePermissions(format!(
+            "Not allowed to view file {file_id}"
+        ))),
+    }
+}
+
+/// List the checkpoints of a file
+pub(crate) async fn get_history(
+    Path(file_id): Path<Uuid>,
+    state: Extension<Arc<State>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<Checkpoint>>> {
+    tracing::trace!("Get history for file {file_id}");
+
+    validate_can_view_file(&state, bearer.token(), file_id).await?;
+    let checkpoints = state.pubsub.lock().await.checkpoints(&file_id).await?;
+
+    Ok(Json(checkpoints))
+}
+
+/// Get a file as it was at a sequence number
+pub(crate) async fn get_history_file(
+    Path((file_id, sequence_num)): Path<(Uuid, u64)>,
+    state: Extension<Arc<State>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Result<Response> {
+    tracing::trace!("Get file {file_id} at sequence number {sequence_num}");
+
+    validate_can_view_file(&state, bearer.token(), file_id).await?;
+    let grid = materialize(&state, file_id, sequence_num).await?;
+    let key = key(file_id, sequence_num);
+    let body = blocking(move || export_file(&key, grid.into_grid())).await??;
+
+    Ok(body.into_response())
+}
+
+/// Compare two versions of a file at the cell level
+pub(crate) async fn get_history_diff(
+    Path((file_id, from, to)): Path<(Uuid, u64, u64)>,
+    state: Extension<Arc<State>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<CellDiff>>> {
+    tracing::trace!("Diff file {file_id} between sequence numbers {from} and {to}");
+
+    validate_can_view_file(&state, bearer.token(), file_id).await?;
+    let (before, after) = tokio::try_join!(
+        materialize(&state, file_id, from),
+        materialize(&state, file_id, to)
+    )?;
+    let diffs = blocking(move || diff_grids(before.grid(), after.grid())).await?;
+
+    Ok(Json(diffs))
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::{
+        SheetPos,
+        controller::transaction::{Transaction, TransactionServer},
+    };
+
+    use super::*;
+    use crate::file::load_file;
+
+    fn new_grid() -> GridController {
+        let file = load_file(
+            "test",
+            include_bytes!("../../quadratic-rust-shared/data/grid/v1_4_simple.grid").to_vec(),
+        )
+        .unwrap();
+
+        GridController::from_grid(file, 0)
+    }
+
+    fn set_value(gc: &mut GridController, x: i64, y: i64, value: &str) -> Vec<Operation> {
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(SheetPos { x, y, sheet_id }, value.to_string(), None);
+        gc.last_transaction().unwrap().operations.clone()
+    }
+
+    fn transaction(
+        file_id: Uuid,
+        sequence_num: u64,
+        operations: &[Operation],
+    ) -> TransactionServer {
+        TransactionServer {
+            id: Uuid::new_v4(),
+            file_id,
+            operations: Transaction::serialize_and_compress(operations).unwrap(),
+            sequence_num,
+        }
+    }
+
+    #[test]
+    fn finds_the_nearest_checkpoint() {
+        let file_id = Uuid::new_v4();
+        let checkpoints = vec![
+            Checkpoint::new(file_id, 1),
+            Checkpoint::new(file_id, 5),
+            Checkpoint::new(file_id, 9),
+        ];
+
+        assert_eq!(nearest_checkpoint(&checkpoints, 0), None);
+        assert_eq!(nearest_checkpoint(&checkpoints, 1), Some(&checkpoints[0]));
+        assert_eq!(nearest_checkpoint(&checkpoints, 8), Some(&checkpoints[1]));
+        assert_eq!(nearest_checkpoint(&checkpoints, 100), Some(&checkpoints[2]));
+    }
+
+    #[test]
+    fn prunes_checkpoints_before_the_retained_sequence_num() {
+        let file_id = Uuid::new_v4();
+        let checkpoints = [3, 6, 9]
+            .into_iter()
+            .map(|sequence_num| Checkpoint::new(file_id, sequence_num))
+            .collect::<Vec<_>>();
+        let pruned = |sequence_num| {
+            prunable_checkpoints(&checkpoints, sequence_num)
+                .into_iter()
+                .map(|checkpoint| checkpoint.sequence_num)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(pruned(3), Vec::<u64>::new());
+        assert_eq!(pruned(6), vec![3]);
+        assert_eq!(pruned(8), vec![3, 6]);
+    }
+
+    #[test]
+    fn replays_retained_transactions() {
+        let file_id = Uuid::new_v4();
+        let mut gc = new_grid();
+        let transactions = vec![
+            transaction(file_id, 3, &set_value(&mut gc, 1, 1, "a")),
+            transaction(file_id, 4, &set_value(&mut gc, 2, 1, "b")),
+            transaction(file_id, 5, &set_value(&mut gc, 3, 1, "c")),
+        ];
+
+        let operations = operations_to_replay(file_id, transactions, 2, 4).unwrap();
+        let mut replayed = new_grid();
+        apply_transaction(&mut replayed, operations);
+
+        let sheet = replayed.grid().sheets()[0].to_owned();
+        assert!(sheet.display_value(Pos { x: 1, y: 1 }).is_some());
+        assert!(sheet.display_value(Pos { x: 2, y: 1 }).is_some());
+        assert!(sheet.display_value(Pos { x: 3, y: 1 }).is_none());
+
+        // sequence number 3 was truncated
+        let transactions = vec![transaction(file_id, 4, &set_value(&mut gc, 2, 1, "b"))];
+        let error = operations_to_replay(file_id, transactions, 2, 4).unwrap_err();
+        assert!(matches!(error, FilesError::NotFound(_)));
+    }
+
+    #[test]
+    fn diffs_two_versions() {
+        let mut before = new_grid();
+        set_value(&mut before, 1, 1, "same");
+        set_value(&mut before, 2, 2, "old");
+        set_value(&mut before, 3, 3, "removed");
+
+        let mut after = GridController::from_grid(before.grid().clone(), 0);
+        set_value(&mut after, 2, 2, "new");
+        set_value(&mut after, 3, 3, "");
+        set_value(&mut after, 4, 4, "added");
+
+        let sheet = &after.grid().sheets()[0];
+        let diff = |x, y, before: Option<&str>, after: Option<&str>| CellDiff {
+            sheet_id: sheet.id.to_string(),
+            sheet_name: sheet.name.to_owned(),
+            x,
+            y,
+            before: before.map(String::from),
+            after: after.map(String::from),
+        };
+
+        assert_eq!(
+            diff_grids(before.grid(), after.grid()),
+            vec![
+                diff(2, 2, Some("old"), Some("new")),
+                diff(3, 3, Some("removed"), None),
+                diff(4, 4, None, Some("added")),
+            ]
+        );
+    }
+}
@@ -8,6 +8,7 @@ mod config;
 mod error;
 mod file;
 mod health;
+mod history;
 mod server;
 mod state;
 mod storage;
@@ -4,20 +4,22 @@
 //! to be shared across all requests and threads.  Adds tracing/logging.
 
 use axum::Json;
-use axum::http::Method;
+use axum::http::{Method, header::CONTENT_TYPE};
 use axum::response::IntoResponse;
 use axum::{Extension, Router, routing::get};
 use quadratic_rust_shared::auth::jwt::get_jwks;
+use quadratic_rust_shared::metrics::{self, METRICS, PUBSUB_BACKLOG};
 use quadratic_rust_shared::storage::Storage;
 use std::time::Duration;
 use std::{net::SocketAddr, sync::Arc};
-use tokio::time;
+use tokio::{signal, time};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::file::get_files_to_process;
 use crate::health::{full_healthcheck, healthcheck};
+use crate::history::{get_history, get_history_diff, get_history_file};
 use crate::state::stats::StatsResponse;
 use crate::storage::{get_presigned_storage, get_storage};
 use crate::truncate::truncate_processed_transactions;
@@ -65,6 +67,15 @@ pub(crate) fn app(state: Arc<State>) -> Router {
                 .post(upload_storage),
         )
         //
+        // list the checkpoints of a file
+        .route("/history/{file_id}", get(get_history))
+        //
+        // get a file at a sequence number
+        .route("/history/{file_id}/{sequence_num}", get(get_history_file))
+        //
+        // diff a file between two sequence numbers
+        .route("/history/{file_id}/diff/{from}/{to}", get(get_history_diff))
+        //
         // auth middleware
         .route_layer(auth)
         //
@@ -79,6 +90,9 @@ pub(crate) fn app(state: Arc<State>) -> Router {
         // stats
         .route("/stats", get(stats))
         //
+        // prometheus metrics
+        .route("/metrics", get(metrics))
+        //
         // presigned urls
         .route("/storage/presigned/{key}", get(get_presigned_storage))
         //
@@ -133,7 +147,10 @@ pub(crate) async fn serve() -> Result<()> {
             let mut interval = time::interval(Duration::from_secs(config.file_check_s as u64));
 
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = state.shutdown.cancelled() => break,
+                }
 
                 if let Err(error) = process(&state, &config.pubsub_active_channels).await {
                     tracing::error!("Error processing files: {error}");
@@ -197,17 +214,62 @@ pub(crate) async fn serve() -> Result<()> {
         }
     });
 
-    axum::serve(
+    let served = axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
     )
+    .with_graceful_shutdown(shutdown_signal(Arc::clone(&state)))
     .await
     .map_err(|e| {
         tracing::warn!("{e}");
         FilesError::InternalServer(e.to_string())
-    })?;
+    });
+
+    // finish the files being processed, so their uploads complete
+    state.shutdown.cancel();
+    state.tasks.close();
+    state.tasks.wait().await;
 
-    Ok(())
+    tracing::info!("Finished processing in-flight files, shutting down");
+
+    served
+}
+
+/// Wait for ctrl+c or SIGTERM, then stop taking new files
+async fn shutdown_signal(state: Arc<State>) {
+    let ctrl_c = async {
+        if let Err(error) = signal::ctrl_c().await {
+            tracing::error!("Error listening for ctrl+c: {error}");
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(error) => {
+                tracing::error!("Error listening for SIGTERM: {error}");
+                std::future::pending::<()>().await
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    tracing::info!(
+        "Shutting down, waiting for {} in-flight file(s)",
+        state.tasks.len()
+    );
+
+    state.shutdown.cancel();
 }
 
 pub(crate) async fn stats(state: Extension<Arc<State>>) -> impl IntoResponse {
@@ -217,6 +279,13 @@ pub(crate) async fn stats(state: Extension<Arc<State>>) -> impl IntoResponse {
     Json(response)
 }
 
+pub(crate) async fn metrics(state: Extension<Arc<State>>) -> impl IntoResponse {
+    let files_to_process_in_pubsub = state.stats.lock().await.files_to_process_in_pubsub;
+    METRICS.set(&PUBSUB_BACKLOG, &[], files_to_process_in_pubsub as f64);
+
+    ([(CONTENT_TYPE, metrics::CONTENT_TYPE)], METRICS.render())
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use crate::test_util::{new_arc_state, response};
@@ -231,4 +300,16 @@ pub(crate) mod tests {
         let response = response(app, Method::GET, "/stats").await;
         assert_eq!(response.status(), StatusCode::OK);
     }
+    #[tokio::test]
+    async fn responds_with_prometheus_metrics() {
+        let state = new_arc_state().await;
+        state.stats.lock().await.files_to_process_in_pubsub = 2;
+        let app = app(state);
+        let response = response(app, Method::GET, "/metrics").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            metrics::CONTENT_TYPE
+        );
+    }
 }
@@ -10,7 +10,9 @@ pub mod stats;
 use jsonwebtoken::jwk::JwkSet;
 use quadratic_rust_shared::pubsub::Config as PubSubConfig;
 use quadratic_rust_shared::pubsub::redis_streams::RedisStreamsConfig;
-use tokio::sync::Mutex;
+use std::sync::Arc;
+use tokio::sync::{Mutex, Semaphore};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use crate::config::Config;
 use crate::error::Result;
@@ -24,6 +26,13 @@ pub(crate) struct State {
     pub(crate) pubsub: Mutex<PubSub>,
     pub(crate) settings: Settings,
     pub(crate) stats: Mutex<Stats>,
+
+    // a permit is held for each file being processed
+    pub(crate) workers: Arc<Semaphore>,
+
+    // in-flight file processing, which shutdown waits for
+    pub(crate) tasks: TaskTracker,
+    pub(crate) shutdown: CancellationToken,
 }
 
 impl State {
@@ -35,10 +44,16 @@ impl State {
             active_channels: config.pubsub_active_channels.to_owned(),
         });
 
+        let settings = Settings::new(config, jwks).await;
+        let workers = Arc::new(Semaphore::new(settings.file_workers));
+
         Ok(State {
             pubsub: Mutex::new(PubSub::new(pubsub_config).await?),
-            settings: Settings::new(config, jwks).await,
+            settings,
             stats: Mutex::new(Stats::new()),
+            workers,
+            tasks: TaskTracker::new(),
+            shutdown: CancellationToken::new(),
         })
     }
 }
@@ -1,8 +1,12 @@
+use quadratic_core::grid::Grid;
 use quadratic_rust_shared::pubsub::{
-    Config as PubSubConfig, PubSub as PubSubTrait, redis_streams::RedisConnection,
+    Config as PubSubConfig, PubSub as PubSubTrait, data_table_rects_key, protections_key,
+    redis_streams::RedisConnection,
 };
+use uuid::Uuid;
 
 use crate::error::Result;
+use crate::history::{Checkpoint, history_key};
 
 #[derive(Debug)]
 pub(crate) struct PubSub {
@@ -41,6 +45,95 @@ impl PubSub {
             }
         }
     }
+
+    /// Seed a file's sheet protections, which multiplayer enforces.  Sheets
+    /// that already have protections stored are left alone, since
+    /// multiplayer keeps them current with transactions that may not be in
+    /// this file yet.  This restores the protections if they're lost.
+    ///
+    /// The output rects of the protected sheets' data tables are always
+    /// replaced, so multiplayer checks edits against the file's tables.
+    pub(crate) async fn seed_protections(&mut self, file_id: &Uuid, grid: &Grid) -> Result<()> {
+        let key = protections_key(file_id);
+        let rects_key = data_table_rects_key(file_id);
+
+        for sheet in grid.sheets().iter() {
+            if sheet.protections.is_empty() {
+                continue;
+            }
+
+            let sheet_id = sheet.id.to_string();
+            let protections = serde_json::to_vec(&sheet.protections)?;
+            self.connection
+                .hash_set_if_missing(&key, &sheet_id, &protections)
+                .await?;
+
+            let rects = serde_json::to_vec(&sheet.data_table_rects())?;
+            self.connection
+                .hash_set(&rects_key, &sheet_id, &rects)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Record a checkpoint in the file's history
+    pub(crate) async fn record_checkpoint(
+        &mut self,
+        file_id: &Uuid,
+        checkpoint: &Checkpoint,
+    ) -> Result<()> {
+        let value = serde_json::to_vec(checkpoint)?;
+        self.connection
+            .hash_set(
+                &history_key(file_id),
+                &checkpoint.sequence_num.to_string(),
+                &value,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remove a checkpoint from the file's history
+    pub(crate) async fn remove_checkpoint(
+        &mut self,
+        file_id: &Uuid,
+        checkpoint: &Checkpoint,
+    ) -> Result<()> {
+        self.connection
+            .hash_remove(
+                &history_key(file_id),
+                &checkpoint.sequence_num.to_string(),
+                &[],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the checkpoints in the file's history, oldest first
+    pub(crate) async fn checkpoints(&mut self, file_id: &Uuid) -> Result<Vec<Checkpoint>> {
+        let mut checkpoints = self
+            .connection
+            .hash_get_all(&history_key(file_id))
+            .await?
+            .into_iter()
+            .filter_map(|(field, value)| {
+                serde_json::from_slice::<Checkpoint>(&value)
+                    .map_err(|error| {
+                        tracing::warn!(
+                            "Skipping undecodable checkpoint {field} for {file_id}: {error}"
+                        )
+                    })
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+
+        checkpoints.sort_by_key(|checkpoint| checkpoint.sequence_num);
+
+        Ok(checkpoints)
+    }
 }
 
 #[cfg(test)]
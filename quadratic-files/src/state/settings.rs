@@ -5,7 +5,19 @@ use quadratic_rust_shared::storage::StorageContainer;
 use quadratic_rust_shared::storage::file_system::{FileSystem, FileSystemConfig};
 use quadratic_rust_shared::storage::s3::{S3, S3Config};
 
-use crate::config::{Config, StorageType};
+use crate::config::{Config, RetentionPolicyType, StorageType};
+
+/// Which transactions to keep once they're older than the truncation age
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RetentionPolicy {
+    /// Discard the transactions up to the checkpoint
+    Age,
+    /// Keep the transactions after the nth most recent checkpoint (n is at
+    /// least 1)
+    Checkpoints(u64),
+    /// Never discard transactions
+    KeepAll,
+}
 
 #[derive(Debug)]
 pub(crate) struct Settings {
@@ -14,6 +26,9 @@ pub(crate) struct Settings {
     pub(crate) m2m_auth_token: String,
     pub(crate) storage: StorageContainer,
     pub(crate) pubsub_processed_transactions_channel: String,
+    pub(crate) retention_policy: RetentionPolicy,
+    pub(crate) file_workers: usize,
+    pub(crate) room_lease_ms: u64,
 }
 
 impl Settings {
@@ -50,6 +65,18 @@ impl Settings {
             }
         };
 
+        let retention_policy = match config.retention_policy {
+            RetentionPolicyType::Age => RetentionPolicy::Age,
+            RetentionPolicyType::Checkpoints => {
+                let count = config
+                    .retention_checkpoints
+                    .expect("Expected RETENTION_CHECKPOINTS to have a value");
+                assert!(count > 0, "Expected RETENTION_CHECKPOINTS to be at least 1");
+                RetentionPolicy::Checkpoints(count)
+            }
+            RetentionPolicyType::KeepAll => RetentionPolicy::KeepAll,
+        };
+
         Settings {
             jwks,
             quadratic_api_uri: config.quadratic_api_uri.to_owned(),
@@ -58,6 +85,9 @@ impl Settings {
             pubsub_processed_transactions_channel: config
                 .pubsub_processed_transactions_channel
                 .to_owned(),
+            retention_policy,
+            file_workers: config.file_workers.max(1),
+            room_lease_ms: config.room_lease_s * 1000,
         }
     }
 }
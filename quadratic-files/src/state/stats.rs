@@ -8,6 +8,9 @@ pub(crate) struct Stats {
     pub(crate) files_to_process_in_pubsub: u64,
     pub(crate) channels_to_truncate_in_pubsub: u64,
     pub(crate) last_truncated_transaction_time: Option<DateTime<Utc>>,
+    pub(crate) files_processing: u64,
+    pub(crate) files_waiting_for_worker: u64,
+    pub(crate) files_skipped_while_leased: u64,
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -18,6 +21,9 @@ pub(crate) struct StatsResponse {
     pub(crate) last_truncated_transaction_time: String,
     pub(crate) last_truncated_transaction_elapsed: String,
     pub(crate) channels_to_truncate_in_pubsub: u64,
+    pub(crate) files_processing: u64,
+    pub(crate) files_waiting_for_worker: u64,
+    pub(crate) files_skipped_while_leased: u64,
 }
 
 impl From<&Stats> for StatsResponse {
@@ -35,6 +41,9 @@ impl From<&Stats> for StatsResponse {
             last_truncated_transaction_time: to_rfc3339(stats.last_truncated_transaction_time),
             last_truncated_transaction_elapsed,
             channels_to_truncate_in_pubsub: stats.channels_to_truncate_in_pubsub,
+            files_processing: stats.files_processing,
+            files_waiting_for_worker: stats.files_waiting_for_worker,
+            files_skipped_while_leased: stats.files_skipped_while_leased,
         }
     }
 }
@@ -1,11 +1,16 @@
 use chrono::{Days, Utc};
 use std::{str::from_utf8, sync::Arc};
 
-use quadratic_rust_shared::pubsub::PubSub as PubSubTrait;
+use quadratic_rust_shared::{
+    pubsub::PubSub as PubSubTrait,
+    storage::{Storage, StorageContainer},
+};
+use uuid::Uuid;
 
 use crate::{
     error::{FilesError, Result},
-    state::State,
+    history::{Checkpoint, prunable_checkpoints},
+    state::{State, pubsub::PubSub, settings::RetentionPolicy},
 };
 
 pub(crate) fn processed_transaction_key(file_id: &str, sequence_num: &str) -> String {
@@ -53,6 +58,50 @@ pub(crate) async fn add_processed_transaction(
     add_processed_transaction_with_key(state, channel, message, "*").await
 }
 
+/// The sequence number to discard a file's transactions up to, or None if
+/// they're all kept.  `sequence_num` is the checkpoint that's old enough to
+/// be truncated, and checkpoints are sorted by sequence number.
+pub(crate) fn retained_sequence_num(
+    policy: RetentionPolicy,
+    checkpoints: &[Checkpoint],
+    sequence_num: u64,
+) -> Option<u64> {
+    match policy {
+        RetentionPolicy::Age => Some(sequence_num),
+        RetentionPolicy::Checkpoints(count) => count
+            .checked_sub(1)
+            .and_then(|nth| checkpoints.iter().rev().nth(nth as usize))
+            .map(|checkpoint| checkpoint.sequence_num.min(sequence_num)),
+        RetentionPolicy::KeepAll => None,
+    }
+}
+
+/// Remove the checkpoints before the retained sequence number from a file's
+/// history and delete their objects.  A checkpoint whose object can't be
+/// deleted stays in the history, so it's retried at the next truncation.
+/// Returns the number of checkpoints removed.
+pub(crate) async fn prune_history(
+    pubsub: &mut PubSub,
+    storage: &StorageContainer,
+    file_id: &Uuid,
+    checkpoints: &[Checkpoint],
+    retained_sequence_num: u64,
+) -> Result<usize> {
+    let mut pruned = 0;
+
+    for checkpoint in prunable_checkpoints(checkpoints, retained_sequence_num) {
+        if let Err(error) = storage.delete(&checkpoint.key).await {
+            tracing::warn!("Error deleting checkpoint {}: {error}", checkpoint.key);
+            continue;
+        }
+
+        pubsub.remove_checkpoint(file_id, checkpoint).await?;
+        pruned += 1;
+    }
+
+    Ok(pruned)
+}
+
 /// Process outstanding transactions in the queue
 pub(crate) async fn truncate_processed_transaction(
     state: &Arc<State>,
@@ -68,14 +117,36 @@ pub(crate) async fn truncate_processed_transaction(
 
     tracing::trace!("Attempting to truncate at sequence number {sequence_num} for file {file_id}");
 
-    // Redis does not trim inclusively, so we need to add a 1 to the sequence number
-    let inclusive_sequence_num = sequence_num.parse::<u64>().unwrap_or(0) + 1;
-
-    // trim the channel at the sequence number
-    pubsub
-        .connection
-        .trim(file_id, &inclusive_sequence_num.to_string())
+    let sequence_num = sequence_num.parse::<u64>().unwrap_or(0);
+    let uuid = Uuid::parse_str(file_id)?;
+    let checkpoints = match state.settings.retention_policy {
+        RetentionPolicy::KeepAll => vec![],
+        _ => pubsub.checkpoints(&uuid).await?,
+    };
+
+    // keep the transactions the retention policy needs to rebuild past versions
+    if let Some(sequence_num) =
+        retained_sequence_num(state.settings.retention_policy, &checkpoints, sequence_num)
+    {
+        // Redis does not trim inclusively, so we need to add a 1 to the sequence number
+        let inclusive_sequence_num = sequence_num + 1;
+
+        // trim the channel at the sequence number
+        pubsub
+            .connection
+            .trim(file_id, &inclusive_sequence_num.to_string())
+            .await?;
+
+        // the checkpoints before it can no longer be replayed from
+        prune_history(
+            &mut pubsub,
+            &state.settings.storage,
+            &uuid,
+            &checkpoints,
+            sequence_num,
+        )
         .await?;
+    }
 
     // trim the process transactions channel for this checkpoint
     pubsub
@@ -150,6 +221,9 @@ pub(crate) async fn truncate_processed_transactions(
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
+    use quadratic_rust_shared::storage::file_system::{FileSystem, FileSystemConfig};
+    use std::env;
     use uuid::Uuid;
 
     use super::*;
@@ -190,6 +264,68 @@ mod tests {
         add_processed_transaction_with_key(state, channel, message, &millis.to_string()).await
     }
 
+    #[test]
+    fn retains_transactions_by_policy() {
+        let file_id = Uuid::new_v4();
+        let checkpoints = [3, 6, 9]
+            .into_iter()
+            .map(|sequence_num| Checkpoint::new(file_id, sequence_num))
+            .collect::<Vec<_>>();
+
+        let retained = |policy| retained_sequence_num(policy, &checkpoints, 6);
+
+        assert_eq!(retained(RetentionPolicy::Age), Some(6));
+        assert_eq!(retained(RetentionPolicy::Checkpoints(1)), Some(6));
+        assert_eq!(retained(RetentionPolicy::Checkpoints(3)), Some(3));
+        assert_eq!(retained(RetentionPolicy::Checkpoints(4)), None);
+        assert_eq!(retained(RetentionPolicy::Checkpoints(0)), None);
+        assert_eq!(retained(RetentionPolicy::KeepAll), None);
+    }
+
+    #[tokio::test]
+    async fn prunes_history() {
+        let state = new_arc_state().await;
+        let storage = StorageContainer::FileSystem(FileSystem::new(FileSystemConfig {
+            path: env::temp_dir().to_str().unwrap().to_string(),
+            encryption_keys: vec![],
+        }));
+        let file_id = Uuid::new_v4();
+        let mut pubsub = state.pubsub.lock().await;
+
+        for sequence_num in [3, 6, 9] {
+            let checkpoint = Checkpoint::new(file_id, sequence_num);
+            storage
+                .write(&checkpoint.key, &Bytes::from_static(b"grid"))
+                .await
+                .unwrap();
+            pubsub
+                .record_checkpoint(&file_id, &checkpoint)
+                .await
+                .unwrap();
+        }
+
+        let checkpoints = pubsub.checkpoints(&file_id).await.unwrap();
+        let pruned = prune_history(&mut pubsub, &storage, &file_id, &checkpoints, 6)
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+
+        let retained = pubsub.checkpoints(&file_id).await.unwrap();
+        assert_eq!(retained, checkpoints[1..]);
+        assert!(!storage.exists(&checkpoints[0].key).await.unwrap());
+
+        for checkpoint in retained.iter() {
+            assert!(storage.exists(&checkpoint.key).await.unwrap());
+            storage.delete(&checkpoint.key).await.unwrap();
+        }
+
+        pubsub
+            .connection
+            .delete(&crate::history::history_key(&file_id))
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn truncates_files() {
         let state = new_arc_state().await;
@@ -3,7 +3,7 @@ from typing import Tuple
 import getCellsA1
 from pandas import DataFrame
 
-from ..utils import result_to_value, to_python_type_df
+from ..utils import apply_column_types, result_to_value, to_python_type_df
 
 results = None
 
@@ -171,6 +171,9 @@ class q:
             df.columns = headers
             df = df.iloc[1:].reset_index(drop=True)
 
+        # use the data table's column types (if any) for the dtypes
+        df = apply_column_types(df, getattr(result, "column_types", None))
+
         return df
 
     def pos(self) -> tuple[int, int]:
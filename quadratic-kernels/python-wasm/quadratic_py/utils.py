@@ -208,6 +208,30 @@ def to_python_type_df(value: str, type_u8: int) -> int | float | str | bool:
         return value
 
 
+# column types as per DataTableColumnType in column_type.rs
+def apply_column_types(df: pd.DataFrame, column_types) -> pd.DataFrame:
+    for index, column_type in enumerate(column_types or []):
+        if not isinstance(column_type, str) or index >= len(df.columns):
+            continue
+
+        column = df.columns[index]
+        try:
+            match column_type:
+                case "Number" | "Currency":
+                    df[column] = pd.to_numeric(df[column])
+                case "Boolean":
+                    df[column] = df[column].astype("boolean")
+                case "Date" | "DateTime":
+                    df[column] = pd.to_datetime(df[column])
+                case "Text":
+                    df[column] = df[column].astype("string")
+        except (ValueError, TypeError):
+            # values that don't fit the type are left as they are
+            pass
+
+    return df
+
+
 def detect_stringified_type(string):
     try:
         parsed_object = ast.literal_eval(string)
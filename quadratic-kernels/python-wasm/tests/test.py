@@ -12,7 +12,7 @@ import pandas as pd
 
 from inspect_python_test import *
 from process_output_test import *
-from quadratic_py.utils import attempt_fix_await, to_python_type, to_quadratic_type, CellValueType
+from quadratic_py.utils import apply_column_types, attempt_fix_await, to_python_type, to_quadratic_type, CellValueType
 
 def a1_to_xy(a1: str) -> tuple:
     # Regular expression to split letters and numbers
@@ -212,6 +212,19 @@ class TestQuadraticApi(TestCase):
         assert cells.equals(pd.DataFrame([["hello 0"]], columns=["hello 0"]))
 
 
+    def test_apply_column_types(self):
+        df = pd.DataFrame(
+            [["1", True, "2024-01-01", "a"], ["2", False, "2024-01-02", "b"]],
+            columns=["n", "b", "d", "t"],
+        )
+        df = apply_column_types(df, ["Number", "Boolean", "Date", None])
+
+        assert pd.api.types.is_numeric_dtype(df["n"])
+        assert str(df["b"].dtype) == "boolean"
+        assert pd.api.types.is_datetime64_any_dtype(df["d"])
+        assert df["t"].dtype == object
+
+
 class TestPos(IsolatedAsyncioTestCase):
     async def test_pos(self):
         result = await run_python.run_python("pos()", (0, 0, "Sheet 1"))
@@ -5,15 +5,15 @@ use uuid::Uuid;
 
 use crate::{
     error::Result,
-    get_room,
-    message::{broadcast, response::MessageResponse},
+    message::{broadcast, broadcast_local, response::MessageResponse},
     state::State,
 };
 
 /// In a separate thread:
 ///   * Process transaction queue for the room
 ///   * Broadcast sequence number to all users in the room
-///   * Check for stale users in rooms and remove them.
+///   * Check for stale users in all rooms, across all multiplayer instances,
+///     and remove them.
 #[tracing::instrument(level = "trace")]
 pub(crate) fn start(
     state: Arc<State>,
@@ -29,30 +29,42 @@ pub(crate) fn start(
             // reconnect if pubsub connection is unhealthy
             state.pubsub.lock().await.reconnect_if_unhealthy().await;
 
-            // get all room ids
-            let rooms = state
+            // get the ids of all rooms with users connected to this instance
+            let local_rooms = state
                 .rooms
                 .lock()
                 .await
                 .par_iter()
-                .map(|room| (room.file_id.to_owned(), room.checkpoint_sequence_num))
+                .map(|room| room.file_id.to_owned())
                 .collect::<Vec<_>>();
 
+            // stale users are removed from every room, so that users of an
+            // instance that went away are removed by the others
+            let rooms = match state.get_room_ids_pubsub().await {
+                Ok(rooms) => rooms,
+                Err(error) => {
+                    tracing::warn!("Error getting rooms from pubsub: {:?}", error);
+                    local_rooms.clone()
+                }
+            };
+
             let state = Arc::clone(&state);
 
             tokio::spawn(async move {
-                // parallelize the work for each room
-                for (file_id, _) in rooms.iter() {
+                // broadcast sequence number to all users in the rooms
+                // connected to this instance
+                for file_id in local_rooms.iter() {
                     tracing::trace!("Processing room {}", file_id);
 
-                    // broadcast sequence number to all users in the room
                     let broadcasted = broadcast_sequence_num(Arc::clone(&state), file_id).await;
 
                     if let Err(error) = broadcasted {
                         tracing::warn!("Error broadcasting sequence number: {:?}", error);
                     }
+                }
 
-                    // remove stale users in the room
+                // remove stale users in all rooms
+                for file_id in rooms.iter() {
                     let removed = remove_stale_users_in_room(
                         Arc::clone(&state),
                         file_id,
@@ -75,11 +87,12 @@ pub(crate) fn start(
     })
 }
 
-// broadcast sequence number to all users in the room
+// broadcast sequence number to all users in the room connected to this
+// instance, other instances do the same for their users
 async fn broadcast_sequence_num(state: Arc<State>, file_id: &Uuid) -> Result<JoinHandle<()>> {
     let sequence_num = state.get_sequence_num(file_id).await?;
 
-    Ok(broadcast(
+    Ok(broadcast_local(
         vec![],
         file_id.to_owned(),
         Arc::clone(&state),
@@ -109,7 +122,7 @@ async fn remove_stale_users_in_room(
         return Ok(None);
     }
 
-    let users = get_room!(state, file_id)?.users.to_owned();
+    let users = state.get_room(file_id).await?.users;
     let message = MessageResponse::from((users, &state.settings.version));
 
     Ok(Some(broadcast(
@@ -124,6 +137,7 @@ async fn remove_stale_users_in_room(
 mod tests {
     use quadratic_core::controller::{GridController, transaction::Transaction};
 
+    use crate::permissions::{ProtectionUpdates, load_protections};
     use crate::test_util::{add_new_user_to_room, new_arc_state, operation};
 
     use super::*;
@@ -138,11 +152,15 @@ mod tests {
         let operations_1 = operation(&mut grid, 0, 0, "1");
         let operations = Transaction::serialize_and_compress(vec![operations_1]).unwrap();
 
+        load_protections(&state, file_id).await.unwrap();
         state
-            .pubsub
-            .lock()
-            .await
-            .push(transaction_id_1, file_id, operations, 1)
+            .push_pubsub(
+                transaction_id_1,
+                file_id,
+                operations,
+                1,
+                &ProtectionUpdates::default(),
+            )
             .await
             .unwrap();
 
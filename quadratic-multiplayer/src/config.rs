@@ -27,6 +27,25 @@ pub(crate) struct Config {
     pub(crate) auth0_jwks_uri: String,
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
+
+    #[serde(default = "default_max_transaction_bytes")]
+    pub(crate) max_transaction_bytes: usize,
+    #[serde(default = "default_max_transaction_decompressed_bytes")]
+    pub(crate) max_transaction_decompressed_bytes: usize,
+    #[serde(default = "default_max_transaction_operations")]
+    pub(crate) max_transaction_operations: usize,
+}
+
+fn default_max_transaction_bytes() -> usize {
+    50 * 1024 * 1024
+}
+
+fn default_max_transaction_decompressed_bytes() -> usize {
+    100 * 1024 * 1024
+}
+
+fn default_max_transaction_operations() -> usize {
+    100_000
 }
 
 /// Load the global configuration from the environment into Config.
@@ -56,4 +75,17 @@ mod tests {
         let config = config().unwrap();
         assert_eq!(config.host, host.to_string());
     }
+
+    #[test]
+    fn defaults_transaction_limits() {
+        let config = config().unwrap();
+        assert_eq!(
+            config.max_transaction_bytes,
+            default_max_transaction_bytes()
+        );
+        assert_eq!(
+            config.max_transaction_operations,
+            default_max_transaction_operations()
+        );
+    }
 }
@@ -60,9 +60,15 @@ pub(crate) enum MpError {
     #[error("Internal server error: {0}")]
     InternalServer(String),
 
+    #[error("Transaction {0} is invalid: {1}")]
+    InvalidTransaction(Uuid, String),
+
     #[error("Requested {0} transactions but only found {1}")]
     MissingTransactions(String, String),
 
+    #[error("Transaction {0} edits a protected sheet: {1}")]
+    ProtectedEdit(Uuid, String),
+
     #[error("PubSub error: {0}")]
     PubSub(String),
 
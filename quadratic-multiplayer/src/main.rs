@@ -13,6 +13,7 @@ mod server;
 mod state;
 #[cfg(test)]
 mod test_util;
+mod validation;
 
 use error::Result;
 
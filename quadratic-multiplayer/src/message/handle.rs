@@ -6,22 +6,21 @@
 //! to all users in a room.
 
 use axum::extract::ws::{Message, WebSocket};
-use base64::{Engine, engine::general_purpose::STANDARD};
 use futures_util::stream::SplitSink;
+use quadratic_rust_shared::metrics::{METRICS, TRANSACTIONS};
 use quadratic_rust_shared::quadratic_api::{FilePermRole, get_file_perms};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::error::{ErrorLevel, MpError, Result};
-use crate::get_mut_room;
 use crate::message::response::Transaction;
 use crate::message::{
     broadcast, request::MessageRequest, response::MessageResponse, send_user_message,
 };
 use crate::permissions::{
-    validate_can_edit_or_view_file, validate_user_can_edit_file,
-    validate_user_can_edit_or_view_file,
+    load_protections, push_transaction, validate_can_edit_or_view_file,
+    validate_user_can_edit_file, validate_user_can_edit_or_view_file,
 };
 use crate::state::{
     State,
@@ -29,6 +28,7 @@ use crate::state::{
     pubsub::GROUP_NAME,
     user::{User, UserState},
 };
+use crate::validation::validate_transaction_blocking;
 
 /// Handle incoming messages.  All requests and responses are strictly typed.
 #[tracing::instrument(level = "trace")]
@@ -123,6 +123,16 @@ pub(crate) async fn handle_message(
                 .enter_room(file_id, &mut user, pre_connection, sequence_num)
                 .await?;
 
+            // load the file's sheet protections ahead of the user's edits
+            let loading = Arc::clone(&state);
+            tokio::spawn(async move {
+                if let Err(error) = load_protections(&loading, file_id).await {
+                    tracing::warn!(
+                        "Error loading the sheet protections of room {file_id}: {error}"
+                    );
+                }
+            });
+
             // direct response to user w/sequence_num after logging in
             send_user_message(
                 session_id,
@@ -177,26 +187,31 @@ pub(crate) async fn handle_message(
             // update the heartbeat
             state.update_user_heartbeat(file_id, &session_id).await?;
 
+            // decode and validate the operations before they're sequenced
+            let (operations, validated) =
+                validate_transaction_blocking(Arc::clone(&state), id, operations).await?;
+
             tracing::trace!(
                 "Transaction received for room {} from user {}, operations: {:?}",
                 file_id,
                 session_id,
-                &operations
+                &validated.operations
             );
 
-            // get and increment the room's sequence_num
-            let room_sequence_num = get_mut_room!(state, file_id)?.increment_sequence_num();
-            let decoded_operations = STANDARD.decode(&operations).map_err(|e| {
-                MpError::Serialization(format!(
-                    "Could not decode base64 encoded operations in transaction {id}: {:?}",
-                    e
-                ))
-            })?;
-
-            // add the transaction to the transaction queue
-            let sequence_num = state
-                .push_pubsub(id, file_id, decoded_operations, room_sequence_num)
-                .await?;
+            // check the operations against the file's sheet protections and
+            // add the transaction to the transaction queue under the room's
+            // next sequence_num
+            let sequence_num = push_transaction(
+                Arc::clone(&state),
+                file_id,
+                session_id,
+                id,
+                &validated.operations,
+                validated.bytes,
+            )
+            .await?;
+
+            METRICS.increment(&TRANSACTIONS, &[], 1.0);
 
             // broadcast the transaction to all users in the room
             let response = MessageResponse::Transaction {
@@ -303,9 +318,14 @@ pub(crate) async fn handle_message(
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use quadratic_core::controller::GridController;
     use quadratic_core::controller::operations::operation::Operation;
     use quadratic_core::controller::transaction::Transaction as CoreTransaction;
     use quadratic_core::grid::SheetId;
+    use quadratic_core::grid::sheet::protection::{
+        Protection, ProtectionAllowList, ProtectionTarget, SheetProtections,
+    };
     use tokio::net::TcpStream;
     use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
     use uuid::Uuid;
@@ -314,7 +334,7 @@ pub(crate) mod tests {
     use crate::message::response::MinVersion;
     use crate::state::settings::version;
     use crate::state::user::{CellEdit, UserStateUpdate};
-    use crate::test_util::{integration_test_receive, new_user, setup};
+    use crate::test_util::{integration_test_receive, new_user, operation, setup};
 
     async fn test_handle(
         socket: Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
@@ -530,6 +550,110 @@ pub(crate) mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn handle_invalid_transaction() {
+        let (_, state, _, file_id, user_1, _) = setup().await;
+        let id = Uuid::new_v4();
+        let stream = state
+            ._get_user_in_room(&file_id, &user_1.session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+
+        let request = MessageRequest::Transaction {
+            id,
+            file_id,
+            session_id: user_1.session_id,
+            operations: STANDARD.encode(b"not operations"),
+        };
+
+        let handled =
+            handle_message(request, state.clone(), stream, PreConnection::new(None)).await;
+        assert!(matches!(handled, Err(MpError::InvalidTransaction(error_id, _)) if error_id == id));
+
+        // the transaction was not assigned a sequence number
+        let sequence_num = state.get_sequence_num(&file_id).await.unwrap();
+        assert_eq!(sequence_num, 0);
+    }
+
+    #[tokio::test]
+    async fn handle_protected_transaction() {
+        let (_, state, _, file_id, user_1, _) = setup().await;
+        let session_id = user_1.session_id;
+        let stream = state
+            ._get_user_in_room(&file_id, &session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let encode = |operations: Vec<Operation>| {
+            STANDARD.encode(CoreTransaction::serialize_and_compress(operations).unwrap())
+        };
+        let request = |id: Uuid, operations: String| MessageRequest::Transaction {
+            id,
+            file_id,
+            session_id,
+            operations,
+        };
+
+        // editors can't lock a sheet
+        let lock = Operation::SetSheetProtections {
+            sheet_id,
+            protections: SheetProtections {
+                protections: vec![Protection {
+                    id: Uuid::new_v4(),
+                    target: ProtectionTarget::Sheet,
+                    allow: ProtectionAllowList::default(),
+                }],
+            },
+        };
+        let id = Uuid::new_v4();
+        let handled = handle_message(
+            request(id, encode(vec![lock.clone()])),
+            state.clone(),
+            stream.clone(),
+            PreConnection::new(None),
+        )
+        .await;
+        assert!(matches!(handled, Err(MpError::ProtectedEdit(error_id, _)) if error_id == id));
+
+        // owners can
+        let owner_perms = vec![FilePermRole::FileDelete, FilePermRole::FileEdit];
+        state
+            .update_user_permissions(file_id, &session_id, owner_perms)
+            .await
+            .unwrap();
+        handle_message(
+            request(Uuid::new_v4(), encode(vec![lock])),
+            state.clone(),
+            stream.clone(),
+            PreConnection::new(None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(state.get_sequence_num(&file_id).await.unwrap(), 1);
+
+        // once locked, editors can't change the sheet
+        let editor_perms = vec![FilePermRole::FileView, FilePermRole::FileEdit];
+        state
+            .update_user_permissions(file_id, &session_id, editor_perms)
+            .await
+            .unwrap();
+        let id = Uuid::new_v4();
+        let handled = handle_message(
+            request(id, encode(vec![operation(&mut grid, 0, 0, "1")])),
+            state.clone(),
+            stream,
+            PreConnection::new(None),
+        )
+        .await;
+        assert!(matches!(handled, Err(MpError::ProtectedEdit(error_id, _)) if error_id == id));
+        assert_eq!(state.get_sequence_num(&file_id).await.unwrap(), 1);
+    }
+
     #[tokio::test]
     async fn handle_missing_transactions() {
         let (socket, state, _, file_id, user_1, _) = setup().await;
@@ -541,10 +665,11 @@ pub(crate) mod tests {
             min_sequence_num: 1,
         };
 
-        // increment the sequence_num
-        get_mut_room!(state, file_id)
-            .unwrap()
-            .increment_sequence_num();
+        // advance the sequence_num without a transaction
+        state
+            .set_max_sequence_num_pubsub(&file_id, 1)
+            .await
+            .unwrap();
 
         let response = MessageResponse::Error {
             error: MpError::MissingTransactions("1".into(), "0".into()), // requested 1, got 0
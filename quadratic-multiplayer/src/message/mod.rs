@@ -1,13 +1,14 @@
 use axum::extract::ws::Message;
-use futures_util::SinkExt;
+use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
-use crate::error::MpError;
+use crate::error::{MpError, Result};
 use crate::message::response::MessageResponse;
 use crate::state::State;
+use crate::state::pubsub::file_id_from_broadcast_channel;
 
 pub mod handle;
 pub mod request;
@@ -48,7 +49,17 @@ pub(crate) struct UserStateUpdate {
     pub viewport: Option<String>,
 }
 
+/// A broadcast to a room, sent to every multiplayer instance through PubSub.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct BroadcastMessage {
+    pub(crate) instance_id: Uuid,
+    pub(crate) exclude: Vec<Uuid>,
+    pub(crate) message: String,
+}
+
 /// Broadcast a message to all users in a room except the sender.
+/// Users connected to this instance receive the message directly, and the
+/// message is fanned out to the other instances through PubSub.
 /// All messages are sent in a separate thread.
 #[tracing::instrument(level = "trace")]
 pub(crate) fn broadcast(
@@ -65,52 +76,140 @@ pub(crate) fn broadcast(
     );
 
     tokio::spawn(async move {
-        if let Ok(room) = state.get_room(&file_id).await {
-            let result = async {
-                let included_users = room
-                    .users
-                    .iter()
-                    .filter(|user| !exclude.contains(&user.session_id));
-
-                if included_users.clone().count() == 0 {
-                    return Ok::<_, MpError>(());
-                }
+        let result = async {
+            let serialized_message = serde_json::to_string(&message)?;
 
-                let serialized_message = serde_json::to_string(&message)?;
-
-                for user in included_users {
-                    if let Some(sender) = &user.socket {
-                        let sent = sender
-                            .lock()
-                            .await
-                            .send(Message::Text(serialized_message.clone().into()))
-                            .await
-                            .map_err(|e| MpError::SendingMessage(e.to_string()));
-
-                        if let Err(error) = sent {
-                            tracing::warn!(
-                                "Error broadcasting to user {} in room {}: {:?}",
-                                user.session_id,
-                                file_id,
-                                error,
-                            );
-
-                            // the user's socket is stale, so remove them from the room
-                            state.leave_room(file_id, &user.session_id).await?;
-                        }
-                    }
-                }
+            send_to_local_users(&state, file_id, &exclude, &serialized_message).await?;
 
-                Ok::<_, MpError>(())
+            let broadcast_message = BroadcastMessage {
+                instance_id: state.instance_id,
+                exclude,
+                message: serialized_message,
             };
+            state.broadcast_pubsub(&file_id, &broadcast_message).await?;
 
-            if let Err(e) = result.await {
-                tracing::warn!("Error broadcasting message: {:?}", e.to_string());
-            }
+            Ok::<_, MpError>(())
+        };
+
+        if let Err(e) = result.await {
+            tracing::warn!("Error broadcasting message: {:?}", e.to_string());
         }
     })
 }
 
+/// Broadcast a message to the users in a room that are connected to this
+/// instance, except the sender.
+/// All messages are sent in a separate thread.
+#[tracing::instrument(level = "trace")]
+pub(crate) fn broadcast_local(
+    exclude: Vec<Uuid>,
+    file_id: Uuid,
+    state: Arc<State>,
+    message: MessageResponse,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let result = async {
+            let serialized_message = serde_json::to_string(&message)?;
+            send_to_local_users(&state, file_id, &exclude, &serialized_message).await
+        };
+
+        if let Err(e) = result.await {
+            tracing::warn!("Error broadcasting message: {:?}", e.to_string());
+        }
+    })
+}
+
+/// Send a serialized message to the users in a room that are connected to
+/// this instance.  Users with a stale socket are removed from the room.
+async fn send_to_local_users(
+    state: &State,
+    file_id: Uuid,
+    exclude: &[Uuid],
+    serialized_message: &str,
+) -> Result<()> {
+    let included_users = state
+        .get_local_users(&file_id)
+        .await
+        .into_iter()
+        .filter(|user| !exclude.contains(&user.session_id));
+
+    for user in included_users {
+        if let Some(sender) = &user.socket {
+            let sent = sender
+                .lock()
+                .await
+                .send(Message::Text(serialized_message.to_owned().into()))
+                .await
+                .map_err(|e| MpError::SendingMessage(e.to_string()));
+
+            if let Err(error) = sent {
+                tracing::warn!(
+                    "Error broadcasting to user {} in room {}: {:?}",
+                    user.session_id,
+                    file_id,
+                    error,
+                );
+
+                // the user's socket is stale, so remove them from the room
+                state.leave_room(file_id, &user.session_id).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Listen for broadcasts from other multiplayer instances and forward them to
+/// the users connected to this instance.  The subscription is made before
+/// returning, so no broadcasts are missed once this resolves.  If the
+/// subscription drops, it is re-established.
+pub(crate) async fn listen_for_broadcasts(state: Arc<State>) -> Result<JoinHandle<()>> {
+    let mut broadcasts = state.listen_pubsub().await?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            while let Some((channel, payload)) = broadcasts.next().await {
+                if let Err(error) = receive_broadcast(&state, &channel, &payload).await {
+                    tracing::warn!("Error receiving broadcast on {channel}: {:?}", error);
+                }
+            }
+
+            tracing::warn!("Broadcast subscription dropped, resubscribing");
+
+            broadcasts = loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+
+                match state.listen_pubsub().await {
+                    Ok(broadcasts) => break broadcasts,
+                    Err(error) => {
+                        tracing::warn!("Error resubscribing to broadcasts: {:?}", error)
+                    }
+                }
+            };
+        }
+    }))
+}
+
+/// Forward a broadcast from another instance to the local users in the room.
+async fn receive_broadcast(state: &State, channel: &str, payload: &[u8]) -> Result<()> {
+    let file_id = file_id_from_broadcast_channel(channel)
+        .ok_or_else(|| MpError::Unknown(format!("Invalid broadcast channel {channel}")))?;
+    let broadcast_message = serde_json::from_slice::<BroadcastMessage>(payload)?;
+
+    // local users already received the message
+    if broadcast_message.instance_id == state.instance_id {
+        return Ok(());
+    }
+
+    send_to_local_users(
+        state,
+        file_id,
+        &broadcast_message.exclude,
+        &broadcast_message.message,
+    )
+    .await
+}
+
 /// Send a message to a specific user in a room.
 /// All messages are sent in a separate thread.
 #[tracing::instrument(level = "trace")]
@@ -122,7 +221,7 @@ pub(crate) fn send_user_message(
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         let result = async {
-            if let Ok(user) = state.get_room(&file_id).await?.get_user(&session_id) {
+            if let Some(user) = state.get_local_user(&file_id, &session_id).await {
                 if let Some(sender) = &user.socket {
                     sender
                         .lock()
@@ -142,3 +241,40 @@ pub(crate) fn send_user_message(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Mutex;
+
+    use crate::test_util::{
+        add_new_user_to_room, add_user_via_ws, integration_test_receive, integration_test_setup,
+        new_arc_state, new_user,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn broadcasts_across_instances() {
+        let state_1 = new_arc_state().await;
+        let state_2 = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+
+        // one user on each instance, only the second one has a socket
+        let user_1 = add_new_user_to_room(file_id, state_1.clone()).await;
+        let socket = Arc::new(Mutex::new(integration_test_setup(state_2.clone()).await));
+        add_user_via_ws(file_id, socket.clone(), new_user()).await;
+
+        let message = MessageResponse::CurrentTransaction { sequence_num: 7 };
+        broadcast(
+            vec![user_1.session_id],
+            file_id,
+            state_1.clone(),
+            message.clone(),
+        )
+        .await
+        .unwrap();
+
+        let received = integration_test_receive(&socket, 1).await;
+        assert_eq!(received, Some(message));
+    }
+}
@@ -1,13 +1,43 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use quadratic_rust_shared::quadratic_api::{FilePermRole, can_edit, can_view};
+use quadratic_core::{
+    Pos,
+    controller::{
+        GridController,
+        operations::operation::Operation,
+        transaction::{Transaction, TransactionServer},
+    },
+    grid::{
+        Grid, SheetId,
+        file::{import, sheet_schema::SheetSchema},
+        sheet::protection::{DataTableRects, ProtectionRole, ProtectionUser, SheetProtections},
+    },
+};
+use quadratic_rust_shared::{
+    pubsub::Sequenced,
+    quadratic_api::{
+        FilePermRole, can_edit, can_view, get_file_checkpoint, get_file_checkpoint_data,
+    },
+};
 use uuid::Uuid;
 
 use crate::{
     error::{MpError, Result},
-    state::State,
+    state::{State, user::User},
 };
 
+/// How many times a transaction is pushed before giving up, when other
+/// instances keep taking the room's next sequence number
+const MAX_PUSH_ATTEMPTS: usize = 50;
+
+/// Changes to a file's sheet protections, stored along with the transaction
+/// that makes them
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ProtectionUpdates {
+    pub(crate) set: HashMap<SheetId, SheetProtections>,
+    pub(crate) removed: Vec<SheetId>,
+}
+
 pub(crate) fn validate_can_edit_or_view_file(roles: &[FilePermRole]) -> Result<()> {
     if !(can_view(roles) || can_edit(roles)) {
         return Err(MpError::FilePermissions(
@@ -48,13 +78,322 @@ pub(crate) async fn validate_user_can_edit_file(
     validate_can_edit_file(&user.permissions)
 }
 
+/// The user as seen by sheet protections.  Users who can delete the file own
+/// it, and may edit anything and change protections.
+pub(crate) fn protection_user(user: &User) -> ProtectionUser {
+    let mut roles = vec![];
+
+    if user.permissions.contains(&FilePermRole::FileDelete) {
+        roles.push(ProtectionRole::Owner);
+    }
+
+    if can_edit(&user.permissions) {
+        roles.push(ProtectionRole::Editor);
+    }
+
+    ProtectionUser {
+        user_id: user.user_id.to_owned(),
+        roles,
+    }
+}
+
+/// The protections of a sheet added by an operation
+fn added_sheet_protections(op: &Operation) -> Option<(SheetId, SheetProtections)> {
+    match op {
+        Operation::AddSheet { sheet } => Some((sheet.id, sheet.protections.to_owned())),
+        Operation::AddSheetSchema { schema } => match schema.as_ref() {
+            SheetSchema::V1_9(schema) if !schema.protections.is_empty() => {
+                let sheet = SheetSchema::V1_9(schema.to_owned()).into_latest().ok()?;
+                Some((sheet.id, sheet.protections))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Keep the data table rects current with an operation of a transaction
+fn update_data_table_rects(tables: &mut HashMap<SheetId, DataTableRects>, op: &Operation) {
+    match op {
+        Operation::AddDataTable {
+            sheet_pos,
+            data_table,
+            ..
+        }
+        | Operation::SetDataTable {
+            sheet_pos,
+            data_table: Some(data_table),
+            ..
+        } => {
+            let pos = Pos::from(*sheet_pos);
+            tables
+                .entry(sheet_pos.sheet_id)
+                .or_default()
+                .set(pos, data_table.output_rect(pos, false));
+        }
+        Operation::SetDataTable {
+            sheet_pos,
+            data_table: None,
+            ..
+        }
+        | Operation::DeleteDataTable { sheet_pos } => {
+            if let Some(rects) = tables.get_mut(&sheet_pos.sheet_id) {
+                rects.remove(Pos::from(*sheet_pos));
+            }
+        }
+        Operation::DuplicateSheet {
+            sheet_id,
+            new_sheet_id,
+        } => {
+            if let Some(rects) = tables.get(sheet_id).cloned() {
+                tables.insert(*new_sheet_id, rects);
+            }
+        }
+        Operation::DeleteSheet { sheet_id } => {
+            tables.remove(sheet_id);
+        }
+        _ => {}
+    }
+}
+
+/// Check the operations of a transaction against the file's sheet
+/// protections.  Returns the changes the operations make to the protections.
+///
+/// Data tables are resolved with `tables`, the output rects of the file the
+/// files service last processed, kept current with the tables added and
+/// removed by the transaction.  quadratic-core also checks the table's
+/// current output before sending the transaction.
+pub(crate) fn validate_protected_edits(
+    id: Uuid,
+    user: &ProtectionUser,
+    mut protections: HashMap<SheetId, SheetProtections>,
+    mut tables: HashMap<SheetId, DataTableRects>,
+    operations: &[Operation],
+) -> Result<ProtectionUpdates> {
+    let mut updates = ProtectionUpdates::default();
+    let unprotected = SheetProtections::default();
+
+    for op in operations.iter() {
+        for (sheet_id, edit) in op.protected_edits() {
+            let sheet_tables = tables.get(&sheet_id);
+            protections
+                .get(&sheet_id)
+                .unwrap_or(&unprotected)
+                .check(user, &edit, |pos| {
+                    sheet_tables.and_then(|rects| rects.get(pos))
+                })
+                .map_err(|e| MpError::ProtectedEdit(id, e))?;
+        }
+
+        update_data_table_rects(&mut tables, op);
+
+        // keep track of changes so later operations are checked against them
+        let change = match op {
+            Operation::SetSheetProtections {
+                sheet_id,
+                protections,
+            } => Some((*sheet_id, protections.to_owned())),
+            Operation::DuplicateSheet {
+                sheet_id,
+                new_sheet_id,
+            } => protections
+                .get(sheet_id)
+                .map(|protections| (*new_sheet_id, protections.to_owned())),
+            Operation::DeleteSheet { sheet_id } => {
+                protections.remove(sheet_id);
+                updates.set.remove(sheet_id);
+                updates.removed.push(*sheet_id);
+                None
+            }
+            _ => added_sheet_protections(op),
+        };
+
+        if let Some((sheet_id, sheet_protections)) = change {
+            updates.removed.retain(|removed| *removed != sheet_id);
+            updates.set.insert(sheet_id, sheet_protections.to_owned());
+            protections.insert(sheet_id, sheet_protections);
+        }
+    }
+
+    Ok(updates)
+}
+
+/// Push a transaction under the room's next sequence number once its
+/// operations pass the file's sheet protections, returning the sequence
+/// number.  The protections are read with the sequence number, and the
+/// transaction's changes to them are stored in the same step it's pushed, so
+/// when another transaction takes the sequence number first this one is
+/// checked again against the protections it left.  The protections are
+/// loaded first if they aren't already.
+pub(crate) async fn push_transaction(
+    state: Arc<State>,
+    file_id: Uuid,
+    session_id: Uuid,
+    id: Uuid,
+    operations: &[Operation],
+    bytes: Vec<u8>,
+) -> Result<u64> {
+    let user = state.get_room(&file_id).await?.get_user(&session_id)?;
+    let user = protection_user(&user);
+
+    for _ in 0..MAX_PUSH_ATTEMPTS {
+        let (sequence_num, protections) = state.get_protections_pubsub(&file_id).await?;
+        let tables = state.get_data_table_rects_pubsub(&file_id).await?;
+        let updates = validate_protected_edits(id, &user, protections, tables, operations)?;
+        let sequence_num = sequence_num + 1;
+
+        match state
+            .push_pubsub(id, file_id, bytes.clone(), sequence_num, &updates)
+            .await?
+        {
+            Sequenced::Published => return Ok(sequence_num),
+            Sequenced::Conflict(_) => {}
+            Sequenced::NotReady => load_protections(&state, file_id).await?,
+        }
+    }
+
+    Err(MpError::PubSub(format!(
+        "Unable to sequence transaction {id} in room {file_id}"
+    )))
+}
+
+/// Load a file's sheet protections, unless they're loaded.  They're read from
+/// the file's last checkpoint with the transactions since replayed.  No
+/// transactions are pushed until the protections are loaded, so the room's
+/// sequence number holds still while they're read.
+pub(crate) async fn load_protections(state: &State, file_id: Uuid) -> Result<()> {
+    if state.protections_loaded_pubsub(&file_id).await? {
+        return Ok(());
+    }
+
+    let sequence_num = state
+        .get_sequence_num_pubsub(&file_id)
+        .await?
+        .unwrap_or_default();
+
+    let (checkpoint_sequence_num, file) = if cfg!(test) {
+        (0, None)
+    } else {
+        let url = &state.settings.quadratic_api_uri;
+        let jwt = &state.settings.m2m_auth_token;
+        let checkpoint = get_file_checkpoint(url, jwt, &file_id).await?;
+        let file = get_file_checkpoint_data(&checkpoint).await?;
+        (checkpoint.sequence_number, Some(file))
+    };
+
+    let transactions = state
+        .get_messages_from_pubsub(&file_id, checkpoint_sequence_num + 1)
+        .await?
+        .into_iter()
+        .filter(|transaction| transaction.sequence_num <= sequence_num)
+        .collect::<Vec<_>>();
+    let expected = sequence_num.saturating_sub(checkpoint_sequence_num);
+
+    if transactions.len() as u64 != expected {
+        return Err(MpError::MissingTransactions(
+            expected.to_string(),
+            transactions.len().to_string(),
+        ));
+    }
+
+    let sheets = tokio::task::spawn_blocking(move || {
+        replay_protections(file, checkpoint_sequence_num, transactions)
+    })
+    .await
+    .map_err(|e| MpError::BackgroundService(e.to_string()))??;
+
+    let mut protections = HashMap::new();
+    for (sheet_id, sheet_protections, rects) in sheets {
+        state
+            .set_data_table_rects_pubsub(&file_id, &sheet_id, &rects)
+            .await?;
+        protections.insert(sheet_id, sheet_protections);
+    }
+
+    if state
+        .initialize_protections_pubsub(&file_id, sequence_num, &protections)
+        .await?
+    {
+        tracing::info!("Loaded the sheet protections of room {file_id} at {sequence_num}");
+    }
+
+    Ok(())
+}
+
+/// Replay transactions on a checkpoint's file, returning the protections and
+/// data table rects of its protected sheets.  Without a file, as in tests,
+/// they're replayed on a test grid.
+fn replay_protections(
+    file: Option<Vec<u8>>,
+    checkpoint_sequence_num: u64,
+    transactions: Vec<TransactionServer>,
+) -> Result<Vec<(SheetId, SheetProtections, DataTableRects)>> {
+    let grid = match file {
+        Some(file) => import(file).map_err(|e| MpError::Serialization(e.to_string()))?,
+        None => Grid::test(),
+    };
+    let mut grid = GridController::from_grid(grid, checkpoint_sequence_num);
+
+    for transaction in transactions {
+        let operations =
+            Transaction::decompress_and_deserialize::<Vec<Operation>>(&transaction.operations)
+                .map_err(|e| MpError::Serialization(e.to_string()))?;
+        grid.server_apply_transaction(operations, None);
+    }
+
+    let sheets = grid
+        .grid()
+        .sheets()
+        .iter()
+        .filter(|sheet| !sheet.protections.is_empty())
+        .map(|sheet| {
+            (
+                sheet.id,
+                sheet.protections.to_owned(),
+                sheet.data_table_rects(),
+            )
+        })
+        .collect();
+
+    Ok(sheets)
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
 
-    use crate::test_util::setup;
+    use quadratic_core::{
+        CellValue, Rect, SheetPos,
+        cell_values::CellValues,
+        grid::sheet::protection::{Protection, ProtectionAllowList, ProtectionTarget},
+    };
+
+    use quadratic_rust_shared::pubsub::{PubSub, protections_key, protections_loaded_key};
+
+    use crate::test_util::{add_new_user_to_room, new_arc_state, new_user, setup};
 
     use super::*;
 
+    fn locked_sheet() -> SheetProtections {
+        SheetProtections {
+            protections: vec![Protection {
+                id: Uuid::new_v4(),
+                target: ProtectionTarget::Sheet,
+                allow: ProtectionAllowList::default(),
+            }],
+        }
+    }
+
+    fn set_value(sheet_id: SheetId) -> Operation {
+        Operation::SetCellValues {
+            sheet_pos: SheetPos {
+                x: 1,
+                y: 1,
+                sheet_id,
+            },
+            values: CellValues::from(CellValue::Text("1".into())),
+        }
+    }
+
     #[tokio::test]
     async fn validates_can_edit_or_view_file() {
         let roles = vec![FilePermRole::FileView, FilePermRole::FileEdit];
@@ -144,4 +483,258 @@ pub(crate) mod tests {
         let result = validate_user_can_edit_file(state.clone(), file_id, session_id).await;
         assert!(result.is_err());
     }
+
+    #[test]
+    fn maps_file_permissions_to_protection_roles() {
+        let mut user = new_user();
+
+        user.permissions = vec![FilePermRole::FileView, FilePermRole::FileEdit];
+        assert_eq!(protection_user(&user).roles, vec![ProtectionRole::Editor]);
+
+        user.permissions = vec![FilePermRole::FileDelete, FilePermRole::FileEdit];
+        assert!(protection_user(&user).is_owner());
+    }
+
+    #[test]
+    fn validates_protected_edits() {
+        let id = Uuid::new_v4();
+        let sheet_id = SheetId::new();
+        let new_sheet_id = SheetId::new();
+        let owner = ProtectionUser {
+            user_id: "owner".into(),
+            roles: vec![ProtectionRole::Owner, ProtectionRole::Editor],
+        };
+        let editor = ProtectionUser {
+            user_id: "editor".into(),
+            roles: vec![ProtectionRole::Editor],
+        };
+        let is_protected = |result: Result<ProtectionUpdates>| match result {
+            Err(MpError::ProtectedEdit(error_id, _)) => error_id == id,
+            _ => false,
+        };
+
+        // editors can't change protections
+        let lock = Operation::SetSheetProtections {
+            sheet_id,
+            protections: locked_sheet(),
+        };
+        let result =
+            validate_protected_edits(id, &editor, HashMap::new(), HashMap::new(), &[lock.clone()]);
+        assert!(is_protected(result));
+
+        // an owner locks the sheet, then edits it in the same transaction
+        let operations = [lock, set_value(sheet_id)];
+        let updates =
+            validate_protected_edits(id, &owner, HashMap::new(), HashMap::new(), &operations)
+                .unwrap();
+        assert!(updates.set.contains_key(&sheet_id));
+
+        // editors can't edit the locked sheet, or a duplicate of it
+        let protections = updates.set;
+        let result = validate_protected_edits(
+            id,
+            &editor,
+            protections.clone(),
+            HashMap::new(),
+            &[set_value(sheet_id)],
+        );
+        assert!(is_protected(result));
+
+        let operations = [
+            Operation::DuplicateSheet {
+                sheet_id,
+                new_sheet_id,
+            },
+            set_value(new_sheet_id),
+        ];
+        let result = validate_protected_edits(
+            id,
+            &editor,
+            protections.clone(),
+            HashMap::new(),
+            &operations,
+        );
+        assert!(is_protected(result));
+
+        // owners can delete the sheet, which removes its protections
+        let operations = [Operation::DeleteSheet { sheet_id }];
+        let updates =
+            validate_protected_edits(id, &owner, protections, HashMap::new(), &operations).unwrap();
+        assert_eq!(updates.removed, vec![sheet_id]);
+        assert!(updates.set.is_empty());
+    }
+
+    #[test]
+    fn validates_edits_inside_protected_data_tables() {
+        let id = Uuid::new_v4();
+        let sheet_id = SheetId::new();
+        let editor = ProtectionUser {
+            user_id: "editor".into(),
+            roles: vec![ProtectionRole::Editor],
+        };
+        let protections = HashMap::from([(
+            sheet_id,
+            SheetProtections {
+                protections: vec![Protection {
+                    id: Uuid::new_v4(),
+                    target: ProtectionTarget::DataTable(Pos { x: 3, y: 3 }),
+                    allow: ProtectionAllowList::default(),
+                }],
+            },
+        )]);
+        let tables = HashMap::from([(
+            sheet_id,
+            DataTableRects {
+                tables: vec![(Pos { x: 3, y: 3 }, Rect::new(3, 3, 4, 6))],
+            },
+        )]);
+        let set_value_at = |x, y| Operation::SetCellValues {
+            sheet_pos: SheetPos { x, y, sheet_id },
+            values: CellValues::from(CellValue::Text("1".into())),
+        };
+
+        // a cell within the table's output is protected
+        let operations = [set_value_at(4, 5)];
+        let result = validate_protected_edits(
+            id,
+            &editor,
+            protections.clone(),
+            tables.clone(),
+            &operations,
+        );
+        assert!(matches!(result, Err(MpError::ProtectedEdit(..))));
+
+        // only the anchor is known without the table's rect
+        let result = validate_protected_edits(
+            id,
+            &editor,
+            protections.clone(),
+            HashMap::new(),
+            &operations,
+        );
+        assert!(result.is_ok());
+
+        let operations = [set_value_at(5, 5)];
+        let result = validate_protected_edits(id, &editor, protections, tables, &operations);
+        assert!(result.is_ok());
+    }
+
+    async fn push(
+        state: &Arc<State>,
+        file_id: Uuid,
+        user: &User,
+        operations: Vec<Operation>,
+    ) -> Result<u64> {
+        let bytes = Transaction::serialize_and_compress(&operations).unwrap();
+        let id = Uuid::new_v4();
+
+        push_transaction(
+            Arc::clone(state),
+            file_id,
+            user.session_id,
+            id,
+            &operations,
+            bytes,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn loads_protections_before_pushing_transactions() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let user = add_new_user_to_room(file_id, state.clone()).await;
+        let sheet_id = SheetId::TEST;
+        let locked = locked_sheet();
+
+        assert!(!state.protections_loaded_pubsub(&file_id).await.unwrap());
+
+        // the owner locks the sheet, once the protections are loaded
+        let owner_perms = vec![FilePermRole::FileDelete, FilePermRole::FileEdit];
+        state
+            .update_user_permissions(file_id, &user.session_id, owner_perms)
+            .await
+            .unwrap();
+        let lock = Operation::SetSheetProtections {
+            sheet_id,
+            protections: locked.clone(),
+        };
+        assert_eq!(push(&state, file_id, &user, vec![lock]).await.unwrap(), 1);
+        assert!(state.protections_loaded_pubsub(&file_id).await.unwrap());
+
+        // the lock is stored with the transaction
+        let (sequence_num, protections) = state.get_protections_pubsub(&file_id).await.unwrap();
+        assert_eq!(sequence_num, 1);
+        assert_eq!(protections.get(&sheet_id), Some(&locked));
+
+        // lose the protections, as when redis is flushed
+        {
+            let mut pubsub = state.pubsub.lock().await;
+            let keys = [protections_key(&file_id), protections_loaded_key(&file_id)];
+            for key in keys {
+                pubsub.connection.delete(&key).await.unwrap();
+            }
+        }
+
+        // they're loaded again, with the lock, before an editor's change
+        let editor_perms = vec![FilePermRole::FileView, FilePermRole::FileEdit];
+        state
+            .update_user_permissions(file_id, &user.session_id, editor_perms)
+            .await
+            .unwrap();
+        let result = push(&state, file_id, &user, vec![set_value(sheet_id)]).await;
+        assert!(matches!(result, Err(MpError::ProtectedEdit(..))));
+        assert_eq!(state.get_sequence_num(&file_id).await.unwrap(), 1);
+
+        let (_, protections) = state.get_protections_pubsub(&file_id).await.unwrap();
+        assert_eq!(protections.get(&sheet_id), Some(&locked));
+    }
+
+    #[tokio::test]
+    async fn pushes_concurrent_transactions_without_gaps() {
+        let states = [new_arc_state().await, new_arc_state().await];
+        let file_id = Uuid::new_v4();
+        let mut users = vec![];
+        for state in states.iter() {
+            users.push(add_new_user_to_room(file_id, state.clone()).await);
+        }
+        let transactions_per_instance = 20;
+
+        let handles = states
+            .iter()
+            .zip(users.iter())
+            .flat_map(|(state, user)| {
+                (0..transactions_per_instance).map(move |_| {
+                    let state = Arc::clone(state);
+                    let session_id = user.session_id;
+                    tokio::spawn(async move {
+                        push_transaction(state, file_id, session_id, Uuid::new_v4(), &[], vec![])
+                            .await
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut sequence_nums = vec![];
+        for handle in handles {
+            sequence_nums.push(handle.await.unwrap().unwrap());
+        }
+        sequence_nums.sort();
+
+        let expected = (1..=2 * transactions_per_instance).collect::<Vec<u64>>();
+        assert_eq!(sequence_nums, expected);
+
+        let transactions = states[0]
+            .get_messages_from_pubsub(&file_id, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|transaction| transaction.sequence_num)
+            .collect::<Vec<_>>();
+        assert_eq!(transactions, expected);
+        assert_eq!(
+            states[1].get_sequence_num(&file_id).await.unwrap(),
+            2 * transactions_per_instance
+        );
+    }
 }
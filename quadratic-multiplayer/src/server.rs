@@ -9,7 +9,7 @@ use axum::{
         connect_info::ConnectInfo,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
-    http::StatusCode,
+    http::{StatusCode, header::CONTENT_TYPE},
     response::IntoResponse,
     routing::get,
 };
@@ -18,6 +18,7 @@ use futures::stream::StreamExt;
 use futures_util::SinkExt;
 use futures_util::stream::SplitSink;
 use quadratic_rust_shared::auth::jwt::{authorize, get_jwks};
+use quadratic_rust_shared::metrics::{self, CONNECTED_USERS, METRICS, ROOMS};
 use serde::{Deserialize, Serialize};
 use std::ops::ControlFlow;
 use std::{net::SocketAddr, sync::Arc};
@@ -31,7 +32,8 @@ use crate::{
     error::{ErrorLevel, MpError, Result},
     health::{full_healthcheck, healthcheck},
     message::{
-        broadcast, handle::handle_message, request::MessageRequest, response::MessageResponse,
+        broadcast, handle::handle_message, listen_for_broadcasts, request::MessageRequest,
+        response::MessageResponse,
     },
     state::{State, connection::PreConnection},
 };
@@ -56,6 +58,9 @@ pub(crate) fn app(state: Arc<State>) -> Router {
         // full healthcheck
         .route("/health/full", get(full_healthcheck))
         //
+        // prometheus metrics
+        .route("/metrics", get(metrics))
+        //
         // state
         .layer(Extension(state))
         //
@@ -66,6 +71,16 @@ pub(crate) fn app(state: Arc<State>) -> Router {
         )
 }
 
+pub(crate) async fn metrics(Extension(state): Extension<Arc<State>>) -> impl IntoResponse {
+    let rooms = state.rooms.lock().await.len();
+    let connected_users = state.connections.lock().await.len();
+
+    METRICS.set(&ROOMS, &[], rooms as f64);
+    METRICS.set(&CONNECTED_USERS, &[], connected_users as f64);
+
+    ([(CONTENT_TYPE, metrics::CONTENT_TYPE)], METRICS.render())
+}
+
 /// Start the websocket server.  This is the entrypoint for the application.
 #[tracing::instrument(level = "trace")]
 pub(crate) async fn serve() -> Result<()> {
@@ -102,6 +117,9 @@ pub(crate) async fn serve() -> Result<()> {
         tracing::warn!("JWT authentication is disabled");
     }
 
+    // forward broadcasts from other multiplayer instances to our users
+    listen_for_broadcasts(Arc::clone(&state)).await?;
+
     // perform various activities in a separate thread
     background_worker::start(
         Arc::clone(&state),
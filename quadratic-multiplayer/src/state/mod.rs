@@ -27,6 +27,9 @@ use self::pubsub::PubSub;
 
 #[derive(Debug)]
 pub(crate) struct State {
+    /// Identifies this multiplayer instance in broadcasts to other instances
+    pub(crate) instance_id: Uuid,
+    /// Rooms with users connected to this instance
     pub(crate) rooms: Mutex<DashMap<Uuid, Room>>,
     pub(crate) connections: Mutex<HashMap<Uuid, Connection>>,
     pub(crate) pubsub: Mutex<PubSub>,
@@ -43,6 +46,7 @@ impl State {
         });
 
         Ok(State {
+            instance_id: Uuid::new_v4(),
             rooms: Mutex::new(DashMap::new()),
             connections: Mutex::new(HashMap::new()),
             pubsub: Mutex::new(PubSub::new(pubsub_config).await?),
@@ -1,15 +1,78 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
 use quadratic_core::controller::transaction::{Transaction, TransactionServer};
+use quadratic_core::grid::{
+    SheetId,
+    sheet::protection::{DataTableRects, SheetProtections},
+};
 use quadratic_rust_shared::pubsub::{
-    Config as PubSubConfig, PubSub as PubSubTrait, redis_streams::RedisConnection,
+    Broadcasts, Config as PubSubConfig, HashUpdates, PubSub as PubSubTrait, Sequenced,
+    data_table_rects_key, protections_key, protections_loaded_key, redis_streams::RedisConnection,
 };
+use serde::de::DeserializeOwned;
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
+use crate::message::BroadcastMessage;
+use crate::permissions::ProtectionUpdates;
 
 use super::State;
+use super::user::User;
 
 pub static GROUP_NAME: &str = "quadratic-multiplayer-1";
 
+/// Prefix of the keys that hold a room's users, user index and sequence number
+pub static ROOM_PREFIX: &str = "multiplayer:room";
+
+/// Prefix of the channels that broadcasts to a room are sent on
+pub static BROADCAST_PREFIX: &str = "multiplayer:broadcast";
+
+fn room_users_key(file_id: &Uuid) -> String {
+    format!("{ROOM_PREFIX}:{file_id}:users")
+}
+
+/// Extract the file id from the key of a room's users
+fn file_id_from_room_users_key(key: &str) -> Option<Uuid> {
+    key.strip_prefix(ROOM_PREFIX)
+        .and_then(|key| key.strip_prefix(':'))
+        .and_then(|key| key.strip_suffix(":users"))
+        .and_then(|file_id| Uuid::parse_str(file_id).ok())
+}
+
+fn room_user_index_key(file_id: &Uuid) -> String {
+    format!("{ROOM_PREFIX}:{file_id}:user_index")
+}
+
+fn room_sequence_num_key(file_id: &Uuid) -> String {
+    format!("{ROOM_PREFIX}:{file_id}:sequence_num")
+}
+
+/// Parse a hash of json-encoded values by sheet id
+fn by_sheet<T: DeserializeOwned>(values: Vec<(String, Vec<u8>)>) -> Result<HashMap<SheetId, T>> {
+    values
+        .into_iter()
+        .map(|(sheet_id, value)| {
+            let sheet_id =
+                SheetId::from_str(&sheet_id).map_err(|e| MpError::Serialization(e.to_string()))?;
+            let value = serde_json::from_slice::<T>(&value)?;
+            Ok((sheet_id, value))
+        })
+        .collect()
+}
+
+fn broadcast_channel(file_id: &Uuid) -> String {
+    format!("{BROADCAST_PREFIX}:{file_id}")
+}
+
+/// Extract the file id from a broadcast channel
+pub(crate) fn file_id_from_broadcast_channel(channel: &str) -> Option<Uuid> {
+    channel
+        .strip_prefix(BROADCAST_PREFIX)
+        .and_then(|file_id| file_id.strip_prefix(':'))
+        .and_then(|file_id| Uuid::parse_str(file_id).ok())
+}
+
 #[derive(Debug)]
 pub(crate) struct PubSub {
     pub(crate) config: PubSubConfig,
@@ -31,13 +94,19 @@ impl PubSub {
         Ok(connection)
     }
 
+    /// Push a transaction under a sequence number, along with the changes it
+    /// makes to the file's sheet protections.  It's only added if the room's
+    /// sequence number is at `sequence_num - 1` and the protections are
+    /// loaded, in which case the room's sequence number is set to
+    /// `sequence_num` and the protections updated in the same step.
     pub(crate) async fn push(
         &mut self,
         id: Uuid,
         file_id: Uuid,
         operations: Vec<u8>,
         sequence_num: u64,
-    ) -> Result<u64> {
+        updates: &ProtectionUpdates,
+    ) -> Result<Sequenced> {
         let transaction = TransactionServer {
             id,
             file_id,
@@ -52,16 +121,37 @@ impl PubSub {
             _ => "active_channels",
         };
 
-        self.connection
-            .publish(
+        let set = updates
+            .set
+            .iter()
+            .map(|(sheet_id, protections)| {
+                Ok((sheet_id.to_string(), serde_json::to_vec(protections)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let removed = updates
+            .removed
+            .iter()
+            .map(|sheet_id| sheet_id.to_string())
+            .collect::<Vec<_>>();
+
+        let pushed = self
+            .connection
+            .publish_sequenced(
                 &file_id.to_string(),
-                &sequence_num.to_string(),
+                &room_sequence_num_key(&file_id),
+                sequence_num,
                 &transaction_compressed,
                 Some(active_channels),
+                Some(HashUpdates {
+                    key: &protections_key(&file_id),
+                    ready_key: &protections_loaded_key(&file_id),
+                    set: &set,
+                    removed: &removed,
+                }),
             )
             .await?;
 
-        Ok(sequence_num)
+        Ok(pushed)
     }
 
     /// Check if the connection is healthy and attempt to reconnect if not
@@ -97,18 +187,20 @@ impl State {
         Ok(())
     }
 
-    /// Push a transaction to the transaction queue
+    /// Push a transaction to the transaction queue under a sequence number,
+    /// along with the changes it makes to the file's sheet protections
     pub(crate) async fn push_pubsub(
         &self,
         id: Uuid,
         file_id: Uuid,
         operations: Vec<u8>,
         sequence_num: u64,
-    ) -> Result<u64> {
+        updates: &ProtectionUpdates,
+    ) -> Result<Sequenced> {
         self.pubsub
             .lock()
             .await
-            .push(id, file_id, operations, sequence_num)
+            .push(id, file_id, operations, sequence_num, updates)
             .await
     }
 
@@ -146,6 +238,274 @@ impl State {
         Ok((message.0, Self::decompress_and_deserialize(message.1)?))
     }
 
+    /// Get all users in a room, across all multiplayer instances
+    pub(crate) async fn get_users_pubsub(&self, file_id: &Uuid) -> Result<Vec<User>> {
+        let users = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .hash_get_all(&room_users_key(file_id))
+            .await?
+            .into_iter()
+            .filter_map(|(session_id, user)| match User::from_stored(&user) {
+                Ok(user) => Some(user),
+                Err(error) => {
+                    tracing::warn!("Error reading user {session_id} in room {file_id}: {error}");
+                    None
+                }
+            })
+            .collect();
+
+        Ok(users)
+    }
+
+    /// Get a user in a room
+    pub(crate) async fn get_user_pubsub(
+        &self,
+        file_id: &Uuid,
+        session_id: &Uuid,
+    ) -> Result<Option<User>> {
+        self.pubsub
+            .lock()
+            .await
+            .connection
+            .hash_get(&room_users_key(file_id), &session_id.to_string())
+            .await?
+            .map(|user| User::from_stored(&user))
+            .transpose()
+    }
+
+    /// Add or update a user in a room.  Returns true if the user is new.
+    pub(crate) async fn set_user_pubsub(&self, file_id: &Uuid, user: &User) -> Result<bool> {
+        let stored = user.to_stored()?;
+        let is_new = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .hash_set(
+                &room_users_key(file_id),
+                &user.session_id.to_string(),
+                &stored,
+            )
+            .await?;
+
+        Ok(is_new)
+    }
+
+    /// Remove a user from a room.  The room's users and user index are
+    /// deleted with the last user, atomically, so a user entering the room at
+    /// the same time isn't lost.  The sequence number is kept so that it keeps
+    /// increasing if the room is opened again.  Returns the number of users
+    /// left.
+    pub(crate) async fn remove_user_pubsub(
+        &self,
+        file_id: &Uuid,
+        session_id: &Uuid,
+    ) -> Result<usize> {
+        let remaining = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .hash_remove(
+                &room_users_key(file_id),
+                &session_id.to_string(),
+                &[&room_user_index_key(file_id)],
+            )
+            .await?;
+
+        Ok(remaining)
+    }
+
+    /// Get the ids of all rooms with users, across all multiplayer instances
+    pub(crate) async fn get_room_ids_pubsub(&self) -> Result<Vec<Uuid>> {
+        let room_ids = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .keys(&format!("{ROOM_PREFIX}:*:users"))
+            .await?
+            .iter()
+            .filter_map(|key| file_id_from_room_users_key(key))
+            .collect();
+
+        Ok(room_ids)
+    }
+
+    /// Get the next user index in a room
+    pub(crate) async fn next_user_index_pubsub(&self, file_id: &Uuid) -> Result<usize> {
+        let index = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .increment(&room_user_index_key(file_id))
+            .await?;
+
+        Ok(index.saturating_sub(1) as usize)
+    }
+
+    /// Get the sequence number of a room, if it has one
+    pub(crate) async fn get_sequence_num_pubsub(&self, file_id: &Uuid) -> Result<Option<u64>> {
+        let sequence_num = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .counter(&room_sequence_num_key(file_id))
+            .await?;
+
+        Ok(sequence_num)
+    }
+
+    /// Raise the sequence number of a room if it's lower.  Returns the
+    /// resulting sequence number.
+    pub(crate) async fn set_max_sequence_num_pubsub(
+        &self,
+        file_id: &Uuid,
+        sequence_num: u64,
+    ) -> Result<u64> {
+        let sequence_num = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .set_max(&room_sequence_num_key(file_id), sequence_num)
+            .await?;
+
+        Ok(sequence_num)
+    }
+
+    /// Get a room's sequence number and the sheet protections of its file as
+    /// of that sequence number.  Unlike a room's users, the protections are
+    /// kept when the room empties.
+    pub(crate) async fn get_protections_pubsub(
+        &self,
+        file_id: &Uuid,
+    ) -> Result<(u64, HashMap<SheetId, SheetProtections>)> {
+        let (sequence_num, protections) = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .counter_and_hash(&room_sequence_num_key(file_id), &protections_key(file_id))
+            .await?;
+
+        Ok((sequence_num.unwrap_or_default(), by_sheet(protections)?))
+    }
+
+    /// Check if a file's sheet protections are loaded
+    pub(crate) async fn protections_loaded_pubsub(&self, file_id: &Uuid) -> Result<bool> {
+        let loaded = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .counter(&protections_loaded_key(file_id))
+            .await?;
+
+        Ok(loaded.is_some())
+    }
+
+    /// Load a file's sheet protections as of a sequence number, unless
+    /// they're already loaded.  Returns true if they were loaded.
+    pub(crate) async fn initialize_protections_pubsub(
+        &self,
+        file_id: &Uuid,
+        sequence_num: u64,
+        protections: &HashMap<SheetId, SheetProtections>,
+    ) -> Result<bool> {
+        let values = protections
+            .iter()
+            .map(|(sheet_id, protections)| {
+                Ok((sheet_id.to_string(), serde_json::to_vec(protections)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let initialized = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .hash_initialize(
+                &protections_key(file_id),
+                &protections_loaded_key(file_id),
+                sequence_num,
+                &values,
+            )
+            .await?;
+
+        Ok(initialized)
+    }
+
+    /// Get the output rects of the data tables of a file's protected sheets,
+    /// as of the last time they were loaded or the files service processed
+    /// the file
+    pub(crate) async fn get_data_table_rects_pubsub(
+        &self,
+        file_id: &Uuid,
+    ) -> Result<HashMap<SheetId, DataTableRects>> {
+        let rects = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .hash_get_all(&data_table_rects_key(file_id))
+            .await?;
+
+        by_sheet(rects)
+    }
+
+    /// Set the output rects of the data tables of a protected sheet
+    pub(crate) async fn set_data_table_rects_pubsub(
+        &self,
+        file_id: &Uuid,
+        sheet_id: &SheetId,
+        rects: &DataTableRects,
+    ) -> Result<()> {
+        let rects = serde_json::to_vec(rects)?;
+        self.pubsub
+            .lock()
+            .await
+            .connection
+            .hash_set(
+                &data_table_rects_key(file_id),
+                &sheet_id.to_string(),
+                &rects,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Send a broadcast to every multiplayer instance
+    pub(crate) async fn broadcast_pubsub(
+        &self,
+        file_id: &Uuid,
+        message: &BroadcastMessage,
+    ) -> Result<()> {
+        let message = serde_json::to_vec(message)?;
+        self.pubsub
+            .lock()
+            .await
+            .connection
+            .broadcast(&broadcast_channel(file_id), &message)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Listen for broadcasts to all rooms, from every multiplayer instance
+    pub(crate) async fn listen_pubsub(&self) -> Result<Broadcasts> {
+        let config = self.pubsub.lock().await.config.to_owned();
+        let broadcasts = RedisConnection::listen(config, &format!("{BROADCAST_PREFIX}:*")).await?;
+
+        Ok(broadcasts)
+    }
+
     fn decompress_and_deserialize(transaction: Vec<u8>) -> Result<TransactionServer> {
         Transaction::decompress_and_deserialize::<TransactionServer>(&transaction)
             .map_err(|e| MpError::Serialization(e.to_string()))
@@ -156,12 +516,14 @@ impl State {
 mod tests {
     use quadratic_core::controller::GridController;
 
+    use crate::permissions::load_protections;
     use crate::test_util::{operation, setup};
 
     use super::*;
     #[tokio::test]
     async fn all_pubsub_functionality() {
         let (_, state, _, file_id, _, _) = setup().await;
+        load_protections(&state, file_id).await.unwrap();
         let mut grid = GridController::test();
         let transaction_id_1 = Uuid::new_v4();
         let operations_1 = operation(&mut grid, 0, 0, "1");
@@ -172,10 +534,17 @@ mod tests {
         let transaction_2 =
             Transaction::serialize_and_compress(vec![operations_2.clone()]).unwrap();
 
-        state
-            .push_pubsub(transaction_id_1, file_id, transaction_1.clone(), 1)
+        let pushed = state
+            .push_pubsub(
+                transaction_id_1,
+                file_id,
+                transaction_1.clone(),
+                1,
+                &ProtectionUpdates::default(),
+            )
             .await
             .unwrap();
+        assert_eq!(pushed, Sequenced::Published);
         let transactions = state.get_messages_from_pubsub(&file_id, 0).await.unwrap();
         let expected_transaction_1 = TransactionServer {
             id: transaction_id_1,
@@ -186,10 +555,17 @@ mod tests {
 
         assert_eq!(transactions[0], expected_transaction_1);
 
-        state
-            .push_pubsub(transaction_id_2, file_id, transaction_2.clone(), 2)
+        let pushed = state
+            .push_pubsub(
+                transaction_id_2,
+                file_id,
+                transaction_2.clone(),
+                2,
+                &ProtectionUpdates::default(),
+            )
             .await
             .unwrap();
+        assert_eq!(pushed, Sequenced::Published);
         let transaction = state.get_messages_from_pubsub(&file_id, 0).await.unwrap();
         let expected_transaction_2 = TransactionServer {
             id: transaction_id_2,
@@ -206,4 +582,34 @@ mod tests {
         assert_eq!(last_transaction.0, "2".to_string());
         assert_eq!(last_transaction.1, expected_transaction_2);
     }
+
+    #[test]
+    fn parses_broadcast_channels() {
+        let file_id = Uuid::new_v4();
+
+        assert_eq!(
+            file_id_from_broadcast_channel(&broadcast_channel(&file_id)),
+            Some(file_id)
+        );
+        assert_eq!(
+            file_id_from_broadcast_channel(&format!("{BROADCAST_PREFIX}{file_id}")),
+            None
+        );
+        assert_eq!(file_id_from_broadcast_channel("invalid"), None);
+    }
+
+    #[test]
+    fn parses_room_users_keys() {
+        let file_id = Uuid::new_v4();
+
+        assert_eq!(
+            file_id_from_room_users_key(&room_users_key(&file_id)),
+            Some(file_id)
+        );
+        assert_eq!(
+            file_id_from_room_users_key(&room_user_index_key(&file_id)),
+            None
+        );
+        assert_eq!(file_id_from_room_users_key("invalid"), None);
+    }
 }
@@ -1,11 +1,22 @@
+//! Rooms
+//!
+//! Room membership, user indices and the sequence number of a room live in
+//! PubSub so that any multiplayer instance can serve any file.  Each instance
+//! also keeps a local copy of the users that are connected to it, along with
+//! their sockets.
+
+use std::collections::HashMap;
+
 use dashmap::DashMap;
 use quadratic_rust_shared::quadratic_api::get_file_checkpoint;
 use serde::Serialize;
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
-use crate::state::{State, user::User};
-use crate::{get_mut_room, get_room};
+use crate::state::{
+    State,
+    user::{User, UserSocket},
+};
 
 use super::connection::{Connection, PreConnection};
 
@@ -14,8 +25,6 @@ pub(crate) struct Room {
     pub(crate) file_id: Uuid,
     pub(crate) users: DashMap<Uuid, User>,
     pub(crate) sequence_num: u64,
-    pub(crate) checkpoint_sequence_num: u64,
-    pub(crate) user_index: usize,
 }
 
 #[cfg(test)]
@@ -33,16 +42,9 @@ impl Room {
             file_id,
             users: DashMap::new(),
             sequence_num,
-            checkpoint_sequence_num: sequence_num,
-            user_index: 0,
         }
     }
 
-    pub fn increment_sequence_num(&mut self) -> u64 {
-        self.sequence_num += 1;
-        self.sequence_num
-    }
-
     pub fn get_user(&self, session_id: &Uuid) -> Result<User> {
         let user = self
             .users
@@ -51,26 +53,65 @@ impl Room {
 
         Ok(user.to_owned())
     }
-
-    /// Gets the next user index and increments the user index to prepare for the next user.
-    pub fn user_index_increment(&mut self) -> usize {
-        let index = self.user_index;
-        self.user_index += 1;
-        index
-    }
 }
 
 impl State {
-    /// Retrieves a copy of a room.
+    /// Retrieves a copy of a room, including users connected to other
+    /// multiplayer instances.  Only users connected to this instance have a
+    /// socket.
     pub(crate) async fn get_room(&self, file_id: &Uuid) -> Result<Room> {
-        let room = get_room!(self, file_id)?.to_owned();
+        let users = self.get_users_pubsub(file_id).await?;
+
+        if users.is_empty() {
+            return Err(MpError::RoomNotFound(file_id.to_string()));
+        }
+
+        let sequence_num = self.get_sequence_num_pubsub(file_id).await?.unwrap_or(0);
+        let mut sockets = self.get_local_sockets(file_id).await;
+        let room = Room::new(*file_id, sequence_num);
+
+        for mut user in users {
+            user.socket = sockets.remove(&user.session_id);
+            room.users.insert(user.session_id, user);
+        }
 
         Ok(room)
     }
 
+    /// Retrieves a copy of the users in a room that are connected to this
+    /// multiplayer instance.
+    pub(crate) async fn get_local_users(&self, file_id: &Uuid) -> Vec<User> {
+        self.rooms
+            .lock()
+            .await
+            .get(file_id)
+            .map(|room| room.users.iter().map(|user| user.to_owned()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Retrieves a copy of a user in a room if they are connected to this
+    /// multiplayer instance.
+    pub(crate) async fn get_local_user(&self, file_id: &Uuid, session_id: &Uuid) -> Option<User> {
+        self.rooms
+            .lock()
+            .await
+            .get(file_id)
+            .and_then(|room| room.users.get(session_id).map(|user| user.to_owned()))
+    }
+
+    /// Retrieves the sockets of the users in a room that are connected to
+    /// this multiplayer instance.
+    async fn get_local_sockets(&self, file_id: &Uuid) -> HashMap<Uuid, UserSocket> {
+        self.get_local_users(file_id)
+            .await
+            .into_iter()
+            .filter_map(|user| user.socket.map(|socket| (user.session_id, socket)))
+            .collect()
+    }
+
     /// Add a user to a room.  If the room doesn't exist, it is created.  Users
-    /// are only added to a room once (DashMap).  Returns true if the user was
-    /// newly added.
+    /// are only added to a room once.  Returns true if the user was newly
+    /// added.
     #[tracing::instrument(level = "trace")]
     pub(crate) async fn enter_room(
         &self,
@@ -80,23 +121,29 @@ impl State {
         sequence_num: u64,
     ) -> Result<bool> {
         let sequence_num = self.get_max_sequence_num(file_id, sequence_num).await?;
-        let rooms = self.rooms.lock().await;
-        let mut room = rooms.entry(file_id).or_insert_with(|| {
-            tracing::info!(
-                "Room {} created with sequence_num {}",
-                file_id,
-                sequence_num
-            );
+        let sequence_num = self
+            .set_max_sequence_num_pubsub(&file_id, sequence_num)
+            .await?;
 
-            Room::new(file_id, sequence_num)
-        });
+        user.index = self.next_user_index_pubsub(&file_id).await?;
 
-        user.index = room.user_index_increment();
+        let is_new = self.set_user_pubsub(&file_id, user).await?;
 
-        let is_new = room
+        self.rooms
+            .lock()
+            .await
+            .entry(file_id)
+            .or_insert_with(|| {
+                tracing::info!(
+                    "Room {} opened on this instance with sequence_num {}",
+                    file_id,
+                    sequence_num
+                );
+
+                Room::new(file_id, sequence_num)
+            })
             .users
-            .insert(user.session_id.to_owned(), user.to_owned())
-            .is_none();
+            .insert(user.session_id.to_owned(), user.to_owned());
 
         let connection = Connection::new(
             pre_connection.id,
@@ -117,8 +164,14 @@ impl State {
     /// Returns true if the room still exists after the user leaves.
     #[tracing::instrument(level = "trace")]
     pub(crate) async fn leave_room(&self, file_id: Uuid, session_id: &Uuid) -> Result<bool> {
-        get_mut_room!(self, file_id)?.users.remove(session_id);
-        let num_in_room = get_room!(self, file_id)?.users.len();
+        let is_local_room = self.remove_local_user(&file_id, session_id).await;
+        let in_room = self.get_user_pubsub(&file_id, session_id).await?.is_some();
+
+        if !is_local_room && !in_room {
+            return Err(MpError::RoomNotFound(file_id.to_string()));
+        }
+
+        let num_in_room = self.remove_user_pubsub(&file_id, session_id).await?;
 
         tracing::info!(
             "User {:?} is leaving room {}, {} user(s) left",
@@ -135,7 +188,27 @@ impl State {
         Ok(num_in_room != 0)
     }
 
-    /// Removes a room.
+    /// Removes a user from this instance's copy of a room, dropping the local
+    /// room once it's empty.  Returns true if the room was open locally.
+    async fn remove_local_user(&self, file_id: &Uuid, session_id: &Uuid) -> bool {
+        let rooms = self.rooms.lock().await;
+        let is_empty = match rooms.get(file_id) {
+            Some(room) => {
+                room.users.remove(session_id);
+                room.users.is_empty()
+            }
+            None => return false,
+        };
+
+        if is_empty {
+            rooms.remove(file_id);
+        }
+
+        true
+    }
+
+    /// Removes this instance's copy of a room.  The room's users and user
+    /// index are removed from PubSub with its last user.
     pub(crate) async fn remove_room(&self, file_id: Uuid) {
         self.rooms.lock().await.remove(&file_id);
 
@@ -144,20 +217,22 @@ impl State {
 
     /// Get a room's current sequence number.
     pub(crate) async fn get_sequence_num(&self, file_id: &Uuid) -> Result<u64> {
-        Ok(get_room!(self, file_id)?.sequence_num)
+        self.get_sequence_num_pubsub(file_id)
+            .await?
+            .ok_or(MpError::RoomNotFound(file_id.to_string()))
     }
 
     /// Get the maximum sequence number for a room.
-    /// If the room doesn't exist in memory, get the latest checkpoint from
+    /// If the room has never been opened, get the latest checkpoint from
     /// quadratic api.
     pub(crate) async fn get_max_sequence_num(
         &self,
         file_id: Uuid,
         sequence_num: u64,
     ) -> Result<u64> {
-        let sequence_num: u64 = match get_room!(self, file_id) {
-            Ok(room) => room.sequence_num.max(sequence_num),
-            Err(_) => {
+        let sequence_num: u64 = match self.get_sequence_num_pubsub(&file_id).await? {
+            Some(room_sequence_num) => room_sequence_num.max(sequence_num),
+            None => {
                 if cfg!(test) {
                     0
                 } else {
@@ -182,33 +257,12 @@ impl State {
     }
 }
 
-#[macro_export]
-macro_rules! get_room {
-    ( $self:ident, $file_id:ident ) => {
-        $self
-            .rooms
-            .lock()
-            .await
-            .get(&$file_id)
-            .ok_or($crate::error::MpError::RoomNotFound($file_id.to_string()))
-    };
-}
-
-#[macro_export]
-macro_rules! get_mut_room {
-    ( $self:ident, $file_id:ident ) => {
-        $self
-            .rooms
-            .lock()
-            .await
-            .get_mut(&$file_id)
-            .ok_or($crate::error::MpError::RoomNotFound($file_id.to_string()))
-    };
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::test_util::{new_state, new_user};
+    use std::sync::Arc;
+
+    use crate::permissions::push_transaction;
+    use crate::test_util::{add_new_user_to_room, new_arc_state, new_state, new_user};
 
     use super::*;
 
@@ -229,16 +283,17 @@ mod tests {
         let user = room.get_user(&user.session_id).unwrap();
 
         assert!(is_new);
-        assert_eq!(state.rooms.lock().await.len(), 1);
+        assert!(state.rooms.lock().await.contains_key(&file_id));
         assert_eq!(room.users.len(), 1);
         assert_eq!(user.index, 0);
 
         let sequence_num = state.get_sequence_num(&file_id).await.unwrap();
         assert_eq!(sequence_num, 0);
 
-        get_mut_room!(state, file_id)
-            .unwrap()
-            .increment_sequence_num();
+        state
+            .set_max_sequence_num_pubsub(&file_id, 1)
+            .await
+            .unwrap();
         let sequence_num = state.get_sequence_num(&file_id).await.unwrap();
         assert_eq!(sequence_num, 1);
 
@@ -258,6 +313,11 @@ mod tests {
         state.leave_room(file_id, &user2.session_id).await.unwrap();
         let room = state.get_room(&file_id).await;
         assert!(room.is_err());
+        assert!(!state.rooms.lock().await.contains_key(&file_id));
+
+        // the sequence number survives the room being removed
+        let sequence_num = state.get_sequence_num(&file_id).await.unwrap();
+        assert_eq!(sequence_num, 1);
     }
 
     #[tokio::test]
@@ -294,4 +354,99 @@ mod tests {
             .unwrap();
         assert_eq!(user2.index, 3);
     }
+
+    #[tokio::test]
+    async fn shares_rooms_across_instances() {
+        let state_1 = new_arc_state().await;
+        let state_2 = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+
+        let user_1 = add_new_user_to_room(file_id, state_1.clone()).await;
+        let user_2 = add_new_user_to_room(file_id, state_2.clone()).await;
+
+        // both instances see both users, but only know about their own
+        for state in [&state_1, &state_2] {
+            let room = state.get_room(&file_id).await.unwrap();
+            assert_eq!(room.users.len(), 2);
+            assert_eq!(room.get_user(&user_1.session_id).unwrap(), user_1);
+            assert_eq!(room.get_user(&user_2.session_id).unwrap(), user_2);
+        }
+        assert_eq!(
+            state_1.get_local_users(&file_id).await,
+            vec![user_1.clone()]
+        );
+        assert_eq!(
+            state_2.get_local_users(&file_id).await,
+            vec![user_2.clone()]
+        );
+        assert_eq!(user_1.index, 0);
+        assert_eq!(user_2.index, 1);
+
+        // the sequence number is shared
+        let push = |state: &Arc<State>, user: &User| {
+            push_transaction(
+                Arc::clone(state),
+                file_id,
+                user.session_id,
+                Uuid::new_v4(),
+                &[],
+                vec![],
+            )
+        };
+        assert_eq!(push(&state_1, &user_1).await.unwrap(), 1);
+        assert_eq!(push(&state_2, &user_2).await.unwrap(), 2);
+        assert_eq!(state_1.get_sequence_num(&file_id).await.unwrap(), 2);
+
+        // leaving on one instance is seen by the other
+        assert!(
+            state_1
+                .leave_room(file_id, &user_1.session_id)
+                .await
+                .unwrap()
+        );
+        assert!(!state_1.rooms.lock().await.contains_key(&file_id));
+        let room = state_2.get_room(&file_id).await.unwrap();
+        assert_eq!(room.users.len(), 1);
+
+        assert!(
+            !state_2
+                .leave_room(file_id, &user_2.session_id)
+                .await
+                .unwrap()
+        );
+        assert!(state_1.get_room(&file_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn removes_stale_users_of_other_instances() {
+        let state_1 = new_arc_state().await;
+        let state_2 = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+
+        // a user connected to an instance that went away
+        add_new_user_to_room(file_id, state_1).await;
+        assert!(
+            state_2
+                .get_room_ids_pubsub()
+                .await
+                .unwrap()
+                .contains(&file_id)
+        );
+
+        state_2
+            .remove_stale_users_in_room(file_id, -1)
+            .await
+            .unwrap();
+        assert!(state_2.get_room(&file_id).await.is_err());
+        assert!(
+            !state_2
+                .get_room_ids_pubsub()
+                .await
+                .unwrap()
+                .contains(&file_id)
+        );
+
+        // the user index was removed with the last user
+        assert_eq!(state_2.next_user_index_pubsub(&file_id).await.unwrap(), 0);
+    }
 }
@@ -9,6 +9,9 @@ pub(crate) struct Settings {
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
     pub(crate) version: String,
+    pub(crate) max_transaction_bytes: usize,
+    pub(crate) max_transaction_decompressed_bytes: usize,
+    pub(crate) max_transaction_operations: usize,
 }
 
 /// Gets the version of the crate (which should be in sync with the client version)
@@ -24,6 +27,9 @@ impl Settings {
             quadratic_api_uri: config.quadratic_api_uri.to_owned(),
             m2m_auth_token: config.m2m_auth_token.to_owned(),
             version: version(),
+            max_transaction_bytes: config.max_transaction_bytes,
+            max_transaction_decompressed_bytes: config.max_transaction_decompressed_bytes,
+            max_transaction_operations: config.max_transaction_operations,
         }
     }
 }
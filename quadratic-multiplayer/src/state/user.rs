@@ -8,7 +8,6 @@ use uuid::Uuid;
 
 use crate::error::{MpError, Result};
 use crate::state::State;
-use crate::{get_mut_room, get_room};
 use quadratic_rust_shared::quadratic_api::FilePermRole;
 
 pub(crate) type UserSocket = Arc<Mutex<SplitSink<WebSocket, Message>>>;
@@ -42,6 +41,41 @@ impl PartialEq for User {
     }
 }
 
+/// A user as stored in PubSub.  The heartbeat isn't sent to clients, but is
+/// stored so that any multiplayer instance can remove stale users.
+#[derive(Serialize, Deserialize)]
+struct StoredUser {
+    #[serde(flatten)]
+    user: User,
+    last_heartbeat: DateTime<Utc>,
+}
+
+impl User {
+    /// Serialize the user for storage in PubSub
+    pub(crate) fn to_stored(&self) -> Result<Vec<u8>> {
+        let stored = StoredUser {
+            user: User {
+                socket: None,
+                ..self.to_owned()
+            },
+            last_heartbeat: self.last_heartbeat,
+        };
+
+        Ok(serde_json::to_vec(&stored)?)
+    }
+
+    /// Deserialize a user stored in PubSub.  The user has no socket.
+    pub(crate) fn from_stored(stored: &[u8]) -> Result<User> {
+        let StoredUser {
+            mut user,
+            last_heartbeat,
+        } = serde_json::from_slice(stored)?;
+        user.last_heartbeat = last_heartbeat;
+
+        Ok(user)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub(crate) struct CellEdit {
     pub active: bool,
@@ -88,17 +122,49 @@ impl State {
         file_id: &Uuid,
         session_id: &Uuid,
     ) -> Result<User> {
-        let user = get_room!(self, file_id)?
-            .users
-            .get(session_id)
+        let mut user = self
+            .get_user_pubsub(file_id, session_id)
+            .await?
             .ok_or(MpError::Unknown(format!(
                 "User {session_id} not found in Room {file_id}"
-            )))?
-            .to_owned();
+            )))?;
+        user.socket = self
+            .get_local_user(file_id, session_id)
+            .await
+            .and_then(|local_user| local_user.socket);
 
         Ok(user)
     }
 
+    /// Updates a user in a room, in PubSub and in this instance's copy of the
+    /// room.  A user is only updated by the instance they're connected to, so
+    /// the read-modify-write doesn't race with other instances.  Does nothing
+    /// if the user isn't in the room.
+    async fn update_user(
+        &self,
+        file_id: &Uuid,
+        session_id: &Uuid,
+        update: impl FnOnce(&mut User),
+    ) -> Result<()> {
+        let Some(mut user) = self.get_user_pubsub(file_id, session_id).await? else {
+            return Ok(());
+        };
+
+        update(&mut user);
+        self.set_user_pubsub(file_id, &user).await?;
+
+        if let Some(room) = self.rooms.lock().await.get(file_id) {
+            room.users
+                .entry(session_id.to_owned())
+                .and_modify(|local_user| {
+                    user.socket = local_user.socket.take();
+                    *local_user = user;
+                });
+        }
+
+        Ok(())
+    }
+
     /// Remove stale users in a room.  Returns the number of users removed in the room, and the number left.
     #[tracing::instrument(level = "trace")]
     pub(crate) async fn remove_stale_users_in_room(
@@ -107,7 +173,9 @@ impl State {
         heartbeat_timeout_s: i64,
     ) -> Result<(usize, usize)> {
         let mut num_active_users = 0;
-        let stale_users = get_room!(self, file_id)?
+        let stale_users = self
+            .get_room(&file_id)
+            .await?
             .users
             .iter()
             .filter(|user| {
@@ -130,7 +198,11 @@ impl State {
                 file_id
             );
 
-            self.leave_room(file_id, &user.session_id).await?;
+            // another instance may have removed the user already
+            match self.leave_room(file_id, &user.session_id).await {
+                Ok(_) | Err(MpError::RoomNotFound(_)) => {}
+                Err(error) => return Err(error),
+            }
             self.connections.lock().await.remove(&user.connection_id);
         }
 
@@ -144,15 +216,11 @@ impl State {
         file_id: Uuid,
         session_id: &Uuid,
     ) -> Result<()> {
-        get_mut_room!(self, file_id)?
-            .users
-            .entry(session_id.to_owned())
-            .and_modify(|user| {
-                user.last_heartbeat = Utc::now();
-                tracing::trace!("Updating heartbeat for {session_id}");
-            });
-
-        Ok(())
+        self.update_user(&file_id, session_id, |user| {
+            user.last_heartbeat = Utc::now();
+            tracing::trace!("Updating heartbeat for {session_id}");
+        })
+        .await
     }
 
     /// Updates a user's permissions in a room
@@ -163,12 +231,8 @@ impl State {
         session_id: &Uuid,
         permissions: Vec<FilePermRole>,
     ) -> Result<()> {
-        get_mut_room!(self, file_id)?
-            .users
-            .entry(session_id.to_owned())
-            .and_modify(|user| user.permissions = permissions);
-
-        Ok(())
+        self.update_user(&file_id, session_id, |user| user.permissions = permissions)
+            .await
     }
 
     /// updates a user's state in a room
@@ -178,56 +242,68 @@ impl State {
         session_id: &Uuid,
         user_state: &UserStateUpdate,
     ) -> Result<()> {
-        get_mut_room!(self, file_id)?
-            .users
-            .entry(session_id.to_owned())
-            .and_modify(|user| {
-                if let Some(sheet_id) = user_state.sheet_id {
-                    user.state.sheet_id = sheet_id;
-                }
-                if let Some(selection) = &user_state.selection {
-                    selection.clone_into(&mut user.state.selection);
-                }
-                if let Some(x) = user_state.x {
-                    user.state.x = x;
+        self.update_user(file_id, session_id, |user| {
+            if let Some(sheet_id) = user_state.sheet_id {
+                user.state.sheet_id = sheet_id;
+            }
+            if let Some(selection) = &user_state.selection {
+                selection.clone_into(&mut user.state.selection);
+            }
+            if let Some(x) = user_state.x {
+                user.state.x = x;
+            }
+            if let Some(y) = user_state.y {
+                user.state.y = y;
+            }
+            if let Some(visible) = user_state.visible {
+                user.state.visible = visible;
+            }
+            if let Some(cell_edit) = user_state.cell_edit.to_owned() {
+                user.state.cell_edit = cell_edit;
+            }
+            if let Some(viewport) = user_state.viewport.to_owned() {
+                user.state.viewport = viewport;
+            }
+            if let Some(follow) = user_state.follow.to_owned() {
+                if follow.is_empty() {
+                    user.state.follow = None;
+                } else {
+                    user.state.follow = Uuid::parse_str(&follow).ok();
                 }
-                if let Some(y) = user_state.y {
-                    user.state.y = y;
-                }
-                if let Some(visible) = user_state.visible {
-                    user.state.visible = visible;
-                }
-                if let Some(cell_edit) = user_state.cell_edit.to_owned() {
-                    user.state.cell_edit = cell_edit;
-                }
-                if let Some(viewport) = user_state.viewport.to_owned() {
-                    user.state.viewport = viewport;
-                }
-                if let Some(follow) = user_state.follow.to_owned() {
-                    if follow.is_empty() {
-                        user.state.follow = None;
-                    } else {
-                        user.state.follow = Uuid::parse_str(&follow).ok();
-                    }
-                }
-
-                user.last_heartbeat = Utc::now();
-            });
+            }
 
-        Ok(())
+            user.last_heartbeat = Utc::now();
+        })
+        .await
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{error::MpError, test_util::setup};
+    use crate::{
+        error::MpError,
+        test_util::{new_user, setup},
+    };
 
     use super::*;
+
+    #[test]
+    fn stores_and_restores_a_user() {
+        let user = new_user();
+        let stored = user.to_stored().unwrap();
+        let restored = User::from_stored(&stored).unwrap();
+
+        assert_eq!(restored, user);
+        assert_eq!(restored.state, user.state);
+        assert_eq!(restored.index, user.index);
+        assert_eq!(restored.last_heartbeat, user.last_heartbeat);
+    }
+
     #[tokio::test]
     async fn removes_stale_users_in_room() {
         // add 2 users to a room
         let (_, state, _, file_id, _, _) = setup().await;
-        assert_eq!(get_room!(state, file_id).unwrap().users.len(), 2);
+        assert_eq!(state.get_room(&file_id).await.unwrap().users.len(), 2);
 
         // remove stale users in the room until the room is empty
         loop {
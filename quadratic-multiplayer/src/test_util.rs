@@ -23,6 +23,7 @@ use tower::ServiceExt;
 use uuid::Uuid;
 
 use crate::config::config;
+use crate::message::listen_for_broadcasts;
 use crate::message::request::MessageRequest;
 use crate::message::response::MessageResponse;
 use crate::server::app;
@@ -86,9 +87,12 @@ pub(crate) async fn new_state() -> State {
     State::new(&config, None).await.unwrap()
 }
 
-/// Create new global state wrapped in an Arc
+/// Create new global state wrapped in an Arc, listening for broadcasts from
+/// other instances
 pub(crate) async fn new_arc_state() -> Arc<State> {
-    Arc::new(new_state().await)
+    let state = Arc::new(new_state().await);
+    listen_for_broadcasts(Arc::clone(&state)).await.unwrap();
+    state
 }
 
 /// Create a new user with fake values
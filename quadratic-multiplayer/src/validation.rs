@@ -0,0 +1,178 @@
+//! Transaction Validation
+//!
+//! Transactions are decoded and validated before they're assigned a sequence
+//! number, so that a buggy or malicious client can't poison a file's stream.
+
+use std::sync::Arc;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use quadratic_core::controller::{operations::operation::Operation, transaction::Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::{MpError, Result},
+    state::{State, settings::Settings},
+};
+
+/// A transaction that has been decoded and validated
+#[derive(Debug)]
+pub(crate) struct ValidatedTransaction {
+    /// The compressed operations, as they're stored in pubsub
+    pub(crate) bytes: Vec<u8>,
+
+    /// The decoded operations
+    pub(crate) operations: Vec<Operation>,
+}
+
+/// Decode the base64 encoded operations of a transaction and validate that
+/// they deserialize into operations within the configured limits.
+pub(crate) fn validate_transaction(
+    id: Uuid,
+    operations: &str,
+    settings: &Settings,
+) -> Result<ValidatedTransaction> {
+    let invalid = |reason: String| MpError::InvalidTransaction(id, reason);
+
+    // check the encoded size before decoding (base64 encodes 3 bytes as 4)
+    if operations.len() / 4 * 3 > settings.max_transaction_bytes {
+        return Err(invalid(format!(
+            "operations exceed the limit of {} bytes",
+            settings.max_transaction_bytes
+        )));
+    }
+
+    let bytes = STANDARD
+        .decode(operations)
+        .map_err(|e| invalid(format!("could not decode base64 encoded operations: {e}")))?;
+
+    if bytes.len() > settings.max_transaction_bytes {
+        return Err(invalid(format!(
+            "operations exceed the limit of {} bytes",
+            settings.max_transaction_bytes
+        )));
+    }
+
+    let decoded = Transaction::decompress_and_deserialize_with_limit::<Vec<Operation>>(
+        &bytes,
+        settings.max_transaction_decompressed_bytes,
+    )
+    .map_err(|e| invalid(format!("could not deserialize operations: {e}")))?;
+
+    if decoded.len() > settings.max_transaction_operations {
+        return Err(invalid(format!(
+            "{} operations exceed the limit of {} operations",
+            decoded.len(),
+            settings.max_transaction_operations
+        )));
+    }
+
+    Ok(ValidatedTransaction {
+        bytes,
+        operations: decoded,
+    })
+}
+
+/// Validate a transaction on a blocking thread.  Decompressing and
+/// deserializing a large transaction is cpu-bound and would otherwise stall
+/// the runtime.  The encoded operations are handed back to be broadcast.
+pub(crate) async fn validate_transaction_blocking(
+    state: Arc<State>,
+    id: Uuid,
+    operations: String,
+) -> Result<(String, ValidatedTransaction)> {
+    tokio::task::spawn_blocking(move || {
+        validate_transaction(id, &operations, &state.settings)
+            .map(|validated| (operations, validated))
+    })
+    .await
+    .map_err(|e| MpError::BackgroundService(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::controller::GridController;
+
+    use crate::test_util::{new_state, operation};
+
+    use super::*;
+
+    fn encode(operations: Vec<Operation>) -> String {
+        STANDARD.encode(Transaction::serialize_and_compress(operations).unwrap())
+    }
+
+    #[tokio::test]
+    async fn validates_a_transaction() {
+        let state = new_state().await;
+        let mut grid = GridController::test();
+        let id = Uuid::new_v4();
+        let operations = vec![operation(&mut grid, 0, 0, "1")];
+
+        let validated =
+            validate_transaction(id, &encode(operations.clone()), &state.settings).unwrap();
+        assert_eq!(validated.operations, operations);
+        assert_eq!(
+            validated.bytes,
+            Transaction::serialize_and_compress(operations).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn validates_a_transaction_on_a_blocking_thread() {
+        let state = Arc::new(new_state().await);
+        let mut grid = GridController::test();
+        let id = Uuid::new_v4();
+        let operations = vec![operation(&mut grid, 0, 0, "1")];
+        let encoded = encode(operations.clone());
+
+        let (returned, validated) =
+            validate_transaction_blocking(Arc::clone(&state), id, encoded.clone())
+                .await
+                .unwrap();
+        assert_eq!(returned, encoded);
+        assert_eq!(validated.operations, operations);
+
+        let result = validate_transaction_blocking(state, id, "not base64!".into()).await;
+        assert!(matches!(result, Err(MpError::InvalidTransaction(..))));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_transactions() {
+        let mut state = new_state().await;
+        let mut grid = GridController::test();
+        let id = Uuid::new_v4();
+        let is_invalid = |result: Result<ValidatedTransaction>| match result {
+            Err(MpError::InvalidTransaction(error_id, _)) => error_id == id,
+            _ => false,
+        };
+
+        // not base64
+        let result = validate_transaction(id, "not base64!", &state.settings);
+        assert!(is_invalid(result));
+
+        // base64, but not operations
+        let not_operations = STANDARD.encode(b"1.0*not operations");
+        let result = validate_transaction(id, &not_operations, &state.settings);
+        assert!(is_invalid(result));
+
+        // too many operations
+        let operations = encode(vec![
+            operation(&mut grid, 0, 0, "1"),
+            operation(&mut grid, 0, 1, "2"),
+        ]);
+        state.settings.max_transaction_operations = 1;
+        let result = validate_transaction(id, &operations, &state.settings);
+        assert!(is_invalid(result));
+
+        // too many bytes
+        state.settings.max_transaction_operations = 2;
+        state.settings.max_transaction_bytes = 10;
+        let result = validate_transaction(id, &operations, &state.settings);
+        assert!(is_invalid(result));
+
+        // too many decompressed bytes
+        state.settings.max_transaction_bytes = 1024;
+        state.settings.max_transaction_decompressed_bytes = 10;
+        let result = validate_transaction(id, &operations, &state.settings);
+        assert!(is_invalid(result));
+    }
+}
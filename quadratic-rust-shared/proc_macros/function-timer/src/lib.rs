@@ -22,6 +22,17 @@ pub fn function_timer(_attrs: TokenStream, item: TokenStream) -> TokenStream {
     let mut print_output = quote! {};
     let should_print = _attrs.to_string().contains("dbgjs");
 
+    // `metrics` feeds the duration to the shared metrics histogram, for
+    // crates that depend on quadratic-rust-shared
+    let record_output = match _attrs.to_string().contains("metrics") {
+        true => quote! {
+            quadratic_rust_shared::metrics::observe_function_duration(#fn_name, duration.num_milliseconds());
+        },
+        false => quote! {
+            crate::FUNCTIONS.lock().unwrap().push((#fn_name.to_string(), duration.num_milliseconds()));
+        },
+    };
+
     if should_print {
         print_output = quote! {
             dbgjs!(format!("Function {} took {} ms", #fn_name, duration.num_milliseconds()));
@@ -36,7 +47,7 @@ pub fn function_timer(_attrs: TokenStream, item: TokenStream) -> TokenStream {
             let end = chrono::Utc::now();
             let duration = end - start;
 
-            crate::FUNCTIONS.lock().unwrap().push((#fn_name.to_string(), duration.num_milliseconds()));
+            #record_output
 
             #print_output
 
@@ -4,8 +4,11 @@
 
 use aws_sdk_s3::{
     Client,
-    operation::{get_object::GetObjectOutput, put_object::PutObjectOutput},
+    operation::{
+        get_object::GetObjectOutput, head_object::HeadObjectOutput, put_object::PutObjectOutput,
+    },
     primitives::{ByteStream, SdkBody},
+    types::{CompletedMultipartUpload, CompletedPart, Object},
 };
 
 use crate::aws::error::Aws as AwsError;
@@ -51,6 +54,194 @@ pub async fn upload_object(
         })
 }
 
+fn s3_error(message: String, error: impl std::fmt::Debug) -> SharedError {
+    SharedError::Aws(AwsError::S3(format!("{message}: {:?}.", error)))
+}
+
+/// List the objects whose keys start with the prefix, following pagination
+pub async fn list_objects(client: &Client, bucket: &str, prefix: &str) -> Result<Vec<Object>> {
+    let mut objects = vec![];
+    let mut pages = client
+        .list_objects_v2()
+        .bucket(bucket)
+        .prefix(prefix)
+        .into_paginator()
+        .send();
+
+    while let Some(page) = pages.next().await {
+        let page = page.map_err(|error| {
+            s3_error(
+                format!("Error listing prefix {prefix} in bucket {bucket}"),
+                error,
+            )
+        })?;
+        objects.extend(page.contents().iter().cloned());
+    }
+
+    Ok(objects)
+}
+
+/// Get an object's metadata from S3.  Returns None if the object doesn't
+/// exist.
+pub async fn head_object(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+) -> Result<Option<HeadObjectOutput>> {
+    match client.head_object().bucket(bucket).key(key).send().await {
+        Ok(output) => Ok(Some(output)),
+        Err(error) if error.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+        Err(error) => Err(s3_error(
+            format!("Error getting metadata of file {key} in bucket {bucket}"),
+            error,
+        )),
+    }
+}
+
+/// Delete an object from S3
+pub async fn delete_object(client: &Client, bucket: &str, key: &str) -> Result<()> {
+    client
+        .delete_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|error| {
+            s3_error(
+                format!("Error deleting file {key} from bucket {bucket}"),
+                error,
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Copy an object within a bucket
+pub async fn copy_object(client: &Client, bucket: &str, from: &str, to: &str) -> Result<()> {
+    client
+        .copy_object()
+        .bucket(bucket)
+        .copy_source(format!("{bucket}/{from}"))
+        .key(to)
+        .send()
+        .await
+        .map_err(|error| {
+            s3_error(
+                format!("Error copying file {from} to {to} in bucket {bucket}"),
+                error,
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Start a multipart upload, returning the upload id
+pub async fn create_multipart_upload(client: &Client, bucket: &str, key: &str) -> Result<String> {
+    let output = client
+        .create_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|error| {
+            s3_error(
+                format!("Error starting upload of file {key} to bucket {bucket}"),
+                error,
+            )
+        })?;
+
+    output.upload_id().map(ToOwned::to_owned).ok_or_else(|| {
+        SharedError::Aws(AwsError::S3(format!(
+            "No upload id returned for file {key} in bucket {bucket}"
+        )))
+    })
+}
+
+/// Upload a part of a multipart upload.  Part numbers start at 1.
+pub async fn upload_part(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    part_number: i32,
+    body: Vec<u8>,
+) -> Result<CompletedPart> {
+    let output = client
+        .upload_part()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(upload_id)
+        .part_number(part_number)
+        .body(ByteStream::from(body))
+        .send()
+        .await
+        .map_err(|error| {
+            s3_error(
+                format!("Error uploading part {part_number} of file {key} to bucket {bucket}"),
+                error,
+            )
+        })?;
+
+    Ok(CompletedPart::builder()
+        .set_e_tag(output.e_tag().map(ToOwned::to_owned))
+        .part_number(part_number)
+        .build())
+}
+
+/// Complete a multipart upload
+pub async fn complete_multipart_upload(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    parts: Vec<CompletedPart>,
+) -> Result<()> {
+    client
+        .complete_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .set_parts(Some(parts))
+                .build(),
+        )
+        .send()
+        .await
+        .map_err(|error| {
+            s3_error(
+                format!("Error completing upload of file {key} to bucket {bucket}"),
+                error,
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Abort a multipart upload, discarding the uploaded parts
+pub async fn abort_multipart_upload(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+) -> Result<()> {
+    client
+        .abort_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(upload_id)
+        .send()
+        .await
+        .map_err(|error| {
+            s3_error(
+                format!("Error aborting upload of file {key} to bucket {bucket}"),
+                error,
+            )
+        })?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod tests {
     // use aws_config::{imds::Client as ImdsClient, provider_config::ProviderConfig};
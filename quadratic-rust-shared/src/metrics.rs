@@ -0,0 +1,318 @@
+//! Metrics
+//!
+//! A small Prometheus registry shared by the Rust services.  Metrics are
+//! recorded into the global `METRICS` registry and rendered in the Prometheus
+//! text format by each service's `/metrics` endpoint.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{LazyLock, Mutex, PoisonError},
+};
+
+/// Content type of the Prometheus text format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Histogram buckets for durations, in seconds
+pub const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram(&'static [f64]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+}
+
+pub const ROOMS: Metric = Metric {
+    name: "quadratic_rooms",
+    help: "Multiplayer rooms with users connected to this instance",
+    kind: MetricKind::Gauge,
+};
+
+pub const CONNECTED_USERS: Metric = Metric {
+    name: "quadratic_connected_users",
+    help: "Users connected to this multiplayer instance",
+    kind: MetricKind::Gauge,
+};
+
+pub const TRANSACTIONS: Metric = Metric {
+    name: "quadratic_transactions_total",
+    help: "Transactions processed",
+    kind: MetricKind::Counter,
+};
+
+pub const PUBSUB_BACKLOG: Metric = Metric {
+    name: "quadratic_pubsub_backlog_files",
+    help: "Files with transactions in pubsub waiting to be checkpointed (a count, not a duration)",
+    kind: MetricKind::Gauge,
+};
+
+pub const CHECKPOINT_DURATION: Metric = Metric {
+    name: "quadratic_checkpoint_duration_seconds",
+    help: "Time to apply a file's transactions and upload the checkpoint",
+    kind: MetricKind::Histogram(DURATION_BUCKETS),
+};
+
+pub const QUERY_DURATION: Metric = Metric {
+    name: "quadratic_query_duration_seconds",
+    help: "Time to run a database query, by database kind",
+    kind: MetricKind::Histogram(DURATION_BUCKETS),
+};
+
+pub const QUERY_BYTES: Metric = Metric {
+    name: "quadratic_query_bytes_total",
+    help: "Bytes returned by database queries, by database kind",
+    kind: MetricKind::Counter,
+};
+
+pub const QUERY_CACHE_HITS: Metric = Metric {
+    name: "quadratic_query_cache_hits_total",
+    help: "Queries answered with cached results, by database kind",
+    kind: MetricKind::Counter,
+};
+
+pub const FUNCTION_DURATION: Metric = Metric {
+    name: "quadratic_function_duration_seconds",
+    help: "Time spent in functions timed by the function-timer macro",
+    kind: MetricKind::Histogram(DURATION_BUCKETS),
+};
+
+type Labels = Vec<(String, String)>;
+
+#[derive(Debug)]
+enum Series {
+    Value(f64),
+    Histogram {
+        // cumulative count of observations at or below each bucket
+        buckets: Vec<u64>,
+        sum: f64,
+        count: u64,
+    },
+}
+
+#[derive(Debug)]
+struct Family {
+    metric: Metric,
+    series: BTreeMap<Labels, Series>,
+}
+
+#[derive(Debug, Default)]
+pub struct Registry {
+    families: Mutex<BTreeMap<&'static str, Family>>,
+}
+
+pub static METRICS: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+impl Registry {
+    fn update(&self, metric: &Metric, labels: &[(&str, &str)], update: impl FnOnce(&mut Series)) {
+        let mut families = self.families.lock().unwrap_or_else(PoisonError::into_inner);
+        let family = families.entry(metric.name).or_insert_with(|| Family {
+            metric: *metric,
+            series: BTreeMap::new(),
+        });
+        let labels = labels
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Labels>();
+        let series = family
+            .series
+            .entry(labels)
+            .or_insert_with(|| match metric.kind {
+                MetricKind::Histogram(buckets) => Series::Histogram {
+                    buckets: vec![0; buckets.len()],
+                    sum: 0.0,
+                    count: 0,
+                },
+                _ => Series::Value(0.0),
+            });
+
+        update(series);
+    }
+
+    /// Add to a counter
+    pub fn increment(&self, metric: &Metric, labels: &[(&str, &str)], by: f64) {
+        self.update(metric, labels, |series| {
+            if let Series::Value(value) = series {
+                *value += by;
+            }
+        });
+    }
+
+    /// Set a gauge
+    pub fn set(&self, metric: &Metric, labels: &[(&str, &str)], to: f64) {
+        self.update(metric, labels, |series| {
+            if let Series::Value(value) = series {
+                *value = to;
+            }
+        });
+    }
+
+    /// Record an observation in a histogram
+    pub fn observe(&self, metric: &Metric, labels: &[(&str, &str)], observed: f64) {
+        let MetricKind::Histogram(bounds) = metric.kind else {
+            return;
+        };
+
+        self.update(metric, labels, |series| {
+            if let Series::Histogram {
+                buckets,
+                sum,
+                count,
+            } = series
+            {
+                for (bucket, bound) in buckets.iter_mut().zip(bounds) {
+                    if observed <= *bound {
+                        *bucket += 1;
+                    }
+                }
+                *sum += observed;
+                *count += 1;
+            }
+        });
+    }
+
+    /// Render all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut output = String::new();
+
+        for family in families.values() {
+            let Metric { name, help, kind } = family.metric;
+            let kind_name = match kind {
+                MetricKind::Counter => "counter",
+                MetricKind::Gauge => "gauge",
+                MetricKind::Histogram(_) => "histogram",
+            };
+
+            writeln!(output, "# HELP {name} {help}").ok();
+            writeln!(output, "# TYPE {name} {kind_name}").ok();
+
+            for (labels, series) in family.series.iter() {
+                match (series, kind) {
+                    (Series::Value(value), _) => {
+                        writeln!(output, "{name}{} {value}", format_labels(labels, None)).ok();
+                    }
+                    (
+                        Series::Histogram {
+                            buckets,
+                            sum,
+                            count,
+                        },
+                        MetricKind::Histogram(bounds),
+                    ) => {
+                        for (bucket, bound) in buckets.iter().zip(bounds) {
+                            let le = bound.to_string();
+                            let labels = format_labels(labels, Some(&le));
+                            writeln!(output, "{name}_bucket{labels} {bucket}").ok();
+                        }
+
+                        let labels_inf = format_labels(labels, Some("+Inf"));
+                        let labels = format_labels(labels, None);
+                        writeln!(output, "{name}_bucket{labels_inf} {count}").ok();
+                        writeln!(output, "{name}_sum{labels} {sum}").ok();
+                        writeln!(output, "{name}_count{labels} {count}").ok();
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        output
+    }
+}
+
+/// Format labels as `{name="value",...}`, with an optional histogram bucket
+fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+    let escape = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    };
+    let labels = labels
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .chain(le.map(|le| ("le", le)))
+        .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+        .collect::<Vec<_>>();
+
+    match labels.is_empty() {
+        true => String::new(),
+        false => format!("{{{}}}", labels.join(",")),
+    }
+}
+
+/// Record how long a function took.  The `function-timer` macro calls this
+/// when used as `#[function_timer(metrics)]`.
+pub fn observe_function_duration(function: &str, milliseconds: i64) {
+    METRICS.observe(
+        &FUNCTION_DURATION,
+        &[("function", function)],
+        milliseconds as f64 / 1000.0,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HISTOGRAM: Metric = Metric {
+        name: "test_duration_seconds",
+        help: "A test histogram",
+        kind: MetricKind::Histogram(&[0.1, 1.0]),
+    };
+
+    #[test]
+    fn renders_counters_and_gauges() {
+        let registry = Registry::default();
+
+        registry.increment(&QUERY_BYTES, &[("kind", "postgres")], 10.0);
+        registry.increment(&QUERY_BYTES, &[("kind", "postgres")], 5.0);
+        registry.increment(&QUERY_BYTES, &[("kind", "my\"sql")], 1.0);
+        registry.set(&ROOMS, &[], 3.0);
+        registry.set(&ROOMS, &[], 2.0);
+
+        assert_eq!(
+            registry.render(),
+            "# HELP quadratic_query_bytes_total Bytes returned by database queries, by database kind\n\
+             # TYPE quadratic_query_bytes_total counter\n\
+             quadratic_query_bytes_total{kind=\"my\\\"sql\"} 1\n\
+             quadratic_query_bytes_total{kind=\"postgres\"} 15\n\
+             # HELP quadratic_rooms Multiplayer rooms with users connected to this instance\n\
+             # TYPE quadratic_rooms gauge\n\
+             quadratic_rooms 2\n"
+        );
+    }
+
+    #[test]
+    fn renders_histograms() {
+        let registry = Registry::default();
+
+        registry.observe(&HISTOGRAM, &[], 0.05);
+        registry.observe(&HISTOGRAM, &[], 0.5);
+        registry.observe(&HISTOGRAM, &[], 5.0);
+
+        // observing a counter is ignored
+        registry.observe(&TRANSACTIONS, &[], 1.0);
+
+        assert_eq!(
+            registry.render(),
+            "# HELP test_duration_seconds A test histogram\n\
+             # TYPE test_duration_seconds histogram\n\
+             test_duration_seconds_bucket{le=\"0.1\"} 1\n\
+             test_duration_seconds_bucket{le=\"1\"} 2\n\
+             test_duration_seconds_bucket{le=\"+Inf\"} 3\n\
+             test_duration_seconds_sum 5.55\n\
+             test_duration_seconds_count 3\n"
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Redis commands shared by the Redis and Redis Streams connections:
+//! counters, hashes, keys, leases and broadcasts.
+
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use redis::{
+    AsyncCommands, Client, ExistenceCheck, Script, SetExpiry, SetOptions,
+    aio::MultiplexedConnection, cmd, pipe,
+};
+
+use crate::error::Result;
+use crate::pubsub::Broadcasts;
+
+/// Raises a counter (KEYS[1]) to a value (ARGV[1]) if it's lower, atomically
+const SET_MAX_SCRIPT: &str = r"
+local current = tonumber(redis.call('GET', KEYS[1]) or '0')
+local value = tonumber(ARGV[1])
+if value > current then
+    redis.call('SET', KEYS[1], ARGV[1])
+    return value
+end
+return current
+";
+
+/// Deletes a lease (KEYS[1]) if it's still held by the owner (ARGV[1])
+const RELEASE_LEASE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// Extends a lease (KEYS[1]) to ARGV[2] milliseconds if it's still held by the
+/// owner (ARGV[1])
+const RENEW_LEASE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+";
+
+/// Removes a field (ARGV[1]) from a hash (KEYS[1]) and, if the hash is left
+/// empty, deletes the other keys (KEYS[2..]), atomically.  Returns the number
+/// of fields left.
+const HASH_REMOVE_SCRIPT: &str = r"
+redis.call('HDEL', KEYS[1], ARGV[1])
+local remaining = redis.call('HLEN', KEYS[1])
+if remaining == 0 then
+    for i = 2, #KEYS do
+        redis.call('DEL', KEYS[i])
+    end
+end
+return remaining
+";
+
+/// Replaces a hash (KEYS[1]) with field/value pairs (ARGV[2..]) and sets its
+/// ready key (KEYS[2]) to ARGV[1], unless the ready key exists.  Returns 1 if
+/// the hash was replaced.
+const HASH_INITIALIZE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[2]) == 1 then
+    return 0
+end
+redis.call('DEL', KEYS[1])
+for i = 2, #ARGV, 2 do
+    redis.call('HSET', KEYS[1], ARGV[i], ARGV[i + 1])
+end
+redis.call('SET', KEYS[2], ARGV[1])
+return 1
+";
+
+/// Number of keys asked for in each SCAN
+const SCAN_COUNT: usize = 1000;
+
+/// Get the value of a counter
+pub(crate) async fn counter(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+) -> Result<Option<u64>> {
+    let value: Option<u64> = connection.get(key).await?;
+
+    Ok(value)
+}
+
+/// Atomically increment a counter, returning the new value
+pub(crate) async fn increment(connection: &mut MultiplexedConnection, key: &str) -> Result<u64> {
+    let value: u64 = connection.incr(key, 1).await?;
+
+    Ok(value)
+}
+
+/// Atomically raise a counter to a value if it's lower, returning the
+/// resulting value
+pub(crate) async fn set_max(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    value: u64,
+) -> Result<u64> {
+    let value: u64 = Script::new(SET_MAX_SCRIPT)
+        .key(key)
+        .arg(value)
+        .invoke_async(connection)
+        .await?;
+
+    Ok(value)
+}
+
+/// Set a field of a hash, returning true if the field is new
+pub(crate) async fn hash_set(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    field: &str,
+    value: &[u8],
+) -> Result<bool> {
+    let added: u64 = connection.hset(key, field, value).await?;
+
+    Ok(added > 0)
+}
+
+/// Set a field of a hash if it doesn't exist, returning true if it was set
+pub(crate) async fn hash_set_if_missing(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    field: &str,
+    value: &[u8],
+) -> Result<bool> {
+    let set: bool = connection.hset_nx(key, field, value).await?;
+
+    Ok(set)
+}
+
+/// Get a field of a hash
+pub(crate) async fn hash_get(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    field: &str,
+) -> Result<Option<Vec<u8>>> {
+    let value: Option<Vec<u8>> = connection.hget(key, field).await?;
+
+    Ok(value)
+}
+
+/// Get all fields of a hash
+pub(crate) async fn hash_get_all(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let values: HashMap<String, Vec<u8>> = connection.hgetall(key).await?;
+
+    Ok(values.into_iter().collect())
+}
+
+/// Atomically remove a field from a hash, deleting `delete_if_empty` if the
+/// hash is left empty.  Returns the number of fields left.
+pub(crate) async fn hash_remove(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    field: &str,
+    delete_if_empty: &[&str],
+) -> Result<usize> {
+    let script = Script::new(HASH_REMOVE_SCRIPT);
+    let mut invocation = script.prepare_invoke();
+    invocation.key(key).arg(field);
+    for key in delete_if_empty {
+        invocation.key(*key);
+    }
+    let remaining: usize = invocation.invoke_async(connection).await?;
+
+    Ok(remaining)
+}
+
+/// Get the value of a counter and all fields of a hash, atomically
+pub(crate) async fn counter_and_hash(
+    connection: &mut MultiplexedConnection,
+    counter_key: &str,
+    hash_key: &str,
+) -> Result<(Option<u64>, Vec<(String, Vec<u8>)>)> {
+    let (counter, values): (Option<u64>, HashMap<String, Vec<u8>>) = pipe()
+        .atomic()
+        .get(counter_key)
+        .hgetall(hash_key)
+        .query_async(connection)
+        .await?;
+
+    Ok((counter, values.into_iter().collect()))
+}
+
+/// Atomically replace a hash and set its ready key, unless the ready key
+/// exists.  Returns true if the hash was replaced.
+pub(crate) async fn hash_initialize(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    ready_key: &str,
+    ready: u64,
+    values: &[(String, Vec<u8>)],
+) -> Result<bool> {
+    let script = Script::new(HASH_INITIALIZE_SCRIPT);
+    let mut invocation = script.prepare_invoke();
+    invocation.key(key).key(ready_key).arg(ready);
+    for (field, value) in values {
+        invocation.arg(field).arg(value);
+    }
+    let initialized: u64 = invocation.invoke_async(connection).await?;
+
+    Ok(initialized > 0)
+}
+
+/// Get the keys matching a pattern.  Uses SCAN, so other clients aren't
+/// blocked while the keyspace is searched.
+pub(crate) async fn keys(
+    connection: &mut MultiplexedConnection,
+    pattern: &str,
+) -> Result<Vec<String>> {
+    let mut keys = vec![];
+    let mut cursor: u64 = 0;
+
+    loop {
+        let (next, batch): (u64, Vec<String>) = cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(SCAN_COUNT)
+            .query_async(connection)
+            .await?;
+        keys.extend(batch);
+
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+
+    keys.sort();
+    keys.dedup();
+
+    Ok(keys)
+}
+
+/// Delete a key
+pub(crate) async fn delete(connection: &mut MultiplexedConnection, key: &str) -> Result<()> {
+    let () = connection.del(key).await?;
+
+    Ok(())
+}
+
+/// Take a lease on a key, unless someone else holds it
+pub(crate) async fn acquire_lease(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    owner: &str,
+    ttl_ms: u64,
+) -> Result<bool> {
+    let options = SetOptions::default()
+        .conditional_set(ExistenceCheck::NX)
+        .with_expiration(SetExpiry::PX(ttl_ms));
+    let set: Option<String> = connection.set_options(key, owner, options).await?;
+
+    Ok(set.is_some())
+}
+
+/// Release a lease if the owner still holds it
+pub(crate) async fn release_lease(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    owner: &str,
+) -> Result<bool> {
+    let released: u64 = Script::new(RELEASE_LEASE_SCRIPT)
+        .key(key)
+        .arg(owner)
+        .invoke_async(connection)
+        .await?;
+
+    Ok(released > 0)
+}
+
+/// Extend a lease if the owner still holds it
+pub(crate) async fn renew_lease(
+    connection: &mut MultiplexedConnection,
+    key: &str,
+    owner: &str,
+    ttl_ms: u64,
+) -> Result<bool> {
+    let renewed: u64 = Script::new(RENEW_LEASE_SCRIPT)
+        .key(key)
+        .arg(owner)
+        .arg(ttl_ms)
+        .invoke_async(connection)
+        .await?;
+
+    Ok(renewed > 0)
+}
+
+/// Broadcast a message to every listener of a channel
+pub(crate) async fn broadcast(
+    connection: &mut MultiplexedConnection,
+    channel: &str,
+    message: &[u8],
+) -> Result<()> {
+    let () = connection.publish(channel, message).await?;
+
+    Ok(())
+}
+
+/// Listen for broadcasts on all channels matching a pattern, on a dedicated
+/// connection
+pub(crate) async fn listen(client: Client, pattern: &str) -> Result<Broadcasts> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.psubscribe(pattern).await?;
+
+    let messages = pubsub.into_on_message().map(|message| {
+        (
+            message.get_channel_name().to_string(),
+            message.get_payload_bytes().to_vec(),
+        )
+    });
+
+    Ok(Box::pin(messages))
+}
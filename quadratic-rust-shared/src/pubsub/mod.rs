@@ -1,9 +1,13 @@
 //! Pubsub code that implements the PubSub trait
 
+mod commands;
 pub mod redis;
 pub mod redis_streams;
 
-use futures_util::Future;
+use std::pin::Pin;
+
+use futures_util::{Future, Stream};
+use uuid::Uuid;
 
 use crate::error::Result;
 use crate::pubsub::redis::RedisConfig;
@@ -16,6 +20,52 @@ pub enum Config {
     RedisStreams(RedisStreamsConfig),
 }
 
+/// Key of the hash that holds a file's sheet protections (sheet id to
+/// json-encoded protections).  Multiplayer loads them from the file, keeps
+/// them current with each transaction and enforces them.  The files service
+/// seeds the sheets that are missing.
+pub fn protections_key(file_id: &Uuid) -> String {
+    format!("multiplayer:room:{file_id}:protections")
+}
+
+/// Key that marks a file's sheet protections as loaded, holding the sequence
+/// number they were loaded at.  Multiplayer accepts no transactions for the
+/// file without it.
+pub fn protections_loaded_key(file_id: &Uuid) -> String {
+    format!("multiplayer:room:{file_id}:protections_loaded")
+}
+
+/// Key of the hash that holds the output rects of the data tables of a
+/// file's protected sheets (sheet id to json-encoded rects).  The files
+/// service sets them from the file and multiplayer checks edits against them.
+pub fn data_table_rects_key(file_id: &Uuid) -> String {
+    format!("multiplayer:room:{file_id}:data_tables")
+}
+
+/// The outcome of publishing a message with `publish_sequenced`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sequenced {
+    /// The message was published with the requested sequence number
+    Published,
+    /// The sequence number was taken; holds the counter's current value
+    Conflict(u64),
+    /// The hash to update isn't ready, so nothing was published
+    NotReady,
+}
+
+/// Changes to a hash, made in the same step as a sequenced publish.  Nothing
+/// is published until `ready_key` exists, so the hash can be loaded first.
+#[derive(Debug, Clone, Copy)]
+pub struct HashUpdates<'a> {
+    pub key: &'a str,
+    pub ready_key: &'a str,
+    pub set: &'a [(String, Vec<u8>)],
+    pub removed: &'a [String],
+}
+
+/// A stream of broadcast messages, as (channel, message) pairs
+pub type Broadcasts = Pin<Box<dyn Stream<Item = (String, Vec<u8>)> + Send>>;
+
 /// Pubsub trait
 pub trait PubSub {
     type Connection;
@@ -64,6 +114,21 @@ pub trait PubSub {
         active_channel: Option<&str>,
     ) -> impl Future<Output = Result<()>> + Send;
 
+    /// Publish a message keyed by `sequence_num` and set the counter at
+    /// `counter_key` to it, atomically, but only if the counter is at
+    /// `sequence_num - 1`.  Messages are then published in order and without
+    /// gaps, however many publishers share the counter.  Any `updates` are
+    /// made to their hash in the same step.
+    fn publish_sequenced(
+        &mut self,
+        channel: &str,
+        counter_key: &str,
+        sequence_num: u64,
+        value: &[u8],
+        active_channel: Option<&str>,
+        updates: Option<HashUpdates<'_>>,
+    ) -> impl Future<Output = Result<Sequenced>> + Send;
+
     /// Acknowledge a message
     fn ack(
         &mut self,
@@ -104,10 +169,130 @@ pub trait PubSub {
         preserve_sequence: bool,
     ) -> impl Future<Output = Result<Vec<(String, Vec<u8>)>>> + Send;
 
+    /// Get messages from a channel between two keys, inclusive
+    fn get_messages_between(
+        &mut self,
+        channel: &str,
+        from: &str,
+        to: &str,
+        preserve_sequence: bool,
+    ) -> impl Future<Output = Result<Vec<(String, Vec<u8>)>>> + Send;
+
     /// Get the last message from a channel
     fn last_message(
         &mut self,
         channel: &str,
         preserve_sequence: bool,
     ) -> impl Future<Output = Result<(String, Vec<u8>)>> + Send;
+
+    /// Get the value of a counter
+    fn counter(&mut self, key: &str) -> impl Future<Output = Result<Option<u64>>> + Send;
+
+    /// Increment a counter, returning the new value
+    fn increment(&mut self, key: &str) -> impl Future<Output = Result<u64>> + Send;
+
+    /// Raise a counter to a value if it's lower, returning the resulting value
+    fn set_max(&mut self, key: &str, value: u64) -> impl Future<Output = Result<u64>> + Send;
+
+    /// Set a field of a hash, returning true if the field is new
+    fn hash_set(
+        &mut self,
+        key: &str,
+        field: &str,
+        value: &[u8],
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Set a field of a hash if it doesn't exist, returning true if it was set
+    fn hash_set_if_missing(
+        &mut self,
+        key: &str,
+        field: &str,
+        value: &[u8],
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Get a field of a hash
+    fn hash_get(
+        &mut self,
+        key: &str,
+        field: &str,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send;
+
+    /// Get all fields of a hash
+    fn hash_get_all(
+        &mut self,
+        key: &str,
+    ) -> impl Future<Output = Result<Vec<(String, Vec<u8>)>>> + Send;
+
+    /// Atomically remove a field from a hash and, if the hash is left empty,
+    /// delete the keys in `delete_if_empty`.  Returns the number of fields
+    /// left.
+    fn hash_remove(
+        &mut self,
+        key: &str,
+        field: &str,
+        delete_if_empty: &[&str],
+    ) -> impl Future<Output = Result<usize>> + Send;
+
+    /// Get the value of a counter and all fields of a hash, atomically
+    fn counter_and_hash(
+        &mut self,
+        counter_key: &str,
+        hash_key: &str,
+    ) -> impl Future<Output = Result<(Option<u64>, Vec<(String, Vec<u8>)>)>> + Send;
+
+    /// Replace a hash with `values` and set `ready_key` to `ready`,
+    /// atomically, unless `ready_key` exists.  Returns true if the hash was
+    /// replaced.
+    fn hash_initialize(
+        &mut self,
+        key: &str,
+        ready_key: &str,
+        ready: u64,
+        values: &[(String, Vec<u8>)],
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Get the keys matching a pattern
+    fn keys(&mut self, pattern: &str) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Delete a key
+    fn delete(&mut self, key: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Take a lease on a key for `ttl_ms` milliseconds, unless someone else
+    /// holds it.  The owner identifies the holder when releasing.  Returns
+    /// true if the lease was taken.
+    fn acquire_lease(
+        &mut self,
+        key: &str,
+        owner: &str,
+        ttl_ms: u64,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Release a lease, if the owner still holds it.  Returns true if it was
+    /// released.
+    fn release_lease(
+        &mut self,
+        key: &str,
+        owner: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Extend a lease to `ttl_ms` milliseconds from now, if the owner still
+    /// holds it.  Returns true if it was renewed.
+    fn renew_lease(
+        &mut self,
+        key: &str,
+        owner: &str,
+        ttl_ms: u64,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Broadcast a message to every listener of a channel.  Unlike `publish`,
+    /// broadcasts are not stored.
+    fn broadcast(
+        &mut self,
+        channel: &str,
+        message: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Listen for broadcasts on all channels matching a pattern, on a
+    /// dedicated connection
+    fn listen(config: Config, pattern: &str) -> impl Future<Output = Result<Broadcasts>> + Send;
 }
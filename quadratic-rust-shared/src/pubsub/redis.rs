@@ -4,7 +4,7 @@ use redis::{
     cmd,
 };
 
-use crate::pubsub::Config;
+use crate::pubsub::{Broadcasts, Config, HashUpdates, Sequenced, commands};
 use crate::{SharedError, error::Result};
 
 #[derive(Debug, Clone)]
@@ -101,6 +101,18 @@ impl super::PubSub for RedisConnection {
         Ok(())
     }
 
+    async fn publish_sequenced(
+        &mut self,
+        _channel: &str,
+        _counter_key: &str,
+        _sequence_num: u64,
+        _value: &[u8],
+        _active_channel: Option<&str>,
+        _updates: Option<HashUpdates<'_>>,
+    ) -> Result<Sequenced> {
+        unimplemented!()
+    }
+
     /// Acknowledge that a message was processed
     async fn ack(
         &mut self,
@@ -147,6 +159,16 @@ impl super::PubSub for RedisConnection {
         unimplemented!()
     }
 
+    async fn get_messages_between(
+        &mut self,
+        _channel: &str,
+        _from: &str,
+        _to: &str,
+        _preserve_sequence: bool,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        unimplemented!()
+    }
+
     async fn last_message(
         &mut self,
         _channel: &str,
@@ -155,6 +177,109 @@ impl super::PubSub for RedisConnection {
         unimplemented!()
     }
 
+    /// Get the value of a counter
+    async fn counter(&mut self, key: &str) -> Result<Option<u64>> {
+        commands::counter(&mut self.multiplex, key).await
+    }
+
+    /// Atomically increment a counter, returning the new value
+    async fn increment(&mut self, key: &str) -> Result<u64> {
+        commands::increment(&mut self.multiplex, key).await
+    }
+
+    /// Atomically raise a counter to a value if it's lower, returning the
+    /// resulting value
+    async fn set_max(&mut self, key: &str, value: u64) -> Result<u64> {
+        commands::set_max(&mut self.multiplex, key, value).await
+    }
+
+    /// Set a field of a hash, returning true if the field is new
+    async fn hash_set(&mut self, key: &str, field: &str, value: &[u8]) -> Result<bool> {
+        commands::hash_set(&mut self.multiplex, key, field, value).await
+    }
+
+    /// Set a field of a hash if it doesn't exist, returning true if it was set
+    async fn hash_set_if_missing(&mut self, key: &str, field: &str, value: &[u8]) -> Result<bool> {
+        commands::hash_set_if_missing(&mut self.multiplex, key, field, value).await
+    }
+
+    /// Get a field of a hash
+    async fn hash_get(&mut self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        commands::hash_get(&mut self.multiplex, key, field).await
+    }
+
+    /// Get all fields of a hash
+    async fn hash_get_all(&mut self, key: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        commands::hash_get_all(&mut self.multiplex, key).await
+    }
+
+    /// Atomically remove a field from a hash, deleting `delete_if_empty` if
+    /// the hash is left empty.  Returns the number of fields left.
+    async fn hash_remove(
+        &mut self,
+        key: &str,
+        field: &str,
+        delete_if_empty: &[&str],
+    ) -> Result<usize> {
+        commands::hash_remove(&mut self.multiplex, key, field, delete_if_empty).await
+    }
+
+    /// Get the value of a counter and all fields of a hash, atomically
+    async fn counter_and_hash(
+        &mut self,
+        counter_key: &str,
+        hash_key: &str,
+    ) -> Result<(Option<u64>, Vec<(String, Vec<u8>)>)> {
+        commands::counter_and_hash(&mut self.multiplex, counter_key, hash_key).await
+    }
+
+    /// Atomically replace a hash and set its ready key, unless the ready key
+    /// exists
+    async fn hash_initialize(
+        &mut self,
+        key: &str,
+        ready_key: &str,
+        ready: u64,
+        values: &[(String, Vec<u8>)],
+    ) -> Result<bool> {
+        commands::hash_initialize(&mut self.multiplex, key, ready_key, ready, values).await
+    }
+
+    /// Get the keys matching a pattern
+    async fn keys(&mut self, pattern: &str) -> Result<Vec<String>> {
+        commands::keys(&mut self.multiplex, pattern).await
+    }
+
+    /// Delete a key
+    async fn delete(&mut self, key: &str) -> Result<()> {
+        commands::delete(&mut self.multiplex, key).await
+    }
+
+    /// Take a lease on a key, unless someone else holds it
+    async fn acquire_lease(&mut self, key: &str, owner: &str, ttl_ms: u64) -> Result<bool> {
+        commands::acquire_lease(&mut self.multiplex, key, owner, ttl_ms).await
+    }
+
+    /// Release a lease if the owner still holds it
+    async fn release_lease(&mut self, key: &str, owner: &str) -> Result<bool> {
+        commands::release_lease(&mut self.multiplex, key, owner).await
+    }
+
+    /// Extend a lease if the owner still holds it
+    async fn renew_lease(&mut self, key: &str, owner: &str, ttl_ms: u64) -> Result<bool> {
+        commands::renew_lease(&mut self.multiplex, key, owner, ttl_ms).await
+    }
+
+    /// Broadcast a message to every listener of a channel
+    async fn broadcast(&mut self, channel: &str, message: &[u8]) -> Result<()> {
+        commands::broadcast(&mut self.multiplex, channel, message).await
+    }
+
+    /// Listen for broadcasts on all channels matching a pattern
+    async fn listen(config: Config, pattern: &str) -> Result<Broadcasts> {
+        commands::listen(client(config)?, pattern).await
+    }
+
     // /// Get the next message from the pubsub server.
     // async fn poll<T>(&mut self) -> impl Stream {
     //     self.pubsub.on_message()
@@ -5,7 +5,7 @@
 use chrono::prelude::*;
 use futures_util::StreamExt;
 use redis::{
-    AsyncCommands, Client, Value,
+    AsyncCommands, Client, Script, Value,
     aio::{Monitor, MultiplexedConnection, PubSub},
     cmd,
     streams::{StreamId, StreamKey, StreamRangeReply, StreamReadOptions, StreamReadReply},
@@ -15,9 +15,50 @@ use std::{
     vec,
 };
 
-use crate::pubsub::Config;
+use crate::pubsub::{Broadcasts, Config, HashUpdates, Sequenced, commands};
 use crate::{SharedError, error::Result};
 
+/// Adds a message (ARGV[2]) to a stream (KEYS[2]) with the id ARGV[1] and sets
+/// the counter (KEYS[1]) to it, if the counter is at ARGV[1] - 1.  If ARGV[4]
+/// is 1, the stream is then scored ARGV[3] in the active channels set (the
+/// next key).  If a hash and its ready key follow, nothing is published while
+/// the ready key is missing; otherwise ARGV[5] field/value pairs are set in the
+/// hash and the fields after them removed.  Returns -1 once published, -2 if
+/// the hash isn't ready, otherwise the counter's value.
+const PUBLISH_SEQUENCED_SCRIPT: &str = r"
+local key = 3
+local active, hash
+if ARGV[4] == '1' then
+    active = KEYS[key]
+    key = key + 1
+end
+if KEYS[key] then
+    hash = KEYS[key]
+    if redis.call('EXISTS', KEYS[key + 1]) == 0 then
+        return -2
+    end
+end
+local current = tonumber(redis.call('GET', KEYS[1]) or '0')
+if tonumber(ARGV[1]) ~= current + 1 then
+    return current
+end
+redis.call('XADD', KEYS[2], ARGV[1], ARGV[1], ARGV[2])
+redis.call('SET', KEYS[1], ARGV[1])
+if active then
+    redis.call('ZADD', active, ARGV[3], KEYS[2])
+end
+if hash then
+    local set = tonumber(ARGV[5])
+    for i = 6, 5 + 2 * set, 2 do
+        redis.call('HSET', hash, ARGV[i], ARGV[i + 1])
+    end
+    for i = 6 + 2 * set, #ARGV do
+        redis.call('HDEL', hash, ARGV[i])
+    end
+end
+return -1
+";
+
 /// Redis Streams configuration
 #[derive(Debug, Clone)]
 pub struct RedisStreamsConfig {
@@ -218,6 +259,59 @@ impl super::PubSub for RedisConnection {
         Ok(())
     }
 
+    /// Publish a message keyed by the next value of a counter, atomically.
+    /// The stream is only written if the counter hasn't moved on, and the
+    /// hash is updated in the same step.
+    async fn publish_sequenced(
+        &mut self,
+        channel: &str,
+        counter_key: &str,
+        sequence_num: u64,
+        value: &[u8],
+        active_channel: Option<&str>,
+        updates: Option<HashUpdates<'_>>,
+    ) -> Result<Sequenced> {
+        let script = Script::new(PUBLISH_SEQUENCED_SCRIPT);
+        let mut invocation = script.prepare_invoke();
+        invocation
+            .key(counter_key)
+            .key(channel)
+            .arg(sequence_num)
+            .arg(value)
+            .arg(Utc::now().timestamp_millis())
+            .arg(active_channel.is_some() as u8);
+
+        if let Some(active_channel) = active_channel {
+            invocation.key(active_channel);
+        }
+
+        match updates {
+            Some(updates) => {
+                invocation
+                    .key(updates.key)
+                    .key(updates.ready_key)
+                    .arg(updates.set.len());
+                for (field, value) in updates.set {
+                    invocation.arg(field).arg(value);
+                }
+                for field in updates.removed {
+                    invocation.arg(field);
+                }
+            }
+            None => {
+                invocation.arg(0);
+            }
+        }
+
+        let current: i64 = invocation.invoke_async(&mut self.multiplex).await?;
+
+        match current {
+            -1 => Ok(Sequenced::Published),
+            -2 => Ok(Sequenced::NotReady),
+            current => Ok(Sequenced::Conflict(current as u64)),
+        }
+    }
+
     /// Acknowledge that a message was processed
     async fn ack(
         &mut self,
@@ -326,6 +420,19 @@ impl super::PubSub for RedisConnection {
         Ok(stream_ids_to_messages(messages.ids, preserve_sequence))
     }
 
+    /// Get messages from a channel between two ids, inclusive
+    async fn get_messages_between(
+        &mut self,
+        channel: &str,
+        from: &str,
+        to: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        let messages: StreamRangeReply = self.multiplex.xrange(channel, from, to).await?;
+
+        Ok(stream_ids_to_messages(messages.ids, preserve_sequence))
+    }
+
     /// Get the last message in a channel
     async fn last_message(&mut self, channel: &str, preserve_sequence: bool) -> Result<Message> {
         let message: StreamRangeReply =
@@ -337,6 +444,109 @@ impl super::PubSub for RedisConnection {
 
         Ok(parse_message(id, preserve_sequence))
     }
+
+    /// Get the value of a counter
+    async fn counter(&mut self, key: &str) -> Result<Option<u64>> {
+        commands::counter(&mut self.multiplex, key).await
+    }
+
+    /// Atomically increment a counter, returning the new value
+    async fn increment(&mut self, key: &str) -> Result<u64> {
+        commands::increment(&mut self.multiplex, key).await
+    }
+
+    /// Atomically raise a counter to a value if it's lower, returning the
+    /// resulting value
+    async fn set_max(&mut self, key: &str, value: u64) -> Result<u64> {
+        commands::set_max(&mut self.multiplex, key, value).await
+    }
+
+    /// Set a field of a hash, returning true if the field is new
+    async fn hash_set(&mut self, key: &str, field: &str, value: &[u8]) -> Result<bool> {
+        commands::hash_set(&mut self.multiplex, key, field, value).await
+    }
+
+    /// Set a field of a hash if it doesn't exist, returning true if it was set
+    async fn hash_set_if_missing(&mut self, key: &str, field: &str, value: &[u8]) -> Result<bool> {
+        commands::hash_set_if_missing(&mut self.multiplex, key, field, value).await
+    }
+
+    /// Get a field of a hash
+    async fn hash_get(&mut self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        commands::hash_get(&mut self.multiplex, key, field).await
+    }
+
+    /// Get all fields of a hash
+    async fn hash_get_all(&mut self, key: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        commands::hash_get_all(&mut self.multiplex, key).await
+    }
+
+    /// Atomically remove a field from a hash, deleting `delete_if_empty` if
+    /// the hash is left empty.  Returns the number of fields left.
+    async fn hash_remove(
+        &mut self,
+        key: &str,
+        field: &str,
+        delete_if_empty: &[&str],
+    ) -> Result<usize> {
+        commands::hash_remove(&mut self.multiplex, key, field, delete_if_empty).await
+    }
+
+    /// Get the value of a counter and all fields of a hash, atomically
+    async fn counter_and_hash(
+        &mut self,
+        counter_key: &str,
+        hash_key: &str,
+    ) -> Result<(Option<u64>, Vec<(String, Vec<u8>)>)> {
+        commands::counter_and_hash(&mut self.multiplex, counter_key, hash_key).await
+    }
+
+    /// Atomically replace a hash and set its ready key, unless the ready key
+    /// exists
+    async fn hash_initialize(
+        &mut self,
+        key: &str,
+        ready_key: &str,
+        ready: u64,
+        values: &[(String, Vec<u8>)],
+    ) -> Result<bool> {
+        commands::hash_initialize(&mut self.multiplex, key, ready_key, ready, values).await
+    }
+
+    /// Get the keys matching a pattern
+    async fn keys(&mut self, pattern: &str) -> Result<Vec<String>> {
+        commands::keys(&mut self.multiplex, pattern).await
+    }
+
+    /// Delete a key
+    async fn delete(&mut self, key: &str) -> Result<()> {
+        commands::delete(&mut self.multiplex, key).await
+    }
+
+    /// Take a lease on a key, unless someone else holds it
+    async fn acquire_lease(&mut self, key: &str, owner: &str, ttl_ms: u64) -> Result<bool> {
+        commands::acquire_lease(&mut self.multiplex, key, owner, ttl_ms).await
+    }
+
+    /// Release a lease if the owner still holds it
+    async fn release_lease(&mut self, key: &str, owner: &str) -> Result<bool> {
+        commands::release_lease(&mut self.multiplex, key, owner).await
+    }
+
+    /// Extend a lease if the owner still holds it
+    async fn renew_lease(&mut self, key: &str, owner: &str, ttl_ms: u64) -> Result<bool> {
+        commands::renew_lease(&mut self.multiplex, key, owner, ttl_ms).await
+    }
+
+    /// Broadcast a message to every listener of a channel
+    async fn broadcast(&mut self, channel: &str, message: &[u8]) -> Result<()> {
+        commands::broadcast(&mut self.multiplex, channel, message).await
+    }
+
+    /// Listen for broadcasts on all channels matching a pattern
+    async fn listen(config: Config, pattern: &str) -> Result<Broadcasts> {
+        commands::listen(client(config)?, pattern).await
+    }
 }
 
 #[cfg(test)]
@@ -468,6 +678,128 @@ pub mod tests {
         assert_eq!(results, ("2".into(), messages[1].into()));
     }
 
+    #[tokio::test]
+    async fn stream_get_messages_between() {
+        let (config, channel) = setup();
+        let group = "group 1";
+
+        let mut connection = RedisConnection::new(config).await.unwrap();
+        connection.subscribe(&channel, group).await.unwrap();
+
+        for key in 1..=4 {
+            connection
+                .publish(&channel, &key.to_string(), key.to_string().as_bytes(), None)
+                .await
+                .unwrap();
+        }
+
+        let results = connection
+            .get_messages_between(&channel, "2", "3", false)
+            .await
+            .unwrap();
+        let values = results
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+
+        assert_eq!(values, vec![b"2".to_vec(), b"3".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn stream_publish_sequenced() {
+        let (config, channel) = setup();
+        let counter_key = Uuid::new_v4().to_string();
+        let active_channels = Uuid::new_v4().to_string();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        for sequence_num in 1..=2 {
+            let published = connection
+                .publish_sequenced(
+                    &channel,
+                    &counter_key,
+                    sequence_num,
+                    sequence_num.to_string().as_bytes(),
+                    Some(&active_channels),
+                    None,
+                )
+                .await
+                .unwrap();
+            assert_eq!(published, Sequenced::Published);
+        }
+
+        // a taken or skipped sequence number leaves the stream alone
+        for sequence_num in [2, 4] {
+            let published = connection
+                .publish_sequenced(&channel, &counter_key, sequence_num, b"late", None, None)
+                .await
+                .unwrap();
+            assert_eq!(published, Sequenced::Conflict(2));
+        }
+
+        let results = connection
+            .get_messages_from(&channel, "0", false)
+            .await
+            .unwrap();
+        let values = results
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+
+        assert_eq!(values, vec![b"1".to_vec(), b"2".to_vec()]);
+        assert_eq!(connection.counter(&counter_key).await.unwrap(), Some(2));
+        assert_eq!(
+            connection.active_channels(&active_channels).await.unwrap(),
+            vec![channel.clone()]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_publish_sequenced_with_hash_updates() {
+        let (config, channel) = setup();
+        let counter_key = Uuid::new_v4().to_string();
+        let hash_key = Uuid::new_v4().to_string();
+        let ready_key = Uuid::new_v4().to_string();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        let set = vec![("a".to_string(), b"1".to_vec())];
+        let removed = vec!["b".to_string()];
+        let updates = HashUpdates {
+            key: &hash_key,
+            ready_key: &ready_key,
+            set: &set,
+            removed: &removed,
+        };
+
+        // nothing is published until the hash is ready
+        let published = connection
+            .publish_sequenced(&channel, &counter_key, 1, b"1", None, Some(updates))
+            .await
+            .unwrap();
+        assert_eq!(published, Sequenced::NotReady);
+        assert_eq!(connection.counter(&counter_key).await.unwrap(), None);
+
+        let values = vec![("b".to_string(), b"2".to_vec())];
+        let initialize = connection.hash_initialize(&hash_key, &ready_key, 0, &values);
+        assert!(initialize.await.unwrap());
+
+        // the hash is only initialized once
+        let initialize = connection.hash_initialize(&hash_key, &ready_key, 0, &[]);
+        assert!(!initialize.await.unwrap());
+
+        let published = connection
+            .publish_sequenced(&channel, &counter_key, 1, b"1", None, Some(updates))
+            .await
+            .unwrap();
+        assert_eq!(published, Sequenced::Published);
+
+        let (counter, hash) = connection
+            .counter_and_hash(&counter_key, &hash_key)
+            .await
+            .unwrap();
+        assert_eq!(counter, Some(1));
+        assert_eq!(hash, set);
+    }
+
     #[tokio::test]
     async fn stream_get_all_channels() {
         let (config, channel) = setup();
@@ -544,4 +876,144 @@ pub mod tests {
         let results = connection.active_channels(&active_channels).await.unwrap();
         assert_eq!(results, vec![channels[1].clone()]);
     }
+
+    #[tokio::test]
+    async fn stream_counters() {
+        let (config, key) = setup();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        assert_eq!(connection.counter(&key).await.unwrap(), None);
+        assert_eq!(connection.set_max(&key, 5).await.unwrap(), 5);
+        assert_eq!(connection.set_max(&key, 3).await.unwrap(), 5);
+        assert_eq!(connection.increment(&key).await.unwrap(), 6);
+        assert_eq!(connection.counter(&key).await.unwrap(), Some(6));
+
+        connection.delete(&key).await.unwrap();
+        assert_eq!(connection.counter(&key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn stream_hashes() {
+        let (config, key) = setup();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        assert!(connection.hash_set(&key, "a", b"1").await.unwrap());
+        assert!(connection.hash_set(&key, "b", b"2").await.unwrap());
+        assert!(!connection.hash_set(&key, "a", b"3").await.unwrap());
+
+        assert_eq!(
+            connection.hash_get(&key, "a").await.unwrap(),
+            Some(b"3".to_vec())
+        );
+        assert_eq!(connection.hash_get(&key, "c").await.unwrap(), None);
+
+        assert!(
+            !connection
+                .hash_set_if_missing(&key, "a", b"4")
+                .await
+                .unwrap()
+        );
+        assert!(
+            connection
+                .hash_set_if_missing(&key, "c", b"5")
+                .await
+                .unwrap()
+        );
+        assert_eq!(connection.hash_remove(&key, "c", &[]).await.unwrap(), 2);
+
+        let mut values = connection.hash_get_all(&key).await.unwrap();
+        values.sort();
+        assert_eq!(
+            values,
+            vec![("a".into(), b"3".to_vec()), ("b".into(), b"2".to_vec())]
+        );
+
+        // other keys are deleted with the last field
+        let other_key = format!("{key}:other");
+        connection.increment(&other_key).await.unwrap();
+        assert_eq!(
+            connection
+                .hash_remove(&key, "a", &[&other_key])
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(connection.counter(&other_key).await.unwrap(), Some(1));
+        assert_eq!(
+            connection
+                .hash_remove(&key, "b", &[&other_key])
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(connection.counter(&other_key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn stream_keys() {
+        let (config, key) = setup();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+        let keys = [format!("{key}:a"), format!("{key}:b")];
+
+        for key in keys.iter() {
+            connection.increment(key).await.unwrap();
+        }
+        assert_eq!(
+            connection.keys(&format!("{key}:*")).await.unwrap(),
+            keys.to_vec()
+        );
+
+        for key in keys.iter() {
+            connection.delete(key).await.unwrap();
+        }
+        assert!(
+            connection
+                .keys(&format!("{key}:*"))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_leases() {
+        let (config, key) = setup();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        assert!(connection.acquire_lease(&key, "a", 10_000).await.unwrap());
+        assert!(!connection.acquire_lease(&key, "b", 10_000).await.unwrap());
+        assert!(!connection.release_lease(&key, "b").await.unwrap());
+        assert!(connection.release_lease(&key, "a").await.unwrap());
+        assert!(connection.acquire_lease(&key, "b", 10_000).await.unwrap());
+
+        // only the owner can renew a lease
+        assert!(connection.renew_lease(&key, "b", 10_000).await.unwrap());
+        assert!(!connection.renew_lease(&key, "a", 10_000).await.unwrap());
+
+        // an expired lease can be taken
+        connection.delete(&key).await.unwrap();
+        assert!(connection.acquire_lease(&key, "a", 1).await.unwrap());
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        assert!(connection.acquire_lease(&key, "b", 10_000).await.unwrap());
+
+        connection.delete(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stream_broadcast_and_listen() {
+        let (config, channel) = setup();
+        let mut listener = RedisConnection::listen(config.clone(), &format!("{channel}:*"))
+            .await
+            .unwrap();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        connection
+            .broadcast(&format!("{channel}:1"), b"hello")
+            .await
+            .unwrap();
+
+        let (received_channel, message) = listener.next().await.unwrap();
+        assert_eq!(received_channel, format!("{channel}:1"));
+        assert_eq!(message, b"hello".to_vec());
+    }
 }
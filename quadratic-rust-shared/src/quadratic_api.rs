@@ -44,6 +44,9 @@ pub struct LastCheckpoint {
     version: String,
     s3_key: String,
     s3_bucket: String,
+    /// A url to download the checkpoint's file from, when reading it
+    #[serde(default, skip_serializing)]
+    pub data_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -110,6 +113,21 @@ pub async fn get_file_checkpoint(
     Ok(response.json::<Checkpoint>().await?.last_checkpoint)
 }
 
+/// Download the file of a checkpoint retrieved with `get_file_checkpoint`.
+pub async fn get_file_checkpoint_data(checkpoint: &LastCheckpoint) -> Result<Vec<u8>> {
+    let url = checkpoint.data_url.as_deref().ok_or_else(|| {
+        SharedError::QuadraticApi(format!(
+            "Checkpoint {} has no data url",
+            checkpoint.sequence_number
+        ))
+    })?;
+    let response = get_client(url, "").send().await?;
+
+    handle_response(&response)?;
+
+    Ok(response.bytes().await?.to_vec())
+}
+
 /// Set the file's checkpoint with the quadratic API server.
 pub async fn set_file_checkpoint(
     base_url: &str,
@@ -126,6 +144,7 @@ pub async fn set_file_checkpoint(
         version,
         s3_key,
         s3_bucket,
+        data_url: None,
     };
 
     let response = reqwest::Client::new()
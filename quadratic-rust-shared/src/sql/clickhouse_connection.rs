@@ -0,0 +1,824 @@
+//! ClickHouse
+//!
+//! Functions to interact with ClickHouse over its HTTP interface.  Results are
+//! read as `JSONCompactEachRowWithNamesAndTypes`: a line of column names, a
+//! line of column types, then a line for each row.  Rows are converted to
+//! Arrow using their column's ClickHouse type, eg, `Nullable(Decimal(10, 2))`.
+//!
+//! Parameters are bound with ClickHouse's query parameters, so placeholders
+//! are named and typed, eg, `{p1:Int64}` for the first parameter.
+
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow::datatypes::Date32Type;
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use bytes::Bytes;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::{Stream, StreamExt, stream};
+use reqwest::{Certificate, Client, Identity, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::error::{Result, SharedError};
+use crate::sql::error::Sql as SqlError;
+use crate::sql::parameter::SqlParameter;
+use crate::sql::schema::{DatabaseSchema, SchemaColumn, TableKind, non_empty, optional};
+use crate::sql::ssh::SshConfig;
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_rows};
+use crate::sql::tls::{TlsConfig, TlsMode};
+use crate::sql::{ArrowType, Connection, parse_port};
+
+const DEFAULT_PORT: u16 = 8123;
+const DEFAULT_TLS_PORT: u16 = 8443;
+const FORMAT: &str = "JSONCompactEachRowWithNamesAndTypes";
+
+/// ClickHouse connection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClickHouseConnection {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub host: String,
+    pub port: Option<String>,
+    pub database: String,
+    pub tls: Option<TlsConfig>,
+    pub ssh: Option<SshConfig>,
+
+    // seconds to cache query results for, overriding the service's default
+    #[serde(default)]
+    pub cache_ttl_s: Option<u64>,
+
+    // reject queries that write data or change the schema
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// An HTTP client for a ClickHouse server.  ClickHouse cancels queries by
+/// their id, so each query sent through the client has the current id.
+#[derive(Debug)]
+pub struct ClickHouseClient {
+    client: Client,
+    url: Url,
+    query_id: String,
+}
+
+#[derive(Debug)]
+pub struct ClickHouseColumn {
+    name: String,
+    data_type: String,
+}
+
+/// Rows share their columns, which are read from the response's header
+#[derive(Debug)]
+pub struct ClickHouseRow {
+    columns: Arc<[ClickHouseColumn]>,
+    values: Vec<Value>,
+}
+
+type RowStream = Pin<Box<dyn Stream<Item = Result<ClickHouseRow>> + Send>>;
+
+fn query_error(e: impl ToString) -> SharedError {
+    SharedError::Sql(SqlError::Query(e.to_string()))
+}
+
+impl ClickHouseConnection {
+    /// Create a new ClickHouse connection
+    pub fn new(
+        username: Option<String>,
+        password: Option<String>,
+        host: String,
+        port: Option<String>,
+        database: String,
+        tls: Option<TlsConfig>,
+        ssh: Option<SshConfig>,
+    ) -> ClickHouseConnection {
+        ClickHouseConnection {
+            username,
+            password,
+            host,
+            port,
+            database,
+            tls,
+            ssh,
+            cache_ttl_s: None,
+            read_only: false,
+        }
+    }
+
+    /// Build the HTTP client.  ClickHouse serves HTTPS on its own port, so
+    /// there's nothing to negotiate: any mode but `disable` uses HTTPS, and
+    /// `prefer` doesn't verify the certificate, like `require`.
+    fn http_client(&self) -> Result<Client> {
+        let connect_error = |e: reqwest::Error| SharedError::Sql(SqlError::Connect(e.to_string()));
+        let mut builder = Client::builder();
+
+        if let Some(ref tls) = self.tls {
+            let mode = match self.ssh {
+                Some(_) => tls.tunnelled_mode(),
+                None => tls.mode,
+            };
+
+            builder = match mode {
+                TlsMode::Disable | TlsMode::VerifyFull => builder,
+                TlsMode::Prefer | TlsMode::Require => builder.danger_accept_invalid_certs(true),
+                TlsMode::VerifyCa => builder.danger_accept_invalid_hostnames(true),
+            };
+
+            if let Some(ref ca_certificate) = tls.ca_certificate {
+                let certificate =
+                    Certificate::from_pem(ca_certificate.as_bytes()).map_err(connect_error)?;
+                builder = builder.add_root_certificate(certificate);
+            }
+
+            if let (Some(client_certificate), Some(client_key)) =
+                (&tls.client_certificate, &tls.client_key)
+            {
+                let identity =
+                    Identity::from_pkcs8_pem(client_certificate.as_bytes(), client_key.as_bytes())
+                        .map_err(connect_error)?;
+                builder = builder.identity(identity);
+            }
+        }
+
+        builder.build().map_err(connect_error)
+    }
+
+    /// Build a query's request, binding `params` to the placeholders `{p1:T}`,
+    /// `{p2:T}`, etc.
+    ///
+    /// Large integers and decimals are quoted so that they aren't rounded to
+    /// an f64, and a query with the same id (eg, one that was abandoned after
+    /// reaching the byte limit) is replaced rather than rejected.
+    fn request(
+        &self,
+        pool: &ClickHouseClient,
+        sql: &str,
+        params: &[SqlParameter],
+    ) -> RequestBuilder {
+        let mut query = vec![
+            ("database".to_string(), self.database.to_owned()),
+            ("query_id".into(), pool.query_id.to_owned()),
+            ("default_format".into(), FORMAT.into()),
+            ("output_format_json_quote_64bit_integers".into(), "1".into()),
+            ("output_format_json_quote_decimals".into(), "1".into()),
+            ("replace_running_query".into(), "1".into()),
+        ];
+
+        // 2 rejects writes and DDL, while still allowing the settings above
+        if self.read_only {
+            query.push(("readonly".into(), "2".into()));
+        }
+
+        for (index, param) in params.iter().enumerate() {
+            query.push((format!("param_p{}", index + 1), parameter_value(param)));
+        }
+
+        let request = pool
+            .client
+            .post(pool.url.to_owned())
+            .query(&query)
+            .body(sql.to_owned());
+
+        match self.username {
+            Some(ref username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        }
+    }
+
+    /// Send a query, streaming its rows as they're read
+    async fn query_rows(
+        &self,
+        pool: &ClickHouseClient,
+        sql: &str,
+        params: &[SqlParameter],
+    ) -> Result<RowStream> {
+        let response = self
+            .request(pool, sql, params)
+            .send()
+            .await
+            .map_err(query_error)?;
+
+        if !response.status().is_success() {
+            let message = response.text().await.map_err(query_error)?;
+            return Err(query_error(message.trim()));
+        }
+
+        let mut lines = lines(response.bytes_stream());
+
+        // statements without results (eg, `create table`) have no header
+        let header = |line: Option<Result<String>>| match line {
+            Some(line) => {
+                let line = line?;
+                serde_json::from_str::<Vec<String>>(&line)
+                    .map(Some)
+                    .map_err(|_| query_error(line.trim()))
+            }
+            None => Ok(None),
+        };
+        let (Some(names), Some(types)) = (header(lines.next().await)?, header(lines.next().await)?)
+        else {
+            return Ok(Box::pin(stream::empty()));
+        };
+
+        let columns = names
+            .into_iter()
+            .zip(types)
+            .map(|(name, data_type)| ClickHouseColumn { name, data_type })
+            .collect::<Arc<[ClickHouseColumn]>>();
+
+        let rows = lines.map(move |line| {
+            let line = line?;
+
+            // errors that happen while the results are sent are written in
+            // place of a row, eg, `Code: 241. DB::Exception: ...`
+            let values =
+                serde_json::from_str::<Vec<Value>>(&line).map_err(|_| query_error(line.trim()))?;
+
+            Ok(ClickHouseRow {
+                columns: Arc::clone(&columns),
+                values,
+            })
+        });
+
+        Ok(Box::pin(rows))
+    }
+
+    /// Query all rows from a ClickHouse database
+    async fn query_all(
+        &self,
+        pool: &ClickHouseClient,
+        sql: &str,
+        params: &[SqlParameter],
+    ) -> Result<Vec<ClickHouseRow>> {
+        let mut rows = vec![];
+        let mut stream = self.query_rows(pool, sql, params).await?;
+
+        while let Some(row) = stream.next().await {
+            rows.push(row?);
+        }
+
+        Ok(rows)
+    }
+}
+
+/// Split a response's body into lines
+fn lines(
+    body: impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static,
+) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
+    let lines = stream::unfold(
+        (Box::pin(body), Vec::new(), false),
+        |(mut body, mut buffer, mut done)| async move {
+            loop {
+                if let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                    let line = String::from_utf8_lossy(&buffer[..end]).into_owned();
+                    buffer.drain(..=end);
+                    return Some((Ok(line), (body, buffer, done)));
+                }
+
+                if done {
+                    if buffer.is_empty() {
+                        return None;
+                    }
+
+                    let line = String::from_utf8_lossy(&buffer).into_owned();
+                    buffer.clear();
+                    return Some((Ok(line), (body, buffer, done)));
+                }
+
+                match body.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                    Some(Err(e)) => return Some((Err(query_error(e)), (body, vec![], true))),
+                    None => done = true,
+                }
+            }
+        },
+    );
+
+    Box::pin(lines)
+}
+
+/// Format a parameter's value for ClickHouse, which parses query parameters
+/// in its escaped (TSV) format
+fn parameter_value(param: &SqlParameter) -> String {
+    match param {
+        SqlParameter::Null => "\\N".into(),
+        SqlParameter::Boolean(value) => value.to_string(),
+        SqlParameter::Integer(value) => value.to_string(),
+        SqlParameter::Float(value) => value.to_string(),
+        SqlParameter::Text(value) => value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n"),
+        SqlParameter::Date(value) => value.format("%Y-%m-%d").to_string(),
+        SqlParameter::Time(value) => value.format("%H:%M:%S%.f").to_string(),
+        SqlParameter::DateTime(value) => value.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+    }
+}
+
+/// Remove the wrappers that don't change how a value is converted, eg,
+/// `LowCardinality(Nullable(String))` is a `String`
+fn unwrap_type(data_type: &str) -> &str {
+    let mut data_type = data_type.trim();
+
+    for wrapper in ["Nullable(", "LowCardinality("] {
+        if let Some(inner) = data_type
+            .strip_prefix(wrapper)
+            .and_then(|inner| inner.strip_suffix(')'))
+        {
+            return unwrap_type(inner);
+        }
+    }
+
+    data_type = data_type.split('(').next().unwrap_or(data_type);
+    data_type.trim()
+}
+
+/// Parse a number, which is quoted when it won't fit in an f64
+fn parse_number<T: FromStr + Default>(value: &Value) -> T {
+    match value {
+        Value::String(value) => value.parse().ok(),
+        Value::Number(value) => value.to_string().parse().ok(),
+        _ => None,
+    }
+    .unwrap_or_default()
+}
+
+fn parse_string(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_owned()
+}
+
+/// The columns of a key or index expression, eg, `id, name` or `(id, name)`.
+/// Expressions are skipped.
+fn key_columns(expression: &str) -> Vec<String> {
+    let expression = expression.trim();
+    let expression = match expression.starts_with('(') && expression.ends_with(')') {
+        true => &expression[1..expression.len() - 1],
+        false => expression,
+    };
+
+    expression
+        .split(", ")
+        .map(|column| column.trim().trim_matches('`'))
+        .filter(|column| !column.is_empty() && !column.contains(['(', ' ']))
+        .map(String::from)
+        .collect()
+}
+
+/// Convert a value to an Arrow type using its ClickHouse type.  Nulls are
+/// converted to their type's default value.
+fn value_to_arrow(data_type: &str, value: &Value) -> ArrowType {
+    match unwrap_type(data_type) {
+        "Int8" => ArrowType::Int8(parse_number(value)),
+        "Int16" => ArrowType::Int16(parse_number(value)),
+        "Int32" => ArrowType::Int32(parse_number(value)),
+        "Int64" => ArrowType::Int64(parse_number(value)),
+        "UInt8" => ArrowType::UInt8(parse_number(value)),
+        "UInt16" => ArrowType::UInt16(parse_number(value)),
+        "UInt32" => ArrowType::UInt32(parse_number(value)),
+        "UInt64" => ArrowType::UInt64(parse_number(value)),
+        "Int128" | "Int256" | "UInt128" | "UInt256" | "Decimal" | "Decimal32" | "Decimal64"
+        | "Decimal128" | "Decimal256" => ArrowType::BigDecimal(parse_number(value)),
+        "Float32" => ArrowType::Float32(parse_number(value)),
+        "Float64" => ArrowType::Float64(parse_number(value)),
+        "Bool" => ArrowType::Boolean(value.as_bool().unwrap_or_default()),
+        "String" | "FixedString" | "Enum8" | "Enum16" | "IPv4" | "IPv6" => {
+            ArrowType::Utf8(parse_string(value))
+        }
+        "UUID" => ArrowType::Uuid(Uuid::parse_str(&parse_string(value)).unwrap_or_default()),
+        "Date" | "Date32" => {
+            let naive_date =
+                NaiveDate::parse_from_str(&parse_string(value), "%Y-%m-%d").unwrap_or_default();
+            ArrowType::Date32(Date32Type::from_naive_date(naive_date))
+        }
+        // in the column's time zone, which isn't included in the value
+        "DateTime" | "DateTime64" => ArrowType::Timestamp(
+            NaiveDateTime::parse_from_str(&parse_string(value), "%Y-%m-%d %H:%M:%S%.f")
+                .unwrap_or_default(),
+        ),
+        "Time" | "Time64" => ArrowType::Time32(
+            NaiveTime::parse_from_str(&parse_string(value), "%H:%M:%S%.f").unwrap_or_default(),
+        ),
+        "Array" | "Tuple" | "Map" | "Nested" | "JSON" | "Object" => {
+            ArrowType::Json(value.to_owned())
+        }
+        "Nothing" => ArrowType::Void,
+        // try to convert others to a string
+        _ => ArrowType::Unsupported,
+    }
+}
+
+#[async_trait]
+impl Connection for ClickHouseConnection {
+    type Conn = ClickHouseClient;
+    type Row = ClickHouseRow;
+    type Column = ClickHouseColumn;
+
+    const KIND: &'static str = "clickhouse";
+
+    fn cache_ttl_s(&self) -> Option<u64> {
+        self.cache_ttl_s
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Get the length of a row
+    fn row_len(row: &Self::Row) -> usize {
+        row.values.len()
+    }
+
+    /// Get the columns of a row
+    fn row_columns(row: &Self::Row) -> Box<dyn Iterator<Item = &Self::Column> + '_> {
+        Box::new(row.columns.iter())
+    }
+
+    /// Get the name of a column
+    fn column_name(col: &Self::Column) -> &str {
+        &col.name
+    }
+
+    /// Connect to a ClickHouse server, through the SSH tunnel if there is
+    /// one.  HTTP is stateless, so this checks that the server can be reached
+    /// with the credentials.
+    async fn connect(&self) -> Result<Self::Conn> {
+        let https = self
+            .tls
+            .as_ref()
+            .is_some_and(|tls| tls.mode != TlsMode::Disable);
+        let scheme = if https { "https" } else { "http" };
+        let port = parse_port(&self.port)?.unwrap_or(match https {
+            true => DEFAULT_TLS_PORT,
+            false => DEFAULT_PORT,
+        });
+
+        let url = match &self.ssh {
+            Some(ssh) => {
+                let local_addr = ssh.tunnel(&self.host, port).await?;
+                format!("{scheme}://{local_addr}/")
+            }
+            None => format!("{scheme}://{}:{port}/", self.host),
+        };
+
+        let pool = ClickHouseClient {
+            client: self.http_client()?,
+            url: Url::parse(&url)
+                .map_err(|e| SharedError::Sql(SqlError::Connect(e.to_string())))?,
+            query_id: Uuid::new_v4().to_string(),
+        };
+
+        self.query_all(&pool, "select 1", &[]).await.map_err(|e| {
+            SharedError::Sql(SqlError::Connect(format!("{:?}: {e}", self.database)))
+        })?;
+
+        Ok(pool)
+    }
+
+    /// Query rows from a ClickHouse database
+    async fn query(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Bytes, bool, usize)> {
+        let mut rows = vec![];
+        let mut over_the_limit = false;
+        let mut bytes = 0;
+        let mut stream = self.query_rows(pool, sql, params).await?;
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+            bytes += Self::row_len(&row) as u64;
+
+            if max_bytes.is_some_and(|max_bytes| bytes > max_bytes) {
+                over_the_limit = true;
+                break;
+            }
+
+            rows.push(row);
+        }
+
+        let (bytes, num_records) = Self::to_parquet(rows)?;
+
+        Ok((bytes, over_the_limit, num_records))
+    }
+
+    /// Stream a page of rows from a ClickHouse database
+    async fn query_stream(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        let rows = self.query_rows(pool, sql, params).await?;
+
+        stream_rows::<Self, _>(rows, page, sender, max_bytes).await
+    }
+
+    /// Give the connection's next query a new id
+    async fn session_id(&self, pool: &mut Self::Conn) -> Result<Option<String>> {
+        pool.query_id = Uuid::new_v4().to_string();
+
+        Ok(Some(pool.query_id.to_owned()))
+    }
+
+    /// Cancel a query by its id with `KILL QUERY`
+    async fn cancel(&self, session_id: &str) -> Result<()> {
+        let cancel_error = |e: String| SharedError::Sql(SqlError::Cancel(e));
+        let query_id = Uuid::parse_str(session_id).map_err(|e| cancel_error(e.to_string()))?;
+        let pool = self.connect().await?;
+
+        self.query_all(
+            &pool,
+            &format!("KILL QUERY WHERE query_id = '{query_id}' ASYNC"),
+            &[],
+        )
+        .await
+        .map_err(|e| cancel_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Get the schema of a ClickHouse database
+    async fn schema(&self, pool: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema> {
+        // ClickHouse calls schemas databases, so default to the connection's database
+        let schemas = match schemas.is_empty() {
+            true => vec![self.database.to_owned()],
+            false => schemas.to_vec(),
+        };
+        let placeholders = (1..=schemas.len())
+            .map(|index| format!("{{p{index}:String}}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let params = schemas
+            .into_iter()
+            .map(SqlParameter::Text)
+            .collect::<Vec<_>>();
+        let mut schema = DatabaseSchema::new(self.database.to_owned());
+
+        // tables, views and their columns
+        let sql = format!(
+            "
+            select c.database, c.table, t.engine, c.name, c.type, c.default_expression, c.comment,
+                t.comment, t.total_rows, t.primary_key
+            from system.columns as c
+                inner join system.tables as t on t.database = c.database and t.name = c.table
+            where c.database in ({placeholders})
+            order by c.database, c.table, c.position"
+        );
+
+        for row in self.query_all(pool, &sql, &params).await? {
+            let row_get = |index: usize| parse_string(&row.values[index]);
+            let table = schema.table_mut(&row_get(0), &row_get(1));
+
+            if table.columns.is_empty() {
+                table.kind = match row_get(2).ends_with("View") {
+                    true => TableKind::View,
+                    false => TableKind::Table,
+                };
+                table.comment = non_empty(Some(row_get(7)));
+                table.row_count = (!row.values[8].is_null()).then(|| parse_number(&row.values[8]));
+                // the sorting key, which isn't unique in ClickHouse
+                table.primary_key = key_columns(&row_get(9));
+            }
+
+            let data_type = row_get(4);
+            let nullable = data_type
+                .strip_prefix("Nullable(")
+                .and_then(|inner| inner.strip_suffix(')'));
+
+            table.columns.push(SchemaColumn {
+                name: row_get(3),
+                r#type: nullable.unwrap_or(&data_type).to_owned(),
+                is_nullable: nullable.is_some(),
+                default: non_empty(Some(row_get(5))),
+                comment: non_empty(Some(row_get(6))),
+            });
+        }
+
+        // data skipping indexes, ClickHouse has no foreign keys
+        let sql = format!(
+            "
+            select database, table, name, expr
+            from system.data_skipping_indices
+            where database in ({placeholders})
+            order by database, table, name"
+        );
+
+        for row in optional("indexes", self.query_all(pool, &sql, &params).await) {
+            let row_get = |index: usize| parse_string(&row.values[index]);
+
+            if let Some(table) = schema.existing_table_mut(&row_get(0), &row_get(1)) {
+                for column in key_columns(&row_get(3)) {
+                    table.add_index_column(&row_get(2), column, false, false);
+                }
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Convert a row to an Arrow type
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType {
+        value_to_arrow(&column.data_type, &row.values[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    fn new_clickhouse_connection() -> ClickHouseConnection {
+        ClickHouseConnection::new(
+            Some("user".into()),
+            Some("password".into()),
+            "0.0.0.0".into(),
+            Some("8123".into()),
+            "clickhouse-connection".into(),
+            None,
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_clickhouse_connection() {
+        let connection = new_clickhouse_connection();
+        let pool = connection.connect().await;
+
+        assert!(pool.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_clickhouse_query_to_arrow() {
+        let connection = new_clickhouse_connection();
+        let pool = connection.connect().await.unwrap();
+        let sql = "select * from all_native_data_types order by id limit 1";
+        let rows = connection.query_all(&pool, sql, &[]).await.unwrap();
+
+        let row = &rows[0];
+        let columns = &row.columns;
+        let to_arrow = |index: usize| ClickHouseConnection::to_arrow(row, &columns[index], index);
+        let big_decimal = |value: &str| ArrowType::BigDecimal(BigDecimal::from_str(value).unwrap());
+
+        assert_eq!(to_arrow(0), ArrowType::UInt32(1));
+        assert_eq!(to_arrow(1), ArrowType::Int8(127));
+        assert_eq!(to_arrow(2), ArrowType::Int16(32767));
+        assert_eq!(to_arrow(3), ArrowType::Int32(2147483647));
+        assert_eq!(to_arrow(4), ArrowType::Int64(9223372036854775807));
+        assert_eq!(
+            to_arrow(5),
+            big_decimal("170141183460469231731687303715884105727")
+        );
+        assert_eq!(to_arrow(6), ArrowType::UInt8(255));
+        assert_eq!(to_arrow(7), ArrowType::UInt16(65535));
+        assert_eq!(to_arrow(8), ArrowType::UInt32(4294967295));
+        assert_eq!(to_arrow(9), ArrowType::UInt64(18446744073709551615));
+        assert_eq!(to_arrow(10), big_decimal("1"));
+        assert_eq!(to_arrow(11), ArrowType::Float32(123.45));
+        assert_eq!(to_arrow(12), ArrowType::Float64(123456789.123456));
+        assert_eq!(to_arrow(13), big_decimal("12345.67"));
+        assert_eq!(to_arrow(14), big_decimal("1234.5678"));
+        assert_eq!(to_arrow(15), ArrowType::Boolean(true));
+        assert_eq!(to_arrow(16), ArrowType::Utf8("string_data".into()));
+        assert_eq!(to_arrow(17), ArrowType::Utf8("fixed".into()));
+        assert_eq!(to_arrow(18), ArrowType::Utf8("".into()));
+        assert_eq!(to_arrow(19), ArrowType::Utf8("low_cardinality_data".into()));
+        assert_eq!(to_arrow(20), ArrowType::Utf8("value1".into()));
+        assert_eq!(
+            to_arrow(21),
+            ArrowType::Uuid(Uuid::parse_str("123e4567-e89b-12d3-a456-426614174000").unwrap())
+        );
+        assert_eq!(to_arrow(22), ArrowType::Utf8("192.168.0.1".into()));
+        assert_eq!(to_arrow(23), ArrowType::Utf8("2001:db8::1".into()));
+        assert_eq!(to_arrow(24), ArrowType::Date32(19871));
+        assert_eq!(to_arrow(25), ArrowType::Date32(19871));
+        assert_eq!(
+            to_arrow(26),
+            ArrowType::Timestamp(NaiveDateTime::from_str("2024-05-28T12:34:56").unwrap())
+        );
+        assert_eq!(
+            to_arrow(27),
+            ArrowType::Timestamp(NaiveDateTime::from_str("2024-05-28T12:34:56.789").unwrap())
+        );
+        assert_eq!(to_arrow(28), ArrowType::Json(json!([1, 2, 3])));
+        assert_eq!(to_arrow(29), ArrowType::Int32(0));
+    }
+
+    #[tokio::test]
+    async fn test_clickhouse_query_with_parameters() {
+        let connection = new_clickhouse_connection();
+        let pool = connection.connect().await.unwrap();
+        let sql =
+            "select {p1:String} as text, {p2:Int64} + 1 as number, {p3:Nullable(String)} as empty";
+        let params = [
+            SqlParameter::Text("O'Brien\t\\".into()),
+            SqlParameter::Integer(41),
+            SqlParameter::Null,
+        ];
+        let rows = connection.query_all(&pool, sql, &params).await.unwrap();
+
+        let row = &rows[0];
+        assert_eq!(row.values[0], json!("O'Brien\t\\"));
+        assert_eq!(parse_number::<i64>(&row.values[1]), 42);
+        assert_eq!(row.values[2], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_clickhouse_query_error() {
+        let connection = new_clickhouse_connection();
+        let pool = connection.connect().await.unwrap();
+        let error = connection
+            .query_all(&pool, "select * from missing_table", &[])
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("UNKNOWN_TABLE"));
+    }
+
+    #[tokio::test]
+    async fn test_clickhouse_schema() {
+        let connection = new_clickhouse_connection();
+        let mut pool = connection.connect().await.unwrap();
+        let schema = connection.schema(&mut pool, &[]).await.unwrap();
+        let table = schema
+            .tables
+            .get("clickhouse-connection.all_native_data_types")
+            .unwrap();
+        let columns = &table.columns;
+
+        assert_eq!(table.kind, TableKind::Table);
+        assert_eq!(table.primary_key, vec!["id"]);
+        assert_eq!(columns[0], SchemaColumn::new("id", "UInt32", false));
+        assert_eq!(
+            columns[18],
+            SchemaColumn::new("nullable_col", "String", true)
+        );
+        assert_eq!(columns[19].r#type, "LowCardinality(String)");
+        assert_eq!(columns[27].r#type, "DateTime64(3)");
+    }
+
+    #[test]
+    fn test_clickhouse_value_to_arrow() {
+        assert_eq!(unwrap_type("LowCardinality(Nullable(String))"), "String");
+        assert_eq!(unwrap_type("Nullable(Decimal(10, 2))"), "Decimal");
+        assert_eq!(unwrap_type("Array(Nullable(Int32))"), "Array");
+        assert_eq!(unwrap_type("Enum8('a' = 1, 'b' = 2)"), "Enum8");
+
+        assert_eq!(
+            value_to_arrow("Nullable(Int64)", &json!("-9223372036854775808")),
+            ArrowType::Int64(i64::MIN)
+        );
+        assert_eq!(
+            value_to_arrow("Nullable(Decimal(10, 2))", &Value::Null),
+            ArrowType::BigDecimal(BigDecimal::default())
+        );
+        assert_eq!(
+            value_to_arrow("DateTime64(6, 'UTC')", &json!("2024-05-28 12:34:56.123456")),
+            ArrowType::Timestamp(NaiveDateTime::from_str("2024-05-28T12:34:56.123456").unwrap())
+        );
+        assert_eq!(
+            value_to_arrow("Map(String, UInt8)", &json!({"a": 1})),
+            ArrowType::Json(json!({"a": 1}))
+        );
+        assert_eq!(
+            value_to_arrow("Nullable(Nothing)", &Value::Null),
+            ArrowType::Void
+        );
+        assert_eq!(
+            value_to_arrow("AggregateFunction(uniq, UInt64)", &Value::Null),
+            ArrowType::Unsupported
+        );
+    }
+
+    #[test]
+    fn test_clickhouse_parameter_value() {
+        assert_eq!(parameter_value(&SqlParameter::Null), "\\N");
+        assert_eq!(
+            parameter_value(&SqlParameter::Text("a\tb\nc\\d".into())),
+            "a\\tb\\nc\\\\d"
+        );
+        assert_eq!(
+            parameter_value(&SqlParameter::DateTime(
+                NaiveDateTime::from_str("2024-12-31T13:45:00").unwrap()
+            )),
+            "2024-12-31 13:45:00"
+        );
+    }
+
+    #[test]
+    fn test_clickhouse_key_columns() {
+        assert_eq!(key_columns("id"), vec!["id"]);
+        assert_eq!(key_columns("(id, `name`)"), vec!["id", "name"]);
+        assert_eq!(key_columns("id, toDate(created)"), vec!["id"]);
+        assert!(key_columns("").is_empty());
+    }
+}
@@ -0,0 +1,735 @@
+//! DuckDB
+//!
+//! Functions to interact with DuckDB, either a database file or an in-memory
+//! database for querying Parquet, CSV and JSON files directly.
+//!
+//! DuckDB runs in process and its API is blocking, so queries run on a
+//! blocking thread and send their rows back as they're read.  Within a data
+//! directory, queries can only read files in that directory.
+
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime, NaiveTime};
+use duckdb::arrow::datatypes::DataType;
+use duckdb::types::{Null, ToSqlOutput, Value};
+use duckdb::{AccessMode, Config, Connection as DuckDbClient, ToSql, params_from_iter};
+use futures_util::{Stream, StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{Sender, channel};
+
+use crate::error::{Result, SharedError};
+use crate::sql::error::Sql as SqlError;
+use crate::sql::parameter::SqlParameter;
+use crate::sql::schema::{DatabaseSchema, SchemaColumn, TableKind, non_empty, optional};
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_rows};
+use crate::sql::{ArrowType, Connection, resolve_path};
+
+/// Rows buffered between a query's blocking thread and its reader
+const ROW_CHANNEL_CAPACITY: usize = 1_000;
+
+/// DuckDB connection.  Databases are local files (or in memory), so there are
+/// no TLS or SSH options.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuckDbConnection {
+    /// Path to the database file, relative to the data directory, or None
+    /// for an in-memory database
+    pub path: Option<String>,
+
+    /// Set by the service rather than the user, so that users can only read
+    /// files in this directory
+    #[serde(skip)]
+    pub data_dir: Option<PathBuf>,
+}
+
+/// A DuckDB connection is used from a blocking thread for each query
+pub type DuckDbConn = Arc<Mutex<DuckDbClient>>;
+
+#[derive(Debug)]
+pub struct DuckDbColumn {
+    name: String,
+    data_type: DataType,
+}
+
+/// DuckDB rows borrow their statement, so their values are copied out
+#[derive(Debug)]
+pub struct DuckDbRow {
+    columns: Arc<[DuckDbColumn]>,
+    values: Vec<Value>,
+}
+
+type RowStream = Pin<Box<dyn Stream<Item = Result<DuckDbRow>> + Send>>;
+
+fn query_error(e: impl ToString) -> SharedError {
+    SharedError::Sql(SqlError::Query(e.to_string()))
+}
+
+impl DuckDbConnection {
+    /// Create a new DuckDB connection
+    pub fn new(path: Option<String>, data_dir: Option<PathBuf>) -> DuckDbConnection {
+        DuckDbConnection { path, data_dir }
+    }
+
+    /// Open the database, restricting file access to the data directory
+    fn open(path: Option<PathBuf>, data_dir: Option<PathBuf>) -> Result<DuckDbClient> {
+        let connect_error = |e: String| SharedError::Sql(SqlError::Connect(e));
+
+        let client = match path {
+            Some(path) => {
+                let config = Config::default()
+                    .access_mode(AccessMode::ReadOnly)
+                    .map_err(|e| connect_error(e.to_string()))?;
+                DuckDbClient::open_with_flags(&path, config)
+                    .map_err(|e| connect_error(format!("{path:?}: {e}")))?
+            }
+            None => DuckDbClient::open_in_memory().map_err(|e| connect_error(e.to_string()))?,
+        };
+
+        // relative paths are found in the data directory, and locking the
+        // configuration stops queries from lifting these restrictions
+        if let Some(data_dir) = data_dir {
+            let data_dir = data_dir
+                .canonicalize()
+                .map_err(|e| connect_error(format!("Data directory {data_dir:?}: {e}")))?;
+            let data_dir = data_dir.to_string_lossy().replace('\'', "''");
+
+            client
+                .execute_batch(&format!(
+                    "SET file_search_path = '{data_dir}';
+                    SET allowed_directories = ['{data_dir}'];
+                    SET enable_external_access = false;
+                    SET lock_configuration = true;"
+                ))
+                .map_err(|e| connect_error(e.to_string()))?;
+        }
+
+        Ok(client)
+    }
+
+    /// Run a query on a blocking thread, streaming its rows
+    fn query_rows(pool: &DuckDbConn, sql: &str, params: &[SqlParameter]) -> RowStream {
+        let (sender, receiver) = channel(ROW_CHANNEL_CAPACITY);
+        let pool = Arc::clone(pool);
+        let sql = sql.to_owned();
+        let params = params.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = Self::read_rows(&pool, &sql, &params, &sender) {
+                let _ = sender.blocking_send(Err(e));
+            }
+        });
+
+        Box::pin(stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|row| (row, receiver))
+        }))
+    }
+
+    /// Read a query's rows until they run out or the reader hangs up
+    fn read_rows(
+        pool: &DuckDbConn,
+        sql: &str,
+        params: &[SqlParameter],
+        sender: &Sender<Result<DuckDbRow>>,
+    ) -> Result<()> {
+        let client = pool.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut statement = client.prepare(sql).map_err(query_error)?;
+        let mut rows = statement
+            .query(params_from_iter(params.iter().map(DuckDbParameter)))
+            .map_err(query_error)?;
+
+        let columns = match rows.as_ref() {
+            Some(statement) => (0..statement.column_count())
+                .map(|index| DuckDbColumn {
+                    name: statement
+                        .column_name(index)
+                        .map(String::to_owned)
+                        .unwrap_or_default(),
+                    data_type: statement.column_type(index),
+                })
+                .collect::<Arc<[DuckDbColumn]>>(),
+            None => return Ok(()),
+        };
+
+        while let Some(row) = rows.next().map_err(query_error)? {
+            let values = (0..columns.len())
+                .map(|index| row.get::<_, Value>(index))
+                .collect::<duckdb::Result<Vec<Value>>>()
+                .map_err(query_error)?;
+            let row = DuckDbRow {
+                columns: Arc::clone(&columns),
+                values,
+            };
+
+            if sender.blocking_send(Ok(row)).is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Query all rows from a DuckDB database
+    async fn query_all(
+        pool: &DuckDbConn,
+        sql: &str,
+        params: &[SqlParameter],
+    ) -> Result<Vec<DuckDbRow>> {
+        let mut rows = vec![];
+        let mut stream = Self::query_rows(pool, sql, params);
+
+        while let Some(row) = stream.next().await {
+            rows.push(row?);
+        }
+
+        Ok(rows)
+    }
+}
+
+/// The text of a catalog value, or an empty string
+fn text(value: &Value) -> String {
+    match value {
+        Value::Text(value) => value.to_owned(),
+        _ => String::new(),
+    }
+}
+
+/// The text of a catalog value that may not be set
+fn optional_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Text(value) => non_empty(Some(value.to_owned())),
+        _ => None,
+    }
+}
+
+/// The texts of a catalog list
+fn texts(value: &Value) -> Vec<String> {
+    match value {
+        Value::List(values) => values.iter().map(text).collect(),
+        _ => vec![],
+    }
+}
+
+/// The columns of a `create index` statement, since DuckDB doesn't list them.
+/// Expressions are skipped.
+fn index_columns(sql: &str) -> Vec<String> {
+    let on = sql.to_lowercase().find(" on ").unwrap_or(0);
+    let (Some(start), Some(end)) = (sql[on..].find('(').map(|start| start + on), sql.rfind(')'))
+    else {
+        return vec![];
+    };
+
+    if start >= end {
+        return vec![];
+    }
+
+    let mut columns = vec![];
+    let mut depth = 0;
+    let mut column = String::new();
+
+    for char in sql[start + 1..end].chars().chain([',']) {
+        match char {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                let name = column.trim();
+
+                if name.len() > 1 && name.starts_with('"') && name.ends_with('"') {
+                    columns.push(name[1..name.len() - 1].replace("\"\"", "\""));
+                } else if !name.is_empty() && !name.contains(['(', ' ']) {
+                    columns.push(name.to_owned());
+                }
+
+                column.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        column.push(char);
+    }
+
+    columns
+}
+
+/// Binds a parameter to a DuckDB statement
+struct DuckDbParameter<'a>(&'a SqlParameter);
+
+impl ToSql for DuckDbParameter<'_> {
+    fn to_sql(&self) -> duckdb::Result<ToSqlOutput<'_>> {
+        match self.0 {
+            SqlParameter::Null => Null.to_sql(),
+            SqlParameter::Boolean(value) => value.to_sql(),
+            SqlParameter::Integer(value) => value.to_sql(),
+            SqlParameter::Float(value) => value.to_sql(),
+            SqlParameter::Text(value) => value.to_sql(),
+            SqlParameter::Date(value) => value.to_sql(),
+            SqlParameter::Time(value) => value.to_sql(),
+            SqlParameter::DateTime(value) => value.to_sql(),
+        }
+    }
+}
+
+#[async_trait]
+impl Connection for DuckDbConnection {
+    type Conn = DuckDbConn;
+    type Row = DuckDbRow;
+    type Column = DuckDbColumn;
+
+    const KIND: &'static str = "duckdb";
+
+    /// Database files are local, so querying them is as cheap as reading
+    /// cached results, which could also be stale after the file changes
+    fn cache_ttl_s(&self) -> Option<u64> {
+        Some(0)
+    }
+
+    /// Get the length of a row
+    fn row_len(row: &Self::Row) -> usize {
+        row.values.len()
+    }
+
+    /// Get the columns of a row
+    fn row_columns(row: &Self::Row) -> Box<dyn Iterator<Item = &Self::Column> + '_> {
+        Box::new(row.columns.iter())
+    }
+
+    /// Get the name of a column
+    fn column_name(col: &Self::Column) -> &str {
+        &col.name
+    }
+
+    /// Open a DuckDB database file read-only, or an in-memory database
+    async fn connect(&self) -> Result<Self::Conn> {
+        let path = self
+            .path
+            .as_deref()
+            .map(|path| resolve_path(path, self.data_dir.as_deref()))
+            .transpose()?;
+        let data_dir = self.data_dir.to_owned();
+
+        let client = tokio::task::spawn_blocking(move || Self::open(path, data_dir))
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Connect(e.to_string())))??;
+
+        Ok(Arc::new(Mutex::new(client)))
+    }
+
+    /// Query rows from a DuckDB database
+    async fn query(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Bytes, bool, usize)> {
+        let mut rows = vec![];
+        let mut over_the_limit = false;
+        let mut bytes = 0;
+        let mut stream = Self::query_rows(pool, sql, params);
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+            bytes += Self::row_len(&row) as u64;
+
+            if max_bytes.is_some_and(|max_bytes| bytes > max_bytes) {
+                over_the_limit = true;
+                break;
+            }
+
+            rows.push(row);
+        }
+
+        let (bytes, num_records) = Self::to_parquet(rows)?;
+
+        Ok((bytes, over_the_limit, num_records))
+    }
+
+    /// Stream a page of rows from a DuckDB database
+    async fn query_stream(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        let rows = Self::query_rows(pool, sql, params);
+
+        stream_rows::<Self, _>(rows, page, sender, max_bytes).await
+    }
+
+    /// Get the schema of a DuckDB database
+    async fn schema(&self, pool: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema> {
+        let mut schema =
+            DatabaseSchema::new(self.path.to_owned().unwrap_or_else(|| "memory".into()));
+        let params = schemas
+            .iter()
+            .map(|schema| SqlParameter::Text(schema.to_owned()))
+            .collect::<Vec<_>>();
+        let filter = match schemas.is_empty() {
+            true => "".to_string(),
+            false => format!(
+                "and schema_name in ({})",
+                vec!["?"; schemas.len()].join(", ")
+            ),
+        };
+
+        // tables, views and their columns
+        let sql = format!("
+            select c.schema_name, c.table_name, v.view_name is not null, c.column_name, c.data_type,
+                c.is_nullable, c.column_default, c.comment, coalesce(t.comment, v.comment), t.estimated_size
+            from duckdb_columns() as c
+                left join duckdb_tables() as t on t.database_name = c.database_name
+                    and t.schema_name = c.schema_name and t.table_name = c.table_name
+                left join duckdb_views() as v on v.database_name = c.database_name
+                    and v.schema_name = c.schema_name and v.view_name = c.table_name
+            where c.database_name = current_database() and not c.internal
+                and c.schema_name not in ('information_schema', 'pg_catalog')
+                {}
+            order by c.schema_name, c.table_name, c.column_index", filter.replace("schema_name", "c.schema_name"));
+
+        for row in DuckDbConnection::query_all(pool, &sql, &params).await? {
+            let table = schema.table_mut(&text(&row.values[0]), &text(&row.values[1]));
+            let is_view = matches!(row.values[2], Value::Boolean(true));
+
+            if table.columns.is_empty() {
+                table.kind = if is_view {
+                    TableKind::View
+                } else {
+                    TableKind::Table
+                };
+                table.comment = optional_text(&row.values[8]);
+                table.row_count = match row.values[9] {
+                    Value::BigInt(count) => Some(count as u64),
+                    _ => None,
+                };
+            }
+
+            table.columns.push(SchemaColumn {
+                name: text(&row.values[3]),
+                r#type: text(&row.values[4]).to_lowercase(),
+                is_nullable: matches!(row.values[5], Value::Boolean(true)),
+                default: optional_text(&row.values[6]),
+                comment: optional_text(&row.values[7]),
+            });
+        }
+
+        // primary and foreign keys, foreign keys reference tables in the same schema
+        let sql = format!("
+            select schema_name, table_name, constraint_type, constraint_name, constraint_column_names,
+                referenced_table, referenced_column_names
+            from duckdb_constraints()
+            where database_name = current_database()
+                and constraint_type in ('PRIMARY KEY', 'FOREIGN KEY')
+                {filter}
+            order by schema_name, table_name, constraint_index");
+
+        for row in optional(
+            "keys",
+            DuckDbConnection::query_all(pool, &sql, &params).await,
+        ) {
+            let schema_name = text(&row.values[0]);
+            let Some(table) = schema.existing_table_mut(&schema_name, &text(&row.values[1])) else {
+                continue;
+            };
+            let columns = texts(&row.values[4]);
+
+            match text(&row.values[2]).as_str() {
+                "PRIMARY KEY" => table.primary_key = columns,
+                _ => {
+                    let name = text(&row.values[3]);
+                    let referenced_columns = texts(&row.values[6]);
+
+                    for (column, referenced_column) in columns.into_iter().zip(referenced_columns) {
+                        table.add_foreign_key_column(
+                            &name,
+                            column,
+                            schema_name.to_owned(),
+                            text(&row.values[5]),
+                            referenced_column,
+                        );
+                    }
+                }
+            }
+        }
+
+        // indexes created with `create index`, keys are indexed without being listed here
+        let sql = format!(
+            "
+            select schema_name, table_name, index_name, is_unique, is_primary, sql
+            from duckdb_indexes()
+            where database_name = current_database()
+                {filter}
+            order by schema_name, table_name, index_name"
+        );
+
+        for row in optional(
+            "indexes",
+            DuckDbConnection::query_all(pool, &sql, &params).await,
+        ) {
+            if let Some(table) =
+                schema.existing_table_mut(&text(&row.values[0]), &text(&row.values[1]))
+            {
+                let name = text(&row.values[2]);
+                let is_unique = matches!(row.values[3], Value::Boolean(true));
+                let is_primary = matches!(row.values[4], Value::Boolean(true));
+
+                for column in index_columns(&text(&row.values[5])) {
+                    table.add_index_column(&name, column, is_unique, is_primary);
+                }
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Convert a row to an Arrow type
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType {
+        match &row.values[index] {
+            Value::Null => default_arrow(&column.data_type),
+            Value::Boolean(value) => ArrowType::Boolean(*value),
+            Value::TinyInt(value) => ArrowType::Int8(*value),
+            Value::SmallInt(value) => ArrowType::Int16(*value),
+            Value::Int(value) => ArrowType::Int32(*value),
+            Value::BigInt(value) => ArrowType::Int64(*value),
+            Value::HugeInt(value) => ArrowType::BigDecimal(BigDecimal::from(*value)),
+            Value::UTinyInt(value) => ArrowType::UInt8(*value),
+            Value::USmallInt(value) => ArrowType::UInt16(*value),
+            Value::UInt(value) => ArrowType::UInt32(*value),
+            Value::UBigInt(value) => ArrowType::UInt64(*value),
+            Value::Float(value) => ArrowType::Float32(*value),
+            Value::Double(value) => ArrowType::Float64(*value),
+            Value::Decimal(value) => {
+                ArrowType::BigDecimal(BigDecimal::from_str(&value.to_string()).unwrap_or_default())
+            }
+            Value::Text(value) | Value::Enum(value) => ArrowType::Utf8(value.to_owned()),
+            Value::Date32(value) => ArrowType::Date32(*value),
+            Value::Time64(unit, value) => {
+                let micros = unit.to_micros(*value);
+                let time = NaiveTime::from_num_seconds_from_midnight_opt(
+                    (micros / 1_000_000) as u32,
+                    (micros % 1_000_000 * 1_000) as u32,
+                );
+                ArrowType::Time32(time.unwrap_or_default())
+            }
+            Value::Timestamp(unit, value) => {
+                let timestamp = DateTime::from_timestamp_micros(unit.to_micros(*value));
+                ArrowType::Timestamp(timestamp.map(|t| t.naive_utc()).unwrap_or_default())
+            }
+            // blobs, intervals and nested types
+            _ => ArrowType::Unsupported,
+        }
+    }
+}
+
+/// The Arrow type of a null, which is the default value of its column's type
+fn default_arrow(data_type: &DataType) -> ArrowType {
+    match data_type {
+        DataType::Boolean => ArrowType::Boolean(false),
+        DataType::Int8 => ArrowType::Int8(0),
+        DataType::Int16 => ArrowType::Int16(0),
+        DataType::Int32 => ArrowType::Int32(0),
+        DataType::Int64 => ArrowType::Int64(0),
+        DataType::UInt8 => ArrowType::UInt8(0),
+        DataType::UInt16 => ArrowType::UInt16(0),
+        DataType::UInt32 => ArrowType::UInt32(0),
+        DataType::UInt64 => ArrowType::UInt64(0),
+        DataType::Float32 => ArrowType::Float32(0.0),
+        DataType::Float64 => ArrowType::Float64(0.0),
+        DataType::Decimal128(..) | DataType::Decimal256(..) => {
+            ArrowType::BigDecimal(BigDecimal::default())
+        }
+        DataType::Utf8 | DataType::LargeUtf8 => ArrowType::Utf8(String::new()),
+        DataType::Date32 => ArrowType::Date32(0),
+        DataType::Time64(_) => ArrowType::Time32(NaiveTime::default()),
+        DataType::Timestamp(..) => ArrowType::Timestamp(NaiveDateTime::default()),
+        DataType::Null => ArrowType::Void,
+        _ => ArrowType::Unsupported,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    fn new_data_dir() -> PathBuf {
+        let data_dir = std::env::temp_dir().join(format!("duckdb-connection-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        data_dir
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_query_to_arrow() {
+        let connection = DuckDbConnection::new(None, None);
+        let pool = connection.connect().await.unwrap();
+        let sql = "select 1::tinyint, 2::int, 3::bigint, 4::ubigint, 1.5::double,
+            12.25::decimal(10, 2), 'text', true, date '2024-05-28', time '12:34:56',
+            timestamp '2024-05-28 12:34:56', null::int, [1, 2]";
+        let rows = DuckDbConnection::query_all(&pool, sql, &[]).await.unwrap();
+
+        let row = &rows[0];
+        let to_arrow = |index: usize| DuckDbConnection::to_arrow(row, &row.columns[index], index);
+        let date = NaiveDate::from_ymd_opt(2024, 5, 28).unwrap();
+        let time = NaiveTime::from_hms_opt(12, 34, 56).unwrap();
+
+        assert_eq!(to_arrow(0), ArrowType::Int8(1));
+        assert_eq!(to_arrow(1), ArrowType::Int32(2));
+        assert_eq!(to_arrow(2), ArrowType::Int64(3));
+        assert_eq!(to_arrow(3), ArrowType::UInt64(4));
+        assert_eq!(to_arrow(4), ArrowType::Float64(1.5));
+        assert_eq!(
+            to_arrow(5),
+            ArrowType::BigDecimal(BigDecimal::from_str("12.25").unwrap())
+        );
+        assert_eq!(to_arrow(6), ArrowType::Utf8("text".into()));
+        assert_eq!(to_arrow(7), ArrowType::Boolean(true));
+        assert_eq!(to_arrow(8), ArrowType::Date32(19871));
+        assert_eq!(to_arrow(9), ArrowType::Time32(time));
+        assert_eq!(to_arrow(10), ArrowType::Timestamp(date.and_time(time)));
+        assert_eq!(to_arrow(11), ArrowType::Int32(0));
+        assert_eq!(to_arrow(12), ArrowType::Unsupported);
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_query_with_parameters() {
+        let connection = DuckDbConnection::new(None, None);
+        let mut pool = connection.connect().await.unwrap();
+        let sql = "select ? as name, ? as age from range(3)";
+        let params = [
+            SqlParameter::Text("O'Brien".into()),
+            SqlParameter::Integer(42),
+        ];
+        let (_, over_the_limit, num_records) = connection
+            .query(&mut pool, sql, &params, None)
+            .await
+            .unwrap();
+
+        assert!(!over_the_limit);
+        assert_eq!(num_records, 3);
+
+        let (_, over_the_limit, num_records) = connection
+            .query(&mut pool, sql, &params, Some(4))
+            .await
+            .unwrap();
+
+        assert!(over_the_limit);
+        assert_eq!(num_records, 2);
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_data_dir() {
+        let data_dir = new_data_dir();
+        let parquet = data_dir.join("data.parquet");
+
+        // write files before the data directory's restrictions apply
+        DuckDbClient::open_in_memory()
+            .unwrap()
+            .execute_batch(&format!(
+                "copy (select range as n from range(5)) to '{}' (format parquet);
+                attach '{}' as db;
+                create table db.numbers as select range as n from range(5);",
+                parquet.display(),
+                data_dir.join("test.duckdb").display(),
+            ))
+            .unwrap();
+
+        let connection = DuckDbConnection::new(None, Some(data_dir.to_owned()));
+        let mut pool = connection.connect().await.unwrap();
+
+        // relative paths are in the data directory
+        let sql = "select * from 'data.parquet'";
+        let (_, _, num_records) = connection.query(&mut pool, sql, &[], None).await.unwrap();
+        assert_eq!(num_records, 5);
+
+        for sql in [
+            "select * from read_csv('/etc/passwd')",
+            "set enable_external_access = true",
+            "attach '/tmp/other.duckdb'",
+        ] {
+            let result = connection.query(&mut pool, sql, &[], None).await;
+            assert!(result.is_err(), "{sql} was allowed");
+        }
+
+        // database files are opened read-only
+        let connection = DuckDbConnection::new(Some("test.duckdb".into()), Some(data_dir));
+        let mut pool = connection.connect().await.unwrap();
+        let sql = "select * from numbers";
+        let (_, _, num_records) = connection.query(&mut pool, sql, &[], None).await.unwrap();
+        assert_eq!(num_records, 5);
+
+        let sql = "delete from numbers";
+        assert!(connection.query(&mut pool, sql, &[], None).await.is_err());
+
+        let schema = connection.schema(&mut pool, &[]).await.unwrap();
+        assert_eq!(
+            schema.tables.get("main.numbers").unwrap().columns,
+            vec![SchemaColumn::new("n", "bigint", true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_schema() {
+        let data_dir = new_data_dir();
+
+        DuckDbClient::open(data_dir.join("test.duckdb"))
+            .unwrap()
+            .execute_batch(
+                "create table customers (id integer primary key, name varchar default 'none');
+                comment on table customers is 'people';
+                comment on column customers.name is 'full name';
+                create table orders (id integer primary key, customer_id integer references customers (id));
+                create index orders_customer_id on orders (customer_id);
+                create view customer_names as select name from customers;",
+            )
+            .unwrap();
+
+        let connection = DuckDbConnection::new(Some("test.duckdb".into()), Some(data_dir));
+        let mut pool = connection.connect().await.unwrap();
+        let schema = connection.schema(&mut pool, &[]).await.unwrap();
+
+        let customers = schema.tables.get("main.customers").unwrap();
+        assert_eq!(customers.kind, TableKind::Table);
+        assert_eq!(customers.comment, Some("people".into()));
+        assert_eq!(customers.primary_key, vec!["id"]);
+        assert_eq!(customers.row_count, Some(0));
+        assert_eq!(customers.columns[1].comment, Some("full name".into()));
+        assert!(customers.columns[1].default.is_some());
+
+        let orders = schema.tables.get("main.orders").unwrap();
+        assert_eq!(orders.foreign_keys[0].columns, vec!["customer_id"]);
+        assert_eq!(orders.foreign_keys[0].referenced_table, "customers");
+        assert_eq!(orders.foreign_keys[0].referenced_columns, vec!["id"]);
+        assert_eq!(orders.indexes[0].name, "orders_customer_id");
+        assert_eq!(orders.indexes[0].columns, vec!["customer_id"]);
+
+        let view = schema.tables.get("main.customer_names").unwrap();
+        assert_eq!(view.kind, TableKind::View);
+        assert_eq!(view.row_count, None);
+
+        let schema = connection
+            .schema(&mut pool, &["other".into()])
+            .await
+            .unwrap();
+        assert!(schema.tables.is_empty());
+    }
+
+    #[test]
+    fn test_index_columns() {
+        let sql = "CREATE INDEX idx ON tbl(a, b);";
+        assert_eq!(index_columns(sql), vec!["a", "b"]);
+
+        let sql = r#"CREATE UNIQUE INDEX "i" ON main."t" ("my col", lower(b), c);"#;
+        assert_eq!(index_columns(sql), vec!["my col", "c"]);
+
+        assert!(index_columns("CREATE INDEX i ON t((a + 1));").is_empty());
+    }
+}
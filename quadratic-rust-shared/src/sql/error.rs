@@ -8,6 +8,9 @@ use thiserror::Error;
 
 #[derive(Error, Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum Sql {
+    #[error("Error cancelling query: {0}")]
+    Cancel(String),
+
     #[error("Error connecting to database: {0}")]
     Connect(String),
 
@@ -17,6 +20,12 @@ pub enum Sql {
     #[error("Error executing query: {0}")]
     Query(String),
 
+    #[error("Read-only connection: {0}")]
+    ReadOnly(String),
+
     #[error("Error creating schema: {0}")]
     Schema(String),
+
+    #[error("Error with SSH tunnel: {0}")]
+    Tunnel(String),
 }
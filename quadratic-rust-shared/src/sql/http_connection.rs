@@ -0,0 +1,1176 @@
+//! HTTP
+//!
+//! Functions to read JSON APIs as if they were a database.  A connection
+//! stores a base URL, its auth and how the API paginates.  A query is a
+//! request relative to the base URL:
+//!
+//! ```text
+//! GET /users?status=active#data.users
+//! Accept: application/json
+//!
+//! {"optional": "body"}
+//! ```
+//!
+//! The first line is the method (GET when it's left out) and the path, whose
+//! fragment is the path to the response's records.  Header lines follow, then
+//! a blank line and the body.  Every page of records is flattened into rows:
+//! nested objects become columns with dotted names (eg, `address.city`) and
+//! arrays are kept as JSON.
+//!
+//! Parameters are bound to the placeholders `{p1}`, `{p2}`, etc.  They're
+//! URL-encoded in the path, text in headers and JSON values in the body.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{Field, Schema as ArrowSchema};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream;
+use reqwest::header::LINK;
+use reqwest::{Client, Method, RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Result, SharedError};
+use crate::sql::error::Sql as SqlError;
+use crate::sql::parameter::SqlParameter;
+use crate::sql::schema::DatabaseSchema;
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_rows};
+use crate::sql::{ArrowType, Connection};
+
+/// Stop paginating after this many pages, in case an API never runs out
+const MAX_PAGES: usize = 1000;
+
+/// HTTP connection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HttpConnection {
+    pub base_url: String,
+    pub auth: Option<HttpAuth>,
+    #[serde(default)]
+    pub pagination: HttpPagination,
+
+    // seconds to cache query results for, overriding the service's default
+    #[serde(default)]
+    pub cache_ttl_s: Option<u64>,
+
+    // the client that sends requests, eg, one that checks the hosts that can
+    // be reached, otherwise a default client
+    #[serde(skip)]
+    pub client: Option<Client>,
+}
+
+/// How requests are authenticated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    Bearer {
+        token: String,
+    },
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    ApiKey {
+        header: String,
+        key: String,
+    },
+}
+
+/// How an API splits its records into pages
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpPagination {
+    #[default]
+    None,
+
+    // eg, `?offset=200&limit=100`, until a page has fewer than `limit` records
+    Offset {
+        offset_param: String,
+        limit_param: String,
+        limit: u64,
+    },
+
+    // eg, `?page=3`, until a page has no records
+    Page {
+        page_param: String,
+        #[serde(default = "default_first_page")]
+        first_page: u64,
+    },
+
+    // the next page's cursor is read from the response, eg, `meta.next_cursor`,
+    // until it's empty
+    Cursor {
+        cursor_path: String,
+        cursor_param: String,
+    },
+
+    // the next page's URL is in the `Link` header, eg, GitHub's API
+    LinkHeader,
+}
+
+fn default_first_page() -> u64 {
+    1
+}
+
+/// The type of a column, inferred from every record's value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpColumnType {
+    Boolean,
+    Integer,
+    Float,
+    Text,
+}
+
+#[derive(Debug)]
+pub struct HttpColumn {
+    name: String,
+    data_type: HttpColumnType,
+}
+
+/// Rows share their columns, which are every record's flattened fields
+#[derive(Debug)]
+pub struct HttpRow {
+    columns: Arc<[HttpColumn]>,
+    values: Vec<Value>,
+}
+
+/// A request read from a query
+#[derive(Debug, PartialEq)]
+struct HttpRequest {
+    method: Method,
+    path: String,
+    records_path: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+fn query_error(e: impl ToString) -> SharedError {
+    SharedError::Sql(SqlError::Query(e.to_string()))
+}
+
+impl HttpConnection {
+    /// Create a new HTTP connection
+    pub fn new(
+        base_url: String,
+        auth: Option<HttpAuth>,
+        pagination: HttpPagination,
+    ) -> HttpConnection {
+        HttpConnection {
+            base_url,
+            auth,
+            pagination,
+            cache_ttl_s: None,
+            client: None,
+        }
+    }
+
+    fn base_url(&self) -> Result<Url> {
+        Url::parse(&self.base_url).map_err(|e| SharedError::Sql(SqlError::Connect(e.to_string())))
+    }
+
+    /// The URL of a path relative to the base URL.  Requests may not leave
+    /// the base URL's origin, so that its auth isn't sent elsewhere.
+    fn url(&self, path: &str) -> Result<Url> {
+        let base_url = self.base_url()?;
+        let url = format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+
+        Url::parse(&url)
+            .map_err(query_error)
+            .and_then(|url| same_origin(&base_url, url))
+    }
+
+    /// Build a request, adding the connection's auth
+    fn request(&self, client: &Client, method: Method, url: Url) -> RequestBuilder {
+        let request = client.request(method, url);
+
+        match &self.auth {
+            Some(HttpAuth::Bearer { token }) => request.bearer_auth(token),
+            Some(HttpAuth::Basic { username, password }) => {
+                request.basic_auth(username, password.as_ref())
+            }
+            Some(HttpAuth::ApiKey { header, key }) => request.header(header, key),
+            None => request,
+        }
+    }
+
+    /// Send a query's request, following the API's pagination until there
+    /// are no more pages or the responses are over `max_bytes`.
+    ///
+    /// Returns: (records, is over the limit)
+    async fn fetch(
+        &self,
+        client: &Client,
+        query: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Vec<Value>, bool)> {
+        let request = HttpRequest::parse(query, params)?;
+        let base_url = self.base_url()?;
+        let mut next = Some(self.pagination.first(self.url(&request.path)?));
+        let mut records = vec![];
+        let mut bytes = 0;
+        let mut pages = 0;
+
+        while let Some(url) = next.take() {
+            let mut builder = self.request(client, request.method.to_owned(), url.to_owned());
+
+            for (name, value) in request.headers.iter() {
+                builder = builder.header(name, value);
+            }
+
+            if let Some(ref body) = request.body {
+                let has_content_type = request
+                    .headers
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+
+                if !has_content_type {
+                    builder = builder.header("content-type", "application/json");
+                }
+
+                builder = builder.body(body.to_owned());
+            }
+
+            let response = builder.send().await.map_err(query_error)?;
+            let status = response.status();
+            let link = response
+                .headers()
+                .get(LINK)
+                .and_then(|link| link.to_str().ok())
+                .and_then(next_link);
+            let body = response.bytes().await.map_err(query_error)?;
+
+            if !status.is_success() {
+                let message = String::from_utf8_lossy(&body);
+                return Err(query_error(format!("{status}: {}", message.trim())));
+            }
+
+            bytes += body.len() as u64;
+
+            if max_bytes.is_some_and(|max_bytes| bytes > max_bytes) {
+                return Ok((records, true));
+            }
+
+            let body = serde_json::from_slice::<Value>(&body)
+                .map_err(|e| query_error(format!("Response is not JSON: {e}")))?;
+            let page_records = records_at(body.to_owned(), request.records_path.as_deref());
+            let count = page_records.len();
+            records.extend(page_records);
+            pages += 1;
+
+            if pages < MAX_PAGES {
+                next = self
+                    .pagination
+                    .next(&url, count, &body, link.as_deref())
+                    .map(|url| same_origin(&base_url, url))
+                    .transpose()?;
+            }
+        }
+
+        Ok((records, false))
+    }
+
+    /// Fetch a query's records as rows
+    async fn query_rows(
+        &self,
+        client: &Client,
+        query: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Vec<HttpRow>, bool)> {
+        let (records, over_the_limit) = self.fetch(client, query, params, max_bytes).await?;
+
+        Ok((to_rows(records), over_the_limit))
+    }
+}
+
+impl HttpPagination {
+    /// The URL of the first page
+    fn first(&self, mut url: Url) -> Url {
+        match self {
+            HttpPagination::Offset {
+                offset_param,
+                limit_param,
+                limit,
+            } => {
+                set_query_param(&mut url, offset_param, "0");
+                set_query_param(&mut url, limit_param, &limit.to_string());
+            }
+            HttpPagination::Page {
+                page_param,
+                first_page,
+            } => set_query_param(&mut url, page_param, &first_page.to_string()),
+            HttpPagination::None | HttpPagination::Cursor { .. } | HttpPagination::LinkHeader => {}
+        }
+
+        url
+    }
+
+    /// The URL of the page after `url`, or None when it was the last page
+    fn next(&self, url: &Url, count: usize, body: &Value, link: Option<&str>) -> Option<Url> {
+        let mut url = url.to_owned();
+
+        match self {
+            HttpPagination::None => return None,
+            HttpPagination::Offset {
+                offset_param,
+                limit,
+                ..
+            } => {
+                if (count as u64) < *limit || count == 0 {
+                    return None;
+                }
+
+                let offset = query_param(&url, offset_param).unwrap_or_default() + count as u64;
+                set_query_param(&mut url, offset_param, &offset.to_string());
+            }
+            HttpPagination::Page { page_param, .. } => {
+                if count == 0 {
+                    return None;
+                }
+
+                let page = query_param(&url, page_param).unwrap_or_default() + 1;
+                set_query_param(&mut url, page_param, &page.to_string());
+            }
+            HttpPagination::Cursor {
+                cursor_path,
+                cursor_param,
+            } => {
+                let cursor = match value_at(body, cursor_path)? {
+                    Value::String(cursor) if !cursor.is_empty() => cursor.to_owned(),
+                    Value::Number(cursor) => cursor.to_string(),
+                    _ => return None,
+                };
+
+                set_query_param(&mut url, cursor_param, &cursor);
+            }
+            HttpPagination::LinkHeader => return url.join(link?).ok(),
+        }
+
+        Some(url)
+    }
+}
+
+impl HttpRequest {
+    /// Read a request from a query, binding its parameters
+    fn parse(query: &str, params: &[SqlParameter]) -> Result<Self> {
+        let mut lines = query.trim_start().lines();
+        let first = lines.next().unwrap_or_default().trim();
+
+        let (method, target) = match first.split_once(char::is_whitespace) {
+            Some((method, target)) => (method, target.trim()),
+            // eg, `POST` for the base URL
+            None if !first.is_empty() && first.chars().all(|c| c.is_ascii_uppercase()) => {
+                (first, "")
+            }
+            None => ("GET", first),
+        };
+        let method = Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|_| query_error(format!("Invalid HTTP method {method:?}")))?;
+
+        let (path, records_path) = match target.split_once('#') {
+            Some((path, records_path)) => (path, Some(records_path.to_owned())),
+            None => (target, None),
+        };
+
+        let mut headers = vec![];
+
+        for line in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| query_error(format!("Invalid header {:?}", line.trim())))?;
+            headers.push((
+                name.trim().to_owned(),
+                bind(value.trim(), params, parameter_text),
+            ));
+        }
+
+        let body = lines.collect::<Vec<_>>().join("\n");
+        let body = (!body.trim().is_empty()).then(|| bind(body.trim(), params, parameter_json));
+
+        Ok(HttpRequest {
+            method,
+            path: bind(path, params, |param| url_encode(&parameter_text(param))),
+            records_path: records_path.filter(|records_path| !records_path.is_empty()),
+            headers,
+            body,
+        })
+    }
+}
+
+/// Check that a URL has the base URL's scheme, host and port
+fn same_origin(base_url: &Url, url: Url) -> Result<Url> {
+    match url.origin() == base_url.origin() {
+        true => Ok(url),
+        false => Err(query_error(format!(
+            "{url} is outside of the connection's base URL"
+        ))),
+    }
+}
+
+fn query_param(url: &Url, name: &str) -> Option<u64> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+/// Set a query parameter, replacing its value if it's already set
+fn set_query_param(url: &mut Url, name: &str, value: &str) {
+    let pairs = url
+        .query_pairs()
+        .filter(|(key, _)| key != name)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(name, value);
+}
+
+/// The URL of the next page in a `Link` header, eg,
+/// `<https://api.github.com/user/repos?page=2>; rel="next"`
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let url = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+
+        parts
+            .filter_map(|part| part.trim().strip_prefix("rel="))
+            .any(|rel| {
+                rel.trim_matches('"')
+                    .split_whitespace()
+                    .any(|rel| rel == "next")
+            })
+            .then(|| url.to_owned())
+    })
+}
+
+/// Replace the placeholders `{p1}`, `{p2}`, etc., with their parameter.
+/// Placeholders without a parameter are left as they are.
+fn bind(text: &str, params: &[SqlParameter], format: impl Fn(&SqlParameter) -> String) -> String {
+    let mut bound = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{p") {
+        bound.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let param = rest[2..].find('}').and_then(|end| {
+            let index = rest[2..2 + end].parse::<usize>().ok()?;
+            let param = params.get(index.checked_sub(1)?)?;
+            Some((param, end + 3))
+        });
+
+        match param {
+            Some((param, len)) => {
+                bound.push_str(&format(param));
+                rest = &rest[len..];
+            }
+            None => {
+                bound.push_str("{p");
+                rest = &rest[2..];
+            }
+        }
+    }
+
+    bound.push_str(rest);
+    bound
+}
+
+fn parameter_text(param: &SqlParameter) -> String {
+    match param {
+        SqlParameter::Null => "".into(),
+        SqlParameter::Boolean(value) => value.to_string(),
+        SqlParameter::Integer(value) => value.to_string(),
+        SqlParameter::Float(value) => value.to_string(),
+        SqlParameter::Text(value) => value.to_owned(),
+        SqlParameter::Date(value) => value.format("%Y-%m-%d").to_string(),
+        SqlParameter::Time(value) => value.format("%H:%M:%S%.f").to_string(),
+        SqlParameter::DateTime(value) => value.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+    }
+}
+
+fn parameter_json(param: &SqlParameter) -> String {
+    let value = match param {
+        SqlParameter::Null => Value::Null,
+        SqlParameter::Boolean(value) => Value::from(*value),
+        SqlParameter::Integer(value) => Value::from(*value),
+        SqlParameter::Float(value) => Value::from(*value),
+        _ => Value::from(parameter_text(param)),
+    };
+
+    value.to_string()
+}
+
+/// Percent-encode everything but unreserved characters
+fn url_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// The value at a dotted path, eg, `data.items` or `results.0`
+fn value_at<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| match value {
+            Value::Object(object) => object.get(key),
+            Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// The records in a response.  Without a path, they're the response itself
+/// when it's an array, otherwise its first field that's an array (eg,
+/// `{"count": 2, "results": [...]}`), otherwise the response is one record.
+fn records_at(body: Value, path: Option<&str>) -> Vec<Value> {
+    let records = match path {
+        Some(path) => value_at(&body, path).cloned().unwrap_or(Value::Null),
+        None => match body {
+            Value::Object(ref object) => object
+                .values()
+                .find(|value| value.is_array())
+                .cloned()
+                .unwrap_or(body),
+            _ => body,
+        },
+    };
+
+    match records {
+        Value::Array(records) => records,
+        Value::Null => vec![],
+        record => vec![record],
+    }
+}
+
+/// Flatten a record's nested objects into dotted names, keeping arrays as
+/// JSON.  A record that isn't an object is a single `value`.
+fn flatten(record: Value) -> Vec<(String, Value)> {
+    fn flatten_into(prefix: &str, value: Value, fields: &mut Vec<(String, Value)>) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    let name = match prefix.is_empty() {
+                        true => key,
+                        false => format!("{prefix}.{key}"),
+                    };
+                    flatten_into(&name, value, fields);
+                }
+            }
+            Value::Array(_) => fields.push((prefix.to_owned(), Value::String(value.to_string()))),
+            value => fields.push((prefix.to_owned(), value)),
+        }
+    }
+
+    let mut fields = vec![];
+
+    match record {
+        Value::Object(_) => flatten_into("", record, &mut fields),
+        Value::Array(_) => fields.push(("value".into(), Value::String(record.to_string()))),
+        value => fields.push(("value".into(), value)),
+    }
+
+    fields
+}
+
+fn value_type(value: &Value) -> Option<HttpColumnType> {
+    match value {
+        Value::Null => None,
+        Value::Bool(_) => Some(HttpColumnType::Boolean),
+        Value::Number(number) if number.is_i64() => Some(HttpColumnType::Integer),
+        Value::Number(_) => Some(HttpColumnType::Float),
+        _ => Some(HttpColumnType::Text),
+    }
+}
+
+/// The type of a column whose values have both types
+fn merge_types(a: HttpColumnType, b: HttpColumnType) -> HttpColumnType {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (HttpColumnType::Integer, HttpColumnType::Float)
+        | (HttpColumnType::Float, HttpColumnType::Integer) => HttpColumnType::Float,
+        _ => HttpColumnType::Text,
+    }
+}
+
+/// Convert records to rows, whose columns are every record's fields in the
+/// order they're first seen.  Missing fields are null.
+fn to_rows(records: Vec<Value>) -> Vec<HttpRow> {
+    let mut names = vec![];
+    let mut types: Vec<Option<HttpColumnType>> = vec![];
+    let mut indexes = HashMap::new();
+    let records = records.into_iter().map(flatten).collect::<Vec<_>>();
+
+    for fields in records.iter() {
+        for (name, value) in fields {
+            let index = *indexes.entry(name.to_owned()).or_insert_with(|| {
+                names.push(name.to_owned());
+                types.push(None);
+                names.len() - 1
+            });
+
+            types[index] = match (types[index], value_type(value)) {
+                (Some(a), Some(b)) => Some(merge_types(a, b)),
+                (a, b) => a.or(b),
+            };
+        }
+    }
+
+    let columns = names
+        .into_iter()
+        .zip(types)
+        .map(|(name, data_type)| HttpColumn {
+            name,
+            // a column of nulls
+            data_type: data_type.unwrap_or(HttpColumnType::Text),
+        })
+        .collect::<Arc<[HttpColumn]>>();
+
+    records
+        .into_iter()
+        .map(|fields| {
+            let mut values = vec![Value::Null; columns.len()];
+
+            for (name, value) in fields {
+                values[indexes[&name]] = value;
+            }
+
+            HttpRow {
+                columns: Arc::clone(&columns),
+                values,
+            }
+        })
+        .collect()
+}
+
+/// A value as text, for columns of mixed types
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value.to_owned()),
+        value => Some(value.to_string()),
+    }
+}
+
+#[async_trait]
+impl Connection for HttpConnection {
+    type Conn = Client;
+    type Row = HttpRow;
+    type Column = HttpColumn;
+
+    const KIND: &'static str = "http";
+
+    fn cache_ttl_s(&self) -> Option<u64> {
+        self.cache_ttl_s
+    }
+
+    /// Get the length of a row
+    fn row_len(row: &Self::Row) -> usize {
+        row.values.len()
+    }
+
+    /// Get the columns of a row
+    fn row_columns(row: &Self::Row) -> Box<dyn Iterator<Item = &Self::Column> + '_> {
+        Box::new(row.columns.iter())
+    }
+
+    /// Get the name of a column
+    fn column_name(col: &Self::Column) -> &str {
+        &col.name
+    }
+
+    /// Check that the base URL can be reached with the connection's auth.
+    /// APIs may not serve anything at the base URL, so only auth errors
+    /// fail.
+    async fn connect(&self) -> Result<Self::Conn> {
+        let connect_error = |e: String| SharedError::Sql(SqlError::Connect(e));
+        let client = self.client.to_owned().unwrap_or_default();
+        let response = self
+            .request(&client, Method::GET, self.base_url()?)
+            .send()
+            .await
+            .map_err(|e| connect_error(e.to_string()))?;
+
+        let status = response.status();
+
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return Err(connect_error(format!("{:?}: {status}", self.base_url)));
+        }
+
+        Ok(client)
+    }
+
+    /// Query records from an API
+    async fn query(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Bytes, bool, usize)> {
+        let (rows, over_the_limit) = self.query_rows(pool, sql, params, max_bytes).await?;
+        let (bytes, num_records) = Self::to_parquet(rows)?;
+
+        Ok((bytes, over_the_limit, num_records))
+    }
+
+    /// Stream a page of records from an API.  Column types are inferred from
+    /// every record, so the responses are read before they're sent.
+    async fn query_stream(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        let (rows, over_the_limit) = self.query_rows(pool, sql, params, max_bytes).await?;
+        let rows = stream::iter(rows.into_iter().map(Ok));
+        let mut summary = stream_rows::<Self, _>(rows, page, sender, max_bytes).await?;
+        summary.over_the_limit |= over_the_limit;
+
+        Ok(summary)
+    }
+
+    /// APIs don't describe their records, so the schema is empty
+    async fn schema(&self, _pool: &mut Self::Conn, _schemas: &[String]) -> Result<DatabaseSchema> {
+        Ok(DatabaseSchema::new(self.base_url.to_owned()))
+    }
+
+    /// Convert a row to an Arrow type
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType {
+        let value = &row.values[index];
+
+        match column.data_type {
+            HttpColumnType::Boolean => value.as_bool().map_or(ArrowType::Void, ArrowType::Boolean),
+            HttpColumnType::Integer => value.as_i64().map_or(ArrowType::Void, ArrowType::Int64),
+            HttpColumnType::Float => value.as_f64().map_or(ArrowType::Void, ArrowType::Float64),
+            HttpColumnType::Text => value_text(value).map_or(ArrowType::Void, ArrowType::Utf8),
+        }
+    }
+
+    /// Convert rows to a record batch using their columns' types, since
+    /// records may leave out fields or set them to null
+    fn to_record_batch(data: &[Self::Row]) -> Result<Option<RecordBatch>> {
+        let Some(first) = data.first().filter(|row| !row.columns.is_empty()) else {
+            return Ok(None);
+        };
+
+        let (fields, cols): (Vec<Field>, Vec<ArrayRef>) = first
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let values = data.iter().map(|row| &row.values[index]);
+                let array: ArrayRef = match column.data_type {
+                    HttpColumnType::Boolean => {
+                        Arc::new(BooleanArray::from_iter(values.map(Value::as_bool)))
+                    }
+                    HttpColumnType::Integer => {
+                        Arc::new(Int64Array::from_iter(values.map(Value::as_i64)))
+                    }
+                    HttpColumnType::Float => {
+                        Arc::new(Float64Array::from_iter(values.map(Value::as_f64)))
+                    }
+                    HttpColumnType::Text => {
+                        Arc::new(StringArray::from_iter(values.map(value_text)))
+                    }
+                };
+                let field = Field::new(&column.name, array.data_type().to_owned(), true);
+
+                (field, array)
+            })
+            .unzip();
+
+        let record_batch = RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), cols)?;
+
+        Ok(Some(record_batch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    fn new_http_connection(server: &MockServer, pagination: HttpPagination) -> HttpConnection {
+        HttpConnection::new(
+            server.url("/v1"),
+            Some(HttpAuth::Bearer {
+                token: "token".into(),
+            }),
+            pagination,
+        )
+    }
+
+    async fn query(connection: &HttpConnection, sql: &str) -> Result<(Vec<HttpRow>, bool)> {
+        let client = connection.connect().await?;
+        connection.query_rows(&client, sql, &[], None).await
+    }
+
+    #[tokio::test]
+    async fn test_http_connection() {
+        let server = MockServer::start_async().await;
+        let connection = new_http_connection(&server, HttpPagination::None);
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/v1")
+                    .header("authorization", "Bearer token");
+                then.status(404);
+            })
+            .await;
+
+        assert!(connection.connect().await.is_ok());
+        mock.assert_async().await;
+
+        let mut connection = new_http_connection(&server, HttpPagination::None);
+        connection.auth = Some(HttpAuth::ApiKey {
+            header: "x-api-key".into(),
+            key: "wrong".into(),
+        });
+        server
+            .mock_async(|when, then| {
+                when.header("x-api-key", "wrong");
+                then.status(401);
+            })
+            .await;
+
+        assert!(connection.connect().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_http_query_flattens_records() {
+        let server = MockServer::start_async().await;
+        let connection = new_http_connection(&server, HttpPagination::None);
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/v1/users/search")
+                    .query_param("name", "O'Brien & co")
+                    .header("x-team", "42")
+                    .json_body(json!({"active": true}));
+                then.status(200).json_body(json!({
+                    "count": 2,
+                    "data": {"users": [
+                        {"id": 1, "name": "Ann", "address": {"city": "Oslo"}, "tags": ["a"]},
+                        {"id": 2.5, "score": null, "address": {"city": "Rome"}},
+                    ]},
+                }));
+            })
+            .await;
+        let client = connection.connect().await.unwrap();
+        let sql = "POST /users/search?name={p1}#data.users\nX-Team: {p2}\n\n{\"active\": {p3}}";
+        let params = [
+            SqlParameter::Text("O'Brien & co".into()),
+            SqlParameter::Integer(42),
+            SqlParameter::Boolean(true),
+        ];
+        let (rows, over_the_limit) = connection
+            .query_rows(&client, sql, &params, None)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert!(!over_the_limit);
+        assert_eq!(rows.len(), 2);
+
+        let columns = rows[0]
+            .columns
+            .iter()
+            .map(|column| (column.name.as_str(), column.data_type))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                ("address.city", HttpColumnType::Text),
+                ("id", HttpColumnType::Float),
+                ("name", HttpColumnType::Text),
+                ("tags", HttpColumnType::Text),
+                ("score", HttpColumnType::Text),
+            ]
+        );
+        assert_eq!(rows[0].values[3], json!("[\"a\"]"));
+        assert_eq!(rows[1].values[2], Value::Null);
+
+        let (_, num_records) = HttpConnection::to_parquet(rows).unwrap();
+        assert_eq!(num_records, 2);
+    }
+
+    #[tokio::test]
+    async fn test_http_query_offset_pagination() {
+        let server = MockServer::start_async().await;
+        let pagination = HttpPagination::Offset {
+            offset_param: "offset".into(),
+            limit_param: "limit".into(),
+            limit: 2,
+        };
+        let connection = new_http_connection(&server, pagination);
+        let first = server
+            .mock_async(|when, then| {
+                when.path("/v1/items").query_param("offset", "0");
+                then.status(200).json_body(json!([{"id": 1}, {"id": 2}]));
+            })
+            .await;
+        let last = server
+            .mock_async(|when, then| {
+                when.path("/v1/items")
+                    .query_param("offset", "2")
+                    .query_param("limit", "2");
+                then.status(200).json_body(json!([{"id": 3}]));
+            })
+            .await;
+
+        let (rows, _) = query(&connection, "/items").await.unwrap();
+
+        first.assert_async().await;
+        last.assert_async().await;
+        assert_eq!(rows.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_http_query_page_pagination() {
+        let server = MockServer::start_async().await;
+        let pagination = HttpPagination::Page {
+            page_param: "page".into(),
+            first_page: 1,
+        };
+        let connection = new_http_connection(&server, pagination);
+        let first = server
+            .mock_async(|when, then| {
+                when.path("/v1/items").query_param("page", "1");
+                then.status(200)
+                    .json_body(json!({"results": [{"id": 1}, {"id": 2}]}));
+            })
+            .await;
+        let last = server
+            .mock_async(|when, then| {
+                when.path("/v1/items").query_param("page", "2");
+                then.status(200).json_body(json!({"results": []}));
+            })
+            .await;
+
+        let (rows, _) = query(&connection, "GET /items").await.unwrap();
+
+        first.assert_async().await;
+        last.assert_async().await;
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_http_query_cursor_pagination() {
+        let server = MockServer::start_async().await;
+        let pagination = HttpPagination::Cursor {
+            cursor_path: "meta.next".into(),
+            cursor_param: "cursor".into(),
+        };
+        let connection = new_http_connection(&server, pagination);
+        let last = server
+            .mock_async(|when, then| {
+                when.path("/v1/items").query_param("cursor", "abc");
+                then.status(200)
+                    .json_body(json!({"items": [{"id": 2}], "meta": {"next": null}}));
+            })
+            .await;
+        let first = server
+            .mock_async(|when, then| {
+                when.path("/v1/items").query_param_missing("cursor");
+                then.status(200)
+                    .json_body(json!({"items": [{"id": 1}], "meta": {"next": "abc"}}));
+            })
+            .await;
+
+        let (rows, _) = query(&connection, "GET /items").await.unwrap();
+
+        first.assert_async().await;
+        last.assert_async().await;
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_http_query_link_header_pagination() {
+        let server = MockServer::start_async().await;
+        let connection = new_http_connection(&server, HttpPagination::LinkHeader);
+        let next = format!("<{}>; rel=\"next\"", server.url("/v1/items?page=2"));
+        let first = server
+            .mock_async(|when, then| {
+                when.path("/v1/items").query_param_missing("page");
+                then.status(200)
+                    .header("link", &next)
+                    .json_body(json!([{"id": 1}]));
+            })
+            .await;
+        let last = server
+            .mock_async(|when, then| {
+                when.path("/v1/items").query_param("page", "2");
+                then.status(200).json_body(json!([{"id": 2}]));
+            })
+            .await;
+
+        let (rows, _) = query(&connection, "GET /items").await.unwrap();
+
+        first.assert_async().await;
+        last.assert_async().await;
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_http_query_errors() {
+        let server = MockServer::start_async().await;
+        let connection = new_http_connection(&server, HttpPagination::LinkHeader);
+        server
+            .mock_async(|when, then| {
+                when.path("/v1/missing");
+                then.status(404).body("Not found");
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.path("/v1/elsewhere");
+                then.status(200)
+                    .header("link", "<https://example.com/steal>; rel=\"next\"")
+                    .json_body(json!([]));
+            })
+            .await;
+
+        let error = query(&connection, "/missing").await.unwrap_err();
+        assert!(error.to_string().contains("404 Not Found: Not found"));
+
+        let error = query(&connection, "/elsewhere").await.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("outside of the connection's base URL")
+        );
+
+        let error = query(&connection, "[GET] /items").await.unwrap_err();
+        assert!(error.to_string().contains("Invalid HTTP method"));
+    }
+
+    #[tokio::test]
+    async fn test_http_query_over_the_limit() {
+        let server = MockServer::start_async().await;
+        let connection = new_http_connection(&server, HttpPagination::None);
+        server
+            .mock_async(|when, then| {
+                when.path("/v1/items");
+                then.status(200).json_body(json!([{"id": 1}, {"id": 2}]));
+            })
+            .await;
+        let client = connection.connect().await.unwrap();
+        let (rows, over_the_limit) = connection
+            .query_rows(&client, "/items", &[], Some(4))
+            .await
+            .unwrap();
+
+        assert!(over_the_limit);
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_http_parse_request() {
+        let request = HttpRequest::parse("  /users", &[]).unwrap();
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(request.path, "/users");
+        assert_eq!(request.records_path, None);
+
+        let sql = "delete /users/{p1}#\nAuthorization: {p2}";
+        let params = [
+            SqlParameter::Text("a b/c".into()),
+            SqlParameter::Text("Token x".into()),
+        ];
+        let request = HttpRequest::parse(sql, &params).unwrap();
+        assert_eq!(request.method, Method::DELETE);
+        assert_eq!(request.path, "/users/a%20b%2Fc");
+        assert_eq!(request.records_path, None);
+        assert_eq!(
+            request.headers,
+            vec![("Authorization".into(), "Token x".into())]
+        );
+        assert_eq!(request.body, None);
+
+        assert!(HttpRequest::parse("GET /users\nnot a header", &[]).is_err());
+    }
+
+    #[test]
+    fn test_http_bind() {
+        let params = [SqlParameter::Text("say \"hi\"".into()), SqlParameter::Null];
+
+        assert_eq!(
+            bind(
+                "{\"a\": {p1}, \"b\": {p2}, \"c\": {p3}}",
+                &params,
+                parameter_json
+            ),
+            "{\"a\": \"say \\\"hi\\\"\", \"b\": null, \"c\": {p3}}"
+        );
+        assert_eq!(bind("{p}{p0}{p1", &params, parameter_text), "{p}{p0}{p1");
+    }
+
+    #[test]
+    fn test_http_records_at() {
+        let body = json!({"count": 2, "items": [{"id": 1}, {"id": 2}]});
+        assert_eq!(records_at(body.to_owned(), None).len(), 2);
+        assert_eq!(
+            records_at(body.to_owned(), Some("items.1")),
+            vec![json!({"id": 2})]
+        );
+        assert!(records_at(body.to_owned(), Some("missing")).is_empty());
+        assert_eq!(records_at(json!({"id": 1}), None), vec![json!({"id": 1})]);
+
+        let rows = to_rows(vec![json!(1), json!("a"), json!([1, 2])]);
+        assert_eq!(rows[0].columns[0].name, "value");
+        assert_eq!(rows[0].columns[0].data_type, HttpColumnType::Text);
+        assert_eq!(rows[2].values[0], json!("[1,2]"));
+    }
+
+    #[test]
+    fn test_http_next_link() {
+        let header = "<https://api.github.com/repos?page=3>; rel=\"last\", \
+            <https://api.github.com/repos?page=2>; rel=\"next\"";
+
+        assert_eq!(
+            next_link(header),
+            Some("https://api.github.com/repos?page=2".into())
+        );
+        assert_eq!(next_link("<https://a.com/?page=1>; rel=\"prev\""), None);
+    }
+
+    #[test]
+    fn test_http_to_record_batch() {
+        let rows = to_rows(vec![
+            json!({"a": null, "b": true, "c": "x"}),
+            json!({"a": 1, "d": 2}),
+        ]);
+        let batch = HttpConnection::to_record_batch(&rows).unwrap().unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.num_columns(), 4);
+        assert_eq!(batch.column(0).null_count(), 1);
+        assert_eq!(batch.column(2).null_count(), 1);
+        assert!(
+            HttpConnection::to_record_batch(&to_rows(vec![json!({})]))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -6,51 +6,156 @@ use arrow::{
 };
 use async_trait::async_trait;
 use bytes::Bytes;
+use parameter::SqlParameter;
 use parquet::arrow::ArrowWriter;
 use schema::DatabaseSchema;
 use snowflake_connection::SnowflakeConnection;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use stream::{ChunkSender, Page, StreamSummary};
 
-use crate::{arrow::arrow_type::ArrowType, error::Result};
+use crate::{
+    arrow::arrow_type::ArrowType,
+    error::{Result, SharedError},
+};
 
 use self::{
-    mssql_connection::MsSqlConnection, mysql_connection::MySqlConnection,
-    postgres_connection::PostgresConnection,
+    clickhouse_connection::ClickHouseConnection, duckdb_connection::DuckDbConnection,
+    http_connection::HttpConnection, mssql_connection::MsSqlConnection,
+    mysql_connection::MySqlConnection, postgres_connection::PostgresConnection,
+    sqlite_connection::SqliteConnection,
 };
 
+pub mod clickhouse_connection;
+pub mod duckdb_connection;
 pub mod error;
+pub mod http_connection;
 pub mod mssql_connection;
 pub mod mysql_connection;
+pub mod parameter;
 pub mod postgres_connection;
+pub mod read_only;
 pub mod schema;
 pub mod snowflake_connection;
+pub mod sqlite_connection;
+pub mod ssh;
+pub mod stream;
+pub mod tls;
 
 pub enum SqlConnection {
     Postgres(PostgresConnection),
     Mysql(MySqlConnection),
     Mssql(MsSqlConnection),
     SnowflakeConnection(SnowflakeConnection),
+    Sqlite(SqliteConnection),
+    DuckDb(DuckDbConnection),
+    ClickHouse(ClickHouseConnection),
+    Http(HttpConnection),
+}
+
+/// Parse a connection's optional port
+pub(crate) fn parse_port(port: &Option<String>) -> Result<Option<u16>> {
+    port.as_ref()
+        .map(|port| {
+            port.parse::<u16>().map_err(|_| {
+                SharedError::Sql(error::Sql::Connect(
+                    "Could not parse port into a number".into(),
+                ))
+            })
+        })
+        .transpose()
+}
+
+/// Resolve the path of a file database.  Within a data directory, paths are
+/// relative to it and may not escape it, eg, with `..` or a symlink.
+pub(crate) fn resolve_path(path: &str, data_dir: Option<&Path>) -> Result<PathBuf> {
+    let connect_error = |e: String| SharedError::Sql(error::Sql::Connect(e));
+
+    let Some(data_dir) = data_dir else {
+        return Ok(PathBuf::from(path));
+    };
+
+    let data_dir = data_dir
+        .canonicalize()
+        .map_err(|e| connect_error(format!("Data directory {data_dir:?}: {e}")))?;
+    let resolved = data_dir
+        .join(path.trim_start_matches('/'))
+        .canonicalize()
+        .map_err(|e| connect_error(format!("{path:?}: {e}")))?;
+
+    if !resolved.starts_with(&data_dir) {
+        return Err(connect_error(format!(
+            "{path:?} is outside of the data directory"
+        )));
+    }
+
+    Ok(resolved)
 }
 
 #[async_trait]
 pub trait Connection {
-    type Conn;
+    type Conn: Send;
     type Row;
     type Column;
 
+    /// The kind of database, eg, for labeling metrics
+    const KIND: &'static str;
+
     // Connect to a database
     async fn connect(&self) -> Result<Self::Conn>;
 
-    /// Generically query a database
+    /// Generically query a database, binding `params` to the query's
+    /// placeholders in order
     ///
     /// Returns: (Parquet bytes, is over the limit, number of records)
     async fn query(
         &self,
         pool: &mut Self::Conn,
         sql: &str,
+        params: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Bytes, bool, usize)>;
 
+    /// Query a page of rows, sending the results as Parquet row groups while
+    /// the query runs rather than collecting them first
+    async fn query_stream(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary>;
+
+    /// Identify the database session that runs a connection's queries, so
+    /// that they can be cancelled from another connection.  Returns None for
+    /// databases whose queries can't be cancelled.
+    async fn session_id(&self, _pool: &mut Self::Conn) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Cancel the query running in a session, from a new connection
+    async fn cancel(&self, _session_id: &str) -> Result<()> {
+        Err(SharedError::Sql(error::Sql::Cancel(format!(
+            "Cancelling {} queries is not supported",
+            Self::KIND
+        ))))
+    }
+
+    /// How long to cache the connection's query results, in seconds, when
+    /// it overrides the service's default.  Zero disables caching.
+    fn cache_ttl_s(&self) -> Option<u64> {
+        None
+    }
+
+    /// Whether the connection may only read data.  Queries on read-only
+    /// connections are checked with `read_only::check_read_only`, and
+    /// databases that have read-only sessions use them too.
+    fn read_only(&self) -> bool {
+        false
+    }
+
     /// Get the number of columns in a row
     fn row_len(row: &Self::Row) -> usize;
 
@@ -60,8 +165,9 @@ pub trait Connection {
     /// Get the name of a column
     fn column_name(col: &Self::Column) -> &str;
 
-    /// Generically query a database
-    async fn schema(&self, pool: &mut Self::Conn) -> Result<DatabaseSchema>;
+    /// Introspect the tables, views, keys and indexes of the database.  An
+    /// empty `schemas` reads every schema, otherwise only the given ones.
+    async fn schema(&self, pool: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema>;
 
     /// Convert a database-specific column to an Arrow type
     fn to_arrow(row: &Self::Row, col: &Self::Column, col_index: usize) -> ArrowType;
@@ -71,11 +177,29 @@ pub trait Connection {
     /// Returns: (Parquet bytes, number of records)
     /// This should work over any row/column SQLx vec
     fn to_parquet(data: Vec<Self::Row>) -> Result<(Bytes, usize)> {
-        if data.is_empty() {
+        let Some(record_batch) = Self::to_record_batch(&data)? else {
             // return Err(SharedError::Sql(Sql::ParquetConversion(
             //     "No data to convert".to_string(),
             // )));
             return Ok((Bytes::new(), 0));
+        };
+
+        let file = Vec::new();
+        let mut writer = ArrowWriter::try_new(file, record_batch.schema(), None)?;
+        let record_count = record_batch.num_rows();
+        writer.write(&record_batch)?;
+        let parquet = writer.into_inner()?;
+
+        Ok((parquet.into(), record_count))
+    }
+
+    /// Default implementation of converting rows to an Arrow record batch,
+    /// whose column types are taken from the first row
+    ///
+    /// Returns None when there are no rows
+    fn to_record_batch(data: &[Self::Row]) -> Result<Option<RecordBatch>> {
+        if data.is_empty() {
+            return Ok(None);
         }
 
         let col_count = Self::row_len(&data[0]);
@@ -83,14 +207,13 @@ pub trait Connection {
         // transpose columns to rows, converting to Arrow types
         let mut transposed = vec![vec![]; col_count];
 
-        for row in &data {
+        for row in data {
             for (col_index, col) in Self::row_columns(row).enumerate() {
                 let value = Self::to_arrow(row, col, col_index);
                 transposed[col_index].push(value);
             }
         }
 
-        let file = Vec::new();
         let cols = transposed
             .into_iter()
             .map(ArrowType::to_array_ref)
@@ -118,13 +241,9 @@ pub trait Connection {
         // }
 
         let schema = ArrowSchema::new(fields);
-        let mut writer = ArrowWriter::try_new(file, Arc::new(schema.clone()), None)?;
         let record_batch = RecordBatch::try_new(Arc::new(schema), cols)?;
-        let record_count = record_batch.num_rows();
-        writer.write(&record_batch)?;
-        let parquet = writer.into_inner()?;
 
-        Ok((parquet.into(), record_count))
+        Ok(Some(record_batch))
     }
 }
 
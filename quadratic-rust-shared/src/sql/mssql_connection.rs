@@ -2,7 +2,6 @@
 //!
 //! Functions to interact with Microsoft SQL Server
 
-use std::collections::BTreeMap;
 use std::str::FromStr;
 
 use arrow::datatypes::Date32Type;
@@ -10,21 +9,29 @@ use async_trait::async_trait;
 use bigdecimal::BigDecimal;
 use bytes::Bytes;
 use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
-use futures_util::{StreamExt, TryStreamExt};
+use futures_util::StreamExt;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use tiberius::ColumnData;
 use tiberius::xml::XmlData;
-use tiberius::{AuthMethod, Client, Column, Config, FromSql, FromSqlOwned, Row};
+use tiberius::{
+    AuthMethod, Client, Column, Config, EncryptionLevel, FromSql, FromSqlOwned, Row, ToSql,
+};
 use tokio::net::TcpStream;
 use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
 use uuid::Uuid;
 
 use crate::arrow::arrow_type::ArrowType;
 use crate::error::{Result, SharedError};
-use crate::sql::Connection;
 use crate::sql::error::Sql as SqlError;
-use crate::sql::schema::{DatabaseSchema, SchemaColumn, SchemaTable};
+use crate::sql::parameter::SqlParameter;
+use crate::sql::schema::{DatabaseSchema, SchemaColumn, TableKind, non_empty, optional};
+use crate::sql::ssh::SshConfig;
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_rows};
+use crate::sql::tls::{TlsConfig, TlsMode};
+use crate::sql::{Connection, parse_port};
+
+const DEFAULT_PORT: u16 = 1433;
 
 /// Microsoft SQL Server connection
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +41,16 @@ pub struct MsSqlConnection {
     pub host: String,
     pub port: Option<String>,
     pub database: String,
+    pub tls: Option<TlsConfig>,
+    pub ssh: Option<SshConfig>,
+
+    // seconds to cache query results for, overriding the service's default
+    #[serde(default)]
+    pub cache_ttl_s: Option<u64>,
+
+    // reject queries that write data or change the schema
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 impl MsSqlConnection {
@@ -44,6 +61,8 @@ impl MsSqlConnection {
         host: String,
         port: Option<String>,
         database: String,
+        tls: Option<TlsConfig>,
+        ssh: Option<SshConfig>,
     ) -> MsSqlConnection {
         MsSqlConnection {
             username,
@@ -51,14 +70,22 @@ impl MsSqlConnection {
             host,
             port,
             database,
+            tls,
+            ssh,
+            cache_ttl_s: None,
+            read_only: false,
         }
     }
 
     /// Query all rows from a SQL Server
-    async fn query_all(client: &mut Client<Compat<TcpStream>>, sql: &str) -> Result<Vec<Row>> {
+    async fn query_all(
+        client: &mut Client<Compat<TcpStream>>,
+        sql: &str,
+        params: &[SqlParameter],
+    ) -> Result<Vec<Row>> {
         let mut rows = vec![];
         let mut row_stream = client
-            .query(sql, &[])
+            .query(sql, &to_sql_params(params))
             .await
             .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?
             .into_row_stream();
@@ -74,12 +101,42 @@ impl MsSqlConnection {
     }
 }
 
+/// Convert parameters to tiberius values, which bind to `@P1`, `@P2`, etc.
+fn to_sql_params(params: &[SqlParameter]) -> Vec<&dyn ToSql> {
+    params.iter().map(to_sql).collect()
+}
+
+fn to_sql(param: &SqlParameter) -> &dyn ToSql {
+    static NULL: Option<&str> = None;
+
+    match param {
+        SqlParameter::Null => &NULL,
+        SqlParameter::Boolean(value) => value,
+        SqlParameter::Integer(value) => value,
+        SqlParameter::Float(value) => value,
+        SqlParameter::Text(value) => value,
+        SqlParameter::Date(value) => value,
+        SqlParameter::Time(value) => value,
+        SqlParameter::DateTime(value) => value,
+    }
+}
+
 #[async_trait]
 impl Connection for MsSqlConnection {
     type Conn = Client<Compat<TcpStream>>;
     type Row = Row;
     type Column = Column;
 
+    const KIND: &'static str = "mssql";
+
+    fn cache_ttl_s(&self) -> Option<u64> {
+        self.cache_ttl_s
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Get the length of a row
     fn row_len(row: &Self::Row) -> usize {
         row.len()
@@ -95,20 +152,24 @@ impl Connection for MsSqlConnection {
         col.name()
     }
 
-    /// Connect to a SQL Server
+    /// Connect to a SQL Server, through the SSH tunnel if there is one
     async fn connect(&self) -> Result<Client<Compat<TcpStream>>> {
+        let connect_error = |e: String| SharedError::Sql(SqlError::Connect(e));
         let mut config = Config::new();
         config.host(&self.host);
         config.database(&self.database);
 
-        if let Some(port) = &self.port {
-            config.port(port.parse::<u16>().map_err(|_| {
-                SharedError::Sql(SqlError::Connect(
-                    "Could not parse port into a number".into(),
-                ))
-            })?);
+        let port = parse_port(&self.port)?;
+
+        if let Some(port) = port {
+            config.port(port);
         }
 
+        // `ApplicationIntent=ReadOnly`, which routes to a readable secondary
+        // when there is one.  A primary still allows writes, so on a primary
+        // read-only relies on `check_read_only` alone.
+        config.readonly(self.read_only);
+
         if let Some(username) = &self.username {
             config.authentication(AuthMethod::sql_server(
                 username,
@@ -118,20 +179,69 @@ impl Connection for MsSqlConnection {
             ));
         }
 
-        config.trust_cert();
+        let tls = self.tls.to_owned().unwrap_or_default();
 
-        let tcp = TcpStream::connect(config.get_addr()).await.map_err(|e| {
+        if tls.has_client_certificate() {
+            return Err(connect_error(
+                "Client certificates are not supported for SQL Server".into(),
+            ));
+        }
+
+        // tiberius reads the CA certificate from a file while connecting
+        let mut ca_certificate_path = None;
+
+        match tls.mode {
+            TlsMode::Disable => config.encryption(EncryptionLevel::NotSupported),
+
+            // SQL Server encrypts by default, so this is the same as require
+            TlsMode::Prefer => config.trust_cert(),
+            TlsMode::Require => {
+                config.encryption(EncryptionLevel::Required);
+                config.trust_cert();
+            }
+
+            // tiberius always verifies the host name along with the CA
+            TlsMode::VerifyCa | TlsMode::VerifyFull => {
+                config.encryption(EncryptionLevel::Required);
+
+                if let Some(ca_certificate) = &tls.ca_certificate {
+                    let path = std::env::temp_dir()
+                        .join(format!("quadratic-mssql-ca-{}.pem", Uuid::new_v4()));
+                    std::fs::write(&path, ca_certificate).map_err(|e| {
+                        connect_error(format!("Failed to write CA certificate: {e}"))
+                    })?;
+                    config.trust_cert_ca(path.to_string_lossy());
+                    ca_certificate_path = Some(path);
+                }
+            }
+        }
+
+        // the TLS host name stays the database's host when tunnelling, since
+        // the stream is created here rather than by tiberius
+        let addr = match &self.ssh {
+            Some(ssh) => ssh
+                .tunnel(&self.host, port.unwrap_or(DEFAULT_PORT))
+                .await?
+                .to_string(),
+            None => config.get_addr(),
+        };
+
+        let tcp = TcpStream::connect(addr).await.map_err(|e| {
             SharedError::Sql(SqlError::Connect(format!("Failed to connect: {}", e)))
         })?;
         tcp.set_nodelay(true).map_err(|e| {
             SharedError::Sql(SqlError::Connect(format!("Failed to set nodelay: {}", e)))
         })?;
 
-        let client = Client::connect(config, tcp.compat_write())
-            .await
-            .map_err(|e| {
-                SharedError::Sql(SqlError::Connect(format!("Failed to create client: {}", e)))
-            })?;
+        let client = Client::connect(config, tcp.compat_write()).await;
+
+        if let Some(path) = ca_certificate_path {
+            let _ = std::fs::remove_file(path);
+        }
+
+        let client = client.map_err(|e| {
+            SharedError::Sql(SqlError::Connect(format!("Failed to create client: {}", e)))
+        })?;
 
         Ok(client)
     }
@@ -141,6 +251,7 @@ impl Connection for MsSqlConnection {
         &self,
         client: &mut Self::Conn,
         sql: &str,
+        params: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Bytes, bool, usize)> {
         let mut rows = vec![];
@@ -150,7 +261,7 @@ impl Connection for MsSqlConnection {
             let mut bytes = 0;
 
             let mut row_stream = client
-                .query(sql, &[])
+                .query(sql, &to_sql_params(params))
                 .await
                 .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?
                 .into_row_stream();
@@ -171,7 +282,7 @@ impl Connection for MsSqlConnection {
                 }
             }
         } else {
-            rows = Self::query_all(client, sql).await?;
+            rows = Self::query_all(client, sql, params).await?;
         }
 
         let (bytes, num_records) = Self::to_parquet(rows)?;
@@ -179,65 +290,221 @@ impl Connection for MsSqlConnection {
         Ok((bytes, over_the_limit, num_records))
     }
 
+    /// Stream a page of rows from a SQL Server
+    async fn query_stream(
+        &self,
+        client: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        let rows = client
+            .query(sql, &to_sql_params(params))
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?
+            .into_row_stream()
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())));
+
+        stream_rows::<Self, _>(rows, page, sender, max_bytes).await
+    }
+
+    /// Get the session's process id
+    async fn session_id(&self, client: &mut Self::Conn) -> Result<Option<String>> {
+        let query_error = |e: String| SharedError::Sql(SqlError::Query(e));
+        let row = client
+            .simple_query("SELECT @@SPID")
+            .await
+            .map_err(|e| query_error(e.to_string()))?
+            .into_row()
+            .await
+            .map_err(|e| query_error(e.to_string()))?
+            .ok_or_else(|| query_error("No session id".into()))?;
+        let spid = row
+            .get::<i16, usize>(0)
+            .ok_or_else(|| query_error("No session id".into()))?;
+
+        Ok(Some(spid.to_string()))
+    }
+
+    /// Cancel a session's query with `KILL`.  SQL Server can only kill the
+    /// whole session, which requires the `ALTER ANY CONNECTION` permission.
+    async fn cancel(&self, session_id: &str) -> Result<()> {
+        let cancel_error = |e: String| SharedError::Sql(SqlError::Cancel(e));
+        let spid = session_id
+            .parse::<i16>()
+            .map_err(|e| cancel_error(e.to_string()))?;
+        let mut client = self.connect().await?;
+
+        client
+            .simple_query(format!("KILL {spid}"))
+            .await
+            .map_err(|e| cancel_error(e.to_string()))?;
+
+        Ok(())
+    }
+
     /// Get the schema of a SQL Server
-    async fn schema(&self, client: &mut Self::Conn) -> Result<DatabaseSchema> {
+    async fn schema(&self, client: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema> {
         let database = self.database.to_owned();
+        let params = schemas
+            .iter()
+            .map(|schema| SqlParameter::Text(schema.to_owned()))
+            .collect::<Vec<_>>();
+        let filter = match schemas.is_empty() {
+            true => "".to_string(),
+            false => {
+                let placeholders = (1..=schemas.len())
+                    .map(|index| format!("@P{index}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("AND s.name IN ({placeholders})")
+            }
+        };
+
+        // tables, views and their columns
         let sql = format!(
             "
 SELECT
-    DB_NAME() AS 'database',
     s.name AS 'schema',
-    t.name AS 'table',
+    o.name AS 'table',
+    RTRIM(o.type) AS 'table_type',
     c.name AS 'column_name',
     TYPE_NAME(c.user_type_id) AS 'column_type',
-    CASE WHEN c.is_nullable = 1 THEN 'YES' ELSE 'NO' END AS 'is_nullable'
+    CASE WHEN c.is_nullable = 1 THEN 'YES' ELSE 'NO' END AS 'is_nullable',
+    dc.definition AS 'column_default',
+    CAST(cep.value AS nvarchar(max)) AS 'column_comment',
+    CAST(tep.value AS nvarchar(max)) AS 'table_comment',
+    (SELECT CAST(SUM(p.rows) AS bigint) FROM {database}.sys.partitions p
+        WHERE p.object_id = o.object_id AND p.index_id IN (0, 1)) AS 'row_count'
 FROM
     {database}.sys.columns c
 INNER JOIN
-    {database}.sys.tables t ON c.object_id = t.object_id
+    {database}.sys.objects o ON c.object_id = o.object_id
 INNER JOIN
-    {database}.sys.schemas s ON t.schema_id = s.schema_id
+    {database}.sys.schemas s ON o.schema_id = s.schema_id
+LEFT JOIN
+    {database}.sys.default_constraints dc ON dc.object_id = c.default_object_id
+LEFT JOIN
+    {database}.sys.extended_properties cep ON cep.class = 1 AND cep.major_id = c.object_id
+        AND cep.minor_id = c.column_id AND cep.name = 'MS_Description'
+LEFT JOIN
+    {database}.sys.extended_properties tep ON tep.class = 1 AND tep.major_id = o.object_id
+        AND tep.minor_id = 0 AND tep.name = 'MS_Description'
+WHERE
+    o.type IN ('U', 'V') AND o.is_ms_shipped = 0 {filter}
 ORDER BY
-    t.name, c.column_id, c.name"
+    s.name, o.name, c.column_id"
         );
 
-        let row_stream = client
-            .query(sql, &[])
-            .await
-            .map_err(|e| SharedError::Sql(SqlError::Schema(e.to_string())))?
-            .into_row_stream();
-
-        let rows: Vec<Row> = row_stream
-            .try_collect()
+        let rows = Self::query_all(client, &sql, &params)
             .await
             .map_err(|e| SharedError::Sql(SqlError::Schema(e.to_string())))?;
 
-        let mut schema = DatabaseSchema {
-            database: self.database.to_owned(),
-            tables: BTreeMap::new(),
-        };
+        let mut schema = DatabaseSchema::new(self.database.to_owned());
 
         for (index, row) in rows.into_iter().enumerate() {
             let safe_get = |data: Option<&str>, kind: &str| {
                 data.map(|s| s.to_string())
                     .unwrap_or(format!("Unknown {kind} - {index}"))
             };
-            let table_name = safe_get(row.get(2), "Table");
-
-            schema
-                .tables
-                .entry(table_name.to_owned())
-                .or_insert_with(|| SchemaTable {
-                    name: table_name,
-                    schema: safe_get(row.get(1), "Schema"),
-                    columns: vec![],
-                })
-                .columns
-                .push(SchemaColumn {
-                    name: safe_get(row.get(3), "Column"),
-                    r#type: safe_get(row.get(4), "Type"),
-                    is_nullable: row.get(5).map_or("NO", |v| v).to_uppercase() == "YES",
-                });
+            let optional_get = |data: Option<&str>| non_empty(data.map(|s| s.to_string()));
+            let table = schema.table_mut(
+                &safe_get(row.get(0), "Schema"),
+                &safe_get(row.get(1), "Table"),
+            );
+            let is_view = row.get::<&str, usize>(2) == Some("V");
+
+            if table.columns.is_empty() {
+                table.kind = if is_view {
+                    TableKind::View
+                } else {
+                    TableKind::Table
+                };
+                table.comment = optional_get(row.get(8));
+                table.row_count = row.get::<i64, usize>(9).map(|count| count as u64);
+            }
+
+            table.columns.push(SchemaColumn {
+                name: safe_get(row.get(3), "Column"),
+                r#type: safe_get(row.get(4), "Type"),
+                is_nullable: row.get(5).map_or("NO", |v| v).to_uppercase() == "YES",
+                default: optional_get(row.get(6)),
+                comment: optional_get(row.get(7)),
+            });
+        }
+
+        // primary keys, one row per column
+        let sql = format!(
+            "
+SELECT s.name, o.name, col.name
+FROM {database}.sys.key_constraints kc
+INNER JOIN {database}.sys.objects o ON o.object_id = kc.parent_object_id
+INNER JOIN {database}.sys.schemas s ON s.schema_id = o.schema_id
+INNER JOIN {database}.sys.index_columns ic ON ic.object_id = kc.parent_object_id AND ic.index_id = kc.unique_index_id
+INNER JOIN {database}.sys.columns col ON col.object_id = ic.object_id AND col.column_id = ic.column_id
+WHERE kc.type = 'PK' {filter}
+ORDER BY s.name, o.name, ic.key_ordinal"
+        );
+
+        for row in optional("primary keys", Self::query_all(client, &sql, &params).await) {
+            let get = |index: usize| row.get::<&str, usize>(index).unwrap_or_default().to_owned();
+
+            if let Some(table) = schema.existing_table_mut(&get(0), &get(1)) {
+                table.primary_key.push(get(2));
+            }
+        }
+
+        // foreign keys, one row per column
+        let sql = format!(
+            "
+SELECT s.name, o.name, fk.name, pc.name, rs.name, ro.name, rc.name
+FROM {database}.sys.foreign_keys fk
+INNER JOIN {database}.sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+INNER JOIN {database}.sys.objects o ON o.object_id = fk.parent_object_id
+INNER JOIN {database}.sys.schemas s ON s.schema_id = o.schema_id
+INNER JOIN {database}.sys.columns pc ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+INNER JOIN {database}.sys.objects ro ON ro.object_id = fk.referenced_object_id
+INNER JOIN {database}.sys.schemas rs ON rs.schema_id = ro.schema_id
+INNER JOIN {database}.sys.columns rc ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+WHERE 1 = 1 {filter}
+ORDER BY s.name, o.name, fk.name, fkc.constraint_column_id"
+        );
+
+        for row in optional("foreign keys", Self::query_all(client, &sql, &params).await) {
+            let get = |index: usize| row.get::<&str, usize>(index).unwrap_or_default().to_owned();
+
+            if let Some(table) = schema.existing_table_mut(&get(0), &get(1)) {
+                table.add_foreign_key_column(&get(2), get(3), get(4), get(5), get(6));
+            }
+        }
+
+        // indexes, one row per key column, heaps have no index
+        let sql = format!(
+            "
+SELECT s.name, o.name, i.name, col.name, i.is_unique, i.is_primary_key
+FROM {database}.sys.indexes i
+INNER JOIN {database}.sys.objects o ON o.object_id = i.object_id
+INNER JOIN {database}.sys.schemas s ON s.schema_id = o.schema_id
+INNER JOIN {database}.sys.index_columns ic ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+    AND ic.is_included_column = 0
+INNER JOIN {database}.sys.columns col ON col.object_id = ic.object_id AND col.column_id = ic.column_id
+WHERE i.index_id > 0 AND i.is_hypothetical = 0 AND o.is_ms_shipped = 0 {filter}
+ORDER BY s.name, o.name, i.name, ic.key_ordinal"
+        );
+
+        for row in optional("indexes", Self::query_all(client, &sql, &params).await) {
+            let get = |index: usize| row.get::<&str, usize>(index).unwrap_or_default().to_owned();
+
+            if let Some(table) = schema.existing_table_mut(&get(0), &get(1)) {
+                table.add_index_column(
+                    &get(2),
+                    get(3),
+                    row.get::<bool, usize>(4).unwrap_or_default(),
+                    row.get::<bool, usize>(5).unwrap_or_default(),
+                );
+            }
         }
 
         Ok(schema)
@@ -350,6 +617,8 @@ mod tests {
             "0.0.0.0".into(),
             Some("1433".into()),
             "AllTypes".into(),
+            None,
+            None,
         )
     }
 
@@ -371,7 +640,7 @@ mod tests {
     async fn test_mssql_query_to_arrow() {
         let (_, client) = setup().await;
         let sql = "SELECT TOP 1 * FROM [dbo].[all_native_data_types] ORDER BY id";
-        let rows = MsSqlConnection::query_all(&mut client.unwrap(), sql)
+        let rows = MsSqlConnection::query_all(&mut client.unwrap(), sql, &[])
             .await
             .unwrap();
 
@@ -466,7 +735,7 @@ mod tests {
     async fn test_mssql_schema() {
         let connection = new_mssql_connection();
         let mut client = connection.connect().await.unwrap();
-        let schema = connection.schema(&mut client).await.unwrap();
+        let schema = connection.schema(&mut client, &[]).await.unwrap();
 
         // for (table_name, table) in &schema.tables {
         //     println!("Table: {}", table_name);
@@ -476,175 +745,46 @@ mod tests {
         // }
 
         let expected = vec![
-            SchemaColumn {
-                name: "id".into(),
-                r#type: "int".into(),
-                is_nullable: false,
-            },
-            SchemaColumn {
-                name: "tinyint_col".into(),
-                r#type: "tinyint".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "smallint_col".into(),
-                r#type: "smallint".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "int_col".into(),
-                r#type: "int".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "bigint_col".into(),
-                r#type: "bigint".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "bit_col".into(),
-                r#type: "bit".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "decimal_col".into(),
-                r#type: "decimal".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "numeric_col".into(),
-                r#type: "numeric".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "money_col".into(),
-                r#type: "money".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "smallmoney_col".into(),
-                r#type: "smallmoney".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "float_col".into(),
-                r#type: "float".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "real_col".into(),
-                r#type: "real".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "date_col".into(),
-                r#type: "date".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "time_col".into(),
-                r#type: "time".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "datetime2_col".into(),
-                r#type: "datetime2".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "datetimeoffset_col".into(),
-                r#type: "datetimeoffset".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "datetime_col".into(),
-                r#type: "datetime".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "smalldatetime_col".into(),
-                r#type: "smalldatetime".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "char_col".into(),
-                r#type: "char".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "varchar_col".into(),
-                r#type: "varchar".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "text_col".into(),
-                r#type: "text".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "nchar_col".into(),
-                r#type: "nchar".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "nvarchar_col".into(),
-                r#type: "nvarchar".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "ntext_col".into(),
-                r#type: "ntext".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "binary_col".into(),
-                r#type: "binary".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "varbinary_col".into(),
-                r#type: "varbinary".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "image_col".into(),
-                r#type: "image".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "json_col".into(),
-                r#type: "nvarchar".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "uniqueidentifier_col".into(),
-                r#type: "uniqueidentifier".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "xml_col".into(),
-                r#type: "xml".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "varchar_max_col".into(),
-                r#type: "varchar".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "nvarchar_max_col".into(),
-                r#type: "nvarchar".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "varbinary_max_col".into(),
-                r#type: "varbinary".into(),
-                is_nullable: true,
-            },
+            SchemaColumn::new("id", "int", false),
+            SchemaColumn::new("tinyint_col", "tinyint", true),
+            SchemaColumn::new("smallint_col", "smallint", true),
+            SchemaColumn::new("int_col", "int", true),
+            SchemaColumn::new("bigint_col", "bigint", true),
+            SchemaColumn::new("bit_col", "bit", true),
+            SchemaColumn::new("decimal_col", "decimal", true),
+            SchemaColumn::new("numeric_col", "numeric", true),
+            SchemaColumn::new("money_col", "money", true),
+            SchemaColumn::new("smallmoney_col", "smallmoney", true),
+            SchemaColumn::new("float_col", "float", true),
+            SchemaColumn::new("real_col", "real", true),
+            SchemaColumn::new("date_col", "date", true),
+            SchemaColumn::new("time_col", "time", true),
+            SchemaColumn::new("datetime2_col", "datetime2", true),
+            SchemaColumn::new("datetimeoffset_col", "datetimeoffset", true),
+            SchemaColumn::new("datetime_col", "datetime", true),
+            SchemaColumn::new("smalldatetime_col", "smalldatetime", true),
+            SchemaColumn::new("char_col", "char", true),
+            SchemaColumn::new("varchar_col", "varchar", true),
+            SchemaColumn::new("text_col", "text", true),
+            SchemaColumn::new("nchar_col", "nchar", true),
+            SchemaColumn::new("nvarchar_col", "nvarchar", true),
+            SchemaColumn::new("ntext_col", "ntext", true),
+            SchemaColumn::new("binary_col", "binary", true),
+            SchemaColumn::new("varbinary_col", "varbinary", true),
+            SchemaColumn::new("image_col", "image", true),
+            SchemaColumn::new("json_col", "nvarchar", true),
+            SchemaColumn::new("uniqueidentifier_col", "uniqueidentifier", true),
+            SchemaColumn::new("xml_col", "xml", true),
+            SchemaColumn::new("varchar_max_col", "varchar", true),
+            SchemaColumn::new("nvarchar_max_col", "nvarchar", true),
+            SchemaColumn::new("varbinary_max_col", "varbinary", true),
         ];
 
-        let columns = &schema.tables.get("all_native_data_types").unwrap().columns;
+        let table = schema.tables.get("dbo.all_native_data_types").unwrap();
 
-        assert_eq!(columns, &expected);
+        assert_eq!(&table.columns, &expected);
+        assert_eq!(table.kind, TableKind::Table);
+        assert_eq!(table.primary_key, vec!["id"]);
+        assert!(table.indexes[0].is_primary);
     }
 }
@@ -2,14 +2,12 @@
 //!
 //! Functions to interact with MySQL
 
-use std::collections::BTreeMap;
-
 use arrow::datatypes::Date32Type;
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
 use bytes::Bytes;
 use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
-use futures_util::StreamExt;
+use futures_util::{StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
@@ -22,8 +20,14 @@ use sqlx::{
 use crate::convert_mysql_type;
 use crate::error::{Result, SharedError};
 use crate::sql::error::Sql as SqlError;
-use crate::sql::schema::{DatabaseSchema, SchemaColumn, SchemaTable};
-use crate::sql::{ArrowType, Connection};
+use crate::sql::parameter::{SqlParameter, bind_sqlx};
+use crate::sql::schema::{DatabaseSchema, SchemaColumn, TableKind, non_empty, optional};
+use crate::sql::ssh::SshConfig;
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_rows};
+use crate::sql::tls::TlsConfig;
+use crate::sql::{ArrowType, Connection, parse_port};
+
+const DEFAULT_PORT: u16 = 3306;
 
 /// MySQL connection
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +37,16 @@ pub struct MySqlConnection {
     pub host: String,
     pub port: Option<String>,
     pub database: String,
+    pub tls: Option<TlsConfig>,
+    pub ssh: Option<SshConfig>,
+
+    // seconds to cache query results for, overriding the service's default
+    #[serde(default)]
+    pub cache_ttl_s: Option<u64>,
+
+    // reject queries that write data or change the schema
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 impl MySqlConnection {
@@ -43,6 +57,8 @@ impl MySqlConnection {
         host: String,
         port: Option<String>,
         database: String,
+        tls: Option<TlsConfig>,
+        ssh: Option<SshConfig>,
     ) -> MySqlConnection {
         MySqlConnection {
             username,
@@ -50,6 +66,10 @@ impl MySqlConnection {
             host,
             port,
             database,
+            tls,
+            ssh,
+            cache_ttl_s: None,
+            read_only: false,
         }
     }
 
@@ -62,6 +82,20 @@ impl MySqlConnection {
 
         Ok(rows)
     }
+
+    /// Query the catalog, binding the schemas to filter by in order
+    async fn query_schema(
+        pool: &mut SqlxMySqlConnection,
+        sql: &str,
+        schemas: &[String],
+    ) -> Result<Vec<MySqlRow>> {
+        schemas
+            .iter()
+            .fold(sqlx::query(sql), |query, schema| query.bind(schema))
+            .fetch_all(pool)
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))
+    }
 }
 
 #[async_trait]
@@ -70,6 +104,16 @@ impl Connection for MySqlConnection {
     type Row = MySqlRow;
     type Column = MySqlColumn;
 
+    const KIND: &'static str = "mysql";
+
+    fn cache_ttl_s(&self) -> Option<u64> {
+        self.cache_ttl_s
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Get the length of a row
     fn row_len(row: &Self::Row) -> usize {
         row.len()
@@ -85,10 +129,9 @@ impl Connection for MySqlConnection {
         col.name()
     }
 
-    /// Connect to a MySQL database
+    /// Connect to a MySQL database, through the SSH tunnel if there is one
     async fn connect(&self) -> Result<Self::Conn> {
         let mut options = MySqlConnectOptions::new();
-        options = options.host(&self.host);
         options = options.database(&self.database);
 
         if let Some(ref username) = self.username {
@@ -99,18 +142,57 @@ impl Connection for MySqlConnection {
             options = options.password(password);
         }
 
-        if let Some(ref port) = self.port {
-            options = options.port(port.parse::<u16>().map_err(|_| {
-                SharedError::Sql(SqlError::Connect(
-                    "Could not parse port into a number".into(),
-                ))
-            })?);
+        let port = parse_port(&self.port)?;
+
+        match &self.ssh {
+            Some(ssh) => {
+                let local_addr = ssh.tunnel(&self.host, port.unwrap_or(DEFAULT_PORT)).await?;
+                options = options
+                    .host(&local_addr.ip().to_string())
+                    .port(local_addr.port());
+            }
+            None => {
+                options = options.host(&self.host);
+
+                if let Some(port) = port {
+                    options = options.port(port);
+                }
+            }
         }
 
-        let pool = options.connect().await.map_err(|e| {
+        if let Some(ref tls) = self.tls {
+            let mode = match self.ssh {
+                Some(_) => tls.tunnelled_mode(),
+                None => tls.mode,
+            };
+            options = options.ssl_mode(mode.into());
+
+            if let Some(ref ca_certificate) = tls.ca_certificate {
+                options = options.ssl_ca_from_pem(ca_certificate.as_bytes().to_vec());
+            }
+
+            if let Some(ref client_certificate) = tls.client_certificate {
+                options = options.ssl_client_cert_from_pem(client_certificate);
+            }
+
+            if let Some(ref client_key) = tls.client_key {
+                options = options.ssl_client_key_from_pem(client_key);
+            }
+        }
+
+        let mut pool = options.connect().await.map_err(|e| {
             SharedError::Sql(SqlError::Connect(format!("{:?}: {e}", self.database)))
         })?;
 
+        // every transaction, including each autocommitted statement, starts
+        // as if with `START TRANSACTION READ ONLY`
+        if self.read_only {
+            sqlx::query("SET SESSION TRANSACTION READ ONLY")
+                .execute(&mut pool)
+                .await
+                .map_err(|e| SharedError::Sql(SqlError::Connect(e.to_string())))?;
+        }
+
         Ok(pool)
     }
 
@@ -119,6 +201,7 @@ impl Connection for MySqlConnection {
         &self,
         pool: &mut Self::Conn,
         sql: &str,
+        params: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Bytes, bool, usize)> {
         let mut rows = vec![];
@@ -126,7 +209,7 @@ impl Connection for MySqlConnection {
 
         if let Some(max_bytes) = max_bytes {
             let mut bytes = 0;
-            let mut stream = sqlx::query(sql).fetch(pool);
+            let mut stream = bind_sqlx(sqlx::query(sql), params).fetch(pool);
 
             while let Some(row) = stream.next().await {
                 let row = row.map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
@@ -140,7 +223,10 @@ impl Connection for MySqlConnection {
                 rows.push(row);
             }
         } else {
-            rows = MySqlConnection::query_all(pool, sql).await?;
+            rows = bind_sqlx(sqlx::query(sql), params)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
         }
 
         let (bytes, num_records) = Self::to_parquet(rows)?;
@@ -148,45 +234,145 @@ impl Connection for MySqlConnection {
         Ok((bytes, over_the_limit, num_records))
     }
 
+    /// Stream a page of rows from a MySQL database
+    async fn query_stream(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        let rows = bind_sqlx(sqlx::query(sql), params)
+            .fetch(pool)
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())));
+
+        stream_rows::<Self, _>(rows, page, sender, max_bytes).await
+    }
+
+    /// Get the connection's thread id
+    async fn session_id(&self, pool: &mut Self::Conn) -> Result<Option<String>> {
+        let id = sqlx::query_scalar::<_, u64>("select connection_id()")
+            .fetch_one(pool)
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+
+        Ok(Some(id.to_string()))
+    }
+
+    /// Cancel a connection's query with `KILL QUERY`, which can't be prepared
+    async fn cancel(&self, session_id: &str) -> Result<()> {
+        let cancel_error = |e: String| SharedError::Sql(SqlError::Cancel(e));
+        let id = session_id
+            .parse::<u64>()
+            .map_err(|e| cancel_error(e.to_string()))?;
+        let mut pool = self.connect().await?;
+
+        sqlx::raw_sql(&format!("KILL QUERY {id}"))
+            .execute(&mut pool)
+            .await
+            .map_err(|e| cancel_error(e.to_string()))?;
+
+        Ok(())
+    }
+
     /// Get the schema of a MySQL database
-    async fn schema(&self, pool: &mut Self::Conn) -> Result<DatabaseSchema> {
-        let database = self.database.to_owned();
+    async fn schema(&self, pool: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema> {
+        // MySQL calls databases schemas, so default to the connection's database
+        let schemas = match schemas.is_empty() {
+            true => vec![self.database.to_owned()],
+            false => schemas.to_vec(),
+        };
+        let placeholders = vec!["?"; schemas.len()].join(", ");
+        let mut schema = DatabaseSchema::new(self.database.to_owned());
+
+        // information_schema returns binary strings in some versions
+        let row_get = |row: &MySqlRow, index: usize| {
+            let bytes: Vec<u8> = row.get::<Vec<u8>, usize>(index);
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+        let row_get_optional = |row: &MySqlRow, index: usize| {
+            let bytes: Option<Vec<u8>> = row.get::<Option<Vec<u8>>, usize>(index);
+            non_empty(bytes.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+        };
+
+        // tables, views and their columns
         let sql = format!("
-            select c.TABLE_SCHEMA as 'database', c.TABLE_SCHEMA as 'schema', c.TABLE_NAME as 'table', 
-                c.COLUMN_NAME as 'column_name', c.DATA_TYPE as 'column_type', c.IS_NULLABLE as 'is_nullable'
+            select c.TABLE_SCHEMA, c.TABLE_NAME, t.TABLE_TYPE, c.COLUMN_NAME, c.DATA_TYPE, c.IS_NULLABLE,
+                c.COLUMN_DEFAULT, c.COLUMN_COMMENT, t.TABLE_COMMENT, cast(t.TABLE_ROWS as unsigned)
             from INFORMATION_SCHEMA.COLUMNS as c
-            where table_schema = '{database}'
-            order by c.TABLE_NAME, c.ORDINAL_POSITION, c.COLUMN_NAME");
+                inner join INFORMATION_SCHEMA.TABLES as t on t.TABLE_SCHEMA = c.TABLE_SCHEMA and t.TABLE_NAME = c.TABLE_NAME
+            where c.TABLE_SCHEMA in ({placeholders})
+            order by c.TABLE_SCHEMA, c.TABLE_NAME, c.ORDINAL_POSITION");
+
+        for row in Self::query_schema(pool, &sql, &schemas).await? {
+            let table = schema.table_mut(&row_get(&row, 0), &row_get(&row, 1));
+            let is_view = row_get(&row, 2).to_lowercase().contains("view");
+
+            if table.columns.is_empty() {
+                table.kind = if is_view {
+                    TableKind::View
+                } else {
+                    TableKind::Table
+                };
+                // MySQL sets the comment of views to "VIEW"
+                table.comment = row_get_optional(&row, 8).filter(|_| !is_view);
+                table.row_count = row.get::<Option<u64>, usize>(9).filter(|_| !is_view);
+            }
 
-        let rows = MySqlConnection::query_all(pool, &sql).await?;
+            table.columns.push(SchemaColumn {
+                name: row_get(&row, 3),
+                r#type: row_get(&row, 4),
+                is_nullable: matches!(row_get(&row, 5).to_lowercase().as_str(), "yes"),
+                // MariaDB returns "NULL" for columns without a default
+                default: row_get_optional(&row, 6).filter(|default| default != "NULL"),
+                comment: row_get_optional(&row, 7),
+            });
+        }
 
-        let mut schema = DatabaseSchema {
-            database: self.database.to_owned(),
-            tables: BTreeMap::new(),
-        };
+        // primary and foreign keys, one row per column
+        let sql = format!(
+            "
+            select k.TABLE_SCHEMA, k.TABLE_NAME, k.CONSTRAINT_NAME, k.COLUMN_NAME,
+                k.REFERENCED_TABLE_SCHEMA, k.REFERENCED_TABLE_NAME, k.REFERENCED_COLUMN_NAME
+            from INFORMATION_SCHEMA.KEY_COLUMN_USAGE as k
+            where k.TABLE_SCHEMA in ({placeholders})
+                and (k.CONSTRAINT_NAME = 'PRIMARY' or k.REFERENCED_TABLE_NAME is not null)
+            order by k.TABLE_SCHEMA, k.TABLE_NAME, k.CONSTRAINT_NAME, k.ORDINAL_POSITION"
+        );
 
-        for row in rows.into_iter() {
-            let row_get = |row: &MySqlRow, index: usize| {
-                let bytes: Vec<u8> = row.get::<Vec<u8>, usize>(index);
-                String::from_utf8_lossy(&bytes).into_owned()
+        for row in optional("keys", Self::query_schema(pool, &sql, &schemas).await) {
+            let Some(table) = schema.existing_table_mut(&row_get(&row, 0), &row_get(&row, 1))
+            else {
+                continue;
             };
 
-            let table_name = row_get(&row, 2);
-
-            schema
-                .tables
-                .entry(table_name.to_owned())
-                .or_insert_with(|| SchemaTable {
-                    name: table_name,
-                    schema: row_get(&row, 1),
-                    columns: vec![],
-                })
-                .columns
-                .push(SchemaColumn {
-                    name: row_get(&row, 3),
-                    r#type: row_get(&row, 4),
-                    is_nullable: matches!(row_get(&row, 5).to_lowercase().as_str(), "yes"),
-                });
+            match row_get_optional(&row, 5) {
+                Some(referenced_table) => table.add_foreign_key_column(
+                    &row_get(&row, 2),
+                    row_get(&row, 3),
+                    row_get(&row, 4),
+                    referenced_table,
+                    row_get(&row, 6),
+                ),
+                None => table.primary_key.push(row_get(&row, 3)),
+            }
+        }
+
+        // indexes, one row per column, functional indexes are skipped
+        let sql = format!("
+            select s.TABLE_SCHEMA, s.TABLE_NAME, s.INDEX_NAME, s.COLUMN_NAME, if(s.NON_UNIQUE = 0, 'YES', 'NO')
+            from INFORMATION_SCHEMA.STATISTICS as s
+            where s.TABLE_SCHEMA in ({placeholders}) and s.COLUMN_NAME is not null
+            order by s.TABLE_SCHEMA, s.TABLE_NAME, s.INDEX_NAME, s.SEQ_IN_INDEX");
+
+        for row in optional("indexes", Self::query_schema(pool, &sql, &schemas).await) {
+            if let Some(table) = schema.existing_table_mut(&row_get(&row, 0), &row_get(&row, 1)) {
+                let name = row_get(&row, 2);
+                let is_unique = row_get(&row, 4) == "YES";
+                table.add_index_column(&name, row_get(&row, 3), is_unique, name == "PRIMARY");
+            }
         }
 
         Ok(schema)
@@ -245,6 +431,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::sql::schema::SchemaIndex;
     // use std::io::Read;
     use bigdecimal::BigDecimal;
     use serde_json::json;
@@ -256,6 +443,8 @@ mod tests {
             "0.0.0.0".into(),
             Some("3306".into()),
             "mysql-connection".into(),
+            None,
+            None,
         )
     }
 
@@ -337,11 +526,55 @@ mod tests {
         assert_eq!(to_arrow(29), ArrowType::Json(json!({"key": "value"})));
     }
 
+    /// MariaDB is queried through MySQL connections
+    #[tokio::test]
+    async fn test_mariadb_query_to_arrow() {
+        let connection = MySqlConnection::new(
+            Some("user".into()),
+            Some("password".into()),
+            "0.0.0.0".into(),
+            Some("3307".into()),
+            "mariadb-connection".into(),
+            None,
+            None,
+        );
+        let mut pool = connection.connect().await.unwrap();
+        let sql = "select * from mariadb_data_types order by id limit 1";
+        let rows = MySqlConnection::query_all(&mut pool, sql).await.unwrap();
+
+        let row = &rows[0];
+        let columns = row.columns();
+        let to_arrow = |index: usize| MySqlConnection::to_arrow(row, &columns[index], index);
+
+        // MariaDB's UUID, INET4 and INET6 are sent as strings, and JSON is an
+        // alias for LONGTEXT
+        assert_eq!(to_arrow(0), ArrowType::Int32(1));
+        assert_eq!(to_arrow(1), ArrowType::UInt32(4294967295));
+        assert_eq!(
+            to_arrow(2),
+            ArrowType::BigDecimal(BigDecimal::from_str("12345.67").unwrap())
+        );
+        assert_eq!(
+            to_arrow(3),
+            ArrowType::Utf8("123e4567-e89b-12d3-a456-426614174000".into())
+        );
+        assert_eq!(to_arrow(4), ArrowType::Utf8("192.168.0.1".into()));
+        assert_eq!(to_arrow(5), ArrowType::Utf8("2001:db8::1".into()));
+        assert_eq!(to_arrow(6), ArrowType::Utf8(r#"{"key": "value"}"#.into()));
+        assert_eq!(
+            to_arrow(7),
+            ArrowType::Timestamp(NaiveDateTime::from_str("2024-05-28T12:34:56.123456").unwrap())
+        );
+
+        let id = connection.session_id(&mut pool).await.unwrap();
+        assert!(id.is_some_and(|id| id.parse::<u64>().is_ok()));
+    }
+
     #[tokio::test]
     async fn test_mysql_schema() {
         let connection = new_mysql_connection();
         let mut pool = connection.connect().await.unwrap();
-        let schema = connection.schema(&mut pool).await.unwrap();
+        let schema = connection.schema(&mut pool, &[]).await.unwrap();
 
         // for (table_name, table) in &_schema.tables {
         //     println!("Table: {}", table_name);
@@ -351,160 +584,54 @@ mod tests {
         // }
 
         let expected = vec![
-            SchemaColumn {
-                name: "id".into(),
-                r#type: "int".into(),
-                is_nullable: false,
-            },
-            SchemaColumn {
-                name: "tinyint_col".into(),
-                r#type: "tinyint".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "smallint_col".into(),
-                r#type: "smallint".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "mediumint_col".into(),
-                r#type: "mediumint".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "int_col".into(),
-                r#type: "int".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "bigint_col".into(),
-                r#type: "bigint".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "decimal_col".into(),
-                r#type: "decimal".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "float_col".into(),
-                r#type: "float".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "double_col".into(),
-                r#type: "double".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "bit_col".into(),
-                r#type: "bit".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "char_col".into(),
-                r#type: "char".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "varchar_col".into(),
-                r#type: "varchar".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "binary_col".into(),
-                r#type: "binary".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "varbinary_col".into(),
-                r#type: "varbinary".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "tinyblob_col".into(),
-                r#type: "tinyblob".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "blob_col".into(),
-                r#type: "blob".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "mediumblob_col".into(),
-                r#type: "mediumblob".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "longblob_col".into(),
-                r#type: "longblob".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "tinytext_col".into(),
-                r#type: "tinytext".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "text_col".into(),
-                r#type: "text".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "mediumtext_col".into(),
-                r#type: "mediumtext".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "longtext_col".into(),
-                r#type: "longtext".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "enum_col".into(),
-                r#type: "enum".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "set_col".into(),
-                r#type: "set".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "date_col".into(),
-                r#type: "date".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "datetime_col".into(),
-                r#type: "datetime".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "timestamp_col".into(),
-                r#type: "timestamp".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "time_col".into(),
-                r#type: "time".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "year_col".into(),
-                r#type: "year".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "json_col".into(),
-                r#type: "json".into(),
-                is_nullable: true,
-            },
+            SchemaColumn::new("id", "int", false),
+            SchemaColumn::new("tinyint_col", "tinyint", true),
+            SchemaColumn::new("smallint_col", "smallint", true),
+            SchemaColumn::new("mediumint_col", "mediumint", true),
+            SchemaColumn::new("int_col", "int", true),
+            SchemaColumn::new("bigint_col", "bigint", true),
+            SchemaColumn::new("decimal_col", "decimal", true),
+            SchemaColumn::new("float_col", "float", true),
+            SchemaColumn::new("double_col", "double", true),
+            SchemaColumn::new("bit_col", "bit", true),
+            SchemaColumn::new("char_col", "char", true),
+            SchemaColumn::new("varchar_col", "varchar", true),
+            SchemaColumn::new("binary_col", "binary", true),
+            SchemaColumn::new("varbinary_col", "varbinary", true),
+            SchemaColumn::new("tinyblob_col", "tinyblob", true),
+            SchemaColumn::new("blob_col", "blob", true),
+            SchemaColumn::new("mediumblob_col", "mediumblob", true),
+            SchemaColumn::new("longblob_col", "longblob", true),
+            SchemaColumn::new("tinytext_col", "tinytext", true),
+            SchemaColumn::new("text_col", "text", true),
+            SchemaColumn::new("mediumtext_col", "mediumtext", true),
+            SchemaColumn::new("longtext_col", "longtext", true),
+            SchemaColumn::new("enum_col", "enum", true),
+            SchemaColumn::new("set_col", "set", true),
+            SchemaColumn::new("date_col", "date", true),
+            SchemaColumn::new("datetime_col", "datetime", true),
+            SchemaColumn::new("timestamp_col", "timestamp", true),
+            SchemaColumn::new("time_col", "time", true),
+            SchemaColumn::new("year_col", "year", true),
+            SchemaColumn::new("json_col", "json", true),
         ];
 
-        let columns = &schema.tables.get("all_native_data_types").unwrap().columns;
+        let table = schema
+            .tables
+            .get("mysql-connection.all_native_data_types")
+            .unwrap();
 
-        assert_eq!(columns, &expected);
+        assert_eq!(&table.columns, &expected);
+        assert_eq!(table.kind, TableKind::Table);
+        assert_eq!(table.primary_key, vec!["id"]);
+        assert_eq!(
+            table.indexes,
+            vec![SchemaIndex {
+                name: "PRIMARY".into(),
+                columns: vec!["id".into()],
+                is_unique: true,
+                is_primary: true,
+            }]
+        );
     }
 }
@@ -0,0 +1,92 @@
+//! Query Parameters
+//!
+//! Typed values that are bound to a query's placeholders, rather than
+//! spliced into the SQL string.  Quadratic's handlebars (eg, `{{A1}}`)
+//! compile into these.
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{Database, Encode, Type, query::Query};
+
+/// A typed value bound to a query parameter.  This must match
+/// `SqlParameter` in quadratic-core.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum SqlParameter {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+}
+
+/// Bind parameters to a SQLx query, in order
+pub(crate) fn bind_sqlx<'q, DB>(
+    mut query: Query<'q, DB, DB::Arguments<'q>>,
+    params: &'q [SqlParameter],
+) -> Query<'q, DB, DB::Arguments<'q>>
+where
+    DB: Database,
+    bool: Encode<'q, DB> + Type<DB>,
+    i64: Encode<'q, DB> + Type<DB>,
+    f64: Encode<'q, DB> + Type<DB>,
+    &'q str: Encode<'q, DB> + Type<DB>,
+    Option<&'q str>: Encode<'q, DB> + Type<DB>,
+    NaiveDate: Encode<'q, DB> + Type<DB>,
+    NaiveTime: Encode<'q, DB> + Type<DB>,
+    NaiveDateTime: Encode<'q, DB> + Type<DB>,
+{
+    for param in params {
+        query = match param {
+            SqlParameter::Null => query.bind(None::<&str>),
+            SqlParameter::Boolean(value) => query.bind(*value),
+            SqlParameter::Integer(value) => query.bind(*value),
+            SqlParameter::Float(value) => query.bind(*value),
+            SqlParameter::Text(value) => query.bind(value.as_str()),
+            SqlParameter::Date(value) => query.bind(*value),
+            SqlParameter::Time(value) => query.bind(*value),
+            SqlParameter::DateTime(value) => query.bind(*value),
+        };
+    }
+
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_parameters() {
+        let json = r#"[
+            {"type":"Null"},
+            {"type":"Boolean","value":true},
+            {"type":"Integer","value":1},
+            {"type":"Float","value":2.5},
+            {"type":"Text","value":"O'Brien"},
+            {"type":"Date","value":"2024-12-31"},
+            {"type":"Time","value":"13:45:00"},
+            {"type":"DateTime","value":"2024-12-31T13:45:00"}
+        ]"#;
+        let params = serde_json::from_str::<Vec<SqlParameter>>(json).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let time = NaiveTime::from_hms_opt(13, 45, 0).unwrap();
+
+        assert_eq!(
+            params,
+            vec![
+                SqlParameter::Null,
+                SqlParameter::Boolean(true),
+                SqlParameter::Integer(1),
+                SqlParameter::Float(2.5),
+                SqlParameter::Text("O'Brien".into()),
+                SqlParameter::Date(date),
+                SqlParameter::Time(time),
+                SqlParameter::DateTime(date.and_time(time)),
+            ]
+        );
+    }
+}
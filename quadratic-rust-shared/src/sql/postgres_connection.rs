@@ -2,14 +2,12 @@
 //!
 //! Functions to interact with PostgreSQL
 
-use std::collections::BTreeMap;
-
 use arrow::datatypes::Date32Type;
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
 use bytes::Bytes;
 use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
-use futures_util::StreamExt;
+use futures_util::{StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
@@ -22,8 +20,14 @@ use sqlx::{
 use crate::convert_pg_type;
 use crate::error::{Result, SharedError};
 use crate::sql::error::Sql as SqlError;
-use crate::sql::schema::{DatabaseSchema, SchemaColumn, SchemaTable};
-use crate::sql::{ArrowType, Connection};
+use crate::sql::parameter::{SqlParameter, bind_sqlx};
+use crate::sql::schema::{DatabaseSchema, SchemaColumn, TableKind, non_empty, optional};
+use crate::sql::ssh::SshConfig;
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_rows};
+use crate::sql::tls::TlsConfig;
+use crate::sql::{ArrowType, Connection, parse_port};
+
+const DEFAULT_PORT: u16 = 5432;
 
 /// PostgreSQL connection
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +37,16 @@ pub struct PostgresConnection {
     pub host: String,
     pub port: Option<String>,
     pub database: String,
+    pub tls: Option<TlsConfig>,
+    pub ssh: Option<SshConfig>,
+
+    // seconds to cache query results for, overriding the service's default
+    #[serde(default)]
+    pub cache_ttl_s: Option<u64>,
+
+    // reject queries that write data or change the schema
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 impl PostgresConnection {
@@ -43,6 +57,8 @@ impl PostgresConnection {
         host: String,
         port: Option<String>,
         database: String,
+        tls: Option<TlsConfig>,
+        ssh: Option<SshConfig>,
     ) -> PostgresConnection {
         PostgresConnection {
             username,
@@ -50,6 +66,10 @@ impl PostgresConnection {
             host,
             port,
             database,
+            tls,
+            ssh,
+            cache_ttl_s: None,
+            read_only: false,
         }
     }
 
@@ -62,6 +82,82 @@ impl PostgresConnection {
 
         Ok(rows)
     }
+
+    /// Fetch the rows of a query, stopping once they're over `max_bytes`.
+    /// Returns the rows and whether they were over the limit.
+    async fn fetch_rows(
+        pool: &mut PgConnection,
+        sql: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Vec<PgRow>, bool)> {
+        let mut rows = vec![];
+        let mut over_the_limit = false;
+
+        if let Some(max_bytes) = max_bytes {
+            let mut bytes = 0;
+            let mut stream = bind_sqlx(sqlx::query(sql), params).fetch(pool);
+
+            while let Some(row) = stream.next().await {
+                let row = row.map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+                bytes += row.len() as u64;
+
+                if bytes > max_bytes {
+                    over_the_limit = true;
+                    break;
+                }
+
+                rows.push(row);
+            }
+            tracing::info!("Query executed with {bytes} bytes");
+        } else {
+            rows = bind_sqlx(sqlx::query(sql), params)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+        }
+
+        Ok((rows, over_the_limit))
+    }
+
+    /// Start a read-only transaction.  Unlike the session's default, which a
+    /// query can turn off (eg, with `set_config`), a transaction can't become
+    /// read-write once it has run a query.
+    async fn begin_read_only(pool: &mut PgConnection) -> Result<()> {
+        sqlx::raw_sql("BEGIN READ ONLY")
+            .execute(pool)
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    /// End a read-only transaction, committing it if the query succeeded
+    async fn end_read_only(pool: &mut PgConnection, commit: bool) -> Result<()> {
+        let sql = match commit {
+            true => "COMMIT",
+            false => "ROLLBACK",
+        };
+        sqlx::raw_sql(sql)
+            .execute(pool)
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+
+        Ok(())
+    }
+
+    /// Query the catalog, binding the schemas to filter by as `$1`
+    async fn query_schema(
+        pool: &mut PgConnection,
+        sql: &str,
+        schemas: &[String],
+    ) -> Result<Vec<PgRow>> {
+        sqlx::query(sql)
+            .bind(schemas.to_vec())
+            .fetch_all(pool)
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))
+    }
 }
 
 #[async_trait]
@@ -70,6 +166,16 @@ impl Connection for PostgresConnection {
     type Row = PgRow;
     type Column = PgColumn;
 
+    const KIND: &'static str = "postgres";
+
+    fn cache_ttl_s(&self) -> Option<u64> {
+        self.cache_ttl_s
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Get the length of a row
     fn row_len(row: &Self::Row) -> usize {
         row.len()
@@ -85,10 +191,10 @@ impl Connection for PostgresConnection {
         col.name()
     }
 
-    /// Connect to a PostgreSQL database
+    /// Connect to a PostgreSQL database, through the SSH tunnel if there is
+    /// one
     async fn connect(&self) -> Result<Self::Conn> {
         let mut options = PgConnectOptions::new();
-        options = options.host(&self.host);
         options = options.database(&self.database);
 
         if let Some(ref username) = self.username {
@@ -99,12 +205,48 @@ impl Connection for PostgresConnection {
             options = options.password(password);
         }
 
-        if let Some(ref port) = self.port {
-            options = options.port(port.parse::<u16>().map_err(|_| {
-                SharedError::Sql(SqlError::Connect(
-                    "Could not parse port into a number".into(),
-                ))
-            })?);
+        let port = parse_port(&self.port)?;
+
+        match &self.ssh {
+            Some(ssh) => {
+                let local_addr = ssh.tunnel(&self.host, port.unwrap_or(DEFAULT_PORT)).await?;
+                options = options
+                    .host(&local_addr.ip().to_string())
+                    .port(local_addr.port());
+            }
+            None => {
+                options = options.host(&self.host);
+
+                if let Some(port) = port {
+                    options = options.port(port);
+                }
+            }
+        }
+
+        if let Some(ref tls) = self.tls {
+            let mode = match self.ssh {
+                Some(_) => tls.tunnelled_mode(),
+                None => tls.mode,
+            };
+            options = options.ssl_mode(mode.into());
+
+            if let Some(ref ca_certificate) = tls.ca_certificate {
+                options = options.ssl_root_cert_from_pem(ca_certificate.as_bytes().to_vec());
+            }
+
+            if let Some(ref client_certificate) = tls.client_certificate {
+                options = options.ssl_client_cert_from_pem(client_certificate);
+            }
+
+            if let Some(ref client_key) = tls.client_key {
+                options = options.ssl_client_key_from_pem(client_key);
+            }
+        }
+
+        // queries also run in read-only transactions, since a query can
+        // change the session's default
+        if self.read_only {
+            options = options.options([("default_transaction_read_only", "on")]);
         }
 
         let pool = options.connect().await.map_err(|e| {
@@ -114,85 +256,204 @@ impl Connection for PostgresConnection {
         Ok(pool)
     }
 
-    /// Query rows from a PostgreSQL database
+    /// Query rows from a PostgreSQL database.  Queries on read-only
+    /// connections run in a read-only transaction.
     async fn query(
         &self,
         pool: &mut Self::Conn,
         sql: &str,
+        params: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Bytes, bool, usize)> {
-        let mut rows = vec![];
-        let mut over_the_limit = false;
-
-        if let Some(max_bytes) = max_bytes {
-            let mut bytes = 0;
-            let mut stream = sqlx::query(sql).fetch(pool);
-
-            while let Some(row) = stream.next().await {
-                let row = row.map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
-                bytes += row.len() as u64;
+        if self.read_only {
+            PostgresConnection::begin_read_only(pool).await?;
+        }
 
-                if bytes > max_bytes {
-                    over_the_limit = true;
-                    break;
-                }
+        let rows = PostgresConnection::fetch_rows(pool, sql, params, max_bytes).await;
 
-                rows.push(row);
+        if self.read_only {
+            let ended = PostgresConnection::end_read_only(pool, rows.is_ok()).await;
+            if rows.is_ok() {
+                ended?;
             }
-            tracing::info!("Query executed with {bytes} bytes");
-        } else {
-            rows = sqlx::query(sql)
-                .fetch_all(pool)
-                .await
-                .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
         }
 
+        let (rows, over_the_limit) = rows?;
         let (bytes, num_records) = Self::to_parquet(rows)?;
 
         Ok((bytes, over_the_limit, num_records))
     }
 
-    /// Get the schema of a PostgreSQL database
-    async fn schema(&self, pool: &mut Self::Conn) -> Result<DatabaseSchema> {
-        let database = self.database.to_owned();
-        let sql = format!("
-            select c.table_catalog as database, c.table_schema as schema, c.table_name as table, c.column_name, c.udt_name as column_type, c.is_nullable
-            from information_schema.tables as t inner join information_schema.columns as c on t.table_name = c.table_name
-            where t.table_type = 'BASE TABLE' 
-                and c.table_schema not in 
-                    ('pg_catalog', 'information_schema')
-                    and c.table_catalog = '{database}'
-            order by c.table_name, c.ordinal_position, c.column_name");
-
-        let rows = Self::query_all(pool, &sql).await?;
-
-        let mut schema = DatabaseSchema {
-            database,
-            tables: BTreeMap::new(),
+    /// Stream a page of rows from a PostgreSQL database.  Queries on
+    /// read-only connections run in a read-only transaction.
+    async fn query_stream(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        if self.read_only {
+            PostgresConnection::begin_read_only(pool).await?;
+        }
+
+        let summary = {
+            let rows = bind_sqlx(sqlx::query(sql), params)
+                .fetch(&mut *pool)
+                .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())));
+
+            stream_rows::<Self, _>(rows, page, sender, max_bytes).await
         };
 
-        for row in rows.into_iter() {
-            let table_name = row.get::<String, usize>(2);
-
-            schema
-                .tables
-                // get or insert the table
-                .entry(table_name.to_owned())
-                .or_insert_with(|| SchemaTable {
-                    name: table_name,
-                    schema: row.get::<String, usize>(1),
-                    columns: vec![],
-                })
-                .columns
-                // add the column to the table
-                .push(SchemaColumn {
-                    name: row.get::<String, usize>(3),
-                    r#type: row.get::<String, usize>(4),
-                    is_nullable: matches!(
-                        row.get::<String, usize>(5).to_lowercase().as_str(),
-                        "yes"
-                    ),
-                });
+        if self.read_only {
+            let ended = PostgresConnection::end_read_only(pool, summary.is_ok()).await;
+            if summary.is_ok() {
+                ended?;
+            }
+        }
+
+        summary
+    }
+
+    /// Get the backend's process id
+    async fn session_id(&self, pool: &mut Self::Conn) -> Result<Option<String>> {
+        let pid = sqlx::query_scalar::<_, i32>("select pg_backend_pid()")
+            .fetch_one(pool)
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+
+        Ok(Some(pid.to_string()))
+    }
+
+    /// Cancel a backend's query with `pg_cancel_backend`
+    async fn cancel(&self, session_id: &str) -> Result<()> {
+        let cancel_error = |e: String| SharedError::Sql(SqlError::Cancel(e));
+        let pid = session_id
+            .parse::<i32>()
+            .map_err(|e| cancel_error(e.to_string()))?;
+        let mut pool = self.connect().await?;
+
+        sqlx::query("select pg_cancel_backend($1)")
+            .bind(pid)
+            .execute(&mut pool)
+            .await
+            .map_err(|e| cancel_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Get the schema of a PostgreSQL database
+    async fn schema(&self, pool: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema> {
+        let mut schema = DatabaseSchema::new(self.database.to_owned());
+
+        // tables, views and their columns
+        let sql = "
+            select n.nspname, c.relname, c.relkind::text, a.attname, t.typname, not a.attnotnull,
+                pg_get_expr(d.adbin, d.adrelid), col_description(c.oid, a.attnum),
+                obj_description(c.oid, 'pg_class'), c.reltuples::float8
+            from pg_catalog.pg_class as c
+                inner join pg_catalog.pg_namespace as n on n.oid = c.relnamespace
+                inner join pg_catalog.pg_attribute as a on a.attrelid = c.oid and a.attnum > 0 and not a.attisdropped
+                inner join pg_catalog.pg_type as t on t.oid = a.atttypid
+                left join pg_catalog.pg_attrdef as d on d.adrelid = c.oid and d.adnum = a.attnum
+            where c.relkind in ('r', 'p', 'v', 'm', 'f')
+                and not c.relispartition
+                and n.nspname not in ('pg_catalog', 'information_schema')
+                and n.nspname not like 'pg_toast%'
+                and (cardinality($1::text[]) = 0 or n.nspname = any($1))
+            order by n.nspname, c.relname, a.attnum";
+
+        for row in Self::query_schema(pool, sql, schemas).await? {
+            let table =
+                schema.table_mut(&row.get::<String, usize>(0), &row.get::<String, usize>(1));
+            let is_view = matches!(row.get::<String, usize>(2).as_str(), "v" | "m");
+
+            if table.columns.is_empty() {
+                table.kind = if is_view {
+                    TableKind::View
+                } else {
+                    TableKind::Table
+                };
+                table.comment = non_empty(row.get::<Option<String>, usize>(8));
+
+                // -1 means that the table hasn't been analyzed yet
+                let row_count = row.get::<Option<f64>, usize>(9).unwrap_or(-1.0);
+                table.row_count = (!is_view && row_count >= 0.0).then_some(row_count as u64);
+            }
+
+            table.columns.push(SchemaColumn {
+                name: row.get::<String, usize>(3),
+                r#type: row.get::<String, usize>(4),
+                is_nullable: row.get::<bool, usize>(5),
+                default: row.get::<Option<String>, usize>(6),
+                comment: non_empty(row.get::<Option<String>, usize>(7)),
+            });
+        }
+
+        // primary and foreign keys, one row per column
+        let sql = "
+            select n.nspname, c.relname, con.conname, con.contype::text, a.attname,
+                fn.nspname, fc.relname, fa.attname
+            from pg_catalog.pg_constraint as con
+                inner join pg_catalog.pg_class as c on c.oid = con.conrelid
+                inner join pg_catalog.pg_namespace as n on n.oid = c.relnamespace
+                cross join lateral unnest(con.conkey, con.confkey) with ordinality as k(attnum, fattnum, ordinal)
+                inner join pg_catalog.pg_attribute as a on a.attrelid = con.conrelid and a.attnum = k.attnum
+                left join pg_catalog.pg_class as fc on fc.oid = con.confrelid
+                left join pg_catalog.pg_namespace as fn on fn.oid = fc.relnamespace
+                left join pg_catalog.pg_attribute as fa on fa.attrelid = con.confrelid and fa.attnum = k.fattnum
+            where con.contype in ('p', 'f')
+                and n.nspname not in ('pg_catalog', 'information_schema')
+                and (cardinality($1::text[]) = 0 or n.nspname = any($1))
+            order by n.nspname, c.relname, con.conname, k.ordinal";
+
+        for row in optional("keys", Self::query_schema(pool, sql, schemas).await) {
+            let Some(table) = schema
+                .existing_table_mut(&row.get::<String, usize>(0), &row.get::<String, usize>(1))
+            else {
+                continue;
+            };
+
+            let column = row.get::<String, usize>(4);
+
+            match row.get::<String, usize>(3).as_str() {
+                "p" => table.primary_key.push(column),
+                _ => table.add_foreign_key_column(
+                    &row.get::<String, usize>(2),
+                    column,
+                    row.get::<String, usize>(5),
+                    row.get::<String, usize>(6),
+                    row.get::<String, usize>(7),
+                ),
+            }
+        }
+
+        // indexes, one row per column, expressions are skipped
+        let sql = "
+            select n.nspname, c.relname, i.relname, a.attname, x.indisunique, x.indisprimary
+            from pg_catalog.pg_index as x
+                inner join pg_catalog.pg_class as c on c.oid = x.indrelid
+                inner join pg_catalog.pg_class as i on i.oid = x.indexrelid
+                inner join pg_catalog.pg_namespace as n on n.oid = c.relnamespace
+                cross join lateral unnest(x.indkey::int2[]) with ordinality as k(attnum, ordinal)
+                inner join pg_catalog.pg_attribute as a on a.attrelid = c.oid and a.attnum = k.attnum
+            where n.nspname not in ('pg_catalog', 'information_schema')
+                and (cardinality($1::text[]) = 0 or n.nspname = any($1))
+            order by n.nspname, c.relname, i.relname, k.ordinal";
+
+        for row in optional("indexes", Self::query_schema(pool, sql, schemas).await) {
+            if let Some(table) = schema
+                .existing_table_mut(&row.get::<String, usize>(0), &row.get::<String, usize>(1))
+            {
+                table.add_index_column(
+                    &row.get::<String, usize>(2),
+                    row.get::<String, usize>(3),
+                    row.get::<bool, usize>(4),
+                    row.get::<bool, usize>(5),
+                );
+            }
         }
 
         Ok(schema)
@@ -282,6 +543,8 @@ macro_rules! convert_pg_type {
 mod tests {
 
     use super::*;
+    use crate::sql::ssh::tests::new_ssh_config;
+    use crate::sql::tls::TlsMode;
     // use std::io::Read;
 
     fn new_postgres_connection() -> PostgresConnection {
@@ -291,6 +554,8 @@ mod tests {
             "127.0.0.1".into(),
             Some("5433".into()),
             "postgres-connection".into(),
+            None,
+            None,
         )
     }
 
@@ -314,11 +579,115 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_postgres_schema() {
+    async fn test_postgres_connection_tls() {
+        // see the postgres-connection-tls service in docker-compose.yml
+        let mut connection = new_postgres_connection();
+        connection.port = Some("5434".into());
+        connection.tls = Some(TlsConfig {
+            mode: TlsMode::Require,
+            ..Default::default()
+        });
+
+        let mut pool = connection.connect().await.unwrap();
+        let sql = "select ssl from pg_stat_ssl where pid = pg_backend_pid()";
+        let rows = PostgresConnection::query_all(&mut pool, sql).await.unwrap();
+        assert!(rows[0].get::<bool, usize>(0));
+
+        // the server's certificate is self-signed
+        connection.tls = Some(TlsConfig {
+            mode: TlsMode::VerifyFull,
+            ..Default::default()
+        });
+        assert!(connection.connect().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_postgres_connection_ssh() {
+        let mut connection = new_postgres_connection();
+
+        // postgres-connection, as seen from the ssh-connection container
+        connection.host = "postgres-connection".into();
+        connection.port = Some("5432".into());
+        connection.ssh = Some(new_ssh_config());
+
+        let mut pool = connection.connect().await.unwrap();
+        let rows = PostgresConnection::query_all(&mut pool, "select 1")
+            .await
+            .unwrap();
+        assert_eq!(rows[0].get::<i32, usize>(0), 1);
+    }
+
+    #[tokio::test]
+    async fn test_postgres_read_only() {
+        let mut connection = new_postgres_connection();
+        connection.read_only = true;
+        let mut pool = connection.connect().await.unwrap();
+
+        // turning off the session's default doesn't make queries read-write
+        let sql = "select set_config('default_transaction_read_only', 'off', false)";
+        connection.query(&mut pool, sql, &[], None).await.unwrap();
+
+        let sql = "create temporary table read_only_test (id integer)";
+        let error = connection
+            .query(&mut pool, sql, &[], None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("read-only transaction"));
+
+        let sql = "select set_config('transaction_read_only', 'off', true)";
+        assert!(connection.query(&mut pool, sql, &[], None).await.is_err());
+
+        // the connection is still usable after a failed query
+        let (_, _, num_records) = connection
+            .query(&mut pool, "select 1", &[], None)
+            .await
+            .unwrap();
+        assert_eq!(num_records, 1);
+    }
+
+    #[tokio::test]
+    async fn test_postgres_cancel() {
         let connection = new_postgres_connection();
         let mut pool = connection.connect().await.unwrap();
-        let _schema = connection.schema(&mut pool).await.unwrap();
+        let session_id = connection.session_id(&mut pool).await.unwrap().unwrap();
 
-        // println!("{:?}", schema);
+        let sleep = PostgresConnection::query_all(&mut pool, "select pg_sleep(30)");
+        let cancel = async {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            connection.cancel(&session_id).await
+        };
+        let (sleep, cancel) = tokio::join!(sleep, cancel);
+
+        assert!(cancel.is_ok());
+        assert!(
+            sleep
+                .unwrap_err()
+                .to_string()
+                .contains("canceling statement")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_postgres_schema() {
+        let connection = new_postgres_connection();
+        let mut pool = connection.connect().await.unwrap();
+        let schema = connection.schema(&mut pool, &[]).await.unwrap();
+        let table = &schema.tables["public.all_native_data_types"];
+
+        assert_eq!(table.kind, TableKind::Table);
+        assert_eq!(table.primary_key, vec!["id"]);
+        assert_eq!(table.indexes[0].name, "all_native_data_types_pkey");
+        assert!(table.indexes[0].is_primary);
+        assert_eq!(
+            table.columns[0].default,
+            Some("nextval('all_native_data_types_id_seq'::regclass)".into())
+        );
+
+        // restricting to another schema skips the table
+        let schema = connection
+            .schema(&mut pool, &["missing".into()])
+            .await
+            .unwrap();
+        assert!(schema.tables.is_empty());
     }
 }
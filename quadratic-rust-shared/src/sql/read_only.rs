@@ -0,0 +1,245 @@
+//! Read-only Queries
+//!
+//! Classify a query's statements, so that queries on read-only connections
+//! that write data or change the schema are rejected before they're sent to
+//! the database.  Databases that have read-only sessions enforce it there
+//! too, but not all do, and their errors are less clear.
+
+use crate::SharedError;
+use crate::error::Result;
+use crate::sql::error::Sql as SqlError;
+
+/// Statements that only read data, by their first keyword
+const READ_STATEMENTS: &[&str] = &[
+    "SELECT", "WITH", "SHOW", "DESCRIBE", "DESC", "EXPLAIN", "VALUES", "TABLE",
+];
+
+/// Keywords that write data within a read statement, eg, a data-modifying
+/// CTE or `SELECT ... INTO`
+const WRITE_KEYWORDS: &[&str] = &["INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "INTO"];
+
+/// Lexical rules that only some databases have
+const QUIRKS: u32 = 5;
+
+/// How a database reads strings and comments
+#[derive(Debug, Clone, Copy)]
+struct Dialect {
+    // eg, 'it\'s' in MySQL
+    backslash_escapes: bool,
+    // eg, $$it's$$ in PostgreSQL
+    dollar_quotes: bool,
+    // eg, # comment in MySQL
+    hash_comments: bool,
+    // eg, // comment in Snowflake
+    slash_comments: bool,
+    // MySQL only starts a comment with "-- ", so "--1" is minus minus one
+    spaced_dash_comments: bool,
+}
+
+impl Dialect {
+    /// Every combination of the quirks
+    fn all() -> impl Iterator<Item = Dialect> {
+        (0..1 << QUIRKS).map(|bits: u32| Dialect {
+            backslash_escapes: bits & 1 != 0,
+            dollar_quotes: bits & 2 != 0,
+            hash_comments: bits & 4 != 0,
+            slash_comments: bits & 8 != 0,
+            spaced_dash_comments: bits & 16 != 0,
+        })
+    }
+}
+
+/// Check that a query only reads data.
+///
+/// The query is read as every database would, so that a statement can't be
+/// hidden from the check in something that only some databases read as a
+/// string or a comment.
+pub fn check_read_only(sql: &str) -> Result<()> {
+    for dialect in Dialect::all() {
+        for statement in statements(sql, dialect) {
+            let Some(first) = statement.first() else {
+                continue;
+            };
+
+            if !READ_STATEMENTS.contains(&first.as_str()) {
+                return Err(read_only_error(first));
+            }
+
+            if let Some(keyword) = statement
+                .iter()
+                .find(|word| WRITE_KEYWORDS.contains(&word.as_str()))
+            {
+                return Err(read_only_error(keyword));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_only_error(keyword: &str) -> SharedError {
+    SharedError::Sql(SqlError::ReadOnly(format!(
+        "{keyword} is not allowed, only queries that read data can run"
+    )))
+}
+
+/// Split a query into statements of uppercase words (keywords and
+/// identifiers), skipping strings, quoted identifiers and comments
+fn statements(sql: &str, dialect: Dialect) -> Vec<Vec<String>> {
+    let chars = sql.chars().collect::<Vec<_>>();
+    let mut statements = vec![vec![]];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        let after_next = chars.get(i + 2).copied();
+
+        i = match chars[i] {
+            ';' => {
+                statements.push(vec![]);
+                i + 1
+            }
+            '-' if next == Some('-')
+                && (!dialect.spaced_dash_comments
+                    || after_next.is_none_or(char::is_whitespace)) =>
+            {
+                line_end(&chars, i)
+            }
+            '#' if dialect.hash_comments => line_end(&chars, i),
+            '/' if next == Some('/') && dialect.slash_comments => line_end(&chars, i),
+            // MySQL runs the contents of `/*! ... */`, so they're read
+            '/' if next == Some('*') && after_next != Some('!') => {
+                find(&chars, i + 2, &['*', '/']).map_or(chars.len(), |end| end + 2)
+            }
+            quote @ ('\'' | '"' | '`') => quoted_end(&chars, i, quote, dialect),
+            '$' if dialect.dollar_quotes => dollar_quoted_end(&chars, i),
+            c if c.is_alphabetic() || c == '_' => {
+                let end = word_end(&chars, i);
+                let word = chars[i..end].iter().collect::<String>().to_uppercase();
+
+                if let Some(statement) = statements.last_mut() {
+                    statement.push(word);
+                }
+
+                end
+            }
+            _ => i + 1,
+        };
+    }
+
+    statements
+}
+
+fn line_end(chars: &[char], start: usize) -> usize {
+    find(chars, start, &['\n']).map_or(chars.len(), |end| end + 1)
+}
+
+fn word_end(chars: &[char], start: usize) -> usize {
+    find_by(chars, start, |c| {
+        !(c.is_alphanumeric() || c == '_' || c == '$')
+    })
+    .unwrap_or(chars.len())
+}
+
+/// The end of a string or quoted identifier.  A doubled quote ends it and
+/// starts another, which skips the same characters.
+fn quoted_end(chars: &[char], start: usize, quote: char, dialect: Dialect) -> usize {
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if dialect.backslash_escapes => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    chars.len()
+}
+
+/// The end of a dollar-quoted string, eg, `$tag$...$tag$`.  A `$` that
+/// doesn't start one (eg, a `$1` placeholder) is skipped.
+fn dollar_quoted_end(chars: &[char], start: usize) -> usize {
+    let tag_end = find_by(chars, start + 1, |c| !(c.is_alphanumeric() || c == '_'));
+    let starts_with_digit = chars.get(start + 1).is_some_and(char::is_ascii_digit);
+
+    match tag_end {
+        Some(tag_end) if chars[tag_end] == '$' && !starts_with_digit => {
+            let delimiter = &chars[start..=tag_end];
+            find(chars, tag_end + 1, delimiter).map_or(chars.len(), |end| end + delimiter.len())
+        }
+        _ => start + 1,
+    }
+}
+
+/// The index of the next occurrence of `pattern`
+fn find(chars: &[char], start: usize, pattern: &[char]) -> Option<usize> {
+    (start..chars.len()).find(|&i| chars[i..].starts_with(pattern))
+}
+
+/// The index of the next character that matches
+fn find_by(chars: &[char], start: usize, matches: impl Fn(char) -> bool) -> Option<usize> {
+    (start..chars.len()).find(|&i| matches(chars[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_queries_that_read() {
+        for sql in [
+            "select * from t where note = 'delete me';",
+            "-- update this later\nSELECT 1",
+            "/* insert here */ select \"update\", `delete` from t",
+            "with x as (select 1) select * from x",
+            "(select 1) union (select 2)",
+            "show tables; describe t; explain select 1",
+            "select 'it''s', $$quoted$$, $1 from t",
+            "",
+        ] {
+            assert_eq!(check_read_only(sql), Ok(()), "{sql}");
+        }
+    }
+
+    #[test]
+    fn rejects_queries_that_write() {
+        for sql in [
+            "drop table t",
+            "UPDATE t SET a = 1",
+            "select 1; delete from t",
+            "with d as (delete from t returning *) select * from d",
+            "select * into t2 from t",
+            "set default_transaction_read_only = off",
+            "select 1 /*! ; delete from t */",
+        ] {
+            assert!(check_read_only(sql).is_err(), "{sql}");
+        }
+    }
+
+    #[test]
+    fn rejects_statements_hidden_by_other_dialects() {
+        for sql in [
+            // MySQL's escaped quote ends the string in PostgreSQL
+            "select 'a\\''; delete from t; -- '",
+            // PostgreSQL's dollar quotes are an identifier in MySQL
+            "select $$ , 1; delete from t; $$",
+            "select $$'$$; delete from t; --'",
+            // comments that only some databases have
+            "select 1 # '\n; delete from t; -- '",
+            "select 1 // '\n; delete from t; -- '",
+            "select 1 --1; delete from t",
+            "select 1 --'\n; delete from t; --'",
+        ] {
+            assert!(check_read_only(sql).is_err(), "{sql}");
+        }
+    }
+
+    #[test]
+    fn names_the_rejected_keyword() {
+        assert_eq!(
+            check_read_only("truncate t"),
+            Err(read_only_error("TRUNCATE"))
+        );
+    }
+}
@@ -5,25 +5,205 @@
 use serde::Serialize;
 use std::collections::BTreeMap;
 
+use crate::error::Result;
+
 /// A column in a database
 #[derive(Debug, Serialize, PartialEq)]
 pub struct SchemaColumn {
     pub name: String,
     pub r#type: String,
     pub is_nullable: bool,
+    pub default: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl SchemaColumn {
+    pub fn new(name: impl Into<String>, r#type: impl Into<String>, is_nullable: bool) -> Self {
+        SchemaColumn {
+            name: name.into(),
+            r#type: r#type.into(),
+            is_nullable,
+            default: None,
+            comment: None,
+        }
+    }
+}
+
+/// Whether a table stores rows or is a (materialized) view
+#[derive(Debug, Default, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TableKind {
+    #[default]
+    Table,
+    View,
+}
+
+/// A foreign key, the columns are in the same order as the referenced columns
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct ForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+/// An index on a table
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct SchemaIndex {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub is_unique: bool,
+    pub is_primary: bool,
 }
 
 /// A table in a database
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Default, Serialize, PartialEq)]
 pub struct SchemaTable {
     pub name: String,
     pub schema: String,
+    pub kind: TableKind,
     pub columns: Vec<SchemaColumn>,
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+    pub indexes: Vec<SchemaIndex>,
+    // estimated by the database, so it can be stale
+    pub row_count: Option<u64>,
+    pub comment: Option<String>,
+}
+
+impl SchemaTable {
+    pub fn new(name: impl Into<String>, schema: impl Into<String>) -> Self {
+        SchemaTable {
+            name: name.into(),
+            schema: schema.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a column to the foreign key `name`, creating it if needed.
+    /// Columns must be added in key order.
+    pub fn add_foreign_key_column(
+        &mut self,
+        name: &str,
+        column: String,
+        referenced_schema: String,
+        referenced_table: String,
+        referenced_column: String,
+    ) {
+        let index = match self.foreign_keys.iter().position(|fk| fk.name == name) {
+            Some(index) => index,
+            None => {
+                self.foreign_keys.push(ForeignKey {
+                    name: name.to_owned(),
+                    referenced_schema,
+                    referenced_table,
+                    ..Default::default()
+                });
+                self.foreign_keys.len() - 1
+            }
+        };
+
+        self.foreign_keys[index].columns.push(column);
+        self.foreign_keys[index]
+            .referenced_columns
+            .push(referenced_column);
+    }
+
+    /// Add a column to the index `name`, creating it if needed.  Columns must
+    /// be added in index order.
+    pub fn add_index_column(
+        &mut self,
+        name: &str,
+        column: String,
+        is_unique: bool,
+        is_primary: bool,
+    ) {
+        let index = match self.indexes.iter().position(|index| index.name == name) {
+            Some(index) => index,
+            None => {
+                self.indexes.push(SchemaIndex {
+                    name: name.to_owned(),
+                    is_unique,
+                    is_primary,
+                    ..Default::default()
+                });
+                self.indexes.len() - 1
+            }
+        };
+
+        self.indexes[index].columns.push(column);
+    }
 }
 
 /// A database schema
 #[derive(Debug, Serialize, PartialEq)]
 pub struct DatabaseSchema {
     pub database: String,
+    // keyed by `schema.table`, so tables with the same name in different
+    // schemas don't collide
     pub tables: BTreeMap<String, SchemaTable>,
 }
+
+impl DatabaseSchema {
+    pub fn new(database: impl Into<String>) -> Self {
+        DatabaseSchema {
+            database: database.into(),
+            tables: BTreeMap::new(),
+        }
+    }
+
+    pub fn key(schema: &str, table: &str) -> String {
+        format!("{schema}.{table}")
+    }
+
+    /// Get a table, creating it if needed
+    pub fn table_mut(&mut self, schema: &str, table: &str) -> &mut SchemaTable {
+        self.tables
+            .entry(Self::key(schema, table))
+            .or_insert_with(|| SchemaTable::new(table, schema))
+    }
+
+    /// Get a table that's already been added, used for details about tables
+    /// that were found with the columns query
+    pub fn existing_table_mut(&mut self, schema: &str, table: &str) -> Option<&mut SchemaTable> {
+        self.tables.get_mut(&Self::key(schema, table))
+    }
+}
+
+/// Keys, indexes, row counts and comments add to a schema, so a database user
+/// that can't read them still gets the tables and columns
+pub(crate) fn optional<T: Default>(details: &str, result: Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        tracing::warn!("Error reading {details} of the schema: {e}");
+        T::default()
+    })
+}
+
+/// An empty string means that a database didn't set a value
+pub(crate) fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_columns_to_keys_and_indexes() {
+        let mut schema = DatabaseSchema::new("db");
+        let table = schema.table_mut("public", "orders");
+        table.add_foreign_key_column("fk", "a".into(), "public".into(), "t".into(), "x".into());
+        table.add_foreign_key_column("fk", "b".into(), "public".into(), "t".into(), "y".into());
+        table.add_index_column("idx", "a".into(), true, false);
+        table.add_index_column("idx", "b".into(), true, false);
+
+        let table = schema.existing_table_mut("public", "orders").unwrap();
+        assert_eq!(table.foreign_keys.len(), 1);
+        assert_eq!(table.foreign_keys[0].columns, vec!["a", "b"]);
+        assert_eq!(table.foreign_keys[0].referenced_columns, vec!["x", "y"]);
+        assert_eq!(table.indexes[0].columns, vec!["a", "b"]);
+        assert!(schema.existing_table_mut("public", "missing").is_none());
+        assert!(schema.tables.contains_key("public.orders"));
+    }
+}
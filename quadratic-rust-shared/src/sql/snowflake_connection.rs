@@ -2,10 +2,12 @@
 //!
 //! Functions to interact with Snowflake
 
-use arrow::array::ArrayRef;
+use arrow::array::{ArrayRef, RecordBatch};
+use arrow::util::display::array_value_to_string;
 use arrow_array::array::Array;
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{NaiveTime, Timelike};
 use futures_util::stream::StreamExt;
 use parquet::arrow::ArrowWriter;
 use serde::{Deserialize, Serialize};
@@ -18,10 +20,13 @@ use crate::arrow::arrow_type::ArrowType;
 use crate::error::{Result, SharedError};
 use crate::sql::Connection;
 use crate::sql::error::Sql as SqlError;
-use crate::sql::schema::{DatabaseSchema, SchemaColumn, SchemaTable};
+use crate::sql::parameter::SqlParameter;
+use crate::sql::schema::{DatabaseSchema, SchemaColumn, TableKind, non_empty, optional};
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_batches};
 use crate::utils::array::transpose;
 
-/// Snowflake connection
+/// Snowflake connection.  Snowflake is always reached over HTTPS, so unlike
+/// the other connections there are no TLS or SSH options.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SnowflakeConnection {
     pub account_identifier: String,
@@ -31,6 +36,15 @@ pub struct SnowflakeConnection {
     pub database: String,
     pub schema: Option<String>,
     pub role: Option<String>,
+
+    // seconds to cache query results for, overriding the service's default
+    #[serde(default)]
+    pub cache_ttl_s: Option<u64>,
+
+    // reject queries that write data or change the schema.  Snowflake has no
+    // read-only session, so this relies on `check_read_only` alone.
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 impl SnowflakeConnection {
@@ -52,8 +66,180 @@ impl SnowflakeConnection {
             database,
             schema,
             role,
+            cache_ttl_s: None,
+            read_only: false,
         }
     }
+
+    /// Run a query, returning its Arrow record batches, or None when the
+    /// response is over the limit
+    async fn query_batches(
+        &self,
+        _client: &mut SnowflakeApi,
+        sql: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<Option<Vec<RecordBatch>>> {
+        let query_error = |e: String| SharedError::Sql(SqlError::Query(e));
+
+        #[cfg(all(any(test, feature = "test"), not(clippy)))]
+        let (mut _client, _recording) = tests::get_mocked(self, "snowflake-connection").await;
+
+        // parameters are sent as bind variables, so Snowflake parses the
+        // placeholders and the values are never part of the query's text
+        let query_result = match params.is_empty() {
+            true => _client.exec_raw(sql, true).await,
+            false => {
+                _client
+                    .exec_raw_with_bindings(sql, bindings(params)?, true)
+                    .await
+            }
+        }
+        .map_err(|e| query_error(e.to_string()))?;
+
+        #[cfg(all(
+            any(test, feature = "test"),
+            feature = "record-request-mock",
+            not(clippy)
+        ))]
+        record_stop(scenario, _recording).await;
+
+        if let RawQueryResult::Stream(mut bytes_stream) = query_result {
+            let mut chunks = vec![];
+
+            while let Some(bytes) = bytes_stream.next().await {
+                let bytes = bytes.map_err(|e| query_error(e.to_string()))?;
+
+                if let Some(max_bytes) = max_bytes {
+                    if (chunks.len() + bytes.len()) as u64 > max_bytes {
+                        return Ok(None);
+                    }
+                }
+
+                chunks.push(bytes);
+            }
+
+            let bytes = chunks.into_iter().flatten().collect::<Vec<u8>>();
+            let resp = serde_json::from_slice::<ExecResponse>(&bytes)
+                .map_err(|e| query_error(e.to_string()))?;
+            let raw_query_result = _client
+                .parse_arrow_raw_response(resp)
+                .await
+                .map_err(|e| query_error(e.to_string()))?;
+            let query_result = raw_query_result
+                .deserialize_arrow()
+                .map_err(|e| query_error(e.to_string()))?;
+
+            if let QueryResult::Arrow(batches) = query_result {
+                return Ok(Some(batches));
+            }
+        }
+
+        Err(SharedError::Sql(SqlError::Query(
+            "Could not convert to Arrow".to_string(),
+        )))
+    }
+}
+
+/// A bind variable, in the form Snowflake's query requests take them.  Values
+/// are sent as text: dates in milliseconds since the epoch, times in
+/// nanoseconds since midnight and timestamps in nanoseconds since the epoch.
+#[derive(Debug, Serialize, PartialEq)]
+struct Binding {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    value: Option<String>,
+}
+
+impl From<&SqlParameter> for Binding {
+    fn from(param: &SqlParameter) -> Self {
+        let (kind, value) = match param {
+            SqlParameter::Null => ("ANY", None),
+            SqlParameter::Boolean(value) => ("BOOLEAN", Some(value.to_string())),
+            SqlParameter::Integer(value) => ("FIXED", Some(value.to_string())),
+            SqlParameter::Float(value) if value.is_finite() => ("REAL", Some(value.to_string())),
+            SqlParameter::Float(_) => ("REAL", None),
+            SqlParameter::Text(value) => ("TEXT", Some(value.to_owned())),
+            SqlParameter::Date(value) => {
+                let millis = value.and_time(NaiveTime::MIN).and_utc().timestamp_millis();
+                ("DATE", Some(millis.to_string()))
+            }
+            SqlParameter::Time(value) => {
+                let nanos = value.num_seconds_from_midnight() as u64 * 1_000_000_000
+                    + value.nanosecond() as u64;
+                ("TIME", Some(nanos.to_string()))
+            }
+            SqlParameter::DateTime(value) => {
+                let nanos = value.and_utc().timestamp_nanos_opt();
+                ("TIMESTAMP_NTZ", nanos.map(|nanos| nanos.to_string()))
+            }
+        };
+
+        Binding { kind, value }
+    }
+}
+
+/// Bind the parameters to the query's `?` placeholders, which are numbered
+/// from 1 in the order they appear
+fn bindings(params: &[SqlParameter]) -> Result<serde_json::Value> {
+    let bindings = params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| ((index + 1).to_string(), Binding::from(param)))
+        .collect::<BTreeMap<_, _>>();
+
+    Ok(serde_json::to_value(bindings)?)
+}
+
+/// Filter a schema query by schema names, which are quoted as string literals
+fn schema_filter(column: &str, schemas: &[String]) -> String {
+    if schemas.is_empty() {
+        return "".into();
+    }
+
+    let schemas = schemas
+        .iter()
+        .map(|schema| format!("'{}'", schema.replace('\\', "\\\\").replace('\'', "''")))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(" AND {column} IN ({schemas})")
+}
+
+/// Run a query and convert its values to strings, nulls are empty strings.
+/// Rows have a value for every column.
+async fn query_strings(client: &SnowflakeApi, sql: &str) -> Result<Vec<Vec<String>>> {
+    let query_error = |e: String| SharedError::Sql(SqlError::Query(e));
+    let result = client
+        .exec(sql)
+        .await
+        .map_err(|e| query_error(e.to_string()))?;
+    let mut data: Vec<Vec<String>> = vec![];
+
+    match result {
+        QueryResult::Arrow(batches) => {
+            for batch in batches {
+                data.resize(batch.num_columns(), vec![]);
+
+                // data in coming in as batches, so we need to combine them
+                for (col_index, col) in batch.columns().iter().enumerate() {
+                    for row_index in 0..col.len() {
+                        let value = match col.is_null(row_index) {
+                            true => String::new(),
+                            false => array_value_to_string(col, row_index)
+                                .map_err(|e| query_error(e.to_string()))?,
+                        };
+
+                        data[col_index].push(value);
+                    }
+                }
+            }
+        }
+        QueryResult::Json(j) => return Err(query_error(format!("Unexpected JSON result: {j}"))),
+        QueryResult::Empty => { /* noop */ }
+    }
+
+    Ok(transpose(data))
 }
 
 /// Implement the Connection trait for Snowflake
@@ -66,6 +252,16 @@ impl Connection for SnowflakeConnection {
     type Row = Arc<dyn Array>;
     type Column = ArrayRef;
 
+    const KIND: &'static str = "snowflake";
+
+    fn cache_ttl_s(&self) -> Option<u64> {
+        self.cache_ttl_s
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Get the length of a row
     fn row_len(_row: &Self::Row) -> usize {
         unimplemented!();
@@ -109,75 +305,53 @@ impl Connection for SnowflakeConnection {
     /// Query rows from a Snowflake database
     async fn query(
         &self,
-        _client: &mut Self::Conn,
+        client: &mut Self::Conn,
         sql: &str,
+        params: &[SqlParameter],
         max_bytes: Option<u64>,
     ) -> Result<(Bytes, bool, usize)> {
-        let query_error = |e: String| SharedError::Sql(SqlError::Query(e));
-
-        #[cfg(all(any(test, feature = "test"), not(clippy)))]
-        let (mut _client, _recording) = tests::get_mocked(self, "snowflake-connection").await;
-
-        let query_result = _client
-            .exec_raw(sql, true)
-            .await
-            .map_err(|e| query_error(e.to_string()))?;
-
-        #[cfg(all(
-            any(test, feature = "test"),
-            feature = "record-request-mock",
-            not(clippy)
-        ))]
-        record_stop(scenario, _recording).await;
-
-        if let RawQueryResult::Stream(mut bytes_stream) = query_result {
-            let mut chunks = vec![];
-
-            while let Some(bytes) = bytes_stream.next().await {
-                let bytes = bytes.map_err(|e| query_error(e.to_string()))?;
-
-                if let Some(max_bytes) = max_bytes {
-                    if (chunks.len() + bytes.len()) as u64 > max_bytes {
-                        return Ok((Bytes::new(), true, 0));
-                    }
-                }
-
-                chunks.push(bytes);
-            }
+        let Some(batches) = self.query_batches(client, sql, params, max_bytes).await? else {
+            return Ok((Bytes::new(), true, 0));
+        };
 
-            let bytes = chunks.into_iter().flatten().collect::<Vec<u8>>();
-            let resp = serde_json::from_slice::<ExecResponse>(&bytes)
-                .map_err(|e| query_error(e.to_string()))?;
-            let raw_query_result = _client
-                .parse_arrow_raw_response(resp)
-                .await
-                .map_err(|e| query_error(e.to_string()))?;
-            let query_result = raw_query_result
-                .deserialize_arrow()
-                .map_err(|e| query_error(e.to_string()))?;
-            let mut num_records = 0;
+        let mut num_records = 0;
+        let file = Vec::new();
+        let mut writer = ArrowWriter::try_new(file, batches[0].schema(), None)?;
 
-            if let QueryResult::Arrow(batches) = query_result {
-                let file = Vec::new();
-                let mut writer = ArrowWriter::try_new(file, batches[0].schema(), None)?;
+        for batch in batches {
+            num_records += batch.num_rows();
+            writer.write(&batch)?;
+        }
 
-                for batch in batches {
-                    num_records += batch.num_rows();
-                    writer.write(&batch)?;
-                }
+        let parquet = writer.into_inner()?;
+        Ok((parquet.into(), false, num_records))
+    }
 
-                let parquet = writer.into_inner()?;
-                return Ok((parquet.into(), false, num_records));
-            }
+    /// Stream a page of rows from a Snowflake database.  Snowflake returns
+    /// the results all at once, so only the conversion to Parquet is streamed.
+    async fn query_stream(
+        &self,
+        client: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        match self.query_batches(client, sql, params, max_bytes).await? {
+            Some(batches) => stream_batches(batches, page, sender, max_bytes).await,
+
+            // the response was over the limit before any rows were decoded
+            None => Ok(StreamSummary {
+                over_the_limit: true,
+                ..Default::default()
+            }),
         }
-
-        Err(SharedError::Sql(SqlError::Query(
-            "Could not convert to Arrow".to_string(),
-        )))
     }
 
-    async fn schema(&self, _client: &mut Self::Conn) -> Result<DatabaseSchema> {
+    async fn schema(&self, _client: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema> {
         let database = self.database.to_owned();
+        let filter = schema_filter("sch.schema_name", schemas);
         let sql = format!(
             "
             SELECT
@@ -200,7 +374,7 @@ impl Connection for SnowflakeConnection {
             JOIN
                 {database}.information_schema.databases db
                 ON sch.catalog_name = db.database_name
-            where sch.schema_name != 'INFORMATION_SCHEMA'
+            where sch.schema_name != 'INFORMATION_SCHEMA'{filter}
             ORDER BY
                 db.database_name,
                 sch.schema_name,
@@ -212,10 +386,7 @@ impl Connection for SnowflakeConnection {
         let (mut _client, _recording) =
             tests::get_mocked(self, "snowflake-connection-schema").await;
 
-        let row_stream = _client
-            .exec(&sql)
-            .await
-            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+        let rows = query_strings(&_client, &sql).await;
 
         #[cfg(all(
             any(test, feature = "test"),
@@ -224,64 +395,109 @@ impl Connection for SnowflakeConnection {
         ))]
         record_stop(scenario, _recording).await;
 
-        let mut data = vec![vec![]; 6];
-
-        match row_stream {
-            QueryResult::Arrow(a) => {
-                for batch in a {
-                    let num_cols = batch.num_columns();
-
-                    #[allow(clippy::needless_range_loop)]
-                    for col_index in 0..num_cols {
-                        let col = batch.column(col_index);
-
-                        // convert columns into a vec of strings
-                        let col_values = col
-                            .as_any()
-                            .downcast_ref::<arrow::array::StringArray>()
-                            .unwrap()
-                            .iter()
-                            .map(|s| s.unwrap_or_default().to_owned())
-                            .collect::<Vec<String>>();
+        let mut schema = DatabaseSchema::new(self.database.to_owned());
 
-                        // data in coming in as batches, so we need to combine them
-                        data[col_index].extend(col_values);
-                    }
-                }
-            }
-            QueryResult::Json(j) => unimplemented!("{j}"),
-            QueryResult::Empty => { /* noop */ }
-        }
-
-        let rows = transpose(data);
-        let mut schema = DatabaseSchema {
-            database: self.database.to_owned(),
-            tables: BTreeMap::new(),
-        };
-
-        for (index, row) in rows.into_iter().enumerate() {
+        for (index, row) in rows?.into_iter().enumerate() {
             let safe_get = |data: Option<&String>, kind: &str| {
                 data.map(|s| s.to_string())
                     .unwrap_or(format!("Unknown {kind} - {index}"))
             };
-            let table_name = safe_get(row.get(2), "Table");
 
             schema
-                .tables
-                .entry(table_name.to_owned())
-                .or_insert_with(|| SchemaTable {
-                    name: table_name,
-                    schema: safe_get(row.get(1), "Schema"),
-                    columns: vec![],
-                })
+                .table_mut(
+                    &safe_get(row.get(1), "Schema"),
+                    &safe_get(row.get(2), "Table"),
+                )
                 .columns
                 .push(SchemaColumn {
                     name: safe_get(row.get(3), "Column"),
                     r#type: safe_get(row.get(4), "Type"),
                     is_nullable: row.get(5).map_or("NO", |v| v).to_uppercase() == "YES",
+                    default: None,
+                    comment: None,
                 });
         }
 
+        // views, row counts, defaults and comments
+        let filter = schema_filter("tbl.table_schema", schemas);
+        let sql = format!(
+            "
+            SELECT
+                tbl.table_schema,
+                tbl.table_name,
+                tbl.table_type,
+                tbl.row_count,
+                tbl.comment,
+                col.column_name,
+                col.column_default,
+                col.comment
+            FROM
+                {database}.information_schema.tables tbl
+            JOIN
+                {database}.information_schema.columns col
+                    ON col.table_catalog = tbl.table_catalog
+                    AND col.table_schema = tbl.table_schema
+                    AND col.table_name = tbl.table_name
+            WHERE tbl.table_schema != 'INFORMATION_SCHEMA'{filter};"
+        );
+
+        for row in optional("details", query_strings(&_client, &sql).await) {
+            let Some(table) = schema.existing_table_mut(&row[0], &row[1]) else {
+                continue;
+            };
+            let is_view = row[2].contains("VIEW");
+
+            table.kind = if is_view {
+                TableKind::View
+            } else {
+                TableKind::Table
+            };
+            table.row_count = row[3].parse().ok();
+            table.comment = non_empty(Some(row[4].to_owned()));
+
+            if let Some(column) = table
+                .columns
+                .iter_mut()
+                .find(|column| column.name == row[5])
+            {
+                column.default = non_empty(Some(row[6].to_owned()));
+                column.comment = non_empty(Some(row[7].to_owned()));
+            }
+        }
+
+        // primary keys, Snowflake records keys without enforcing them
+        let sql = format!(
+            "SHOW PRIMARY KEYS IN DATABASE {database}
+            ->> SELECT \"schema_name\", \"table_name\", \"column_name\"
+                FROM $1 ORDER BY \"schema_name\", \"table_name\", \"key_sequence\";"
+        );
+
+        for row in optional("primary keys", query_strings(&_client, &sql).await) {
+            if let Some(table) = schema.existing_table_mut(&row[0], &row[1]) {
+                table.primary_key.push(row[2].to_owned());
+            }
+        }
+
+        // foreign keys, one row per column
+        let sql = format!(
+            "SHOW IMPORTED KEYS IN DATABASE {database}
+            ->> SELECT \"fk_schema_name\", \"fk_table_name\", \"fk_name\", \"fk_column_name\",
+                    \"pk_schema_name\", \"pk_table_name\", \"pk_column_name\"
+                FROM $1 ORDER BY \"fk_schema_name\", \"fk_table_name\", \"fk_name\", \"key_sequence\";"
+        );
+
+        for row in optional("foreign keys", query_strings(&_client, &sql).await) {
+            if let Some(table) = schema.existing_table_mut(&row[0], &row[1]) {
+                table.add_foreign_key_column(
+                    &row[2],
+                    row[3].to_owned(),
+                    row[4].to_owned(),
+                    row[5].to_owned(),
+                    row[6].to_owned(),
+                );
+            }
+        }
+
         Ok(schema)
     }
 }
@@ -421,12 +637,43 @@ pub mod tests {
             .query(
                 &mut client,
                 "select * from all_native_data_types;",
+                &[],
                 max_bytes,
             )
             .await
             .unwrap()
     }
 
+    #[test]
+    fn test_snowflake_bindings() {
+        let date = chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap();
+        let time = NaiveTime::from_hms_nano_opt(0, 0, 1, 5).unwrap();
+        let params = [
+            SqlParameter::Text("O'Brien\\".into()),
+            SqlParameter::Integer(1),
+            SqlParameter::Float(2.5),
+            SqlParameter::Boolean(true),
+            SqlParameter::Null,
+            SqlParameter::Date(date),
+            SqlParameter::Time(time),
+            SqlParameter::DateTime(date.and_time(time)),
+        ];
+
+        assert_eq!(
+            bindings(&params).unwrap(),
+            serde_json::json!({
+                "1": { "type": "TEXT", "value": "O'Brien\\" },
+                "2": { "type": "FIXED", "value": "1" },
+                "3": { "type": "REAL", "value": "2.5" },
+                "4": { "type": "BOOLEAN", "value": "true" },
+                "5": { "type": "ANY", "value": null },
+                "6": { "type": "DATE", "value": "86400000" },
+                "7": { "type": "TIME", "value": "1000000005" },
+                "8": { "type": "TIMESTAMP_NTZ", "value": "86401000000005" },
+            })
+        );
+    }
+
     #[tokio::test]
     async fn test_snowflake_connection() {
         let (_, client) = setup().await;
@@ -457,102 +704,34 @@ pub mod tests {
     async fn test_snowflake_schema() {
         let connection = new_snowflake_connection();
         let mut client = connection.connect().await.unwrap();
-        let schema = connection.schema(&mut client).await.unwrap();
+        let schema = connection.schema(&mut client, &[]).await.unwrap();
 
         let expected = vec![
-            SchemaColumn {
-                name: "INTEGER_COL".into(),
-                r#type: "NUMBER".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "FLOAT_COL".into(),
-                r#type: "FLOAT".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "NUMBER_COL".into(),
-                r#type: "NUMBER".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "DECIMAL_COL".into(),
-                r#type: "NUMBER".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "BOOLEAN_COL".into(),
-                r#type: "BOOLEAN".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "VARCHAR_COL".into(),
-                r#type: "TEXT".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "CHAR_COL".into(),
-                r#type: "TEXT".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "STRING_COL".into(),
-                r#type: "TEXT".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "BINARY_COL".into(),
-                r#type: "BINARY".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "DATE_COL".into(),
-                r#type: "DATE".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "TIME_COL".into(),
-                r#type: "TIME".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "TIMESTAMP_NTZ_COL".into(),
-                r#type: "TIMESTAMP_NTZ".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "TIMESTAMP_LTZ_COL".into(),
-                r#type: "TIMESTAMP_LTZ".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "TIMESTAMP_TZ_COL".into(),
-                r#type: "TIMESTAMP_TZ".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "VARIANT_COL".into(),
-                r#type: "VARIANT".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "OBJECT_COL".into(),
-                r#type: "OBJECT".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "ARRAY_COL".into(),
-                r#type: "ARRAY".into(),
-                is_nullable: true,
-            },
-            SchemaColumn {
-                name: "GEOGRAPHY_COL".into(),
-                r#type: "GEOGRAPHY".into(),
-                is_nullable: true,
-            },
+            SchemaColumn::new("INTEGER_COL", "NUMBER", true),
+            SchemaColumn::new("FLOAT_COL", "FLOAT", true),
+            SchemaColumn::new("NUMBER_COL", "NUMBER", true),
+            SchemaColumn::new("DECIMAL_COL", "NUMBER", true),
+            SchemaColumn::new("BOOLEAN_COL", "BOOLEAN", true),
+            SchemaColumn::new("VARCHAR_COL", "TEXT", true),
+            SchemaColumn::new("CHAR_COL", "TEXT", true),
+            SchemaColumn::new("STRING_COL", "TEXT", true),
+            SchemaColumn::new("BINARY_COL", "BINARY", true),
+            SchemaColumn::new("DATE_COL", "DATE", true),
+            SchemaColumn::new("TIME_COL", "TIME", true),
+            SchemaColumn::new("TIMESTAMP_NTZ_COL", "TIMESTAMP_NTZ", true),
+            SchemaColumn::new("TIMESTAMP_LTZ_COL", "TIMESTAMP_LTZ", true),
+            SchemaColumn::new("TIMESTAMP_TZ_COL", "TIMESTAMP_TZ", true),
+            SchemaColumn::new("VARIANT_COL", "VARIANT", true),
+            SchemaColumn::new("OBJECT_COL", "OBJECT", true),
+            SchemaColumn::new("ARRAY_COL", "ARRAY", true),
+            SchemaColumn::new("GEOGRAPHY_COL", "GEOGRAPHY", true),
         ];
 
-        let columns = &schema.tables.get("ALL_NATIVE_DATA_TYPES").unwrap().columns;
+        let columns = &schema
+            .tables
+            .get("PUBLIC.ALL_NATIVE_DATA_TYPES")
+            .unwrap()
+            .columns;
 
         assert_eq!(columns, &expected);
     }
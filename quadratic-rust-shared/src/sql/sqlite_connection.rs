@@ -0,0 +1,540 @@
+//! SQLite
+//!
+//! Functions to interact with SQLite database files.  Databases are opened
+//! read-only, from the data directory when there is one, and can't attach
+//! other databases.
+
+use std::path::PathBuf;
+
+use arrow::datatypes::Date32Type;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use sqlx::{
+    Column, ConnectOptions, Row, SqliteConnection as SqlxSqliteConnection, TypeInfo, ValueRef,
+    sqlite::{SqliteColumn, SqliteConnectOptions, SqliteRow},
+};
+
+use crate::convert_sqlite_type;
+use crate::error::{Result, SharedError};
+use crate::sql::error::Sql as SqlError;
+use crate::sql::parameter::{SqlParameter, bind_sqlx};
+use crate::sql::schema::{DatabaseSchema, SchemaColumn, TableKind, optional};
+use crate::sql::stream::{ChunkSender, Page, StreamSummary, stream_rows};
+use crate::sql::{ArrowType, Connection, resolve_path};
+
+const MAIN_SCHEMA: &str = "main";
+
+/// SQLite connection.  Databases are local files, so there are no TLS or SSH
+/// options.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SqliteConnection {
+    /// Path to the database file, relative to the data directory
+    pub path: String,
+
+    /// Set by the service rather than the user, so that users can only open
+    /// databases in this directory
+    #[serde(skip)]
+    pub data_dir: Option<PathBuf>,
+}
+
+impl SqliteConnection {
+    /// Create a new SQLite connection
+    pub fn new(path: String, data_dir: Option<PathBuf>) -> SqliteConnection {
+        SqliteConnection { path, data_dir }
+    }
+
+    /// Stop queries from attaching other databases.  ATTACH opens any file the
+    /// service can read (and writes to it, since read-only only applies to the
+    /// main database), so a connection must only see its own database.
+    async fn deny_attach(pool: &mut SqlxSqliteConnection) -> Result<()> {
+        let mut handle = pool
+            .lock_handle()
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Connect(e.to_string())))?;
+
+        // SAFETY: the handle is an open connection, and it's locked while
+        // the limit is set
+        unsafe {
+            libsqlite3_sys::sqlite3_limit(
+                handle.as_raw_handle().as_ptr(),
+                libsqlite3_sys::SQLITE_LIMIT_ATTACHED,
+                0,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Query all rows from a SQLite database
+    async fn query_all(pool: &mut SqlxSqliteConnection, sql: &str) -> Result<Vec<SqliteRow>> {
+        let rows = sqlx::query(sql)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl Connection for SqliteConnection {
+    type Conn = SqlxSqliteConnection;
+    type Row = SqliteRow;
+    type Column = SqliteColumn;
+
+    const KIND: &'static str = "sqlite";
+
+    /// Database files are local, so querying them is as cheap as reading
+    /// cached results, which could also be stale after the file changes
+    fn cache_ttl_s(&self) -> Option<u64> {
+        Some(0)
+    }
+
+    /// Get the length of a row
+    fn row_len(row: &Self::Row) -> usize {
+        row.len()
+    }
+
+    /// Get the columns of a row
+    fn row_columns(row: &Self::Row) -> Box<dyn Iterator<Item = &Self::Column> + '_> {
+        Box::new(row.columns().iter())
+    }
+
+    /// Get the name of a column
+    fn column_name(col: &Self::Column) -> &str {
+        col.name()
+    }
+
+    /// Open a SQLite database file, read-only, without attaching others
+    async fn connect(&self) -> Result<Self::Conn> {
+        let path = resolve_path(&self.path, self.data_dir.as_deref())?;
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .read_only(true)
+            .create_if_missing(false);
+
+        let mut pool = options
+            .connect()
+            .await
+            .map_err(|e| SharedError::Sql(SqlError::Connect(format!("{:?}: {e}", self.path))))?;
+        SqliteConnection::deny_attach(&mut pool).await?;
+
+        Ok(pool)
+    }
+
+    /// Query rows from a SQLite database
+    async fn query(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        max_bytes: Option<u64>,
+    ) -> Result<(Bytes, bool, usize)> {
+        let mut rows = vec![];
+        let mut over_the_limit = false;
+
+        if let Some(max_bytes) = max_bytes {
+            let mut bytes = 0;
+            let mut stream = bind_sqlx(sqlx::query(sql), params).fetch(pool);
+
+            while let Some(row) = stream.next().await {
+                let row = row.map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+                bytes += row.len() as u64;
+
+                if bytes > max_bytes {
+                    over_the_limit = true;
+                    break;
+                }
+
+                rows.push(row);
+            }
+        } else {
+            rows = bind_sqlx(sqlx::query(sql), params)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())))?;
+        }
+
+        let (bytes, num_records) = Self::to_parquet(rows)?;
+
+        Ok((bytes, over_the_limit, num_records))
+    }
+
+    /// Stream a page of rows from a SQLite database
+    async fn query_stream(
+        &self,
+        pool: &mut Self::Conn,
+        sql: &str,
+        params: &[SqlParameter],
+        page: Page,
+        max_bytes: Option<u64>,
+        sender: ChunkSender,
+    ) -> Result<StreamSummary> {
+        let rows = bind_sqlx(sqlx::query(sql), params)
+            .fetch(pool)
+            .map_err(|e| SharedError::Sql(SqlError::Query(e.to_string())));
+
+        stream_rows::<Self, _>(rows, page, sender, max_bytes).await
+    }
+
+    /// Get the schema of a SQLite database.  SQLite has no schemas within a
+    /// database, so tables are in the "main" schema.
+    async fn schema(&self, pool: &mut Self::Conn, schemas: &[String]) -> Result<DatabaseSchema> {
+        let mut schema = DatabaseSchema::new(self.path.to_owned());
+
+        // a database file only has the main schema
+        if !schemas.is_empty() && !schemas.iter().any(|schema| schema == MAIN_SCHEMA) {
+            return Ok(schema);
+        }
+
+        // tables, views and their columns
+        let sql = "
+            select m.name as table_name, m.type as table_type, p.name as column_name,
+                p.type as column_type, p.\"notnull\" as not_null, p.dflt_value as column_default
+            from sqlite_master as m
+            join pragma_table_info(m.name) as p
+            where m.type in ('table', 'view') and m.name not like 'sqlite_%'
+            order by m.name, p.cid";
+
+        for row in SqliteConnection::query_all(pool, sql).await? {
+            let table = schema.table_mut(MAIN_SCHEMA, &row.get::<String, usize>(0));
+
+            if row.get::<String, usize>(1) == "view" {
+                table.kind = TableKind::View;
+            }
+
+            table.columns.push(SchemaColumn {
+                name: row.get::<String, usize>(2),
+                r#type: row.get::<String, usize>(3).to_lowercase(),
+                is_nullable: row.get::<i64, usize>(4) == 0,
+                default: row.get::<Option<String>, usize>(5),
+                comment: None,
+            });
+        }
+
+        // primary keys, in key order
+        let sql = "
+            select m.name, p.name
+            from sqlite_master as m
+            join pragma_table_info(m.name) as p
+            where m.type = 'table' and p.pk > 0
+            order by m.name, p.pk";
+
+        for row in optional("primary keys", SqliteConnection::query_all(pool, sql).await) {
+            if let Some(table) =
+                schema.existing_table_mut(MAIN_SCHEMA, &row.get::<String, usize>(0))
+            {
+                table.primary_key.push(row.get::<String, usize>(1));
+            }
+        }
+
+        // foreign keys, which reference the primary key when no columns are given
+        let sql = "
+            select m.name, f.id, f.\"from\", f.\"table\",
+                coalesce(f.\"to\", (select p.name from pragma_table_info(f.\"table\") as p where p.pk = f.seq + 1))
+            from sqlite_master as m
+            join pragma_foreign_key_list(m.name) as f
+            where m.type = 'table'
+            order by m.name, f.id, f.seq";
+
+        for row in optional("foreign keys", SqliteConnection::query_all(pool, sql).await) {
+            if let Some(table) =
+                schema.existing_table_mut(MAIN_SCHEMA, &row.get::<String, usize>(0))
+            {
+                // SQLite doesn't keep the names of foreign keys
+                table.add_foreign_key_column(
+                    &format!("fk_{}", row.get::<i64, usize>(1)),
+                    row.get::<String, usize>(2),
+                    MAIN_SCHEMA.into(),
+                    row.get::<String, usize>(3),
+                    row.get::<Option<String>, usize>(4).unwrap_or_default(),
+                );
+            }
+        }
+
+        // indexes, one row per column, expressions are skipped
+        let sql = "
+            select m.name, i.name, c.name, i.\"unique\", i.origin
+            from sqlite_master as m
+            join pragma_index_list(m.name) as i
+            join pragma_index_info(i.name) as c
+            where m.type = 'table' and c.name is not null
+            order by m.name, i.name, c.seqno";
+
+        for row in optional("indexes", SqliteConnection::query_all(pool, sql).await) {
+            if let Some(table) =
+                schema.existing_table_mut(MAIN_SCHEMA, &row.get::<String, usize>(0))
+            {
+                table.add_index_column(
+                    &row.get::<String, usize>(1),
+                    row.get::<String, usize>(2),
+                    row.get::<i64, usize>(3) == 1,
+                    row.get::<String, usize>(4) == "pk",
+                );
+            }
+        }
+
+        // row counts are only known after `analyze`, which creates sqlite_stat1
+        let sql = "select 1 from sqlite_master where name = 'sqlite_stat1'";
+        let has_stats =
+            !optional("statistics", SqliteConnection::query_all(pool, sql).await).is_empty();
+
+        if has_stats {
+            let sql = "select tbl, max(cast(stat as integer)) from sqlite_stat1 group by tbl";
+
+            for row in optional("row counts", SqliteConnection::query_all(pool, sql).await) {
+                if let Some(table) =
+                    schema.existing_table_mut(MAIN_SCHEMA, &row.get::<String, usize>(0))
+                {
+                    table.row_count = Some(row.get::<i64, usize>(1) as u64);
+                }
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Convert a row to an Arrow type.  SQLite values have a type rather than
+    /// their columns, so columns without a declared type (eg, expressions)
+    /// use the type of their value.
+    fn to_arrow(row: &Self::Row, column: &Self::Column, index: usize) -> ArrowType {
+        let declared = column.type_info();
+        let type_info = match declared.is_null() {
+            true => row
+                .try_get_raw(index)
+                .map(|value| value.type_info().into_owned())
+                .unwrap_or_else(|_| declared.to_owned()),
+            false => declared.to_owned(),
+        };
+
+        match type_info.name() {
+            "TEXT" => ArrowType::Utf8(convert_sqlite_type!(String, row, index)),
+            "INTEGER" => ArrowType::Int64(convert_sqlite_type!(i64, row, index)),
+            "REAL" => ArrowType::Float64(convert_sqlite_type!(f64, row, index)),
+            // numeric affinity stores integers or reals
+            "NUMERIC" => ArrowType::Float64(
+                row.try_get_unchecked::<f64, usize>(index)
+                    .unwrap_or_default(),
+            ),
+            "BOOLEAN" => ArrowType::Boolean(convert_sqlite_type!(bool, row, index)),
+            "DATE" => {
+                let naive_date = convert_sqlite_type!(NaiveDate, row, index);
+                ArrowType::Date32(Date32Type::from_naive_date(naive_date))
+            }
+            "TIME" => ArrowType::Time32(convert_sqlite_type!(NaiveTime, row, index)),
+            "DATETIME" => ArrowType::Timestamp(convert_sqlite_type!(NaiveDateTime, row, index)),
+            "NULL" => ArrowType::Void,
+            // BLOB
+            _ => ArrowType::Unsupported,
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! convert_sqlite_type {
+    ( $kind:ty, $row:ident, $index:ident ) => {{
+        $row.try_get::<$kind, usize>($index)
+            .ok()
+            .unwrap_or_default()
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::sql::schema::{ForeignKey, SchemaIndex};
+    use uuid::Uuid;
+
+    /// Create a database file in a new data directory
+    async fn new_sqlite_connection() -> SqliteConnection {
+        let data_dir = std::env::temp_dir().join(format!("sqlite-connection-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        let mut pool = SqliteConnectOptions::new()
+            .filename(data_dir.join("test.sqlite"))
+            .create_if_missing(true)
+            .connect()
+            .await
+            .unwrap();
+
+        sqlx::raw_sql(
+            "create table all_types (
+                id integer primary key not null,
+                text_col text,
+                real_col real,
+                numeric_col numeric,
+                boolean_col boolean,
+                date_col date,
+                time_col time,
+                datetime_col datetime,
+                blob_col blob
+            );
+            insert into all_types values (
+                1, 'text_data', 1.5, 12.25, true, '2024-05-28', '12:34:56',
+                '2024-05-28 12:34:56', x'00'
+            );
+            create table orders (
+                id integer primary key,
+                all_types_id integer not null default 1 references all_types (id)
+            );
+            create index orders_all_types_id on orders (all_types_id);
+            create view all_types_view as select id, text_col from all_types;",
+        )
+        .execute(&mut pool)
+        .await
+        .unwrap();
+
+        SqliteConnection::new("test.sqlite".into(), Some(data_dir))
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_query_to_arrow() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+        let sql = "select *, 1 + 1 as expression from all_types";
+        let rows = SqliteConnection::query_all(&mut pool, sql).await.unwrap();
+
+        let row = &rows[0];
+        let columns = row.columns();
+        let to_arrow = |index: usize| SqliteConnection::to_arrow(row, &columns[index], index);
+
+        assert_eq!(to_arrow(0), ArrowType::Int64(1));
+        assert_eq!(to_arrow(1), ArrowType::Utf8("text_data".into()));
+        assert_eq!(to_arrow(2), ArrowType::Float64(1.5));
+        assert_eq!(to_arrow(3), ArrowType::Float64(12.25));
+        assert_eq!(to_arrow(4), ArrowType::Boolean(true));
+        assert_eq!(to_arrow(5), ArrowType::Date32(19871));
+        assert_eq!(
+            to_arrow(6),
+            ArrowType::Time32(NaiveTime::from_str("12:34:56").unwrap())
+        );
+        assert_eq!(
+            to_arrow(7),
+            ArrowType::Timestamp(NaiveDateTime::from_str("2024-05-28T12:34:56").unwrap())
+        );
+        assert_eq!(to_arrow(8), ArrowType::Unsupported);
+        assert_eq!(to_arrow(9), ArrowType::Int64(2));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_query_with_parameters() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+        let sql = "select text_col from all_types where id = ?";
+        let params = [SqlParameter::Integer(1)];
+        let (_, over_the_limit, num_records) = connection
+            .query(&mut pool, sql, &params, None)
+            .await
+            .unwrap();
+
+        assert!(!over_the_limit);
+        assert_eq!(num_records, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_is_read_only() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+        let sql = "delete from all_types";
+
+        assert!(connection.query(&mut pool, sql, &[], None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_attach() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+        let other = std::env::temp_dir().join(format!("sqlite-other-{}", Uuid::new_v4()));
+
+        for sql in [
+            format!("attach '{}' as other", other.display()),
+            "attach '/etc/passwd' as other".to_string(),
+            "attach ':memory:' as other".to_string(),
+        ] {
+            let result = connection.query(&mut pool, &sql, &[], None).await;
+            assert!(result.is_err(), "{sql} was allowed");
+        }
+        assert!(!other.exists());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_path_outside_data_dir() {
+        let connection = new_sqlite_connection().await;
+        let data_dir = connection.data_dir.to_owned();
+
+        for path in ["../test.sqlite", "/etc/passwd", "missing.sqlite"] {
+            let connection = SqliteConnection::new(path.into(), data_dir.to_owned());
+            assert!(connection.connect().await.is_err(), "{path} was opened");
+        }
+
+        // absolute paths are relative to the data directory
+        let path = data_dir.as_deref().unwrap().canonicalize().unwrap();
+        assert_eq!(
+            resolve_path("/test.sqlite", data_dir.as_deref()).unwrap(),
+            path.join("test.sqlite")
+        );
+        assert_eq!(
+            resolve_path("/etc/passwd", None).unwrap(),
+            Path::new("/etc/passwd")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_schema() {
+        let connection = new_sqlite_connection().await;
+        let mut pool = connection.connect().await.unwrap();
+        let schema = connection.schema(&mut pool, &[]).await.unwrap();
+        let table = schema.tables.get("main.all_types").unwrap();
+
+        assert_eq!(table.schema, "main");
+        assert_eq!(table.kind, TableKind::Table);
+        assert_eq!(table.primary_key, vec!["id"]);
+        assert_eq!(table.columns[0], SchemaColumn::new("id", "integer", false));
+        assert_eq!(
+            table.columns[1],
+            SchemaColumn::new("text_col", "text", true)
+        );
+        assert_eq!(table.columns.len(), 9);
+
+        let orders = schema.tables.get("main.orders").unwrap();
+        assert_eq!(orders.columns[1].default, Some("1".into()));
+        assert_eq!(
+            orders.foreign_keys,
+            vec![ForeignKey {
+                name: "fk_0".into(),
+                columns: vec!["all_types_id".into()],
+                referenced_schema: "main".into(),
+                referenced_table: "all_types".into(),
+                referenced_columns: vec!["id".into()],
+            }]
+        );
+        assert_eq!(
+            orders.indexes,
+            vec![SchemaIndex {
+                name: "orders_all_types_id".into(),
+                columns: vec!["all_types_id".into()],
+                is_unique: false,
+                is_primary: false,
+            }]
+        );
+
+        let view = schema.tables.get("main.all_types_view").unwrap();
+        assert_eq!(view.kind, TableKind::View);
+        assert_eq!(view.columns.len(), 2);
+
+        // only the main schema exists
+        let schema = connection
+            .schema(&mut pool, &["other".into()])
+            .await
+            .unwrap();
+        assert!(schema.tables.is_empty());
+    }
+}
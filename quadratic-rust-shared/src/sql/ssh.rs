@@ -0,0 +1,294 @@
+//! SSH Tunnels
+//!
+//! Reach databases behind a bastion host by forwarding a local port to the
+//! database over SSH.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use russh::Disconnect;
+use russh::client::{self, Handle};
+use russh_keys::PublicKeyBase64;
+use russh_keys::key::PublicKey;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+
+use crate::error::{Result, SharedError};
+use crate::sql::error::Sql as SqlError;
+use crate::sql::parse_port;
+
+const DEFAULT_SSH_PORT: u16 = 22;
+
+/// How long the tunnel waits for the database driver to connect
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SshAuth {
+    Password {
+        password: String,
+    },
+
+    /// OpenSSH or PEM encoded private key
+    PrivateKey {
+        private_key: String,
+        passphrase: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SshConfig {
+    pub host: String,
+    pub port: Option<String>,
+    pub username: String,
+    pub auth: SshAuth,
+
+    /// The bastion's public key in OpenSSH format (eg, `ssh-ed25519 AAAA...`).
+    /// Required: connections without it are refused, and the error includes
+    /// the key the bastion presented so that it can be verified and added.
+    pub host_key: Option<String>,
+}
+
+fn tunnel_error(e: impl ToString) -> SharedError {
+    SharedError::Sql(SqlError::Tunnel(e.to_string()))
+}
+
+/// Parse a public key in OpenSSH format, ignoring the comment
+fn parse_host_key(host_key: &str) -> Result<PublicKey> {
+    let mut parts = host_key.split_whitespace();
+    let key = match (parts.next(), parts.next()) {
+        (Some(_algorithm), Some(key)) => key,
+        (Some(key), None) => key,
+        _ => return Err(tunnel_error("Host key is empty")),
+    };
+
+    russh_keys::parse_public_key_base64(key).map_err(tunnel_error)
+}
+
+struct Client {
+    host_key: Option<PublicKey>,
+
+    /// The key the server presented, in OpenSSH format
+    server_key: Arc<Mutex<Option<String>>>,
+}
+
+#[async_trait]
+impl client::Handler for Client {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKey,
+    ) -> std::result::Result<bool, Self::Error> {
+        let server_key = format!(
+            "{} {}",
+            server_public_key.name(),
+            server_public_key.public_key_base64()
+        );
+
+        if let Ok(mut presented) = self.server_key.lock() {
+            *presented = Some(server_key);
+        }
+
+        // never trust an unknown server
+        Ok(match &self.host_key {
+            Some(host_key) => host_key.fingerprint() == server_public_key.fingerprint(),
+            None => false,
+        })
+    }
+}
+
+impl SshConfig {
+    /// Connect and authenticate to the bastion host
+    async fn session(&self) -> Result<Handle<Client>> {
+        let port = parse_port(&self.port)?.unwrap_or(DEFAULT_SSH_PORT);
+        let host_key = self.host_key.as_deref().map(parse_host_key).transpose()?;
+        let config = Arc::new(client::Config::default());
+        let server_key = Arc::new(Mutex::new(None));
+        let client = Client {
+            host_key: host_key.clone(),
+            server_key: Arc::clone(&server_key),
+        };
+
+        let mut session = client::connect(config, (self.host.as_str(), port), client)
+            .await
+            .map_err(|e| {
+                let presented = server_key.lock().ok().and_then(|key| key.clone());
+
+                match (presented, host_key) {
+                    (Some(presented), None) => tunnel_error(format!(
+                        "A host key is required for {}. The server presented `{presented}`; verify it and add it to the connection",
+                        self.host
+                    )),
+                    (Some(presented), Some(_)) => tunnel_error(format!(
+                        "Host key mismatch for {}. The server presented `{presented}`",
+                        self.host
+                    )),
+                    (None, _) => tunnel_error(e),
+                }
+            })?;
+
+        let authenticated = match &self.auth {
+            SshAuth::Password { password } => {
+                session
+                    .authenticate_password(&self.username, password)
+                    .await
+            }
+            SshAuth::PrivateKey {
+                private_key,
+                passphrase,
+            } => {
+                let key = russh_keys::decode_secret_key(private_key, passphrase.as_deref())
+                    .map_err(tunnel_error)?;
+
+                session
+                    .authenticate_publickey(&self.username, Arc::new(key))
+                    .await
+            }
+        }
+        .map_err(tunnel_error)?;
+
+        if !authenticated {
+            return Err(tunnel_error(format!(
+                "Authentication failed for {}@{}",
+                self.username, self.host
+            )));
+        }
+
+        Ok(session)
+    }
+
+    /// Open a tunnel to `remote_host:remote_port` through the bastion host.
+    /// Returns the local address that the database driver should connect to.
+    ///
+    /// The tunnel forwards a single connection and closes along with it, so
+    /// it lives exactly as long as the database connection.
+    pub async fn tunnel(&self, remote_host: &str, remote_port: u16) -> Result<SocketAddr> {
+        let session = self.session().await?;
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .map_err(tunnel_error)?;
+        let local_addr = listener.local_addr().map_err(tunnel_error)?;
+
+        // open the channel before returning, so that a bastion that can't
+        // reach the database fails the connection
+        let channel = session
+            .channel_open_direct_tcpip(
+                remote_host,
+                remote_port as u32,
+                local_addr.ip().to_string(),
+                local_addr.port() as u32,
+            )
+            .await
+            .map_err(tunnel_error)?;
+
+        tokio::spawn(async move {
+            let forward = async {
+                let (mut local, _) = tokio::time::timeout(ACCEPT_TIMEOUT, listener.accept())
+                    .await
+                    .map_err(tunnel_error)?
+                    .map_err(tunnel_error)?;
+                let mut remote = channel.into_stream();
+
+                tokio::io::copy_bidirectional(&mut local, &mut remote)
+                    .await
+                    .map_err(tunnel_error)
+            };
+
+            if let Err(error) = forward.await {
+                tracing::warn!("SSH tunnel closed: {error}");
+            }
+
+            let _ = session
+                .disconnect(Disconnect::ByApplication, "", "en")
+                .await;
+        });
+
+        Ok(local_addr)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // see docker/ssh-connection/ssh_host_keys
+    const SSH_CONNECTION_HOST_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIC9LF5dKZxbROgbveVS1Qri/AHV82i7cKCQIr+VLv7cx";
+
+    // see the ssh-connection service in docker-compose.yml
+    pub(crate) fn new_ssh_config() -> SshConfig {
+        SshConfig {
+            host: "127.0.0.1".into(),
+            port: Some("2222".into()),
+            username: "user".into(),
+            auth: SshAuth::Password {
+                password: "password".into(),
+            },
+            host_key: Some(SSH_CONNECTION_HOST_KEY.into()),
+        }
+    }
+
+    #[test]
+    fn test_parse_host_key() {
+        let key = "AAAAC3NzaC1lZDI1NTE5AAAAINmhY79hBi15VTiOk1pIrY6Zvd4eyZugKff4I+7TfBwH";
+        let fingerprint = parse_host_key(key).unwrap().fingerprint();
+
+        assert_eq!(
+            parse_host_key(&format!("ssh-ed25519 {key} user@bastion"))
+                .unwrap()
+                .fingerprint(),
+            fingerprint
+        );
+        assert!(parse_host_key("").is_err());
+        assert!(parse_host_key("ssh-ed25519 not-a-key").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ssh_tunnel() {
+        // postgres-connection, as seen from the ssh-connection container
+        let local_addr = new_ssh_config()
+            .tunnel("postgres-connection", 5432)
+            .await
+            .unwrap();
+
+        assert!(local_addr.ip().is_loopback());
+        assert!(tokio::net::TcpStream::connect(local_addr).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ssh_tunnel_authentication_failure() {
+        let mut config = new_ssh_config();
+        config.auth = SshAuth::Password {
+            password: "wrong".into(),
+        };
+
+        assert!(config.tunnel("postgres-connection", 5432).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ssh_tunnel_requires_host_key() {
+        let mut config = new_ssh_config();
+        config.host_key = None;
+
+        let error = config
+            .tunnel("postgres-connection", 5432)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(SSH_CONNECTION_HOST_KEY));
+    }
+
+    #[tokio::test]
+    async fn test_ssh_tunnel_host_key_mismatch() {
+        let mut config = new_ssh_config();
+        config.host_key = Some(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINmhY79hBi15VTiOk1pIrY6Zvd4eyZugKff4I+7TfBwH"
+                .into(),
+        );
+
+        assert!(config.tunnel("postgres-connection", 5432).await.is_err());
+    }
+}
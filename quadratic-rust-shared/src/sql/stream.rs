@@ -0,0 +1,414 @@
+//! Streaming Query Results
+//!
+//! Rather than collecting every row before converting to Parquet, rows are
+//! converted in batches and each batch is written as a Parquet row group.  The
+//! bytes of a row group are sent as soon as they're written, so a response can
+//! be chunked while the query is still running.
+//!
+//! Since headers are sent before the results, the Parquet footer's key/value
+//! metadata records whether the results are over the limit or there are more
+//! pages (see `OVER_THE_LIMIT_KEY` and `NEXT_OFFSET_KEY`).
+
+use std::sync::Arc;
+
+use arrow::array::RecordBatch;
+use arrow::compute::cast;
+use arrow::datatypes::SchemaRef;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use parquet::arrow::ArrowWriter;
+use parquet::format::KeyValue;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+
+use crate::error::Result;
+use crate::sql::Connection;
+
+/// Number of rows in each row group
+pub const BATCH_ROWS: usize = 10_000;
+
+/// Footer metadata key, set to "true" when the results were truncated
+pub const OVER_THE_LIMIT_KEY: &str = "quadratic:over_the_limit";
+
+/// Footer metadata key, set to the offset of the next page when there is one
+pub const NEXT_OFFSET_KEY: &str = "quadratic:next_offset";
+
+/// Receives the Parquet bytes as they're written
+pub type ChunkSender = Sender<Result<Bytes>>;
+
+/// A page of query results.  Pages are taken from the query's results rather
+/// than by rewriting the query, so any statement can be paginated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page {
+    #[serde(default)]
+    pub offset: u64,
+    pub limit: Option<u64>,
+}
+
+impl Page {
+    /// Whether this page is every row
+    pub fn is_all(&self) -> bool {
+        self.offset == 0 && self.limit.is_none()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StreamSummary {
+    pub num_records: usize,
+    pub bytes: u64,
+    pub over_the_limit: bool,
+
+    /// The offset of the next page, if there are more rows
+    pub next_offset: Option<u64>,
+}
+
+/// Writes record batches as Parquet row groups, sending the bytes of each row
+/// group as soon as it's written.  The Parquet writer buffers its output, so
+/// small row groups are sent together.
+pub struct ParquetStream {
+    writer: Option<(ArrowWriter<Vec<u8>>, SchemaRef)>,
+    sender: ChunkSender,
+    max_bytes: Option<u64>,
+    summary: StreamSummary,
+}
+
+impl ParquetStream {
+    pub fn new(sender: ChunkSender, max_bytes: Option<u64>) -> Self {
+        ParquetStream {
+            writer: None,
+            sender,
+            max_bytes,
+            summary: StreamSummary::default(),
+        }
+    }
+
+    /// Write a batch as a row group and send its bytes.
+    ///
+    /// Returns false when no more batches should be written, either because
+    /// the results are over the limit or the receiver has hung up.
+    pub async fn write(&mut self, batch: RecordBatch) -> Result<bool> {
+        if batch.num_rows() == 0 {
+            return Ok(true);
+        }
+
+        let (writer, schema) = match &mut self.writer {
+            Some(writer) => writer,
+            None => self.writer.insert((
+                ArrowWriter::try_new(Vec::new(), batch.schema(), None)?,
+                batch.schema(),
+            )),
+        };
+
+        // the first batch decides the column types, so later batches whose
+        // types were inferred differently (eg, all nulls) are cast to them
+        let batch = match batch.schema() == *schema {
+            true => batch,
+            false => {
+                let columns = batch
+                    .columns()
+                    .iter()
+                    .zip(schema.fields())
+                    .map(|(column, field)| cast(column, field.data_type()))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+
+                RecordBatch::try_new(Arc::clone(schema), columns)?
+            }
+        };
+
+        writer.write(&batch)?;
+        writer.flush()?;
+        self.summary.num_records += batch.num_rows();
+
+        // the writer buffers small row groups, so this includes unsent bytes
+        let over_the_limit = self
+            .max_bytes
+            .is_some_and(|max_bytes| writer.bytes_written() as u64 > max_bytes);
+        let bytes = std::mem::take(writer.inner_mut());
+        let sent = self.send(bytes).await;
+
+        if over_the_limit {
+            self.summary.over_the_limit = true;
+            return Ok(false);
+        }
+
+        Ok(sent)
+    }
+
+    /// Write the footer, returning a summary of what was sent
+    pub async fn finish(mut self, next_offset: Option<u64>) -> Result<StreamSummary> {
+        self.summary.next_offset = next_offset;
+
+        if let Some((mut writer, _)) = self.writer.take() {
+            if self.summary.over_the_limit {
+                writer.append_key_value_metadata(KeyValue::new(
+                    OVER_THE_LIMIT_KEY.into(),
+                    "true".to_string(),
+                ));
+            }
+
+            if let Some(next_offset) = next_offset {
+                writer.append_key_value_metadata(KeyValue::new(
+                    NEXT_OFFSET_KEY.into(),
+                    next_offset.to_string(),
+                ));
+            }
+
+            let bytes = writer.into_inner()?;
+            self.send(bytes).await;
+        }
+
+        Ok(self.summary)
+    }
+
+    async fn send(&mut self, bytes: Vec<u8>) -> bool {
+        if bytes.is_empty() {
+            return !self.sender.is_closed();
+        }
+
+        self.summary.bytes += bytes.len() as u64;
+        self.sender.send(Ok(bytes.into())).await.is_ok()
+    }
+}
+
+/// Stream a page of rows as Parquet row groups of `BATCH_ROWS` rows
+pub async fn stream_rows<C, S>(
+    mut rows: S,
+    page: Page,
+    sender: ChunkSender,
+    max_bytes: Option<u64>,
+) -> Result<StreamSummary>
+where
+    C: Connection + ?Sized,
+    S: Stream<Item = Result<C::Row>> + Unpin,
+{
+    let mut stream = ParquetStream::new(sender, max_bytes);
+    let mut batch = Vec::with_capacity(BATCH_ROWS);
+    let mut skipped = 0;
+    let mut taken = 0;
+    let mut next_offset = None;
+    let mut writing = true;
+
+    while let Some(row) = rows.next().await {
+        let row = row?;
+
+        if skipped < page.offset {
+            skipped += 1;
+            continue;
+        }
+
+        // one row past the page means there's another page
+        if page.limit.is_some_and(|limit| taken == limit) {
+            next_offset = Some(page.offset + taken);
+            break;
+        }
+
+        batch.push(row);
+        taken += 1;
+
+        if batch.len() == BATCH_ROWS {
+            if let Some(record_batch) = C::to_record_batch(&batch)? {
+                writing = stream.write(record_batch).await?;
+            }
+
+            batch.clear();
+
+            if !writing {
+                break;
+            }
+        }
+    }
+
+    if writing {
+        if let Some(record_batch) = C::to_record_batch(&batch)? {
+            stream.write(record_batch).await?;
+        }
+    }
+
+    stream.finish(next_offset).await
+}
+
+/// Stream a page of record batches, for databases that return Arrow data
+pub async fn stream_batches(
+    batches: Vec<RecordBatch>,
+    page: Page,
+    sender: ChunkSender,
+    max_bytes: Option<u64>,
+) -> Result<StreamSummary> {
+    let mut stream = ParquetStream::new(sender, max_bytes);
+    let mut offset = page.offset;
+    let mut remaining = page.limit.unwrap_or(u64::MAX);
+    let mut next_offset = None;
+
+    for batch in batches {
+        let num_rows = batch.num_rows() as u64;
+
+        if offset >= num_rows {
+            offset -= num_rows;
+            continue;
+        }
+
+        if remaining == 0 {
+            next_offset = Some(page.offset + stream.summary.num_records as u64);
+            break;
+        }
+
+        let length = (num_rows - offset).min(remaining);
+        let batch = batch.slice(offset as usize, length as usize);
+        remaining -= length;
+
+        // rows past the end of the page means there's another page
+        if length < num_rows - offset {
+            next_offset = Some(page.offset + (stream.summary.num_records + length as usize) as u64);
+        }
+
+        offset = 0;
+
+        if !stream.write(batch).await? || next_offset.is_some() {
+            break;
+        }
+    }
+
+    stream.finish(next_offset).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow::array::{Array, ArrayRef, Int32Array, NullArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use tokio::sync::mpsc::channel;
+
+    fn batch(column: ArrayRef) -> RecordBatch {
+        let field = Field::new("a", column.data_type().to_owned(), true);
+        RecordBatch::try_new(Arc::new(Schema::new(vec![field])), vec![column]).unwrap()
+    }
+
+    async fn received(mut receiver: tokio::sync::mpsc::Receiver<Result<Bytes>>) -> Vec<Bytes> {
+        let mut chunks = vec![];
+
+        while let Some(chunk) = receiver.recv().await {
+            chunks.push(chunk.unwrap());
+        }
+
+        chunks
+    }
+
+    #[tokio::test]
+    async fn streams_row_groups() {
+        let (sender, receiver) = channel(10);
+        let mut stream = ParquetStream::new(sender, None);
+
+        assert!(
+            stream
+                .write(batch(Arc::new(Int32Array::from(vec![1, 2]))))
+                .await
+                .unwrap()
+        );
+
+        // a column of nulls is cast to the first batch's type
+        assert!(
+            stream
+                .write(batch(Arc::new(NullArray::new(1))))
+                .await
+                .unwrap()
+        );
+
+        let summary = stream.finish(Some(3)).await.unwrap();
+        let chunks = received(receiver).await;
+
+        assert_eq!(summary.num_records, 3);
+        assert_eq!(
+            summary.bytes,
+            chunks.iter().map(|c| c.len() as u64).sum::<u64>()
+        );
+
+        let parquet = Bytes::from(chunks.concat());
+        let builder = ParquetRecordBatchReaderBuilder::try_new(parquet).unwrap();
+        let metadata = builder.metadata().file_metadata().key_value_metadata();
+        assert!(
+            metadata
+                .unwrap()
+                .iter()
+                .any(|kv| kv.key == NEXT_OFFSET_KEY && kv.value.as_deref() == Some("3"))
+        );
+
+        let batches = builder
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+        assert_eq!(batches[0].schema().field(0).data_type(), &DataType::Int32);
+    }
+
+    #[tokio::test]
+    async fn streams_a_page_of_batches() {
+        let batches = vec![
+            batch(Arc::new(Int32Array::from(vec![1, 2, 3]))),
+            batch(Arc::new(Int32Array::from(vec![4, 5, 6]))),
+        ];
+        let page = Page {
+            offset: 2,
+            limit: Some(2),
+        };
+        let (sender, receiver) = channel(10);
+        let summary = stream_batches(batches.clone(), page, sender, None)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.num_records, 2);
+        assert_eq!(summary.next_offset, Some(4));
+
+        let parquet = Bytes::from(received(receiver).await.concat());
+        let values = ParquetRecordBatchReaderBuilder::try_new(parquet)
+            .unwrap()
+            .build()
+            .unwrap()
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let column = batch.column(0).as_any().downcast_ref::<Int32Array>();
+                column.unwrap().values().to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![3, 4]);
+
+        // the last page
+        let page = Page {
+            offset: 4,
+            limit: Some(2),
+        };
+        let (sender, _receiver) = channel(10);
+        let summary = stream_batches(batches, page, sender, None).await.unwrap();
+
+        assert_eq!(summary.num_records, 2);
+        assert_eq!(summary.next_offset, None);
+    }
+
+    #[tokio::test]
+    async fn stops_over_the_limit() {
+        let (sender, receiver) = channel(10);
+        let mut stream = ParquetStream::new(sender, Some(1));
+
+        assert!(
+            !stream
+                .write(batch(Arc::new(Int32Array::from(vec![1, 2]))))
+                .await
+                .unwrap()
+        );
+
+        let summary = stream.finish(None).await.unwrap();
+        assert!(summary.over_the_limit);
+
+        let parquet = Bytes::from(received(receiver).await.concat());
+        let builder = ParquetRecordBatchReaderBuilder::try_new(parquet).unwrap();
+        let metadata = builder.metadata().file_metadata().key_value_metadata();
+        assert!(
+            metadata
+                .unwrap()
+                .iter()
+                .any(|kv| kv.key == OVER_THE_LIMIT_KEY)
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! TLS
+//!
+//! TLS options shared by the database connections.  Certificates and keys are
+//! PEM encoded strings rather than paths, since they're stored with the
+//! connection in quadratic-api.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlSslMode, postgres::PgSslMode};
+
+/// How strictly TLS is required, named after libpq's `sslmode`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TlsMode {
+    /// Never use TLS
+    Disable,
+
+    /// Use TLS if the server supports it, without verifying the certificate
+    #[default]
+    Prefer,
+
+    /// Require TLS, without verifying the certificate
+    Require,
+
+    /// Require TLS and verify the certificate against the CA
+    VerifyCa,
+
+    /// Require TLS, verify the certificate against the CA and verify that the
+    /// certificate matches the host
+    VerifyFull,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    #[serde(default)]
+    pub mode: TlsMode,
+
+    /// PEM encoded CA certificate, for verifying the server.  When empty, the
+    /// system's root certificates are used.
+    pub ca_certificate: Option<String>,
+
+    /// PEM encoded client certificate and key, for servers that require
+    /// certificate authentication
+    pub client_certificate: Option<String>,
+    pub client_key: Option<String>,
+}
+
+impl TlsConfig {
+    /// Whether the client presents a certificate to the server
+    pub fn has_client_certificate(&self) -> bool {
+        self.client_certificate.is_some() || self.client_key.is_some()
+    }
+
+    /// The mode for drivers that reach the database through an SSH tunnel's
+    /// local address.  The certificate can't match that address, so
+    /// `verify-full` only verifies the certificate against the CA.
+    pub(crate) fn tunnelled_mode(&self) -> TlsMode {
+        match self.mode {
+            TlsMode::VerifyFull => TlsMode::VerifyCa,
+            mode => mode,
+        }
+    }
+}
+
+impl From<TlsMode> for PgSslMode {
+    fn from(mode: TlsMode) -> Self {
+        match mode {
+            TlsMode::Disable => PgSslMode::Disable,
+            TlsMode::Prefer => PgSslMode::Prefer,
+            TlsMode::Require => PgSslMode::Require,
+            TlsMode::VerifyCa => PgSslMode::VerifyCa,
+            TlsMode::VerifyFull => PgSslMode::VerifyFull,
+        }
+    }
+}
+
+impl From<TlsMode> for MySqlSslMode {
+    fn from(mode: TlsMode) -> Self {
+        match mode {
+            TlsMode::Disable => MySqlSslMode::Disabled,
+            TlsMode::Prefer => MySqlSslMode::Preferred,
+            TlsMode::Require => MySqlSslMode::Required,
+            TlsMode::VerifyCa => MySqlSslMode::VerifyCa,
+            TlsMode::VerifyFull => MySqlSslMode::VerifyIdentity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_tls_config() {
+        let json = r#"{"mode":"verify-full","ca_certificate":"-----BEGIN CERTIFICATE-----"}"#;
+        let tls = serde_json::from_str::<TlsConfig>(json).unwrap();
+
+        assert_eq!(tls.mode, TlsMode::VerifyFull);
+        assert!(matches!(PgSslMode::from(tls.mode), PgSslMode::VerifyFull));
+        assert!(!tls.has_client_certificate());
+        assert_eq!(tls.tunnelled_mode(), TlsMode::VerifyCa);
+
+        let tls = serde_json::from_str::<TlsConfig>("{}").unwrap();
+        assert_eq!(tls, TlsConfig::default());
+        assert!(matches!(
+            MySqlSslMode::from(tls.mode),
+            MySqlSslMode::Preferred
+        ));
+    }
+}
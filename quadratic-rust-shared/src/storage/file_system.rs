@@ -4,11 +4,15 @@
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
-use tokio::fs::{File, create_dir_all};
+use tokio::fs::{File, create_dir_all, read_dir, remove_dir, remove_file, try_exists};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
 
-use super::Storage;
+use super::{ObjectMetadata, Storage, StorageStream};
 use crate::SharedError;
 use crate::error::Result;
 use crate::storage::error::Storage as StorageError;
@@ -58,6 +62,129 @@ impl Storage for FileSystem {
         Ok(())
     }
 
+    /// List the files whose keys start with the prefix.  Keys are stored as
+    /// `{uuid}/{file_name}`, so only the directories that match are read.
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMetadata>> {
+        let list_error = |e: std::io::Error| Self::list_error(prefix, &e);
+        let mut objects = vec![];
+        let mut dirs = match read_dir(&self.config.path).await {
+            Ok(dirs) => dirs,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(objects),
+            Err(e) => return Err(list_error(e)),
+        };
+
+        while let Some(dir) = dirs.next_entry().await.map_err(list_error)? {
+            let dir_name = dir.file_name().to_string_lossy().into_owned();
+            let may_match =
+                dir_name.starts_with(prefix) || prefix.starts_with(&format!("{dir_name}-"));
+
+            if !may_match || !dir.file_type().await.map_err(list_error)?.is_dir() {
+                continue;
+            }
+
+            let mut files = read_dir(dir.path()).await.map_err(list_error)?;
+
+            while let Some(file) = files.next_entry().await.map_err(list_error)? {
+                let key = format!("{dir_name}-{}", file.file_name().to_string_lossy());
+                let metadata = file.metadata().await.map_err(list_error)?;
+
+                if metadata.is_file() && key.starts_with(prefix) {
+                    objects.push(object_metadata(key, &metadata));
+                }
+            }
+        }
+
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(objects)
+    }
+
+    /// Delete the file from the file system, along with its directory once
+    /// it's empty.
+    async fn delete(&self, key: &str) -> Result<()> {
+        let (file_path, dir) = self.full_path(key, false).await?;
+
+        match remove_file(file_path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(Self::delete_error(key, &e)),
+            _ => {
+                // fails if other files are in the directory
+                remove_dir(dir).await.ok();
+                Ok(())
+            }
+        }
+    }
+
+    /// Check if the file exists in the file system.
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let file_path = self.full_path(key, false).await?.0;
+
+        try_exists(file_path)
+            .await
+            .map_err(|e| Self::read_error(key, &e))
+    }
+
+    /// Copy the file within the file system.
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        let from_path = self.full_path(from, false).await?.0;
+        let to_path = self.full_path(to, true).await?.0;
+
+        tokio::fs::copy(from_path, to_path)
+            .await
+            .map_err(|e| Self::copy_error(from, to, &e))?;
+
+        Ok(())
+    }
+
+    /// Get the size and modified time of the file.
+    async fn metadata(&self, key: &str) -> Result<ObjectMetadata> {
+        let file_path = self.full_path(key, false).await?.0;
+        let metadata = tokio::fs::metadata(file_path)
+            .await
+            .map_err(|e| Self::read_error(key, &e))?;
+
+        Ok(object_metadata(key.to_owned(), &metadata))
+    }
+
+    /// Read the file from the file system in chunks.
+    async fn read_stream(&self, key: &str) -> Result<StorageStream> {
+        let file_path = self.full_path(key, false).await?.0;
+        let file = File::open(file_path)
+            .await
+            .map_err(|e| Self::read_error(key, &e))?;
+        let key = key.to_owned();
+        let stream =
+            ReaderStream::new(file).map(move |chunk| chunk.map_err(|e| Self::read_error(&key, &e)));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Write the chunks to the file system.  The file is removed if the
+    /// stream fails, so a partial file is never left behind.
+    async fn write_stream(&self, key: &str, mut stream: StorageStream) -> Result<()> {
+        let file_path = self.full_path(key, true).await?.0;
+        let mut file = File::create(&file_path)
+            .await
+            .map_err(|e| Self::write_error(key, &e))?;
+
+        let written: Result<()> = async {
+            while let Some(chunk) = stream.next().await {
+                file.write_all(&chunk?)
+                    .await
+                    .map_err(|e| Self::write_error(key, &e))?;
+            }
+
+            file.flush().await.map_err(|e| Self::write_error(key, &e))
+        }
+        .await;
+
+        if written.is_err() {
+            drop(file);
+            remove_file(&file_path).await.ok();
+        }
+
+        written
+    }
+
     /// Return the path to the file system.
     fn path(&self) -> &str {
         &self.config.path
@@ -105,9 +232,16 @@ impl FileSystem {
     }
 }
 
+fn object_metadata(key: String, metadata: &std::fs::Metadata) -> ObjectMetadata {
+    ObjectMetadata {
+        key,
+        size: metadata.len(),
+        modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use tokio::fs::{remove_dir, remove_file};
     use uuid::Uuid;
 
     use super::*;
@@ -139,4 +273,10 @@ mod tests {
 
         assert_eq!(data, &read_data);
     }
+
+    #[tokio::test]
+    async fn file_system_conformance() {
+        let storage = FileSystem { config: config() };
+        crate::storage::tests::test_storage_conformance(&storage).await;
+    }
 }
@@ -1,8 +1,12 @@
 //! Storage code that implements the Storage trait
 
+use std::pin::Pin;
+
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use file_system::FileSystemConfig;
+use futures_util::Stream;
 use s3::S3Config;
 
 use crate::{SharedError, error::Result, storage::error::Storage as StorageError};
@@ -23,6 +27,17 @@ pub enum StorageContainer {
     FileSystem(file_system::FileSystem),
 }
 
+/// A stream of chunks of an object
+pub type StorageStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>;
+
+/// Information about a stored object
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectMetadata {
+    pub key: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
 #[async_trait]
 pub trait Storage {
     type Config;
@@ -32,6 +47,27 @@ pub trait Storage {
     fn path(&self) -> &str;
     fn config(&self) -> Self::Config;
 
+    /// List the objects whose keys start with the prefix, sorted by key
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMetadata>>;
+
+    /// Delete an object.  Deleting a missing object is not an error.
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Check if an object exists
+    async fn exists(&self, key: &str) -> Result<bool>;
+
+    /// Copy an object, replacing the destination if it exists
+    async fn copy(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Get the size and modified time of an object
+    async fn metadata(&self, key: &str) -> Result<ObjectMetadata>;
+
+    /// Read an object in chunks, without loading it into memory
+    async fn read_stream(&self, key: &str) -> Result<StorageStream>;
+
+    /// Write an object from a stream of chunks, without loading it into memory
+    async fn write_stream(&self, key: &str, stream: StorageStream) -> Result<()>;
+
     fn read_error(key: &str, e: impl ToString) -> SharedError {
         SharedError::Storage(StorageError::Read(key.into(), e.to_string()))
     }
@@ -39,6 +75,18 @@ pub trait Storage {
     fn write_error(key: &str, e: impl ToString) -> SharedError {
         SharedError::Storage(StorageError::Write(key.into(), e.to_string()))
     }
+
+    fn list_error(prefix: &str, e: impl ToString) -> SharedError {
+        SharedError::Storage(StorageError::List(prefix.into(), e.to_string()))
+    }
+
+    fn delete_error(key: &str, e: impl ToString) -> SharedError {
+        SharedError::Storage(StorageError::Delete(key.into(), e.to_string()))
+    }
+
+    fn copy_error(from: &str, to: &str, e: impl ToString) -> SharedError {
+        SharedError::Storage(StorageError::Copy(from.into(), to.into(), e.to_string()))
+    }
 }
 
 // TODO(ddimaria): this is a temp hack to get around some trait issues, do something better
@@ -60,6 +108,55 @@ impl Storage for StorageContainer {
         }
     }
 
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMetadata>> {
+        match self {
+            Self::S3(s3) => s3.list(prefix).await,
+            Self::FileSystem(fs) => fs.list(prefix).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match self {
+            Self::S3(s3) => s3.delete(key).await,
+            Self::FileSystem(fs) => fs.delete(key).await,
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        match self {
+            Self::S3(s3) => s3.exists(key).await,
+            Self::FileSystem(fs) => fs.exists(key).await,
+        }
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        match self {
+            Self::S3(s3) => s3.copy(from, to).await,
+            Self::FileSystem(fs) => fs.copy(from, to).await,
+        }
+    }
+
+    async fn metadata(&self, key: &str) -> Result<ObjectMetadata> {
+        match self {
+            Self::S3(s3) => s3.metadata(key).await,
+            Self::FileSystem(fs) => fs.metadata(key).await,
+        }
+    }
+
+    async fn read_stream(&self, key: &str) -> Result<StorageStream> {
+        match self {
+            Self::S3(s3) => s3.read_stream(key).await,
+            Self::FileSystem(fs) => fs.read_stream(key).await,
+        }
+    }
+
+    async fn write_stream(&self, key: &str, stream: StorageStream) -> Result<()> {
+        match self {
+            Self::S3(s3) => s3.write_stream(key, stream).await,
+            Self::FileSystem(fs) => fs.write_stream(key, stream).await,
+        }
+    }
+
     fn path(&self) -> &str {
         match self {
             Self::S3(s3) => s3.path(),
@@ -74,3 +171,79 @@ impl Storage for StorageContainer {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use futures_util::{TryStreamExt, stream};
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// The behavior every storage implementation must share
+    pub(crate) async fn test_storage_conformance(storage: &(impl Storage + Sync)) {
+        let file_id = Uuid::new_v4();
+        let key = |sequence_num: u64| format!("{file_id}-{sequence_num}.grid");
+        let data = Bytes::from("Hello, world!");
+
+        // write, exists and metadata
+        assert!(!storage.exists(&key(0)).await.unwrap());
+        storage.write(&key(0), &data).await.unwrap();
+        assert!(storage.exists(&key(0)).await.unwrap());
+
+        let metadata = storage.metadata(&key(0)).await.unwrap();
+        assert_eq!(metadata.key, key(0));
+        assert_eq!(metadata.size, data.len() as u64);
+        assert!(metadata.modified.is_some());
+        assert!(storage.metadata(&key(9)).await.is_err());
+
+        // copy
+        storage.copy(&key(0), &key(1)).await.unwrap();
+        assert_eq!(storage.read(&key(1)).await.unwrap(), data);
+
+        // streaming
+        let chunks = ["Hello, ", "streaming ", "world!"].map(|chunk| Ok(Bytes::from(chunk)));
+        let stream: StorageStream = Box::pin(stream::iter(chunks));
+        storage.write_stream(&key(2), stream).await.unwrap();
+
+        let chunks = storage
+            .read_stream(&key(2))
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), b"Hello, streaming world!");
+
+        // a failed stream doesn't leave a partial object behind
+        let error = SharedError::Storage(StorageError::Write(key(3), "stream failed".into()));
+        let stream: StorageStream =
+            Box::pin(stream::iter([Ok(Bytes::from("partial")), Err(error)]));
+        assert!(storage.write_stream(&key(3), stream).await.is_err());
+        assert!(!storage.exists(&key(3)).await.unwrap());
+
+        // list
+        let keys = storage
+            .list(&file_id.to_string())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| object.key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![key(0), key(1), key(2)]);
+        assert!(
+            storage
+                .list(&Uuid::new_v4().to_string())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // delete, which is fine to repeat
+        for sequence_num in 0..3 {
+            storage.delete(&key(sequence_num)).await.unwrap();
+        }
+        storage.delete(&key(0)).await.unwrap();
+        assert!(!storage.exists(&key(0)).await.unwrap());
+        assert!(storage.list(&file_id.to_string()).await.unwrap().is_empty());
+    }
+}
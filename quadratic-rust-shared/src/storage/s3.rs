@@ -3,15 +3,25 @@
 //! Functions to interact with S3
 
 use async_trait::async_trait;
-use aws_sdk_s3::Client;
+use aws_sdk_s3::{Client, primitives::DateTime as AwsDateTime};
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use tokio_util::io::ReaderStream;
 
-use super::Storage;
+use super::{ObjectMetadata, Storage, StorageStream};
 use crate::{
-    aws::s3::{download_object, upload_object},
+    aws::s3::{
+        abort_multipart_upload, complete_multipart_upload, copy_object, create_multipart_upload,
+        delete_object, download_object, head_object, list_objects, upload_object, upload_part,
+    },
     error::Result,
 };
 
+/// S3 requires every part of a multipart upload but the last to be at least
+/// 5 MiB
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
 /// S3 configuration
 #[derive(Debug, Clone)]
 pub struct S3Config {
@@ -58,6 +68,134 @@ impl Storage for S3 {
         Ok(())
     }
 
+    /// List the objects in the S3 bucket whose keys start with the prefix.
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMetadata>> {
+        let S3Config { client, bucket } = &self.config;
+
+        let mut objects = list_objects(client, bucket, prefix)
+            .await
+            .map_err(|e| Self::list_error(prefix, &e))?
+            .into_iter()
+            .filter_map(|object| {
+                Some(ObjectMetadata {
+                    key: object.key()?.to_owned(),
+                    size: object.size().unwrap_or_default().max(0) as u64,
+                    modified: object.last_modified().and_then(to_utc),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(objects)
+    }
+
+    /// Delete the object from the S3 bucket.
+    async fn delete(&self, key: &str) -> Result<()> {
+        let S3Config { client, bucket } = &self.config;
+
+        delete_object(client, bucket, key)
+            .await
+            .map_err(|e| Self::delete_error(key, &e))
+    }
+
+    /// Check if the object exists in the S3 bucket.
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let S3Config { client, bucket } = &self.config;
+
+        let object = head_object(client, bucket, key)
+            .await
+            .map_err(|e| Self::read_error(key, &e))?;
+
+        Ok(object.is_some())
+    }
+
+    /// Copy the object within the S3 bucket.
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        let S3Config { client, bucket } = &self.config;
+
+        copy_object(client, bucket, from, to)
+            .await
+            .map_err(|e| Self::copy_error(from, to, &e))
+    }
+
+    /// Get the size and modified time of the object.
+    async fn metadata(&self, key: &str) -> Result<ObjectMetadata> {
+        let S3Config { client, bucket } = &self.config;
+
+        let object = head_object(client, bucket, key)
+            .await
+            .map_err(|e| Self::read_error(key, &e))?
+            .ok_or_else(|| Self::read_error(key, "Not found"))?;
+
+        Ok(ObjectMetadata {
+            key: key.to_owned(),
+            size: object.content_length().unwrap_or_default().max(0) as u64,
+            modified: object.last_modified().and_then(to_utc),
+        })
+    }
+
+    /// Read the object from the S3 bucket in chunks.
+    async fn read_stream(&self, key: &str) -> Result<StorageStream> {
+        let S3Config { client, bucket } = &self.config;
+
+        let file = download_object(client, bucket, key)
+            .await
+            .map_err(|e| Self::read_error(key, &e))?;
+        let key = key.to_owned();
+        let stream = ReaderStream::new(file.body.into_async_read())
+            .map(move |chunk| chunk.map_err(|e| Self::read_error(&key, &e)));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Write the chunks to the S3 bucket as a multipart upload.  The upload
+    /// is aborted if the stream fails, so a partial object is never created.
+    async fn write_stream(&self, key: &str, mut stream: StorageStream) -> Result<()> {
+        let S3Config { client, bucket } = &self.config;
+
+        let upload_id = create_multipart_upload(client, bucket, key)
+            .await
+            .map_err(|e| Self::write_error(key, &e))?;
+
+        let uploaded: Result<()> = async {
+            let mut parts = vec![];
+            let mut buffer = Vec::with_capacity(MIN_PART_SIZE);
+
+            while let Some(chunk) = stream.next().await {
+                buffer.extend_from_slice(&chunk?);
+
+                if buffer.len() >= MIN_PART_SIZE {
+                    let body = std::mem::replace(&mut buffer, Vec::with_capacity(MIN_PART_SIZE));
+                    let part_number = parts.len() as i32 + 1;
+                    parts.push(
+                        upload_part(client, bucket, key, &upload_id, part_number, body).await?,
+                    );
+                }
+            }
+
+            // the last part may be smaller, and there must be at least one
+            if !buffer.is_empty() || parts.is_empty() {
+                let part_number = parts.len() as i32 + 1;
+                parts
+                    .push(upload_part(client, bucket, key, &upload_id, part_number, buffer).await?);
+            }
+
+            complete_multipart_upload(client, bucket, key, &upload_id, parts).await
+        }
+        .await;
+
+        if let Err(error) = uploaded {
+            abort_multipart_upload(client, bucket, key, &upload_id)
+                .await
+                .ok();
+
+            return Err(Self::write_error(key, &error));
+        }
+
+        Ok(())
+    }
+
     /// Return the S3 bucket.
     fn path(&self) -> &str {
         &self.config.bucket
@@ -76,7 +214,34 @@ impl S3 {
     }
 }
 
+fn to_utc(date_time: &AwsDateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(date_time.secs(), date_time.subsec_nanos())
+}
+
 #[cfg(test)]
 mod tests {
-    // TODO(ddimaria): add tests once we have S3 mocks in place
+    use super::*;
+    use crate::aws::client;
+
+    const BUCKET: &str = "quadratic-rust-shared-test";
+
+    /// Connects to the local S3-compatible stand-in (localstack, see
+    /// docker-compose.yml) and creates the test bucket.
+    async fn storage() -> S3 {
+        let client = client("test", "test", "us-east-2", "Quadratic Rust Shared", true).await;
+
+        // the bucket may already exist from a previous run
+        client.create_bucket().bucket(BUCKET).send().await.ok();
+
+        S3::new(S3Config {
+            client,
+            bucket: BUCKET.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn s3_conformance() {
+        let storage = storage().await;
+        crate::storage::tests::test_storage_conformance(&storage).await;
+    }
 }
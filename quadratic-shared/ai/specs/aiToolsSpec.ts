@@ -77,7 +77,7 @@ const array2DSchema = z
 const cellLanguageSchema = z
   .string()
   .transform((val) => val.toLowerCase())
-  .pipe(z.enum(['python', 'javascript', 'formula']))
+  .pipe(z.enum(['python', 'javascript', 'formula', 'sql']))
   .transform((val) => val.charAt(0).toUpperCase() + val.slice(1))
   .pipe(z.enum(['Python', 'Javascript', 'Formula']));
 
@@ -267,7 +267,7 @@ Always refer to the data from cell by its position in a1 notation from respectiv
         code_cell_language: {
           type: 'string',
           description:
-            'The language of the code cell, this can be one of Python, Javascript or Formula. This is case sensitive.',
+            'The language of the code cell, this can be one of Python, Javascript, Formula or Sql. Sql cells run a SELECT statement over the data tables in the file, referenced by table name. This is case sensitive.',
         },
         code_cell_position: {
           type: 'string',
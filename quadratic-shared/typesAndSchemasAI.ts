@@ -120,10 +120,10 @@ export type ToolResultContextType = z.infer<typeof ToolResultContextTypeSchema>;
 const UserPromptContextTypeSchema = z.literal('userPrompt');
 export type UserPromptContextType = z.infer<typeof UserPromptContextTypeSchema>;
 
-const CodeCellLanguageSchema = z.enum(['Python', 'Javascript', 'Formula', 'Import']).or(
+const CodeCellLanguageSchema = z.enum(['Python', 'Javascript', 'Formula', 'Import', 'Sql']).or(
   z.object({
     Connection: z.object({
-      kind: z.enum(['POSTGRES', 'MYSQL', 'MSSQL', 'SNOWFLAKE']),
+      kind: z.enum(['POSTGRES', 'MYSQL', 'MSSQL', 'SNOWFLAKE', 'SQLITE', 'DUCKDB', 'CLICKHOUSE', 'HTTP']),
       id: z.string(),
     }),
   })
@@ -334,7 +334,7 @@ const AIToolArgsSchema: z.ZodType = z.lazy(() =>
 );
 export type AIToolArgs = z.infer<typeof AIToolArgsSchema>;
 
-const CodeCellTypeSchema = z.enum(['Python', 'Javascript', 'Formula', 'Connection', 'Import']);
+const CodeCellTypeSchema = z.enum(['Python', 'Javascript', 'Formula', 'Connection', 'Import', 'Sql']);
 export type CodeCellType = z.infer<typeof CodeCellTypeSchema>;
 
 const AISourceSchema = z.enum([
@@ -57,6 +57,29 @@ export type Connection = z.infer<typeof ConnectionSchema>;
  * Schemas for individual connections
  * =============================================================================
  */
+// Matches `TlsConfig` in quadratic-rust-shared; certificates are PEM encoded
+const ConnectionTlsSchema = z.object({
+  mode: z.enum(['disable', 'prefer', 'require', 'verify-ca', 'verify-full']).default('prefer'),
+  ca_certificate: z.string().optional().transform(transformEmptyStringToUndefined),
+  client_certificate: z.string().optional().transform(transformEmptyStringToUndefined),
+  client_key: z.string().optional().transform(transformEmptyStringToUndefined),
+});
+// Matches `SshConfig` in quadratic-rust-shared
+const ConnectionSshSchema = z.object({
+  host: ConnectionHostSchema,
+  port: z.string().optional().transform(transformEmptyStringToUndefined),
+  username: z.string().min(1, { message: 'Required' }),
+  auth: z.discriminatedUnion('type', [
+    z.object({ type: z.literal('password'), password: z.string().min(1, { message: 'Required' }) }),
+    z.object({
+      type: z.literal('private_key'),
+      private_key: z.string().min(1, { message: 'Required' }),
+      passphrase: z.string().optional().transform(transformEmptyStringToUndefined),
+    }),
+  ]),
+  host_key: z.string().min(1, { message: 'Required' }),
+});
+
 export const ConnectionTypeDetailsPostgresSchema = z.object({
   host: ConnectionHostSchema,
   port: z
@@ -75,6 +98,8 @@ export const ConnectionTypeDetailsPostgresSchema = z.object({
   database: z.string().min(1, { message: 'Required' }),
   username: z.string().min(1, { message: 'Required' }),
   password: z.string().optional().transform(transformEmptyStringToUndefined),
+  tls: ConnectionTlsSchema.optional(),
+  ssh: ConnectionSshSchema.optional(),
 });
 export const ConnectionTypeDetailsMysqlSchema = ConnectionTypeDetailsPostgresSchema;
 export const ConnectionTypeDetailsMssqlSchema = z.object({
@@ -95,6 +120,8 @@ export const ConnectionTypeDetailsMssqlSchema = z.object({
   database: z.string().optional(),
   username: z.string().min(1, { message: 'Required' }),
   password: z.string().min(1, { message: 'Required' }),
+  tls: ConnectionTlsSchema.optional(),
+  ssh: ConnectionSshSchema.optional(),
 });
 export const ConnectionTypeDetailsSnowflakeSchema = z.object({
   account_identifier: z.string().min(1, { message: 'Required' }),